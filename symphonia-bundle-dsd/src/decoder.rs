@@ -0,0 +1,237 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::codecs::{CODEC_TYPE_DSD_LSBF, CODEC_TYPE_DSD_LSBF_PLANAR};
+use symphonia_core::codecs::{CODEC_TYPE_DSD_MSBF, CODEC_TYPE_DSD_MSBF_PLANAR};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::support_codec;
+
+use log::debug;
+
+use crate::filter::{Decimator, FilterBank};
+
+/// The default output sample rate is the lowest rate at or above this rate.
+const DEFAULT_MIN_OUTPUT_RATE: u32 = 88_200;
+
+/// The default capacity of the audio buffer in frames if the maximum packet duration is not known.
+const DEFAULT_MAX_FRAMES: u64 = 4096;
+
+/// DSD specific decoder options.
+///
+/// These options are only used by `DsdDecoder::try_new_with_options`. `Decoder::try_new`, and
+/// therefore any codec registry, always uses the defaults, so the output sample rate then depends
+/// only on the DSD sample rate.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DsdDecoderOptions {
+    /// The output sample rate. It must be the DSD sample rate divided by 8, 16, 32, or a higher
+    /// power of 2. Defaults to 88.2 kHz or 96 kHz for DSD64 and above.
+    pub output_rate: Option<u32>,
+}
+
+/// Direct Stream Digital (DSD) decoder.
+///
+/// The 1-bit DSD stream is converted to PCM by low-pass filtering and decimation. The passband
+/// extends up-to 30 kHz, or 45% of the output sample rate if lower. A DSD stream with 50%
+/// modulation is output at -6 dBFS, leaving headroom for the higher modulation levels permitted
+/// by the Super Audio CD specification.
+pub struct DsdDecoder {
+    params: CodecParameters,
+    bank: FilterBank,
+    decimators: Vec<Decimator>,
+    /// The decimation factor.
+    factor: u64,
+    is_lsb_first: bool,
+    is_planar: bool,
+    samples: Vec<f32>,
+    buf: AudioBuffer<f32>,
+}
+
+impl DsdDecoder {
+    /// Instantiates the decoder with DSD specific options.
+    pub fn try_new_with_options(
+        params: &CodecParameters,
+        _options: &DecoderOptions,
+        dsd_options: DsdDecoderOptions,
+    ) -> Result<Self> {
+        let (is_lsb_first, is_planar) = match params.codec {
+            CODEC_TYPE_DSD_LSBF => (true, false),
+            CODEC_TYPE_DSD_LSBF_PLANAR => (true, true),
+            CODEC_TYPE_DSD_MSBF => (false, false),
+            CODEC_TYPE_DSD_MSBF_PLANAR => (false, true),
+            _ => return unsupported_error("dsd: invalid codec type"),
+        };
+
+        let dsd_rate = match params.sample_rate {
+            Some(rate) if rate > 0 && rate % 8 == 0 => rate,
+            _ => return unsupported_error("dsd: invalid sample rate"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) => channels,
+            None => return unsupported_error("dsd: channels are required"),
+        };
+
+        // The number of decimate-by-2 stages after the initial decimation by 8.
+        let n_stages = match dsd_options.output_rate {
+            Some(rate) => {
+                let factor = if rate > 0 && dsd_rate % rate == 0 { dsd_rate / rate } else { 0 };
+
+                if factor < 8 || !factor.is_power_of_two() {
+                    return unsupported_error("dsd: unsupported output sample rate");
+                }
+
+                (factor / 8).trailing_zeros()
+            }
+            None => {
+                let mut n_stages = 0;
+
+                while dsd_rate / (16 << n_stages) >= DEFAULT_MIN_OUTPUT_RATE {
+                    n_stages += 1;
+                }
+
+                n_stages
+            }
+        };
+
+        let factor = 8u64 << n_stages;
+        let output_rate = (u64::from(dsd_rate) / factor) as u32;
+
+        debug!("dsd: converting {} Hz to {} Hz", dsd_rate, output_rate);
+
+        let bank = FilterBank::new(dsd_rate, n_stages);
+
+        let decimators = (0..channels.count()).map(|_| Decimator::new(&bank)).collect();
+
+        let max_frames = match params.max_frames_per_packet {
+            Some(max_frames) => (max_frames + factor - 1) / factor,
+            None => DEFAULT_MAX_FRAMES,
+        };
+
+        let spec = SignalSpec::new(output_rate, channels);
+
+        Ok(DsdDecoder {
+            params: params.clone(),
+            bank,
+            decimators,
+            factor,
+            is_lsb_first,
+            is_planar,
+            samples: Vec::new(),
+            buf: AudioBuffer::new(max_frames, spec),
+        })
+    }
+
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let buf = packet.buf();
+
+        let n_channels = self.decimators.len();
+
+        if buf.len() % n_channels != 0 {
+            return decode_error("dsd: packet is not a multiple of the channel count");
+        }
+
+        let n_bytes = buf.len() / n_channels;
+
+        // The number of output frames depends on the state of the decimators, but is the same for
+        // all channels.
+        let max_frames = (8 * n_bytes as u64) / self.factor + 1;
+
+        if max_frames > self.buf.capacity() as u64 {
+            self.buf = AudioBuffer::new(max_frames, *self.buf.spec());
+        }
+
+        self.buf.clear();
+
+        let is_lsb_first = self.is_lsb_first;
+
+        let normalize = |byte: u8| if is_lsb_first { byte.reverse_bits() } else { byte };
+
+        for ch in 0..n_channels {
+            self.samples.clear();
+
+            let decimator = &mut self.decimators[ch];
+
+            if self.is_planar {
+                let bytes = buf[ch * n_bytes..(ch + 1) * n_bytes].iter().map(|&b| normalize(b));
+                decimator.process(&self.bank, bytes, &mut self.samples);
+            }
+            else {
+                let bytes = buf[ch..].iter().step_by(n_channels).map(|&b| normalize(b));
+                decimator.process(&self.bank, bytes, &mut self.samples);
+            }
+
+            if ch == 0 {
+                self.buf.render_reserved(Some(self.samples.len()));
+            }
+
+            self.buf.chan_mut(ch).copy_from_slice(&self.samples);
+        }
+
+        // The trim is in DSD samples.
+        let trim_start = u64::from(packet.trim_start()) / self.factor;
+        let trim_end = u64::from(packet.trim_end()) / self.factor;
+
+        self.buf.trim(trim_start as usize, trim_end as usize);
+
+        Ok(())
+    }
+}
+
+impl Decoder for DsdDecoder {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+        DsdDecoder::try_new_with_options(params, options, Default::default())
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[
+            support_codec!(CODEC_TYPE_DSD_LSBF, "dsd_lsbf", "DSD (LSB First)"),
+            support_codec!(
+                CODEC_TYPE_DSD_LSBF_PLANAR,
+                "dsd_lsbf_planar",
+                "DSD (LSB First, Planar)"
+            ),
+            support_codec!(CODEC_TYPE_DSD_MSBF, "dsd_msbf", "DSD (MSB First)"),
+            support_codec!(
+                CODEC_TYPE_DSD_MSBF_PLANAR,
+                "dsd_msbf_planar",
+                "DSD (MSB First, Planar)"
+            ),
+        ]
+    }
+
+    fn reset(&mut self) {
+        for decimator in self.decimators.iter_mut() {
+            decimator.reset();
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
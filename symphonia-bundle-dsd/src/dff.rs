@@ -0,0 +1,533 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DSD_MSBF};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, StandardTagKey, Tag, Value};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::id3v2;
+
+use log::{debug, info, warn};
+
+/// The number of bytes of each channel in a packet.
+const PACKET_LEN_PER_CHANNEL: u64 = 4096;
+
+/// The marker type of a track start marker.
+const MARK_TYPE_TRACK_START: u16 = 0;
+
+/// A chunk header.
+struct ChunkHeader {
+    id: [u8; 4],
+    len: u64,
+}
+
+impl ChunkHeader {
+    fn read<B: ReadBytes>(reader: &mut B) -> Result<ChunkHeader> {
+        let id = reader.read_quad_bytes()?;
+        let len = reader.read_be_u64()?;
+
+        Ok(ChunkHeader { id, len })
+    }
+
+    /// Gets the length of the chunk's data including padding to an even length.
+    fn padded_len(&self) -> u64 {
+        self.len + (self.len & 1)
+    }
+}
+
+/// A marker from the edited master information chunk.
+struct Marker {
+    /// The position of the marker in samples, relative to the start of the stream.
+    pos: i64,
+    mark_type: u16,
+    text: String,
+}
+
+/// Reads a text string preceeded by its 32-bit length. The string is padded to an even length.
+fn read_text<B: ReadBytes>(reader: &mut B) -> Result<String> {
+    let len = reader.read_be_u32()?;
+
+    let buf = reader.read_boxed_slice_exact(len as usize)?;
+
+    if len & 1 != 0 {
+        reader.read_u8()?;
+    }
+
+    // Strings are not null-terminated, but may be null padded.
+    Ok(String::from_utf8_lossy(&buf).trim_end_matches('\0').to_string())
+}
+
+/// The properties of the sound data.
+#[derive(Default)]
+struct Properties {
+    sample_rate: Option<u32>,
+    channels: Option<Channels>,
+    n_channels: u16,
+}
+
+impl Properties {
+    /// Reads the property chunk.
+    fn read<B: ReadBytes>(reader: &mut B, len: u64) -> Result<Properties> {
+        if len < 4 || reader.read_quad_bytes()? != *b"SND " {
+            return unsupported_error("dff: unsupported property type");
+        }
+
+        let mut props = Properties::default();
+        let mut consumed = 4;
+
+        while consumed + 12 <= len {
+            let chunk = ChunkHeader::read(reader)?;
+
+            match &chunk.id {
+                b"FS  " => props.sample_rate = Some(reader.read_be_u32()?),
+                b"CHNL" => {
+                    props.n_channels = reader.read_be_u16()?;
+
+                    let mut ids = Vec::with_capacity(usize::from(props.n_channels));
+
+                    for _ in 0..props.n_channels {
+                        ids.push(reader.read_quad_bytes()?);
+                    }
+
+                    props.channels = Some(channels_from_ids(&ids)?);
+                }
+                b"CMPR" => {
+                    let compression = reader.read_quad_bytes()?;
+
+                    if compression != *b"DSD " {
+                        return unsupported_error("dff: compressed audio is not supported");
+                    }
+                }
+                _ => (),
+            }
+
+            // Skip the remainder of the chunk.
+            let read = match &chunk.id {
+                b"FS  " => 4,
+                b"CHNL" => 2 + 4 * u64::from(props.n_channels),
+                b"CMPR" => 4,
+                _ => 0,
+            };
+
+            if read > chunk.len {
+                return decode_error("dff: property chunk is too short");
+            }
+
+            reader.ignore_bytes(chunk.padded_len() - read)?;
+
+            consumed += 12 + chunk.padded_len();
+        }
+
+        Ok(props)
+    }
+}
+
+/// Gets the channels from a list of channel identifiers.
+fn channels_from_ids(ids: &[[u8; 4]]) -> Result<Channels> {
+    let mut channels = Channels::empty();
+    let mut is_ordered = true;
+
+    for id in ids {
+        let channel = match id {
+            b"SLFT" | b"MLFT" => Channels::FRONT_LEFT,
+            b"SRGT" | b"MRGT" => Channels::FRONT_RIGHT,
+            b"C   " => Channels::FRONT_CENTRE,
+            b"LFE " => Channels::LFE1,
+            b"LS  " => Channels::REAR_LEFT,
+            b"RS  " => Channels::REAR_RIGHT,
+            _ => Channels::empty(),
+        };
+
+        // Channels must be unique, and in the same order as Symphonia's channel order.
+        if channel.is_empty() || channel.bits() <= channels.bits() {
+            is_ordered = false;
+            break;
+        }
+
+        channels |= channel;
+    }
+
+    if is_ordered {
+        return Ok(channels);
+    }
+
+    info!("dff: unknown or unordered channel identifiers, channel positions may be incorrect");
+
+    match Channels::from_bits(((1u64 << ids.len()) - 1) as u32) {
+        Some(channels) if !ids.is_empty() => Ok(channels),
+        _ => unsupported_error("dff: unsupported number of channels"),
+    }
+}
+
+/// Reads the comments chunk.
+fn read_comments<B: ReadBytes>(reader: &mut B, builder: &mut MetadataBuilder) -> Result<()> {
+    let n_comments = reader.read_be_u16()?;
+
+    for _ in 0..n_comments {
+        // Skip the time stamp, comment type, and comment reference.
+        reader.ignore_bytes(10)?;
+
+        let text = read_text(reader)?;
+
+        builder.add_tag(Tag::new(Some(StandardTagKey::Comment), "COMT", Value::from(text)));
+    }
+
+    Ok(())
+}
+
+/// Reads the edited master information chunk.
+fn read_master_info<B: ReadBytes>(
+    reader: &mut B,
+    len: u64,
+    builder: &mut MetadataBuilder,
+    markers: &mut Vec<Marker>,
+    sample_rate: u32,
+) -> Result<()> {
+    let mut consumed = 0;
+
+    while consumed + 12 <= len {
+        let chunk = ChunkHeader::read(reader)?;
+
+        let mut scoped = ScopedStream::new(&mut *reader, chunk.padded_len());
+
+        match &chunk.id {
+            b"DIAR" => {
+                let text = read_text(&mut scoped)?;
+                builder.add_tag(Tag::new(Some(StandardTagKey::Artist), "DIAR", Value::from(text)));
+            }
+            b"DITI" => {
+                let text = read_text(&mut scoped)?;
+                builder.add_tag(Tag::new(
+                    Some(StandardTagKey::TrackTitle),
+                    "DITI",
+                    Value::from(text),
+                ));
+            }
+            b"EMID" => {
+                let buf = scoped.read_boxed_slice_exact(chunk.len as usize)?;
+                let text = String::from_utf8_lossy(&buf).trim_end_matches('\0').to_string();
+                builder.add_tag(Tag::new(None, "EMID", Value::from(text)));
+            }
+            b"MARK" => {
+                let hours = u64::from(scoped.read_be_u16()?);
+                let minutes = u64::from(scoped.read_u8()?);
+                let seconds = u64::from(scoped.read_u8()?);
+                let samples = u64::from(scoped.read_be_u32()?);
+                let offset = scoped.read_be_i32()?;
+                let mark_type = scoped.read_be_u16()?;
+
+                // Skip the marker channel and track flags.
+                scoped.ignore_bytes(4)?;
+
+                let text = read_text(&mut scoped)?;
+
+                let secs = 3600 * hours + 60 * minutes + seconds;
+                let pos = (secs * u64::from(sample_rate) + samples) as i64 + i64::from(offset);
+
+                markers.push(Marker { pos, mark_type, text });
+            }
+            _ => (),
+        }
+
+        scoped.ignore()?;
+
+        consumed += 12 + chunk.padded_len();
+    }
+
+    Ok(())
+}
+
+/// Direct Stream Digital Interchange File Format (DSDIFF) format reader.
+///
+/// `DffReader` implements a demuxer for uncompressed DSDIFF files. Each packet contains interleaved
+/// bytes of every channel.
+pub struct DffReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    data_start_pos: u64,
+    data_end_pos: u64,
+    n_channels: u64,
+}
+
+impl QueryDescriptor for DffReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "dff",
+            "Direct Stream Digital Interchange File Format",
+            &["dff"],
+            &["audio/x-dff"],
+            &[b"FRM8"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for DffReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let form = ChunkHeader::read(&mut source)?;
+
+        if form.id != *b"FRM8" {
+            return unsupported_error("dff: missing frm8 chunk");
+        }
+
+        if form.len < 4 || source.read_quad_bytes()? != *b"DSD " {
+            return unsupported_error("dff: form type is not dsd");
+        }
+
+        let form_end_pos = source.pos() - 4 + form.len;
+
+        let mut props = None;
+        let mut data = None;
+        let mut builder = MetadataBuilder::new();
+        let mut id3_metadata = None;
+        let mut markers = Vec::new();
+
+        while source.pos() + 12 <= form_end_pos {
+            let chunk = ChunkHeader::read(&mut source)?;
+
+            match &chunk.id {
+                b"FVER" => {
+                    let version = source.read_be_u32()?;
+
+                    if version >> 24 != 1 {
+                        return unsupported_error("dff: unsupported format version");
+                    }
+
+                    source.ignore_bytes(chunk.padded_len() - 4)?;
+                }
+                b"PROP" => {
+                    let mut scoped = ScopedStream::new(&mut source, chunk.padded_len());
+                    props = Some(Properties::read(&mut scoped, chunk.len)?);
+                    scoped.ignore()?;
+                }
+                b"DSD " => {
+                    let data_start_pos = source.pos();
+
+                    data = Some((data_start_pos, data_start_pos + chunk.len));
+
+                    // Chunks following the sound data can only be read if the stream is
+                    // seekable.
+                    if !source.is_seekable() {
+                        break;
+                    }
+
+                    source.seek(SeekFrom::Start(data_start_pos + chunk.padded_len()))?;
+                }
+                b"DST " => return unsupported_error("dff: dst compressed audio is not supported"),
+                b"COMT" => {
+                    let mut scoped = ScopedStream::new(&mut source, chunk.padded_len());
+
+                    if let Err(err) = read_comments(&mut scoped, &mut builder) {
+                        warn!("dff: failed to read comments: {}", err);
+                    }
+
+                    scoped.ignore()?;
+                }
+                b"DIIN" => {
+                    let sample_rate = props.as_ref().and_then(|p: &Properties| p.sample_rate);
+
+                    let mut scoped = ScopedStream::new(&mut source, chunk.padded_len());
+
+                    if let Err(err) = read_master_info(
+                        &mut scoped,
+                        chunk.len,
+                        &mut builder,
+                        &mut markers,
+                        sample_rate.unwrap_or(0),
+                    ) {
+                        warn!("dff: failed to read edited master information: {}", err);
+                    }
+
+                    scoped.ignore()?;
+                }
+                b"ID3 " => {
+                    let mut scoped = ScopedStream::new(&mut source, chunk.padded_len());
+
+                    let mut id3_builder = MetadataBuilder::new();
+
+                    match id3v2::read_id3v2(&mut scoped, &mut id3_builder) {
+                        Ok(_) => id3_metadata = Some(id3_builder.metadata()),
+                        Err(err) => warn!("dff: failed to read id3v2 tag: {}", err),
+                    }
+
+                    scoped.ignore()?;
+                }
+                _ => {
+                    debug!("ignoring chunk: {}", String::from_utf8_lossy(&chunk.id));
+                    source.ignore_bytes(chunk.padded_len())?;
+                }
+            }
+        }
+
+        let props = match props {
+            Some(props) => props,
+            None => return decode_error("dff: missing property chunk"),
+        };
+
+        let (sample_rate, channels) = match (props.sample_rate, props.channels) {
+            (Some(sample_rate), Some(channels)) if sample_rate > 0 => (sample_rate, channels),
+            _ => return decode_error("dff: missing sample rate or channels"),
+        };
+
+        let (data_start_pos, data_end_pos) = match data {
+            Some(data) => data,
+            None => return decode_error("dff: missing sound data chunk"),
+        };
+
+        if source.pos() != data_start_pos {
+            source.seek(SeekFrom::Start(data_start_pos))?;
+        }
+
+        let n_channels = channels.count() as u64;
+
+        let mut metadata = MetadataLog::default();
+
+        metadata.push(builder.metadata());
+
+        if let Some(id3_metadata) = id3_metadata {
+            metadata.push(id3_metadata);
+        }
+
+        // Track start markers are exposed as cues.
+        let cues = markers
+            .iter()
+            .filter(|marker| marker.mark_type == MARK_TYPE_TRACK_START)
+            .enumerate()
+            .map(|(i, marker)| {
+                let tags = if marker.text.is_empty() {
+                    Vec::new()
+                }
+                else {
+                    vec![Tag::new(None, "MARK", Value::from(marker.text.clone()))]
+                };
+
+                Cue {
+                    index: i as u32,
+                    start_ts: marker.pos.max(0) as u64,
+                    tags,
+                    points: Vec::new(),
+                }
+            })
+            .collect();
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_DSD_MSBF)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_bits_per_coded_sample(1)
+            .with_channels(channels)
+            .with_n_frames(8 * ((data_end_pos - data_start_pos) / n_channels))
+            .with_max_frames_per_packet(8 * PACKET_LEN_PER_CHANNEL);
+
+        Ok(DffReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues,
+            metadata,
+            data_start_pos,
+            data_end_pos,
+            n_channels,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+
+        // Only read whole bytes of every channel.
+        let len = (self.data_end_pos.saturating_sub(pos) / self.n_channels)
+            .min(PACKET_LEN_PER_CHANNEL)
+            * self.n_channels;
+
+        if len == 0 {
+            return end_of_stream_error();
+        }
+
+        let data = self.reader.read_boxed_slice_exact(len as usize)?;
+
+        let ts = 8 * ((pos - self.data_start_pos) / self.n_channels);
+        let dur = 8 * (len / self.n_channels);
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, data))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                if let Some(sample_rate) = params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        // Seek to the start of the packet containing the required timestamp.
+        let packet_dur = 8 * PACKET_LEN_PER_CHANNEL;
+
+        let actual_ts = (required_ts / packet_dur) * packet_dur;
+        let pos = self.data_start_pos + (actual_ts / 8) * self.n_channels;
+
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if pos >= current_pos {
+                self.reader.ignore_bytes(pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        debug!("seeked to actual_ts={}, required_ts={}", actual_ts, required_ts);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
@@ -0,0 +1,339 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::CODEC_TYPE_DSD_MSBF_PLANAR;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DSD_LSBF_PLANAR};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::id3v2;
+
+use log::{debug, warn};
+
+/// The DSD chunk identifier.
+const DSD_CHUNK_ID: [u8; 4] = *b"DSD ";
+
+/// The format chunk identifier.
+const FMT_CHUNK_ID: [u8; 4] = *b"fmt ";
+
+/// The data chunk identifier.
+const DATA_CHUNK_ID: [u8; 4] = *b"data";
+
+/// The format chunk.
+#[derive(Debug)]
+struct FmtChunk {
+    /// The channel type.
+    channel_type: u32,
+    /// The number of channels.
+    n_channels: u32,
+    /// The DSD sample rate.
+    sample_rate: u32,
+    /// The bits per sample: 1 if the least-significant bit of each byte is first, or 8 if the
+    /// most-significant bit is first.
+    bits_per_sample: u32,
+    /// The number of samples per channel.
+    n_samples: u64,
+    /// The length of the block of each channel in bytes.
+    block_len: u32,
+}
+
+impl FmtChunk {
+    fn read<B: ReadBytes>(reader: &mut B) -> Result<FmtChunk> {
+        let version = reader.read_u32()?;
+        let format_id = reader.read_u32()?;
+
+        if version != 1 {
+            return unsupported_error("dsf: unsupported format version");
+        }
+
+        if format_id != 0 {
+            return unsupported_error("dsf: unsupported format, only raw dsd is supported");
+        }
+
+        let fmt = FmtChunk {
+            channel_type: reader.read_u32()?,
+            n_channels: reader.read_u32()?,
+            sample_rate: reader.read_u32()?,
+            bits_per_sample: reader.read_u32()?,
+            n_samples: reader.read_u64()?,
+            block_len: reader.read_u32()?,
+        };
+
+        // Reserved.
+        reader.read_u32()?;
+
+        if fmt.n_channels == 0 || fmt.block_len == 0 {
+            return decode_error("dsf: invalid number of channels or block size");
+        }
+
+        Ok(fmt)
+    }
+
+    /// Gets the channels from the channel type.
+    fn channels(&self) -> Result<Channels> {
+        let channels = match self.channel_type {
+            1 => Channels::FRONT_CENTRE,
+            2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+            3 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE,
+            4 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            }
+            5 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+            }
+            6 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            }
+            7 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            }
+            _ => return unsupported_error("dsf: unsupported channel type"),
+        };
+
+        if channels.count() != self.n_channels as usize {
+            return decode_error("dsf: channel type does not match the number of channels");
+        }
+
+        Ok(channels)
+    }
+}
+
+/// DSD Stream File (DSF) format reader.
+///
+/// `DsfReader` implements a demuxer for DSF files. Each packet contains one block of every
+/// channel, in planar order.
+pub struct DsfReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    data_start_pos: u64,
+    /// The length of a block group (a block of every channel) in bytes.
+    group_len: u64,
+    /// The number of samples per channel in a block group.
+    group_dur: u64,
+    n_samples: u64,
+    next_group: u64,
+}
+
+impl QueryDescriptor for DsfReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!("dsf", "DSD Stream File", &["dsf"], &["audio/x-dsf"], &[b"DSD "])]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for DsfReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // The DSD chunk.
+        if source.read_quad_bytes()? != DSD_CHUNK_ID {
+            return unsupported_error("dsf: missing dsd chunk");
+        }
+
+        let dsd_chunk_len = source.read_u64()?;
+        let _file_len = source.read_u64()?;
+        let metadata_pos = source.read_u64()?;
+
+        if dsd_chunk_len < 28 {
+            return decode_error("dsf: invalid dsd chunk size");
+        }
+
+        source.ignore_bytes(dsd_chunk_len - 28)?;
+
+        // The format chunk.
+        if source.read_quad_bytes()? != FMT_CHUNK_ID {
+            return unsupported_error("dsf: missing format chunk");
+        }
+
+        let fmt_chunk_len = source.read_u64()?;
+
+        if fmt_chunk_len < 52 {
+            return decode_error("dsf: invalid format chunk size");
+        }
+
+        let fmt = FmtChunk::read(&mut source)?;
+
+        debug!("{:?}", fmt);
+
+        source.ignore_bytes(fmt_chunk_len - 52)?;
+
+        let codec = match fmt.bits_per_sample {
+            1 => CODEC_TYPE_DSD_LSBF_PLANAR,
+            8 => CODEC_TYPE_DSD_MSBF_PLANAR,
+            _ => return unsupported_error("dsf: unsupported bits per sample"),
+        };
+
+        let channels = fmt.channels()?;
+
+        // The data chunk.
+        if source.read_quad_bytes()? != DATA_CHUNK_ID {
+            return unsupported_error("dsf: missing data chunk");
+        }
+
+        let data_chunk_len = source.read_u64()?;
+
+        if data_chunk_len < 12 {
+            return decode_error("dsf: invalid data chunk size");
+        }
+
+        let data_start_pos = source.pos();
+
+        let group_len = u64::from(fmt.n_channels) * u64::from(fmt.block_len);
+        let group_dur = 8 * u64::from(fmt.block_len);
+
+        // The sample count must not exceed the data in the data chunk.
+        let n_samples = fmt.n_samples.min((data_chunk_len - 12) / group_len * group_dur);
+
+        let mut metadata = MetadataLog::default();
+
+        // An ID3v2 tag may be stored at the end of the file. It can only be read if the stream is
+        // seekable.
+        if metadata_pos != 0 && source.is_seekable() {
+            source.seek(SeekFrom::Start(metadata_pos))?;
+
+            let mut builder = MetadataBuilder::new();
+
+            match id3v2::read_id3v2(&mut source, &mut builder) {
+                Ok(_) => metadata.push(builder.metadata()),
+                Err(err) => warn!("dsf: failed to read id3v2 tag: {}", err),
+            }
+
+            source.seek(SeekFrom::Start(data_start_pos))?;
+        }
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(codec)
+            .with_sample_rate(fmt.sample_rate)
+            .with_time_base(TimeBase::new(1, fmt.sample_rate))
+            .with_bits_per_coded_sample(1)
+            .with_channels(channels)
+            .with_n_frames(n_samples)
+            .with_max_frames_per_packet(group_dur);
+
+        Ok(DsfReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata,
+            data_start_pos,
+            group_len,
+            group_dur,
+            n_samples,
+            next_group: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let ts = self.next_group * self.group_dur;
+
+        if ts >= self.n_samples {
+            return end_of_stream_error();
+        }
+
+        let data = self.reader.read_boxed_slice_exact(self.group_len as usize)?;
+
+        // The final block of each channel is padded, the padding is trimmed.
+        let dur = self.group_dur.min(self.n_samples - ts);
+        let trim_end = (self.group_dur - dur) as u32;
+
+        self.next_group += 1;
+
+        Ok(Packet::new_trimmed_from_boxed_slice(0, ts, dur, 0, trim_end, data))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                if let Some(sample_rate) = self.tracks[0].codec_params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        if required_ts > self.n_samples {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // Block groups are always seeked to, so the actual timestamp is the start of the block
+        // group containing the required timestamp.
+        let group = required_ts / self.group_dur;
+        let pos = self.data_start_pos + group * self.group_len;
+
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if pos >= current_pos {
+                self.reader.ignore_bytes(pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_group = group;
+
+        let actual_ts = group * self.group_dur;
+
+        debug!(
+            "seeked to block group={} actual_ts={}, required_ts={}",
+            group, actual_ts, required_ts
+        );
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
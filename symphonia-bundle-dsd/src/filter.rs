@@ -0,0 +1,260 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Multi-stage decimation of a 1-bit DSD stream to PCM.
+//!
+//! The first stage low-pass filters the bitstream and decimates it by 8 using per-byte lookup
+//! tables. Each following stage low-pass filters, and decimates by 2. All filters are linear-phase
+//! FIR filters designed with a Kaiser window.
+
+use std::f64::consts::PI;
+
+/// The stopband attenuation of all filters in dB.
+const ATTENUATION: f64 = 120.0;
+
+/// The upper bound of the passband in Hz.
+const MAX_PASSBAND: f64 = 30_000.0;
+
+/// Evaluates the zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+
+    while term > 1e-12 * sum {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
+/// Designs a low-pass filter with unity DC gain. The cutoff and transition width are normalized
+/// to the input sample rate. If given, the number of taps is rounded up to a multiple of `align`.
+fn design_lowpass(cutoff: f64, transition: f64, align: usize) -> Vec<f32> {
+    let beta = 0.1102 * (ATTENUATION - 8.7);
+
+    let n_taps = ((ATTENUATION - 7.95) / (14.36 * transition)).ceil() as usize + 1;
+    let n_taps = ((n_taps + align - 1) / align) * align;
+
+    let centre = (n_taps - 1) as f64 / 2.0;
+
+    let taps: Vec<f64> = (0..n_taps)
+        .map(|n| {
+            let t = n as f64 - centre;
+
+            let sinc =
+                if t == 0.0 { 2.0 * cutoff } else { (2.0 * PI * cutoff * t).sin() / (PI * t) };
+
+            let r = t / centre;
+            let window = bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(beta);
+
+            sinc * window
+        })
+        .collect();
+
+    let sum: f64 = taps.iter().sum();
+
+    taps.iter().map(|&tap| (tap / sum) as f32).collect()
+}
+
+/// The filters shared by the decimators of all channels.
+pub struct FilterBank {
+    /// For each byte of the first stage's history, the sum of the filter taps applied to each
+    /// possible byte value.
+    byte_tables: Vec<[f32; 256]>,
+    /// The taps of each decimate-by-2 stage.
+    stages: Vec<Vec<f32>>,
+}
+
+impl FilterBank {
+    /// Instantiates the filters to decimate a DSD stream with the given sample rate by a factor of
+    /// `8 * 2^n_stages`.
+    pub fn new(dsd_rate: u32, n_stages: u32) -> Self {
+        let dsd_rate = f64::from(dsd_rate);
+
+        let output_rate = dsd_rate / f64::from(8 << n_stages);
+        let passband = (0.45 * output_rate).min(MAX_PASSBAND);
+
+        // The first stage. Only frequencies that would alias into the passband after decimation by
+        // 8 must be attenuated.
+        let rate = dsd_rate / 8.0;
+
+        let taps = design_lowpass(0.5 * rate / dsd_rate, (rate - 2.0 * passband) / dsd_rate, 8);
+
+        let byte_tables = taps
+            .chunks_exact(8)
+            .map(|taps| {
+                let mut table = [0.0; 256];
+
+                // The most-significant bit is the oldest bit of the byte.
+                for (byte, sum) in table.iter_mut().enumerate() {
+                    *sum = taps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &tap)| {
+                            if byte & (0x80 >> i) != 0 {
+                                tap
+                            }
+                            else {
+                                -tap
+                            }
+                        })
+                        .sum();
+                }
+
+                table
+            })
+            .collect();
+
+        // The decimate-by-2 stages.
+        let stages = (0..n_stages)
+            .map(|stage| {
+                let input_rate = rate / f64::from(1 << stage);
+
+                design_lowpass(0.25, (0.5 * input_rate - 2.0 * passband) / input_rate, 1)
+            })
+            .collect();
+
+        FilterBank { byte_tables, stages }
+    }
+}
+
+/// A history of samples that can be viewed as a contiguous slice, oldest first.
+struct History<T: Copy + Default> {
+    buf: Vec<T>,
+    pos: usize,
+}
+
+impl<T: Copy + Default> History<T> {
+    fn new(len: usize) -> Self {
+        History { buf: vec![Default::default(); 2 * len], pos: 0 }
+    }
+
+    fn push(&mut self, value: T) {
+        let len = self.buf.len() / 2;
+
+        self.buf[self.pos] = value;
+        self.buf[self.pos + len] = value;
+
+        self.pos = (self.pos + 1) % len;
+    }
+
+    fn samples(&self) -> &[T] {
+        &self.buf[self.pos..self.pos + self.buf.len() / 2]
+    }
+
+    fn clear(&mut self) {
+        self.buf.iter_mut().for_each(|value| *value = Default::default());
+        self.pos = 0;
+    }
+}
+
+/// A decimate-by-2 stage of a channel.
+struct Stage {
+    history: History<f32>,
+    /// If `true`, the next input sample produces an output sample.
+    is_odd: bool,
+}
+
+/// Converts the DSD stream of a channel to PCM.
+pub struct Decimator {
+    bytes: History<u8>,
+    stages: Vec<Stage>,
+}
+
+impl Decimator {
+    pub fn new(bank: &FilterBank) -> Self {
+        let stages = bank
+            .stages
+            .iter()
+            .map(|taps| Stage { history: History::new(taps.len()), is_odd: false })
+            .collect();
+
+        let mut decimator = Decimator { bytes: History::new(bank.byte_tables.len()), stages };
+        decimator.reset();
+        decimator
+    }
+
+    /// Resets the filter history to silence.
+    pub fn reset(&mut self) {
+        // Silence is an alternating bit pattern.
+        for _ in 0..self.bytes.buf.len() / 2 {
+            self.bytes.push(0x69);
+        }
+
+        for stage in self.stages.iter_mut() {
+            stage.history.clear();
+            stage.is_odd = false;
+        }
+    }
+
+    /// Filters and decimates DSD bytes, most-significant bit first, and appends the output samples
+    /// to `out`.
+    pub fn process<I>(&mut self, bank: &FilterBank, bytes: I, out: &mut Vec<f32>)
+    where
+        I: Iterator<Item = u8>,
+    {
+        'bytes: for byte in bytes {
+            self.bytes.push(byte);
+
+            let mut sample: f32 = self
+                .bytes
+                .samples()
+                .iter()
+                .zip(&bank.byte_tables)
+                .map(|(&byte, table)| table[usize::from(byte)])
+                .sum();
+
+            for (stage, taps) in self.stages.iter_mut().zip(&bank.stages) {
+                stage.history.push(sample);
+
+                stage.is_odd = !stage.is_odd;
+
+                // Every second sample is discarded.
+                if stage.is_odd {
+                    continue 'bytes;
+                }
+
+                sample = stage.history.samples().iter().zip(taps).map(|(&x, &tap)| x * tap).sum();
+            }
+
+            out.push(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decimator, FilterBank};
+
+    fn decimate(dsd_rate: u32, n_stages: u32, byte: u8, n_bytes: usize) -> Vec<f32> {
+        let bank = FilterBank::new(dsd_rate, n_stages);
+        let mut decimator = Decimator::new(&bank);
+
+        let mut out = Vec::new();
+        decimator.process(&bank, std::iter::repeat(byte).take(n_bytes), &mut out);
+        out
+    }
+
+    #[test]
+    fn verify_dc_gain() {
+        // Silence.
+        let out = decimate(2_822_400, 2, 0x69, 16384);
+        assert_eq!(out.len(), 4096);
+        assert!(out[2048..].iter().all(|&s| s.abs() < 1e-4));
+
+        // Full-scale positive, and negative modulation.
+        let out = decimate(5_644_800, 3, 0xff, 16384);
+        assert_eq!(out.len(), 2048);
+        assert!(out[1024..].iter().all(|&s| (s - 1.0).abs() < 1e-4));
+
+        let out = decimate(2_822_400, 0, 0x00, 16384);
+        assert!(out[8192..].iter().all(|&s| (s + 1.0).abs() < 1e-4));
+    }
+}
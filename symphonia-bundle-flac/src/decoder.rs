@@ -20,7 +20,7 @@ use symphonia_core::formats::Packet;
 use symphonia_core::io::{BitReaderLtr, BufReader, ReadBitsLtr};
 use symphonia_core::support_codec;
 use symphonia_core::units::TimeBase;
-use symphonia_core::util::bits::sign_extend_leq32_to_i32;
+use symphonia_core::util::bits::{sign_extend_leq32_to_i32, sign_extend_leq64_to_i64};
 use symphonia_utils_xiph::flac::metadata::StreamInfo;
 
 use log::{debug, log_enabled, warn};
@@ -80,12 +80,39 @@ fn decorrelate_right_side(right: &[i32], side: &mut [i32]) {
     }
 }
 
+// For 32-bit streams, the Side (Difference) channel requires 33 bits per sample. These variants of
+// the decorrelation functions take the Side channel as 64-bit samples and write the restored
+// channel into `out`.
+
+fn decorrelate_left_side_wide(left: &[i32], side: &[i64], out: &mut [i32]) {
+    for ((o, s), l) in out.iter_mut().zip(side).zip(left) {
+        *o = (i64::from(*l) - *s) as i32;
+    }
+}
+
+fn decorrelate_mid_side_wide(mid: &mut [i32], side: &[i64], out: &mut [i32]) {
+    // See `decorrelate_mid_side` for an explanation.
+    for ((m, s), o) in mid.iter_mut().zip(side).zip(out) {
+        let mid = (i64::from(*m) << 1) | (*s & 1);
+        *m = ((mid + *s) >> 1) as i32;
+        *o = ((mid - *s) >> 1) as i32;
+    }
+}
+
+fn decorrelate_right_side_wide(right: &[i32], side: &[i64], out: &mut [i32]) {
+    for ((o, s), r) in out.iter_mut().zip(side).zip(right) {
+        *o = (i64::from(*r) + *s) as i32;
+    }
+}
+
 /// Free Lossless Audio Codec (FLAC) decoder.
 pub struct FlacDecoder {
     params: CodecParameters,
     is_validating: bool,
     validator: Validator,
     buf: AudioBuffer<i32>,
+    /// Scratch buffer for the 33-bit Side channel of 32-bit stereo streams.
+    side_buf: Vec<i64>,
 }
 
 impl FlacDecoder {
@@ -116,9 +143,23 @@ impl FlacDecoder {
         //     bits_per_sample,
         //     &header.channel_assignment);
 
+        if bits_per_sample > 32 {
+            return decode_error("flac: bits per sample exceeds 32");
+        }
+
+        let n_samples = header.block_num_samples as usize;
+
         // Reserve a writeable chunk in the buffer equal to the number of samples in the block.
         self.buf.clear();
-        self.buf.render_reserved(Some(header.block_num_samples as usize));
+        self.buf.render_reserved(Some(n_samples));
+
+        // If the Side channel of a stereo pair would exceed 32 bits per sample, it must be decoded
+        // into a wider scratch buffer.
+        let is_wide_side = bits_per_sample == 32;
+
+        if is_wide_side && self.side_buf.len() < n_samples {
+            self.side_buf.resize(n_samples, 0);
+        }
 
         // Only Bitstream reading for subframes.
         {
@@ -138,25 +179,46 @@ impl FlacDecoder {
                     let (left, side) = self.buf.chan_pair_mut(0, 1);
 
                     read_subframe(&mut bs, bits_per_sample, left)?;
-                    read_subframe(&mut bs, bits_per_sample + 1, side)?;
 
-                    decorrelate_left_side(left, side);
+                    if is_wide_side {
+                        let wide = &mut self.side_buf[..n_samples];
+                        read_subframe_wide(&mut bs, bits_per_sample + 1, wide)?;
+                        decorrelate_left_side_wide(left, wide, side);
+                    }
+                    else {
+                        read_subframe(&mut bs, bits_per_sample + 1, side)?;
+                        decorrelate_left_side(left, side);
+                    }
                 }
                 ChannelAssignment::MidSide => {
                     let (mid, side) = self.buf.chan_pair_mut(0, 1);
 
                     read_subframe(&mut bs, bits_per_sample, mid)?;
-                    read_subframe(&mut bs, bits_per_sample + 1, side)?;
 
-                    decorrelate_mid_side(mid, side);
+                    if is_wide_side {
+                        let wide = &mut self.side_buf[..n_samples];
+                        read_subframe_wide(&mut bs, bits_per_sample + 1, wide)?;
+                        decorrelate_mid_side_wide(mid, wide, side);
+                    }
+                    else {
+                        read_subframe(&mut bs, bits_per_sample + 1, side)?;
+                        decorrelate_mid_side(mid, side);
+                    }
                 }
                 ChannelAssignment::RightSide => {
                     let (side, right) = self.buf.chan_pair_mut(0, 1);
 
-                    read_subframe(&mut bs, bits_per_sample + 1, side)?;
-                    read_subframe(&mut bs, bits_per_sample, right)?;
-
-                    decorrelate_right_side(right, side);
+                    if is_wide_side {
+                        let wide = &mut self.side_buf[..n_samples];
+                        read_subframe_wide(&mut bs, bits_per_sample + 1, wide)?;
+                        read_subframe(&mut bs, bits_per_sample, right)?;
+                        decorrelate_right_side_wide(right, wide, side);
+                    }
+                    else {
+                        read_subframe(&mut bs, bits_per_sample + 1, side)?;
+                        read_subframe(&mut bs, bits_per_sample, right)?;
+                        decorrelate_right_side(right, side);
+                    }
                 }
             }
         }
@@ -174,6 +236,8 @@ impl FlacDecoder {
             self.buf.transform(|sample| sample << shift);
         }
 
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
         Ok(())
     }
 }
@@ -226,6 +290,7 @@ impl Decoder for FlacDecoder {
             is_validating: options.verify,
             validator: Default::default(),
             buf,
+            side_buf: Vec::new(),
         })
     }
 
@@ -300,7 +365,7 @@ enum SubFrameType {
     Linear(u32),
 }
 
-fn read_subframe<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i32]) -> Result<()> {
+fn read_subframe_header<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32) -> Result<(SubFrameType, u32)> {
     // First sub-frame bit must always 0.
     if bs.read_bool()? {
         return decode_error("flac: subframe padding is not 0");
@@ -331,6 +396,22 @@ fn read_subframe<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i32]) ->
     // dropped bits per sample.
     let dropped_bps = if bs.read_bool()? { bs.read_unary_zeros()? + 1 } else { 0 };
 
+    // There must be atleast one bit per sample remaining after the dropped bits are removed.
+    if dropped_bps >= frame_bps {
+        return decode_error("flac: subframe wasted bits exceed bits per sample");
+    }
+
+    // trace!("\tsubframe: type={:?}, bps={}, dropped_bps={}",
+    //     &subframe_type,
+    //     frame_bps - dropped_bps,
+    //     dropped_bps);
+
+    Ok((subframe_type, dropped_bps))
+}
+
+fn read_subframe<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i32]) -> Result<()> {
+    let (subframe_type, dropped_bps) = read_subframe_header(bs, frame_bps)?;
+
     // The bits per sample stated in the frame header is for the decoded audio sub-block samples.
     // However, it is likely that the lower order bits of all the samples are simply 0. Therefore,
     // the encoder will truncate `dropped_bps` of lower order bits for every sample in a sub-block.
@@ -338,11 +419,6 @@ fn read_subframe<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i32]) ->
     // sub-frame and obtaining the truncated audio sub-block samples.
     let bps = frame_bps - dropped_bps;
 
-    // trace!("\tsubframe: type={:?}, bps={}, dropped_bps={}",
-    //     &subframe_type,
-    //     bps,
-    //     dropped_bps);
-
     match subframe_type {
         SubFrameType::Constant => decode_constant(bs, bps, buf)?,
         SubFrameType::Verbatim => decode_verbatim(bs, bps, buf)?,
@@ -356,6 +432,30 @@ fn read_subframe<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i32]) ->
     Ok(())
 }
 
+/// Reads a subframe with up-to 33 bits per sample. This is only required for the Side channel of
+/// 32-bit stereo streams.
+fn read_subframe_wide<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i64]) -> Result<()> {
+    let (subframe_type, dropped_bps) = read_subframe_header(bs, frame_bps)?;
+
+    let bps = frame_bps - dropped_bps;
+
+    match subframe_type {
+        SubFrameType::Constant => decode_constant_wide(bs, bps, buf)?,
+        SubFrameType::Verbatim => decode_verbatim_wide(bs, bps, buf)?,
+        SubFrameType::FixedLinear(order) => decode_fixed_linear_wide(bs, bps, order, buf)?,
+        SubFrameType::Linear(order) => decode_linear_wide(bs, bps, order, buf)?,
+    };
+
+    // Shift the samples to account for the dropped bits.
+    if dropped_bps > 0 {
+        for sample in buf.iter_mut() {
+            *sample = sample.wrapping_shl(dropped_bps);
+        }
+    }
+
+    Ok(())
+}
+
 #[inline(always)]
 fn samples_shl(shift: u32, buf: &mut [i32]) {
     if shift > 0 {
@@ -383,6 +483,24 @@ fn decode_verbatim<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i32]) -> Res
     Ok(())
 }
 
+fn decode_constant_wide<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i64]) -> Result<()> {
+    let const_sample = sign_extend_leq64_to_i64(bs.read_bits_leq64(bps)?, bps);
+
+    for sample in buf.iter_mut() {
+        *sample = const_sample;
+    }
+
+    Ok(())
+}
+
+fn decode_verbatim_wide<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i64]) -> Result<()> {
+    for sample in buf.iter_mut() {
+        *sample = sign_extend_leq64_to_i64(bs.read_bits_leq64(bps)?, bps);
+    }
+
+    Ok(())
+}
+
 fn decode_fixed_linear<B: ReadBitsLtr>(
     bs: &mut B,
     bps: u32,
@@ -405,13 +523,26 @@ fn decode_fixed_linear<B: ReadBitsLtr>(
     Ok(())
 }
 
-fn decode_linear<B: ReadBitsLtr>(bs: &mut B, bps: u32, order: u32, buf: &mut [i32]) -> Result<()> {
-    // The order of the Linear Predictor should be between 1 and 32.
-    debug_assert!(order > 0 && order <= 32);
+fn decode_fixed_linear_wide<B: ReadBitsLtr>(
+    bs: &mut B,
+    bps: u32,
+    order: u32,
+    buf: &mut [i64],
+) -> Result<()> {
+    decode_verbatim_wide(bs, bps, &mut buf[..order as usize])?;
 
-    // The first `order` samples are encoded verbatim to warm-up the LPC decoder.
-    decode_verbatim(bs, bps, &mut buf[0..order as usize])?;
+    decode_residual(bs, order, buf)?;
+
+    fixed_predict_wide(order, buf);
+
+    Ok(())
+}
 
+/// Reads the quantized linear predictor coefficients and shift of a LPC subframe. The coefficients
+/// are returned in reverse order with the first coefficient at index 31.
+///
+/// The shift may be negative, in which case the predicted value is shifted left instead of right.
+fn read_qlp_coeffs<B: ReadBitsLtr>(bs: &mut B, order: u32) -> Result<([i32; 32], i32)> {
     // Quantized linear predictor (QLP) coefficients precision in bits (1-16).
     let qlp_precision = bs.read_bits_leq32(4)? + 1;
 
@@ -422,47 +553,88 @@ fn decode_linear<B: ReadBitsLtr>(bs: &mut B, bps: u32, order: u32, buf: &mut [i3
     // QLP coefficients bit shift [-16, 15].
     let qlp_coeff_shift = sign_extend_leq32_to_i32(bs.read_bits_leq32(5)?, 5);
 
-    if qlp_coeff_shift >= 0 {
-        let mut qlp_coeffs = [0i32; 32];
-
-        for c in qlp_coeffs.iter_mut().rev().take(order as usize) {
-            *c = sign_extend_leq32_to_i32(bs.read_bits_leq32(qlp_precision)?, qlp_precision);
-        }
+    let mut qlp_coeffs = [0i32; 32];
 
-        decode_residual(bs, order, buf)?;
+    for c in qlp_coeffs.iter_mut().rev().take(order as usize) {
+        *c = sign_extend_leq32_to_i32(bs.read_bits_leq32(qlp_precision)?, qlp_precision);
+    }
 
-        // Helper function to dispatch to a predictor with a maximum order of N.
-        #[inline(always)]
-        fn lpc<const N: usize>(order: u32, coeffs: &[i32; 32], coeff_shift: i32, buf: &mut [i32]) {
-            let coeffs_n = (&coeffs[32 - N..32]).try_into().unwrap();
-            lpc_predict::<N>(order as usize, coeffs_n, coeff_shift as u32, buf);
-        }
+    Ok((qlp_coeffs, qlp_coeff_shift))
+}
 
-        // Pick the best length linear predictor to use based on the order. Most FLAC streams use
-        // the subset format and have an order <= 12. Therefore, for orders <= 12, dispatch to
-        // predictors that roughly match the order. If a predictor is too long for a given order,
-        // then there will be wasted computations. On the other hand, it is not worth the code bloat
-        // to specialize for every order <= 12.
-        match order {
-            0..=4 => lpc::<4>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            5..=6 => lpc::<6>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            7..=8 => lpc::<8>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            9..=10 => lpc::<10>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            11..=12 => lpc::<12>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            _ => lpc::<32>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-        };
+/// Applies the QLP coefficient shift to a predicted value. A negative shift is a left shift.
+///
+/// The shift is applied after summing since shifting the coefficients instead could overflow the
+/// sum. A left shift may discard high bits, but only the low bits of the prediction are kept
+/// anyways.
+#[inline(always)]
+fn lpc_shift(predicted: i64, coeff_shift: i32) -> i64 {
+    if coeff_shift >= 0 {
+        predicted >> coeff_shift
     }
     else {
-        return unsupported_error("flac: lpc shifts less than 0 are not supported");
+        predicted.wrapping_shl(coeff_shift.unsigned_abs())
+    }
+}
+
+fn decode_linear<B: ReadBitsLtr>(bs: &mut B, bps: u32, order: u32, buf: &mut [i32]) -> Result<()> {
+    // The order of the Linear Predictor should be between 1 and 32.
+    debug_assert!(order > 0 && order <= 32);
+
+    // The first `order` samples are encoded verbatim to warm-up the LPC decoder.
+    decode_verbatim(bs, bps, &mut buf[0..order as usize])?;
+
+    let (qlp_coeffs, qlp_coeff_shift) = read_qlp_coeffs(bs, order)?;
+
+    decode_residual(bs, order, buf)?;
+
+    // Helper function to dispatch to a predictor with a maximum order of N.
+    #[inline(always)]
+    fn lpc<const N: usize>(order: u32, coeffs: &[i32; 32], coeff_shift: i32, buf: &mut [i32]) {
+        let coeffs_n = (&coeffs[32 - N..32]).try_into().unwrap();
+        lpc_predict::<N>(order as usize, coeffs_n, coeff_shift, buf);
     }
 
+    // Pick the best length linear predictor to use based on the order. Most FLAC streams use
+    // the subset format and have an order <= 12. Therefore, for orders <= 12, dispatch to
+    // predictors that roughly match the order. If a predictor is too long for a given order,
+    // then there will be wasted computations. On the other hand, it is not worth the code bloat
+    // to specialize for every order <= 12.
+    match order {
+        0..=4 => lpc::<4>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        5..=6 => lpc::<6>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        7..=8 => lpc::<8>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        9..=10 => lpc::<10>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        11..=12 => lpc::<12>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        _ => lpc::<32>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+    };
+
+    Ok(())
+}
+
+fn decode_linear_wide<B: ReadBitsLtr>(
+    bs: &mut B,
+    bps: u32,
+    order: u32,
+    buf: &mut [i64],
+) -> Result<()> {
+    debug_assert!(order > 0 && order <= 32);
+
+    decode_verbatim_wide(bs, bps, &mut buf[0..order as usize])?;
+
+    let (qlp_coeffs, qlp_coeff_shift) = read_qlp_coeffs(bs, order)?;
+
+    decode_residual(bs, order, buf)?;
+
+    lpc_predict_wide(order as usize, &qlp_coeffs, qlp_coeff_shift, buf);
+
     Ok(())
 }
 
-fn decode_residual<B: ReadBitsLtr>(
+fn decode_residual<B: ReadBitsLtr, S: From<i32>>(
     bs: &mut B,
     n_prelude_samples: u32,
-    buf: &mut [i32],
+    buf: &mut [S],
 ) -> Result<()> {
     let method_enc = bs.read_bits_leq32(2)?;
 
@@ -523,10 +695,10 @@ fn decode_residual<B: ReadBitsLtr>(
     Ok(())
 }
 
-fn decode_rice_partition<B: ReadBitsLtr>(
+fn decode_rice_partition<B: ReadBitsLtr, S: From<i32>>(
     bs: &mut B,
     param_bit_width: u32,
-    buf: &mut [i32],
+    buf: &mut [S],
 ) -> Result<()> {
     // Read the encoding parameter, generally the Rice parameter.
     let rice_param = bs.read_bits_leq32(param_bit_width)?;
@@ -542,10 +714,13 @@ fn decode_rice_partition<B: ReadBitsLtr>(
         for sample in buf.iter_mut() {
             let q = bs.read_unary_zeros()?;
             let r = bs.read_bits_leq32(rice_param)?;
-            *sample = rice_signed_to_i32((q << rice_param) | r);
+            *sample = rice_signed_to_i32((q << rice_param) | r).into();
         }
     }
     else {
+        // The escaped partition stores residuals in binary with a fixed bit width. Residuals are
+        // limited to 32 bits by the specification, even for 32-bit streams, so a 5-bit width is
+        // always sufficient. A width of 0 indicates all residuals in the partition are 0.
         let residual_bits = bs.read_bits_leq32(5)?;
 
         // trace!(
@@ -556,7 +731,8 @@ fn decode_rice_partition<B: ReadBitsLtr>(
 
         // Read each binary encoded residual and store in buffer.
         for sample in buf.iter_mut() {
-            *sample = sign_extend_leq32_to_i32(bs.read_bits_leq32(residual_bits)?, residual_bits);
+            *sample =
+                sign_extend_leq32_to_i32(bs.read_bits_leq32(residual_bits)?, residual_bits).into();
         }
     }
 
@@ -623,7 +799,7 @@ fn fixed_predict(order: u32, buf: &mut [i32]) {
         // s(i) = 1*s(i),
         1 => {
             for i in 1..buf.len() {
-                buf[i] += buf[i - 1];
+                buf[i] = buf[i].wrapping_add(buf[i - 1]);
             }
         }
         // A 2nd order predictor uses the polynomial: s(i) = 2*s(i-1) - 1*s(i-2).
@@ -631,7 +807,7 @@ fn fixed_predict(order: u32, buf: &mut [i32]) {
             for i in 2..buf.len() {
                 let a = Wrapping(-1) * Wrapping(i64::from(buf[i - 2]));
                 let b = Wrapping(2) * Wrapping(i64::from(buf[i - 1]));
-                buf[i] += (a + b).0 as i32;
+                buf[i] = buf[i].wrapping_add((a + b).0 as i32);
             }
         }
         // A 3rd order predictor uses the polynomial: s(i) = 3*s(i-1) - 3*s(i-2) + 1*s(i-3).
@@ -640,7 +816,7 @@ fn fixed_predict(order: u32, buf: &mut [i32]) {
                 let a = Wrapping(1) * Wrapping(i64::from(buf[i - 3]));
                 let b = Wrapping(-3) * Wrapping(i64::from(buf[i - 2]));
                 let c = Wrapping(3) * Wrapping(i64::from(buf[i - 1]));
-                buf[i] += (a + b + c).0 as i32;
+                buf[i] = buf[i].wrapping_add((a + b + c).0 as i32);
             }
         }
         // A 4th order predictor uses the polynomial:
@@ -651,7 +827,7 @@ fn fixed_predict(order: u32, buf: &mut [i32]) {
                 let b = Wrapping(4) * Wrapping(i64::from(buf[i - 3]));
                 let c = Wrapping(-6) * Wrapping(i64::from(buf[i - 2]));
                 let d = Wrapping(4) * Wrapping(i64::from(buf[i - 1]));
-                buf[i] += (a + b + c + d).0 as i32;
+                buf[i] = buf[i].wrapping_add((a + b + c + d).0 as i32);
             }
         }
         _ => unreachable!(),
@@ -662,7 +838,10 @@ fn fixed_predict(order: u32, buf: &mut [i32]) {
 /// specified by `order`. Coefficients must be stored in reverse order in `coeffs` with the first
 /// coefficient at index 31. Coefficients at indices less than 31 - `order` must be 0.
 /// It is expected that the first `order` samples in `buf` are warm-up samples.
-fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: u32, buf: &mut [i32]) {
+///
+/// With at most 15-bit coefficients and 32 taps, the sum never exceeds 51 bits for 32-bit samples,
+/// or about 52 bits for the 33-bit side channel of 32-bit audio (see `lpc_predict_wide`).
+fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: i32, buf: &mut [i32]) {
     // Order must be less than or equal to the number of coefficients.
     debug_assert!(order <= coeffs.len());
 
@@ -680,7 +859,7 @@ fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: u32
             .map(|(&c, &sample)| c as i64 * sample as i64)
             .sum::<i64>();
 
-        buf[i] += (predicted >> coeff_shift) as i32;
+        buf[i] = buf[i].wrapping_add(lpc_shift(predicted, coeff_shift) as i32);
     }
 
     // If the pre-fill operation filled the entire sample buffer, return immediately.
@@ -696,6 +875,268 @@ fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: u32
             .map(|(&c, &s)| i64::from(c) * i64::from(s))
             .sum::<i64>();
 
-        buf[i] += (predicted >> coeff_shift) as i32;
+        buf[i] = buf[i].wrapping_add(lpc_shift(predicted, coeff_shift) as i32);
+    }
+}
+
+/// Fixed predictor for subframes with more than 32 bits per sample.
+fn fixed_predict_wide(order: u32, buf: &mut [i64]) {
+    debug_assert!(order <= 4);
+
+    // The polynomial coefficients of each fixed predictor order, most recent sample first.
+    const COEFFS: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+
+    let coeffs = COEFFS[order as usize];
+
+    for i in coeffs.len()..buf.len() {
+        let predicted = coeffs
+            .iter()
+            .zip(buf[..i].iter().rev())
+            .fold(0i64, |acc, (&c, &s)| acc.wrapping_add(c.wrapping_mul(s)));
+
+        buf[i] = buf[i].wrapping_add(predicted);
+    }
+}
+
+/// Linear Predictive Coding (LPC) decoder for subframes with more than 32 bits per sample. The
+/// coefficients are stored as described for `lpc_predict`.
+fn lpc_predict_wide(order: usize, coeffs: &[i32; 32], coeff_shift: i32, buf: &mut [i64]) {
+    debug_assert!(order <= buf.len());
+
+    let coeffs = &coeffs[32 - order..32];
+
+    for i in order..buf.len() {
+        // With at most 15-bit coefficients, 33-bit samples, and 32 taps, the sum of a valid stream
+        // never exceeds 52 bits. However, a corrupt stream could produce wider samples, so wrap.
+        let predicted = coeffs
+            .iter()
+            .zip(&buf[i - order..i])
+            .map(|(&c, &s)| i64::from(c).wrapping_mul(s))
+            .fold(0i64, |acc, p| acc.wrapping_add(p));
+
+        buf[i] = buf[i].wrapping_add(lpc_shift(predicted, coeff_shift));
+    }
+}
+
+#[test]
+fn verify_decorrelate_wide() {
+    let left = [i32::MAX, i32::MIN, 0, -1, 1];
+    let right = [i32::MIN, i32::MAX, 0, 1, -1];
+
+    // Side is Left - Right and requires 33 bits.
+    let side: Vec<i64> =
+        left.iter().zip(&right).map(|(&l, &r)| i64::from(l) - i64::from(r)).collect();
+
+    // Mid is (Left + Right) / 2.
+    let mid: Vec<i32> = left
+        .iter()
+        .zip(&right)
+        .map(|(&l, &r)| ((i64::from(l) + i64::from(r)) >> 1) as i32)
+        .collect();
+
+    let mut out = [0i32; 5];
+    decorrelate_left_side_wide(&left, &side, &mut out);
+    assert_eq!(out, right);
+
+    let mut out = [0i32; 5];
+    decorrelate_right_side_wide(&right, &side, &mut out);
+    assert_eq!(out, left);
+
+    let mut mid_out = mid.clone();
+    let mut out = [0i32; 5];
+    decorrelate_mid_side_wide(&mut mid_out, &side, &mut out);
+    assert_eq!(mid_out, left);
+    assert_eq!(out, right);
+}
+
+/// A minimal MSb-first bit writer for building test subframes and frames.
+#[cfg(test)]
+#[derive(Default)]
+struct BitWriter {
+    buf: Vec<u8>,
+    n_bits: u32,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    /// Writes the `width` least-significant bits of `bits`, most-significant first. Signed values
+    /// are therefore written in two's complement.
+    fn write(&mut self, bits: u64, width: u32) {
+        for i in (0..width).rev() {
+            if self.n_bits % 8 == 0 {
+                self.buf.push(0);
+            }
+            let bit = ((bits >> i) & 1) as u8;
+            *self.buf.last_mut().unwrap() |= bit << (7 - self.n_bits % 8);
+            self.n_bits += 1;
+        }
+    }
+
+    /// Writes a LPC subframe without wasted bits, and with one binary encoded residual partition.
+    fn write_lpc_subframe(
+        &mut self,
+        bps: u32,
+        warm_up: &[i64],
+        coeffs: &[i32],
+        shift: i32,
+        residuals: &[i64],
+    ) {
+        assert_eq!(warm_up.len(), coeffs.len());
+
+        // Padding, LPC subframe type with order - 1, and no wasted bits.
+        self.write(0, 1);
+        self.write(0x20 | (coeffs.len() as u64 - 1), 6);
+        self.write(0, 1);
+
+        for &sample in warm_up {
+            self.write(sample as u64, bps);
+        }
+
+        // 15-bit precision (minus one), and the shift.
+        self.write(14, 4);
+        self.write(shift as u64, 5);
+
+        // Coefficients are stored most recent sample first.
+        for &c in coeffs {
+            self.write(c as u64, 15);
+        }
+
+        // Rice coding, 1 partition, escaped with 24-bit residuals.
+        self.write(0, 2);
+        self.write(0, 4);
+        self.write(0xf, 4);
+        self.write(24, 5);
+
+        for &r in residuals {
+            self.write(r as u64, 24);
+        }
+    }
+
+    fn write_verbatim_subframe(&mut self, bps: u32, samples: &[i64]) {
+        self.write(0, 1);
+        self.write(1, 6);
+        self.write(0, 1);
+
+        for &sample in samples {
+            self.write(sample as u64, bps);
+        }
+    }
+}
+
+#[test]
+fn verify_decode_lpc_negative_shift() {
+    // A 2nd order predictor of (3*s(i-1) - 2*s(i-2)) << 1.
+    let coeffs = [3, -2];
+    let shift = -1;
+
+    let signal: [i64; 16] =
+        [1000, -2000, 1500, 30, -999, 0, 1, -1, 700, 701, 702, -32, -32768, 32767, 5, 0];
+
+    let residuals: Vec<i64> =
+        (2..16).map(|i| signal[i] - ((3 * signal[i - 1] - 2 * signal[i - 2]) << 1)).collect();
+
+    let mut bw = BitWriter::default();
+    bw.write_lpc_subframe(16, &signal[..2], &coeffs, shift, &residuals);
+
+    let mut bs = BitReaderLtr::new(&bw.buf);
+    let mut buf = [0i32; 16];
+
+    read_subframe(&mut bs, 16, &mut buf).unwrap();
+
+    let expected: Vec<i32> = signal.iter().map(|&s| s as i32).collect();
+
+    assert_eq!(&buf[..], &expected[..]);
+}
+
+#[test]
+fn verify_decode_32bit_stream() {
+    use symphonia_core::checksum::Crc8Ccitt;
+    use symphonia_core::io::Monitor;
+
+    // A 32-bit stereo stream with 16 sample blocks.
+    let mut info = BitWriter::default();
+    info.write(16, 16);
+    info.write(16, 16);
+    info.write(0, 24);
+    info.write(0, 24);
+    info.write(44_100, 20);
+    info.write(1, 3);
+    info.write(31, 5);
+    info.write(16, 36);
+    info.write(0, 64);
+    info.write(0, 64);
+
+    // The Left channel is predicted with full-scale coefficients and the largest negative shift
+    // such that the prediction is far wider than 64 bits before wrapping to 32 bits.
+    let coeffs = [-16384; 4];
+    let shift = -16;
+    let warm_up = [i64::from(i32::MIN); 4];
+    let residuals = [1, -1, 1000, -1000, 0, 0, 7, 7, 8_388_607, -8_388_608, 0, 2];
+
+    let mut left = warm_up.iter().map(|&s| s as i32).collect::<Vec<i32>>();
+
+    for &r in residuals.iter() {
+        let n = left.len();
+
+        let predicted: i128 = coeffs
+            .iter()
+            .zip(left[n - 4..].iter().rev())
+            .map(|(&c, &s)| i128::from(c) * i128::from(s))
+            .sum();
+
+        left.push(((predicted << -shift) + i128::from(r)) as i32);
+    }
+
+    let right = [
+        i32::MAX,
+        i32::MIN,
+        0,
+        -1,
+        1,
+        i32::MAX,
+        -5,
+        123_456_789,
+        i32::MIN,
+        i32::MIN,
+        i32::MAX,
+        0,
+        99,
+        -99,
+        1 << 30,
+        -(1 << 30),
+    ];
+
+    // The Side channel is Left - Right and requires 33 bits.
+    let side =
+        left.iter().zip(&right).map(|(&l, &r)| i64::from(l) - i64::from(r)).collect::<Vec<i64>>();
+
+    // Sync code for a fixed block size stream, 8-bit block size, 44.1 kHz, Left/Side, and 32 bits
+    // per sample, frame number 0, and block size minus one.
+    let mut frame = vec![0xff, 0xf8, 0x69, 0x8e, 0x00, 15];
+
+    let mut crc8 = Crc8Ccitt::new(0);
+    crc8.process_buf_bytes(&frame);
+    frame.push(crc8.crc());
+
+    let mut bw = BitWriter::default();
+    bw.write_lpc_subframe(32, &warm_up, &coeffs, shift, &residuals);
+    bw.write_verbatim_subframe(33, &side);
+
+    frame.extend_from_slice(&bw.buf);
+    frame.extend_from_slice(&[0, 0]);
+
+    let mut params = CodecParameters::new();
+    params.for_codec(CODEC_TYPE_FLAC).with_extra_data(info.buf.into_boxed_slice());
+
+    let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+    let packet = Packet::new_from_slice(0, 0, 16, &frame);
+
+    match decoder.decode(&packet).unwrap() {
+        AudioBufferRef::S32(buf) => {
+            assert_eq!(buf.chan(0), &left[..]);
+            assert_eq!(buf.chan(1), &right[..]);
+        }
+        _ => panic!("expected 32-bit samples"),
     }
 }
@@ -5,12 +5,13 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::io::{Seek, SeekFrom};
+use std::io::{ErrorKind, Seek, SeekFrom};
 
 use symphonia_core::support_format;
 
 use symphonia_core::codecs::{CodecParameters, VerificationCheck, CODEC_TYPE_FLAC};
-use symphonia_core::errors::{decode_error, seek_error, unsupported_error, Result, SeekErrorKind};
+use symphonia_core::errors::{decode_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Error, Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::formats::util::{SeekIndex, SeekSearchResult};
 use symphonia_core::io::*;
@@ -21,7 +22,7 @@ use symphonia_utils_xiph::flac::metadata::*;
 
 use log::{debug, info};
 
-use super::parser::PacketParser;
+use super::parser::{PacketParser, SyncInfo};
 
 /// The FLAC start of stream marker: "fLaC" in ASCII.
 const FLAC_STREAM_MARKER: [u8; 4] = *b"fLaC";
@@ -130,6 +131,192 @@ impl FlacReader {
 
         Ok(FlacReader { reader, metadata, tracks, cues, index, first_frame_offset, parser })
     }
+
+    /// Synchronize to the next frame that starts within the timestamp bounds formed by
+    /// `min_ts..=max_ts`. Frame headers outside of these bounds, though valid, must be false
+    /// positives since the bounds were established by previously found frames.
+    fn sync_bounded(&mut self, min_ts: u64, max_ts: u64) -> Result<SyncInfo> {
+        loop {
+            let sync = self.parser.resync(&mut self.reader)?;
+
+            if sync.ts >= min_ts && sync.ts <= max_ts {
+                return Ok(sync);
+            }
+
+            // Advance the reader such that the next iteration will sync to a different frame.
+            self.reader.read_byte()?;
+        }
+    }
+
+    /// Seek a seekable stream by bisecting it. Returns the timestamp of the packet seeked to.
+    fn seek_bisect(&mut self, ts: u64) -> Result<u64> {
+        // The range formed by start_byte_offset..end_byte_offset defines an area where the
+        // bisection search for the packet containing the desired timestamp will be performed. The
+        // lower bound is set to the byte offset of the first frame, while the upper bound is
+        // set to the length of the stream. The timestamp bounds, min_ts..=max_ts, are the
+        // timestamps of the frames at the lower and upper byte offsets, and are used to reject
+        // false frame headers.
+        let mut start_byte_offset = self.first_frame_offset;
+        let mut end_byte_offset = self.reader.seek(SeekFrom::End(0))?;
+        let mut min_ts = 0;
+        let mut max_ts = self.tracks[0].codec_params.n_frames.unwrap_or(u64::MAX);
+
+        // If there is an index, use it to refine the bisection search range.
+        if let Some(ref index) = self.index {
+            // Search the index for the timestamp. Adjust the search based on the result.
+            match index.search(ts) {
+                // Search from the start of stream up-to an ending point.
+                SeekSearchResult::Upper(upper) => {
+                    end_byte_offset = self.first_frame_offset + upper.byte_offset;
+                    max_ts = upper.frame_ts;
+                }
+                // Search from a starting point up-to the end of the stream.
+                SeekSearchResult::Lower(lower) => {
+                    start_byte_offset = self.first_frame_offset + lower.byte_offset;
+                    min_ts = lower.frame_ts;
+                }
+                // Search between two points of the stream.
+                SeekSearchResult::Range(lower, upper) => {
+                    start_byte_offset = self.first_frame_offset + lower.byte_offset;
+                    end_byte_offset = self.first_frame_offset + upper.byte_offset;
+                    min_ts = lower.frame_ts;
+                    max_ts = upper.frame_ts;
+                }
+                // Search the entire stream (default behaviour, so do nothing).
+                SeekSearchResult::Stream => (),
+            }
+        }
+
+        // When the search range is smaller than 2x the maximum frame size, the bisection search
+        // becomes inefficient. Exit the search, and search the range linearly frame-by-frame.
+        let min_range = 2 * self.parser.max_frame_size().unwrap_or(8192).max(1024) as u64;
+
+        // Bisect the range of bytes formed by start_byte_offset..end_byte_offset. Every iteration
+        // halves the search range, therefore a seek takes logarithmic time regardless of whether
+        // the stream has a seek table.
+        while end_byte_offset - start_byte_offset > min_range {
+            let mid_byte_offset = (start_byte_offset + end_byte_offset) / 2;
+            self.reader.seek(SeekFrom::Start(mid_byte_offset))?;
+
+            let sync = match self.sync_bounded(min_ts, max_ts) {
+                Ok(sync) => sync,
+                // No frame starts after the mid-point, search the lower half.
+                Err(Error::IoError(ref err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    end_byte_offset = mid_byte_offset;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            // The resynchronized frame starts at the current position of the reader. No frame
+            // starts between the mid-point and this position.
+            let frame_byte_offset = self.reader.pos();
+
+            if frame_byte_offset >= end_byte_offset || ts < sync.ts {
+                // Either no frame starts between the mid-point and the end of the range, or the
+                // frame found starts after the desired timestamp. In either case, the desired
+                // frame must start before the mid-point.
+                end_byte_offset = mid_byte_offset;
+                max_ts = max_ts.min(sync.ts);
+            }
+            else if ts < sync.ts + sync.dur {
+                // The frame contains the desired timestamp.
+                return Ok(sync.ts);
+            }
+            else {
+                // The frame precedes the desired timestamp.
+                start_byte_offset = frame_byte_offset;
+                min_ts = sync.ts;
+            }
+        }
+
+        // The bisection did not find the exact frame, but the range has been narrowed. Seek to
+        // the start of the range, and continue with a linear search.
+        self.reader.seek(SeekFrom::Start(start_byte_offset))?;
+        self.parser.soft_reset();
+
+        let mut sync = self.sync_bounded(min_ts, max_ts)?;
+        let mut frame_byte_offset = self.reader.pos();
+
+        let mut prev = None;
+
+        // Parse packets to step from frame-to-frame. Parsing validates the packet CRC, therefore
+        // false frame headers within the audio data are skipped over.
+        loop {
+            if ts < sync.ts {
+                // The desired timestamp was overshot, possibly because the stream is corrupted.
+                // Seek back to the previous frame since it must start before the desired
+                // timestamp.
+                if let Some((prev_byte_offset, prev_ts)) = prev {
+                    frame_byte_offset = prev_byte_offset;
+                    sync.ts = prev_ts;
+                }
+                break;
+            }
+            else if ts < sync.ts + sync.dur {
+                // The frame contains the desired timestamp.
+                break;
+            }
+
+            prev = Some((frame_byte_offset, sync.ts));
+
+            let packet = match self.parser.parse(&mut self.reader) {
+                Ok(packet) => packet,
+                // The desired timestamp is past the last frame, seek to the last frame.
+                Err(Error::IoError(ref err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+
+            // The reader is positioned at the start of the next frame.
+            frame_byte_offset = self.reader.pos();
+
+            sync = SyncInfo { ts: packet.ts + packet.dur, dur: 0 };
+
+            // Peek the duration of the next frame.
+            match self.parser.resync(&mut self.reader) {
+                Ok(next) => {
+                    frame_byte_offset = self.reader.pos();
+                    sync = next;
+                }
+                Err(Error::IoError(ref err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    // There are no more frames, seek to the last frame.
+                    if let Some((prev_byte_offset, prev_ts)) = prev {
+                        frame_byte_offset = prev_byte_offset;
+                        sync.ts = prev_ts;
+                    }
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.reader.seek(SeekFrom::Start(frame_byte_offset))?;
+
+        Ok(sync.ts)
+    }
+
+    /// Seek an unseekable stream by linearly searching forward. Returns the timestamp of the
+    /// packet seeked to.
+    fn seek_forward(&mut self, ts: u64) -> Result<u64> {
+        // Linearly search the stream packet-by-packet for the packet that contains the desired
+        // timestamp. This is the ONLY way for a unseekable stream to be "seeked" forward.
+        loop {
+            let sync = self.parser.resync(&mut self.reader)?;
+
+            // The desired timestamp precedes the current packet's timestamp. Attempted to seek
+            // backwards on an unseekable stream.
+            if ts < sync.ts {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+            // The desired timestamp is contained within the current packet.
+            else if ts < sync.ts + sync.dur {
+                return Ok(sync.ts);
+            }
+
+            // Advance the reader such that the next iteration will sync to a different frame.
+            self.reader.read_byte()?;
+        }
+    }
 }
 
 impl QueryDescriptor for FlacReader {
@@ -221,97 +408,17 @@ impl FormatReader for FlacReader {
             }
         }
 
-        // If the reader supports seeking, coarsely seek to the nearest packet with a timestamp
-        // lower than the desired timestamp using a binary search.
-        if self.reader.is_seekable() {
-            // The range formed by start_byte_offset..end_byte_offset defines an area where the
-            // binary search for the packet containing the desired timestamp will be performed. The
-            // lower bound is set to the byte offset of the first frame, while the upper bound is
-            // set to the length of the stream.
-            let mut start_byte_offset = self.first_frame_offset;
-            let mut end_byte_offset = self.reader.seek(SeekFrom::End(0))?;
-
-            // If there is an index, use it to refine the binary search range.
-            if let Some(ref index) = self.index {
-                // Search the index for the timestamp. Adjust the search based on the result.
-                match index.search(ts) {
-                    // Search from the start of stream up-to an ending point.
-                    SeekSearchResult::Upper(upper) => {
-                        end_byte_offset = self.first_frame_offset + upper.byte_offset;
-                    }
-                    // Search from a starting point up-to the end of the stream.
-                    SeekSearchResult::Lower(lower) => {
-                        start_byte_offset = self.first_frame_offset + lower.byte_offset;
-                    }
-                    // Search between two points of the stream.
-                    SeekSearchResult::Range(lower, upper) => {
-                        start_byte_offset = self.first_frame_offset + lower.byte_offset;
-                        end_byte_offset = self.first_frame_offset + upper.byte_offset;
-                    }
-                    // Search the entire stream (default behaviour, so do nothing).
-                    SeekSearchResult::Stream => (),
-                }
-            }
-
-            // Binary search the range of bytes formed by start_by_offset..end_byte_offset for the
-            // desired frame timestamp. When the difference of the range reaches 2x the maximum
-            // frame size, exit the loop and search from the start_byte_offset linearly. The binary
-            // search becomes inefficient when the range is small.
-            while end_byte_offset - start_byte_offset > 2 * 8096 {
-                let mid_byte_offset = (start_byte_offset + end_byte_offset) / 2;
-                self.reader.seek(SeekFrom::Start(mid_byte_offset))?;
-
-                let sync = self.parser.resync(&mut self.reader)?;
-
-                if ts < sync.ts {
-                    end_byte_offset = mid_byte_offset;
-                }
-                else if ts >= sync.ts && ts < sync.ts + sync.dur {
-                    debug!("seeked to ts={} (delta={})", sync.ts, sync.ts as i64 - ts as i64);
-
-                    return Ok(SeekedTo { track_id: 0, actual_ts: sync.ts, required_ts: ts });
-                }
-                else {
-                    start_byte_offset = mid_byte_offset;
-                }
-            }
-
-            // The binary search did not find an exact frame, but the range has been narrowed. Seek
-            // to the start of the range, and continue with a linear search.
-            self.reader.seek(SeekFrom::Start(start_byte_offset))?;
-        }
-
-        // Linearly search the stream packet-by-packet for the packet that contains the desired
-        // timestamp. This search is used to find the exact packet containing the desired timestamp
-        // after the search range was narrowed by the binary search. It is also the ONLY way for a
-        // unseekable stream to be "seeked" forward.
-        let packet = loop {
-            let sync = self.parser.resync(&mut self.reader)?;
-
-            // The desired timestamp precedes the current packet's timestamp.
-            if ts < sync.ts {
-                // Attempted to seek backwards on an unseekable stream.
-                if !self.reader.is_seekable() {
-                    return seek_error(SeekErrorKind::ForwardOnly);
-                }
-                // Overshot a regular seek, or the stream is corrupted, not necessarily an error
-                // per-say.
-                else {
-                    break sync;
-                }
-            }
-            // The desired timestamp is contained within the current packet.
-            else if ts >= sync.ts && ts < sync.ts + sync.dur {
-                break sync;
-            }
+        // If the reader supports seeking, bisect the stream to find the packet containing the
+        // desired timestamp.
+        let packet_ts =
+            if self.reader.is_seekable() { self.seek_bisect(ts)? } else { self.seek_forward(ts)? };
 
-            // Advance the reader such that the next iteration will sync to a different frame.
-            self.reader.read_byte()?;
-        };
+        // The reader was moved, therefore the parser must be reset.
+        self.parser.soft_reset();
 
-        debug!("seeked to packet_ts={} (delta={})", packet.ts, packet.ts as i64 - ts as i64);
+        debug!("seeked to packet_ts={} (delta={})", packet_ts, packet_ts as i64 - ts as i64);
 
-        Ok(SeekedTo { track_id: 0, actual_ts: packet.ts, required_ts: ts })
+        Ok(SeekedTo { track_id: 0, actual_ts: packet_ts, required_ts: ts })
     }
 
     fn into_inner(self: Box<Self>) -> MediaSourceStream {
@@ -373,3 +480,111 @@ fn read_stream_info_block<B: ReadBytes + FiniteStream>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{AudioBufferRef, Signal};
+    use symphonia_core::checksum::{Crc16Ansi, Crc8Ccitt};
+    use symphonia_core::codecs::{Decoder, DecoderOptions};
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, Monitor};
+
+    use super::FlacReader;
+    use crate::FlacDecoder;
+
+    const BLOCK_LEN: u64 = 1024;
+    const N_FRAMES: u64 = 200;
+
+    /// Gets the sample at a timestamp. The samples are pseudo-random so that the audio data
+    /// contains false frame headers.
+    fn sample(ts: u64) -> i16 {
+        let x = ts.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (x >> 48) as i16
+    }
+
+    /// Builds a 16-bit mono stream of fixed block size verbatim frames with no seek table.
+    fn make_stream() -> Vec<u8> {
+        let mut buf = b"fLaC".to_vec();
+
+        // The stream info block is the last metadata block. The frame sizes are unknown.
+        buf.extend_from_slice(&[0x80, 0x00, 0x00, 0x22]);
+        buf.extend_from_slice(&(BLOCK_LEN as u16).to_be_bytes());
+        buf.extend_from_slice(&(BLOCK_LEN as u16).to_be_bytes());
+        buf.extend_from_slice(&[0; 6]);
+
+        // Sample rate of 44.1 kHz, 1 channel, 16 bits per sample, and the total number of frames.
+        let n_samples = BLOCK_LEN * N_FRAMES;
+        let info = (44_100u64 << 44) | (15 << 36) | n_samples;
+        buf.extend_from_slice(&info.to_be_bytes());
+        buf.extend_from_slice(&[0; 16]);
+
+        for n in 0..N_FRAMES {
+            let start = buf.len();
+
+            // Fixed block size, 1024 samples, 44.1 kHz, mono, 16 bits per sample.
+            buf.extend_from_slice(&[0xff, 0xf8, 0xa9, 0x08]);
+
+            // The UTF-8 coded frame number.
+            if n < 0x80 {
+                buf.push(n as u8);
+            }
+            else {
+                buf.extend_from_slice(&[0xc0 | (n >> 6) as u8, 0x80 | (n & 0x3f) as u8]);
+            }
+
+            let mut crc8 = Crc8Ccitt::new(0);
+            crc8.process_buf_bytes(&buf[start..]);
+            buf.push(crc8.crc());
+
+            // A verbatim subframe.
+            buf.push(0x02);
+
+            for ts in n * BLOCK_LEN..(n + 1) * BLOCK_LEN {
+                buf.extend_from_slice(&sample(ts).to_be_bytes());
+            }
+
+            let mut crc16 = Crc16Ansi::new(0);
+            crc16.process_buf_bytes(&buf[start..]);
+            buf.extend_from_slice(&crc16.crc().to_be_bytes());
+        }
+
+        buf
+    }
+
+    #[test]
+    fn verify_seek_without_seek_table() {
+        let source =
+            MediaSourceStream::new(Box::new(Cursor::new(make_stream())), Default::default());
+
+        let mut reader = FlacReader::try_new(source, &FormatOptions::default()).unwrap();
+
+        let mut decoder =
+            FlacDecoder::try_new(&reader.tracks()[0].codec_params, &DecoderOptions::default())
+                .unwrap();
+
+        let n_samples = BLOCK_LEN * N_FRAMES;
+
+        for &required_ts in &[0, 1, 1023, 1024, 50_000, 150_001, 100, n_samples - 1, 3000, 0] {
+            let seeked = reader
+                .seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: required_ts, track_id: 0 })
+                .unwrap();
+
+            assert!(seeked.actual_ts <= required_ts);
+            assert!(required_ts < seeked.actual_ts + BLOCK_LEN);
+
+            // The next packet must be the frame that was seeked to.
+            let packet = reader.next_packet().unwrap();
+
+            assert_eq!(packet.ts(), seeked.actual_ts);
+
+            match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::S32(buf) => {
+                    assert_eq!(buf.chan(0)[0] >> 16, i32::from(sample(packet.ts())));
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
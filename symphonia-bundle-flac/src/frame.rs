@@ -186,6 +186,7 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<Fram
         0x4 => Some(16),
         0x5 => Some(20),
         0x6 => Some(24),
+        0x7 => Some(32),
         _ => {
             return decode_error("flac: bits per sample set to reserved value");
         }
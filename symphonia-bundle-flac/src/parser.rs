@@ -297,7 +297,7 @@ pub struct PacketParser {
 
 impl PacketParser {
     /// Perform a soft reset of the parser. Call this after a discontinuity in the stream.
-    fn soft_reset(&mut self) {
+    pub fn soft_reset(&mut self) {
         self.builder.reset();
         self.fsma.reset();
     }
@@ -421,9 +421,16 @@ impl PacketParser {
                 return Ok(fragment);
             }
 
-            // If a fragment could not be read, synchronization was lost. Try to resync.
+            // If a fragment could not be read, synchronization was lost. Try to resync. Since the
+            // stream is being read sequentially, the frame resynchronized to must continue the
+            // sequence of the last valid frame. This prevents a false frame header within the
+            // corrupted region from being synchronized to.
             warn!("synchronization lost");
-            let _ = self.resync(reader)?;
+            let frame_pos = sync_to_frame(reader, &self.info, self.builder.last_header())?.0;
+
+            reader.seek_buffered(frame_pos);
+
+            self.soft_reset();
         }
     }
 
@@ -459,24 +466,8 @@ impl PacketParser {
     {
         let init_pos = reader.pos();
 
-        let mut frame_pos;
-
-        let header = loop {
-            let sync = sync_frame(reader)?;
-
-            frame_pos = reader.pos() - 2;
-
-            if let Ok(header) = read_frame_header(reader, sync) {
-                // Do a strict frame header check with no previous header.
-                if strict_frame_header_check(&self.info, &header, None) {
-                    break header;
-                }
-            }
-
-            // If the header check failed, then seek to one byte past the start of the false frame
-            // and continue trying to resynchronize.
-            reader.seek_buffered(frame_pos + 1);
-        };
+        // Do a strict frame header check with no previous header.
+        let (frame_pos, header) = sync_to_frame(reader, &self.info, None)?;
 
         let sync = calc_sync_info(&self.info, &header);
 
@@ -491,6 +482,11 @@ impl PacketParser {
         Ok(sync)
     }
 
+    /// Get the maximum frame size in bytes, if known.
+    pub fn max_frame_size(&self) -> Option<usize> {
+        self.builder.max_size
+    }
+
     /// Reset the packet parser for a new stream.
     pub fn reset(&mut self, info: StreamInfo) {
         let max_frame_size =
@@ -502,6 +498,34 @@ impl PacketParser {
     }
 }
 
+/// Synchronize the reader to the next frame header that passes a strict frame header check, and
+/// return the position of the frame and its header. The reader is left after the frame header.
+fn sync_to_frame<B>(
+    reader: &mut B,
+    stream_info: &StreamInfo,
+    last_header: Option<&FrameHeader>,
+) -> Result<(u64, FrameHeader)>
+where
+    B: ReadBytes + SeekBuffered,
+{
+    loop {
+        let sync = sync_frame(reader)?;
+
+        let frame_pos = reader.pos() - 2;
+
+        // Reading the frame header validates the header CRC-8.
+        if let Ok(header) = read_frame_header(reader, sync) {
+            if strict_frame_header_check(stream_info, &header, last_header) {
+                return Ok((frame_pos, header));
+            }
+        }
+
+        // If the header check failed, then seek to one byte past the start of the false frame
+        // and continue trying to resynchronize.
+        reader.seek_buffered(frame_pos + 1);
+    }
+}
+
 fn calc_sync_info(stream_info: &StreamInfo, header: &FrameHeader) -> SyncInfo {
     let is_fixed = stream_info.block_len_max == stream_info.block_len_min;
 
@@ -570,6 +594,14 @@ fn strict_frame_header_check(
         return false;
     }
 
+    // If the total number of samples in the stream is known, the block must start within the
+    // stream.
+    if let Some(n_samples) = stream_info.n_samples {
+        if calc_sync_info(stream_info, header).ts >= n_samples {
+            return false;
+        }
+    }
+
     // Channel assignments.
     let num_frame_channels = match header.channel_assignment {
         ChannelAssignment::Independant(num) => num,
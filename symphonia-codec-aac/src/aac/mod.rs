@@ -412,6 +412,8 @@ impl AacDecoder {
             _ => return unsupported_error("aac: object type"),
         }
 
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
         Ok(())
     }
 }
@@ -1,7 +1,7 @@
 use symphonia_codec_aac::{AacDecoder, AdtsReader};
 use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_AAC};
 use symphonia_core::errors;
-use symphonia_core::formats::{FormatOptions, FormatReader};
+use symphonia_core::formats::{FormatOptions, FormatReader, Packet};
 use symphonia_core::io::MediaSourceStream;
 
 fn test_decode(data: Vec<u8>) -> symphonia_core::errors::Result<()> {
@@ -33,3 +33,21 @@ fn invalid_channels_aac() {
 
     assert!(matches!(err, errors::Error::Unsupported(_)));
 }
+
+#[test]
+fn trimmed_packet_aac() {
+    // AAC-LC, 44.1 kHz, mono.
+    let mut params = CodecParameters::new();
+    params.for_codec(CODEC_TYPE_AAC).with_extra_data(Box::new([0x12, 0x08]));
+
+    let mut decoder = AacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+    // A silent single channel element with no scale factor bands, followed by an end element.
+    let frame = [0x00, 0x00, 0x00, 0x07];
+
+    let packet = Packet::new_from_slice(0, 0, 1024, &frame);
+    assert_eq!(decoder.decode(&packet).unwrap().frames(), 1024);
+
+    let packet = Packet::new_trimmed_from_slice(0, 1024, 1024, 100, 24, &frame);
+    assert_eq!(decoder.decode(&packet).unwrap().frames(), 900);
+}
@@ -0,0 +1,276 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The parametric bit allocation routine.
+//!
+//! The number of bits used to code each mantissa is not transmitted. Instead, the decoder derives
+//! it from the exponents and a handful of bit allocation parameters using a psychoacoustic model
+//! shared with the encoder.
+
+use symphonia_core::errors::{decode_error, Result};
+
+use crate::tables::{BAND_START, BAP, HEARING_THRESHOLD, LOG_ADD, N_BANDS};
+
+/// Parameters of the bit allocation routine shared by all channels.
+#[derive(Default)]
+pub struct BitAllocParams {
+    pub sr_code: usize,
+    pub sr_shift: u32,
+    pub slow_decay: i32,
+    pub fast_decay: i32,
+    pub slow_gain: i32,
+    pub db_per_bit: i32,
+    pub floor: i32,
+    pub cpl_fast_leak: i32,
+    pub cpl_slow_leak: i32,
+}
+
+/// A delta bit allocation segment.
+#[derive(Copy, Clone, Default)]
+pub struct DeltaSegment {
+    /// The offset in bands from the end of the previous segment.
+    pub offset: u8,
+    /// The length of the segment in bands.
+    pub len: u8,
+    /// The delta bit allocation code.
+    pub value: u8,
+}
+
+/// Delta bit allocation information for a channel.
+#[derive(Copy, Clone, Default)]
+pub struct DeltaBitAlloc {
+    pub n_segments: usize,
+    pub segments: [DeltaSegment; 8],
+}
+
+/// Gets the band containing a transform coefficient.
+fn bin_to_band(bin: usize) -> usize {
+    BAND_START[..N_BANDS].iter().rposition(|&start| usize::from(start) <= bin).unwrap_or(0)
+}
+
+/// Maps the exponents of the transform coefficients in `start..end` to a power spectral density
+/// (PSD), and integrates the PSD over each band.
+pub fn calc_psd(
+    exps: &[u8; 256],
+    start: usize,
+    end: usize,
+    psd: &mut [i16; 256],
+    band_psd: &mut [i16; N_BANDS],
+) {
+    for (psd, &exp) in psd[start..end].iter_mut().zip(&exps[start..end]) {
+        *psd = 3072 - (i16::from(exp) << 7);
+    }
+
+    let mut bin = start;
+    let mut band = bin_to_band(start);
+
+    loop {
+        let band_end = usize::from(BAND_START[band + 1]).min(end);
+
+        let mut acc = i32::from(psd[bin]);
+        bin += 1;
+
+        while bin < band_end {
+            let p = i32::from(psd[bin]);
+            let max = acc.max(p);
+            let adr = (max - ((acc + p + 1) >> 1)).min(255) as usize;
+
+            acc = max + i32::from(LOG_ADD[adr]);
+            bin += 1;
+        }
+
+        band_psd[band] = acc as i16;
+        band += 1;
+
+        if end <= usize::from(BAND_START[band]) {
+            break;
+        }
+    }
+}
+
+fn calc_lowcomp1(lowcomp: i32, b0: i32, b1: i32, c: i32) -> i32 {
+    if b0 + 256 == b1 {
+        c
+    }
+    else if b0 > b1 {
+        (lowcomp - 64).max(0)
+    }
+    else {
+        lowcomp
+    }
+}
+
+fn calc_lowcomp(lowcomp: i32, b0: i32, b1: i32, band: usize) -> i32 {
+    if band < 7 {
+        calc_lowcomp1(lowcomp, b0, b1, 384)
+    }
+    else if band < 20 {
+        calc_lowcomp1(lowcomp, b0, b1, 320)
+    }
+    else {
+        (lowcomp - 128).max(0)
+    }
+}
+
+/// Computes the masking curve from the integrated PSD of the bands covering the transform
+/// coefficients in `start..end`, and applies delta bit allocation.
+#[allow(clippy::too_many_arguments, clippy::needless_range_loop)]
+pub fn calc_mask(
+    params: &BitAllocParams,
+    band_psd: &[i16; N_BANDS],
+    start: usize,
+    end: usize,
+    fast_gain: i32,
+    is_lfe: bool,
+    dba: Option<&DeltaBitAlloc>,
+    mask: &mut [i16; N_BANDS],
+) -> Result<()> {
+    let psd = |band: usize| i32::from(band_psd[band]);
+
+    let mut excite = [0i32; N_BANDS];
+
+    let band_start = bin_to_band(start);
+    let band_end = bin_to_band(end - 1) + 1;
+
+    let mut fast_leak;
+    let mut slow_leak;
+
+    let begin = if band_start == 0 {
+        // Full-bandwidth and LFE channels apply a low frequency compensation to the first bands.
+        let mut lowcomp = 0;
+
+        lowcomp = calc_lowcomp1(lowcomp, psd(0), psd(1), 384);
+        excite[0] = psd(0) - fast_gain - lowcomp;
+
+        lowcomp = calc_lowcomp1(lowcomp, psd(1), psd(2), 384);
+        excite[1] = psd(1) - fast_gain - lowcomp;
+
+        let mut begin = 7;
+
+        fast_leak = 0;
+        slow_leak = 0;
+
+        for band in 2..7 {
+            if !(is_lfe && band == 6) {
+                lowcomp = calc_lowcomp1(lowcomp, psd(band), psd(band + 1), 384);
+            }
+
+            fast_leak = psd(band) - fast_gain;
+            slow_leak = psd(band) - params.slow_gain;
+            excite[band] = fast_leak - lowcomp;
+
+            if !(is_lfe && band == 6) && psd(band) <= psd(band + 1) {
+                begin = band + 1;
+                break;
+            }
+        }
+
+        for band in begin..band_end.min(22) {
+            if !(is_lfe && band == 6) {
+                lowcomp = calc_lowcomp(lowcomp, psd(band), psd(band + 1), band);
+            }
+
+            fast_leak = (fast_leak - params.fast_decay).max(psd(band) - fast_gain);
+            slow_leak = (slow_leak - params.slow_decay).max(psd(band) - params.slow_gain);
+            excite[band] = (fast_leak - lowcomp).max(slow_leak);
+        }
+
+        22
+    }
+    else {
+        // The coupling channel starts with the leak values transmitted in the bitstream.
+        fast_leak = (params.cpl_fast_leak << 8) + 768;
+        slow_leak = (params.cpl_slow_leak << 8) + 768;
+
+        band_start
+    };
+
+    for band in begin..band_end {
+        fast_leak = (fast_leak - params.fast_decay).max(psd(band) - fast_gain);
+        slow_leak = (slow_leak - params.slow_decay).max(psd(band) - params.slow_gain);
+        excite[band] = fast_leak.max(slow_leak);
+    }
+
+    for band in band_start..band_end {
+        let tmp = params.db_per_bit - psd(band);
+
+        if tmp > 0 {
+            excite[band] += tmp >> 2;
+        }
+
+        let threshold = HEARING_THRESHOLD[band >> params.sr_shift][params.sr_code];
+
+        mask[band] = excite[band].max(i32::from(threshold)) as i16;
+    }
+
+    if let Some(dba) = dba {
+        let mut band = band_start;
+
+        for seg in &dba.segments[..dba.n_segments] {
+            band += usize::from(seg.offset);
+
+            let len = usize::from(seg.len);
+
+            if band + len > N_BANDS {
+                return decode_error("ac3: delta bit allocation segment out-of-bounds");
+            }
+
+            let delta = if seg.value >= 4 {
+                (i16::from(seg.value) - 3) << 7
+            }
+            else {
+                (i16::from(seg.value) - 4) << 7
+            };
+
+            for mask in mask[band..band + len].iter_mut() {
+                *mask += delta;
+            }
+
+            band += len;
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the bit allocation pointers of the transform coefficients in `start..end`.
+pub fn calc_bap(
+    mask: &[i16; N_BANDS],
+    psd: &[i16; 256],
+    start: usize,
+    end: usize,
+    snr_offset: i32,
+    floor: i32,
+    bap: &mut [u8; 256],
+) {
+    // An SNR offset of -960 indicates that no mantissas are coded.
+    if snr_offset == -960 {
+        bap[start..end].iter_mut().for_each(|bap| *bap = 0);
+        return;
+    }
+
+    let mut bin = start;
+    let mut band = bin_to_band(start);
+
+    loop {
+        let m = ((i32::from(mask[band]) - snr_offset - floor).max(0) & 0x1fe0) + floor;
+
+        band += 1;
+
+        let band_end = usize::from(BAND_START[band]).min(end);
+
+        while bin < band_end {
+            let address = ((i32::from(psd[bin]) - m) >> 5).clamp(0, 63) as usize;
+            bap[bin] = BAP[address];
+            bin += 1;
+        }
+
+        if end <= band_end {
+            break;
+        }
+    }
+}
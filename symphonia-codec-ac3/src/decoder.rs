@@ -0,0 +1,761 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::SignalSpec;
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal};
+use symphonia_core::checksum::Crc16Ansi;
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_AC3, CODEC_TYPE_EAC3};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::Monitor;
+use symphonia_core::support_codec;
+
+use log::warn;
+
+use crate::frame::Substream;
+use crate::header::{FrameHeader, StreamType, BLOCK_LEN, FRAME_HEADER_LEN, MAX_BLOCKS};
+use crate::layout::{layout_channels, output_layout};
+use crate::transform::Transform;
+
+/// The maximum number of substreams decoded: the first independent substream, and its 8
+/// dependent substreams.
+const MAX_SUBSTREAMS: usize = 9;
+
+/// The maximum number of frames (samples per channel) in a packet.
+const MAX_FRAMES: usize = MAX_BLOCKS * BLOCK_LEN;
+
+/// Dynamic range compression modes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DynamicRange {
+    /// Do not apply dynamic range compression.
+    Off,
+    /// Apply the dynamic range gain words ("line" mode). The `cut` and `boost` scale factors, in
+    /// the range [0, 1], scale the attenuation and amplification of loud and quiet passages,
+    /// respectively.
+    Line { cut: f32, boost: f32 },
+    /// Apply the heavy compression gain words if present, otherwise the dynamic range gain words
+    /// ("RF" mode).
+    Rf,
+}
+
+impl Default for DynamicRange {
+    fn default() -> Self {
+        DynamicRange::Line { cut: 1.0, boost: 1.0 }
+    }
+}
+
+/// AC-3 and E-AC-3 specific decoder options.
+///
+/// A decoder instantiated by a codec registry, or by `Decoder::try_new`, uses the default options.
+/// To use other options, instantiate the decoder with `Ac3Decoder::try_new_with_options`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Ac3DecoderOptions {
+    /// The dynamic range compression mode. Defaults to line mode with full cut and boost.
+    pub dynamic_range: DynamicRange,
+    /// If `true`, streams with more than 2 channels are downmixed to stereo. Defaults to `false`.
+    pub downmix: bool,
+}
+
+/// Dolby Digital (AC-3) and Dolby Digital Plus (E-AC-3) decoder.
+///
+/// Packets must contain one or more complete frames starting with a frame of the first independent
+/// substream. Dependent substreams are combined with the independent substream to produce up-to
+/// 7.1 channels. Other independent substreams (additional programs) are ignored.
+///
+/// Dynamic range compression and downmixing are controlled by `Ac3DecoderOptions`.
+pub struct Ac3Decoder {
+    params: CodecParameters,
+    options: Ac3DecoderOptions,
+    is_verifying: bool,
+    n_crc_errors: u64,
+    substreams: Vec<Substream>,
+    transform: Transform,
+    buf: AudioBuffer<f32>,
+}
+
+impl Ac3Decoder {
+    /// Instantiates the decoder with AC-3 specific options.
+    pub fn try_new_with_options(
+        params: &CodecParameters,
+        options: &DecoderOptions,
+        ac3_options: Ac3DecoderOptions,
+    ) -> Result<Self> {
+        // This decoder only supports AC-3 and E-AC-3.
+        if params.codec != CODEC_TYPE_AC3 && params.codec != CODEC_TYPE_EAC3 {
+            return unsupported_error("ac3: invalid codec type");
+        }
+
+        // The sample rate and channels are signalled in every frame, and the audio buffer is
+        // recreated after the first frame if they differ.
+        let spec = SignalSpec::new(
+            params.sample_rate.unwrap_or(48_000),
+            params.channels.unwrap_or(Channels::FRONT_LEFT | Channels::FRONT_RIGHT),
+        );
+
+        Ok(Ac3Decoder {
+            params: params.clone(),
+            options: ac3_options,
+            is_verifying: options.verify,
+            n_crc_errors: 0,
+            substreams: (0..MAX_SUBSTREAMS).map(|_| Substream::new()).collect(),
+            transform: Transform::new(),
+            buf: AudioBuffer::new(MAX_FRAMES as u64, spec),
+        })
+    }
+
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let mut buf = packet.buf();
+
+        // The headers of the decoded frames of the current independent frame and its dependent
+        // frames, indexed by substream.
+        let mut group: Vec<Option<FrameHeader>> = vec![None; MAX_SUBSTREAMS];
+        let mut skip = false;
+
+        self.buf.clear();
+
+        while buf.len() >= FRAME_HEADER_LEN {
+            let header = FrameHeader::parse(buf)?;
+
+            if header.frame_len > buf.len() {
+                return decode_error("ac3: frame is truncated");
+            }
+
+            let (frame, rest) = buf.split_at(header.frame_len);
+            buf = rest;
+
+            if header.starts_packet() {
+                // Output the previous independent frame, and its dependent frames, before starting
+                // a new one.
+                if group[0].is_some() {
+                    self.output(&group)?;
+                }
+
+                group.iter_mut().for_each(|header| *header = None);
+                skip = false;
+            }
+            else if group[0].is_none() {
+                return decode_error("ac3: packet does not start with an independent frame");
+            }
+            else if header.stream_type != StreamType::Dependent {
+                // An independent substream other than the first is an additional program. It, and
+                // its dependent substreams, are ignored.
+                skip = true;
+            }
+
+            if skip {
+                continue;
+            }
+
+            if self.is_verifying {
+                let mut crc16 = Crc16Ansi::new(0);
+                crc16.process_buf_bytes(&frame[2..]);
+
+                if crc16.crc() != 0 {
+                    warn!("ac3: frame crc mismatch at ts={}", packet.ts());
+                    self.n_crc_errors += 1;
+                }
+            }
+
+            let index = match &group[0] {
+                Some(first) if header.stream_type == StreamType::Dependent => {
+                    if header.n_blocks != first.n_blocks || header.sample_rate != first.sample_rate
+                    {
+                        return decode_error("ac3: dependent substream does not match");
+                    }
+
+                    1 + usize::from(header.substream_id)
+                }
+                _ => 0,
+            };
+
+            self.substreams[index].decode(
+                &header,
+                frame,
+                self.options.dynamic_range,
+                &mut self.transform,
+            )?;
+
+            group[index] = Some(header);
+        }
+
+        if group[0].is_none() {
+            return decode_error("ac3: packet does not contain a frame");
+        }
+
+        self.output(&group)?;
+
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+        Ok(())
+    }
+
+    /// Appends the samples of the decoded substreams to the audio buffer.
+    fn output(&mut self, group: &[Option<FrameHeader>]) -> Result<()> {
+        let first = group[0].as_ref().unwrap();
+
+        let layout = output_layout(
+            group.iter().enumerate().filter_map(|(i, header)| header.as_ref().map(|h| (i, h))),
+        );
+
+        let downmix = self.options.downmix && layout.len() > 2;
+
+        let channels = if downmix {
+            Channels::FRONT_LEFT | Channels::FRONT_RIGHT
+        }
+        else {
+            layout_channels(&layout)
+        };
+
+        let spec = SignalSpec::new(first.sample_rate, channels);
+
+        if *self.buf.spec() != spec {
+            if self.buf.frames() > 0 {
+                return decode_error("ac3: channel layout changed within a packet");
+            }
+
+            self.buf = AudioBuffer::new(MAX_FRAMES as u64, spec);
+        }
+
+        let n_samples = first.n_samples();
+        let offset = self.buf.frames();
+
+        if offset + n_samples > self.buf.capacity() {
+            return decode_error("ac3: packet contains too many samples");
+        }
+
+        self.buf.render_reserved(Some(n_samples));
+
+        let mut planes = self.buf.planes_mut();
+        let planes = planes.planes();
+
+        if downmix {
+            let clev = self.substreams[0].centre_mix_level;
+            let slev = self.substreams[0].surround_mix_level;
+
+            let gains: Vec<(f32, f32)> =
+                layout.iter().map(|out| out.location.downmix_gains(clev, slev)).collect();
+
+            // Normalize the gains to prevent clipping.
+            let sum_l: f32 = gains.iter().map(|g| g.0).sum();
+            let sum_r: f32 = gains.iter().map(|g| g.1).sum();
+            let norm = 1.0 / sum_l.max(sum_r).max(1.0);
+
+            let (left, right) = planes.split_at_mut(1);
+            let left = &mut left[0][offset..offset + n_samples];
+            let right = &mut right[0][offset..offset + n_samples];
+
+            left.iter_mut().for_each(|s| *s = 0.0);
+            right.iter_mut().for_each(|s| *s = 0.0);
+
+            for (out, &(gain_l, gain_r)) in layout.iter().zip(&gains) {
+                let samples = &self.substreams[out.substream].samples[out.index][..n_samples];
+
+                for ((l, r), &s) in left.iter_mut().zip(right.iter_mut()).zip(samples) {
+                    *l += norm * gain_l * s;
+                    *r += norm * gain_r * s;
+                }
+            }
+        }
+        else {
+            for (plane, out) in planes.iter_mut().zip(&layout) {
+                plane[offset..offset + n_samples].copy_from_slice(
+                    &self.substreams[out.substream].samples[out.index][..n_samples],
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for Ac3Decoder {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+        Ac3Decoder::try_new_with_options(params, options, Default::default())
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[
+            support_codec!(CODEC_TYPE_AC3, "ac3", "Dolby Digital (AC-3)"),
+            support_codec!(CODEC_TYPE_EAC3, "eac3", "Dolby Digital Plus (E-AC-3)"),
+        ]
+    }
+
+    fn reset(&mut self) {
+        for substream in self.substreams.iter_mut() {
+            substream.reset();
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        let mut result: FinalizeResult = Default::default();
+
+        // If verifying, every decoded frame must have matched its CRC.
+        if self.is_verifying {
+            if self.n_crc_errors > 0 {
+                warn!("verification: {} frame(s) failed the crc check", self.n_crc_errors);
+            }
+
+            result.verify_ok = Some(self.n_crc_errors == 0);
+        }
+
+        result
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::checksum::Crc16Ansi;
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions};
+    use symphonia_core::codecs::{CODEC_TYPE_AC3, CODEC_TYPE_EAC3};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::Monitor;
+
+    use super::{Ac3Decoder, Ac3DecoderOptions, DynamicRange};
+
+    /// The length of the synthesized frames in bytes.
+    const FRAME_LEN: usize = 1536;
+
+    #[derive(Default)]
+    struct BitWriter {
+        buf: Vec<u8>,
+        n_bits: usize,
+    }
+
+    impl BitWriter {
+        fn put(&mut self, value: u32, n: u32) {
+            for i in (0..n).rev() {
+                if self.n_bits % 8 == 0 {
+                    self.buf.push(0);
+                }
+
+                let bit = ((value >> i) & 0x1) as u8;
+                *self.buf.last_mut().unwrap() |= bit << (7 - self.n_bits % 8);
+                self.n_bits += 1;
+            }
+        }
+
+        /// Pads the frame with zeros, and appends the CRC.
+        fn finish(mut self) -> Vec<u8> {
+            assert!(self.buf.len() <= FRAME_LEN - 2);
+
+            self.buf.resize(FRAME_LEN - 2, 0);
+
+            let mut crc16 = Crc16Ansi::new(0);
+            crc16.process_buf_bytes(&self.buf[2..]);
+
+            self.buf.extend_from_slice(&crc16.crc().to_be_bytes());
+            self.buf
+        }
+    }
+
+    /// Writes the bandwidth codes, and D15 exponents, of each channel of the first block.
+    ///
+    /// Channels with dither carry dither noise. The exponents of all other channels are large
+    /// enough that no bits are allocated, and without dither, they are silent.
+    fn put_exponents(bs: &mut BitWriter, dither: &[bool], lfe: bool) {
+        for _ in dither {
+            bs.put(0, 6);
+        }
+
+        for &dither in dither {
+            if dither {
+                bs.put(2, 4);
+                (0..24).for_each(|_| bs.put(62, 7));
+            }
+            else {
+                bs.put(15, 4);
+                bs.put(124, 7);
+                bs.put(117, 7);
+                (0..22).for_each(|_| bs.put(62, 7));
+            }
+
+            // Gain range.
+            bs.put(0, 2);
+        }
+
+        if lfe {
+            bs.put(15, 4);
+            bs.put(124, 7);
+            bs.put(117, 7);
+        }
+    }
+
+    /// Writes the bit allocation parameters with the lowest SNR offset, and no bits are allocated
+    /// to dither noise.
+    fn put_bit_alloc(bs: &mut BitWriter) {
+        bs.put(1, 1);
+        bs.put(2, 2);
+        bs.put(1, 2);
+        bs.put(1, 2);
+        bs.put(2, 2);
+        bs.put(0, 3);
+    }
+
+    /// Synthesizes a 48 kHz AC-3 frame.
+    fn ac3_frame(acmod: u8, lfe: bool, dither: &[bool], dynrng: Option<u8>) -> Vec<u8> {
+        let mut bs = BitWriter::default();
+
+        // Sync word, CRC, sample rate code, frame size code (384 kbps), bsid, and bsmod.
+        bs.put(0x0b77, 16);
+        bs.put(0, 16);
+        bs.put(0, 2);
+        bs.put(28, 6);
+        bs.put(8, 5);
+        bs.put(0, 3);
+
+        bs.put(u32::from(acmod), 3);
+
+        if acmod & 0x1 != 0 && acmod != 0x1 {
+            bs.put(0, 2);
+        }
+        if acmod & 0x4 != 0 {
+            bs.put(0, 2);
+        }
+        if acmod == 0x2 {
+            bs.put(0, 2);
+        }
+
+        bs.put(u32::from(lfe), 1);
+
+        // Dialogue normalization, and no compression gain, language code, or mixing level.
+        bs.put(27, 5);
+        bs.put(0, 3);
+
+        // Copyright, original bitstream, time codes, and additional bitstream information.
+        bs.put(0, 5);
+
+        for blk in 0..6 {
+            // Block switch, and dither flags.
+            dither.iter().for_each(|_| bs.put(0, 1));
+            dither.iter().for_each(|&dither| bs.put(u32::from(dither), 1));
+
+            match dynrng {
+                Some(dynrng) => {
+                    bs.put(1, 1);
+                    bs.put(u32::from(dynrng), 8);
+                }
+                _ => bs.put(0, 1),
+            }
+
+            if blk == 0 {
+                // New coupling strategy without coupling.
+                bs.put(0b10, 2);
+
+                // No rematrixing.
+                if acmod == 0x2 {
+                    bs.put(0, 1);
+                }
+
+                // D15 exponent strategies.
+                dither.iter().for_each(|_| bs.put(1, 2));
+
+                if lfe {
+                    bs.put(1, 1);
+                }
+
+                put_exponents(&mut bs, dither, lfe);
+                put_bit_alloc(&mut bs);
+
+                // SNR offsets and fast gain codes.
+                bs.put(1, 1);
+                bs.put(0, 6);
+
+                for _ in 0..dither.len() + usize::from(lfe) {
+                    bs.put(0, 4 + 3);
+                }
+            }
+            else {
+                // Reuse the coupling strategy, rematrixing flags, exponents, bit allocation
+                // parameters, and SNR offsets.
+                bs.put(0, 1);
+
+                if acmod == 0x2 {
+                    bs.put(0, 1);
+                }
+                dither.iter().for_each(|_| bs.put(0, 2));
+
+                if lfe {
+                    bs.put(0, 1);
+                }
+
+                bs.put(0, 2);
+            }
+
+            // No delta bit allocation, or skip field.
+            bs.put(0, 2);
+        }
+
+        bs.finish()
+    }
+
+    /// Synthesizes a 48 kHz E-AC-3 frame with 6 blocks. The exponent strategies are either sent
+    /// for each block, or as a frame exponent strategy combination.
+    fn eac3_frame(
+        acmod: u8,
+        lfe: bool,
+        chanmap: Option<u16>,
+        dither: &[bool],
+        frame_exp_strategies: bool,
+    ) -> Vec<u8> {
+        let is_dependent = chanmap.is_some();
+
+        let mut bs = BitWriter::default();
+
+        // Sync word, stream type, substream identifier, frame size, sample rate code, and number
+        // of blocks code.
+        bs.put(0x0b77, 16);
+        bs.put(u32::from(is_dependent), 2);
+        bs.put(0, 3);
+        bs.put((FRAME_LEN / 2 - 1) as u32, 11);
+        bs.put(0, 2);
+        bs.put(3, 2);
+
+        bs.put(u32::from(acmod), 3);
+        bs.put(u32::from(lfe), 1);
+        bs.put(16, 5);
+
+        // Dialogue normalization, and no compression gain.
+        bs.put(27, 5);
+        bs.put(0, 1);
+
+        if let Some(chanmap) = chanmap {
+            bs.put(1, 1);
+            bs.put(u32::from(chanmap), 16);
+        }
+
+        // No mixing metadata, informational metadata, or additional bitstream information.
+        bs.put(0, 3);
+
+        // Exponent strategy syntax, no adaptive hybrid transform, SNR offset strategy 0, no
+        // transient pre-noise processing or block switch flags, dither flags, bit allocation
+        // parameters, and no fast gain codes, delta bit allocation, skip fields, or spectral
+        // extension attenuation.
+        bs.put(u32::from(!frame_exp_strategies), 1);
+        bs.put(0b0011_0000, 11);
+
+        // No coupling in any block.
+        if acmod > 0x1 {
+            bs.put(0, 6);
+        }
+
+        // D15 exponents in the first block, reused thereafter.
+        if frame_exp_strategies {
+            dither.iter().for_each(|_| bs.put(0, 5));
+        }
+        else {
+            for blk in 0..6 {
+                dither.iter().for_each(|_| bs.put(if blk == 0 { 1 } else { 0 }, 2));
+            }
+        }
+
+        if lfe {
+            bs.put(0b100000, 6);
+        }
+
+        // Converter exponent strategies.
+        if !is_dependent {
+            dither.iter().for_each(|_| bs.put(0, 5));
+        }
+
+        // SNR offsets, and no block start information.
+        bs.put(0, 6 + 4);
+        bs.put(0, 1);
+
+        for blk in 0..6 {
+            dither.iter().for_each(|&dither| bs.put(u32::from(dither), 1));
+
+            // No dynamic range gain word, or spectral extension.
+            bs.put(0, 2);
+
+            if acmod == 0x2 {
+                // No rematrixing.
+                bs.put(0, if blk == 0 { 4 } else { 1 });
+            }
+
+            if blk == 0 {
+                put_exponents(&mut bs, dither, lfe);
+                put_bit_alloc(&mut bs);
+            }
+            else {
+                bs.put(0, 1);
+            }
+
+            // Converter SNR offset.
+            if !is_dependent {
+                bs.put(0, 1);
+            }
+        }
+
+        bs.finish()
+    }
+
+    /// Decodes a packet, and returns the channels, the samples of each channel, and if the CRC
+    /// of every frame was verified.
+    fn decode(
+        codec: symphonia_core::codecs::CodecType,
+        packet: &[u8],
+        options: Ac3DecoderOptions,
+    ) -> (Channels, Vec<Vec<f32>>, bool) {
+        let mut params = CodecParameters::new();
+        params.for_codec(codec);
+
+        let mut decoder =
+            Ac3Decoder::try_new_with_options(&params, &DecoderOptions { verify: true }, options)
+                .unwrap();
+
+        let packet = Packet::new_from_slice(0, 0, 0, packet);
+
+        let (channels, planes) = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::F32(buf) => {
+                let channels = buf.spec().channels;
+                (channels, (0..channels.count()).map(|ch| buf.chan(ch).to_vec()).collect())
+            }
+            _ => unreachable!(),
+        };
+
+        (channels, planes, decoder.finalize().verify_ok == Some(true))
+    }
+
+    fn is_silent(samples: &[f32]) -> bool {
+        samples.iter().all(|&s| s == 0.0)
+    }
+
+    #[test]
+    fn verify_ac3_decode() {
+        // A 5.1 frame with noise in the centre channel only.
+        let frame = ac3_frame(7, true, &[false, true, false, false, false], None);
+
+        let (channels, planes, verify_ok) = decode(CODEC_TYPE_AC3, &frame, Default::default());
+
+        let surround_5_1 = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::FRONT_CENTRE
+            | Channels::LFE1
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT;
+
+        assert!(verify_ok);
+        assert_eq!(channels, surround_5_1);
+
+        for (channel, samples) in channels.iter().zip(&planes) {
+            assert_eq!(samples.len(), 1536);
+            assert_eq!(is_silent(samples), channel != Channels::FRONT_CENTRE);
+        }
+    }
+
+    #[test]
+    fn verify_ac3_crc_mismatch() {
+        let mut frame = ac3_frame(1, false, &[true], None);
+        frame[FRAME_LEN - 3] ^= 0x1;
+
+        let (channels, planes, verify_ok) = decode(CODEC_TYPE_AC3, &frame, Default::default());
+
+        assert!(!verify_ok);
+        assert_eq!(channels, Channels::FRONT_LEFT);
+        assert!(!is_silent(&planes[0]));
+    }
+
+    #[test]
+    fn verify_ac3_downmix() {
+        let frame = ac3_frame(7, true, &[false, true, false, false, false], None);
+
+        let options = Ac3DecoderOptions { downmix: true, ..Default::default() };
+
+        let (channels, planes, _) = decode(CODEC_TYPE_AC3, &frame, options);
+
+        // The centre channel is mixed equally into the left and right channels.
+        assert_eq!(channels, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+        assert!(!is_silent(&planes[0]));
+        assert_eq!(planes[0], planes[1]);
+    }
+
+    #[test]
+    fn verify_ac3_dynamic_range() {
+        // A dynamic range gain word of +6 dB.
+        let frame = ac3_frame(1, false, &[true], Some(0x20));
+
+        let decode_drc = |dynamic_range| {
+            let options = Ac3DecoderOptions { dynamic_range, ..Default::default() };
+            decode(CODEC_TYPE_AC3, &frame, options).1.remove(0)
+        };
+
+        let off = decode_drc(DynamicRange::Off);
+        let line = decode_drc(DynamicRange::Line { cut: 1.0, boost: 1.0 });
+        let no_boost = decode_drc(DynamicRange::Line { cut: 1.0, boost: 0.0 });
+
+        assert_eq!(no_boost, off);
+
+        for (&line, &off) in line.iter().zip(&off) {
+            assert!((line - 2.0 * off).abs() <= 1e-6);
+        }
+    }
+
+    #[test]
+    fn verify_eac3_dependent_decode() {
+        // A 5.1 independent frame, and a dependent frame carrying the rear surround channels,
+        // with noise in the rear left channel only.
+        let independent = eac3_frame(7, true, None, &[false; 5], false);
+        let dependent = eac3_frame(2, false, Some(0x0200), &[true, false], false);
+        let packet = [independent, dependent].concat();
+
+        let (channels, planes, verify_ok) = decode(CODEC_TYPE_EAC3, &packet, Default::default());
+
+        // The surround channels of the independent substream become side channels.
+        let surround_7_1 = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::FRONT_CENTRE
+            | Channels::LFE1
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT
+            | Channels::SIDE_LEFT
+            | Channels::SIDE_RIGHT;
+
+        assert!(verify_ok);
+        assert_eq!(channels, surround_7_1);
+
+        for (channel, samples) in channels.iter().zip(&planes) {
+            assert_eq!(samples.len(), 1536);
+            assert_eq!(is_silent(samples), channel != Channels::REAR_LEFT);
+        }
+    }
+
+    #[test]
+    fn verify_eac3_frame_exp_strategies() {
+        // The same stereo frame with exponent strategies sent for each block, and as a frame
+        // exponent strategy combination.
+        let block = eac3_frame(2, false, None, &[true, false], false);
+        let frame = eac3_frame(2, false, None, &[true, false], true);
+
+        let (_, block_planes, _) = decode(CODEC_TYPE_EAC3, &block, Default::default());
+        let (_, frame_planes, verify_ok) = decode(CODEC_TYPE_EAC3, &frame, Default::default());
+
+        assert!(verify_ok);
+        assert!(!is_silent(&frame_planes[0]));
+        assert!(is_silent(&frame_planes[1]));
+        assert_eq!(frame_planes, block_planes);
+    }
+}
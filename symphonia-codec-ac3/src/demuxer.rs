@@ -0,0 +1,411 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_AC3, CODEC_TYPE_EAC3};
+use symphonia_core::errors::{seek_error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, info, warn};
+
+use crate::header::{FrameHeader, StreamType, FRAME_HEADER_LEN, SYNC_WORD};
+use crate::layout::{layout_channels, output_layout};
+
+/// The maximum length of an E-AC-3 frame in bytes.
+const MAX_FRAME_LEN: u64 = 4096;
+
+/// Dolby Digital (AC-3) and Dolby Digital Plus (E-AC-3) elementary stream reader.
+///
+/// `Ac3Reader` implements a demuxer for raw AC-3 and E-AC-3 sync frames. Each packet contains a
+/// frame of the first independent substream, and the frames of all other substreams that follow
+/// it.
+pub struct Ac3Reader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    first_packet_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for Ac3Reader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "ac3",
+            "Dolby Digital (AC-3/E-AC-3)",
+            &["ac3", "eac3", "ec3"],
+            &["audio/ac3", "audio/eac3"],
+            &[&[0x0b, 0x77]]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for Ac3Reader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // Read the first packet to get the stream parameters.
+        let (headers, packet) = read_packet_strict(&mut source)?;
+
+        let first = &headers[0];
+
+        // Only the first independent substream, and its dependent substreams, are decoded.
+        let substreams = headers
+            .iter()
+            .take_while(|header| {
+                header.stream_type == StreamType::Dependent || header.starts_packet()
+            })
+            .map(|header| match header.stream_type {
+                StreamType::Dependent => (1 + usize::from(header.substream_id), header),
+                _ => (0, header),
+            });
+
+        let channels = layout_channels(&output_layout(substreams));
+
+        let codec = if headers.iter().any(|header| header.is_eac3()) {
+            CODEC_TYPE_EAC3
+        }
+        else {
+            CODEC_TYPE_AC3
+        };
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(codec)
+            .with_sample_rate(first.sample_rate)
+            .with_time_base(TimeBase::new(1, first.sample_rate))
+            .with_channels(channels)
+            .with_max_frames_per_packet(first.n_samples() as u64);
+
+        // Rewind back to the start of the first packet so that it may be decoded.
+        source.seek_buffered_rev(packet.len());
+
+        let first_packet_pos = source.pos();
+
+        // The stream has no header, therefore estimate the duration if seekable.
+        if source.is_seekable() {
+            info!("estimating duration from bitrate, may be inaccurate for vbr streams");
+
+            if let Some(n_frames) = estimate_n_frames(&mut source) {
+                params.with_n_frames(n_frames);
+            }
+        }
+
+        Ok(Ac3Reader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            first_packet_pos,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let (headers, packet) = read_packet(&mut self.reader)?;
+
+        let ts = self.next_packet_ts;
+        let dur = headers[0].n_samples() as u64;
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, packet.into_boxed_slice()))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Get the timestamp of the desired audio frame.
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // Use the sample rate to calculate the frame timestamp. If sample rate is not
+                // known, the seek cannot be completed.
+                if let Some(sample_rate) = self.tracks[0].codec_params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        // If the stream is unseekable and the required timestamp in the past, then return an
+        // error, it is not possible to seek to it.
+        let is_seekable = self.reader.is_seekable();
+
+        if !is_seekable && required_ts < self.next_packet_ts {
+            return seek_error(SeekErrorKind::ForwardOnly);
+        }
+
+        debug!("seeking to ts={}", required_ts);
+
+        // In coarse seek mode, the underlying media source stream will be roughly seeked based on
+        // the required timestamp and the total duration of the media. In accurate seek mode, the
+        // stream is only seeked back to the start if the required timestamp is in the past.
+        match mode {
+            SeekMode::Coarse if is_seekable => self.preseek_coarse(required_ts)?,
+            SeekMode::Accurate => self.preseek_accurate(required_ts)?,
+            _ => (),
+        };
+
+        // Parse packets one-by-one until the packet containing the required timestamp is reached.
+        loop {
+            let (headers, packet) = read_packet(&mut self.reader)?;
+
+            let dur = headers[0].n_samples() as u64;
+
+            if self.next_packet_ts + dur > required_ts {
+                // Rewind back to the start of the packet.
+                self.reader.seek_buffered_rev(packet.len());
+                break;
+            }
+
+            self.next_packet_ts += dur;
+        }
+
+        debug!(
+            "seeked to ts={} (delta={})",
+            self.next_packet_ts,
+            self.next_packet_ts as i64 - required_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts: self.next_packet_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+impl Ac3Reader {
+    /// Seeks the media source stream to a byte position roughly where the packet with the required
+    /// timestamp should be located.
+    fn preseek_coarse(&mut self, required_ts: u64) -> Result<()> {
+        // Get the total byte length of the stream. It is not possible to seek without this.
+        let total_byte_len = match self.reader.byte_len() {
+            Some(byte_len) => byte_len,
+            None => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        // Get the total duration of the stream. It is not possible to seek without this.
+        let duration = match self.tracks[0].codec_params.n_frames {
+            Some(n_frames) if n_frames > 0 => n_frames,
+            _ => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        let audio_byte_len = total_byte_len - self.first_packet_pos;
+
+        // Calculate, roughly, where the packet containing the required timestamp is located, then
+        // subtract the maximum frame length to prefer a packet before the required timestamp.
+        let packet_pos =
+            ((u128::from(required_ts) * u128::from(audio_byte_len)) / u128::from(duration)) as u64;
+
+        let seek_pos = packet_pos.saturating_sub(MAX_FRAME_LEN) + self.first_packet_pos;
+
+        self.reader.seek(SeekFrom::Start(seek_pos))?;
+
+        // Resync to the start of the next packet.
+        let (headers, _) = read_packet_strict(&mut self.reader)?;
+
+        // Calculate, roughly, the timestamp of the packet based on the byte position after resync,
+        // and round it to a multiple of the packet duration.
+        let seeked_pos = self.reader.pos();
+
+        let ts = ((u128::from(seeked_pos - self.first_packet_pos) * u128::from(duration))
+            / u128::from(audio_byte_len)) as u64;
+
+        let packet_dur = headers[0].n_samples() as u64;
+
+        self.next_packet_ts = (ts / packet_dur) * packet_dur;
+
+        Ok(())
+    }
+
+    /// Seeks the media source stream back to the start of the first packet if the required
+    /// timestamp is in the past.
+    fn preseek_accurate(&mut self, required_ts: u64) -> Result<()> {
+        if required_ts < self.next_packet_ts {
+            let seeked_pos = self.reader.seek(SeekFrom::Start(self.first_packet_pos))?;
+
+            // Since the elementary stream has no timestamp information, the position seeked
+            // to must be exactly as requested.
+            if seeked_pos != self.first_packet_pos {
+                return seek_error(SeekErrorKind::Unseekable);
+            }
+
+            self.next_packet_ts = 0;
+        }
+
+        Ok(())
+    }
+}
+
+/// Synchronizes to, and reads, the next frame.
+fn read_frame(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u8>)> {
+    loop {
+        let mut sync = 0u16;
+
+        while sync != SYNC_WORD {
+            sync = (sync << 8) | u16::from(reader.read_u8()?);
+        }
+
+        let mut buf = vec![0; FRAME_HEADER_LEN];
+
+        buf[..2].copy_from_slice(&sync.to_be_bytes());
+        reader.read_buf_exact(&mut buf[2..])?;
+
+        match FrameHeader::parse(&buf) {
+            Ok(header) if header.frame_len >= FRAME_HEADER_LEN => {
+                buf.resize(header.frame_len, 0);
+                reader.read_buf_exact(&mut buf[FRAME_HEADER_LEN..])?;
+
+                return Ok((header, buf));
+            }
+            _ => {
+                // Random data can look like a sync word. Rewind to the byte after the sync word to
+                // try again.
+                reader.seek_buffered_rev(FRAME_HEADER_LEN - 2);
+            }
+        }
+    }
+}
+
+/// Reads the next packet. A packet starts with a frame of the first independent substream, and
+/// contains all frames up-to the next frame of the first independent substream.
+fn read_packet(reader: &mut MediaSourceStream) -> Result<(Vec<FrameHeader>, Vec<u8>)> {
+    let (header, mut packet) = loop {
+        let (header, frame) = read_frame(reader)?;
+
+        if header.starts_packet() {
+            break (header, frame);
+        }
+
+        warn!("skipping frame of substream {} outside of a packet", header.substream_id);
+    };
+
+    let mut headers = vec![header];
+
+    loop {
+        let mut buf = [0; FRAME_HEADER_LEN];
+
+        // If the header of the next frame can't be read, then the stream has likely ended.
+        if reader.read_buf_exact(&mut buf).is_err() {
+            break;
+        }
+
+        match FrameHeader::parse(&buf) {
+            Ok(header) if !header.starts_packet() && header.frame_len >= FRAME_HEADER_LEN => {
+                let start = packet.len();
+
+                packet.extend_from_slice(&buf);
+                packet.resize(start + header.frame_len, 0);
+
+                reader.read_buf_exact(&mut packet[start + FRAME_HEADER_LEN..])?;
+
+                headers.push(header);
+            }
+            _ => {
+                reader.seek_buffered_rev(FRAME_HEADER_LEN);
+                break;
+            }
+        }
+    }
+
+    Ok((headers, packet))
+}
+
+/// Reads the next packet, and checks if the next frame begins immediately after the packet.
+fn read_packet_strict(reader: &mut MediaSourceStream) -> Result<(Vec<FrameHeader>, Vec<u8>)> {
+    loop {
+        let (headers, packet) = read_packet(reader)?;
+
+        // Get the position before trying to read the next sync word.
+        let pos = reader.pos();
+
+        // If the sync word could not be read, the stream may have ended and this check cannot be
+        // performed.
+        if let Ok(sync) = reader.read_be_u16() {
+            if sync != SYNC_WORD {
+                warn!("skipping junk at {} bytes", pos - packet.len() as u64);
+
+                // Rewind to the second byte of the rejected packet to prevent syncing to the same
+                // spot again.
+                reader.seek_buffered_rev(packet.len() + 2 - 1);
+                continue;
+            }
+        }
+
+        reader.seek_buffered(pos);
+
+        break Ok((headers, packet));
+    }
+}
+
+/// Estimates the total number of frames (samples per channel) in the media source stream.
+fn estimate_n_frames(reader: &mut MediaSourceStream) -> Option<u64> {
+    const MAX_PACKETS: u64 = 16;
+    const MAX_LEN: u64 = 16 * 1024;
+
+    let start_pos = reader.pos();
+
+    let total_len = match reader.byte_len() {
+        Some(len) => len - start_pos,
+        _ => return None,
+    };
+
+    let mut total_packet_len = 0;
+    let mut total_packets = 0;
+    let mut total_dur = 0;
+
+    let n_frames = loop {
+        let (headers, packet) = match read_packet(reader) {
+            Ok(packet) => packet,
+            // If the stream ended, then the duration is exact.
+            Err(_) if total_packets > 0 => break Some(total_dur),
+            _ => break None,
+        };
+
+        total_packet_len += packet.len() as u64;
+        total_packets += 1;
+        total_dur += headers[0].n_samples() as u64;
+
+        // Read up-to 16 packets, or 16kB, then calculate the average packet length, and from that,
+        // the total number of packets.
+        if total_packets > MAX_PACKETS || total_packet_len > MAX_LEN {
+            let avg_packet_len = total_packet_len as f64 / total_packets as f64;
+            let avg_packet_dur = total_dur as f64 / total_packets as f64;
+
+            break Some(((total_len as f64 / avg_packet_len) * avg_packet_dur) as u64);
+        }
+    };
+
+    // Rewind back to the first packet.
+    reader.seek_buffered_rev((reader.pos() - start_pos) as usize);
+
+    n_frames
+}
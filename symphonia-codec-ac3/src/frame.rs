@@ -0,0 +1,1647 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, ReadBitsLtr};
+
+use crate::bitalloc::{calc_bap, calc_mask, calc_psd, BitAllocParams, DeltaBitAlloc};
+use crate::decoder::DynamicRange;
+use crate::header::{FrameHeader, StreamType, BLOCK_LEN, MAX_BLOCKS};
+use crate::tables::*;
+use crate::transform::Transform;
+
+/// The maximum number of channels in a substream, including the coupling channel.
+const MAX_CHANNELS: usize = 7;
+
+/// The index of the coupling channel.
+const CPL_CH: usize = 0;
+
+/// The maximum number of coupling sub-bands.
+const MAX_CPL_BANDS: usize = 18;
+
+/// The maximum number of spectral extension sub-bands.
+const MAX_SPX_BANDS: usize = 17;
+
+/// Exponent strategy: reuse the exponents of the previous block.
+const EXP_REUSE: u8 = 0;
+/// Exponent strategy: new exponents, one per mantissa.
+const EXP_D15: u8 = 1;
+/// Exponent strategy: new exponents, one per 2 mantissas.
+const EXP_D25: u8 = 2;
+/// Exponent strategy: new exponents, one per 4 mantissas.
+const EXP_D45: u8 = 3;
+
+/// Delta bit allocation mode: a new delta bit allocation follows.
+const DBA_NEW: u8 = 1;
+/// Delta bit allocation mode: no delta bit allocation.
+const DBA_NONE: u8 = 2;
+/// Delta bit allocation mode: reserved.
+const DBA_RESERVED: u8 = 3;
+
+/// Gets the exponent strategy of each block for a frame exponent strategy combination (ATSC A/52
+/// Table E2.14).
+///
+/// Bits 4 to 0 of the combination signal that new exponents are sent in blocks 1 to 5,
+/// respectively. New exponents are always sent in block 0. Exponents used by 4 or more blocks use
+/// the D15 strategy, by 2 or 3 blocks use the D25 strategy, and by 1 block use the D45 strategy.
+fn frame_exp_strategies(combination: u32) -> [u8; MAX_BLOCKS] {
+    let is_new = |blk: usize| blk == 0 || combination & (1 << (MAX_BLOCKS - 1 - blk)) != 0;
+
+    let mut strategies = [EXP_REUSE; MAX_BLOCKS];
+
+    for blk in (0..MAX_BLOCKS).filter(|&blk| is_new(blk)) {
+        let n_blocks = 1 + (blk + 1..MAX_BLOCKS).take_while(|&next| !is_new(next)).count();
+
+        strategies[blk] = match n_blocks {
+            1 => EXP_D45,
+            2 | 3 => EXP_D25,
+            _ => EXP_D15,
+        };
+    }
+
+    strategies
+}
+
+/// A simple linear congruential generator used for dithering and noise.
+struct Lcg(u32);
+
+impl Lcg {
+    /// Gets a pseudo-random value in the range [-1, 1).
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (self.0 as i32) as f32 / 2_147_483_648.0
+    }
+}
+
+/// Grouped mantissas of the current block. Mantissas with bit allocation pointers 1, 2, and 4
+/// are grouped into a single code word that is shared across channels.
+#[derive(Default)]
+struct MantissaGroups {
+    b1: [f32; 3],
+    n_b1: usize,
+    b2: [f32; 3],
+    n_b2: usize,
+    b4: [f32; 2],
+    n_b4: usize,
+}
+
+/// Gets the dequantized value of level `k` of a symmetric quantizer with `n` levels.
+#[inline(always)]
+fn level(k: u32, n: u32) -> f32 {
+    (2 * k as i32 - (n as i32 - 1)) as f32 / n as f32
+}
+
+impl MantissaGroups {
+    fn read(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        bap: u8,
+        dither: Option<&mut Lcg>,
+    ) -> Result<f32> {
+        let value = match bap {
+            0 => match dither {
+                // Dither is scaled by 1/sqrt(2).
+                Some(lcg) => 0.707_106_77 * lcg.next(),
+                None => 0.0,
+            },
+            1 => {
+                if self.n_b1 == 0 {
+                    let code = bs.read_bits_leq32(5)?;
+                    self.b1 = [level(code / 9, 3), level((code / 3) % 3, 3), level(code % 3, 3)];
+                    self.n_b1 = 3;
+                }
+                self.n_b1 -= 1;
+                self.b1[2 - self.n_b1]
+            }
+            2 => {
+                if self.n_b2 == 0 {
+                    let code = bs.read_bits_leq32(7)?;
+                    self.b2 = [level(code / 25, 5), level((code / 5) % 5, 5), level(code % 5, 5)];
+                    self.n_b2 = 3;
+                }
+                self.n_b2 -= 1;
+                self.b2[2 - self.n_b2]
+            }
+            3 => level(bs.read_bits_leq32(3)?, 7),
+            4 => {
+                if self.n_b4 == 0 {
+                    let code = bs.read_bits_leq32(7)?;
+                    self.b4 = [level(code / 11, 11), level(code % 11, 11)];
+                    self.n_b4 = 2;
+                }
+                self.n_b4 -= 1;
+                self.b4[1 - self.n_b4]
+            }
+            5 => level(bs.read_bits_leq32(4)?, 15),
+            _ => {
+                // Asymmetric quantization, the mantissa is a two's complement fraction.
+                let bits = u32::from(MANTISSA_BITS[usize::from(bap)]);
+                bs.read_bits_leq32_signed(bits)? as f32 / (1u32 << (bits - 1)) as f32
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+/// Decodes the band structure of the sub-bands `start..end`, and returns the number of bands. Each
+/// sub-band is 12 transform coefficients wide, and a set flag in the band structure merges a
+/// sub-band with the band of the preceeding sub-band.
+fn band_sizes(band_struct: &[bool], start: usize, end: usize, sizes: &mut [usize]) -> usize {
+    let mut n_bands = 1;
+
+    sizes[0] = 12;
+
+    for &merge in &band_struct[start + 1..end] {
+        if merge {
+            sizes[n_bands - 1] += 12;
+        }
+        else {
+            sizes[n_bands] = 12;
+            n_bands += 1;
+        }
+    }
+
+    n_bands
+}
+
+/// The decoder state of a single AC-3 or E-AC-3 substream.
+pub struct Substream {
+    // Frame parameters.
+    acmod: u8,
+    n_fbw: usize,
+    lfe_ch: Option<usize>,
+    n_blocks: usize,
+    is_eac3: bool,
+    stream_type: StreamType,
+
+    /// The centre downmix level.
+    pub centre_mix_level: f32,
+    /// The surround downmix level.
+    pub surround_mix_level: f32,
+
+    compr: [Option<u8>; 2],
+    dynrng: [u8; 2],
+
+    // Frame syntax. For AC-3 all elements are present in all blocks.
+    snr_offset_strategy: u8,
+    block_switch_syntax: bool,
+    dither_flag_syntax: bool,
+    bit_alloc_syntax: bool,
+    fast_gain_syntax: bool,
+    dba_syntax: bool,
+    skip_syntax: bool,
+    spx_atten_code: [Option<u8>; MAX_CHANNELS],
+    cpl_strategy_exists: [bool; MAX_BLOCKS],
+    cpl_in_use_blk: [bool; MAX_BLOCKS],
+    exp_strategy: [[u8; MAX_CHANNELS]; MAX_BLOCKS],
+    first_cpl_leak: bool,
+    first_cpl_coords: [bool; MAX_CHANNELS],
+    first_spx_coords: [bool; MAX_CHANNELS],
+
+    // Block state.
+    block_switch: [bool; MAX_CHANNELS],
+    dither_flag: [bool; MAX_CHANNELS],
+
+    spx_in_use: bool,
+    channel_uses_spx: [bool; MAX_CHANNELS],
+    spx_copy_start: usize,
+    spx_begin: usize,
+    spx_end: usize,
+    spx_band_struct: [bool; MAX_SPX_BANDS],
+    n_spx_bands: usize,
+    spx_band_sizes: [usize; MAX_SPX_BANDS],
+    spx_noise_blend: [[f32; MAX_SPX_BANDS]; MAX_CHANNELS],
+    spx_signal_blend: [[f32; MAX_SPX_BANDS]; MAX_CHANNELS],
+
+    cpl_in_use: bool,
+    channel_in_cpl: [bool; MAX_CHANNELS],
+    phase_flags_in_use: bool,
+    phase_flags: [bool; MAX_CPL_BANDS],
+    cpl_band_struct: [bool; MAX_CPL_BANDS],
+    n_cpl_bands: usize,
+    cpl_band_sizes: [usize; MAX_CPL_BANDS],
+    cpl_coords: [[f32; MAX_CPL_BANDS]; MAX_CHANNELS],
+
+    n_rematrix_bands: usize,
+    rematrix_flags: [bool; 4],
+
+    start_freq: [usize; MAX_CHANNELS],
+    end_freq: [usize; MAX_CHANNELS],
+    exps: [[u8; 256]; MAX_CHANNELS],
+
+    bit_alloc: BitAllocParams,
+    snr_offset: [i32; MAX_CHANNELS],
+    fast_gain: [i32; MAX_CHANNELS],
+    dba: [DeltaBitAlloc; MAX_CHANNELS],
+    psd: [[i16; 256]; MAX_CHANNELS],
+    band_psd: [[i16; N_BANDS]; MAX_CHANNELS],
+    mask: [[i16; N_BANDS]; MAX_CHANNELS],
+    bap: [[u8; 256]; MAX_CHANNELS],
+
+    coeffs: [[f32; 256]; MAX_CHANNELS],
+    delay: [[f32; BLOCK_LEN]; MAX_CHANNELS],
+    lcg: Lcg,
+
+    /// The decoded samples of each channel, excluding the coupling channel. The low-frequency
+    /// effects channel, if present, is last.
+    pub samples: Vec<Vec<f32>>,
+}
+
+impl Substream {
+    pub fn new() -> Self {
+        Substream {
+            acmod: 0,
+            n_fbw: 0,
+            lfe_ch: None,
+            n_blocks: 0,
+            is_eac3: false,
+            stream_type: StreamType::Independent,
+            centre_mix_level: 0.0,
+            surround_mix_level: 0.0,
+            compr: [None; 2],
+            dynrng: [0; 2],
+            snr_offset_strategy: 0,
+            block_switch_syntax: false,
+            dither_flag_syntax: false,
+            bit_alloc_syntax: false,
+            fast_gain_syntax: false,
+            dba_syntax: false,
+            skip_syntax: false,
+            spx_atten_code: [None; MAX_CHANNELS],
+            cpl_strategy_exists: [false; MAX_BLOCKS],
+            cpl_in_use_blk: [false; MAX_BLOCKS],
+            exp_strategy: [[EXP_REUSE; MAX_CHANNELS]; MAX_BLOCKS],
+            first_cpl_leak: false,
+            first_cpl_coords: [true; MAX_CHANNELS],
+            first_spx_coords: [true; MAX_CHANNELS],
+            block_switch: [false; MAX_CHANNELS],
+            dither_flag: [false; MAX_CHANNELS],
+            spx_in_use: false,
+            channel_uses_spx: [false; MAX_CHANNELS],
+            spx_copy_start: 0,
+            spx_begin: 0,
+            spx_end: 0,
+            spx_band_struct: [false; MAX_SPX_BANDS],
+            n_spx_bands: 0,
+            spx_band_sizes: [0; MAX_SPX_BANDS],
+            spx_noise_blend: [[0.0; MAX_SPX_BANDS]; MAX_CHANNELS],
+            spx_signal_blend: [[0.0; MAX_SPX_BANDS]; MAX_CHANNELS],
+            cpl_in_use: false,
+            channel_in_cpl: [false; MAX_CHANNELS],
+            phase_flags_in_use: false,
+            phase_flags: [false; MAX_CPL_BANDS],
+            cpl_band_struct: [false; MAX_CPL_BANDS],
+            n_cpl_bands: 0,
+            cpl_band_sizes: [0; MAX_CPL_BANDS],
+            cpl_coords: [[0.0; MAX_CPL_BANDS]; MAX_CHANNELS],
+            n_rematrix_bands: 0,
+            rematrix_flags: [false; 4],
+            start_freq: [0; MAX_CHANNELS],
+            end_freq: [0; MAX_CHANNELS],
+            exps: [[0; 256]; MAX_CHANNELS],
+            bit_alloc: Default::default(),
+            snr_offset: [0; MAX_CHANNELS],
+            fast_gain: [0; MAX_CHANNELS],
+            dba: [Default::default(); MAX_CHANNELS],
+            psd: [[0; 256]; MAX_CHANNELS],
+            band_psd: [[0; N_BANDS]; MAX_CHANNELS],
+            mask: [[0; N_BANDS]; MAX_CHANNELS],
+            bap: [[0; 256]; MAX_CHANNELS],
+            coeffs: [[0.0; 256]; MAX_CHANNELS],
+            delay: [[0.0; BLOCK_LEN]; MAX_CHANNELS],
+            lcg: Lcg(1),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Gets the number of channels, including the low-frequency effects channel.
+    pub fn n_channels(&self) -> usize {
+        self.n_fbw + usize::from(self.lfe_ch.is_some())
+    }
+
+    /// Resets the overlap state.
+    pub fn reset(&mut self) {
+        for delay in self.delay.iter_mut() {
+            *delay = [0.0; BLOCK_LEN];
+        }
+    }
+
+    /// Decodes a frame. The decoded samples are stored in `samples`.
+    pub fn decode(
+        &mut self,
+        header: &FrameHeader,
+        buf: &[u8],
+        drc: DynamicRange,
+        transform: &mut Transform,
+    ) -> Result<()> {
+        let lfe_ch = if header.lfe { Some(header.n_fbw_channels() + 1) } else { None };
+
+        // The overlap state is invalid if the channel configuration changes.
+        if header.acmod != self.acmod || lfe_ch != self.lfe_ch {
+            self.reset();
+        }
+
+        self.acmod = header.acmod;
+        self.n_fbw = header.n_fbw_channels();
+        self.lfe_ch = lfe_ch;
+        self.n_blocks = header.n_blocks;
+        self.is_eac3 = header.is_eac3();
+        self.stream_type = header.stream_type;
+
+        self.bit_alloc.sr_code = header.sr_code;
+        self.bit_alloc.sr_shift = header.sr_shift;
+
+        // The low-frequency effects channel always uses the first 7 transform coefficients.
+        if let Some(lfe_ch) = self.lfe_ch {
+            self.start_freq[lfe_ch] = 0;
+            self.end_freq[lfe_ch] = 7;
+        }
+
+        let n_channels = self.n_channels();
+
+        self.samples.resize_with(n_channels, Default::default);
+
+        for samples in self.samples.iter_mut() {
+            samples.resize(header.n_samples(), 0.0);
+        }
+
+        let mut bs = BitReaderLtr::new(buf);
+
+        if self.is_eac3 {
+            self.read_eac3_bsi(&mut bs, header)?;
+            self.read_audfrm(&mut bs, header)?;
+        }
+        else {
+            self.read_ac3_bsi(&mut bs, header)?;
+            self.init_ac3_syntax();
+        }
+
+        for blk in 0..self.n_blocks {
+            self.read_audblk(&mut bs, blk)?;
+            self.read_mantissas(&mut bs)?;
+            self.synthesize(blk, drc, transform);
+        }
+
+        Ok(())
+    }
+
+    fn read_ac3_bsi(&mut self, bs: &mut BitReaderLtr<'_>, header: &FrameHeader) -> Result<()> {
+        // Sync information, bsid, and bsmod.
+        bs.ignore_bits(40 + 5 + 3)?;
+
+        let acmod = bs.read_bits_leq32(3)? as u8;
+
+        self.centre_mix_level = CENTRE_MIX_LEVELS[0];
+        self.surround_mix_level = SURROUND_MIX_LEVELS[0];
+
+        if acmod & 0x1 != 0 && acmod != 0x1 {
+            self.centre_mix_level = CENTRE_MIX_LEVELS[bs.read_bits_leq32(2)? as usize];
+        }
+
+        if acmod & 0x4 != 0 {
+            self.surround_mix_level = SURROUND_MIX_LEVELS[bs.read_bits_leq32(2)? as usize];
+        }
+
+        if acmod == 0x2 {
+            // Dolby Surround mode.
+            bs.ignore_bits(2)?;
+        }
+
+        // Low-frequency effects on.
+        bs.ignore_bit()?;
+
+        // Dual-mono streams carry a second set of program information.
+        for i in 0..if acmod == 0 { 2 } else { 1 } {
+            // Dialogue normalization.
+            bs.ignore_bits(5)?;
+
+            self.compr[i] = if bs.read_bool()? { Some(bs.read_bits_leq32(8)? as u8) } else { None };
+
+            // Language code.
+            if bs.read_bool()? {
+                bs.ignore_bits(8)?;
+            }
+
+            // Mixing level, and room type.
+            if bs.read_bool()? {
+                bs.ignore_bits(7)?;
+            }
+        }
+
+        // Copyright, and original bitstream flags.
+        bs.ignore_bits(2)?;
+
+        if header.bsid == 6 {
+            // Alternate bitstream syntax with extended mix levels.
+            if bs.read_bool()? {
+                // Preferred stereo downmix mode, and Lt/Rt mix levels.
+                bs.ignore_bits(2 + 3 + 3)?;
+
+                self.centre_mix_level = EXT_MIX_LEVELS[bs.read_bits_leq32(3)? as usize];
+
+                // Surround mix levels above -3 dB are reserved and treated as -3 dB.
+                let lorosurmixlev = (bs.read_bits_leq32(3)? as usize).max(3);
+                self.surround_mix_level = EXT_MIX_LEVELS[lorosurmixlev];
+            }
+
+            if bs.read_bool()? {
+                bs.ignore_bits(14)?;
+            }
+        }
+        else {
+            // Time codes.
+            if bs.read_bool()? {
+                bs.ignore_bits(14)?;
+            }
+            if bs.read_bool()? {
+                bs.ignore_bits(14)?;
+            }
+        }
+
+        read_addbsi(bs)
+    }
+
+    fn read_eac3_bsi(&mut self, bs: &mut BitReaderLtr<'_>, header: &FrameHeader) -> Result<()> {
+        // Sync word, stream type, substream identifier, frame size, sample rate code, number of
+        // blocks, audio coding mode, low-frequency effects on, and bsid. These were already
+        // parsed into the frame header.
+        bs.ignore_bits(16 + 2 + 3 + 11 + 2 + 2 + 3 + 1 + 5)?;
+
+        let acmod = header.acmod;
+        let n_programs = if acmod == 0 { 2 } else { 1 };
+
+        self.centre_mix_level = EXT_MIX_LEVELS[4];
+        self.surround_mix_level = EXT_MIX_LEVELS[4];
+
+        for i in 0..n_programs {
+            // Dialogue normalization.
+            bs.ignore_bits(5)?;
+
+            self.compr[i] = if bs.read_bool()? { Some(bs.read_bits_leq32(8)? as u8) } else { None };
+        }
+
+        // Custom channel map. This was already parsed into the frame header.
+        if header.stream_type == StreamType::Dependent && bs.read_bool()? {
+            bs.ignore_bits(16)?;
+        }
+
+        // Mixing metadata.
+        if bs.read_bool()? {
+            if acmod > 0x2 {
+                // Preferred stereo downmix mode.
+                bs.ignore_bits(2)?;
+            }
+
+            if acmod & 0x1 != 0 && acmod > 0x2 {
+                bs.ignore_bits(3)?;
+                self.centre_mix_level = EXT_MIX_LEVELS[bs.read_bits_leq32(3)? as usize];
+            }
+
+            if acmod & 0x4 != 0 {
+                bs.ignore_bits(3)?;
+                let lorosurmixlev = (bs.read_bits_leq32(3)? as usize).max(3);
+                self.surround_mix_level = EXT_MIX_LEVELS[lorosurmixlev];
+            }
+
+            // Low-frequency effects mix level.
+            if header.lfe && bs.read_bool()? {
+                bs.ignore_bits(5)?;
+            }
+
+            if header.stream_type == StreamType::Independent {
+                // Program scale factors.
+                for _ in 0..n_programs {
+                    if bs.read_bool()? {
+                        bs.ignore_bits(6)?;
+                    }
+                }
+
+                if bs.read_bool()? {
+                    bs.ignore_bits(6)?;
+                }
+
+                // Mixing parameter data.
+                match bs.read_bits_leq32(2)? {
+                    1 => bs.ignore_bits(5)?,
+                    2 => bs.ignore_bits(12)?,
+                    3 => {
+                        let len = bs.read_bits_leq32(5)? + 2;
+                        bs.ignore_bits(8 * len)?;
+                    }
+                    _ => (),
+                }
+
+                // Pan information.
+                if acmod < 0x2 {
+                    for _ in 0..n_programs {
+                        if bs.read_bool()? {
+                            bs.ignore_bits(14)?;
+                        }
+                    }
+                }
+
+                // Frame mixing configuration information.
+                if bs.read_bool()? {
+                    for _ in 0..header.n_blocks {
+                        if header.n_blocks == 1 || bs.read_bool()? {
+                            bs.ignore_bits(5)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Informational metadata.
+        if bs.read_bool()? {
+            // Bitstream mode, copyright, and original bitstream flags.
+            bs.ignore_bits(3 + 1 + 1)?;
+
+            if acmod == 0x2 {
+                // Dolby Surround, and Dolby Headphone modes.
+                bs.ignore_bits(2 + 2)?;
+            }
+
+            if acmod >= 0x6 {
+                // Dolby Surround EX mode.
+                bs.ignore_bits(2)?;
+            }
+
+            // Mixing level, room type, and A/D converter type.
+            for _ in 0..n_programs {
+                if bs.read_bool()? {
+                    bs.ignore_bits(8)?;
+                }
+            }
+
+            // Source sample rate code, only present if the sample rate is not reduced.
+            if header.sample_rate == SAMPLE_RATES[header.sr_code] {
+                bs.ignore_bit()?;
+            }
+        }
+
+        // Converter synchronization flag.
+        if header.stream_type == StreamType::Independent && header.n_blocks != MAX_BLOCKS {
+            bs.ignore_bit()?;
+        }
+
+        // Frame size code of the original AC-3 frame.
+        if header.stream_type == StreamType::Ac3Convert
+            && (header.n_blocks == MAX_BLOCKS || bs.read_bool()?)
+        {
+            bs.ignore_bits(6)?;
+        }
+
+        read_addbsi(bs)
+    }
+
+    /// Initializes the frame syntax for an AC-3 frame, where all elements may be present in every
+    /// block.
+    fn init_ac3_syntax(&mut self) {
+        self.snr_offset_strategy = 2;
+        self.block_switch_syntax = true;
+        self.dither_flag_syntax = true;
+        self.bit_alloc_syntax = true;
+        self.fast_gain_syntax = false;
+        self.dba_syntax = true;
+        self.skip_syntax = true;
+        self.spx_atten_code = [None; MAX_CHANNELS];
+        self.first_cpl_leak = false;
+    }
+
+    /// Reads the E-AC-3 audio frame element that carries the strategies shared by all blocks.
+    fn read_audfrm(&mut self, bs: &mut BitReaderLtr<'_>, header: &FrameHeader) -> Result<()> {
+        let n_blocks = self.n_blocks;
+        let n_fbw = self.n_fbw;
+
+        let (exp_strategy_syntax, aht_syntax) =
+            if n_blocks == MAX_BLOCKS { (bs.read_bool()?, bs.read_bool()?) } else { (true, false) };
+
+        self.snr_offset_strategy = bs.read_bits_leq32(2)? as u8;
+
+        let transient_proc_syntax = bs.read_bool()?;
+
+        self.block_switch_syntax = bs.read_bool()?;
+        self.dither_flag_syntax = bs.read_bool()?;
+        self.bit_alloc_syntax = bs.read_bool()?;
+        self.fast_gain_syntax = bs.read_bool()?;
+        self.dba_syntax = bs.read_bool()?;
+        self.skip_syntax = bs.read_bool()?;
+
+        let spx_atten_syntax = bs.read_bool()?;
+
+        // Coupling strategy of each block. The strategy is always present in the first block.
+        self.cpl_strategy_exists = [false; MAX_BLOCKS];
+        self.cpl_in_use_blk = [false; MAX_BLOCKS];
+
+        self.cpl_strategy_exists[0] = true;
+
+        if self.acmod > 0x1 {
+            self.cpl_in_use_blk[0] = bs.read_bool()?;
+
+            for blk in 1..n_blocks {
+                self.cpl_strategy_exists[blk] = bs.read_bool()?;
+
+                self.cpl_in_use_blk[blk] = if self.cpl_strategy_exists[blk] {
+                    bs.read_bool()?
+                }
+                else {
+                    self.cpl_in_use_blk[blk - 1]
+                };
+            }
+        }
+
+        // Exponent strategies.
+        if exp_strategy_syntax {
+            for blk in 0..n_blocks {
+                self.exp_strategy[blk][CPL_CH] =
+                    if self.cpl_in_use_blk[blk] { bs.read_bits_leq32(2)? as u8 } else { EXP_REUSE };
+
+                for ch in 1..=n_fbw {
+                    self.exp_strategy[blk][ch] = bs.read_bits_leq32(2)? as u8;
+                }
+            }
+        }
+        else {
+            // Frame-based exponent strategies, only possible with 6 blocks. The coupling channel
+            // strategies are only present if coupling is in use in any block.
+            let first_ch = if self.cpl_in_use_blk.contains(&true) { CPL_CH } else { 1 };
+
+            for ch in first_ch..=n_fbw {
+                let strategies = frame_exp_strategies(bs.read_bits_leq32(5)?);
+
+                for (blk, &strategy) in strategies.iter().enumerate() {
+                    self.exp_strategy[blk][ch] =
+                        if ch != CPL_CH || self.cpl_in_use_blk[blk] { strategy } else { EXP_REUSE };
+                }
+            }
+
+            if first_ch != CPL_CH {
+                (0..n_blocks).for_each(|blk| self.exp_strategy[blk][CPL_CH] = EXP_REUSE);
+            }
+        }
+
+        if let Some(lfe_ch) = self.lfe_ch {
+            for blk in 0..n_blocks {
+                self.exp_strategy[blk][lfe_ch] = bs.read_bits_leq32(1)? as u8;
+            }
+        }
+
+        // Converter exponent strategies.
+        if self.stream_type == StreamType::Independent
+            && (n_blocks == MAX_BLOCKS || bs.read_bool()?)
+        {
+            bs.ignore_bits(5 * n_fbw as u32)?;
+        }
+
+        // Adaptive hybrid transform. It may only be used by channels that reuse the exponents of
+        // the first block for the entire frame.
+        if aht_syntax {
+            let n_cpl_blocks = self.cpl_in_use_blk.iter().filter(|&&in_use| in_use).count();
+
+            for ch in usize::from(n_cpl_blocks != MAX_BLOCKS)..=self.n_channels() {
+                let use_aht = (1..MAX_BLOCKS).all(|blk| {
+                    self.exp_strategy[blk][ch] == EXP_REUSE
+                        && !(ch == CPL_CH && self.cpl_strategy_exists[blk])
+                });
+
+                if use_aht && bs.read_bool()? {
+                    return unsupported_error("ac3: adaptive hybrid transform is not supported");
+                }
+            }
+        }
+
+        // SNR offsets shared by all blocks and channels.
+        if self.snr_offset_strategy == 0 {
+            let csnr = bs.read_bits_leq32(6)? as i32;
+            let fsnr = bs.read_bits_leq32(4)? as i32;
+
+            self.snr_offset = [(((csnr - 15) << 4) + fsnr) << 2; MAX_CHANNELS];
+        }
+
+        // Transient pre-noise processing.
+        if transient_proc_syntax {
+            for _ in 0..n_fbw {
+                if bs.read_bool()? {
+                    bs.ignore_bits(10 + 8)?;
+                }
+            }
+        }
+
+        // Spectral extension attenuation.
+        for ch in 1..=n_fbw {
+            self.spx_atten_code[ch] = if spx_atten_syntax && bs.read_bool()? {
+                Some(bs.read_bits_leq32(5)? as u8)
+            }
+            else {
+                None
+            };
+        }
+
+        // Block start information.
+        if n_blocks > 1 && bs.read_bool()? {
+            let log2_len = 31 - (header.frame_len as u32 - 2).leading_zeros();
+            bs.ignore_bits((n_blocks as u32 - 1) * (4 + log2_len))?;
+        }
+
+        // Without bit allocation parameters in the bitstream, the default parameters are used.
+        if !self.bit_alloc_syntax {
+            self.bit_alloc.slow_decay = i32::from(SLOW_DECAY[2]) >> self.bit_alloc.sr_shift;
+            self.bit_alloc.fast_decay = i32::from(FAST_DECAY[1]) >> self.bit_alloc.sr_shift;
+            self.bit_alloc.slow_gain = i32::from(SLOW_GAIN[1]);
+            self.bit_alloc.db_per_bit = i32::from(DB_PER_BIT[2]);
+            self.bit_alloc.floor = i32::from(FLOOR[7]);
+        }
+
+        // Each frame is independently decodable, therefore the first coordinates and leak values
+        // are always present.
+        self.first_cpl_leak = true;
+        self.first_cpl_coords = [true; MAX_CHANNELS];
+        self.first_spx_coords = [true; MAX_CHANNELS];
+
+        Ok(())
+    }
+
+    /// Reads an audio block.
+    fn read_audblk(&mut self, bs: &mut BitReaderLtr<'_>, blk: usize) -> Result<()> {
+        let n_fbw = self.n_fbw;
+        let n_channels = self.n_channels();
+        let n_programs = if self.acmod == 0 { 2 } else { 1 };
+
+        // Block switch flags.
+        for ch in 1..=n_fbw {
+            self.block_switch[ch] = self.block_switch_syntax && bs.read_bool()?;
+        }
+
+        // Dither flags. Dither is enabled if the flags are not present.
+        for ch in 1..=n_fbw {
+            self.dither_flag[ch] = !self.dither_flag_syntax || bs.read_bool()?;
+        }
+
+        // Dynamic range gain words.
+        for i in 0..n_programs {
+            if bs.read_bool()? {
+                self.dynrng[i] = bs.read_bits_leq32(8)? as u8;
+            }
+            else if blk == 0 {
+                self.dynrng[i] = 0;
+            }
+        }
+
+        // Spectral extension strategy.
+        if self.is_eac3 && (blk == 0 || bs.read_bool()?) {
+            self.spx_in_use = bs.read_bool()?;
+
+            if self.spx_in_use {
+                self.read_spx_strategy(bs, blk)?;
+            }
+        }
+
+        if !self.is_eac3 || !self.spx_in_use {
+            self.spx_in_use = false;
+
+            for ch in 1..=n_fbw {
+                self.channel_uses_spx[ch] = false;
+                self.first_spx_coords[ch] = true;
+            }
+        }
+
+        // Spectral extension coordinates.
+        if self.spx_in_use {
+            self.read_spx_coords(bs)?;
+        }
+
+        // Coupling strategy.
+        let cpl_strategy_exists =
+            if self.is_eac3 { self.cpl_strategy_exists[blk] } else { bs.read_bool()? };
+
+        if cpl_strategy_exists {
+            self.cpl_in_use = if self.is_eac3 { self.cpl_in_use_blk[blk] } else { bs.read_bool()? };
+
+            if self.cpl_in_use {
+                self.read_cpl_strategy(bs, blk)?;
+            }
+            else {
+                for ch in 1..=n_fbw {
+                    self.first_cpl_coords[ch] = true;
+                }
+
+                self.channel_in_cpl = [false; MAX_CHANNELS];
+                self.first_cpl_leak = self.is_eac3;
+                self.phase_flags_in_use = false;
+            }
+        }
+        else if blk == 0 {
+            return decode_error("ac3: coupling strategy must be present in the first block");
+        }
+
+        // Coupling coordinates.
+        if self.cpl_in_use {
+            self.read_cpl_coords(bs, blk)?;
+        }
+
+        // Rematrixing strategy and flags.
+        if self.acmod == 0x2 {
+            if (self.is_eac3 && blk == 0) || bs.read_bool()? {
+                self.n_rematrix_bands = 4;
+
+                if self.cpl_in_use && self.start_freq[CPL_CH] <= 61 {
+                    self.n_rematrix_bands -= 1 + usize::from(self.start_freq[CPL_CH] == 37);
+                }
+                else if self.spx_in_use && self.spx_begin <= 61 {
+                    self.n_rematrix_bands -= 1;
+                }
+
+                for flag in self.rematrix_flags[..self.n_rematrix_bands].iter_mut() {
+                    *flag = bs.read_bool()?;
+                }
+            }
+            else if blk == 0 {
+                self.n_rematrix_bands = 0;
+            }
+        }
+
+        // Exponent strategies.
+        if !self.is_eac3 {
+            self.exp_strategy[blk][CPL_CH] =
+                if self.cpl_in_use { bs.read_bits_leq32(2)? as u8 } else { EXP_REUSE };
+
+            for ch in 1..=n_fbw {
+                self.exp_strategy[blk][ch] = bs.read_bits_leq32(2)? as u8;
+            }
+
+            if let Some(lfe_ch) = self.lfe_ch {
+                self.exp_strategy[blk][lfe_ch] = bs.read_bits_leq32(1)? as u8;
+            }
+        }
+
+        let first_ch = usize::from(!self.cpl_in_use);
+
+        if blk == 0 && self.exp_strategy[0][first_ch..=n_channels].contains(&EXP_REUSE) {
+            return decode_error("ac3: exponents must be present in the first block");
+        }
+
+        // Channel bandwidths.
+        for ch in 1..=n_fbw {
+            if self.exp_strategy[blk][ch] != EXP_REUSE {
+                self.start_freq[ch] = 0;
+
+                self.end_freq[ch] = if self.channel_in_cpl[ch] {
+                    self.start_freq[CPL_CH]
+                }
+                else if self.channel_uses_spx[ch] {
+                    self.spx_begin
+                }
+                else {
+                    let code = bs.read_bits_leq32(6)? as usize;
+
+                    if code > 60 {
+                        return decode_error("ac3: invalid channel bandwidth code");
+                    }
+
+                    73 + 3 * code
+                };
+            }
+        }
+
+        // Exponents.
+        for ch in first_ch..=n_channels {
+            let strategy = self.exp_strategy[blk][ch];
+
+            if strategy == EXP_REUSE {
+                continue;
+            }
+
+            let group_size = 1 << (strategy - 1);
+            let is_lfe = Some(ch) == self.lfe_ch;
+
+            let n_groups = if ch == CPL_CH {
+                (self.end_freq[CPL_CH] - self.start_freq[CPL_CH]) / (3 * group_size)
+            }
+            else if is_lfe {
+                2
+            }
+            else {
+                (self.end_freq[ch] + 3 * group_size - 4) / (3 * group_size)
+            };
+
+            self.read_exponents(bs, ch, group_size, n_groups)?;
+
+            // Gain range.
+            if ch != CPL_CH && !is_lfe {
+                bs.ignore_bits(2)?;
+            }
+        }
+
+        // Bit allocation parameters.
+        if self.bit_alloc_syntax && bs.read_bool()? {
+            let sr_shift = self.bit_alloc.sr_shift;
+
+            self.bit_alloc.slow_decay =
+                i32::from(SLOW_DECAY[bs.read_bits_leq32(2)? as usize]) >> sr_shift;
+            self.bit_alloc.fast_decay =
+                i32::from(FAST_DECAY[bs.read_bits_leq32(2)? as usize]) >> sr_shift;
+            self.bit_alloc.slow_gain = i32::from(SLOW_GAIN[bs.read_bits_leq32(2)? as usize]);
+            self.bit_alloc.db_per_bit = i32::from(DB_PER_BIT[bs.read_bits_leq32(2)? as usize]);
+            self.bit_alloc.floor = i32::from(FLOOR[bs.read_bits_leq32(3)? as usize]);
+        }
+        else if !self.is_eac3 && blk == 0 {
+            return decode_error(
+                "ac3: bit allocation parameters must be present in the first block",
+            );
+        }
+
+        // SNR offsets.
+        let snr_offset_exists = if self.is_eac3 {
+            self.snr_offset_strategy != 0 && (blk == 0 || bs.read_bool()?)
+        }
+        else {
+            bs.read_bool()?
+        };
+
+        if snr_offset_exists {
+            let csnr = (bs.read_bits_leq32(6)? as i32 - 15) << 4;
+            let mut fsnr = 0;
+
+            for ch in first_ch..=n_channels {
+                if ch == first_ch || self.snr_offset_strategy == 2 {
+                    fsnr = bs.read_bits_leq32(4)? as i32;
+                }
+
+                self.snr_offset[ch] = (csnr + fsnr) << 2;
+
+                // AC-3 frames always transmit a fast gain code with the SNR offset.
+                if !self.is_eac3 {
+                    self.fast_gain[ch] = i32::from(FAST_GAIN[bs.read_bits_leq32(3)? as usize]);
+                }
+            }
+        }
+        else if !self.is_eac3 && blk == 0 {
+            return decode_error("ac3: snr offsets must be present in the first block");
+        }
+
+        // Fast gain codes.
+        if self.fast_gain_syntax && bs.read_bool()? {
+            for ch in first_ch..=n_channels {
+                self.fast_gain[ch] = i32::from(FAST_GAIN[bs.read_bits_leq32(3)? as usize]);
+            }
+        }
+        else if self.is_eac3 && blk == 0 {
+            self.fast_gain = [i32::from(FAST_GAIN[4]); MAX_CHANNELS];
+        }
+
+        // Converter SNR offset.
+        if self.is_eac3 && self.stream_type == StreamType::Independent && bs.read_bool()? {
+            bs.ignore_bits(10)?;
+        }
+
+        // Coupling leak initialization.
+        if self.cpl_in_use {
+            if self.first_cpl_leak || bs.read_bool()? {
+                self.bit_alloc.cpl_fast_leak = bs.read_bits_leq32(3)? as i32;
+                self.bit_alloc.cpl_slow_leak = bs.read_bits_leq32(3)? as i32;
+            }
+            else if !self.is_eac3 && blk == 0 {
+                return decode_error("ac3: coupling leak must be present in the first block");
+            }
+
+            self.first_cpl_leak = false;
+        }
+
+        // Delta bit allocation.
+        if self.dba_syntax && bs.read_bool()? {
+            let mut modes = [0; MAX_CHANNELS];
+
+            for mode in modes[first_ch..=n_fbw].iter_mut() {
+                *mode = bs.read_bits_leq32(2)? as u8;
+
+                if *mode == DBA_RESERVED {
+                    return decode_error("ac3: reserved delta bit allocation mode");
+                }
+            }
+
+            for (ch, &mode) in modes.iter().enumerate().take(n_fbw + 1).skip(first_ch) {
+                match mode {
+                    DBA_NEW => {
+                        let dba = &mut self.dba[ch];
+
+                        dba.n_segments = bs.read_bits_leq32(3)? as usize + 1;
+
+                        for seg in dba.segments[..dba.n_segments].iter_mut() {
+                            seg.offset = bs.read_bits_leq32(5)? as u8;
+                            seg.len = bs.read_bits_leq32(4)? as u8;
+                            seg.value = bs.read_bits_leq32(3)? as u8;
+                        }
+                    }
+                    DBA_NONE => self.dba[ch].n_segments = 0,
+                    _ => (),
+                }
+            }
+        }
+        else if blk == 0 {
+            for dba in self.dba.iter_mut() {
+                dba.n_segments = 0;
+            }
+        }
+
+        // Skip field.
+        if self.skip_syntax && bs.read_bool()? {
+            let len = bs.read_bits_leq32(9)?;
+            bs.ignore_bits(8 * len)?;
+        }
+
+        // Compute the bit allocation of each channel.
+        for ch in first_ch..=n_channels {
+            let start = self.start_freq[ch];
+            let end = self.end_freq[ch];
+
+            if start >= end {
+                continue;
+            }
+
+            let is_lfe = Some(ch) == self.lfe_ch;
+
+            calc_psd(&self.exps[ch], start, end, &mut self.psd[ch], &mut self.band_psd[ch]);
+
+            calc_mask(
+                &self.bit_alloc,
+                &self.band_psd[ch],
+                start,
+                end,
+                self.fast_gain[ch],
+                is_lfe,
+                if is_lfe { None } else { Some(&self.dba[ch]) },
+                &mut self.mask[ch],
+            )?;
+
+            calc_bap(
+                &self.mask[ch],
+                &self.psd[ch],
+                start,
+                end,
+                self.snr_offset[ch],
+                self.bit_alloc.floor,
+                &mut self.bap[ch],
+            );
+        }
+
+        Ok(())
+    }
+
+    fn read_spx_strategy(&mut self, bs: &mut BitReaderLtr<'_>, blk: usize) -> Result<()> {
+        let n_fbw = self.n_fbw;
+
+        for ch in 1..=n_fbw {
+            self.channel_uses_spx[ch] = n_fbw == 1 || bs.read_bool()?;
+        }
+
+        let copy_start = bs.read_bits_leq32(2)? as usize;
+
+        let mut begin = bs.read_bits_leq32(3)? as usize + 2;
+        if begin > 7 {
+            begin += begin - 7;
+        }
+
+        let mut end = bs.read_bits_leq32(3)? as usize + 5;
+        if end > 7 {
+            end += end - 7;
+        }
+
+        if begin >= end {
+            return decode_error("ac3: invalid spectral extension range");
+        }
+
+        self.spx_copy_start = 25 + 12 * copy_start;
+        self.spx_begin = 25 + 12 * begin;
+        self.spx_end = 25 + 12 * end;
+
+        if self.spx_copy_start >= self.spx_begin {
+            return decode_error("ac3: invalid spectral extension copy start");
+        }
+
+        read_band_struct(
+            bs,
+            true,
+            blk,
+            &DEFAULT_SPX_BAND_STRUCT,
+            &mut self.spx_band_struct,
+            begin,
+            end,
+        )?;
+
+        self.n_spx_bands = band_sizes(&self.spx_band_struct, begin, end, &mut self.spx_band_sizes);
+
+        Ok(())
+    }
+
+    fn read_spx_coords(&mut self, bs: &mut BitReaderLtr<'_>) -> Result<()> {
+        for ch in 1..=self.n_fbw {
+            if !self.channel_uses_spx[ch] {
+                self.first_spx_coords[ch] = true;
+                continue;
+            }
+
+            if !self.first_spx_coords[ch] && !bs.read_bool()? {
+                continue;
+            }
+
+            self.first_spx_coords[ch] = false;
+
+            let blend = bs.read_bits_leq32(5)? as f32 / 32.0;
+            let master = 3 * bs.read_bits_leq32(2)? as i32;
+
+            let mut bin = self.spx_begin;
+
+            for bnd in 0..self.n_spx_bands {
+                let band_size = self.spx_band_sizes[bnd];
+
+                // The ratio of noise to signal increases with frequency.
+                let centre = (bin + band_size / 2) as f32;
+                let ratio = (centre / self.spx_end as f32 - blend).clamp(0.0, 1.0);
+
+                bin += band_size;
+
+                let exp = bs.read_bits_leq32(4)? as i32;
+                let mant = bs.read_bits_leq32(2)?;
+
+                let mant = if exp == 15 { mant << 1 } else { mant + 4 };
+                let coord = mant as f32 * 2f32.powi(2 - exp - master);
+
+                self.spx_noise_blend[ch][bnd] = coord * (3.0 * ratio).sqrt();
+                self.spx_signal_blend[ch][bnd] = coord * (1.0 - ratio).sqrt();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_cpl_strategy(&mut self, bs: &mut BitReaderLtr<'_>, blk: usize) -> Result<()> {
+        // Enhanced coupling.
+        if self.is_eac3 && bs.read_bool()? {
+            return unsupported_error("ac3: enhanced coupling is not supported");
+        }
+
+        // In E-AC-3 stereo frames, both channels are always coupled.
+        if self.is_eac3 && self.acmod == 0x2 {
+            self.channel_in_cpl[1] = true;
+            self.channel_in_cpl[2] = true;
+        }
+        else {
+            for ch in 1..=self.n_fbw {
+                self.channel_in_cpl[ch] = bs.read_bool()?;
+            }
+        }
+
+        if self.acmod == 0x2 {
+            self.phase_flags_in_use = bs.read_bool()?;
+        }
+
+        let begin = bs.read_bits_leq32(4)? as usize;
+
+        // When spectral extension is used, coupling ends where spectral extension begins.
+        let end = if self.spx_in_use {
+            (self.spx_begin - 37) / 12
+        }
+        else {
+            bs.read_bits_leq32(4)? as usize + 3
+        };
+
+        if begin >= end {
+            return decode_error("ac3: invalid coupling range");
+        }
+
+        read_band_struct(
+            bs,
+            self.is_eac3,
+            blk,
+            &DEFAULT_CPL_BAND_STRUCT,
+            &mut self.cpl_band_struct,
+            begin,
+            end,
+        )?;
+
+        self.n_cpl_bands = band_sizes(&self.cpl_band_struct, begin, end, &mut self.cpl_band_sizes);
+
+        self.start_freq[CPL_CH] = 37 + 12 * begin;
+        self.end_freq[CPL_CH] = 37 + 12 * end;
+
+        Ok(())
+    }
+
+    fn read_cpl_coords(&mut self, bs: &mut BitReaderLtr<'_>, blk: usize) -> Result<()> {
+        let mut cpl_coords_exist = false;
+
+        for ch in 1..=self.n_fbw {
+            if !self.channel_in_cpl[ch] {
+                self.first_cpl_coords[ch] = true;
+                continue;
+            }
+
+            if (self.is_eac3 && self.first_cpl_coords[ch]) || bs.read_bool()? {
+                self.first_cpl_coords[ch] = false;
+
+                cpl_coords_exist = true;
+
+                let master = 3 * bs.read_bits_leq32(2)? as i32;
+
+                for coord in self.cpl_coords[ch][..self.n_cpl_bands].iter_mut() {
+                    let exp = bs.read_bits_leq32(4)? as i32;
+                    let mant = bs.read_bits_leq32(4)?;
+
+                    // Exponents of 15 indicate an unnormalized mantissa.
+                    let mant =
+                        if exp == 15 { mant as f32 / 16.0 } else { (mant + 16) as f32 / 32.0 };
+
+                    *coord = 8.0 * mant * 2f32.powi(-(exp + master));
+                }
+            }
+            else if blk == 0 {
+                return decode_error(
+                    "ac3: coupling coordinates must be present in the first block",
+                );
+            }
+        }
+
+        // Phase flags.
+        if self.acmod == 0x2 && cpl_coords_exist {
+            for flag in self.phase_flags[..self.n_cpl_bands].iter_mut() {
+                *flag = self.phase_flags_in_use && bs.read_bool()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_exponents(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        ch: usize,
+        group_size: usize,
+        n_groups: usize,
+    ) -> Result<()> {
+        let exps = &mut self.exps[ch];
+        let start = self.start_freq[ch];
+
+        let abs_exp = bs.read_bits_leq32(4)? as i32;
+
+        // The absolute exponent of the coupling channel is not the exponent of a transform
+        // coefficient, but only a reference for the first differential exponent.
+        let (mut prev, mut bin) = if ch == CPL_CH {
+            (abs_exp << 1, start)
+        }
+        else {
+            exps[start] = abs_exp as u8;
+            (abs_exp, start + 1)
+        };
+
+        for _ in 0..n_groups {
+            let code = bs.read_bits_leq32(7)?;
+
+            if code > 124 {
+                return decode_error("ac3: invalid exponent group");
+            }
+
+            for &delta in &[code / 25, (code / 5) % 5, code % 5] {
+                prev += delta as i32 - 2;
+
+                if prev < 0 || prev > 24 {
+                    return decode_error("ac3: exponent out-of-range");
+                }
+
+                if bin + group_size > exps.len() {
+                    return decode_error("ac3: too many exponents");
+                }
+
+                exps[bin..bin + group_size].iter_mut().for_each(|exp| *exp = prev as u8);
+
+                bin += group_size;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the mantissas of all channels in the current block, and computes the transform
+    /// coefficients.
+    fn read_mantissas(&mut self, bs: &mut BitReaderLtr<'_>) -> Result<()> {
+        let n_fbw = self.n_fbw;
+
+        let mut groups = MantissaGroups::default();
+        let mut got_cpl = !self.cpl_in_use;
+
+        for ch in 1..=self.n_channels() {
+            let dither = ch <= n_fbw && self.dither_flag[ch];
+
+            self.read_channel_mantissas(bs, &mut groups, ch, dither)?;
+
+            // The mantissas of the coupling channel follow those of the first coupled channel.
+            if !got_cpl && ch <= n_fbw && self.channel_in_cpl[ch] {
+                self.read_channel_mantissas(bs, &mut groups, CPL_CH, true)?;
+                got_cpl = true;
+            }
+        }
+
+        // Uncouple the coupled channels.
+        if self.cpl_in_use {
+            for ch in 1..=n_fbw {
+                if !self.channel_in_cpl[ch] {
+                    continue;
+                }
+
+                let mut bin = self.start_freq[CPL_CH];
+
+                for bnd in 0..self.n_cpl_bands {
+                    let mut coord = self.cpl_coords[ch][bnd];
+
+                    if ch == 2 && self.phase_flags_in_use && self.phase_flags[bnd] {
+                        coord = -coord;
+                    }
+
+                    for _ in 0..self.cpl_band_sizes[bnd] {
+                        // Dither must be removed from channels that do not use it.
+                        self.coeffs[ch][bin] =
+                            if !self.dither_flag[ch] && self.bap[CPL_CH][bin] == 0 {
+                                0.0
+                            }
+                            else {
+                                coord * self.coeffs[CPL_CH][bin]
+                            };
+
+                        bin += 1;
+                    }
+                }
+            }
+        }
+
+        // Clear the transform coefficients above the bandwidth of each channel.
+        for ch in 1..=self.n_channels() {
+            let end = if ch <= n_fbw && self.channel_in_cpl[ch] {
+                self.end_freq[CPL_CH]
+            }
+            else {
+                self.end_freq[ch]
+            };
+
+            self.coeffs[ch][end..].iter_mut().for_each(|coeff| *coeff = 0.0);
+        }
+
+        Ok(())
+    }
+
+    fn read_channel_mantissas(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        groups: &mut MantissaGroups,
+        ch: usize,
+        dither: bool,
+    ) -> Result<()> {
+        for bin in self.start_freq[ch]..self.end_freq[ch] {
+            let bap = self.bap[ch][bin];
+
+            let mant = groups.read(bs, bap, if dither { Some(&mut self.lcg) } else { None })?;
+
+            self.coeffs[ch][bin] = mant / (1u32 << self.exps[ch][bin]) as f32;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the dynamic range gain of program `i`.
+    fn dynamic_range_gain(&self, i: usize, drc: DynamicRange) -> f32 {
+        match drc {
+            DynamicRange::Off => 1.0,
+            DynamicRange::Line { cut, boost } => {
+                let gain = dynrng_gain(self.dynrng[i]);
+
+                if gain < 1.0 {
+                    gain.powf(cut)
+                }
+                else {
+                    gain.powf(boost)
+                }
+            }
+            DynamicRange::Rf => match self.compr[i] {
+                Some(compr) => compr_gain(compr),
+                None => dynrng_gain(self.dynrng[i]),
+            },
+        }
+    }
+
+    /// Applies spectral extension by copying low frequency transform coefficients to the high
+    /// frequency bands, and blending them with noise.
+    fn apply_spx(&mut self) {
+        let mut wrap_flags = [false; MAX_SPX_BANDS];
+
+        wrap_flags[0] = true;
+
+        for ch in 1..=self.n_fbw {
+            if !self.channel_uses_spx[ch] {
+                continue;
+            }
+
+            let coeffs = &mut self.coeffs[ch];
+
+            // Copy the transform coefficients. If a band does not fit in the remainder of the copy
+            // region, the copy wraps back to the start of the region.
+            let mut src = self.spx_copy_start;
+            let mut dst = self.spx_begin;
+
+            for (wrap, &band_size) in
+                wrap_flags.iter_mut().zip(&self.spx_band_sizes[..self.n_spx_bands])
+            {
+                if src + band_size > self.spx_begin {
+                    src = self.spx_copy_start;
+                    *wrap = true;
+                }
+
+                for _ in 0..band_size {
+                    if src == self.spx_begin {
+                        src = self.spx_copy_start;
+                    }
+
+                    coeffs[dst] = coeffs[src];
+
+                    src += 1;
+                    dst += 1;
+                }
+            }
+
+            // Compute the RMS energy of each band.
+            let mut rms_energy = [0.0; MAX_SPX_BANDS];
+            let mut bin = self.spx_begin;
+
+            for (rms, &band_size) in
+                rms_energy.iter_mut().zip(&self.spx_band_sizes[..self.n_spx_bands])
+            {
+                let sum: f32 = coeffs[bin..bin + band_size].iter().map(|c| c * c).sum();
+
+                *rms = (sum / band_size as f32).sqrt();
+
+                bin += band_size;
+            }
+
+            // Attenuate the transform coefficients at the wrap points with a notch filter.
+            if let Some(code) = self.spx_atten_code[ch] {
+                let atten = [
+                    2f32.powf(-f32::from(code + 1) / 15.0),
+                    2f32.powf(-2.0 * f32::from(code + 1) / 15.0),
+                    2f32.powf(-3.0 * f32::from(code + 1) / 15.0),
+                ];
+
+                let mut bin = self.spx_begin - 2;
+
+                for (&wrap, &band_size) in
+                    wrap_flags.iter().zip(&self.spx_band_sizes[..self.n_spx_bands])
+                {
+                    if wrap {
+                        coeffs[bin] *= atten[0];
+                        coeffs[bin + 1] *= atten[1];
+                        coeffs[bin + 2] *= atten[2];
+                        coeffs[bin + 3] *= atten[1];
+                        coeffs[bin + 4] *= atten[0];
+                    }
+
+                    bin += band_size;
+                }
+            }
+
+            // Scale the transform coefficients, and blend them with noise.
+            let mut bin = self.spx_begin;
+
+            for bnd in 0..self.n_spx_bands {
+                let noise_scale = self.spx_noise_blend[ch][bnd] * rms_energy[bnd];
+                let signal_scale = self.spx_signal_blend[ch][bnd];
+
+                for coeff in coeffs[bin..bin + self.spx_band_sizes[bnd]].iter_mut() {
+                    *coeff = *coeff * signal_scale + noise_scale * self.lcg.next();
+                }
+
+                bin += self.spx_band_sizes[bnd];
+            }
+        }
+    }
+
+    /// Synthesizes the samples of block `blk` from the transform coefficients.
+    fn synthesize(&mut self, blk: usize, drc: DynamicRange, transform: &mut Transform) {
+        let n_fbw = self.n_fbw;
+        let n_channels = self.n_channels();
+
+        // Rematrixing.
+        if self.acmod == 0x2 {
+            let end = self.end_freq[1].min(self.end_freq[2]);
+
+            for bnd in 0..self.n_rematrix_bands {
+                if !self.rematrix_flags[bnd] {
+                    continue;
+                }
+
+                let start = REMATRIX_BAND_START[bnd];
+                let stop = REMATRIX_BAND_START[bnd + 1].min(end);
+
+                for bin in start..stop {
+                    let l = self.coeffs[1][bin];
+                    let r = self.coeffs[2][bin];
+
+                    self.coeffs[1][bin] = l + r;
+                    self.coeffs[2][bin] = l - r;
+                }
+            }
+        }
+
+        // Dynamic range compression. In dual-mono streams, the second channel is the second
+        // program.
+        for ch in 1..=n_channels {
+            let program = if self.acmod == 0 && ch == 2 { 1 } else { 0 };
+
+            let gain = self.dynamic_range_gain(program, drc);
+
+            if gain != 1.0 {
+                self.coeffs[ch].iter_mut().for_each(|coeff| *coeff *= gain);
+            }
+        }
+
+        if self.spx_in_use {
+            self.apply_spx();
+        }
+
+        for ch in 1..=n_channels {
+            // The low-frequency effects channel never uses block switching.
+            let is_short = ch <= n_fbw && self.block_switch[ch];
+
+            let out = &mut self.samples[ch - 1][blk * BLOCK_LEN..(blk + 1) * BLOCK_LEN];
+
+            transform.process(&self.coeffs[ch], is_short, &mut self.delay[ch], out);
+        }
+    }
+}
+
+/// Reads the additional bitstream information.
+fn read_addbsi(bs: &mut BitReaderLtr<'_>) -> Result<()> {
+    if bs.read_bool()? {
+        let len = bs.read_bits_leq32(6)? + 1;
+        bs.ignore_bits(8 * len)?;
+    }
+    Ok(())
+}
+
+/// Reads the band structure of the sub-bands `start..end`. E-AC-3 frames may omit the band
+/// structure, in which case the previous band structure is reused, or in the first block, the
+/// default band structure is used.
+fn read_band_struct(
+    bs: &mut BitReaderLtr<'_>,
+    is_eac3: bool,
+    blk: usize,
+    default: &[bool],
+    band_struct: &mut [bool],
+    start: usize,
+    end: usize,
+) -> Result<()> {
+    if !is_eac3 || bs.read_bool()? {
+        for flag in band_struct[start + 1..end].iter_mut() {
+            *flag = bs.read_bool()?;
+        }
+    }
+    else if blk == 0 {
+        band_struct.copy_from_slice(default);
+    }
+    Ok(())
+}
+
+/// Gets the gain of a dynamic range gain word.
+fn dynrng_gain(code: u8) -> f32 {
+    // The 3 most significant bits are a signed exponent, and the remaining bits are the fraction
+    // of a mantissa in the range [1, 2).
+    let exp = i32::from(code as i8 >> 5);
+    2f32.powi(exp) * f32::from(32 + (code & 0x1f)) / 32.0
+}
+
+/// Gets the gain of a compression gain word.
+fn compr_gain(code: u8) -> f32 {
+    // The 4 most significant bits are a signed exponent, and the remaining bits are the fraction
+    // of a mantissa in the range [1, 2).
+    let exp = i32::from(code as i8 >> 4);
+    2f32.powi(exp) * f32::from(16 + (code & 0xf)) / 16.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_frame_exp_strategies() {
+        // A selection of rows from ATSC A/52 Table E2.14.
+        let r = EXP_REUSE;
+
+        assert_eq!(frame_exp_strategies(0), [EXP_D15, r, r, r, r, r]);
+        assert_eq!(frame_exp_strategies(1), [EXP_D15, r, r, r, r, EXP_D45]);
+        assert_eq!(frame_exp_strategies(6), [EXP_D25, r, r, EXP_D45, EXP_D25, r]);
+        assert_eq!(frame_exp_strategies(8), [EXP_D25, r, EXP_D15, r, r, r]);
+        assert_eq!(frame_exp_strategies(16), [EXP_D45, EXP_D15, r, r, r, r]);
+        assert_eq!(frame_exp_strategies(25), [EXP_D45, EXP_D45, EXP_D25, r, r, EXP_D45]);
+        assert_eq!(frame_exp_strategies(31), [EXP_D45; MAX_BLOCKS]);
+    }
+}
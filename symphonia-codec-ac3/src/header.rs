@@ -0,0 +1,241 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, ReadBitsLtr};
+
+use crate::tables::{AC3_BIT_RATES, SAMPLE_RATES};
+
+/// The sync word that starts every AC-3 and E-AC-3 frame.
+pub const SYNC_WORD: u16 = 0x0b77;
+
+/// The number of bytes at the start of a frame required to parse the frame header.
+pub const FRAME_HEADER_LEN: usize = 12;
+
+/// The number of samples per channel in an audio block.
+pub const BLOCK_LEN: usize = 256;
+
+/// The maximum number of audio blocks in a frame.
+pub const MAX_BLOCKS: usize = 6;
+
+/// The number of full-bandwidth channels for each audio coding mode.
+const ACMOD_N_FBW: [usize; 8] = [2, 1, 2, 3, 3, 4, 4, 5];
+
+/// The type of a frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StreamType {
+    /// An independent AC-3 or E-AC-3 frame.
+    Independent,
+    /// An E-AC-3 frame extending the preceeding independent frame with more channels.
+    Dependent,
+    /// An E-AC-3 frame converted from an AC-3 stream.
+    Ac3Convert,
+}
+
+/// The fixed fields at the start of a frame.
+#[derive(Clone, Debug)]
+pub struct FrameHeader {
+    /// The bitstream identification. Values of 10 and below are AC-3, while values between 11 and
+    /// 16 are E-AC-3.
+    pub bsid: u8,
+    /// The stream type.
+    pub stream_type: StreamType,
+    /// The substream identifier. Always 0 for AC-3.
+    pub substream_id: u8,
+    /// The length of the frame in bytes, including the sync word.
+    pub frame_len: usize,
+    /// The sample rate.
+    pub sample_rate: u32,
+    /// The sample rate code used for bit allocation.
+    pub sr_code: usize,
+    /// The bit allocation shift for reduced sample rate AC-3 streams.
+    pub sr_shift: u32,
+    /// The number of audio blocks in the frame.
+    pub n_blocks: usize,
+    /// The audio coding mode.
+    pub acmod: u8,
+    /// The low-frequency effects channel is present.
+    pub lfe: bool,
+    /// The custom channel map of a dependent E-AC-3 substream.
+    pub chanmap: Option<u16>,
+}
+
+impl FrameHeader {
+    /// Parses the header from the first `FRAME_HEADER_LEN` bytes of a frame.
+    pub fn parse(buf: &[u8]) -> Result<FrameHeader> {
+        if buf.len() < FRAME_HEADER_LEN {
+            return decode_error("ac3: frame header is too short");
+        }
+
+        if u16::from_be_bytes([buf[0], buf[1]]) != SYNC_WORD {
+            return decode_error("ac3: missing sync word");
+        }
+
+        // The bitstream identification is at the same position in both AC-3 and E-AC-3 frames.
+        match buf[5] >> 3 {
+            0..=10 => FrameHeader::parse_ac3(buf),
+            11..=16 => FrameHeader::parse_eac3(buf),
+            _ => unsupported_error("ac3: unsupported bitstream identification"),
+        }
+    }
+
+    fn parse_ac3(buf: &[u8]) -> Result<FrameHeader> {
+        let mut bs = BitReaderLtr::new(&buf[4..]);
+
+        let fscod = bs.read_bits_leq32(2)? as usize;
+        let frmsizecod = bs.read_bits_leq32(6)? as usize;
+
+        let bsid = bs.read_bits_leq32(5)? as u8;
+        let _bsmod = bs.read_bits_leq32(3)?;
+        let acmod = bs.read_bits_leq32(3)? as u8;
+
+        if fscod == 3 {
+            return decode_error("ac3: invalid sample rate code");
+        }
+
+        if frmsizecod >= 2 * AC3_BIT_RATES.len() {
+            return decode_error("ac3: invalid frame size code");
+        }
+
+        // Skip the centre mix level, surround mix level, and Dolby Surround mode.
+        if acmod & 0x1 != 0 && acmod != 0x1 {
+            bs.ignore_bits(2)?;
+        }
+        if acmod & 0x4 != 0 {
+            bs.ignore_bits(2)?;
+        }
+        if acmod == 0x2 {
+            bs.ignore_bits(2)?;
+        }
+
+        let lfe = bs.read_bool()?;
+
+        // The frame size in 16-bit words. At 44.1 kHz, frames are padded by a word when the least
+        // significant bit of the frame size code is set to maintain the nominal bit rate.
+        let bit_rate = AC3_BIT_RATES[frmsizecod >> 1] as usize;
+
+        let n_words = match fscod {
+            0 => 2 * bit_rate,
+            1 => (320 * bit_rate) / 147 + (frmsizecod & 0x1),
+            _ => 3 * bit_rate,
+        };
+
+        // Bitstream identifications 9 and 10 indicate half and quarter sample rate streams.
+        let sr_shift = u32::from(bsid.max(8) - 8);
+
+        Ok(FrameHeader {
+            bsid,
+            stream_type: StreamType::Independent,
+            substream_id: 0,
+            frame_len: 2 * n_words,
+            sample_rate: SAMPLE_RATES[fscod] >> sr_shift,
+            sr_code: fscod,
+            sr_shift,
+            n_blocks: MAX_BLOCKS,
+            acmod,
+            lfe,
+            chanmap: None,
+        })
+    }
+
+    fn parse_eac3(buf: &[u8]) -> Result<FrameHeader> {
+        let mut bs = BitReaderLtr::new(&buf[2..]);
+
+        let stream_type = match bs.read_bits_leq32(2)? {
+            0 => StreamType::Independent,
+            1 => StreamType::Dependent,
+            2 => StreamType::Ac3Convert,
+            _ => return decode_error("ac3: invalid stream type"),
+        };
+
+        let substream_id = bs.read_bits_leq32(3)? as u8;
+        let frmsiz = bs.read_bits_leq32(11)? as usize;
+
+        let fscod = bs.read_bits_leq32(2)? as usize;
+
+        // A sample rate code of 3 indicates a reduced sample rate, and 6 blocks per frame.
+        let (sample_rate, sr_code, sr_shift, n_blocks) = if fscod == 3 {
+            let fscod2 = bs.read_bits_leq32(2)? as usize;
+
+            if fscod2 == 3 {
+                return decode_error("ac3: invalid reduced sample rate code");
+            }
+
+            (SAMPLE_RATES[fscod2] / 2, fscod2, 1, MAX_BLOCKS)
+        }
+        else {
+            let n_blocks = [1, 2, 3, 6][bs.read_bits_leq32(2)? as usize];
+            (SAMPLE_RATES[fscod], fscod, 0, n_blocks)
+        };
+
+        let acmod = bs.read_bits_leq32(3)? as u8;
+        let lfe = bs.read_bool()?;
+        let bsid = bs.read_bits_leq32(5)? as u8;
+
+        // The frame must be at-least large enough to contain the header.
+        if frmsiz < 2 {
+            return decode_error("ac3: invalid frame size");
+        }
+
+        // Skip the dialogue normalization and compression gain of each program.
+        for _ in 0..if acmod == 0 { 2 } else { 1 } {
+            bs.ignore_bits(5)?;
+
+            if bs.read_bool()? {
+                bs.ignore_bits(8)?;
+            }
+        }
+
+        let chanmap = if stream_type == StreamType::Dependent && bs.read_bool()? {
+            Some(bs.read_bits_leq32(16)? as u16)
+        }
+        else {
+            None
+        };
+
+        Ok(FrameHeader {
+            bsid,
+            stream_type,
+            substream_id,
+            frame_len: 2 * (frmsiz + 1),
+            sample_rate,
+            sr_code,
+            sr_shift,
+            n_blocks,
+            acmod,
+            lfe,
+            chanmap,
+        })
+    }
+
+    /// Returns `true` if the frame is an E-AC-3 frame.
+    pub fn is_eac3(&self) -> bool {
+        self.bsid > 10
+    }
+
+    /// Returns `true` if the frame starts a new packet. A packet contains the frame of the first
+    /// independent substream, and the frames of all other substreams up-to the next frame of the
+    /// first independent substream.
+    pub fn starts_packet(&self) -> bool {
+        self.stream_type != StreamType::Dependent && self.substream_id == 0
+    }
+
+    /// Gets the number of full-bandwidth channels.
+    pub fn n_fbw_channels(&self) -> usize {
+        ACMOD_N_FBW[usize::from(self.acmod)]
+    }
+
+    /// Gets the total number of channels, including the low-frequency effects channel.
+    pub fn n_channels(&self) -> usize {
+        self.n_fbw_channels() + usize::from(self.lfe)
+    }
+
+    /// Gets the number of samples per channel in the frame.
+    pub fn n_samples(&self) -> usize {
+        BLOCK_LEN * self.n_blocks
+    }
+}
@@ -0,0 +1,203 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+
+use log::warn;
+
+use crate::header::FrameHeader;
+
+/// The location of a channel as signalled by an E-AC-3 channel map.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    L,
+    C,
+    R,
+    Ls,
+    Rs,
+    Lc,
+    Rc,
+    Lrs,
+    Rrs,
+    Cs,
+    Ts,
+    Lsd,
+    Rsd,
+    Lw,
+    Rw,
+    Vhl,
+    Vhr,
+    Vhc,
+    Lts,
+    Rts,
+    Lfe2,
+    Lfe,
+}
+
+/// The channel locations of each bit of an E-AC-3 channel map, most-significant bit first.
+const CHANMAP_LOCATIONS: [&[Location]; 16] = [
+    &[Location::L],
+    &[Location::C],
+    &[Location::R],
+    &[Location::Ls],
+    &[Location::Rs],
+    &[Location::Lc, Location::Rc],
+    &[Location::Lrs, Location::Rrs],
+    &[Location::Cs],
+    &[Location::Ts],
+    &[Location::Lsd, Location::Rsd],
+    &[Location::Lw, Location::Rw],
+    &[Location::Vhl, Location::Vhr],
+    &[Location::Vhc],
+    &[Location::Lts, Location::Rts],
+    &[Location::Lfe2],
+    &[Location::Lfe],
+];
+
+/// The full-bandwidth channel locations of each audio coding mode.
+const ACMOD_LOCATIONS: [&[Location]; 8] = [
+    &[Location::L, Location::R],
+    &[Location::C],
+    &[Location::L, Location::R],
+    &[Location::L, Location::C, Location::R],
+    &[Location::L, Location::R, Location::Cs],
+    &[Location::L, Location::C, Location::R, Location::Cs],
+    &[Location::L, Location::R, Location::Ls, Location::Rs],
+    &[Location::L, Location::C, Location::R, Location::Ls, Location::Rs],
+];
+
+impl Location {
+    pub fn to_channel(self, has_rear: bool) -> Channels {
+        match self {
+            Location::L => Channels::FRONT_LEFT,
+            Location::C => Channels::FRONT_CENTRE,
+            Location::R => Channels::FRONT_RIGHT,
+            // The surround channels are side channels if there are also rear surround channels.
+            Location::Ls if has_rear => Channels::SIDE_LEFT,
+            Location::Rs if has_rear => Channels::SIDE_RIGHT,
+            Location::Ls => Channels::REAR_LEFT,
+            Location::Rs => Channels::REAR_RIGHT,
+            Location::Lc => Channels::FRONT_LEFT_CENTRE,
+            Location::Rc => Channels::FRONT_RIGHT_CENTRE,
+            Location::Lrs => Channels::REAR_LEFT,
+            Location::Rrs => Channels::REAR_RIGHT,
+            Location::Cs => Channels::REAR_CENTRE,
+            Location::Ts => Channels::TOP_CENTRE,
+            Location::Lsd => Channels::REAR_LEFT_CENTRE,
+            Location::Rsd => Channels::REAR_RIGHT_CENTRE,
+            Location::Lw => Channels::FRONT_LEFT_WIDE,
+            Location::Rw => Channels::FRONT_RIGHT_WIDE,
+            Location::Vhl => Channels::TOP_FRONT_LEFT,
+            Location::Vhr => Channels::TOP_FRONT_RIGHT,
+            Location::Vhc => Channels::TOP_FRONT_CENTRE,
+            Location::Lts => Channels::TOP_REAR_LEFT,
+            Location::Rts => Channels::TOP_REAR_RIGHT,
+            Location::Lfe2 => Channels::LFE2,
+            Location::Lfe => Channels::LFE1,
+        }
+    }
+
+    /// Gets the left and right stereo downmix gains.
+    pub fn downmix_gains(self, clev: f32, slev: f32) -> (f32, f32) {
+        const M3DB: f32 = 0.707_106_77;
+
+        match self {
+            Location::L => (1.0, 0.0),
+            Location::R => (0.0, 1.0),
+            Location::Lc | Location::Lw | Location::Vhl => (M3DB, 0.0),
+            Location::Rc | Location::Rw | Location::Vhr => (0.0, M3DB),
+            Location::C | Location::Vhc | Location::Ts => (clev, clev),
+            Location::Ls | Location::Lrs | Location::Lsd | Location::Lts => (slev, 0.0),
+            Location::Rs | Location::Rrs | Location::Rsd | Location::Rts => (0.0, slev),
+            Location::Cs => (M3DB * slev, M3DB * slev),
+            Location::Lfe | Location::Lfe2 => (0.0, 0.0),
+        }
+    }
+}
+
+/// Gets the location of each channel in a substream.
+pub fn substream_locations(header: &FrameHeader) -> Vec<Location> {
+    // A dependent substream may carry a custom channel map. It is only used if it is consistent
+    // with the number of channels in the substream.
+    if let Some(chanmap) = header.chanmap {
+        let locations: Vec<Location> = (0..16)
+            .filter(|i| (chanmap >> (15 - i)) & 0x1 != 0)
+            .flat_map(|i| CHANMAP_LOCATIONS[i].iter().copied())
+            .collect();
+
+        if locations.len() == header.n_channels() {
+            return locations;
+        }
+
+        warn!("ac3: channel map does not match the number of channels");
+    }
+
+    let mut locations = ACMOD_LOCATIONS[usize::from(header.acmod)].to_vec();
+
+    if header.lfe {
+        locations.push(Location::Lfe);
+    }
+
+    locations
+}
+
+/// A channel of the combined output of all substreams.
+#[derive(Copy, Clone, Debug)]
+pub struct OutputChannel {
+    /// The location of the channel.
+    pub location: Location,
+    /// The channel.
+    pub channel: Channels,
+    /// The index of the substream carrying the channel.
+    pub substream: usize,
+    /// The index of the channel within the substream.
+    pub index: usize,
+}
+
+/// Combines the channels of the independent substream, and its dependent substreams, into a single
+/// layout ordered by channel. Channels of dependent substreams replace the channels of the
+/// independent substream with the same location.
+pub fn output_layout<'a, I>(headers: I) -> Vec<OutputChannel>
+where
+    I: Iterator<Item = (usize, &'a FrameHeader)>,
+{
+    let mut layout: Vec<OutputChannel> = Vec::new();
+
+    for (substream, header) in headers {
+        for (index, location) in substream_locations(header).into_iter().enumerate() {
+            let output = OutputChannel { location, channel: Channels::empty(), substream, index };
+
+            match layout.iter_mut().find(|out| out.location == location) {
+                Some(out) => *out = output,
+                None => layout.push(output),
+            }
+        }
+    }
+
+    let has_rear = layout.iter().any(|out| matches!(out.location, Location::Lrs | Location::Rrs));
+
+    for out in layout.iter_mut() {
+        out.channel = out.location.to_channel(has_rear);
+    }
+
+    // By convention, a mono stream uses the front-left channel.
+    if let [out] = &mut layout[..] {
+        if out.location == Location::C {
+            out.channel = Channels::FRONT_LEFT;
+        }
+    }
+
+    // Audio planes are ordered by channel.
+    layout.sort_by_key(|out| out.channel.bits());
+
+    layout
+}
+
+/// Gets the channels of a layout.
+pub fn layout_channels(layout: &[OutputChannel]) -> Channels {
+    layout.iter().fold(Channels::empty(), |acc, out| acc | out.channel)
+}
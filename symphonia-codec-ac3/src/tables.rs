@@ -0,0 +1,142 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tables from ATSC A/52.
+
+/// The number of bit allocation bands.
+pub const N_BANDS: usize = 50;
+
+/// The first transform coefficient (bin) of each bit allocation band, and the end of the last band.
+#[rustfmt::skip]
+pub const BAND_START: [u8; N_BANDS + 1] = [
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,
+     16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,  27,  28,  31,  34,  37,
+     40,  43,  46,  49,  55,  61,  67,  73,  79,  85,  97, 109, 121, 133, 157, 181,
+    205, 229, 253,
+];
+
+/// Log-addition table used to integrate the power spectral density over a band.
+#[rustfmt::skip]
+pub const LOG_ADD: [u16; 256] = [
+    0x0040, 0x003f, 0x003e, 0x003d, 0x003c, 0x003b, 0x003a, 0x0039,
+    0x0038, 0x0037, 0x0036, 0x0035, 0x0034, 0x0034, 0x0033, 0x0032,
+    0x0031, 0x0030, 0x002f, 0x002f, 0x002e, 0x002d, 0x002c, 0x002c,
+    0x002b, 0x002a, 0x0029, 0x0029, 0x0028, 0x0027, 0x0026, 0x0026,
+    0x0025, 0x0024, 0x0024, 0x0023, 0x0023, 0x0022, 0x0021, 0x0021,
+    0x0020, 0x0020, 0x001f, 0x001e, 0x001e, 0x001d, 0x001d, 0x001c,
+    0x001c, 0x001b, 0x001b, 0x001a, 0x001a, 0x0019, 0x0019, 0x0018,
+    0x0018, 0x0017, 0x0017, 0x0016, 0x0016, 0x0015, 0x0015, 0x0015,
+    0x0014, 0x0014, 0x0013, 0x0013, 0x0013, 0x0012, 0x0012, 0x0012,
+    0x0011, 0x0011, 0x0011, 0x0010, 0x0010, 0x0010, 0x000f, 0x000f,
+    0x000f, 0x000e, 0x000e, 0x000e, 0x000d, 0x000d, 0x000d, 0x000d,
+    0x000c, 0x000c, 0x000c, 0x000c, 0x000b, 0x000b, 0x000b, 0x000b,
+    0x000a, 0x000a, 0x000a, 0x000a, 0x000a, 0x0009, 0x0009, 0x0009,
+    0x0009, 0x0009, 0x0008, 0x0008, 0x0008, 0x0008, 0x0008, 0x0008,
+    0x0007, 0x0007, 0x0007, 0x0007, 0x0007, 0x0007, 0x0006, 0x0006,
+    0x0006, 0x0006, 0x0006, 0x0006, 0x0006, 0x0006, 0x0005, 0x0005,
+    0x0005, 0x0005, 0x0005, 0x0005, 0x0005, 0x0005, 0x0004, 0x0004,
+    0x0004, 0x0004, 0x0004, 0x0004, 0x0004, 0x0004, 0x0004, 0x0004,
+    0x0004, 0x0003, 0x0003, 0x0003, 0x0003, 0x0003, 0x0003, 0x0003,
+    0x0003, 0x0003, 0x0003, 0x0003, 0x0003, 0x0003, 0x0003, 0x0002,
+    0x0002, 0x0002, 0x0002, 0x0002, 0x0002, 0x0002, 0x0002, 0x0002,
+    0x0002, 0x0002, 0x0002, 0x0002, 0x0002, 0x0002, 0x0002, 0x0002,
+    0x0002, 0x0002, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001,
+    0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001,
+    0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001,
+    0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001,
+    0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+];
+
+/// The hearing threshold of each band, indexed by band and sample rate code.
+#[rustfmt::skip]
+pub const HEARING_THRESHOLD: [[u16; 3]; N_BANDS] = [
+    [0x04d0, 0x04f0, 0x0580], [0x04d0, 0x04f0, 0x0580], [0x0440, 0x0460, 0x04b0],
+    [0x0400, 0x0410, 0x0450], [0x03e0, 0x03e0, 0x0420], [0x03c0, 0x03d0, 0x03f0],
+    [0x03b0, 0x03c0, 0x03e0], [0x03b0, 0x03b0, 0x03d0], [0x03a0, 0x03b0, 0x03c0],
+    [0x03a0, 0x03a0, 0x03b0], [0x03a0, 0x03a0, 0x03b0], [0x03a0, 0x03a0, 0x03b0],
+    [0x03a0, 0x03a0, 0x03a0], [0x0390, 0x03a0, 0x03a0], [0x0390, 0x0390, 0x03a0],
+    [0x0390, 0x0390, 0x03a0], [0x0380, 0x0390, 0x03a0], [0x0380, 0x0380, 0x03a0],
+    [0x0370, 0x0380, 0x03a0], [0x0370, 0x0380, 0x03a0], [0x0360, 0x0370, 0x0390],
+    [0x0360, 0x0370, 0x0390], [0x0350, 0x0360, 0x0390], [0x0350, 0x0360, 0x0390],
+    [0x0340, 0x0350, 0x0380], [0x0340, 0x0350, 0x0380], [0x0330, 0x0340, 0x0380],
+    [0x0320, 0x0340, 0x0370], [0x0310, 0x0320, 0x0360], [0x0300, 0x0310, 0x0350],
+    [0x02f0, 0x0300, 0x0340], [0x02f0, 0x02f0, 0x0330], [0x02f0, 0x02f0, 0x0320],
+    [0x02f0, 0x02f0, 0x0310], [0x0300, 0x02f0, 0x0300], [0x0310, 0x0300, 0x02f0],
+    [0x0340, 0x0320, 0x02f0], [0x0390, 0x0350, 0x02f0], [0x03e0, 0x0390, 0x0300],
+    [0x0420, 0x03e0, 0x0310], [0x0460, 0x0420, 0x0330], [0x0490, 0x0450, 0x0350],
+    [0x04a0, 0x04a0, 0x03c0], [0x0460, 0x0490, 0x0410], [0x0440, 0x0460, 0x0470],
+    [0x0440, 0x0440, 0x04a0], [0x0520, 0x0480, 0x0460], [0x0800, 0x0630, 0x0440],
+    [0x0840, 0x0840, 0x0450], [0x0840, 0x0840, 0x04e0],
+];
+
+/// Bit allocation pointer table, indexed by the masked power spectral density.
+#[rustfmt::skip]
+pub const BAP: [u8; 64] = [
+     0,  1,  1,  1,  1,  1,  2,  2,  3,  3,  3,  4,  4,  5,  5,  6,
+     6,  6,  6,  7,  7,  7,  7,  8,  8,  8,  8,  9,  9,  9,  9, 10,
+    10, 10, 10, 11, 11, 11, 11, 12, 12, 12, 12, 13, 13, 13, 13, 14,
+    14, 14, 14, 14, 14, 14, 14, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+];
+
+/// Slow decay values, indexed by the slow decay code.
+pub const SLOW_DECAY: [u16; 4] = [0x0f, 0x11, 0x13, 0x15];
+
+/// Fast decay values, indexed by the fast decay code.
+pub const FAST_DECAY: [u16; 4] = [0x3f, 0x53, 0x67, 0x7b];
+
+/// Slow gain values, indexed by the slow gain code.
+pub const SLOW_GAIN: [u16; 4] = [0x540, 0x4d8, 0x478, 0x410];
+
+/// dB per bit values, indexed by the dB per bit code.
+pub const DB_PER_BIT: [u16; 4] = [0x000, 0x700, 0x900, 0xb00];
+
+/// Masking floor values, indexed by the masking floor code.
+pub const FLOOR: [i16; 8] = [0x2f0, 0x2b0, 0x270, 0x230, 0x1f0, 0x170, 0x0f0, -0x800];
+
+/// Fast gain values, indexed by the fast gain code.
+pub const FAST_GAIN: [u16; 8] = [0x080, 0x100, 0x180, 0x200, 0x280, 0x300, 0x380, 0x400];
+
+/// The number of bits per mantissa for bit allocation pointers 6 and above. Mantissas for smaller
+/// pointers are grouped, or quantized to a non-power-of-two number of levels.
+pub const MANTISSA_BITS: [u8; 16] = [0, 0, 0, 3, 0, 4, 5, 6, 7, 8, 9, 10, 11, 12, 14, 16];
+
+/// The start of each rematrixing band, and the end of the last band.
+pub const REMATRIX_BAND_START: [usize; 5] = [13, 25, 37, 61, 253];
+
+/// The nominal bit rate in kbit/s of an AC-3 frame, indexed by half of the frame size code.
+pub const AC3_BIT_RATES: [u32; 19] =
+    [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640];
+
+/// The sample rates indexed by the sample rate code.
+pub const SAMPLE_RATES: [u32; 3] = [48000, 44100, 32000];
+
+/// Centre mix levels, indexed by the 2-bit AC-3 centre mix level code.
+pub const CENTRE_MIX_LEVELS: [f32; 4] = [0.707_106_77, 0.594_603_55, 0.5, 0.594_603_55];
+
+/// Surround mix levels, indexed by the 2-bit AC-3 surround mix level code.
+pub const SURROUND_MIX_LEVELS: [f32; 4] = [0.707_106_77, 0.5, 0.0, 0.5];
+
+/// Mix levels, indexed by the 3-bit E-AC-3 Lo/Ro mix level codes.
+pub const EXT_MIX_LEVELS: [f32; 8] =
+    [std::f32::consts::SQRT_2, 1.189_207_1, 1.0, 0.840_896_4, 0.707_106_77, 0.594_603_55, 0.5, 0.0];
+
+/// The default E-AC-3 coupling band structure, indexed by sub-band.
+pub const DEFAULT_CPL_BAND_STRUCT: [bool; 18] = [
+    false, false, false, false, false, false, false, false, true, false, true, true, false, true,
+    true, true, true, true,
+];
+
+/// The default E-AC-3 spectral extension band structure, indexed by sub-band.
+pub const DEFAULT_SPX_BAND_STRUCT: [bool; 17] = [
+    false, false, false, false, false, false, false, true, false, true, false, true, false, true,
+    true, true, true,
+];
@@ -0,0 +1,141 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::f64::consts::PI;
+
+use symphonia_core::dsp::mdct::Imdct;
+
+use crate::header::BLOCK_LEN;
+
+/// The alpha parameter of the Kaiser-Bessel derived window.
+const KBD_ALPHA: f64 = 5.0;
+
+/// The inverse transform and windowing of an audio block.
+///
+/// A block is normally transformed by a single 512-sample transform. When block switching is
+/// used, the block is instead transformed by two 256-sample transforms to improve time resolution.
+/// In both cases the transformed samples are windowed and overlapped with the previous block.
+pub struct Transform {
+    imdct_long: Imdct,
+    imdct_short: Imdct,
+    window: [f32; 2 * BLOCK_LEN],
+    spec: [f32; BLOCK_LEN / 2],
+    long: [f32; 2 * BLOCK_LEN],
+    short: [f32; BLOCK_LEN],
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        // The forward transform is scaled by -2/N, therefore the inverse transform must be scaled
+        // by -2 for perfect reconstruction.
+        Transform {
+            imdct_long: Imdct::new_scaled(BLOCK_LEN, -2.0),
+            imdct_short: Imdct::new_scaled(BLOCK_LEN / 2, -2.0),
+            window: kbd_window(),
+            spec: [0.0; BLOCK_LEN / 2],
+            long: [0.0; 2 * BLOCK_LEN],
+            short: [0.0; BLOCK_LEN],
+        }
+    }
+
+    /// Transforms the coefficients of a block, and overlaps the result with the delayed samples of
+    /// the previous block. The delayed samples are replaced with those of the current block.
+    pub fn process(
+        &mut self,
+        coeffs: &[f32; BLOCK_LEN],
+        is_short: bool,
+        delay: &mut [f32; BLOCK_LEN],
+        out: &mut [f32],
+    ) {
+        let (first, second) = self.window.split_at(BLOCK_LEN);
+
+        if !is_short {
+            self.imdct_long.imdct(coeffs, &mut self.long);
+
+            let (long0, long1) = self.long.split_at(BLOCK_LEN);
+
+            for i in 0..BLOCK_LEN {
+                out[i] = long0[i] * first[i] + delay[i];
+                delay[i] = long1[i] * second[i];
+            }
+        }
+        else {
+            const M: usize = BLOCK_LEN / 2;
+
+            // The even coefficients belong to the first transform, and the odd coefficients to the
+            // second. The short transforms use a different phase than a regular inverse MDCT, the
+            // first is shifted by -M/2 samples, and the second by +M/2 samples. The output of an
+            // inverse MDCT is anti-periodic with a period of 2M samples.
+            for (spec, coeff) in self.spec.iter_mut().zip(coeffs.iter().step_by(2)) {
+                *spec = *coeff;
+            }
+
+            self.imdct_short.imdct(&self.spec, &mut self.short);
+
+            for i in 0..2 * M {
+                let y = if i < M / 2 { -self.short[i + 3 * M / 2] } else { self.short[i - M / 2] };
+                out[i] = y * first[i] + delay[i];
+            }
+
+            for (spec, coeff) in self.spec.iter_mut().zip(coeffs.iter().skip(1).step_by(2)) {
+                *spec = *coeff;
+            }
+
+            self.imdct_short.imdct(&self.spec, &mut self.short);
+
+            for i in 0..2 * M {
+                let y =
+                    if i < 3 * M / 2 { self.short[i + M / 2] } else { -self.short[i - 3 * M / 2] };
+                delay[i] = y * second[i];
+            }
+        }
+    }
+}
+
+/// Generates the 512-sample Kaiser-Bessel derived window.
+fn kbd_window() -> [f32; 2 * BLOCK_LEN] {
+    let mut kernel = [0.0f64; BLOCK_LEN + 1];
+
+    let n = BLOCK_LEN as f64 / 2.0;
+
+    for (i, k) in kernel.iter_mut().enumerate() {
+        let x = (i as f64 - n) / n;
+        *k = bessel_i0(PI * KBD_ALPHA * (1.0 - x * x).sqrt());
+    }
+
+    let total: f64 = kernel.iter().sum();
+
+    let mut window = [0.0; 2 * BLOCK_LEN];
+    let mut sum = 0.0;
+
+    for i in 0..BLOCK_LEN {
+        sum += kernel[i];
+
+        let w = (sum / total).sqrt() as f32;
+
+        window[i] = w;
+        window[2 * BLOCK_LEN - 1 - i] = w;
+    }
+
+    window
+}
+
+/// Computes the zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+
+    // The series converges quickly for the arguments used by the window.
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
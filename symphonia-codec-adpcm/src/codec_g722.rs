@@ -0,0 +1,237 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::util::clamp::clamp_i16;
+
+use crate::common::from_i16_shift;
+
+#[rustfmt::skip]
+const LOW_INV_QUANT6: [i32; 64] = [
+    -17, -17, -17, -17, -3101, -2738, -2376, -2088,
+    -1873, -1689, -1535, -1399, -1279, -1170, -1072, -982,
+    -899, -822, -750, -682, -618, -558, -501, -447,
+    -396, -347, -300, -254, -211, -170, -130, -91,
+    3101, 2738, 2376, 2088, 1873, 1689, 1535, 1399,
+    1279, 1170, 1072, 982, 899, 822, 750, 682,
+    618, 558, 501, 447, 396, 347, 300, 254,
+    211, 170, 130, 91, 54, 17, -54, -17,
+];
+
+#[rustfmt::skip]
+const LOW_INV_QUANT4: [i32; 16] = [
+    0, -2557, -1612, -1121, -786, -530, -323, -150,
+    2557, 1612, 1121, 786, 530, 323, 150, 0,
+];
+
+#[rustfmt::skip]
+const LOW_LOG_FACTOR_STEP: [i32; 16] = [
+    -60, 3042, 1198, 538, 334, 172, 58, -30,
+    3042, 1198, 538, 334, 172, 58, -30, -60,
+];
+
+const HIGH_INV_QUANT: [i32; 4] = [-926, -202, 926, 202];
+
+const HIGH_LOG_FACTOR_STEP: [i32; 2] = [798, -214];
+
+#[rustfmt::skip]
+const INV_LOG2_TABLE: [i32; 32] = [
+    2048, 2093, 2139, 2186, 2233, 2282, 2332, 2383,
+    2435, 2489, 2543, 2599, 2656, 2714, 2774, 2834,
+    2896, 2960, 3025, 3091, 3158, 3228, 3298, 3371,
+    3444, 3520, 3597, 3676, 3756, 3838, 3922, 4008,
+];
+
+/// The even coefficients of the symmetric 24-tap receive quadrature mirror filter. The odd
+/// coefficients are the even coefficients in reverse.
+const QMF_COEFFS: [i32; 12] = [3, -11, 12, 32, -210, 951, 3876, -805, 362, -156, 53, -11];
+
+fn clip_14(value: i32) -> i32 {
+    value.clamp(-(1 << 14), (1 << 14) - 1)
+}
+
+fn linear_scale_factor(log_factor: i32) -> i32 {
+    let wd1 = INV_LOG2_TABLE[((log_factor >> 6) & 31) as usize];
+    let shift = log_factor >> 11;
+    if shift < 0 {
+        wd1 >> -shift
+    }
+    else {
+        wd1 << shift
+    }
+}
+
+/// The adaptive predictor and quantizer state of one sub-band.
+#[derive(Default)]
+struct G722Band {
+    s_predictor: i32,
+    s_zero: i32,
+    part_reconst_mem: [bool; 2],
+    prev_qtzd_reconst: i32,
+    pole_mem: [i32; 2],
+    diff_mem: [i32; 6],
+    zero_mem: [i32; 6],
+    log_factor: i32,
+    scale_factor: i32,
+}
+
+impl G722Band {
+    fn new(scale_factor: i32) -> Self {
+        G722Band { scale_factor, ..Default::default() }
+    }
+
+    fn do_adaptive_prediction(&mut self, cur_diff: i32) {
+        let cur_part_reconst = self.s_zero + cur_diff < 0;
+
+        let sg0 = if cur_part_reconst != self.part_reconst_mem[0] { 1 } else { -1 };
+        let sg1 = if cur_part_reconst == self.part_reconst_mem[1] { 1 } else { -1 };
+        self.part_reconst_mem[1] = self.part_reconst_mem[0];
+        self.part_reconst_mem[0] = cur_part_reconst;
+
+        // Update the pole section.
+        self.pole_mem[1] = (((sg0 * self.pole_mem[0].clamp(-8191, 8191)) >> 5)
+            + (sg1 * 128)
+            + ((self.pole_mem[1] * 127) >> 7))
+            .clamp(-12288, 12288);
+
+        let limit = 15360 - self.pole_mem[1];
+        self.pole_mem[0] = (-192 * sg0 + ((self.pole_mem[0] * 255) >> 8)).clamp(-limit, limit);
+
+        // Update the zero section.
+        let mut s_zero = 0;
+
+        for k in (0..6).rev() {
+            let tmp = if k > 0 { self.diff_mem[k - 1] } else { cur_diff * 2 };
+
+            self.zero_mem[k] = (self.zero_mem[k] * 255) >> 8;
+
+            if cur_diff != 0 {
+                self.zero_mem[k] += if (self.diff_mem[k] ^ cur_diff) < 0 { -128 } else { 128 };
+            }
+
+            self.diff_mem[k] = tmp;
+            s_zero += (tmp * self.zero_mem[k]) >> 15;
+        }
+
+        self.s_zero = s_zero;
+
+        let cur_qtzd_reconst = i32::from(clamp_i16((self.s_predictor + cur_diff) * 2));
+
+        self.s_predictor = i32::from(clamp_i16(
+            self.s_zero
+                + ((self.pole_mem[0] * cur_qtzd_reconst) >> 15)
+                + ((self.pole_mem[1] * self.prev_qtzd_reconst) >> 15),
+        ));
+
+        self.prev_qtzd_reconst = cur_qtzd_reconst;
+    }
+
+    fn update_low(&mut self, ilow: usize) {
+        self.do_adaptive_prediction((self.scale_factor * LOW_INV_QUANT4[ilow]) >> 10);
+
+        self.log_factor =
+            (((self.log_factor * 127) >> 7) + LOW_LOG_FACTOR_STEP[ilow]).clamp(0, 18432);
+        self.scale_factor = linear_scale_factor(self.log_factor - (8 << 11));
+    }
+
+    fn update_high(&mut self, dhigh: i32, ihigh: usize) {
+        self.do_adaptive_prediction(dhigh);
+
+        self.log_factor =
+            (((self.log_factor * 127) >> 7) + HIGH_LOG_FACTOR_STEP[ihigh & 1]).clamp(0, 22528);
+        self.scale_factor = linear_scale_factor(self.log_factor - (10 << 11));
+    }
+}
+
+/// The state of a 64 kbit/s G.722 decoder for one channel.
+pub(crate) struct G722State {
+    bands: [G722Band; 2],
+    /// The last 24 reconstructed sub-band sample pairs, oldest first.
+    prev_samples: [i32; 24],
+}
+
+impl G722State {
+    pub(crate) fn new() -> Self {
+        G722State { bands: [G722Band::new(8), G722Band::new(2)], prev_samples: [0; 24] }
+    }
+
+    /// Decode one code word to two 16-bit samples.
+    pub(crate) fn decode(&mut self, byte: u8, out: &mut [i32]) {
+        let ihigh = usize::from(byte >> 6);
+        let ilow = usize::from(byte & 0x3f);
+
+        // Lower sub-band.
+        let low = &mut self.bands[0];
+        let rlow = clip_14(((low.scale_factor * LOW_INV_QUANT6[ilow]) >> 10) + low.s_predictor);
+        low.update_low(ilow >> 2);
+
+        // Higher sub-band.
+        let high = &mut self.bands[1];
+        let dhigh = (high.scale_factor * HIGH_INV_QUANT[ihigh]) >> 10;
+        let rhigh = clip_14(dhigh + high.s_predictor);
+        high.update_high(dhigh, ihigh);
+
+        // Receive quadrature mirror filter.
+        self.prev_samples.copy_within(2.., 0);
+        self.prev_samples[22] = rlow + rhigh;
+        self.prev_samples[23] = rlow - rhigh;
+
+        let mut xout = [0; 2];
+
+        for (i, pair) in self.prev_samples.chunks_exact(2).enumerate() {
+            xout[1] += pair[0] * QMF_COEFFS[i];
+            xout[0] += pair[1] * QMF_COEFFS[11 - i];
+        }
+
+        out[0] = from_i16_shift!(clamp_i16(xout[0] >> 11));
+        out[1] = from_i16_shift!(clamp_i16(xout[1] >> 11));
+    }
+}
+
+/// Decode a stream of G.722 code words interleaved per channel.
+pub(crate) fn decode(buf: &[u8], states: &mut [G722State], out: &mut [&mut [i32]]) {
+    let n_channels = states.len();
+
+    for (i, frame) in buf.chunks_exact(n_channels).enumerate() {
+        let range = 2 * i..2 * (i + 1);
+
+        for ((state, out), &byte) in states.iter_mut().zip(out.iter_mut()).zip(frame) {
+            state.decode(byte, &mut out[range.clone()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::G722State;
+
+    #[rustfmt::skip]
+    const DECODED: [i16; 64] = [
+        -1, 0, 0, -1, -1, 1, -1, -3,
+        0, 6, -6, -28, -13, 4, 14, -13,
+        -18, -17, 14, 7, -33, -105, -40, 76,
+        61, -103, -92, -3, 73, 2, -86, -169,
+        118, 452, 19, -784, -394, 813, 684, -674,
+        -865, 116, 612, 71, -569, -676, 604, 2113,
+        684, -2352, -1866, 1585, 1877, -1286, -2079, 44,
+        1345, 418, -887, -1287, 670, 3290, 1445, -2936,
+    ];
+
+    #[test]
+    fn verify_g722_decode() {
+        let mut state = G722State::new();
+        let mut decoded = Vec::new();
+
+        // The code words `(37 * i + 11) mod 256` for `i` in `0..32`.
+        for i in 0..32 {
+            let mut out = [0; 2];
+            state.decode(((37 * i + 11) % 256) as u8, &mut out);
+            decoded.extend(out.iter().map(|&sample| (sample >> 16) as i16));
+        }
+
+        assert_eq!(decoded, DECODED);
+    }
+}
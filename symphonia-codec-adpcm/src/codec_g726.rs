@@ -0,0 +1,393 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, BitReaderRtl, ReadBitsLtr, ReadBitsRtl};
+use symphonia_core::util::clamp::clamp_i16;
+
+use crate::common::from_i16_shift;
+
+/// Quantization tables for a code size.
+struct G726Tables {
+    /// Inverse quantizer output, indexed by code word.
+    iquant: &'static [i16],
+    /// Scale factor multiplier, indexed by code word.
+    w: &'static [i16],
+    /// Rate of change of the scale factor, indexed by code word.
+    f: &'static [u8],
+}
+
+const IQUANT_16: [i16; 4] = [116, 365, 365, 116];
+const W_16: [i16; 4] = [-22, 439, 439, -22];
+const F_16: [u8; 4] = [0, 7, 7, 0];
+
+const IQUANT_24: [i16; 8] = [i16::MIN, 135, 273, 373, 373, 273, 135, i16::MIN];
+const W_24: [i16; 8] = [-4, 30, 137, 582, 582, 137, 30, -4];
+const F_24: [u8; 8] = [0, 1, 2, 7, 7, 2, 1, 0];
+
+#[rustfmt::skip]
+const IQUANT_32: [i16; 16] = [
+    i16::MIN, 4, 135, 213, 273, 323, 373, 425,
+    425, 373, 323, 273, 213, 135, 4, i16::MIN,
+];
+#[rustfmt::skip]
+const W_32: [i16; 16] = [
+    -12, 18, 41, 64, 112, 198, 355, 1122,
+    1122, 355, 198, 112, 64, 41, 18, -12,
+];
+const F_32: [u8; 16] = [0, 0, 0, 1, 1, 1, 3, 7, 7, 3, 1, 1, 1, 0, 0, 0];
+
+#[rustfmt::skip]
+const IQUANT_40: [i16; 32] = [
+    i16::MIN, -66, 28, 104, 169, 224, 274, 318,
+    358, 395, 429, 459, 488, 514, 539, 566,
+    566, 539, 514, 488, 459, 429, 395, 358,
+    318, 274, 224, 169, 104, 28, -66, i16::MIN,
+];
+#[rustfmt::skip]
+const W_40: [i16; 32] = [
+    14, 14, 24, 39, 40, 41, 58, 100,
+    141, 179, 219, 280, 358, 440, 529, 696,
+    696, 529, 440, 358, 280, 219, 179, 141,
+    100, 58, 41, 40, 39, 24, 14, 14,
+];
+#[rustfmt::skip]
+const F_40: [u8; 32] = [
+    0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 2, 3, 4, 5, 6, 6,
+    6, 6, 5, 4, 3, 2, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0,
+];
+
+const G726_TABLES: [G726Tables; 4] = [
+    G726Tables { iquant: &IQUANT_16, w: &W_16, f: &F_16 },
+    G726Tables { iquant: &IQUANT_24, w: &W_24, f: &F_24 },
+    G726Tables { iquant: &IQUANT_32, w: &W_32, f: &F_32 },
+    G726Tables { iquant: &IQUANT_40, w: &W_40, f: &F_40 },
+];
+
+/// The order in which code words are packed into bytes.
+#[derive(Copy, Clone)]
+pub(crate) enum BitOrder {
+    /// The first code word is packed into the most-significant bits of a byte.
+    BigEndian,
+    /// The first code word is packed into the least-significant bits of a byte.
+    LittleEndian,
+}
+
+/// A value in the floating-point format used by G.726: a sign, a 4-bit exponent, and a 6-bit
+/// mantissa.
+#[derive(Copy, Clone)]
+struct Float11 {
+    sign: bool,
+    exp: u8,
+    mant: u8,
+}
+
+impl Float11 {
+    fn new(value: i32) -> Self {
+        let sign = value < 0;
+        let mag = value.unsigned_abs();
+
+        let exp = 32 - mag.leading_zeros();
+        let mant = if mag != 0 { (mag << 6) >> exp } else { 1 << 5 };
+
+        Float11 { sign, exp: exp as u8, mant: mant as u8 }
+    }
+
+    /// Multiply two values. Like the reference, the product is truncated to 16 bits.
+    fn mul(&self, other: &Float11) -> i16 {
+        let exp = i32::from(self.exp) + i32::from(other.exp);
+        let res = (i32::from(self.mant) * i32::from(other.mant) + 0x30) >> 4;
+        let res = if exp > 19 { res << (exp - 19) } else { res >> (19 - exp) };
+        let res = if self.sign != other.sign { -res } else { res };
+        res as i16
+    }
+}
+
+fn sgn(value: i32) -> i32 {
+    if value < 0 {
+        -1
+    }
+    else {
+        1
+    }
+}
+
+/// The state of a G.726 decoder for one channel.
+pub(crate) struct G726State {
+    tables: &'static G726Tables,
+    code_size: u32,
+    /// Previous reconstructed signal.
+    sr: [Float11; 2],
+    /// Previous quantized differences.
+    dq: [Float11; 6],
+    /// Second order predictor coefficients.
+    a: [i32; 2],
+    /// Sixth order predictor coefficients.
+    b: [i32; 6],
+    /// Signs of the previous two partial reconstructed signals.
+    pk: [i32; 2],
+    /// Speed control.
+    ap: i32,
+    /// Fast scale factor.
+    yu: i32,
+    /// Slow scale factor.
+    yl: i32,
+    /// Short-term average magnitude.
+    dms: i32,
+    /// Long-term average magnitude.
+    dml: i32,
+    /// Tone detected.
+    td: bool,
+    /// Signal estimate.
+    se: i32,
+    /// Partial signal estimate of the sixth order predictor.
+    sez: i32,
+    /// Quantizer scale factor.
+    y: i32,
+}
+
+impl G726State {
+    pub(crate) fn new(code_size: u32) -> Result<Self> {
+        if !(2..=5).contains(&code_size) {
+            return unsupported_error("adpcm (g726): bits per coded sample must be 2 to 5 bits");
+        }
+
+        let zero = Float11 { sign: false, exp: 0, mant: 1 << 5 };
+
+        Ok(G726State {
+            tables: &G726_TABLES[code_size as usize - 2],
+            code_size,
+            sr: [zero; 2],
+            dq: [zero; 6],
+            a: [0; 2],
+            b: [0; 6],
+            pk: [1; 2],
+            ap: 0,
+            yu: 544,
+            yl: 34816,
+            dms: 0,
+            dml: 0,
+            td: false,
+            se: 0,
+            sez: 0,
+            y: 544,
+        })
+    }
+
+    pub(crate) fn code_size(&self) -> u32 {
+        self.code_size
+    }
+
+    /// Inverse adaptive quantizer.
+    fn inverse_quant(&self, code: usize) -> i32 {
+        let dql = i32::from(self.tables.iquant[code]) + (self.y >> 2);
+        let dex = (dql >> 7) & 0xf;
+        let dqt = (1 << 7) + (dql & 0x7f);
+        if dql < 0 {
+            0
+        }
+        else {
+            (dqt << dex) >> 7
+        }
+    }
+
+    /// Decode one code word to a 16-bit sample.
+    pub(crate) fn decode(&mut self, code: u32) -> i32 {
+        let code = code as usize;
+        let is_negative = (code >> (self.code_size - 1)) != 0;
+
+        let mut dq = self.inverse_quant(code);
+
+        // Transition detect.
+        let ylint = self.yl >> 15;
+        let ylfrac = (self.yl >> 10) & 0x1f;
+        let thr2 = if ylint > 9 { 0x1f << 10 } else { (0x20 + ylfrac) << ylint };
+        let tr = self.td && dq > ((3 * thr2) >> 2);
+
+        if is_negative {
+            dq = -dq;
+        }
+
+        let re_signal = i32::from((self.se + dq) as i16);
+
+        // Update the predictor coefficients.
+        let pk0 = if self.sez + dq != 0 { sgn(self.sez + dq) } else { 0 };
+        let dq0 = if dq != 0 { sgn(dq) } else { 0 };
+
+        if tr {
+            self.a = [0; 2];
+            self.b = [0; 6];
+        }
+        else {
+            let fa1 = ((-self.a[0] * self.pk[0] * pk0) >> 5).clamp(-256, 255);
+
+            self.a[1] += 128 * pk0 * self.pk[1] + fa1 - (self.a[1] >> 7);
+            self.a[1] = self.a[1].clamp(-12288, 12288);
+            self.a[0] += 64 * 3 * pk0 * self.pk[0] - (self.a[0] >> 8);
+            self.a[0] = self.a[0].clamp(-(15360 - self.a[1]), 15360 - self.a[1]);
+
+            for (b, dq) in self.b.iter_mut().zip(self.dq.iter()) {
+                *b += 128 * dq0 * if dq.sign { -1 } else { 1 } - (*b >> 8);
+            }
+        }
+
+        // Update the previous quantized differences, reconstructed signals, and signs.
+        self.pk[1] = self.pk[0];
+        self.pk[0] = if pk0 != 0 { pk0 } else { 1 };
+        self.sr[1] = self.sr[0];
+        self.sr[0] = Float11::new(re_signal);
+        self.dq.copy_within(0..5, 1);
+        self.dq[0] = Float11 { sign: is_negative, ..Float11::new(dq) };
+
+        self.td = self.a[1] < -11776;
+
+        // Update the speed control.
+        let f = i32::from(self.tables.f[code]);
+        self.dms += (f << 4) + ((-self.dms) >> 5);
+        self.dml += (f << 4) + ((-self.dml) >> 7);
+
+        if tr {
+            self.ap = 256;
+        }
+        else {
+            self.ap += (-self.ap) >> 4;
+            if self.y <= 1535 || self.td || ((self.dms << 2) - self.dml).abs() >= (self.dml >> 3) {
+                self.ap += 0x20;
+            }
+        }
+
+        // Update the scale factors.
+        self.yu = (self.y + i32::from(self.tables.w[code]) + ((-self.y) >> 5)).clamp(544, 5120);
+        self.yl += self.yu + ((-self.yl) >> 6);
+
+        let al = if self.ap >= 256 { 1 << 6 } else { self.ap >> 2 };
+        self.y = (self.yl + (self.yu - (self.yl >> 6)) * al) >> 6;
+
+        // Compute the signal estimate for the next code word.
+        let mut se = 0;
+        for (b, dq) in self.b.iter().zip(self.dq.iter()) {
+            se += i32::from(Float11::new(*b >> 2).mul(dq));
+        }
+        self.sez = se >> 1;
+        for (a, sr) in self.a.iter().zip(self.sr.iter()) {
+            se += i32::from(Float11::new(*a >> 2).mul(sr));
+        }
+        self.se = se >> 1;
+
+        // The reconstructed signal is a 14-bit sample.
+        from_i16_shift!(clamp_i16(re_signal << 2))
+    }
+}
+
+/// Decode a stream of interleaved G.726 code words.
+pub(crate) fn decode(
+    buf: &[u8],
+    states: &mut [G726State],
+    bit_order: BitOrder,
+    out: &mut [&mut [i32]],
+) -> Result<()> {
+    let code_size = states[0].code_size();
+
+    match bit_order {
+        BitOrder::BigEndian => {
+            let mut bs = BitReaderLtr::new(buf);
+            for i in 0..out[0].len() {
+                for (state, out) in states.iter_mut().zip(out.iter_mut()) {
+                    out[i] = state.decode(bs.read_bits_leq32(code_size)?);
+                }
+            }
+        }
+        BitOrder::LittleEndian => {
+            let mut bs = BitReaderRtl::new(buf);
+            for i in 0..out[0].len() {
+                for (state, out) in states.iter_mut().zip(out.iter_mut()) {
+                    out[i] = state.decode(bs.read_bits_leq32(code_size)?);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Float11, G726State};
+
+    /// Decode a sequence of code words, and return the 16-bit samples.
+    fn decode(code_size: u32, codes: impl Iterator<Item = u32>) -> Vec<i16> {
+        let mut state = G726State::new(code_size).unwrap();
+        codes.map(|code| (state.decode(code) >> 16) as i16).collect()
+    }
+
+    /// The code words `(7 * i + 3) mod 2^code_size` for `i` in `0..32`.
+    fn ramp(code_size: u32) -> impl Iterator<Item = u32> {
+        (0..32).map(move |i| (7 * i + 3) % (1 << code_size))
+    }
+
+    #[rustfmt::skip]
+    const DECODED_16: [i16; 32] = [
+        -12, -60, 68, 20, -20, -60, 92, 32,
+        -36, -96, 156, 76, -92, -236, 396, 212,
+        -240, -544, 900, 508, -592, -1168, 1872, 1100,
+        -1292, -2320, 3592, 2180, -2640, -4324, 6340, 4060,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_24: [i16; 32] = [
+        60, 40, 16, 0, 0, -16, -36, -72,
+        124, 136, 72, 8, -4, -60, -124, -248,
+        428, 600, 388, 68, -32, -280, -512, -924,
+        1388, 2136, 1452, 316, -200, -992, -1552, -2460,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_32: [i16; 32] = [
+        24, -48, 8, -88, 0, 96, -32, 56,
+        -60, 32, -104, 12, 196, -112, 484, -392,
+        396, -612, 248, -1084, 268, 2640, -1724, 2700,
+        -2952, 2080, -4012, 1308, 4396, -3660, 11408, -10520,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_40: [i16; 32] = [
+        12, 88, -172, -64, 0, 40, 156, -168,
+        -48, 16, 124, -396, -344, -8, 200, 672,
+        -968, -520, 160, 800, 2124, -1968, -880, 832,
+        3188, -4352, -3288, 772, 3312, 7100, -7428, -4240,
+    ];
+
+    #[test]
+    fn verify_g726_decode() {
+        assert_eq!(decode(2, ramp(2)), DECODED_16);
+        assert_eq!(decode(3, ramp(3)), DECODED_24);
+        assert_eq!(decode(4, ramp(4)), DECODED_32);
+        assert_eq!(decode(5, ramp(5)), DECODED_40);
+    }
+
+    #[test]
+    fn verify_g726_mul_truncation() {
+        // 58 * 62 * 2^9 / 16 does not fit in 16 bits.
+        assert_eq!(Float11::new(15000).mul(&Float11::new(16000)), -14848);
+        assert_eq!(Float11::new(-15000).mul(&Float11::new(16000)), 14848);
+
+        // A full-scale square wave overflows the signal estimate.
+        let decoded = decode(
+            3,
+            (0..64).map(|i| {
+                if i % 2 == 0 {
+                    3
+                }
+                else {
+                    4
+                }
+            }),
+        );
+
+        assert_eq!(decoded[54], 31208);
+    }
+}
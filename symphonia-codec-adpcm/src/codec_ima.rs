@@ -12,7 +12,7 @@ use symphonia_core::util::clamp::clamp_i16;
 use crate::common::{from_i16_shift, u16_to_i32, Nibble};
 
 #[rustfmt::skip]
-const IMA_INDEX_TABLE: [i32; 16] = [
+pub(crate) const IMA_INDEX_TABLE: [i32; 16] = [
     -1, -1, -1, -1, 2, 4, 6, 8,
     -1, -1, -1, -1, 2, 4, 6, 8,
 ];
@@ -30,8 +30,9 @@ const IMA_STEP_TABLE: [i32; 89] = [
     15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
 ];
 
-/// `AdpcmImaBlockStatus` contains values to decode a block
-struct AdpcmImaBlockStatus {
+/// `AdpcmImaBlockStatus` contains values to decode a block, or a headerless stream
+#[derive(Default)]
+pub(crate) struct AdpcmImaBlockStatus {
     predictor: i32,
     step_index: i32,
 }
@@ -49,6 +50,18 @@ impl AdpcmImaBlockStatus {
         Ok(status)
     }
 
+    /// Read a QuickTime preamble: the upper 9 bits of the predictor followed by the step index,
+    /// packed into a big-endian 16-bit word.
+    fn read_qt_preamble<B: ReadBytes>(stream: &mut B) -> Result<Self> {
+        let preamble = u16_to_i32!(stream.read_be_u16()?);
+        let step_index = preamble & 0x7f;
+        if step_index > 88 {
+            return decode_error("adpcm (ima): invalid step index");
+        }
+        let status = Self { predictor: preamble & !0x7f, step_index };
+        Ok(status)
+    }
+
     fn expand_nibble(&mut self, byte: u8, nibble: Nibble) -> i32 {
         let nibble = nibble.get_nibble(byte);
         let step = IMA_STEP_TABLE[self.step_index as usize];
@@ -60,6 +73,28 @@ impl AdpcmImaBlockStatus {
         self.step_index = (self.step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
         from_i16_shift!(self.predictor)
     }
+
+    /// Expand a nibble using the bit-wise difference calculation of the IMA reference decoder,
+    /// as used by the QuickTime and DVI variants.
+    fn expand_nibble_bitwise(&mut self, byte: u8, nibble: Nibble) -> i32 {
+        let nibble = nibble.get_nibble(byte);
+        let step = IMA_STEP_TABLE[self.step_index as usize];
+        let mut diff = step >> 3;
+        if nibble & 0x04 != 0 {
+            diff += step;
+        }
+        if nibble & 0x02 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 0x01 != 0 {
+            diff += step >> 2;
+        }
+        let predictor =
+            if nibble & 0x08 != 0 { self.predictor - diff } else { self.predictor + diff };
+        self.predictor = clamp_i16(predictor) as i32;
+        self.step_index = (self.step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+        from_i16_shift!(self.predictor)
+    }
 }
 
 pub(crate) fn decode_mono<B: ReadBytes>(
@@ -100,3 +135,96 @@ pub(crate) fn decode_stereo<B: ReadBytes>(
     }
     Ok(())
 }
+
+/// Decode one channel of a QuickTime block: a 2 byte preamble followed by 64 samples in 32 bytes.
+/// The block of each channel follows the block of the previous channel.
+pub(crate) fn decode_qt_channel<B: ReadBytes>(stream: &mut B, buffer: &mut [i32]) -> Result<()> {
+    let mut status = AdpcmImaBlockStatus::read_qt_preamble(stream)?;
+    for samples in buffer.chunks_exact_mut(2) {
+        let nibbles = stream.read_u8()?;
+        samples[0] = status.expand_nibble_bitwise(nibbles, Nibble::Lower);
+        samples[1] = status.expand_nibble_bitwise(nibbles, Nibble::Upper);
+    }
+    Ok(())
+}
+
+/// Decode a headerless DVI stream. Samples are interleaved per nibble, and the upper nibble of a
+/// byte precedes the lower nibble.
+pub(crate) fn decode_dvi(buf: &[u8], states: &mut [AdpcmImaBlockStatus], out: &mut [&mut [i32]]) {
+    let n_channels = states.len();
+    for i in 0..out[0].len() * n_channels {
+        let nibble = if i % 2 == 0 { Nibble::Upper } else { Nibble::Lower };
+        let (frame, channel) = (i / n_channels, i % n_channels);
+        out[channel][frame] = states[channel].expand_nibble_bitwise(buf[i / 2], nibble);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_dvi, decode_qt_channel, AdpcmImaBlockStatus};
+    use symphonia_core::io::BufReader;
+
+    /// The bytes `(37 * i + 11) mod 256` for `i` in `0..n`.
+    fn ramp(n: usize) -> Vec<u8> {
+        (0..n).map(|i| ((37 * i + 11) % 256) as u8).collect()
+    }
+
+    #[rustfmt::skip]
+    const DECODED_QT: [i16; 64] = [
+        4579, 4582, 4585, 4606, 4636, 4682, 4651, 4734,
+        4553, 4475, 4688, 4430, 4327, 3917, 3188, 3287,
+        3920, 4495, 4421, 5169, 4075, 6260, 7821, 6401,
+        10274, 5293, -734, -11270, -6964, -3049, 12376, 27091,
+        13714, 32767, 32767, 30455, 32767, 17379, 3389, -19504,
+        -32768, -32768, 4094, 16380, 5208, 28907, -11104, 32767,
+        32767, 29043, 25658, 10270, -20509, -32768, -12290, -32768,
+        28668, 32767, -751, 27918, 32767, 32767, 32767, 28672,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_DVI: [[i16; 16]; 2] = [
+        [
+            0, 4, 12, 31, 24, 1, -39, -34,
+            1, 51, 152, 79, -41, -252, -167, 15,
+        ],
+        [
+            -4, -4, 4, -2, -20, 3, -6, -42,
+            -7, -11, -57, -26, 57, -52, -9, 164,
+        ],
+    ];
+
+    #[test]
+    fn verify_qt_decode() {
+        // A predictor of 0x1200, and a step index of 16.
+        let mut block = vec![0x12, 0x10];
+        block.extend(ramp(32));
+
+        let mut out = [0; 64];
+        decode_qt_channel(&mut BufReader::new(&block), &mut out).unwrap();
+
+        let decoded: Vec<i16> = out.iter().map(|&sample| (sample >> 16) as i16).collect();
+        assert_eq!(decoded, DECODED_QT);
+    }
+
+    #[test]
+    fn verify_qt_invalid_step_index() {
+        let mut block = vec![0x00, 89];
+        block.extend(ramp(32));
+
+        let mut out = [0; 64];
+        assert!(decode_qt_channel(&mut BufReader::new(&block), &mut out).is_err());
+    }
+
+    #[test]
+    fn verify_dvi_decode() {
+        let mut states = [AdpcmImaBlockStatus::default(), AdpcmImaBlockStatus::default()];
+        let (mut left, mut right) = ([0; 16], [0; 16]);
+
+        decode_dvi(&ramp(16), &mut states, &mut [&mut left, &mut right]);
+
+        for (out, expected) in [left, right].iter().zip(DECODED_DVI.iter()) {
+            let decoded: Vec<i16> = out.iter().map(|&sample| (sample >> 16) as i16).collect();
+            assert_eq!(&decoded, expected);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common::from_i16_shift;
+
+/// Step index adjustments for 3-bit code words.
+const MACE_INDEX_3BIT: [i16; 8] = [-13, 8, 76, 222, 222, 76, 8, -13];
+
+/// Step index adjustments for 2-bit code words.
+const MACE_INDEX_2BIT: [i16; 4] = [-18, 140, 140, -18];
+
+/// Quantizer output magnitudes for 3-bit code words, indexed by step index.
+#[rustfmt::skip]
+const MACE_TABLE_3BIT: [[i16; 4]; 128] = [
+    [   37,   116,   206,   330], [   39,   121,   216,   346], [   41,   127,   225,   361],
+    [   42,   132,   235,   377], [   44,   137,   245,   392], [   46,   144,   256,   410],
+    [   48,   150,   267,   428], [   51,   157,   280,   449], [   53,   165,   293,   470],
+    [   55,   172,   306,   490], [   58,   179,   319,   511], [   60,   187,   333,   534],
+    [   63,   195,   348,   557], [   66,   205,   364,   583], [   69,   214,   380,   609],
+    [   72,   223,   396,   635], [   75,   233,   414,   663], [   79,   244,   433,   694],
+    [   82,   254,   453,   725], [   86,   265,   472,   756], [   90,   278,   495,   792],
+    [   94,   290,   516,   826], [   98,   303,   538,   862], [  102,   316,   562,   901],
+    [  107,   331,   588,   942], [  112,   345,   614,   983], [  117,   361,   641,  1027],
+    [  122,   377,   670,  1074], [  127,   394,   701,  1123], [  133,   411,   732,  1172],
+    [  139,   430,   764,  1224], [  145,   449,   799,  1280], [  152,   469,   835,  1337],
+    [  159,   490,   872,  1397], [  166,   512,   911,  1459], [  173,   535,   951,  1523],
+    [  181,   558,   993,  1590], [  189,   584,  1038,  1663], [  197,   610,  1085,  1738],
+    [  206,   637,  1133,  1815], [  215,   665,  1183,  1895], [  225,   695,  1237,  1980],
+    [  235,   726,  1291,  2068], [  246,   759,  1349,  2161], [  257,   792,  1409,  2257],
+    [  268,   828,  1472,  2357], [  280,   865,  1538,  2463], [  293,   903,  1606,  2572],
+    [  306,   944,  1678,  2688], [  319,   986,  1753,  2807], [  334,  1030,  1832,  2933],
+    [  349,  1076,  1914,  3065], [  364,  1124,  1999,  3202], [  380,  1174,  2088,  3344],
+    [  398,  1227,  2182,  3494], [  415,  1281,  2278,  3649], [  434,  1339,  2380,  3811],
+    [  453,  1398,  2486,  3982], [  473,  1461,  2598,  4160], [  495,  1526,  2714,  4346],
+    [  517,  1594,  2835,  4540], [  540,  1665,  2961,  4741], [  564,  1740,  3093,  4953],
+    [  589,  1818,  3232,  5175], [  615,  1898,  3375,  5405], [  643,  1984,  3527,  5647],
+    [  671,  2072,  3683,  5898], [  701,  2164,  3848,  6161], [  733,  2261,  4020,  6438],
+    [  765,  2362,  4199,  6724], [  800,  2467,  4386,  7024], [  835,  2578,  4583,  7339],
+    [  873,  2692,  4786,  7664], [  912,  2813,  5001,  8008], [  952,  2938,  5223,  8364],
+    [  995,  3070,  5457,  8739], [ 1039,  3207,  5701,  9129], [ 1086,  3350,  5956,  9537],
+    [ 1134,  3499,  6220,  9960], [ 1185,  3655,  6497, 10404], [ 1238,  3818,  6788, 10869],
+    [ 1293,  3989,  7091, 11355], [ 1351,  4166,  7407, 11861], [ 1411,  4352,  7738, 12390],
+    [ 1474,  4547,  8084, 12946], [ 1540,  4750,  8444, 13522], [ 1609,  4962,  8821, 14126],
+    [ 1680,  5183,  9215, 14756], [ 1756,  5415,  9626, 15415], [ 1834,  5657, 10057, 16104],
+    [ 1916,  5909, 10505, 16822], [ 2001,  6173, 10975, 17574], [ 2091,  6448, 11463, 18356],
+    [ 2184,  6736, 11974, 19175], [ 2282,  7037, 12510, 20032], [ 2383,  7351, 13068, 20926],
+    [ 2490,  7679, 13652, 21861], [ 2601,  8021, 14260, 22834], [ 2717,  8380, 14897, 23854],
+    [ 2838,  8753, 15561, 24918], [ 2965,  9144, 16256, 26031], [ 3097,  9553, 16982, 27193],
+    [ 3236,  9979, 17740, 28407], [ 3380, 10424, 18532, 29675], [ 3531, 10890, 19359, 31000],
+    [ 3688, 11375, 20222, 32382], [ 3853, 11883, 21125, 32767], [ 4025, 12414, 22069, 32767],
+    [ 4205, 12967, 23053, 32767], [ 4392, 13546, 24082, 32767], [ 4589, 14151, 25157, 32767],
+    [ 4793, 14783, 26280, 32767], [ 5007, 15442, 27452, 32767], [ 5231, 16132, 28678, 32767],
+    [ 5464, 16851, 29957, 32767], [ 5708, 17603, 31294, 32767], [ 5963, 18389, 32691, 32767],
+    [ 6229, 19210, 32767, 32767], [ 6507, 20067, 32767, 32767], [ 6797, 20963, 32767, 32767],
+    [ 7101, 21899, 32767, 32767], [ 7418, 22876, 32767, 32767], [ 7749, 23897, 32767, 32767],
+    [ 8095, 24964, 32767, 32767], [ 8456, 26078, 32767, 32767], [ 8833, 27242, 32767, 32767],
+    [ 9228, 28457, 32767, 32767], [ 9639, 29727, 32767, 32767],
+];
+
+/// Quantizer output magnitudes for 2-bit code words, indexed by step index.
+#[rustfmt::skip]
+const MACE_TABLE_2BIT: [[i16; 2]; 128] = [
+    [   64,   216], [   67,   226], [   70,   236], [   74,   246], [   77,   257],
+    [   80,   268], [   84,   280], [   88,   294], [   92,   307], [   96,   321],
+    [  100,   334], [  104,   350], [  109,   365], [  114,   382], [  119,   399],
+    [  124,   416], [  130,   434], [  136,   454], [  142,   475], [  148,   495],
+    [  155,   519], [  162,   541], [  169,   564], [  176,   590], [  185,   617],
+    [  193,   644], [  201,   673], [  210,   703], [  220,   735], [  230,   767],
+    [  240,   801], [  251,   838], [  262,   876], [  274,   914], [  286,   955],
+    [  299,   997], [  312,  1041], [  326,  1089], [  341,  1138], [  356,  1188],
+    [  372,  1241], [  388,  1297], [  406,  1354], [  424,  1415], [  443,  1478],
+    [  462,  1544], [  483,  1613], [  505,  1684], [  527,  1760], [  551,  1838],
+    [  576,  1921], [  601,  2007], [  628,  2097], [  656,  2190], [  686,  2288],
+    [  716,  2389], [  748,  2496], [  781,  2607], [  816,  2724], [  853,  2846],
+    [  891,  2973], [  930,  3104], [  972,  3243], [ 1016,  3389], [ 1061,  3539],
+    [ 1108,  3698], [ 1158,  3862], [ 1209,  4035], [ 1264,  4216], [ 1320,  4403],
+    [ 1379,  4599], [ 1441,  4806], [ 1505,  5019], [ 1572,  5244], [ 1642,  5477],
+    [ 1715,  5722], [ 1792,  5978], [ 1872,  6245], [ 1955,  6522], [ 2043,  6813],
+    [ 2134,  7118], [ 2229,  7436], [ 2329,  7767], [ 2432,  8114], [ 2541,  8477],
+    [ 2655,  8854], [ 2773,  9250], [ 2897,  9663], [ 3026, 10094], [ 3162, 10546],
+    [ 3303, 11016], [ 3450, 11508], [ 3604, 12020], [ 3765, 12556], [ 3933, 13118],
+    [ 4108, 13703], [ 4292, 14315], [ 4483, 14953], [ 4683, 15621], [ 4892, 16318],
+    [ 5111, 17046], [ 5339, 17807], [ 5577, 18602], [ 5826, 19433], [ 6086, 20300],
+    [ 6358, 21205], [ 6642, 22152], [ 6938, 23141], [ 7248, 24173], [ 7571, 25252],
+    [ 7909, 26380], [ 8262, 27557], [ 8631, 28786], [ 9016, 30072], [ 9419, 31413],
+    [ 9839, 32767], [10278, 32767], [10737, 32767], [11216, 32767], [11717, 32767],
+    [12240, 32767], [12786, 32767], [13356, 32767], [13953, 32767], [14576, 32767],
+    [15226, 32767], [15906, 32767], [16615, 32767],
+];
+
+/// The compression ratio of a MACE stream.
+#[derive(Copy, Clone)]
+pub(crate) enum MaceRatio {
+    /// MACE 3:1. Each byte encodes 3 samples.
+    Mace3,
+    /// MACE 6:1. Each byte encodes 6 samples.
+    Mace6,
+}
+
+/// Clamp to a 16-bit sample, however, the original implementation clamps negative overflows to
+/// -32767.
+fn clamp_mace(value: i32) -> i16 {
+    if value > 32767 {
+        32767
+    }
+    else if value < -32768 {
+        -32767
+    }
+    else {
+        value as i16
+    }
+}
+
+/// The original implementation produces 8-bit samples. Expand the upper byte to 16-bits.
+fn expand_8bit(value: i16) -> i32 {
+    let value = value as u16;
+    from_i16_shift!(((value & 0xff00) | (value >> 8)) as i16)
+}
+
+/// `MaceStatus` contains values to decode a stream.
+#[derive(Default)]
+pub(crate) struct MaceStatus {
+    index: i16,
+    factor: i16,
+    prev2: i16,
+    previous: i16,
+    level: i16,
+}
+
+impl MaceStatus {
+    /// Read the quantizer output for the code word and adapt the step index. Code words 0, 1, and
+    /// 2 occupy 3, 2, and 3 bits of a byte, respectively.
+    fn read_table(&mut self, code: u8, word: usize) -> i16 {
+        let row = usize::from((self.index & 0x7f0) as u16 >> 4);
+        let code = usize::from(code);
+
+        let (current, adjust) = if word == 1 {
+            let current = match code {
+                0..=1 => MACE_TABLE_2BIT[row][code],
+                _ => -1 - MACE_TABLE_2BIT[row][3 - code],
+            };
+            (current, MACE_INDEX_2BIT[code])
+        }
+        else {
+            let current = match code {
+                0..=3 => MACE_TABLE_3BIT[row][code],
+                _ => -1 - MACE_TABLE_3BIT[row][7 - code],
+            };
+            (current, MACE_INDEX_3BIT[code])
+        };
+
+        self.index = self.index.wrapping_add(adjust.wrapping_sub(self.index >> 5)).max(0);
+
+        current
+    }
+
+    /// Decode one MACE 3:1 code word to one sample.
+    fn decode_mace3(&mut self, code: u8, word: usize) -> i32 {
+        let current = self.read_table(code, word);
+        let current = clamp_mace(i32::from(current) + i32::from(self.level));
+
+        self.level = current - (current >> 3);
+
+        expand_8bit(current)
+    }
+
+    /// Decode one MACE 6:1 code word to two samples.
+    fn decode_mace6(&mut self, code: u8, word: usize, out: &mut [i32]) {
+        let current = self.read_table(code, word);
+
+        self.factor = if (self.previous ^ current) >= 0 {
+            self.factor.saturating_add(506)
+        }
+        else if i32::from(self.factor) - 314 < -32768 {
+            -32767
+        }
+        else {
+            self.factor - 314
+        };
+
+        let current = clamp_mace(i32::from(current) + i32::from(self.level));
+
+        self.level = ((i32::from(current) * i32::from(self.factor)) >> 15) as i16;
+
+        let current = current >> 1;
+        let delta = self.prev2.wrapping_sub(current) >> 2;
+
+        out[0] = expand_8bit(self.previous.wrapping_add(self.prev2).wrapping_sub(delta));
+        out[1] = expand_8bit(self.previous.wrapping_add(current).wrapping_add(delta));
+
+        self.prev2 = self.previous;
+        self.previous = current;
+    }
+}
+
+/// Decode a MACE stream. MACE 3:1 interleaves pairs of bytes per channel, while MACE 6:1
+/// interleaves single bytes per channel.
+pub(crate) fn decode(
+    buf: &[u8],
+    states: &mut [MaceStatus],
+    ratio: MaceRatio,
+    out: &mut [&mut [i32]],
+) {
+    let n_channels = states.len();
+
+    match ratio {
+        MaceRatio::Mace3 => {
+            for (i, frame) in buf.chunks_exact(2 * n_channels).enumerate() {
+                for (ch, (state, out)) in states.iter_mut().zip(out.iter_mut()).enumerate() {
+                    let out = &mut out[6 * i..6 * (i + 1)];
+
+                    for (k, &byte) in frame[2 * ch..2 * (ch + 1)].iter().enumerate() {
+                        let codes = [byte & 7, (byte >> 3) & 3, byte >> 5];
+
+                        for (word, &code) in codes.iter().enumerate() {
+                            out[3 * k + word] = state.decode_mace3(code, word);
+                        }
+                    }
+                }
+            }
+        }
+        MaceRatio::Mace6 => {
+            for (i, frame) in buf.chunks_exact(n_channels).enumerate() {
+                for ((state, out), &byte) in states.iter_mut().zip(out.iter_mut()).zip(frame) {
+                    let out = &mut out[6 * i..6 * (i + 1)];
+
+                    let codes = [byte >> 5, (byte >> 3) & 3, byte & 7];
+
+                    for (word, &code) in codes.iter().enumerate() {
+                        state.decode_mace6(code, word, &mut out[2 * word..2 * (word + 1)]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, MaceRatio, MaceStatus};
+
+    #[rustfmt::skip]
+    const DECODED_MACE3_MONO: [i16; 24] = [
+        257, 514, 514, 514, 0, 257, -258, -1029,
+        0, 1285, 771, 2827, 2056, 1028, -1800, -6683,
+        -4370, -6169, -3085, 1285, 257, -2314, 2313, 3084,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_MACE3_STEREO: [[i16; 12]; 2] = [
+        [257, 514, 514, 514, 0, 257, 257, 0, -772, -2314, -1286, -2057],
+        [-1, -258, -1, 257, 0, 771, 1285, 1799, 1542, 514, 1542, 1799],
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_MACE6_MONO: [i16; 48] = [
+        0, 0, 0, 0, 0, 257, 257, 257,
+        257, 0, -1, -1, -1, 0, 257, 0,
+        -258, -772, -515, 0, 257, 514, 514, 514,
+        257, -258, -1029, -2057, -2057, -1286, -1029, -1029,
+        -1029, -515, -772, -1286, -1800, -2314, -1543, 257,
+        1799, 2827, 3084, 2313, 2056, 2570, 2313, 1285,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED_MACE6_STEREO: [[i16; 24]; 2] = [
+        [
+            0, 0, 0, 0, 0, 257, 257, 257, 257, 0, -1, -515,
+            -772, -1029, -1286, -1286, -1029, -515, -258, -258, -1, 257, 514, 1028,
+        ],
+        [
+            0, 0, 0, -1, -1, -1, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, -1, -1, -258, -258, -258, -1, 257, 257, 0,
+        ],
+    ];
+
+    /// Decode the bytes `(37 * i + 11) mod 256` for `i` in `0..8`, and return the 16-bit samples
+    /// of each channel.
+    fn decode_ramp(ratio: MaceRatio, n_channels: usize) -> Vec<Vec<i16>> {
+        let buf: Vec<u8> = (0..8).map(|i| ((37 * i + 11) % 256) as u8).collect();
+
+        let n_frames = match ratio {
+            MaceRatio::Mace3 => 3 * buf.len() / n_channels,
+            MaceRatio::Mace6 => 6 * buf.len() / n_channels,
+        };
+
+        let mut states: Vec<MaceStatus> = (0..n_channels).map(|_| Default::default()).collect();
+        let mut planes = vec![vec![0; n_frames]; n_channels];
+
+        {
+            let mut out: Vec<&mut [i32]> = planes.iter_mut().map(|p| p.as_mut_slice()).collect();
+            decode(&buf, &mut states, ratio, &mut out);
+        }
+
+        planes.iter().map(|p| p.iter().map(|&s| (s >> 16) as i16).collect()).collect()
+    }
+
+    #[test]
+    fn verify_mace3_decode() {
+        assert_eq!(decode_ramp(MaceRatio::Mace3, 1), [DECODED_MACE3_MONO.to_vec()]);
+        assert_eq!(
+            decode_ramp(MaceRatio::Mace3, 2),
+            [DECODED_MACE3_STEREO[0].to_vec(), DECODED_MACE3_STEREO[1].to_vec()]
+        );
+    }
+
+    #[test]
+    fn verify_mace6_decode() {
+        assert_eq!(decode_ramp(MaceRatio::Mace6, 1), [DECODED_MACE6_MONO.to_vec()]);
+        assert_eq!(
+            decode_ramp(MaceRatio::Mace6, 2),
+            [DECODED_MACE6_STEREO[0].to_vec(), DECODED_MACE6_STEREO[1].to_vec()]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::codec_ima::IMA_INDEX_TABLE;
+use crate::common::{from_i16_shift, Nibble};
+
+#[rustfmt::skip]
+const OKI_STEP_TABLE: [i32; 49] = [
+    16, 17, 19, 21, 23, 25, 28, 31, 34, 37,
+    41, 45, 50, 55, 60, 66, 73, 80, 88, 97,
+    107, 118, 130, 143, 157, 173, 190, 209, 230, 253,
+    279, 307, 337, 371, 408, 449, 494, 544, 598, 658,
+    724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552,
+];
+
+/// `AdpcmOkiStatus` contains values to decode a stream
+#[derive(Default)]
+pub(crate) struct AdpcmOkiStatus {
+    predictor: i32,
+    step_index: i32,
+}
+
+impl AdpcmOkiStatus {
+    fn expand_nibble(&mut self, byte: u8, nibble: Nibble) -> i32 {
+        let nibble = nibble.get_nibble(byte);
+        let step = OKI_STEP_TABLE[self.step_index as usize];
+        let sign = (nibble & 0x08) != 0;
+        let delta = (nibble & 0x07) as i32;
+        let diff = ((2 * delta + 1) * step) >> 3;
+        let predictor = if sign { self.predictor - diff } else { self.predictor + diff };
+        // Samples are 12-bit.
+        self.predictor = predictor.clamp(-2048, 2047);
+        self.step_index = (self.step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 48);
+        from_i16_shift!(self.predictor << 4)
+    }
+}
+
+/// Decode a headerless OKI stream. Samples are interleaved per nibble, and the upper nibble of a
+/// byte precedes the lower nibble.
+pub(crate) fn decode(buf: &[u8], states: &mut [AdpcmOkiStatus], out: &mut [&mut [i32]]) {
+    let n_channels = states.len();
+    for i in 0..out[0].len() * n_channels {
+        let nibble = if i % 2 == 0 { Nibble::Upper } else { Nibble::Lower };
+        let (frame, channel) = (i / n_channels, i % n_channels);
+        out[channel][frame] = states[channel].expand_nibble(buf[i / 2], nibble);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, AdpcmOkiStatus};
+
+    #[rustfmt::skip]
+    const DECODED: [i16; 32] = [
+        32, -192, 32, 64, 416, 912, 1920, 1200,
+        816, -976, -3312, -496, -5424, -7440, -6832, -14080,
+        -7168, -896, 8080, 6896, 23216, -2432, -17952, -3856,
+        -26928, 19632, -20720, -32768, -23456, -14992, 2944, 32752,
+    ];
+
+    #[test]
+    fn verify_oki_decode() {
+        // The bytes `(37 * i + 11) mod 256` for `i` in `0..16`.
+        let buf: Vec<u8> = (0..16).map(|i| ((37 * i + 11) % 256) as u8).collect();
+
+        let mut states = [AdpcmOkiStatus::default()];
+        let mut out = [0; 32];
+        decode(&buf, &mut states, &mut [&mut out]);
+
+        let decoded: Vec<i16> = out.iter().map(|&sample| (sample >> 16) as i16).collect();
+        assert_eq!(decoded, DECODED);
+    }
+}
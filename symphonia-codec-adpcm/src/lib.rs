@@ -19,22 +19,59 @@ use symphonia_core::support_codec;
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
 use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CodecType};
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
-use symphonia_core::codecs::{CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS};
+use symphonia_core::codecs::{CODEC_TYPE_ADPCM_G722, CODEC_TYPE_ADPCM_G726};
+use symphonia_core::codecs::{CODEC_TYPE_ADPCM_G726LE, CODEC_TYPE_ADPCM_IMA_DVI};
+use symphonia_core::codecs::{CODEC_TYPE_ADPCM_IMA_QT, CODEC_TYPE_ADPCM_IMA_WAV};
+use symphonia_core::codecs::{CODEC_TYPE_ADPCM_MS, CODEC_TYPE_ADPCM_OKI};
+use symphonia_core::codecs::{CODEC_TYPE_MACE3, CODEC_TYPE_MACE6};
 use symphonia_core::errors::{unsupported_error, Result};
 use symphonia_core::formats::Packet;
 use symphonia_core::io::ReadBytes;
 
+use codec_g722::G722State;
+use codec_g726::{BitOrder, G726State};
+use codec_ima::AdpcmImaBlockStatus;
+use codec_mace::{MaceRatio, MaceStatus};
+use codec_oki::AdpcmOkiStatus;
+
+mod codec_g722;
+mod codec_g726;
 mod codec_ima;
+mod codec_mace;
 mod codec_ms;
+mod codec_oki;
 mod common;
 
 fn is_supported_adpcm_codec(codec_type: CodecType) -> bool {
-    matches!(codec_type, CODEC_TYPE_ADPCM_MS | CODEC_TYPE_ADPCM_IMA_WAV)
+    matches!(
+        codec_type,
+        CODEC_TYPE_ADPCM_MS
+            | CODEC_TYPE_ADPCM_IMA_WAV
+            | CODEC_TYPE_ADPCM_IMA_QT
+            | CODEC_TYPE_ADPCM_IMA_DVI
+            | CODEC_TYPE_ADPCM_OKI
+            | CODEC_TYPE_ADPCM_G722
+            | CODEC_TYPE_ADPCM_G726
+            | CODEC_TYPE_ADPCM_G726LE
+            | CODEC_TYPE_MACE3
+            | CODEC_TYPE_MACE6
+    )
 }
 
 enum InnerDecoder {
     AdpcmMs,
     AdpcmIma,
+    AdpcmImaQt,
+    /// IMA DVI decoder state for each channel.
+    AdpcmImaDvi(Vec<AdpcmImaBlockStatus>),
+    /// OKI decoder state for each channel.
+    AdpcmOki(Vec<AdpcmOkiStatus>),
+    /// G.722 decoder state for each channel.
+    G722(Vec<G722State>),
+    /// G.726 decoder state for each channel, and the bit packing order.
+    G726(Vec<G726State>, BitOrder),
+    /// MACE decoder state for each channel, and the compression ratio.
+    Mace(Vec<MaceStatus>, MaceRatio),
 }
 
 impl InnerDecoder {
@@ -42,6 +79,7 @@ impl InnerDecoder {
         match *self {
             InnerDecoder::AdpcmMs => codec_ms::decode_mono,
             InnerDecoder::AdpcmIma => codec_ima::decode_mono,
+            _ => unreachable!(),
         }
     }
 
@@ -51,6 +89,7 @@ impl InnerDecoder {
         match *self {
             InnerDecoder::AdpcmMs => codec_ms::decode_stereo,
             InnerDecoder::AdpcmIma => codec_ima::decode_stereo,
+            _ => unreachable!(),
         }
     }
 }
@@ -64,6 +103,20 @@ pub struct AdpcmDecoder {
 
 impl AdpcmDecoder {
     fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        match self.inner_decoder {
+            InnerDecoder::AdpcmMs | InnerDecoder::AdpcmIma | InnerDecoder::AdpcmImaQt => {
+                self.decode_blocks(packet)?
+            }
+            _ => self.decode_stream(packet)?,
+        }
+
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+        Ok(())
+    }
+
+    /// Decode a packet of independently coded blocks.
+    fn decode_blocks(&mut self, packet: &Packet) -> Result<()> {
         let mut stream = packet.as_buf_reader();
 
         let frames_per_block = self.params.frames_per_block.unwrap() as usize;
@@ -74,8 +127,20 @@ impl AdpcmDecoder {
         self.buf.render_reserved(Some(block_count * frames_per_block));
 
         let channel_count = self.buf.spec().channels.count();
-        match channel_count {
-            1 => {
+        match (&self.inner_decoder, channel_count) {
+            (InnerDecoder::AdpcmImaQt, _) => {
+                // The blocks of each channel are stored in sequence, so any number of channels
+                // may be decoded one at a time.
+                for block_id in 0..block_count {
+                    let offset = frames_per_block * block_id;
+                    let buffer_range = offset..(offset + frames_per_block);
+                    for ch in 0..channel_count {
+                        let buffer = &mut self.buf.chan_mut(ch)[buffer_range.clone()];
+                        codec_ima::decode_qt_channel(&mut stream, buffer)?;
+                    }
+                }
+            }
+            (_, 1) => {
                 let buffer = self.buf.chan_mut(0);
                 let decode_mono = self.inner_decoder.decode_mono_fn();
                 for block_id in 0..block_count {
@@ -85,7 +150,7 @@ impl AdpcmDecoder {
                     decode_mono(&mut stream, buffer, frames_per_block)?;
                 }
             }
-            2 => {
+            (_, 2) => {
                 let buffers = self.buf.chan_pair_mut(0, 1);
                 let decode_stereo = self.inner_decoder.decode_stereo_fn();
                 for block_id in 0..block_count {
@@ -101,6 +166,50 @@ impl AdpcmDecoder {
 
         Ok(())
     }
+
+    /// Decode a packet of code words that continue the stream of the previous packet.
+    fn decode_stream(&mut self, packet: &Packet) -> Result<()> {
+        let buf = packet.buf();
+        let n_channels = self.buf.spec().channels.count();
+
+        let n_frames = match &self.inner_decoder {
+            InnerDecoder::AdpcmImaDvi(_) | InnerDecoder::AdpcmOki(_) => 2 * buf.len() / n_channels,
+            InnerDecoder::G722(_) => 2 * (buf.len() / n_channels),
+            InnerDecoder::G726(states, _) => {
+                8 * buf.len() / (states[0].code_size() as usize * n_channels)
+            }
+            InnerDecoder::Mace(_, MaceRatio::Mace3) => 6 * (buf.len() / (2 * n_channels)),
+            InnerDecoder::Mace(_, MaceRatio::Mace6) => 6 * (buf.len() / n_channels),
+            _ => unreachable!(),
+        };
+
+        // Packets may be larger than advertised by the demuxer, grow the buffer if required.
+        if n_frames > self.buf.capacity() {
+            self.buf = AudioBuffer::new(n_frames as u64, *self.buf.spec());
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_frames));
+
+        let mut planes = self.buf.planes_mut();
+
+        match &mut self.inner_decoder {
+            InnerDecoder::AdpcmImaDvi(states) => {
+                codec_ima::decode_dvi(buf, states, planes.planes())
+            }
+            InnerDecoder::AdpcmOki(states) => codec_oki::decode(buf, states, planes.planes()),
+            InnerDecoder::G722(states) => codec_g722::decode(buf, states, planes.planes()),
+            InnerDecoder::G726(states, bit_order) => {
+                codec_g726::decode(buf, states, *bit_order, planes.planes())?
+            }
+            InnerDecoder::Mace(states, ratio) => {
+                codec_mace::decode(buf, states, *ratio, planes.planes())
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
 }
 
 impl Decoder for AdpcmDecoder {
@@ -110,15 +219,29 @@ impl Decoder for AdpcmDecoder {
             return unsupported_error("adpcm: invalid codec type");
         }
 
+        let is_block_codec = matches!(
+            params.codec,
+            CODEC_TYPE_ADPCM_MS | CODEC_TYPE_ADPCM_IMA_WAV | CODEC_TYPE_ADPCM_IMA_QT
+        );
+
+        // The buffer is grown as required for codecs that are not block-based.
         let frames = match params.max_frames_per_packet {
             Some(frames) => frames,
+            None if !is_block_codec => 0,
             _ => return unsupported_error("adpcm: maximum frames per packet is required"),
         };
 
-        if params.frames_per_block.is_none() || params.frames_per_block.unwrap() == 0 {
+        if is_block_codec
+            && (params.frames_per_block.is_none() || params.frames_per_block.unwrap() == 0)
+        {
             return unsupported_error("adpcm: valid frames per block is required");
         }
 
+        // QuickTime blocks always contain 64 frames.
+        if params.codec == CODEC_TYPE_ADPCM_IMA_QT && params.frames_per_block != Some(64) {
+            return unsupported_error("adpcm (ima): frames per block must be 64 for ima_qt");
+        }
+
         let rate = match params.sample_rate {
             Some(rate) => rate,
             _ => return unsupported_error("adpcm: sample rate is required"),
@@ -134,9 +257,55 @@ impl Decoder for AdpcmDecoder {
             return unsupported_error("adpcm: channels or channel_layout is required");
         };
 
+        // Microsoft and IMA WAV blocks interleave at most two channels.
+        if matches!(params.codec, CODEC_TYPE_ADPCM_MS | CODEC_TYPE_ADPCM_IMA_WAV)
+            && spec.channels.count() > 2
+        {
+            return unsupported_error("adpcm: more than 2 channels are not supported");
+        }
+
         let inner_decoder = match params.codec {
             CODEC_TYPE_ADPCM_MS => InnerDecoder::AdpcmMs,
             CODEC_TYPE_ADPCM_IMA_WAV => InnerDecoder::AdpcmIma,
+            CODEC_TYPE_ADPCM_IMA_QT => InnerDecoder::AdpcmImaQt,
+            CODEC_TYPE_ADPCM_IMA_DVI => InnerDecoder::AdpcmImaDvi(
+                (0..spec.channels.count()).map(|_| Default::default()).collect(),
+            ),
+            CODEC_TYPE_ADPCM_OKI => InnerDecoder::AdpcmOki(
+                (0..spec.channels.count()).map(|_| Default::default()).collect(),
+            ),
+            CODEC_TYPE_ADPCM_G722 => {
+                InnerDecoder::G722((0..spec.channels.count()).map(|_| G722State::new()).collect())
+            }
+            CODEC_TYPE_ADPCM_G726 | CODEC_TYPE_ADPCM_G726LE => {
+                let code_size = match params.bits_per_coded_sample {
+                    Some(bits) => bits,
+                    _ => {
+                        return unsupported_error("adpcm (g726): bits per coded sample is required")
+                    }
+                };
+
+                let bit_order = match params.codec {
+                    CODEC_TYPE_ADPCM_G726 => BitOrder::BigEndian,
+                    _ => BitOrder::LittleEndian,
+                };
+
+                let states = (0..spec.channels.count())
+                    .map(|_| G726State::new(code_size))
+                    .collect::<Result<_>>()?;
+
+                InnerDecoder::G726(states, bit_order)
+            }
+            CODEC_TYPE_MACE3 | CODEC_TYPE_MACE6 => {
+                let ratio = match params.codec {
+                    CODEC_TYPE_MACE3 => MaceRatio::Mace3,
+                    _ => MaceRatio::Mace6,
+                };
+
+                let states = (0..spec.channels.count()).map(|_| Default::default()).collect();
+
+                InnerDecoder::Mace(states, ratio)
+            }
             _ => return unsupported_error("adpcm: codec is unsupported"),
         };
 
@@ -151,11 +320,37 @@ impl Decoder for AdpcmDecoder {
         &[
             support_codec!(CODEC_TYPE_ADPCM_MS, "adpcm_ms", "Microsoft ADPCM"),
             support_codec!(CODEC_TYPE_ADPCM_IMA_WAV, "adpcm_ima_wav", "ADPCM IMA WAV"),
+            support_codec!(CODEC_TYPE_ADPCM_IMA_QT, "adpcm_ima_qt", "ADPCM IMA QuickTime"),
+            support_codec!(CODEC_TYPE_ADPCM_IMA_DVI, "adpcm_ima_dvi", "ADPCM IMA DVI"),
+            support_codec!(CODEC_TYPE_ADPCM_OKI, "adpcm_oki", "OKI/Dialogic ADPCM"),
+            support_codec!(CODEC_TYPE_ADPCM_G722, "adpcm_g722", "G.722 ADPCM"),
+            support_codec!(CODEC_TYPE_ADPCM_G726, "adpcm_g726", "G.726 ADPCM"),
+            support_codec!(CODEC_TYPE_ADPCM_G726LE, "adpcm_g726le", "G.726 ADPCM (Little Endian)"),
+            support_codec!(CODEC_TYPE_MACE3, "mace3", "MACE 3:1"),
+            support_codec!(CODEC_TYPE_MACE6, "mace6", "MACE 6:1"),
         ]
     }
 
     fn reset(&mut self) {
-        // No state is stored between packets, therefore do nothing.
+        // Only the stream decoders store state between packets.
+        match &mut self.inner_decoder {
+            InnerDecoder::AdpcmImaDvi(states) => {
+                states.iter_mut().for_each(|s| *s = Default::default())
+            }
+            InnerDecoder::AdpcmOki(states) => {
+                states.iter_mut().for_each(|s| *s = Default::default())
+            }
+            InnerDecoder::G722(states) => states.iter_mut().for_each(|s| *s = G722State::new()),
+            InnerDecoder::G726(states, _) => {
+                for state in states.iter_mut() {
+                    *state = G726State::new(state.code_size()).unwrap();
+                }
+            }
+            InnerDecoder::Mace(states, _) => {
+                states.iter_mut().for_each(|s| *s = Default::default())
+            }
+            _ => (),
+        }
     }
 
     fn codec_params(&self) -> &CodecParameters {
@@ -180,3 +375,59 @@ impl Decoder for AdpcmDecoder {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_ADPCM_IMA_QT};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::BufReader;
+
+    use super::{codec_ima, AdpcmDecoder};
+
+    #[test]
+    fn verify_qt_multichannel_decode() {
+        // Two blocks of three channels, each channel with a different predictor and step index.
+        let mut buf = Vec::new();
+        for block in 0..2u8 {
+            for ch in 0..3u8 {
+                buf.extend([0x10 * (block + 1), 8 * ch].iter());
+                buf.extend((0..32u8).map(|i| i.wrapping_mul(37 + ch) ^ block));
+            }
+        }
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_ADPCM_IMA_QT)
+            .with_sample_rate(8000)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE)
+            .with_frames_per_block(64)
+            .with_max_frames_per_packet(128);
+
+        let mut decoder = AdpcmDecoder::try_new(&params, &Default::default()).unwrap();
+        let packet = Packet::new_from_slice(0, 0, 128, &buf);
+        assert_eq!(decoder.decode(&packet).unwrap().frames(), 128);
+
+        // Each channel block decodes independently of the others.
+        for (i, block) in buf.chunks_exact(34).enumerate() {
+            let mut expected = [0; 64];
+            codec_ima::decode_qt_channel(&mut BufReader::new(block), &mut expected).unwrap();
+
+            let (block_id, ch) = (i / 3, i % 3);
+            assert_eq!(decoder.buf.chan(ch)[64 * block_id..64 * (block_id + 1)], expected[..]);
+        }
+    }
+
+    #[test]
+    fn verify_ima_wav_rejects_multichannel() {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(super::CODEC_TYPE_ADPCM_IMA_WAV)
+            .with_sample_rate(8000)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE)
+            .with_frames_per_block(505)
+            .with_max_frames_per_packet(505);
+
+        assert!(AdpcmDecoder::try_new(&params, &Default::default()).is_err());
+    }
+}
@@ -29,6 +29,8 @@ use symphonia_core::formats::Packet;
 use symphonia_core::io::{BitReaderLtr, BufReader, FiniteStream, ReadBitsLtr, ReadBytes};
 use symphonia_core::support_codec;
 
+use log::{debug, warn};
+
 /// Supported ALAC version.
 const ALAC_VERSION: u8 = 0;
 
@@ -49,6 +51,17 @@ const ALAC_ELEM_TAG_FIL: u32 = 6;
 /// Frame End Element (END) tag.
 const ALAC_ELEM_TAG_END: u32 = 7;
 
+/// The maximum number of channels supported by the decoder.
+const ALAC_MAX_CHANNELS: u8 = 26;
+
+/// The CoreAudio channel layout tag for discrete channels in order (147 << 16), excluding the
+/// number of channels in the lower 16 bits.
+const LAYOUT_TAG_DISCRETE_IN_ORDER: u32 = 0x93_0000;
+
+/// The tolerance, in percent, between the average bit rate stated in the magic cookie and the
+/// decoded average bit rate.
+const AVG_BIT_RATE_TOLERANCE: u64 = 1;
+
 /// An ALAC channel layout.
 #[derive(Debug)]
 enum ChannelLayout {
@@ -69,22 +82,27 @@ enum ChannelLayout {
     /// Centre, Front Left of Centre, Front Right of Centre, Front Left, Front Right, Side Left,
     /// Side Right, LFE
     Mpeg7p1B,
+    /// A number of discrete channels without a predefined layout.
+    Discrete(u8),
 }
 
 impl ChannelLayout {
     /// Given the current ALAC channel layout, this function will return a mappings of an ALAC
-    /// channel number (the index into the array) to a Symphonia `AudioBuffer` channel index.
-    fn channel_map(&self) -> [u8; 8] {
-        match self {
-            ChannelLayout::Mono => [0, 0, 0, 0, 0, 0, 0, 0],
-            ChannelLayout::Stereo => [0, 1, 0, 0, 0, 0, 0, 0],
-            ChannelLayout::Mpeg3p0B => [2, 0, 1, 0, 0, 0, 0, 0],
-            ChannelLayout::Mpeg4p0B => [2, 0, 1, 3, 0, 0, 0, 0],
-            ChannelLayout::Mpeg5p0D => [2, 0, 1, 3, 4, 0, 0, 0],
-            ChannelLayout::Mpeg5p1D => [2, 0, 1, 4, 5, 3, 0, 0],
-            ChannelLayout::Aac6p1 => [2, 0, 1, 5, 6, 4, 3, 0],
-            ChannelLayout::Mpeg7p1B => [2, 4, 5, 0, 1, 6, 7, 3],
-        }
+    /// channel number (the index into the vector) to a Symphonia `AudioBuffer` channel index.
+    fn channel_map(&self) -> Vec<usize> {
+        let map: &[usize] = match self {
+            ChannelLayout::Mono => &[0],
+            ChannelLayout::Stereo => &[0, 1],
+            ChannelLayout::Mpeg3p0B => &[2, 0, 1],
+            ChannelLayout::Mpeg4p0B => &[2, 0, 1, 3],
+            ChannelLayout::Mpeg5p0D => &[2, 0, 1, 3, 4],
+            ChannelLayout::Mpeg5p1D => &[2, 0, 1, 4, 5, 3],
+            ChannelLayout::Aac6p1 => &[2, 0, 1, 5, 6, 4, 3],
+            ChannelLayout::Mpeg7p1B => &[2, 4, 5, 0, 1, 6, 7, 3],
+            // Discrete channels are stored in-order.
+            ChannelLayout::Discrete(num_channels) => return (0..*num_channels as usize).collect(),
+        };
+        map.to_vec()
     }
 
     /// Get a Symphonia channels bitmask from the ALAC channel layout.
@@ -135,8 +153,17 @@ impl ChannelLayout {
                     | Channels::SIDE_RIGHT
                     | Channels::LFE1
             }
+            ChannelLayout::Discrete(num_channels) => {
+                // Discrete channels have no speaker assignment. Use the first N channels.
+                Channels::from_bits_truncate(!((!0 << 1) << (num_channels - 1)))
+            }
         }
     }
+
+    /// Returns `true` if the channel layout is discrete.
+    fn is_discrete(&self) -> bool {
+        matches!(self, ChannelLayout::Discrete(_))
+    }
 }
 
 /// The ALAC "magic cookie" or codec specific configuration.
@@ -189,10 +216,14 @@ impl MagicCookie {
             return decode_error("alac: invalid bit depth");
         }
 
-        // Only 8 channel layouts exist.
-        // TODO: Support discrete/auxiliary channels.
-        if config.num_channels < 1 || config.num_channels > 8 {
-            return unsupported_error("alac: more than 8 channels");
+        // Only 8 channel layouts exist, all further channels are discrete. Symphonia can only
+        // represent a limited number of channels.
+        if config.num_channels < 1 {
+            return decode_error("alac: invalid number of channels");
+        }
+
+        if config.num_channels > ALAC_MAX_CHANNELS {
+            return unsupported_error("alac: too many channels");
         }
 
         // If the magic cookie is 48 bytes, the channel layout is explictly set, otherwise select a
@@ -232,6 +263,15 @@ impl MagicCookie {
                 0x8e_0007 => ChannelLayout::Aac6p1,
                 // 127 << 16
                 0x7f_0008 => ChannelLayout::Mpeg7p1B,
+                // 147 << 16
+                tag if tag & 0xffff_0000 == LAYOUT_TAG_DISCRETE_IN_ORDER => {
+                    if tag & 0xffff != u32::from(config.num_channels) {
+                        return decode_error(
+                            "alac: the number of channels differs from the channel layout",
+                        );
+                    }
+                    ChannelLayout::Discrete(config.num_channels)
+                }
                 _ => return decode_error("alac: invalid channel layout tag"),
             };
 
@@ -252,11 +292,7 @@ impl MagicCookie {
         }
         else {
             // If extra channel information is not provided, use the number of channels to assign
-            // a channel layout.
-            //
-            // TODO: If the number of channels is > 2, then the additional channels are considered
-            // discrete and not part of a channel layout. However, Symphonia does not support
-            // discrete/auxiliary channels so the standard ALAC channel layouts are used for now.
+            // a channel layout. Beyond 8 channels, the channels are discrete.
             match config.num_channels {
                 1 => ChannelLayout::Mono,
                 2 => ChannelLayout::Stereo,
@@ -266,7 +302,7 @@ impl MagicCookie {
                 6 => ChannelLayout::Mpeg5p1D,
                 7 => ChannelLayout::Aac6p1,
                 8 => ChannelLayout::Mpeg7p1B,
-                _ => return decode_error("alac: unknown channel layout for number of channels"),
+                num_channels => ChannelLayout::Discrete(num_channels),
             }
         };
 
@@ -465,6 +501,92 @@ impl ElementChannel {
     }
 }
 
+/// Decode-time verification state.
+struct Verification {
+    /// If `false`, a packet failed verification.
+    is_ok: bool,
+    /// If `true`, all packets since the start of the stream were decoded.
+    is_complete: bool,
+    /// If `true`, the last decoded packet was shorter than its duration.
+    is_short: bool,
+    /// The total number of bytes of all decoded packets.
+    num_bytes: u64,
+    /// The total number of decoded frames.
+    num_frames: u64,
+}
+
+impl Verification {
+    fn new() -> Self {
+        Verification {
+            is_ok: true,
+            is_complete: true,
+            is_short: false,
+            num_bytes: 0,
+            num_frames: 0,
+        }
+    }
+
+    /// Verify a decoded packet against the magic cookie and the packet's duration.
+    fn verify_packet(&mut self, config: &MagicCookie, packet: &Packet, num_frames: usize) {
+        let len = packet.buf().len();
+
+        if config.max_frame_bytes > 0 && len > config.max_frame_bytes as usize {
+            warn!(
+                "verification: packet size of {} bytes exceeds the maximum of {} bytes",
+                len, config.max_frame_bytes
+            );
+            self.is_ok = false;
+        }
+
+        // Only the final packet of the stream may decode to less frames than its duration since
+        // some containers state a constant duration for all packets.
+        if self.is_short {
+            warn!("verification: a packet follows a partial packet");
+            self.is_ok = false;
+        }
+
+        // The decoded frames are compared against the duration of the packet before trimming.
+        let block_dur = packet.block_dur();
+
+        if block_dur > 0 {
+            if num_frames as u64 > block_dur {
+                warn!(
+                    "verification: decoded {} frames, but the packet has a duration of {} frames",
+                    num_frames, block_dur
+                );
+                self.is_ok = false;
+            }
+
+            self.is_short = (num_frames as u64) < block_dur;
+        }
+
+        self.num_bytes += len as u64;
+        self.num_frames += num_frames as u64;
+    }
+
+    /// Get the final verification result.
+    fn result(&self, config: &MagicCookie) -> bool {
+        let mut is_ok = self.is_ok;
+
+        // The average bit rate can only be verified if the entire stream was decoded.
+        if self.is_complete && config.avg_bit_rate > 0 && self.num_frames > 0 {
+            let expected = u64::from(config.avg_bit_rate);
+            let decoded = 8 * self.num_bytes * u64::from(config.sample_rate) / self.num_frames;
+
+            debug!("verification: expected average bit rate = {}", expected);
+            debug!("verification: decoded average bit rate  = {}", decoded);
+
+            if 100 * (expected.max(decoded) - expected.min(decoded))
+                > AVG_BIT_RATE_TOLERANCE * expected
+            {
+                is_ok = false;
+            }
+        }
+
+        is_ok
+    }
+}
+
 /// Apple Lossless Audio Codec (ALAC) decoder.
 pub struct AlacDecoder {
     /// Codec paramters.
@@ -474,15 +596,19 @@ pub struct AlacDecoder {
     tail_bits: Vec<u16>,
     /// ALAC codec-specific configuration.
     config: MagicCookie,
+    /// A mapping of ALAC channel number to output buffer channel index.
+    channel_map: Vec<usize>,
+    /// Verification state, if verification is enabled.
+    verification: Option<Verification>,
     /// Output buffer.
     buf: AudioBuffer<i32>,
 }
 
 impl AlacDecoder {
-    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<usize> {
         let mut bs = BitReaderLtr::new(packet.buf());
 
-        let channel_map = self.config.channel_layout.channel_map();
+        let channel_map = &self.channel_map;
         let num_channels = self.config.num_channels as usize;
         let mut next_channel = 0;
         let mut num_frames = 0;
@@ -496,7 +622,7 @@ impl AlacDecoder {
 
             match tag {
                 ALAC_ELEM_TAG_SCE | ALAC_ELEM_TAG_LFE => {
-                    let out0 = self.buf.chan_mut(channel_map[next_channel] as usize);
+                    let out0 = self.buf.chan_mut(channel_map[next_channel]);
 
                     num_frames =
                         decode_sce_or_cpe(&self.config, &mut bs, &mut self.tail_bits, out0, None)?;
@@ -511,8 +637,8 @@ impl AlacDecoder {
                     }
 
                     let (out0, out1) = self.buf.chan_pair_mut(
-                        channel_map[next_channel + 0] as usize,
-                        channel_map[next_channel + 1] as usize,
+                        channel_map[next_channel + 0],
+                        channel_map[next_channel + 1],
                     );
 
                     num_frames = decode_sce_or_cpe(
@@ -575,12 +701,14 @@ impl AlacDecoder {
             self.buf.transform(|sample| sample << shift);
         }
 
-        Ok(())
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+        Ok(num_frames)
     }
 }
 
 impl Decoder for AlacDecoder {
-    fn try_new(params: &CodecParameters, _: &DecoderOptions) -> Result<Self> {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
         // Verify codec type.
         if params.codec != CODEC_TYPE_ALAC {
             return unsupported_error("alac: invalid codec type");
@@ -594,16 +722,48 @@ impl Decoder for AlacDecoder {
             return unsupported_error("alac: missing extra data");
         };
 
-        let spec = SignalSpec::new(config.sample_rate, config.channel_layout.channels());
+        let mut channels = config.channel_layout.channels();
+
+        // The container may provide a channel layout (e.g., the CAF or MP4 "chan" chunk or atom).
+        // ALAC channels are always stored in the order of the ALAC channel layout, therefore the
+        // container's channel layout may only assign roles to discrete channels.
+        if let Some(container_channels) = params.channels {
+            if container_channels.count() == usize::from(config.num_channels) {
+                if config.channel_layout.is_discrete() {
+                    channels = container_channels;
+                }
+                else if container_channels != channels {
+                    debug!("alac: ignoring the container channel layout");
+                }
+            }
+            else {
+                warn!("alac: the container channel layout does not match the number of channels");
+            }
+        }
+
+        let spec = SignalSpec::new(config.sample_rate, channels);
         let buf = AudioBuffer::new(u64::from(config.frame_length), spec);
 
         let max_tail_values = min(2, config.num_channels) as usize * config.frame_length as usize;
 
-        Ok(AlacDecoder { params: params.clone(), tail_bits: vec![0; max_tail_values], buf, config })
+        let verification = if options.verify { Some(Verification::new()) } else { None };
+
+        Ok(AlacDecoder {
+            params: params.clone(),
+            tail_bits: vec![0; max_tail_values],
+            channel_map: config.channel_layout.channel_map(),
+            verification,
+            buf,
+            config,
+        })
     }
 
     fn reset(&mut self) {
-        // Nothing to do.
+        // After a reset, not all packets will have been decoded.
+        if let Some(verification) = &mut self.verification {
+            verification.is_complete = false;
+            verification.is_short = false;
+        }
     }
 
     fn supported_codecs() -> &'static [CodecDescriptor] {
@@ -615,17 +775,33 @@ impl Decoder for AlacDecoder {
     }
 
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
-        if let Err(e) = self.decode_inner(packet) {
-            self.buf.clear();
-            Err(e)
-        }
-        else {
-            Ok(self.buf.as_audio_buffer_ref())
+        match self.decode_inner(packet) {
+            Ok(num_frames) => {
+                if let Some(verification) = &mut self.verification {
+                    verification.verify_packet(&self.config, packet, num_frames);
+                }
+
+                Ok(self.buf.as_audio_buffer_ref())
+            }
+            Err(e) => {
+                if let Some(verification) = &mut self.verification {
+                    verification.is_ok = false;
+                }
+
+                self.buf.clear();
+                Err(e)
+            }
         }
     }
 
     fn finalize(&mut self) -> FinalizeResult {
-        Default::default()
+        let mut result: FinalizeResult = Default::default();
+
+        if let Some(verification) = &self.verification {
+            result.verify_ok = Some(verification.result(&self.config));
+        }
+
+        result
     }
 
     fn last_decoded(&self) -> AudioBufferRef<'_> {
@@ -819,3 +995,232 @@ fn decorrelate_mid_side(out0: &mut [i32], out1: &mut [i32], weight: i32, shift:
         *s1 = *s0 - *s1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_ALAC};
+    use symphonia_core::formats::Packet;
+
+    use super::{AlacDecoder, ALAC_ELEM_TAG_CPE, ALAC_ELEM_TAG_END, ALAC_ELEM_TAG_SCE};
+
+    /// The number of frames per packet of the synthesized streams.
+    const FRAME_LEN: u32 = 4;
+
+    #[derive(Default)]
+    struct BitWriter {
+        buf: Vec<u8>,
+        n_bits: usize,
+    }
+
+    impl BitWriter {
+        fn put(&mut self, value: u32, n: u32) {
+            for i in (0..n).rev() {
+                if self.n_bits % 8 == 0 {
+                    self.buf.push(0);
+                }
+
+                let bit = ((value >> i) & 0x1) as u8;
+                *self.buf.last_mut().unwrap() |= bit << (7 - self.n_bits % 8);
+                self.n_bits += 1;
+            }
+        }
+
+        /// Writes an uncompressed 16-bit SCE, or CPE if two channels are provided.
+        fn put_element(&mut self, tag: u32, channels: &[&[i16]]) {
+            self.put(tag, 3);
+            // Element instance tag, and unused header bits.
+            self.put(0, 4);
+            self.put(0, 12);
+            // Not a partial frame, no shift, and uncompressed.
+            self.put(0, 1);
+            self.put(0, 2);
+            self.put(1, 1);
+
+            // For a CPE, the samples are interleaved.
+            for i in 0..FRAME_LEN as usize {
+                for ch in channels {
+                    self.put(ch[i] as u16 as u32, 16);
+                }
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            self.put(ALAC_ELEM_TAG_END, 3);
+            self.buf
+        }
+    }
+
+    /// Makes a 24 byte magic cookie for a 16-bit, 44.1 kHz, stream.
+    fn magic_cookie(num_channels: u8, max_frame_bytes: u32) -> Box<[u8]> {
+        let mut cookie = FRAME_LEN.to_be_bytes().to_vec();
+        // Compatible version, bit depth, pb, mb, kb, and the number of channels.
+        cookie.extend_from_slice(&[0, 16, 40, 10, 14, num_channels]);
+        // Maximum run.
+        cookie.extend_from_slice(&255u16.to_be_bytes());
+        cookie.extend_from_slice(&max_frame_bytes.to_be_bytes());
+        // No average bit rate.
+        cookie.extend_from_slice(&0u32.to_be_bytes());
+        cookie.extend_from_slice(&44_100u32.to_be_bytes());
+        cookie.into_boxed_slice()
+    }
+
+    fn new_decoder(cookie: Box<[u8]>, channels: Option<Channels>, verify: bool) -> AlacDecoder {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_ALAC).with_extra_data(cookie);
+
+        if let Some(channels) = channels {
+            params.with_channels(channels);
+        }
+
+        let options = DecoderOptions { verify };
+
+        AlacDecoder::try_new(&params, &options).unwrap()
+    }
+
+    /// The samples of ALAC channel `ch`.
+    fn samples(ch: usize) -> Vec<i16> {
+        (0..FRAME_LEN as i16).map(|i| 100 * (ch as i16 + 1) + i).collect()
+    }
+
+    /// Decodes a packet, and returns the samples of each output channel.
+    fn decode(decoder: &mut AlacDecoder, packet: &Packet) -> Vec<Vec<i32>> {
+        match decoder.decode(packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                (0..buf.spec().channels.count()).map(|ch| buf.chan(ch).to_vec()).collect()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn expected(ch: usize) -> Vec<i32> {
+        samples(ch).iter().map(|&s| i32::from(s) << 16).collect()
+    }
+
+    #[test]
+    fn verify_mpeg_5p1_element_order() {
+        // An MPEG 5.1 D stream codes C, L + R, Ls + Rs, and LFE.
+        let mut bw = BitWriter::default();
+        bw.put_element(ALAC_ELEM_TAG_SCE, &[&samples(0)]);
+        bw.put_element(ALAC_ELEM_TAG_CPE, &[&samples(1), &samples(2)]);
+        bw.put_element(ALAC_ELEM_TAG_CPE, &[&samples(3), &samples(4)]);
+        bw.put_element(super::ALAC_ELEM_TAG_LFE, &[&samples(5)]);
+
+        let mut decoder = new_decoder(magic_cookie(6, 0), None, false);
+
+        let packet = Packet::new_from_slice(0, 0, u64::from(FRAME_LEN), &bw.finish());
+        let out = decode(&mut decoder, &packet);
+
+        assert_eq!(
+            decoder.last_decoded().spec().channels,
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::LFE1
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+        );
+
+        // Output channels are in the order of L, R, C, LFE, Ls, Rs.
+        for (out_ch, &alac_ch) in [1, 2, 0, 5, 3, 4].iter().enumerate() {
+            assert_eq!(out[out_ch], expected(alac_ch));
+        }
+    }
+
+    #[test]
+    fn verify_discrete_channels() {
+        // Beyond 8 channels, the channels are discrete and stored in order.
+        let mut bw = BitWriter::default();
+
+        for ch in (0..10).step_by(2) {
+            bw.put_element(ALAC_ELEM_TAG_CPE, &[&samples(ch), &samples(ch + 1)]);
+        }
+
+        let buf = bw.finish();
+
+        let mut decoder = new_decoder(magic_cookie(10, 0), None, false);
+
+        let out = decode(&mut decoder, &Packet::new_from_slice(0, 0, 4, &buf));
+
+        assert_eq!(decoder.last_decoded().spec().channels.bits(), 0x3ff);
+
+        for (ch, samples) in out.iter().enumerate() {
+            assert_eq!(samples, &expected(ch));
+        }
+
+        // The container channel layout assigns roles to discrete channels.
+        let channels = Channels::from_bits(0x3ff << 4).unwrap();
+
+        let mut decoder = new_decoder(magic_cookie(10, 0), Some(channels), false);
+
+        let out = decode(&mut decoder, &Packet::new_from_slice(0, 0, 4, &buf));
+
+        assert_eq!(decoder.last_decoded().spec().channels, channels);
+
+        for (ch, samples) in out.iter().enumerate() {
+            assert_eq!(samples, &expected(ch));
+        }
+    }
+
+    fn stereo_packet() -> Vec<u8> {
+        let mut bw = BitWriter::default();
+        bw.put_element(ALAC_ELEM_TAG_CPE, &[&samples(0), &samples(1)]);
+        bw.finish()
+    }
+
+    #[test]
+    fn verify_trimmed_packets() {
+        let buf = stereo_packet();
+
+        let mut decoder = new_decoder(magic_cookie(2, buf.len() as u32), None, true);
+
+        // Trimmed packets are verified against their duration before trimming.
+        let packets = [
+            Packet::new_trimmed_from_slice(0, 0, 1, 3, 0, &buf),
+            Packet::new_from_slice(0, 1, 4, &buf),
+            Packet::new_trimmed_from_slice(0, 5, 2, 0, 2, &buf),
+        ];
+
+        for packet in packets.iter() {
+            let out = decode(&mut decoder, packet);
+            assert_eq!(out[0].len() as u64, packet.dur());
+        }
+
+        assert_eq!(decoder.finalize().verify_ok, Some(true));
+    }
+
+    #[test]
+    fn verify_max_frame_bytes() {
+        let buf = stereo_packet();
+
+        // A packet larger than the maximum frame size fails verification.
+        let mut decoder = new_decoder(magic_cookie(2, buf.len() as u32 - 1), None, true);
+
+        decode(&mut decoder, &Packet::new_from_slice(0, 0, 4, &buf));
+
+        assert_eq!(decoder.finalize().verify_ok, Some(false));
+    }
+
+    #[test]
+    fn verify_partial_packets() {
+        let buf = stereo_packet();
+
+        let mut decoder = new_decoder(magic_cookie(2, 0), None, true);
+
+        // Only the final packet may decode to less frames than its duration.
+        decode(&mut decoder, &Packet::new_from_slice(0, 0, 8, &buf));
+
+        assert_eq!(decoder.finalize().verify_ok, Some(true));
+
+        decode(&mut decoder, &Packet::new_from_slice(0, 8, 8, &buf));
+
+        assert_eq!(decoder.finalize().verify_ok, Some(false));
+
+        // More frames than the packet duration fails verification.
+        let mut decoder = new_decoder(magic_cookie(2, 0), None, true);
+
+        decode(&mut decoder, &Packet::new_from_slice(0, 0, 2, &buf));
+
+        assert_eq!(decoder.finalize().verify_ok, Some(false));
+    }
+}
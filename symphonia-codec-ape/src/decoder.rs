@@ -0,0 +1,486 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::checksum::Crc32Le;
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_MONKEYS_AUDIO};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BufReader, Monitor, ReadBytes};
+use symphonia_core::support_codec;
+
+use log::warn;
+
+use crate::entropy::{RangeDecoder, Rice};
+use crate::filter::{filter_params, NnFilter};
+use crate::header::ApeConfig;
+use crate::predictor::Predictor;
+
+/// The frame contains a CRC and frame flags.
+const APE_FRAME_HAS_FLAGS: u32 = 0x8000_0000;
+
+/// The first channel of the frame is silent.
+const APE_FRAME_FLAG_MONO_SILENCE: u32 = 1;
+/// Both channels of the frame are silent.
+const APE_FRAME_FLAG_STEREO_SILENCE: u32 = 3;
+/// Both channels of the frame are identical, only the first channel is coded.
+const APE_FRAME_FLAG_PSEUDO_STEREO: u32 = 4;
+
+/// The maximum number of blocks per frame of any version.
+const APE_MAX_BLOCKS_PER_FRAME: u64 = 73728 * 4;
+
+/// Monkey's Audio (APE) decoder.
+///
+/// Packets must contain one complete frame, with the 32-bit little-endian words of the frame
+/// already converted to a big-endian bitstream, as produced by `ApeReader`. Each frame is verified
+/// against its CRC if verification is enabled.
+pub struct ApeDecoder {
+    params: CodecParameters,
+    config: ApeConfig,
+    bits_per_sample: u32,
+    is_verifying: bool,
+    n_crc_errors: u64,
+    predictor: Predictor,
+    /// The cascaded NN filters of each channel.
+    filters: [Vec<NnFilter>; 2],
+    /// The residuals, and then decoded samples, of each channel.
+    decoded: [Vec<i32>; 2],
+    buf: AudioBuffer<i32>,
+}
+
+impl ApeDecoder {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let n_blocks = packet.block_dur() as usize;
+
+        if n_blocks > self.buf.capacity() {
+            return decode_error("ape: frame exceeds the maximum number of blocks");
+        }
+
+        let mut reader = BufReader::new(packet.buf());
+
+        let mut crc = reader.read_be_u32()?;
+
+        let frame_flags = if crc & APE_FRAME_HAS_FLAGS != 0 {
+            crc &= !APE_FRAME_HAS_FLAGS;
+            reader.read_be_u32()?
+        }
+        else {
+            0
+        };
+
+        let n_channels = self.buf.spec().channels.count();
+
+        for decoded in self.decoded.iter_mut() {
+            decoded.clear();
+            decoded.resize(n_blocks, 0);
+        }
+
+        // Every frame is coded independently.
+        self.predictor.reset();
+
+        for filter in self.filters.iter_mut().flatten() {
+            filter.reset();
+        }
+
+        let is_mono = n_channels == 1 || frame_flags & APE_FRAME_FLAG_PSEUDO_STEREO != 0;
+
+        let is_silent = if is_mono {
+            frame_flags & APE_FRAME_FLAG_MONO_SILENCE != 0
+        }
+        else {
+            frame_flags & APE_FRAME_FLAG_STEREO_SILENCE == APE_FRAME_FLAG_STEREO_SILENCE
+        };
+
+        // Silent frames are not coded, the decoded samples are already zeroed.
+        if !is_silent {
+            // The first byte of the coded data is ignored.
+            reader.read_u8()?;
+
+            let mut rc = RangeDecoder::new(reader.read_buf_bytes_available_ref());
+
+            let decode_value = if self.config.version >= 3990 {
+                RangeDecoder::decode_value_3990
+            }
+            else {
+                RangeDecoder::decode_value_3900
+            };
+
+            let mut rice_y = Rice::default();
+            let mut rice_x = Rice::default();
+
+            let [y, x] = &mut self.decoded;
+
+            if is_mono {
+                for sample in y.iter_mut() {
+                    *sample = decode_value(&mut rc, &mut rice_y)?;
+                }
+            }
+            else {
+                for (y, x) in y.iter_mut().zip(x.iter_mut()) {
+                    *y = decode_value(&mut rc, &mut rice_y)?;
+                    *x = decode_value(&mut rc, &mut rice_x)?;
+                }
+            }
+
+            if rc.is_overrun() {
+                return decode_error("ape: frame is truncated");
+            }
+
+            let [y_filters, x_filters] = &mut self.filters;
+
+            for filter in y_filters.iter_mut() {
+                filter.decompress(y);
+            }
+
+            if is_mono {
+                self.predictor.decode_mono(y);
+            }
+            else {
+                for filter in x_filters.iter_mut() {
+                    filter.decompress(x);
+                }
+
+                self.predictor.decode_stereo(y, x);
+
+                // Decorrelate the channels.
+                for (y, x) in y.iter_mut().zip(x.iter_mut()) {
+                    let left = x.wrapping_sub(*y / 2);
+                    let right = left.wrapping_add(*y);
+                    *y = left;
+                    *x = right;
+                }
+            }
+        }
+
+        // Pseudo-stereo frames duplicate the first channel.
+        if n_channels == 2 && is_mono {
+            let [y, x] = &mut self.decoded;
+            x.copy_from_slice(y);
+        }
+
+        if self.is_verifying {
+            let decoded_crc = self.frame_crc(n_channels, n_blocks);
+
+            if decoded_crc != crc {
+                warn!("ape: frame crc mismatch at ts={}", packet.ts());
+                self.n_crc_errors += 1;
+            }
+        }
+
+        // Scale the samples to the full range of an i32.
+        let shift = 32 - self.bits_per_sample;
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_blocks));
+
+        for (ch, decoded) in self.decoded.iter().enumerate().take(n_channels) {
+            for (out, &sample) in self.buf.chan_mut(ch).iter_mut().zip(decoded) {
+                *out = sample.wrapping_shl(shift);
+            }
+        }
+
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+        Ok(())
+    }
+
+    /// Computes the CRC of the decoded frame.
+    fn frame_crc(&self, n_channels: usize, n_blocks: usize) -> u32 {
+        let n_bytes = (self.bits_per_sample / 8) as usize;
+
+        let mut crc32 = Crc32Le::new(u32::MAX);
+
+        for i in 0..n_blocks {
+            for decoded in &self.decoded[..n_channels] {
+                let sample = decoded[i];
+
+                // The CRC is computed over the samples as they would be stored in a WAVE file.
+                if n_bytes == 1 {
+                    crc32.process_byte((sample + 0x80) as u8);
+                }
+                else {
+                    crc32.process_buf_bytes(&sample.to_le_bytes()[..n_bytes]);
+                }
+            }
+        }
+
+        !crc32.crc() >> 1
+    }
+}
+
+impl Decoder for ApeDecoder {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+        // This decoder only supports Monkey's Audio.
+        if params.codec != CODEC_TYPE_MONKEYS_AUDIO {
+            return unsupported_error("ape: invalid codec type");
+        }
+
+        let config = match params.extra_data.as_ref() {
+            Some(extra_data) => ApeConfig::read(extra_data)?,
+            _ => return unsupported_error("ape: missing extra data"),
+        };
+
+        let sample_rate = match params.sample_rate {
+            Some(sample_rate) => sample_rate,
+            _ => return unsupported_error("ape: sample rate is required"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) if channels.count() == 1 || channels.count() == 2 => channels,
+            Some(_) => return unsupported_error("ape: unsupported number of channels"),
+            _ => return unsupported_error("ape: channels are required"),
+        };
+
+        let bits_per_sample = match params.bits_per_sample {
+            Some(bps @ 8) | Some(bps @ 16) | Some(bps @ 24) => bps,
+            Some(_) => return unsupported_error("ape: unsupported bits per sample"),
+            _ => return unsupported_error("ape: bits per sample is required"),
+        };
+
+        let max_blocks = params.max_frames_per_packet.unwrap_or(APE_MAX_BLOCKS_PER_FRAME);
+
+        if max_blocks > APE_MAX_BLOCKS_PER_FRAME {
+            return unsupported_error("ape: too many blocks per frame");
+        }
+
+        let new_filters = || -> Vec<NnFilter> {
+            filter_params(config.level)
+                .iter()
+                .map(|&(order, shift)| NnFilter::new(order, shift, config.version))
+                .collect()
+        };
+
+        let spec = SignalSpec::new(sample_rate, channels);
+
+        Ok(ApeDecoder {
+            params: params.clone(),
+            config,
+            bits_per_sample,
+            is_verifying: options.verify,
+            n_crc_errors: 0,
+            predictor: Predictor::new(config.version),
+            filters: [new_filters(), new_filters()],
+            decoded: [Vec::new(), Vec::new()],
+            buf: AudioBuffer::new(max_blocks, spec),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_MONKEYS_AUDIO, "ape", "Monkey's Audio")]
+    }
+
+    fn reset(&mut self) {
+        // The decoder state is reset at the start of every frame, therefore do nothing.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        let mut result: FinalizeResult = Default::default();
+
+        // If verifying, every decoded frame must have matched its CRC.
+        if self.is_verifying {
+            if self.n_crc_errors > 0 {
+                warn!("verification: {} frame(s) failed the crc check", self.n_crc_errors);
+            }
+
+            result.verify_ok = Some(self.n_crc_errors == 0);
+        }
+
+        result
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::CODEC_TYPE_MONKEYS_AUDIO;
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions};
+    use symphonia_core::formats::Packet;
+
+    use super::ApeDecoder;
+    use crate::entropy::tests::RangeEncoder;
+    use crate::entropy::Rice;
+    use crate::predictor::Predictor;
+
+    const N_BLOCKS: usize = 1200;
+
+    /// Generates a noisy 16-bit sine wave for each channel.
+    fn test_signal(n_channels: usize) -> Vec<Vec<i32>> {
+        let mut state = 0x8765_4321u32;
+
+        (0..n_channels)
+            .map(|ch| {
+                (0..N_BLOCKS)
+                    .map(|i| {
+                        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+                        let t = (i * (ch + 1)) as f64 / 40.0;
+                        let noise = (state >> 24) as i32 - 128;
+
+                        (20000.0 * t.sin()) as i32 + noise
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Computes the CRC of the interleaved 16-bit samples, bit-by-bit, as Monkey's Audio does.
+    fn reference_crc(channels: &[Vec<i32>]) -> u32 {
+        let mut crc = !0u32;
+
+        for i in 0..N_BLOCKS {
+            for samples in channels {
+                for &byte in &(samples[i] as i16).to_le_bytes() {
+                    crc ^= u32::from(byte);
+
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+                    }
+                }
+            }
+        }
+
+        !crc >> 1
+    }
+
+    fn encode_value(version: u16, enc: &mut RangeEncoder, rice: &mut Rice, value: i32) {
+        if version >= 3990 {
+            enc.encode_value_3990(rice, value);
+        }
+        else {
+            enc.encode_value_3900(rice, value);
+        }
+    }
+
+    /// Encodes a fast compression level frame. The residuals are found by running the predictor
+    /// of the decoder with a zero residual, since its output is offset by exactly the residual.
+    fn encode_frame(version: u16, channels: &[Vec<i32>], crc: u32) -> Vec<u8> {
+        let mut predictor = Predictor::new(version);
+        let mut enc = RangeEncoder::new();
+        let mut rice = [Rice::default(), Rice::default()];
+
+        for i in 0..N_BLOCKS {
+            if channels.len() == 1 {
+                let mut zero = [0];
+                predictor.clone().decode_mono(&mut zero);
+
+                let mut y = [channels[0][i] - zero[0]];
+                encode_value(version, &mut enc, &mut rice[0], y[0]);
+
+                predictor.decode_mono(&mut y);
+            }
+            else {
+                // Correlate the channels.
+                let (left, right) = (channels[0][i], channels[1][i]);
+                let y = right - left;
+                let x = left + y / 2;
+
+                let (mut zero_y, mut zero_x) = ([0], [0]);
+                predictor.clone().decode_stereo(&mut zero_y, &mut zero_x);
+                let ry = y - zero_y[0];
+
+                let (mut zero_y, mut zero_x) = ([ry], [0]);
+                predictor.clone().decode_stereo(&mut zero_y, &mut zero_x);
+                let rx = x - zero_x[0];
+
+                let [rice_y, rice_x] = &mut rice;
+                encode_value(version, &mut enc, rice_y, ry);
+                encode_value(version, &mut enc, rice_x, rx);
+
+                predictor.decode_stereo(&mut [ry], &mut [rx]);
+            }
+        }
+
+        let mut frame = crc.to_be_bytes().to_vec();
+        frame.extend_from_slice(&enc.finish());
+        frame
+    }
+
+    /// Decodes a single frame, and returns the decoded samples and the verification result.
+    fn decode_frame(version: u16, n_channels: usize, frame: &[u8]) -> (Vec<Vec<i32>>, bool) {
+        let channels = match n_channels {
+            1 => Channels::FRONT_LEFT,
+            _ => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        };
+
+        let mut extra_data = version.to_le_bytes().to_vec();
+        extra_data.extend_from_slice(&[0xe8, 0x03, 0x00, 0x00]);
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_MONKEYS_AUDIO)
+            .with_sample_rate(44100)
+            .with_channels(channels)
+            .with_bits_per_sample(16)
+            .with_max_frames_per_packet(N_BLOCKS as u64)
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        let mut decoder = ApeDecoder::try_new(&params, &DecoderOptions { verify: true }).unwrap();
+
+        let packet = Packet::new_from_slice(0, 0, N_BLOCKS as u64, frame);
+
+        let decoded = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                (0..n_channels).map(|ch| buf.chan(ch).iter().map(|&s| s >> 16).collect()).collect()
+            }
+            _ => unreachable!(),
+        };
+
+        (decoded, decoder.finalize().verify_ok == Some(true))
+    }
+
+    fn verify_decode(version: u16, n_channels: usize) {
+        let signal = test_signal(n_channels);
+        let crc = reference_crc(&signal);
+
+        let frame = encode_frame(version, &signal, crc);
+
+        let (decoded, verify_ok) = decode_frame(version, n_channels, &frame);
+        assert_eq!(decoded, signal);
+        assert!(verify_ok);
+
+        // A corrupt CRC must fail verification.
+        let frame = encode_frame(version, &signal, crc ^ 1);
+
+        let (decoded, verify_ok) = decode_frame(version, n_channels, &frame);
+        assert_eq!(decoded, signal);
+        assert!(!verify_ok);
+    }
+
+    #[test]
+    fn verify_decode_3930_mono() {
+        verify_decode(3930, 1);
+    }
+
+    #[test]
+    fn verify_decode_3990_mono() {
+        verify_decode(3990, 1);
+    }
+
+    #[test]
+    fn verify_decode_3990_stereo() {
+        verify_decode(3990, 2);
+    }
+}
@@ -0,0 +1,335 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_MONKEYS_AUDIO};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::{apev2, id3v1};
+
+use log::{debug, warn};
+
+use crate::header::{ApeHeader, APE_STREAM_MARKER};
+
+/// The length of an ID3v1 tag.
+const ID3V1_TAG_LEN: u64 = 128;
+
+/// A Monkey's Audio frame.
+struct ApeFrame {
+    /// The byte position of the 32-bit word containing the first byte of the frame.
+    pos: u64,
+    /// The length of the frame in bytes, starting from `pos`, rounded up to a multiple of 4.
+    len: u64,
+    /// The number of bytes in the first word preceeding the start of the frame.
+    skip: usize,
+    /// The number of blocks (audio frames) in the frame.
+    n_blocks: u32,
+}
+
+/// Monkey's Audio (APE) format reader.
+///
+/// `ApeReader` implements a demuxer for Monkey's Audio files created by version 3.93 and later.
+pub struct ApeReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    frames: Vec<ApeFrame>,
+    blocks_per_frame: u64,
+    next_frame: usize,
+}
+
+impl QueryDescriptor for ApeReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "ape",
+            "Monkey's Audio",
+            &["ape"],
+            &["audio/ape", "audio/x-ape"],
+            &[b"MAC "]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for ApeReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // Positions in the seek table are relative to the stream marker. Any data preceeding the
+        // marker, such as an ID3v2 tag, must be accounted for.
+        let marker_pos = source.pos();
+
+        let marker = source.read_quad_bytes()?;
+
+        if marker != APE_STREAM_MARKER {
+            return unsupported_error("ape: missing monkey's audio stream marker");
+        }
+
+        let header = ApeHeader::read(&mut source)?;
+
+        debug!("{:?}", header);
+
+        let mut metadata = MetadataLog::default();
+
+        // Tags are stored at the end of the stream, and can only be read if the stream is
+        // seekable. The audio data ends where the tags start.
+        let data_end = if source.is_seekable() {
+            let data_end = read_trailing_tags(&mut source, &mut metadata)?;
+            source.seek(SeekFrom::Start(marker_pos + header.first_frame_pos))?;
+            Some(data_end.saturating_sub(u64::from(header.wav_tail_len)))
+        }
+        else {
+            None
+        };
+
+        let frames = build_frames(&header, marker_pos, data_end)?;
+
+        let channels = match header.n_channels {
+            1 => Channels::FRONT_LEFT,
+            _ => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        };
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_MONKEYS_AUDIO)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_bits_per_sample(header.bits_per_sample)
+            .with_channels(channels)
+            .with_n_frames(header.n_blocks())
+            .with_max_frames_per_packet(u64::from(header.blocks_per_frame))
+            .with_extra_data(header.extra_data());
+
+        Ok(ApeReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata,
+            frames,
+            blocks_per_frame: u64::from(header.blocks_per_frame),
+            next_frame: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame = match self.frames.get(self.next_frame) {
+            Some(frame) => frame,
+            None => return end_of_stream_error(),
+        };
+
+        // Consecutive frames may share a word, therefore the reader may need to step back.
+        let pos = self.reader.pos();
+
+        if frame.pos < pos {
+            let delta = (pos - frame.pos) as usize;
+
+            if self.reader.seek_buffered_rel(-(delta as isize)) != frame.pos {
+                self.reader.seek(SeekFrom::Start(frame.pos))?;
+            }
+        }
+        else if frame.pos > pos {
+            self.reader.ignore_bytes(frame.pos - pos)?;
+        }
+
+        let mut buf = vec![0; frame.len as usize];
+
+        // The final frame may be shorter than its estimated length.
+        let is_final = self.next_frame + 1 == self.frames.len();
+
+        if is_final {
+            let mut read = 0;
+
+            while read < buf.len() {
+                match self.reader.read_buf(&mut buf[read..])? {
+                    0 => break,
+                    n => read += n,
+                }
+            }
+        }
+        else {
+            self.reader.read_buf_exact(&mut buf)?;
+        }
+
+        // The frame is stored as a sequence of 32-bit little-endian words. Convert it into a
+        // big-endian bitstream, and drop the bytes belonging to the previous frame.
+        for word in buf.chunks_exact_mut(4) {
+            word.reverse();
+        }
+
+        let data = buf.split_off(frame.skip).into_boxed_slice();
+
+        let ts = self.next_frame as u64 * self.blocks_per_frame;
+        let dur = u64::from(frame.n_blocks);
+
+        self.next_frame += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, data))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                if let Some(sample_rate) = params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        if self.frames.is_empty() {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // Frames are always seeked to, so the actual timestamp is the start of the frame
+        // containing the required timestamp.
+        let index = ((required_ts / self.blocks_per_frame) as usize).min(self.frames.len() - 1);
+        let pos = self.frames[index].pos;
+
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if pos >= current_pos {
+                self.reader.ignore_bytes(pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_frame = index;
+
+        let actual_ts = index as u64 * self.blocks_per_frame;
+
+        debug!("seeked to frame={} actual_ts={}, required_ts={}", index, actual_ts, required_ts);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// Reads the ID3v1 and APE tags at the end of a seekable stream. Returns the position of the start
+/// of the tags, or the end of the stream if there are none.
+fn read_trailing_tags(reader: &mut MediaSourceStream, metadata: &mut MetadataLog) -> Result<u64> {
+    let mut end = reader.seek(SeekFrom::End(0))?;
+
+    // An ID3v1 tag is always the last 128 bytes of the stream.
+    if end >= ID3V1_TAG_LEN {
+        reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+        if reader.read_triple_bytes()? == *b"TAG" {
+            reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+            let mut builder = MetadataBuilder::new();
+
+            match id3v1::read_id3v1(reader, &mut builder) {
+                Ok(_) => metadata.push(builder.metadata()),
+                Err(err) => warn!("ape: failed to read id3v1 tag: {}", err),
+            }
+
+            end -= ID3V1_TAG_LEN;
+        }
+    }
+
+    // An APE tag preceeds the ID3v1 tag, if there is one.
+    let mut builder = MetadataBuilder::new();
+
+    match apev2::read_ape_tag_before(reader, end, &mut builder) {
+        Ok(Some(start)) => {
+            metadata.push(builder.metadata());
+            end = start;
+        }
+        Ok(None) => (),
+        Err(err) => warn!("ape: failed to read ape tag: {}", err),
+    }
+
+    Ok(end)
+}
+
+/// Builds the list of frames from the seek table. If known, `data_end` is the position of the end
+/// of the audio data.
+fn build_frames(
+    header: &ApeHeader,
+    marker_pos: u64,
+    data_end: Option<u64>,
+) -> Result<Vec<ApeFrame>> {
+    let n_frames = header.total_frames as usize;
+
+    let first_pos = marker_pos + header.first_frame_pos;
+
+    let mut frames = Vec::with_capacity(n_frames);
+
+    for i in 0..n_frames {
+        let start = if i == 0 { first_pos } else { marker_pos + u64::from(header.seek_table[i]) };
+
+        let end = if i + 1 < n_frames {
+            marker_pos + u64::from(header.seek_table[i + 1])
+        }
+        else {
+            // If the end of the audio data is unknown, estimate the length of the final frame.
+            match data_end {
+                Some(data_end) if data_end > start => data_end,
+                _ => start + 8 * u64::from(header.final_frame_blocks),
+            }
+        };
+
+        if start < first_pos || end < start {
+            return decode_error("ape: invalid seek table");
+        }
+
+        let n_blocks =
+            if i + 1 < n_frames { header.blocks_per_frame } else { header.final_frame_blocks };
+
+        // Frames are aligned to 32-bit words relative to the first frame.
+        let skip = (start - first_pos) & 3;
+        let pos = start - skip;
+        let len = (end - pos + 3) & !3;
+
+        frames.push(ApeFrame { pos, len, skip: skip as usize, n_blocks });
+    }
+
+    Ok(frames)
+}
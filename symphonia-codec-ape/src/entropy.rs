@@ -0,0 +1,497 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+
+/// The number of bits in the range decoder state.
+const CODE_BITS: u32 = 32;
+const TOP_VALUE: u32 = 1 << (CODE_BITS - 1);
+const EXTRA_BITS: u32 = (CODE_BITS - 2) % 8 + 1;
+const BOTTOM_VALUE: u32 = TOP_VALUE >> 8;
+
+/// The symbol that signals an escaped overflow value.
+const MODEL_ELEMENTS: u32 = 64;
+
+/// Cumulative symbol frequencies for versions prior to 3.99.
+const COUNTS_3970: [u32; 22] = [
+    0, 14824, 28224, 39348, 47855, 53994, 58171, 60926, 62682, 63786, 64463, 64878, 65126, 65276,
+    65365, 65419, 65450, 65469, 65480, 65487, 65491, 65493,
+];
+
+/// Symbol frequencies for versions prior to 3.99.
+const COUNTS_DIFF_3970: [u32; 21] = [
+    14824, 13400, 11124, 8507, 6139, 4177, 2755, 1756, 1104, 677, 415, 248, 150, 89, 54, 31, 19,
+    11, 7, 4, 2,
+];
+
+/// Cumulative symbol frequencies for version 3.99 and later.
+const COUNTS_3980: [u32; 22] = [
+    0, 19578, 36160, 48417, 56323, 60899, 63265, 64435, 64971, 65232, 65351, 65416, 65447, 65466,
+    65476, 65482, 65485, 65488, 65490, 65491, 65492, 65493,
+];
+
+/// Symbol frequencies for version 3.99 and later.
+const COUNTS_DIFF_3980: [u32; 21] = [
+    19578, 16582, 12257, 7906, 4576, 2366, 1170, 536, 261, 119, 65, 31, 19, 10, 6, 3, 3, 2, 1, 1, 1,
+];
+
+/// An adaptive Rice parameter.
+pub struct Rice {
+    k: u32,
+    ksum: u32,
+}
+
+impl Default for Rice {
+    fn default() -> Self {
+        Rice { k: 10, ksum: (1 << 10) * 16 }
+    }
+}
+
+impl Rice {
+    fn update(&mut self, x: u32) {
+        let lim = if self.k > 0 { 1 << (self.k + 4) } else { 0 };
+
+        self.ksum = self
+            .ksum
+            .wrapping_add((x.wrapping_add(1)) / 2)
+            .wrapping_sub(self.ksum.wrapping_add(16) >> 5);
+
+        if self.ksum < lim {
+            self.k -= 1;
+        }
+        else if self.ksum >= (1 << (self.k + 5)) && self.k < 24 {
+            self.k += 1;
+        }
+    }
+}
+
+/// A range decoder over the big-endian frame bitstream.
+pub struct RangeDecoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    low: u32,
+    range: u32,
+    help: u32,
+    buffer: u32,
+    /// Set if the decoder attempted to read past the end of the buffer.
+    overrun: bool,
+}
+
+impl<'a> RangeDecoder<'a> {
+    /// Instantiate a range decoder and read the first byte of state.
+    pub fn new(buf: &'a [u8]) -> Self {
+        let mut rc = RangeDecoder {
+            buf,
+            pos: 0,
+            low: 0,
+            range: 1 << EXTRA_BITS,
+            help: 0,
+            buffer: 0,
+            overrun: false,
+        };
+
+        rc.buffer = u32::from(rc.next_byte());
+        rc.low = rc.buffer >> (8 - EXTRA_BITS);
+        rc
+    }
+
+    /// Returns true if the decoder read past the end of the buffer.
+    pub fn is_overrun(&self) -> bool {
+        self.overrun
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        match self.buf.get(self.pos) {
+            Some(&byte) => {
+                self.pos += 1;
+                byte
+            }
+            None => {
+                self.overrun = true;
+                0
+            }
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.range <= BOTTOM_VALUE {
+            self.buffer = (self.buffer << 8) | u32::from(self.next_byte());
+            self.low = (self.low << 8) | ((self.buffer >> 1) & 0xff);
+            self.range <<= 8;
+        }
+    }
+
+    fn decode_culfreq(&mut self, tot_f: u32) -> u32 {
+        self.normalize();
+        self.help = self.range / tot_f;
+        self.low / self.help
+    }
+
+    fn decode_culshift(&mut self, shift: u32) -> u32 {
+        self.normalize();
+        self.help = self.range >> shift;
+        self.low / self.help
+    }
+
+    fn update(&mut self, sy_f: u32, lt_f: u32) {
+        self.low = self.low.wrapping_sub(self.help.wrapping_mul(lt_f));
+        self.range = self.help.wrapping_mul(sy_f);
+    }
+
+    fn decode_bits(&mut self, n: u32) -> u32 {
+        let sym = self.decode_culshift(n);
+        self.update(1, sym);
+        sym
+    }
+
+    fn decode_symbol(&mut self, counts: &[u32; 22], counts_diff: &[u32; 21]) -> Result<u32> {
+        let cf = self.decode_culshift(16);
+
+        // Symbols past the end of the frequency table are escaped.
+        if cf > 65492 {
+            if cf > 65535 {
+                return decode_error("ape: invalid symbol");
+            }
+
+            self.update(1, cf);
+            return Ok(cf + 63 - 65535);
+        }
+
+        let symbol = counts[1..].iter().position(|&count| count > cf).unwrap_or(20);
+
+        self.update(counts_diff[symbol], counts[symbol]);
+
+        Ok(symbol as u32)
+    }
+
+    /// Decodes a value using the entropy coding of versions prior to 3.99.
+    pub fn decode_value_3900(&mut self, rice: &mut Rice) -> Result<i32> {
+        let mut overflow = self.decode_symbol(&COUNTS_3970, &COUNTS_DIFF_3970)?;
+
+        let k = if overflow == MODEL_ELEMENTS - 1 {
+            overflow = 0;
+            self.decode_bits(5)
+        }
+        else {
+            rice.k.saturating_sub(1)
+        };
+
+        let mut x = if k <= 16 {
+            self.decode_bits(k)
+        }
+        else if k <= 31 {
+            let lo = self.decode_bits(16);
+            lo | (self.decode_bits(k - 16) << 16)
+        }
+        else {
+            return decode_error("ape: too many bits");
+        };
+
+        x = x.wrapping_add(overflow << k);
+
+        rice.update(x);
+
+        Ok(to_signed(x))
+    }
+
+    /// Decodes a value using the entropy coding of version 3.99 and later.
+    pub fn decode_value_3990(&mut self, rice: &mut Rice) -> Result<i32> {
+        let pivot = (rice.ksum >> 5).max(1);
+
+        let mut overflow = self.decode_symbol(&COUNTS_3980, &COUNTS_DIFF_3980)?;
+
+        if overflow == MODEL_ELEMENTS - 1 {
+            overflow = self.decode_bits(16) << 16;
+            overflow |= self.decode_bits(16);
+        }
+
+        let base = if pivot < 0x10000 {
+            let base = self.decode_culfreq(pivot);
+            self.update(1, base);
+            base
+        }
+        else {
+            // The pivot does not fit in 16 bits, decode the base in two parts.
+            let mut base_hi = pivot;
+            let mut bbits = 0;
+
+            while base_hi & !0xffff != 0 {
+                base_hi >>= 1;
+                bbits += 1;
+            }
+
+            let base_hi = self.decode_culfreq(base_hi + 1);
+            self.update(1, base_hi);
+
+            let base_lo = self.decode_culfreq(1 << bbits);
+            self.update(1, base_lo);
+
+            (base_hi << bbits).wrapping_add(base_lo)
+        };
+
+        let x = base.wrapping_add(overflow.wrapping_mul(pivot));
+
+        rice.update(x);
+
+        Ok(to_signed(x))
+    }
+}
+
+/// Converts an unsigned value to a signed value: 0, 1, 2, 3, 4 map to 0, 1, -1, 2, -2.
+#[inline(always)]
+fn to_signed(x: u32) -> i32 {
+    if x & 1 != 0 {
+        ((x >> 1) as i32).wrapping_add(1)
+    }
+    else {
+        -((x >> 1) as i32)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    const SHIFT_BITS: u32 = CODE_BITS - 9;
+
+    /// A range encoder producing a bitstream for `RangeDecoder`, including the ignored first byte.
+    pub struct RangeEncoder {
+        buf: Vec<u8>,
+        low: u32,
+        range: u32,
+        help: u32,
+        buffer: u32,
+    }
+
+    impl RangeEncoder {
+        pub fn new() -> Self {
+            RangeEncoder { buf: Vec::new(), low: 0, range: TOP_VALUE, help: 0, buffer: 0 }
+        }
+
+        fn put(&mut self, byte: u32, carry: u32) {
+            self.buf.push(byte as u8);
+
+            while self.help > 0 {
+                self.buf.push(carry as u8);
+                self.help -= 1;
+            }
+        }
+
+        fn normalize(&mut self) {
+            while self.range <= BOTTOM_VALUE {
+                if self.low < (0xff << SHIFT_BITS) {
+                    self.put(self.buffer, 0xff);
+                    self.buffer = self.low >> SHIFT_BITS;
+                }
+                else if self.low & TOP_VALUE != 0 {
+                    self.put(self.buffer + 1, 0);
+                    self.buffer = self.low >> SHIFT_BITS;
+                }
+                else {
+                    self.help += 1;
+                }
+
+                self.low = (self.low << 8) & (TOP_VALUE - 1);
+                self.range <<= 8;
+            }
+        }
+
+        fn encode_shift(&mut self, sy_f: u32, lt_f: u32, shift: u32) {
+            self.normalize();
+            let r = self.range >> shift;
+            self.range = r * sy_f;
+            self.low += r * lt_f;
+        }
+
+        fn encode_freq(&mut self, value: u32, tot_f: u32) {
+            self.normalize();
+            let r = self.range / tot_f;
+            self.range = r;
+            self.low += r * value;
+        }
+
+        fn encode_bits(&mut self, value: u32, n: u32) {
+            self.encode_shift(1, value, n);
+        }
+
+        fn encode_symbol(&mut self, symbol: u32, counts: &[u32; 22], counts_diff: &[u32; 21]) {
+            if symbol > 20 {
+                self.encode_shift(1, symbol + 65535 - 63, 16);
+            }
+            else {
+                let symbol = symbol as usize;
+                self.encode_shift(counts_diff[symbol], counts[symbol], 16);
+            }
+        }
+
+        fn encode_k_bits(&mut self, x: u32, k: u32) {
+            if k <= 16 {
+                self.encode_bits(x & ((1 << k) - 1), k);
+            }
+            else {
+                self.encode_bits(x & 0xffff, 16);
+                self.encode_bits((x >> 16) & ((1 << (k - 16)) - 1), k - 16);
+            }
+        }
+
+        /// Encodes a value using the entropy coding of versions prior to 3.99.
+        pub fn encode_value_3900(&mut self, rice: &mut Rice, value: i32) {
+            let x = to_unsigned(value);
+            let k = rice.k.saturating_sub(1);
+
+            if x >> k < MODEL_ELEMENTS - 1 {
+                self.encode_symbol(x >> k, &COUNTS_3970, &COUNTS_DIFF_3970);
+                self.encode_k_bits(x, k);
+            }
+            else {
+                // Escape the value and code it with enough bits.
+                let k = 32 - x.leading_zeros();
+                self.encode_symbol(MODEL_ELEMENTS - 1, &COUNTS_3970, &COUNTS_DIFF_3970);
+                self.encode_bits(k, 5);
+                self.encode_k_bits(x, k);
+            }
+
+            rice.update(x);
+        }
+
+        /// Encodes a value using the entropy coding of version 3.99 and later.
+        pub fn encode_value_3990(&mut self, rice: &mut Rice, value: i32) {
+            let x = to_unsigned(value);
+            let pivot = (rice.ksum >> 5).max(1);
+
+            let overflow = x / pivot;
+            let base = x % pivot;
+
+            if overflow < MODEL_ELEMENTS - 1 {
+                self.encode_symbol(overflow, &COUNTS_3980, &COUNTS_DIFF_3980);
+            }
+            else {
+                self.encode_symbol(MODEL_ELEMENTS - 1, &COUNTS_3980, &COUNTS_DIFF_3980);
+                self.encode_bits(overflow >> 16, 16);
+                self.encode_bits(overflow & 0xffff, 16);
+            }
+
+            if pivot < 0x10000 {
+                self.encode_freq(base, pivot);
+            }
+            else {
+                let bbits = 32 - (pivot >> 16).leading_zeros();
+                self.encode_freq(base >> bbits, (pivot >> bbits) + 1);
+                self.encode_freq(base & ((1 << bbits) - 1), 1 << bbits);
+            }
+
+            rice.update(x);
+        }
+
+        /// Flushes the encoder and returns the bitstream.
+        pub fn finish(mut self) -> Vec<u8> {
+            self.normalize();
+
+            let tmp = (self.low >> SHIFT_BITS) + 1;
+
+            if tmp > 0xff {
+                self.put(self.buffer + 1, 0);
+            }
+            else {
+                self.put(self.buffer, 0xff);
+            }
+
+            self.buf.extend_from_slice(&[tmp as u8, 0, 0, 0]);
+            self.buf
+        }
+    }
+
+    fn to_unsigned(value: i32) -> u32 {
+        if value > 0 {
+            (value as u32) * 2 - 1
+        }
+        else {
+            value.unsigned_abs() * 2
+        }
+    }
+
+    /// Generates values of widely varying magnitude to exercise every coding path.
+    fn test_values() -> Vec<i32> {
+        let mut state = 0x1234_5678u32;
+
+        (0..2000)
+            .map(|i| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+                // Sweep the magnitude up to 27 bits and back down again.
+                let bits = if i < 1000 { i / 37 } else { (2000 - i) / 37 };
+                let value = (state >> 4) & ((1 << bits) - 1);
+
+                if state & 1 != 0 {
+                    value as i32
+                }
+                else {
+                    -(value as i32)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify_to_unsigned() {
+        for &value in &[0, 1, -1, 2, -2, 1000, -1000] {
+            assert_eq!(to_signed(to_unsigned(value)), value);
+        }
+    }
+
+    #[test]
+    fn verify_decode_value_3900() {
+        let values = test_values();
+
+        let mut enc = RangeEncoder::new();
+        let mut rice = Rice::default();
+
+        for &value in &values {
+            enc.encode_value_3900(&mut rice, value);
+        }
+
+        let buf = enc.finish();
+
+        // The first byte is ignored.
+        let mut rc = RangeDecoder::new(&buf[1..]);
+        let mut rice = Rice::default();
+
+        for &value in &values {
+            assert_eq!(rc.decode_value_3900(&mut rice).unwrap(), value);
+        }
+
+        assert!(!rc.is_overrun());
+    }
+
+    #[test]
+    fn verify_decode_value_3990() {
+        let values = test_values();
+
+        let mut enc = RangeEncoder::new();
+        let mut rice = Rice::default();
+        let mut max_pivot = 0;
+
+        for &value in &values {
+            max_pivot = max_pivot.max(rice.ksum >> 5);
+            enc.encode_value_3990(&mut rice, value);
+        }
+
+        // The pivot must have been large enough to code the base in two parts.
+        assert!(max_pivot >= 0x10000);
+
+        let buf = enc.finish();
+
+        let mut rc = RangeDecoder::new(&buf[1..]);
+        let mut rice = Rice::default();
+
+        for &value in &values {
+            assert_eq!(rc.decode_value_3990(&mut rice).unwrap(), value);
+        }
+
+        assert!(!rc.is_overrun());
+    }
+}
@@ -0,0 +1,166 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::header::CompressionLevel;
+
+/// The number of samples processed before the history buffers are rewound.
+const WINDOW_LEN: usize = 512;
+
+/// Gets the order and fractional bits of the cascaded neural network (NN) filters used at a
+/// compression level. The filters are listed in the order they are applied when decoding.
+pub fn filter_params(level: CompressionLevel) -> &'static [(usize, u32)] {
+    match level {
+        CompressionLevel::Fast => &[],
+        CompressionLevel::Normal => &[(16, 11)],
+        CompressionLevel::High => &[(64, 11)],
+        CompressionLevel::ExtraHigh => &[(32, 10), (256, 13)],
+        CompressionLevel::Insane => &[(16, 11), (256, 13), (1280, 15)],
+    }
+}
+
+/// An adaptive neural network (NN) filter.
+pub struct NnFilter {
+    order: usize,
+    shift: u32,
+    version: u16,
+    coeffs: Vec<i16>,
+    /// The saturated filter output history.
+    input: Vec<i16>,
+    /// The coefficient adaption deltas.
+    delta: Vec<i16>,
+    /// The position of the current sample in the history buffers.
+    pos: usize,
+    running_avg: i32,
+}
+
+impl NnFilter {
+    pub fn new(order: usize, shift: u32, version: u16) -> Self {
+        NnFilter {
+            order,
+            shift,
+            version,
+            coeffs: vec![0; order],
+            input: vec![0; WINDOW_LEN + order],
+            delta: vec![0; WINDOW_LEN + order],
+            pos: order,
+            running_avg: 0,
+        }
+    }
+
+    /// Resets the filter state.
+    pub fn reset(&mut self) {
+        self.coeffs.iter_mut().for_each(|c| *c = 0);
+        self.input.iter_mut().for_each(|s| *s = 0);
+        self.delta.iter_mut().for_each(|s| *s = 0);
+        self.pos = self.order;
+        self.running_avg = 0;
+    }
+
+    /// Filters the samples in-place.
+    pub fn decompress(&mut self, samples: &mut [i32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.decompress_sample(*sample);
+        }
+    }
+
+    fn decompress_sample(&mut self, value: i32) -> i32 {
+        let order = self.order;
+        let pos = self.pos;
+
+        let input = &self.input[pos - order..pos];
+        let delta = &self.delta[pos - order..pos];
+
+        // Compute the dot product using the current coefficients.
+        let dot = self
+            .coeffs
+            .iter()
+            .zip(input)
+            .fold(0i32, |acc, (&c, &s)| acc.wrapping_add(i32::from(c) * i32::from(s)));
+
+        // Adapt the coefficients in the direction that would have reduced the error.
+        if value < 0 {
+            for (c, &d) in self.coeffs.iter_mut().zip(delta) {
+                *c = c.wrapping_add(d);
+            }
+        }
+        else if value > 0 {
+            for (c, &d) in self.coeffs.iter_mut().zip(delta) {
+                *c = c.wrapping_sub(d);
+            }
+        }
+
+        let rounded = (i64::from(dot) + (1 << (self.shift - 1))) >> self.shift;
+        let output = value.wrapping_add(rounded as i32);
+
+        self.input[pos] = saturate_i16(output);
+
+        if self.version >= 3980 {
+            let abs = i64::from(output).abs();
+            let avg = i64::from(self.running_avg);
+
+            self.delta[pos] = if abs > avg * 3 {
+                if output < 0 {
+                    32
+                }
+                else {
+                    -32
+                }
+            }
+            else if abs > (avg * 4) / 3 {
+                if output < 0 {
+                    16
+                }
+                else {
+                    -16
+                }
+            }
+            else if abs > 0 {
+                if output < 0 {
+                    8
+                }
+                else {
+                    -8
+                }
+            }
+            else {
+                0
+            };
+
+            self.running_avg = self.running_avg.wrapping_add(((abs - avg) / 16) as i32);
+
+            self.delta[pos - 1] >>= 1;
+            self.delta[pos - 2] >>= 1;
+            self.delta[pos - 8] >>= 1;
+        }
+        else {
+            self.delta[pos] = match output {
+                0 => 0,
+                x if x < 0 => 4,
+                _ => -4,
+            };
+
+            self.delta[pos - 4] >>= 1;
+            self.delta[pos - 8] >>= 1;
+        }
+
+        self.pos += 1;
+
+        // Rewind the history buffers once the window is exhausted.
+        if self.pos == WINDOW_LEN + order {
+            self.input.copy_within(WINDOW_LEN.., 0);
+            self.delta.copy_within(WINDOW_LEN.., 0);
+            self.pos = order;
+        }
+
+        output
+    }
+}
+
+#[inline(always)]
+fn saturate_i16(value: i32) -> i16 {
+    value.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16
+}
@@ -0,0 +1,333 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+/// The Monkey's Audio stream marker: "MAC " in ASCII.
+pub const APE_STREAM_MARKER: [u8; 4] = *b"MAC ";
+
+/// The oldest supported file version (3.93).
+const APE_MIN_VERSION: u16 = 3930;
+
+/// The file version where the descriptor was introduced (3.98).
+const APE_DESCRIPTOR_VERSION: u16 = 3980;
+
+/// The length of the extra data passed from the reader to the decoder.
+pub const APE_EXTRA_DATA_LEN: usize = 6;
+
+/// Samples are 8-bit.
+const APE_FORMAT_FLAG_8_BIT: u16 = 1 << 0;
+/// The header contains a peak level (versions prior to 3.98).
+const APE_FORMAT_FLAG_HAS_PEAK_LEVEL: u16 = 1 << 2;
+/// Samples are 24-bit.
+const APE_FORMAT_FLAG_24_BIT: u16 = 1 << 3;
+/// The header contains the number of seek table entries (versions prior to 3.98).
+const APE_FORMAT_FLAG_HAS_SEEK_ELEMENTS: u16 = 1 << 4;
+/// The WAVE header was not stored, and must be created on decode.
+const APE_FORMAT_FLAG_CREATE_WAV_HEADER: u16 = 1 << 5;
+
+/// The compression level of a Monkey's Audio stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CompressionLevel {
+    Fast,
+    Normal,
+    High,
+    ExtraHigh,
+    Insane,
+}
+
+impl CompressionLevel {
+    fn from_u16(level: u16) -> Option<CompressionLevel> {
+        match level {
+            1000 => Some(CompressionLevel::Fast),
+            2000 => Some(CompressionLevel::Normal),
+            3000 => Some(CompressionLevel::High),
+            4000 => Some(CompressionLevel::ExtraHigh),
+            5000 => Some(CompressionLevel::Insane),
+            _ => None,
+        }
+    }
+}
+
+/// The stream parameters required by the decoder.
+///
+/// The parameters are passed from the reader to the decoder as extra data. The extra data is 6
+/// bytes long and contains the file version, compression level, and format flags, each as a
+/// 16-bit little-endian integer.
+#[derive(Copy, Clone, Debug)]
+pub struct ApeConfig {
+    /// The file version multiplied by 1000 (e.g., 3990 for version 3.99).
+    pub version: u16,
+    /// The compression level.
+    pub level: CompressionLevel,
+}
+
+impl ApeConfig {
+    /// Reads the decoder configuration from the extra data.
+    pub fn read(extra_data: &[u8]) -> Result<ApeConfig> {
+        let mut reader = BufReader::new(extra_data);
+
+        let version = reader.read_u16()?;
+        let level = reader.read_u16()?;
+
+        if version < APE_MIN_VERSION {
+            return unsupported_error("ape: file versions prior to 3.93 are not supported");
+        }
+
+        let level = match CompressionLevel::from_u16(level) {
+            Some(level) => level,
+            _ => return unsupported_error("ape: unsupported compression level"),
+        };
+
+        Ok(ApeConfig { version, level })
+    }
+}
+
+/// The Monkey's Audio descriptor and header.
+#[derive(Debug)]
+pub struct ApeHeader {
+    /// The file version multiplied by 1000.
+    pub version: u16,
+    /// The compression level multiplied by 1000.
+    pub compression_level: u16,
+    /// The format flags.
+    pub format_flags: u16,
+    /// The number of blocks (audio frames) in every frame except the last.
+    pub blocks_per_frame: u32,
+    /// The number of blocks in the final frame.
+    pub final_frame_blocks: u32,
+    /// The total number of frames.
+    pub total_frames: u32,
+    /// The number of bits per sample.
+    pub bits_per_sample: u32,
+    /// The number of channels.
+    pub n_channels: u32,
+    /// The sample rate.
+    pub sample_rate: u32,
+    /// The length of the WAVE file trailer stored after the audio data.
+    pub wav_tail_len: u32,
+    /// The byte position of every frame relative to the start of the stream marker.
+    pub seek_table: Vec<u32>,
+    /// The byte position of the first frame relative to the start of the stream marker.
+    pub first_frame_pos: u64,
+}
+
+impl ApeHeader {
+    /// Reads the descriptor, header, and seek table. The reader must be positioned after the
+    /// stream marker.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<ApeHeader> {
+        let version = reader.read_u16()?;
+
+        if version < APE_MIN_VERSION {
+            return unsupported_error("ape: file versions prior to 3.93 are not supported");
+        }
+
+        let mut header = if version >= APE_DESCRIPTOR_VERSION {
+            read_descriptor_and_header(reader, version)?
+        }
+        else {
+            read_old_header(reader, version)?
+        };
+
+        if CompressionLevel::from_u16(header.compression_level).is_none() {
+            return unsupported_error("ape: unsupported compression level");
+        }
+
+        if header.n_channels < 1 || header.n_channels > 2 {
+            return unsupported_error("ape: unsupported number of channels");
+        }
+
+        match header.bits_per_sample {
+            8 | 16 | 24 => (),
+            _ => return unsupported_error("ape: unsupported bits per sample"),
+        }
+
+        if header.sample_rate == 0 {
+            return decode_error("ape: invalid sample rate");
+        }
+
+        if header.blocks_per_frame == 0 || header.final_frame_blocks > header.blocks_per_frame {
+            return decode_error("ape: invalid number of blocks per frame");
+        }
+
+        if header.total_frames > 0 && header.seek_table.len() < header.total_frames as usize {
+            return decode_error("ape: seek table is too short");
+        }
+
+        // Only the seek table entries of frames that exist are used.
+        header.seek_table.truncate(header.total_frames as usize);
+
+        Ok(header)
+    }
+
+    /// Gets the total number of blocks (audio frames) in the stream.
+    pub fn n_blocks(&self) -> u64 {
+        match self.total_frames {
+            0 => 0,
+            n => {
+                u64::from(n - 1) * u64::from(self.blocks_per_frame)
+                    + u64::from(self.final_frame_blocks)
+            }
+        }
+    }
+
+    /// Gets the extra data passed to the decoder.
+    pub fn extra_data(&self) -> Box<[u8]> {
+        let mut buf = [0; APE_EXTRA_DATA_LEN];
+        buf[0..2].copy_from_slice(&self.version.to_le_bytes());
+        buf[2..4].copy_from_slice(&self.compression_level.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.format_flags.to_le_bytes());
+        Box::new(buf)
+    }
+}
+
+/// Reads the descriptor and header of files created by version 3.98 and later.
+fn read_descriptor_and_header<B: ReadBytes>(reader: &mut B, version: u16) -> Result<ApeHeader> {
+    // Padding.
+    reader.read_u16()?;
+
+    let descriptor_len = reader.read_u32()?;
+    let header_len = reader.read_u32()?;
+    let seek_table_len = reader.read_u32()?;
+    let wav_header_len = reader.read_u32()?;
+    let _audio_data_len = reader.read_u32()?;
+    let _audio_data_len_high = reader.read_u32()?;
+    let wav_tail_len = reader.read_u32()?;
+
+    // MD5 of the file data. It can only be verified by the reader, not by the decoder.
+    reader.ignore_bytes(16)?;
+
+    // The descriptor may be extended by future versions.
+    if descriptor_len < 52 {
+        return decode_error("ape: invalid descriptor length");
+    }
+
+    reader.ignore_bytes(u64::from(descriptor_len - 52))?;
+
+    let compression_level = reader.read_u16()?;
+    let format_flags = reader.read_u16()?;
+    let blocks_per_frame = reader.read_u32()?;
+    let final_frame_blocks = reader.read_u32()?;
+    let total_frames = reader.read_u32()?;
+    let bits_per_sample = u32::from(reader.read_u16()?);
+    let n_channels = u32::from(reader.read_u16()?);
+    let sample_rate = reader.read_u32()?;
+
+    // The header may also be extended by future versions.
+    if header_len < 24 {
+        return decode_error("ape: invalid header length");
+    }
+
+    reader.ignore_bytes(u64::from(header_len - 24))?;
+
+    let seek_table = read_seek_table(reader, seek_table_len / 4)?;
+
+    // The WAVE header follows the seek table.
+    reader.ignore_bytes(u64::from(wav_header_len))?;
+
+    let first_frame_pos = u64::from(descriptor_len)
+        + u64::from(header_len)
+        + u64::from(seek_table_len)
+        + u64::from(wav_header_len);
+
+    Ok(ApeHeader {
+        version,
+        compression_level,
+        format_flags,
+        blocks_per_frame,
+        final_frame_blocks,
+        total_frames,
+        bits_per_sample,
+        n_channels,
+        sample_rate,
+        wav_tail_len,
+        seek_table,
+        first_frame_pos,
+    })
+}
+
+/// Reads the header of files created by versions prior to 3.98.
+fn read_old_header<B: ReadBytes>(reader: &mut B, version: u16) -> Result<ApeHeader> {
+    let compression_level = reader.read_u16()?;
+    let format_flags = reader.read_u16()?;
+    let n_channels = u32::from(reader.read_u16()?);
+    let sample_rate = reader.read_u32()?;
+    let wav_header_len = reader.read_u32()?;
+    let wav_tail_len = reader.read_u32()?;
+    let total_frames = reader.read_u32()?;
+    let final_frame_blocks = reader.read_u32()?;
+
+    // The marker, version, and fixed header fields.
+    let mut header_len: u32 = 32;
+
+    if format_flags & APE_FORMAT_FLAG_HAS_PEAK_LEVEL != 0 {
+        reader.read_u32()?;
+        header_len += 4;
+    }
+
+    let n_seek_entries = if format_flags & APE_FORMAT_FLAG_HAS_SEEK_ELEMENTS != 0 {
+        header_len += 4;
+        reader.read_u32()?
+    }
+    else {
+        total_frames
+    };
+
+    let bits_per_sample = if format_flags & APE_FORMAT_FLAG_8_BIT != 0 {
+        8
+    }
+    else if format_flags & APE_FORMAT_FLAG_24_BIT != 0 {
+        24
+    }
+    else {
+        16
+    };
+
+    let blocks_per_frame = if version >= 3950 { 73728 * 4 } else { 73728 };
+
+    // The WAVE header precedes the seek table. If the header must be created, it was not stored.
+    let wav_header_len =
+        if format_flags & APE_FORMAT_FLAG_CREATE_WAV_HEADER == 0 { wav_header_len } else { 0 };
+
+    reader.ignore_bytes(u64::from(wav_header_len))?;
+
+    let seek_table = read_seek_table(reader, n_seek_entries)?;
+
+    let first_frame_pos =
+        u64::from(header_len) + u64::from(wav_header_len) + 4 * u64::from(n_seek_entries);
+
+    Ok(ApeHeader {
+        version,
+        compression_level,
+        format_flags,
+        blocks_per_frame,
+        final_frame_blocks,
+        total_frames,
+        bits_per_sample,
+        n_channels,
+        sample_rate,
+        wav_tail_len,
+        seek_table,
+        first_frame_pos,
+    })
+}
+
+fn read_seek_table<B: ReadBytes>(reader: &mut B, n_entries: u32) -> Result<Vec<u32>> {
+    // Each entry is 4 bytes, limit the allocation to a sane size.
+    if n_entries > 1 << 24 {
+        return decode_error("ape: seek table is too large");
+    }
+
+    let mut seek_table = Vec::with_capacity(n_entries as usize);
+
+    for _ in 0..n_entries {
+        seek_table.push(reader.read_u32()?);
+    }
+
+    Ok(seek_table)
+}
@@ -0,0 +1,219 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The number of samples processed before the history buffer is rewound.
+const HISTORY_LEN: usize = 512;
+
+/// The number of history elements required by the predictor.
+const PREDICTOR_LEN: usize = 50;
+
+const PREDICTOR_ORDER: usize = 8;
+
+// Offsets into the history buffer of the delayed samples, and adaption signs, of each stage of
+// the predictor for the Y (0) and X (1) channels.
+const DELAY_A: [usize; 2] = [18 + PREDICTOR_ORDER * 4, 18 + PREDICTOR_ORDER * 2];
+const DELAY_B: [usize; 2] = [18 + PREDICTOR_ORDER * 3, 18 + PREDICTOR_ORDER];
+const ADAPT_A: [usize; 2] = [18, 14];
+const ADAPT_B: [usize; 2] = [10, 5];
+
+/// The initial stage A coefficients.
+const INITIAL_COEFFS_A: [i32; 4] = [360, 317, -109, 98];
+
+/// Gets the inverted sign of a value: -1 for positive, 1 for negative, and 0 for zero.
+#[inline(always)]
+fn inv_sign(x: i32) -> i32 {
+    -x.signum()
+}
+
+/// The adaptive predictor used by files created by version 3.93 and later.
+#[derive(Clone)]
+pub struct Predictor {
+    version: u16,
+    history: [i32; HISTORY_LEN + PREDICTOR_LEN],
+    /// The position of the current sample in the history buffer.
+    pos: usize,
+    last_a: [i32; 2],
+    filter_a: [i32; 2],
+    filter_b: [i32; 2],
+    coeffs_a: [[i32; 4]; 2],
+    coeffs_b: [[i32; 5]; 2],
+}
+
+impl Predictor {
+    pub fn new(version: u16) -> Self {
+        Predictor {
+            version,
+            history: [0; HISTORY_LEN + PREDICTOR_LEN],
+            pos: 0,
+            last_a: [0; 2],
+            filter_a: [0; 2],
+            filter_b: [0; 2],
+            coeffs_a: [INITIAL_COEFFS_A; 2],
+            coeffs_b: [[0; 5]; 2],
+        }
+    }
+
+    /// Resets the predictor state.
+    pub fn reset(&mut self) {
+        *self = Predictor::new(self.version);
+    }
+
+    /// Applies the prediction to a mono channel in-place.
+    pub fn decode_mono(&mut self, samples: &mut [i32]) {
+        if self.version >= 3950 {
+            self.decode_mono_3950(samples);
+        }
+        else {
+            for sample in samples.iter_mut() {
+                *sample = self.update_3930(*sample, 0);
+                self.advance();
+            }
+        }
+    }
+
+    /// Applies the prediction to a pair of channels in-place.
+    pub fn decode_stereo(&mut self, y: &mut [i32], x: &mut [i32]) {
+        if self.version >= 3950 {
+            for (y, x) in y.iter_mut().zip(x.iter_mut()) {
+                *y = self.update_filter(*y, 0);
+                *x = self.update_filter(*x, 1);
+                self.advance();
+            }
+        }
+        else {
+            // Prior to version 3.95, the first channel in the bitstream is X.
+            for (a, b) in y.iter_mut().zip(x.iter_mut()) {
+                let (x, y) = (*a, *b);
+                *a = self.update_3930(y, 0);
+                *b = self.update_3930(x, 1);
+                self.advance();
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        self.pos += 1;
+
+        // Rewind the history buffer once it is exhausted.
+        if self.pos == HISTORY_LEN {
+            self.history.copy_within(HISTORY_LEN.., 0);
+            self.pos = 0;
+        }
+    }
+
+    fn update_3930(&mut self, value: i32, ch: usize) -> i32 {
+        let buf = &mut self.history[self.pos..];
+        let da = DELAY_A[ch];
+
+        buf[da] = self.last_a[ch];
+
+        let d0 = buf[da];
+        let d1 = buf[da].wrapping_sub(buf[da - 1]);
+        let d2 = buf[da - 1].wrapping_sub(buf[da - 2]);
+        let d3 = buf[da - 2].wrapping_sub(buf[da - 3]);
+
+        let coeffs = &mut self.coeffs_a[ch];
+
+        let prediction = d0
+            .wrapping_mul(coeffs[0])
+            .wrapping_add(d1.wrapping_mul(coeffs[1]))
+            .wrapping_add(d2.wrapping_mul(coeffs[2]))
+            .wrapping_add(d3.wrapping_mul(coeffs[3]));
+
+        self.last_a[ch] = value.wrapping_add(prediction >> 9);
+        self.filter_a[ch] = self.last_a[ch].wrapping_add(self.filter_a[ch].wrapping_mul(31) >> 5);
+
+        let sign = inv_sign(value);
+
+        for (c, d) in coeffs.iter_mut().zip(&[d0, d1, d2, d3]) {
+            *c = c.wrapping_add(if *d < 0 { sign } else { -sign });
+        }
+
+        self.filter_a[ch]
+    }
+
+    fn update_filter(&mut self, value: i32, ch: usize) -> i32 {
+        let buf = &mut self.history[self.pos..];
+        let (da, db, aa, ab) = (DELAY_A[ch], DELAY_B[ch], ADAPT_A[ch], ADAPT_B[ch]);
+
+        // Stage A: predict from the previous output of this channel.
+        buf[da] = self.last_a[ch];
+        buf[aa] = inv_sign(buf[da]);
+        buf[da - 1] = buf[da].wrapping_sub(buf[da - 1]);
+        buf[aa - 1] = inv_sign(buf[da - 1]);
+
+        let coeffs_a = &mut self.coeffs_a[ch];
+
+        let prediction_a =
+            (0..4).fold(0i32, |acc, i| acc.wrapping_add(buf[da - i].wrapping_mul(coeffs_a[i])));
+
+        // Stage B: predict from the scaled first-order filtered output of the other channel.
+        buf[db] = self.filter_a[ch ^ 1].wrapping_sub(self.filter_b[ch].wrapping_mul(31) >> 5);
+        buf[ab] = inv_sign(buf[db]);
+        buf[db - 1] = buf[db].wrapping_sub(buf[db - 1]);
+        buf[ab - 1] = inv_sign(buf[db - 1]);
+
+        self.filter_b[ch] = self.filter_a[ch ^ 1];
+
+        let coeffs_b = &mut self.coeffs_b[ch];
+
+        let prediction_b =
+            (0..5).fold(0i32, |acc, i| acc.wrapping_add(buf[db - i].wrapping_mul(coeffs_b[i])));
+
+        self.last_a[ch] = value.wrapping_add(prediction_a.wrapping_add(prediction_b >> 1) >> 10);
+        self.filter_a[ch] = self.last_a[ch].wrapping_add(self.filter_a[ch].wrapping_mul(31) >> 5);
+
+        // Adapt the coefficients.
+        let sign = inv_sign(value);
+
+        for (i, c) in coeffs_a.iter_mut().enumerate() {
+            *c = c.wrapping_add(buf[aa - i].wrapping_mul(sign));
+        }
+
+        for (i, c) in coeffs_b.iter_mut().enumerate() {
+            *c = c.wrapping_add(buf[ab - i].wrapping_mul(sign));
+        }
+
+        self.filter_a[ch]
+    }
+
+    fn decode_mono_3950(&mut self, samples: &mut [i32]) {
+        let mut current_a = self.last_a[0];
+
+        for sample in samples.iter_mut() {
+            let value = *sample;
+            let buf = &mut self.history[self.pos..];
+            let (da, aa) = (DELAY_A[0], ADAPT_A[0]);
+
+            buf[da] = current_a;
+            buf[da - 1] = buf[da].wrapping_sub(buf[da - 1]);
+
+            let coeffs = &mut self.coeffs_a[0];
+
+            let prediction =
+                (0..4).fold(0i32, |acc, i| acc.wrapping_add(buf[da - i].wrapping_mul(coeffs[i])));
+
+            current_a = value.wrapping_add(prediction >> 10);
+
+            buf[aa] = inv_sign(buf[da]);
+            buf[aa - 1] = inv_sign(buf[da - 1]);
+
+            let sign = inv_sign(value);
+
+            for (i, c) in coeffs.iter_mut().enumerate() {
+                *c = c.wrapping_add(buf[aa - i].wrapping_mul(sign));
+            }
+
+            self.advance();
+
+            self.filter_a[0] = current_a.wrapping_add(self.filter_a[0].wrapping_mul(31) >> 5);
+            *sample = self.filter_a[0];
+        }
+
+        self.last_a[0] = current_a;
+    }
+}
@@ -90,6 +90,10 @@ impl GenericAudioBuffer {
     fn clear(&mut self) {
         impl_generic_audio_buffer_func!(self, buf, buf.clear());
     }
+
+    fn trim(&mut self, start: usize, end: usize) {
+        impl_generic_audio_buffer_func!(self, buf, buf.trim(start, end));
+    }
 }
 
 impl AsAudioBufferRef for GenericAudioBuffer {
@@ -362,6 +366,8 @@ impl PcmDecoder {
             _ => unsupported_error("pcm: codec is unsupported"),
         };
 
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
         Ok(())
     }
 }
@@ -0,0 +1,486 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::checksum::Crc32Le;
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_TTA};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BitReaderRtl, Monitor, ReadBitsRtl};
+use symphonia_core::support_codec;
+use symphonia_core::units::TimeBase;
+
+use log::warn;
+
+use crate::header::frame_len;
+
+/// An adaptive Rice decoder.
+///
+/// Values are coded with one of two Rice parameters, `k0` and `k1`. A zero unary prefix selects
+/// `k0`, otherwise `k1` is used and the value is offset by `2^k0`.
+struct Rice {
+    k0: u32,
+    k1: u32,
+    sum0: u32,
+    sum1: u32,
+}
+
+impl Rice {
+    fn new() -> Self {
+        Rice { k0: 10, k1: 10, sum0: 1 << 14, sum1: 1 << 14 }
+    }
+
+    fn read<B: ReadBitsRtl>(&mut self, bs: &mut B) -> Result<u32> {
+        let unary = bs.read_unary_ones()?;
+
+        let (depth, unary, k) = if unary == 0 { (0, 0, self.k0) } else { (1, unary - 1, self.k1) };
+
+        let mut value =
+            if k > 0 { unary.wrapping_shl(k).wrapping_add(bs.read_bits_leq32(k)?) } else { unary };
+
+        if depth == 1 {
+            update_rice_param(&mut self.k1, &mut self.sum1, value);
+            value = value.wrapping_add(1 << self.k0);
+        }
+
+        update_rice_param(&mut self.k0, &mut self.sum0, value);
+
+        Ok(value)
+    }
+}
+
+#[inline(always)]
+fn update_rice_param(k: &mut u32, sum: &mut u32, value: u32) {
+    *sum = sum.wrapping_add(value).wrapping_sub(*sum >> 4);
+
+    if *k > 0 && u64::from(*sum) < 1 << (*k + 4) {
+        *k -= 1;
+    }
+    else if u64::from(*sum) > 1 << (*k + 5) {
+        *k += 1;
+    }
+}
+
+/// The adaptive hybrid filter.
+struct HybridFilter {
+    shift: u32,
+    round: i32,
+    error: i32,
+    qm: [i32; 8],
+    dx: [i32; 8],
+    dl: [i32; 8],
+}
+
+impl HybridFilter {
+    fn new(shift: u32) -> Self {
+        HybridFilter {
+            shift,
+            round: 1 << (shift - 1),
+            error: 0,
+            qm: [0; 8],
+            dx: [0; 8],
+            dl: [0; 8],
+        }
+    }
+
+    fn process(&mut self, value: i32) -> i32 {
+        let HybridFilter { qm, dx, dl, .. } = self;
+
+        // Adapt the coefficients in the direction that would have reduced the previous error.
+        if self.error < 0 {
+            qm.iter_mut().zip(dx.iter()).for_each(|(q, &d)| *q = q.wrapping_sub(d));
+        }
+        else if self.error > 0 {
+            qm.iter_mut().zip(dx.iter()).for_each(|(q, &d)| *q = q.wrapping_add(d));
+        }
+
+        let sum = qm
+            .iter()
+            .zip(dl.iter())
+            .fold(self.round, |acc, (&q, &d)| acc.wrapping_add(q.wrapping_mul(d)));
+
+        dx.copy_within(1..5, 0);
+        dl.copy_within(1..5, 0);
+
+        dx[4] = (dl[4] >> 30) | 1;
+        dx[5] = ((dl[5] >> 30) | 1) << 1;
+        dx[6] = ((dl[6] >> 30) | 1) << 1;
+        dx[7] = ((dl[7] >> 30) | 1) << 2;
+
+        self.error = value;
+
+        let output = value.wrapping_add(sum >> self.shift);
+
+        // Store the output, and its first, second, and third order differences.
+        dl[4] = dl[5].wrapping_neg();
+        dl[5] = dl[6].wrapping_neg();
+        dl[6] = output.wrapping_sub(dl[7]);
+        dl[7] = output;
+        dl[5] = dl[5].wrapping_add(dl[6]);
+        dl[4] = dl[4].wrapping_add(dl[5]);
+
+        output
+    }
+}
+
+/// The decoding state of a single channel.
+struct ChannelState {
+    rice: Rice,
+    filter: HybridFilter,
+    prev: i32,
+}
+
+impl ChannelState {
+    fn new(bits_per_sample: u32) -> Self {
+        // The filter precision depends on the sample size.
+        let shift = match bits_per_sample {
+            16 => 9,
+            _ => 10,
+        };
+
+        ChannelState { rice: Rice::new(), filter: HybridFilter::new(shift), prev: 0 }
+    }
+}
+
+/// True Audio (TTA) decoder.
+///
+/// Packets must contain one complete TTA1 frame including its trailing CRC. Each frame is verified
+/// against its CRC if verification is enabled. The number of samples in a frame is given by the
+/// packet duration or, if the packet has no duration, by the total number of frames in the stream.
+pub struct TtaDecoder {
+    params: CodecParameters,
+    bits_per_sample: u32,
+    frame_len: usize,
+    is_verifying: bool,
+    n_crc_errors: u64,
+    channels: Vec<ChannelState>,
+    buf: AudioBuffer<i32>,
+}
+
+impl TtaDecoder {
+    /// Converts a timestamp or duration in the time base of the stream to a number of samples.
+    fn ts_to_samples(&self, ts: u64) -> u64 {
+        let sample_rate = self.buf.spec().rate;
+
+        match self.params.time_base {
+            Some(tb) if tb != TimeBase::new(1, sample_rate) => {
+                let time = tb.calc_time(ts);
+                let sample_rate = f64::from(sample_rate);
+                time.seconds * sample_rate as u64 + (time.frac * sample_rate).round() as u64
+            }
+            _ => ts,
+        }
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let buf = packet.buf();
+
+        if buf.len() < 4 {
+            return decode_error("tta: frame is too short");
+        }
+
+        let (data, crc) = buf.split_at(buf.len() - 4);
+
+        if self.is_verifying {
+            let mut crc32 = Crc32Le::new(!0);
+            crc32.process_buf_bytes(data);
+
+            if !crc32.crc() != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+                warn!("tta: frame crc mismatch at ts={}", packet.ts());
+                self.n_crc_errors += 1;
+            }
+        }
+
+        // The final frame may be shorter than the others. If the packet has no duration, then the
+        // length of the final frame can only be derived from the total length of the stream.
+        let n_samples = match packet.block_dur() {
+            0 => match self.params.n_frames {
+                Some(n_frames) => {
+                    let end = self.ts_to_samples(n_frames);
+                    let start = self.ts_to_samples(packet.ts());
+
+                    if start >= end {
+                        return decode_error("tta: frame is beyond the end of the stream");
+                    }
+
+                    (end - start).min(self.frame_len as u64) as usize
+                }
+                None => return decode_error("tta: frame duration is required"),
+            },
+            dur => self.ts_to_samples(dur).min(self.frame_len as u64) as usize,
+        };
+
+        // Every frame is coded independently.
+        for state in self.channels.iter_mut() {
+            *state = ChannelState::new(self.bits_per_sample);
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_samples));
+
+        let n_channels = self.channels.len();
+
+        let mut bs = BitReaderRtl::new(data);
+
+        {
+            let mut planes = self.buf.planes_mut();
+            let planes = planes.planes();
+
+            for i in 0..n_samples {
+                for (ch, state) in self.channels.iter_mut().enumerate() {
+                    let value = state.rice.read(&mut bs)?;
+
+                    // Unsigned to signed: 0, 1, 2, 3, 4 map to 0, 1, -1, 2, -2.
+                    let value = if value & 1 != 0 {
+                        ((value >> 1) as i32).wrapping_add(1)
+                    }
+                    else {
+                        -((value >> 1) as i32)
+                    };
+
+                    let value = state.filter.process(value);
+
+                    // Fixed first-order prediction.
+                    let sample = match self.bits_per_sample {
+                        8 => value.wrapping_add(predict(state.prev, 4)),
+                        _ => value.wrapping_add(predict(state.prev, 5)),
+                    };
+
+                    state.prev = sample;
+                    planes[ch][i] = sample;
+                }
+
+                // Decorrelate the channels. The last channel is stored relative to the second last
+                // channel, and all others relative to the next channel.
+                if n_channels > 1 {
+                    let last =
+                        planes[n_channels - 1][i].wrapping_add(planes[n_channels - 2][i] / 2);
+                    planes[n_channels - 1][i] = last;
+
+                    for ch in (0..n_channels - 1).rev() {
+                        planes[ch][i] = planes[ch + 1][i].wrapping_sub(planes[ch][i]);
+                    }
+                }
+            }
+
+            // Scale the samples to the full range of an i32.
+            let shift = 32 - self.bits_per_sample;
+
+            for plane in planes.iter_mut() {
+                for sample in plane.iter_mut() {
+                    *sample = sample.wrapping_shl(shift);
+                }
+            }
+        }
+
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+        Ok(())
+    }
+}
+
+/// Predicts the next sample as `prev * (2^k - 1) / 2^k`.
+#[inline(always)]
+fn predict(prev: i32, k: u32) -> i32 {
+    (((i64::from(prev) << k) - i64::from(prev)) >> k) as i32
+}
+
+impl Decoder for TtaDecoder {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+        // This decoder only supports TTA.
+        if params.codec != CODEC_TYPE_TTA {
+            return unsupported_error("tta: invalid codec type");
+        }
+
+        let sample_rate = match params.sample_rate {
+            Some(sample_rate) if frame_len(sample_rate) > 0 => sample_rate,
+            Some(_) => return unsupported_error("tta: invalid sample rate"),
+            _ => return unsupported_error("tta: sample rate is required"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) => channels,
+            _ => return unsupported_error("tta: channels are required"),
+        };
+
+        let bits_per_sample = match params.bits_per_sample {
+            Some(bps @ 8) | Some(bps @ 16) | Some(bps @ 24) => bps,
+            Some(_) => return unsupported_error("tta: unsupported bits per sample"),
+            _ => return unsupported_error("tta: bits per sample is required"),
+        };
+
+        let frame_len = frame_len(sample_rate) as usize;
+
+        let spec = SignalSpec::new(sample_rate, channels);
+
+        Ok(TtaDecoder {
+            params: params.clone(),
+            bits_per_sample,
+            frame_len,
+            is_verifying: options.verify,
+            n_crc_errors: 0,
+            channels: (0..channels.count()).map(|_| ChannelState::new(bits_per_sample)).collect(),
+            buf: AudioBuffer::new(frame_len as u64, spec),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_TTA, "tta", "True Audio")]
+    }
+
+    fn reset(&mut self) {
+        // The decoder state is reset at the start of every frame, therefore do nothing.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        let mut result: FinalizeResult = Default::default();
+
+        // If verifying, every decoded frame must have matched its CRC.
+        if self.is_verifying {
+            if self.n_crc_errors > 0 {
+                warn!("verification: {} frame(s) failed the crc check", self.n_crc_errors);
+            }
+
+            result.verify_ok = Some(self.n_crc_errors == 0);
+        }
+
+        result
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_TTA};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::units::TimeBase;
+
+    use super::TtaDecoder;
+
+    /// A 16 sample, 16-bit stereo TTA1 frame with its trailing CRC.
+    #[rustfmt::skip]
+    const FRAME: [u8; 50] = [
+        0x8d, 0x5e, 0xc6, 0x01, 0x4e, 0x54, 0x1f, 0xab, 0x84, 0x2e, 0x96, 0xd8, 0x54,
+        0x84, 0x2e, 0xad, 0xae, 0x0b, 0xe4, 0xdc, 0xa8, 0x0b, 0x1c, 0x9b, 0xec, 0x4d,
+        0xde, 0x28, 0x0c, 0xde, 0xf4, 0x32, 0x07, 0xc1, 0xd1, 0xbd, 0xd1, 0x4a, 0xde,
+        0x80, 0xa5, 0xde, 0xc7, 0xc5, 0x9c, 0x64, 0x29, 0xc5, 0x32, 0xdf,
+    ];
+
+    #[rustfmt::skip]
+    const DECODED: [[i16; 16]; 2] = [
+        [
+            420, 173, 425, 417, 520, 755, 656, 1235,
+            876, 728, 329, 375, 1878, 1989, 2358, 770,
+        ],
+        [
+            1398, 433, 1330, 1076, 1549, 1998, 1866, 3252,
+            2458, 1744, 650, 578, 1125, 1673, 2677, 2054,
+        ],
+    ];
+
+    fn params() -> CodecParameters {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_TTA)
+            .with_sample_rate(44100)
+            .with_time_base(TimeBase::new(1, 44100))
+            .with_bits_per_sample(16)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+        params
+    }
+
+    fn verify_decoded(decoded: AudioBufferRef<'_>) {
+        let decoded = match decoded {
+            AudioBufferRef::S32(buf) => buf,
+            _ => panic!("expected s32 samples"),
+        };
+
+        assert_eq!(decoded.frames(), 16);
+
+        for (ch, expected) in DECODED.iter().enumerate() {
+            let samples: Vec<i16> = decoded.chan(ch).iter().map(|&s| (s >> 16) as i16).collect();
+            assert_eq!(&samples, expected);
+        }
+    }
+
+    #[test]
+    fn verify_decode_final_frame() {
+        let options = DecoderOptions { verify: true };
+        let mut decoder = TtaDecoder::try_new(&params(), &options).unwrap();
+
+        let packet = Packet::new_from_slice(0, 46080, 16, &FRAME);
+        verify_decoded(decoder.decode(&packet).unwrap());
+
+        assert_eq!(decoder.finalize().verify_ok, Some(true));
+    }
+
+    #[test]
+    fn verify_decode_crc_mismatch() {
+        let options = DecoderOptions { verify: true };
+        let mut decoder = TtaDecoder::try_new(&params(), &options).unwrap();
+
+        let mut frame = FRAME;
+        frame[49] ^= 1;
+
+        // A corrupt CRC does not prevent decoding, but fails verification.
+        let packet = Packet::new_from_slice(0, 0, 16, &frame);
+        verify_decoded(decoder.decode(&packet).unwrap());
+
+        assert_eq!(decoder.finalize().verify_ok, Some(false));
+    }
+
+    #[test]
+    fn verify_decode_without_duration() {
+        let mut params = params();
+        params.with_n_frames(46080 + 16);
+
+        let mut decoder = TtaDecoder::try_new(&params, &Default::default()).unwrap();
+
+        // The length of the final frame is derived from the total number of frames.
+        let packet = Packet::new_from_slice(0, 46080, 0, &FRAME);
+        verify_decoded(decoder.decode(&packet).unwrap());
+
+        // A frame past the end of the stream, or in a stream of unknown length, is rejected.
+        let packet = Packet::new_from_slice(0, 2 * 46080, 0, &FRAME);
+        assert!(decoder.decode(&packet).is_err());
+
+        let mut decoder = TtaDecoder::try_new(&self::params(), &Default::default()).unwrap();
+        assert!(decoder.decode(&packet).is_err());
+    }
+
+    #[test]
+    fn verify_decode_other_time_base() {
+        let mut params = params();
+        params.with_time_base(TimeBase::new(1, 88200));
+
+        let mut decoder = TtaDecoder::try_new(&params, &Default::default()).unwrap();
+
+        // The packet duration is converted from the time base of the stream to samples.
+        let packet = Packet::new_from_slice(0, 2 * 46080, 32, &FRAME);
+        verify_decoded(decoder.decode(&packet).unwrap());
+    }
+}
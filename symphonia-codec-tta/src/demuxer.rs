@@ -0,0 +1,394 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::checksum::Crc32Le;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_TTA};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::{apev2, id3v1};
+
+use log::{debug, warn};
+
+use crate::header::{TtaHeader, TTA_STREAM_MARKER};
+
+/// The length of an ID3v1 tag.
+const ID3V1_TAG_LEN: u64 = 128;
+
+/// A TTA frame.
+struct TtaFrame {
+    /// The byte position of the frame.
+    pos: u64,
+    /// The length of the frame in bytes, including the trailing CRC.
+    len: u32,
+    /// The number of samples per channel in the frame.
+    n_samples: u32,
+}
+
+/// True Audio (TTA) format reader.
+///
+/// `TtaReader` implements a demuxer for unencrypted TTA1 streams.
+pub struct TtaReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    frames: Vec<TtaFrame>,
+    frame_len: u64,
+    next_frame: usize,
+}
+
+impl QueryDescriptor for TtaReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!("tta", "True Audio", &["tta"], &["audio/tta", "audio/x-tta"], &[b"TTA1"])]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for TtaReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let marker = source.read_quad_bytes()?;
+
+        if marker != TTA_STREAM_MARKER {
+            return unsupported_error("tta: missing tta1 stream marker");
+        }
+
+        let header = TtaHeader::read(&mut source)?;
+
+        debug!("{:?}", header);
+
+        let frames = read_seek_table(&mut source, &header)?;
+
+        let mut metadata = MetadataLog::default();
+
+        // Tags are stored at the end of the stream, and can only be read if the stream is
+        // seekable.
+        if source.is_seekable() {
+            let data_start = source.pos();
+            read_trailing_tags(&mut source, &mut metadata)?;
+            source.seek(SeekFrom::Start(data_start))?;
+        }
+
+        // When the channel count is >2 then enable the first N channels in WAVE order.
+        let channels = match Channels::from_bits(((1u64 << header.n_channels) - 1) as u32) {
+            Some(channels) => channels,
+            None => return unsupported_error("tta: unsupported number of channels"),
+        };
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_TTA)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_bits_per_sample(u32::from(header.bits_per_sample))
+            .with_channels(channels)
+            .with_n_frames(u64::from(header.n_samples))
+            .with_max_frames_per_packet(u64::from(header.frame_len()));
+
+        Ok(TtaReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata,
+            frames,
+            frame_len: u64::from(header.frame_len()),
+            next_frame: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame = match self.frames.get(self.next_frame) {
+            Some(frame) => frame,
+            None => return end_of_stream_error(),
+        };
+
+        let pos = self.reader.pos();
+
+        if frame.pos > pos {
+            self.reader.ignore_bytes(frame.pos - pos)?;
+        }
+        else if frame.pos < pos {
+            self.reader.seek(SeekFrom::Start(frame.pos))?;
+        }
+
+        let data = self.reader.read_boxed_slice_exact(frame.len as usize)?;
+
+        let ts = self.next_frame as u64 * self.frame_len;
+        let dur = u64::from(frame.n_samples);
+
+        self.next_frame += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, data))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                if let Some(sample_rate) = params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        if self.frames.is_empty() {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // Frames are always seeked to, so the actual timestamp is the start of the frame
+        // containing the required timestamp.
+        let index = ((required_ts / self.frame_len) as usize).min(self.frames.len() - 1);
+        let pos = self.frames[index].pos;
+
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if pos >= current_pos {
+                self.reader.ignore_bytes(pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_frame = index;
+
+        let actual_ts = index as u64 * self.frame_len;
+
+        debug!("seeked to frame={} actual_ts={}, required_ts={}", index, actual_ts, required_ts);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// Reads the seek table following the header, and builds the list of frames from it.
+fn read_seek_table(reader: &mut MediaSourceStream, header: &TtaHeader) -> Result<Vec<TtaFrame>> {
+    let n_frames = header.n_frames();
+
+    // Each entry is 4 bytes, limit the allocation to a sane size.
+    if n_frames > 1 << 24 {
+        return decode_error("tta: seek table is too large");
+    }
+
+    let mut sizes = Vec::with_capacity(n_frames as usize);
+    let mut crc32 = Crc32Le::new(!0);
+
+    for _ in 0..n_frames {
+        let size = reader.read_u32()?;
+        crc32.process_buf_bytes(&size.to_le_bytes());
+        sizes.push(size);
+    }
+
+    // A corrupt seek table is not fatal since the frame sizes may still be correct.
+    if !crc32.crc() != reader.read_u32()? {
+        warn!("tta: seek table crc mismatch");
+    }
+
+    let frame_len = header.frame_len();
+
+    let mut pos = reader.pos();
+    let mut frames = Vec::with_capacity(sizes.len());
+
+    for (i, &len) in sizes.iter().enumerate() {
+        // Every frame contains at least a 4 byte CRC.
+        if len < 4 {
+            return decode_error("tta: invalid frame size");
+        }
+
+        let n_samples = if i + 1 < sizes.len() {
+            frame_len
+        }
+        else {
+            header.n_samples - (n_frames - 1) * frame_len
+        };
+
+        frames.push(TtaFrame { pos, len, n_samples });
+
+        pos += u64::from(len);
+    }
+
+    Ok(frames)
+}
+
+/// Reads the ID3v1 and APE tags at the end of a seekable stream.
+fn read_trailing_tags(reader: &mut MediaSourceStream, metadata: &mut MetadataLog) -> Result<()> {
+    let mut end = reader.seek(SeekFrom::End(0))?;
+
+    // An ID3v1 tag is always the last 128 bytes of the stream.
+    if end >= ID3V1_TAG_LEN {
+        reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+        if reader.read_triple_bytes()? == *b"TAG" {
+            reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+            let mut builder = MetadataBuilder::new();
+
+            match id3v1::read_id3v1(reader, &mut builder) {
+                Ok(_) => metadata.push(builder.metadata()),
+                Err(err) => warn!("tta: failed to read id3v1 tag: {}", err),
+            }
+
+            end -= ID3V1_TAG_LEN;
+        }
+    }
+
+    // An APE tag preceeds the ID3v1 tag, if there is one.
+    let mut builder = MetadataBuilder::new();
+
+    match apev2::read_ape_tag_before(reader, end, &mut builder) {
+        Ok(Some(_)) => metadata.push(builder.metadata()),
+        Ok(None) => (),
+        Err(err) => warn!("tta: failed to read ape tag: {}", err),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::Channels;
+    use symphonia_core::checksum::Crc32Le;
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, Monitor};
+
+    use super::TtaReader;
+
+    /// The frame sizes of a stream with a sample rate of 245 Hz, and therefore 256 samples per
+    /// frame, and 600 samples in total.
+    const FRAME_SIZES: [u32; 3] = [10, 12, 8];
+
+    fn crc32(buf: &[u8]) -> [u8; 4] {
+        let mut crc32 = Crc32Le::new(!0);
+        crc32.process_buf_bytes(buf);
+        (!crc32.crc()).to_le_bytes()
+    }
+
+    fn tta_stream(sizes: &[u32]) -> Vec<u8> {
+        let mut buf = b"TTA1".to_vec();
+        buf.extend(1u16.to_le_bytes().iter());
+        buf.extend(2u16.to_le_bytes().iter());
+        buf.extend(16u16.to_le_bytes().iter());
+        buf.extend(245u32.to_le_bytes().iter());
+        buf.extend(600u32.to_le_bytes().iter());
+        let crc = crc32(&buf);
+        buf.extend(crc.iter());
+
+        let table: Vec<u8> = sizes.iter().flat_map(|size| size.to_le_bytes().to_vec()).collect();
+        buf.extend(table.iter());
+        buf.extend(crc32(&table).iter());
+
+        // Fill each frame with its index.
+        for (i, &size) in sizes.iter().enumerate() {
+            buf.extend(std::iter::repeat(i as u8).take(size as usize));
+        }
+
+        buf
+    }
+
+    fn read_tta(buf: Vec<u8>) -> Result<TtaReader, Error> {
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        TtaReader::try_new(source, &Default::default())
+    }
+
+    #[test]
+    fn verify_read_frames() {
+        let mut reader = read_tta(tta_stream(&FRAME_SIZES)).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.sample_rate, Some(245));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT | Channels::FRONT_RIGHT));
+        assert_eq!(params.bits_per_sample, Some(16));
+        assert_eq!(params.n_frames, Some(600));
+        assert_eq!(params.max_frames_per_packet, Some(256));
+
+        // The final frame contains the remaining samples.
+        for (i, (&size, &dur)) in FRAME_SIZES.iter().zip([256, 256, 88].iter()).enumerate() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts(), 256 * i as u64);
+            assert_eq!(packet.dur(), dur);
+            assert_eq!(packet.buf(), vec![i as u8; size as usize].as_slice());
+        }
+
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_seek() {
+        let mut reader = read_tta(tta_stream(&FRAME_SIZES)).unwrap();
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 300, track_id: 0 });
+        let seeked = seeked.unwrap();
+        assert_eq!((seeked.actual_ts, seeked.required_ts), (256, 300));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts(), 256);
+        assert_eq!(packet.buf(), [1; 12]);
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 601, track_id: 0 });
+        assert!(seeked.is_err());
+    }
+
+    #[test]
+    fn verify_invalid_streams() {
+        // The header CRC must match.
+        let mut buf = tta_stream(&FRAME_SIZES);
+        buf[18] ^= 1;
+        assert!(read_tta(buf).is_err());
+
+        // Every frame must contain at least a CRC.
+        assert!(read_tta(tta_stream(&[10, 3, 8])).is_err());
+
+        // The seek table must contain an entry for every frame.
+        let mut buf = tta_stream(&FRAME_SIZES);
+        buf.truncate(22 + 8);
+        assert!(read_tta(buf).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::checksum::Crc32Le;
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{Monitor, ReadBytes};
+
+/// The TTA1 stream marker: "TTA1" in ASCII.
+pub const TTA_STREAM_MARKER: [u8; 4] = *b"TTA1";
+
+/// Samples are stored as integer PCM.
+const TTA_FORMAT_SIMPLE: u16 = 1;
+/// Samples are stored as integer PCM, and the stream is encrypted with a password.
+const TTA_FORMAT_ENCRYPTED: u16 = 2;
+
+/// Gets the number of samples per channel in every frame except the last, given the sample rate.
+/// Each frame is approximately 1.045 seconds long.
+pub fn frame_len(sample_rate: u32) -> u32 {
+    ((u64::from(sample_rate) * 256) / 245) as u32
+}
+
+/// The TTA1 stream header.
+#[derive(Debug)]
+pub struct TtaHeader {
+    /// The number of channels.
+    pub n_channels: u16,
+    /// The number of bits per sample.
+    pub bits_per_sample: u16,
+    /// The sample rate.
+    pub sample_rate: u32,
+    /// The total number of samples per channel.
+    pub n_samples: u32,
+}
+
+impl TtaHeader {
+    /// Reads the header. The reader must be positioned after the stream marker.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<TtaHeader> {
+        let mut buf = [0; 18];
+        buf[..4].copy_from_slice(&TTA_STREAM_MARKER);
+        reader.read_buf_exact(&mut buf[4..])?;
+
+        // The header CRC covers the stream marker and all the fields preceeding it.
+        let mut crc32 = Crc32Le::new(!0);
+        crc32.process_buf_bytes(&buf);
+
+        if !crc32.crc() != reader.read_u32()? {
+            return decode_error("tta: header crc mismatch");
+        }
+
+        let format = u16::from_le_bytes([buf[4], buf[5]]);
+        let n_channels = u16::from_le_bytes([buf[6], buf[7]]);
+        let bits_per_sample = u16::from_le_bytes([buf[8], buf[9]]);
+        let sample_rate = u32::from_le_bytes([buf[10], buf[11], buf[12], buf[13]]);
+        let n_samples = u32::from_le_bytes([buf[14], buf[15], buf[16], buf[17]]);
+
+        match format {
+            TTA_FORMAT_SIMPLE => (),
+            TTA_FORMAT_ENCRYPTED => return unsupported_error("tta: encrypted streams"),
+            _ => return unsupported_error("tta: unsupported format"),
+        }
+
+        if n_channels < 1 || n_channels > 32 {
+            return unsupported_error("tta: unsupported number of channels");
+        }
+
+        match bits_per_sample {
+            8 | 16 | 24 => (),
+            _ => return unsupported_error("tta: unsupported bits per sample"),
+        }
+
+        if frame_len(sample_rate) == 0 {
+            return decode_error("tta: invalid sample rate");
+        }
+
+        Ok(TtaHeader { n_channels, bits_per_sample, sample_rate, n_samples })
+    }
+
+    /// Gets the number of samples per channel in every frame except the last.
+    pub fn frame_len(&self) -> u32 {
+        frame_len(self.sample_rate)
+    }
+
+    /// Gets the total number of frames.
+    pub fn n_frames(&self) -> u32 {
+        let frame_len = self.frame_len();
+        self.n_samples / frame_len + u32::from(self.n_samples % frame_len != 0)
+    }
+}
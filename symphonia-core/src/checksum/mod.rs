@@ -14,6 +14,6 @@ mod crc8;
 mod md5;
 
 pub use crc16::{Crc16Ansi, Crc16AnsiLe};
-pub use crc32::Crc32;
+pub use crc32::{Crc32, Crc32Le};
 pub use crc8::Crc8Ccitt;
 pub use md5::Md5;
@@ -177,6 +177,22 @@ pub const CODEC_TYPE_ADPCM_MS: CodecType = CodecType(0x203);
 pub const CODEC_TYPE_ADPCM_IMA_WAV: CodecType = CodecType(0x204);
 /// ADPCM IMA QuickTime
 pub const CODEC_TYPE_ADPCM_IMA_QT: CodecType = CodecType(0x205);
+/// ADPCM IMA DVI (headerless 4-bit stream)
+pub const CODEC_TYPE_ADPCM_IMA_DVI: CodecType = CodecType(0x206);
+/// OKI/Dialogic ADPCM
+pub const CODEC_TYPE_ADPCM_OKI: CodecType = CodecType(0x207);
+
+// DSD audio codecs
+//-----------------
+
+/// 1-bit Direct Stream Digital (DSD), least-significant bit first, interleaved bytes
+pub const CODEC_TYPE_DSD_LSBF: CodecType = CodecType(0x300);
+/// 1-bit Direct Stream Digital (DSD), least-significant bit first, planar bytes
+pub const CODEC_TYPE_DSD_LSBF_PLANAR: CodecType = CodecType(0x301);
+/// 1-bit Direct Stream Digital (DSD), most-significant bit first, interleaved bytes
+pub const CODEC_TYPE_DSD_MSBF: CodecType = CodecType(0x302);
+/// 1-bit Direct Stream Digital (DSD), most-significant bit first, planar bytes
+pub const CODEC_TYPE_DSD_MSBF_PLANAR: CodecType = CodecType(0x303);
 
 // Compressed lossy audio codecs
 //------------------------------
@@ -205,7 +221,7 @@ pub const CODEC_TYPE_ATRAC3: CodecType = CodecType(0x1009);
 pub const CODEC_TYPE_ATRAC3PLUS: CodecType = CodecType(0x100a);
 /// Adaptive Transform Acoustic Coding 9 (ATRAC9)
 pub const CODEC_TYPE_ATRAC9: CodecType = CodecType(0x100b);
-/// AC-3, E-AC-3, Dolby Digital (ATSC A/52)
+/// Enhanced AC-3 (E-AC-3), Dolby Digital Plus (ATSC A/52 Annex E)
 pub const CODEC_TYPE_EAC3: CodecType = CodecType(0x100c);
 /// Dolby AC-4 (ETSI TS 103 190)
 pub const CODEC_TYPE_AC4: CodecType = CodecType(0x100d);
@@ -213,6 +229,20 @@ pub const CODEC_TYPE_AC4: CodecType = CodecType(0x100d);
 pub const CODEC_TYPE_DCA: CodecType = CodecType(0x100e);
 /// Windows Media Audio
 pub const CODEC_TYPE_WMA: CodecType = CodecType(0x100f);
+/// GSM 06.10 full-rate speech
+pub const CODEC_TYPE_GSM: CodecType = CodecType(0x1010);
+/// GSM 06.10 full-rate speech, Microsoft variant with 2 frames packed into 65 bytes
+pub const CODEC_TYPE_GSM_MS: CodecType = CodecType(0x1011);
+/// Macintosh Audio Compression/Expansion (MACE) 3:1
+pub const CODEC_TYPE_MACE3: CodecType = CodecType(0x1012);
+/// Macintosh Audio Compression/Expansion (MACE) 6:1
+pub const CODEC_TYPE_MACE6: CodecType = CodecType(0x1013);
+/// AC-3, Dolby Digital (ATSC A/52)
+pub const CODEC_TYPE_AC3: CodecType = CodecType(0x1014);
+/// Adaptive Multi-Rate Narrowband (AMR-NB, 3GPP TS 26.090)
+pub const CODEC_TYPE_AMRNB: CodecType = CodecType(0x1015);
+/// Adaptive Multi-Rate Wideband (AMR-WB, 3GPP TS 26.190)
+pub const CODEC_TYPE_AMRWB: CodecType = CodecType(0x1016);
 
 // Compressed lossless audio codecs
 //---------------------------------
@@ -0,0 +1,539 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `cue` module provides an adapter to present the `Cue`s of a `FormatReader` as individual
+//! virtual tracks.
+
+use crate::codecs::CodecParameters;
+use crate::errors::{end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind};
+use crate::io::MediaSourceStream;
+use crate::meta::{Metadata, MetadataBuilder, MetadataLog, Tag};
+use crate::units::TimeBase;
+
+use super::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track};
+
+/// A `CueTrack` describes a virtual track formed by the range of a source track between the
+/// timestamp of a `Cue` and the timestamp of the next `Cue`, or the end of the source track.
+#[derive(Clone, Debug)]
+pub struct CueTrack {
+    /// The index of the `Cue` the virtual track was derived from.
+    pub cue_index: u32,
+    /// The timestamp of the first frame of the virtual track in the source track's `TimeBase`
+    /// units.
+    pub start_ts: u64,
+    /// The duration of the virtual track in the source track's `TimeBase` units. May be unknown if
+    /// this is the last virtual track and the length of the source track is unknown.
+    pub dur: Option<u64>,
+    /// The `Tag`s of the `Cue` the virtual track was derived from.
+    pub tags: Vec<Tag>,
+}
+
+impl CueTrack {
+    /// Get the timestamp of the frame after the last frame of the virtual track in the source
+    /// track's `TimeBase` units, if known.
+    pub fn end_ts(&self) -> Option<u64> {
+        self.dur.map(|dur| self.start_ts + dur)
+    }
+}
+
+/// Convert a frame timestamp to a timestamp in `TimeBase` units.
+fn frames_to_ts(params: &CodecParameters, frames: u64) -> u64 {
+    match (params.sample_rate, params.time_base) {
+        (Some(sample_rate), Some(tb)) if tb != TimeBase::new(1, sample_rate) => {
+            let ts = u128::from(frames) * u128::from(tb.denom)
+                / (u128::from(sample_rate) * u128::from(tb.numer));
+            ts as u64
+        }
+        _ => frames,
+    }
+}
+
+/// Convert a timestamp in `TimeBase` units to a number of frames.
+fn ts_to_frames(params: &CodecParameters, ts: u64) -> u64 {
+    match (params.sample_rate, params.time_base) {
+        (Some(sample_rate), Some(tb)) if tb != TimeBase::new(1, sample_rate) => {
+            let frames = u128::from(ts) * u128::from(sample_rate) * u128::from(tb.numer)
+                / u128::from(tb.denom);
+            frames as u64
+        }
+        _ => ts,
+    }
+}
+
+/// Derive the virtual tracks formed by a list of `Cue`s on a source track.
+///
+/// Each virtual track spans from the timestamp of a `Cue` up-to the timestamp of the next `Cue`.
+/// The last virtual track spans up-to the end of the source track. Virtual tracks with a duration
+/// of 0, such as the one formed by a lead-out `Cue`, are omitted.
+///
+/// `Cue`s are relative to the start of the stream, while the timestamps of the virtual tracks are
+/// offset by the start timestamp of the source track.
+pub fn cue_tracks(params: &CodecParameters, cues: &[Cue]) -> Vec<CueTrack> {
+    let mut sorted: Vec<&Cue> = cues.iter().collect();
+    sorted.sort_by_key(|cue| cue.start_ts);
+
+    let to_ts = |frames: u64| params.start_ts + frames_to_ts(params, frames);
+
+    let n_frames = params.n_frames.map(to_ts);
+
+    let mut tracks = Vec::with_capacity(sorted.len());
+
+    for (i, cue) in sorted.iter().enumerate() {
+        let start_ts = to_ts(cue.start_ts);
+
+        let end_ts = match sorted.get(i + 1) {
+            Some(next) => Some(to_ts(next.start_ts)),
+            None => n_frames,
+        };
+
+        // A cue starting after the end of the source track is invalid.
+        let dur = match end_ts {
+            Some(end_ts) if end_ts <= start_ts => continue,
+            Some(end_ts) => Some(end_ts - start_ts),
+            None => None,
+        };
+
+        tracks.push(CueTrack { cue_index: cue.index, start_ts, dur, tags: cue.tags.clone() });
+    }
+
+    tracks
+}
+
+/// A `CueTrackReader` is a `FormatReader` adapter that presents a range of a source track, as
+/// defined by a `Cue`, as if it were an individual track.
+///
+/// The virtual track has its own timeline starting at 0, a duration, and metadata consisting of
+/// the source's metadata amended with the tags of the `Cue`. Seeks are relative to the start of the
+/// virtual track, and packets straddling the boundaries of the virtual track are trimmed. Only
+/// packets belonging to the source track are returned.
+///
+/// After selecting a new virtual track with [`CueTrackReader::select`], the `Decoder` consuming
+/// packets from the reader should be reset.
+pub struct CueTrackReader {
+    reader: Box<dyn FormatReader>,
+    cue_tracks: Vec<CueTrack>,
+    source_cues: Vec<Cue>,
+    cues: Vec<Cue>,
+    source: Track,
+    tracks: Vec<Track>,
+    metadata: MetadataLog,
+    current: usize,
+}
+
+impl CueTrackReader {
+    /// Instantiate a `CueTrackReader` presenting the `Cue`s of the default track of the provided
+    /// `FormatReader` as virtual tracks. The first virtual track is selected.
+    pub fn new(reader: Box<dyn FormatReader>) -> Result<Self> {
+        let cues = reader.cues().to_vec();
+        Self::with_cues(reader, cues)
+    }
+
+    /// Instantiate a `CueTrackReader` presenting the provided `Cue`s, such as those read from an
+    /// external cue sheet, as virtual tracks of the default track of the provided `FormatReader`.
+    /// The first virtual track is selected.
+    pub fn with_cues(reader: Box<dyn FormatReader>, cues: Vec<Cue>) -> Result<Self> {
+        let source = match reader.default_track() {
+            Some(track) => track.clone(),
+            None => return unsupported_error("cue: no source track"),
+        };
+
+        let cue_tracks = cue_tracks(&source.codec_params, &cues);
+
+        if cue_tracks.is_empty() {
+            return unsupported_error("cue: no cues");
+        }
+
+        let mut cue_reader = CueTrackReader {
+            reader,
+            cue_tracks,
+            source_cues: cues,
+            cues: Vec::new(),
+            source,
+            tracks: Vec::new(),
+            metadata: Default::default(),
+            current: 0,
+        };
+
+        cue_reader.select(0)?;
+
+        Ok(cue_reader)
+    }
+
+    /// Gets a list of all virtual tracks.
+    pub fn cue_tracks(&self) -> &[CueTrack] {
+        &self.cue_tracks
+    }
+
+    /// Gets the index of the selected virtual track.
+    pub fn selected(&self) -> usize {
+        self.current
+    }
+
+    /// Select the virtual track at the provided index into the list of virtual tracks. The source
+    /// is seeked to the start of the virtual track.
+    pub fn select(&mut self, index: usize) -> Result<()> {
+        let cue_track = match self.cue_tracks.get(index) {
+            Some(cue_track) => cue_track,
+            None => return seek_error(SeekErrorKind::OutOfRange),
+        };
+
+        self.reader.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp { ts: cue_track.start_ts, track_id: self.source.id },
+        )?;
+
+        // The codec parameters of the virtual track are those of the source track, but with its
+        // own timeline. Verification checks apply to the entire source track, and are therefore
+        // dropped.
+        let mut codec_params = self.source.codec_params.clone();
+
+        codec_params.start_ts = 0;
+        codec_params.n_frames = cue_track.dur;
+        codec_params.verification_check = None;
+
+        let mut track = Track::new(self.source.id, codec_params);
+        track.language = self.source.language.clone();
+
+        self.tracks = vec![track];
+
+        // The cues of the virtual track are the cue it was derived from, rebased to the start of the
+        // virtual track.
+        self.cues = self
+            .source_cues
+            .iter()
+            .find(|cue| cue.index == cue_track.cue_index)
+            .map(|cue| Cue { start_ts: 0, ..cue.clone() })
+            .into_iter()
+            .collect();
+
+        // The metadata of the virtual track is the current metadata of the source, with any tags
+        // provided by the cue replacing those of the source with the same standard key.
+        let mut builder = MetadataBuilder::new();
+
+        if let Some(rev) = self.reader.metadata().current() {
+            let is_replaced = |tag: &Tag| {
+                tag.std_key.is_some()
+                    && cue_track.tags.iter().any(|cue_tag| cue_tag.std_key == tag.std_key)
+            };
+
+            for tag in rev.tags().iter().filter(|tag| !is_replaced(tag)) {
+                builder.add_tag(tag.clone());
+            }
+
+            for visual in rev.visuals() {
+                builder.add_visual(visual.clone());
+            }
+
+            for vendor_data in rev.vendor_data() {
+                builder.add_vendor_data(vendor_data.clone());
+            }
+        }
+
+        for tag in cue_track.tags.iter() {
+            builder.add_tag(tag.clone());
+        }
+
+        self.metadata.push(builder.metadata());
+
+        self.current = index;
+
+        Ok(())
+    }
+
+    /// Gets an immutable reference to the source `FormatReader`.
+    pub fn reader(&self) -> &dyn FormatReader {
+        self.reader.as_ref()
+    }
+
+    /// Destroys the `CueTrackReader` and returns the source `FormatReader`.
+    pub fn into_reader(self) -> Box<dyn FormatReader> {
+        self.reader
+    }
+}
+
+impl FormatReader for CueTrackReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // A `CueTrackReader` can only be instantiated from another `FormatReader`.
+        unsupported_error("cue: a cue track reader must be created from a format reader")
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let cue_track = &self.cue_tracks[self.current];
+
+        // Get the timestamp relative to the start of the virtual track.
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => match self.source.codec_params.time_base {
+                Some(tb) => tb.calc_timestamp(time),
+                None => return seek_error(SeekErrorKind::Unseekable),
+            },
+        };
+
+        if let Some(dur) = cue_track.dur {
+            if ts >= dur {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        let seeked = self.reader.seek(
+            mode,
+            SeekTo::TimeStamp { ts: cue_track.start_ts + ts, track_id: self.source.id },
+        )?;
+
+        Ok(SeekedTo {
+            track_id: self.source.id,
+            required_ts: ts,
+            actual_ts: seeked.actual_ts.saturating_sub(cue_track.start_ts),
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let cue_track = &self.cue_tracks[self.current];
+
+        let start_ts = cue_track.start_ts;
+        let end_ts = cue_track.end_ts();
+
+        loop {
+            let mut packet = self.reader.next_packet()?;
+
+            // Drop packets from other tracks.
+            if packet.track_id() != self.source.id {
+                continue;
+            }
+
+            // Drop packets that end before the start of the virtual track, including
+            // zero-duration packets that start before it.
+            if packet.ts < start_ts && packet.ts + packet.dur <= start_ts {
+                continue;
+            }
+
+            // The virtual track ends once a packet starting after its end is read.
+            if let Some(end_ts) = end_ts {
+                if packet.ts >= end_ts {
+                    return end_of_stream_error();
+                }
+            }
+
+            // Trim the portions of the packet that are outside of the virtual track.
+            let trim_start = start_ts.saturating_sub(packet.ts).min(packet.dur);
+
+            let trim_end = match end_ts {
+                Some(end_ts) => (packet.ts + packet.dur).saturating_sub(end_ts),
+                None => 0,
+            };
+
+            // The trims are in frames, while timestamps are in `TimeBase` units.
+            let params = &self.source.codec_params;
+
+            packet.trim_start += ts_to_frames(params, trim_start) as u32;
+            packet.trim_end += ts_to_frames(params, trim_end) as u32;
+            packet.dur -= trim_start + trim_end;
+            packet.ts = (packet.ts + trim_start) - start_ts;
+
+            return Ok(packet);
+        }
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cue_tracks, CueTrackReader};
+    use crate::codecs::CodecParameters;
+    use std::io::Cursor;
+
+    use crate::errors::{end_of_stream_error, seek_error, unsupported_error};
+    use crate::errors::{Result, SeekErrorKind};
+    use crate::formats::{
+        Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+    };
+    use crate::io::MediaSourceStream;
+    use crate::meta::{Metadata, MetadataLog};
+    use crate::units::TimeBase;
+
+    /// A reader of a 48 kHz track with a millisecond time base, and 20 ms packets.
+    struct MsReader {
+        tracks: Vec<Track>,
+        metadata: MetadataLog,
+        packets: Vec<(u64, u64)>,
+        next: usize,
+    }
+
+    impl MsReader {
+        fn new() -> Self {
+            Self::with_packets((0..50).map(|i| (20 * i, 20)).collect())
+        }
+
+        /// Instantiate a reader that returns packets with the given timestamps and durations.
+        fn with_packets(packets: Vec<(u64, u64)>) -> Self {
+            let mut params = CodecParameters::new();
+            params.with_sample_rate(48_000).with_time_base(TimeBase::new(1, 1000));
+
+            MsReader {
+                tracks: vec![Track::new(0, params)],
+                metadata: Default::default(),
+                packets,
+                next: 0,
+            }
+        }
+    }
+
+    impl FormatReader for MsReader {
+        fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+            unsupported_error("test reader cannot be instantiated from a source")
+        }
+
+        fn cues(&self) -> &[Cue] {
+            &[]
+        }
+
+        fn metadata(&mut self) -> Metadata<'_> {
+            self.metadata.metadata()
+        }
+
+        fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+            let required_ts = match to {
+                SeekTo::TimeStamp { ts, .. } => ts,
+                _ => return seek_error(SeekErrorKind::Unseekable),
+            };
+
+            // Seek up to 20 ms before the required timestamp, as a coarse seek may.
+            self.next = self
+                .packets
+                .iter()
+                .position(|&(ts, dur)| ts + dur + 20 > required_ts)
+                .unwrap_or(self.packets.len());
+
+            let actual_ts = self.packets.get(self.next).map_or(required_ts, |&(ts, _)| ts);
+
+            Ok(SeekedTo { track_id: 0, required_ts, actual_ts })
+        }
+
+        fn tracks(&self) -> &[Track] {
+            &self.tracks
+        }
+
+        fn next_packet(&mut self) -> Result<Packet> {
+            let (ts, dur) = match self.packets.get(self.next) {
+                Some(&packet) => packet,
+                None => return end_of_stream_error(),
+            };
+
+            self.next += 1;
+            Ok(Packet::new_from_slice(0, ts, dur, &[]))
+        }
+
+        fn into_inner(self: Box<Self>) -> MediaSourceStream {
+            MediaSourceStream::new(Box::new(Cursor::new(Vec::new())), Default::default())
+        }
+    }
+
+    fn cue(index: u32, start_ts: u64) -> Cue {
+        Cue { index, start_ts, tags: Vec::new(), points: Vec::new() }
+    }
+
+    #[test]
+    fn verify_cue_tracks() {
+        let mut params = CodecParameters::new();
+        params.with_sample_rate(44_100).with_time_base(TimeBase::new(1, 44_100));
+
+        // Unknown length, unsorted cues.
+        let tracks = cue_tracks(&params, &[cue(2, 1000), cue(1, 0), cue(3, 5000)]);
+
+        assert_eq!(tracks.len(), 3);
+        assert_eq!((tracks[0].cue_index, tracks[0].start_ts, tracks[0].dur), (1, 0, Some(1000)));
+        assert_eq!((tracks[1].cue_index, tracks[1].start_ts, tracks[1].dur), (2, 1000, Some(4000)));
+        assert_eq!((tracks[2].cue_index, tracks[2].start_ts, tracks[2].dur), (3, 5000, None));
+
+        // Known length with a lead-out cue.
+        params.with_n_frames(8000);
+
+        let tracks = cue_tracks(&params, &[cue(1, 0), cue(2, 1000), cue(170, 8000)]);
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[1].end_ts(), Some(8000));
+
+        // Cues in frames, timestamps in milliseconds.
+        params.with_time_base(TimeBase::new(1, 1000));
+        params.n_frames = None;
+
+        let tracks = cue_tracks(&params, &[cue(1, 0), cue(2, 44_100)]);
+
+        assert_eq!(tracks[0].dur, Some(1000));
+
+        // Cues relative to the start of the stream, timestamps offset by the start timestamp.
+        params.with_start_ts(60_000).with_n_frames(88_200);
+
+        let tracks = cue_tracks(&params, &[cue(1, 0), cue(2, 44_100)]);
+
+        assert_eq!((tracks[0].start_ts, tracks[0].dur), (60_000, Some(1000)));
+        assert_eq!((tracks[1].start_ts, tracks[1].end_ts()), (61_000, Some(62_000)));
+    }
+
+    #[test]
+    fn verify_cue_track_trims_in_frames() {
+        // Cues at 105 ms and 250 ms.
+        let cues = vec![cue(1, 0), cue(2, 5040), cue(3, 12_000)];
+
+        let mut reader = CueTrackReader::with_cues(Box::new(MsReader::new()), cues).unwrap();
+
+        reader.select(1).unwrap();
+
+        // The first packet spans 100..120 ms, and starts 5 ms (240 frames) before the virtual
+        // track.
+        let packet = reader.next_packet().unwrap();
+
+        assert_eq!((packet.ts, packet.dur), (0, 15));
+        assert_eq!((packet.trim_start, packet.trim_end), (240, 0));
+
+        // The last packet spans 240..260 ms, and ends 10 ms (480 frames) after the virtual track.
+        let packet = std::iter::from_fn(|| reader.next_packet().ok()).last().unwrap();
+
+        assert_eq!((packet.ts, packet.dur), (135, 10));
+        assert_eq!((packet.trim_start, packet.trim_end), (0, 480));
+    }
+
+    #[test]
+    fn verify_cue_track_skips_zero_duration_packets() {
+        // A zero-duration packet at 90 ms, before the virtual track starting at 100 ms.
+        let packets = vec![(80, 10), (90, 0), (90, 10), (100, 0), (100, 20)];
+
+        let cues = vec![cue(1, 0), cue(2, 4800)];
+
+        let mut reader =
+            CueTrackReader::with_cues(Box::new(MsReader::with_packets(packets)), cues).unwrap();
+
+        reader.select(1).unwrap();
+
+        // The zero-duration packet at the start of the virtual track is kept.
+        let packet = reader.next_packet().unwrap();
+
+        assert_eq!((packet.ts, packet.dur), (0, 0));
+
+        let packet = reader.next_packet().unwrap();
+
+        assert_eq!((packet.ts, packet.dur), (0, 20));
+        assert_eq!((packet.trim_start, packet.trim_end), (0, 0));
+
+        assert!(reader.next_packet().is_err());
+    }
+}
@@ -14,6 +14,8 @@ use crate::io::{BufReader, MediaSourceStream};
 use crate::meta::{Metadata, Tag};
 use crate::units::{Time, TimeStamp};
 
+pub mod cue;
+
 pub mod prelude {
     //! The `formats` module prelude.
 
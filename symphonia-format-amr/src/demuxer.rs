@@ -0,0 +1,444 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CodecType, CODEC_TYPE_AMRNB, CODEC_TYPE_AMRWB};
+use symphonia_core::errors::{seek_error, unsupported_error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, info};
+
+/// The maximum length of a storage format header, including the channel description.
+const MAX_HEADER_LEN: usize = 15;
+
+/// The length of the speech payload of each AMR-NB frame type, excluding the frame header.
+const AMRNB_PAYLOAD_LEN: [u8; 16] = [12, 13, 15, 17, 19, 20, 26, 31, 5, 0, 0, 0, 0, 0, 0, 0];
+
+/// The length of the speech payload of each AMR-WB frame type, excluding the frame header.
+const AMRWB_PAYLOAD_LEN: [u8; 16] = [17, 23, 32, 36, 40, 46, 50, 58, 60, 5, 0, 0, 0, 0, 0, 0];
+
+/// The variant of the AMR codec.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Variant {
+    /// Narrowband, 8 kHz.
+    Nb,
+    /// Wideband, 16 kHz.
+    Wb,
+}
+
+impl Variant {
+    fn codec(self) -> CodecType {
+        match self {
+            Variant::Nb => CODEC_TYPE_AMRNB,
+            Variant::Wb => CODEC_TYPE_AMRWB,
+        }
+    }
+
+    fn sample_rate(self) -> u32 {
+        match self {
+            Variant::Nb => 8_000,
+            Variant::Wb => 16_000,
+        }
+    }
+
+    /// Gets the number of samples in a 20 ms speech frame.
+    fn frame_len(self) -> u64 {
+        match self {
+            Variant::Nb => 160,
+            Variant::Wb => 320,
+        }
+    }
+
+    /// Gets the length of a speech frame, including the frame header, from the frame header.
+    fn frame_len_bytes(self, header: u8) -> usize {
+        let frame_type = usize::from((header >> 3) & 0xf);
+
+        let payload_len = match self {
+            Variant::Nb => AMRNB_PAYLOAD_LEN[frame_type],
+            Variant::Wb => AMRWB_PAYLOAD_LEN[frame_type],
+        };
+
+        1 + usize::from(payload_len)
+    }
+}
+
+/// Adaptive Multi-Rate (AMR) storage format reader.
+///
+/// `AmrReader` implements a demuxer for the AMR-NB and AMR-WB storage formats defined in RFC 4867.
+/// Each packet contains one speech frame per channel, including the frame headers.
+pub struct AmrReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    variant: Variant,
+    n_channels: usize,
+    first_packet_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for AmrReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "amr",
+            "Adaptive Multi-Rate",
+            &["amr", "awb"],
+            &["audio/amr", "audio/amr-wb"],
+            &[b"#!AMR\n", b"#!AMR-WB\n", b"#!AMR_MC1.0\n", b"#!AMR-WB_MC1.0\n"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Gets the channels for a multi-channel storage format channel count. Only the channel
+/// arrangements of RFC 3551 that are in Symphonia's channel order are supported.
+fn channels(n_channels: u32) -> Result<Channels> {
+    let channels = match n_channels {
+        1 => Channels::FRONT_LEFT,
+        2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        3 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE,
+        5 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+        }
+        _ => return unsupported_error("amr: unsupported channel arrangement"),
+    };
+
+    Ok(channels)
+}
+
+/// Reads the storage format header, and returns the codec variant and channels.
+fn read_header<B: ReadBytes>(reader: &mut B) -> Result<(Variant, Channels)> {
+    let mut magic = Vec::with_capacity(MAX_HEADER_LEN);
+
+    // The magic number is terminated by a line feed.
+    loop {
+        let byte = reader.read_u8()?;
+
+        magic.push(byte);
+
+        if byte == b'\n' {
+            break;
+        }
+
+        if magic.len() >= MAX_HEADER_LEN {
+            return unsupported_error("amr: missing amr stream marker");
+        }
+    }
+
+    let (variant, is_multi_channel) = match magic.as_slice() {
+        b"#!AMR\n" => (Variant::Nb, false),
+        b"#!AMR-WB\n" => (Variant::Wb, false),
+        b"#!AMR_MC1.0\n" => (Variant::Nb, true),
+        b"#!AMR-WB_MC1.0\n" => (Variant::Wb, true),
+        _ => return unsupported_error("amr: missing amr stream marker"),
+    };
+
+    let channels = if is_multi_channel {
+        // The channel description. The upper 28 bits are reserved.
+        channels(reader.read_be_u32()? & 0xf)?
+    }
+    else {
+        Channels::FRONT_LEFT
+    };
+
+    Ok((variant, channels))
+}
+
+impl FormatReader for AmrReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let (variant, channels) = read_header(&mut source)?;
+
+        let first_packet_pos = source.pos();
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(variant.codec())
+            .with_sample_rate(variant.sample_rate())
+            .with_time_base(TimeBase::new(1, variant.sample_rate()))
+            .with_channels(channels)
+            .with_max_frames_per_packet(variant.frame_len());
+
+        let mut reader = AmrReader {
+            reader: source,
+            tracks: Vec::new(),
+            cues: Vec::new(),
+            metadata: Default::default(),
+            variant,
+            n_channels: channels.count(),
+            first_packet_pos,
+            next_packet_ts: 0,
+        };
+
+        // The storage format has no duration information, therefore estimate it if seekable.
+        if reader.reader.is_seekable() {
+            info!("estimating duration from bitrate, may be inaccurate for vbr streams");
+
+            if let Some(n_frames) = reader.estimate_n_frames() {
+                params.with_n_frames(n_frames);
+            }
+        }
+
+        reader.tracks.push(Track::new(0, params));
+
+        Ok(reader)
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let mut packet = Vec::new();
+
+        // Read one speech frame for each channel.
+        for _ in 0..self.n_channels {
+            let header = self.reader.read_u8()?;
+
+            let start = packet.len();
+
+            packet.resize(start + self.variant.frame_len_bytes(header), 0);
+            packet[start] = header;
+
+            self.reader.read_buf_exact(&mut packet[start + 1..])?;
+        }
+
+        let ts = self.next_packet_ts;
+        let dur = self.variant.frame_len();
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, packet.into_boxed_slice()))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Get the timestamp of the desired audio frame.
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                TimeBase::new(1, self.variant.sample_rate()).calc_timestamp(time)
+            }
+        };
+
+        debug!("seeking to ts={}", required_ts);
+
+        // Speech frames have no sync word, therefore the stream can only be seeked by parsing
+        // every packet from a known packet boundary. If the required timestamp is in the past,
+        // then seek back to the first packet.
+        if required_ts < self.next_packet_ts {
+            if !self.reader.is_seekable() {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            let seeked_pos = self.reader.seek(SeekFrom::Start(self.first_packet_pos))?;
+
+            if seeked_pos != self.first_packet_pos {
+                return seek_error(SeekErrorKind::Unseekable);
+            }
+
+            self.next_packet_ts = 0;
+        }
+
+        // All packets have the same duration, therefore skip packets until the next packet
+        // contains the required timestamp.
+        let dur = self.variant.frame_len();
+
+        while self.next_packet_ts + dur <= required_ts {
+            self.skip_packet()?;
+            self.next_packet_ts += dur;
+        }
+
+        debug!(
+            "seeked to ts={} (delta={})",
+            self.next_packet_ts,
+            self.next_packet_ts as i64 - required_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts: self.next_packet_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+impl AmrReader {
+    /// Skips the speech frames of the next packet, and returns the length of the packet.
+    fn skip_packet(&mut self) -> Result<u64> {
+        let mut len = 0;
+
+        for _ in 0..self.n_channels {
+            let header = self.reader.read_u8()?;
+            let frame_len = self.variant.frame_len_bytes(header) as u64;
+
+            self.reader.ignore_bytes(frame_len - 1)?;
+
+            len += frame_len;
+        }
+
+        Ok(len)
+    }
+
+    /// Estimates the total number of frames (samples per channel) in the media source stream.
+    fn estimate_n_frames(&mut self) -> Option<u64> {
+        const MAX_PACKETS: u64 = 256;
+
+        let total_len = match self.reader.byte_len() {
+            Some(len) => len - self.first_packet_pos,
+            _ => return None,
+        };
+
+        let mut total_packet_len = 0;
+        let mut total_packets = 0;
+
+        let n_packets = loop {
+            match self.skip_packet() {
+                Ok(len) => {
+                    total_packet_len += len;
+                    total_packets += 1;
+                }
+                // If the stream ended, then the duration is exact.
+                Err(_) => break Some(total_packets),
+            }
+
+            // Read up-to 256 packets, then calculate the average packet length, and from that, the
+            // total number of packets.
+            if total_packets >= MAX_PACKETS {
+                let avg_packet_len = total_packet_len as f64 / total_packets as f64;
+
+                break Some((total_len as f64 / avg_packet_len) as u64);
+            }
+        };
+
+        // Rewind back to the first packet.
+        if self.reader.seek(SeekFrom::Start(self.first_packet_pos)).is_err() {
+            return None;
+        }
+
+        n_packets.map(|n_packets| n_packets * self.variant.frame_len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_reader(buf: Vec<u8>) -> Result<AmrReader> {
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        AmrReader::try_new(source, &Default::default())
+    }
+
+    /// Gets a speech frame of the given frame type with a zeroed payload.
+    fn frame(variant: Variant, frame_type: u8) -> Vec<u8> {
+        // The frame header, with the quality bit set.
+        let header = (frame_type << 3) | 0x04;
+
+        let mut frame = vec![0; variant.frame_len_bytes(header)];
+        frame[0] = header;
+        frame
+    }
+
+    #[test]
+    fn verify_amrnb_reader() {
+        // 12.2 kbit/s, 4.75 kbit/s, and no data frames.
+        let frames = [frame(Variant::Nb, 7), frame(Variant::Nb, 0), frame(Variant::Nb, 15)];
+
+        let mut buf = b"#!AMR\n".to_vec();
+        frames.iter().for_each(|frame| buf.extend_from_slice(frame));
+
+        let mut reader = new_reader(buf).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.codec, CODEC_TYPE_AMRNB);
+        assert_eq!(params.sample_rate, Some(8_000));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT));
+        assert_eq!(params.n_frames, Some(3 * 160));
+
+        for (i, frame) in frames.iter().enumerate() {
+            let packet = reader.next_packet().unwrap();
+
+            assert_eq!((packet.ts(), packet.dur()), (160 * i as u64, 160));
+            assert_eq!(packet.buf(), &frame[..]);
+        }
+
+        assert!(reader.next_packet().is_err());
+
+        // Seeking back returns the packet containing the required timestamp.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 200, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 160);
+
+        assert_eq!(reader.next_packet().unwrap().buf(), &frames[1][..]);
+    }
+
+    #[test]
+    fn verify_amrwb_multi_channel_reader() {
+        // 23.85 kbit/s, and 12.65 kbit/s frames.
+        let frames = [frame(Variant::Wb, 8), frame(Variant::Wb, 2)];
+
+        assert_eq!((frames[0].len(), frames[1].len()), (61, 33));
+
+        // Two channels, with two packets of one frame per channel.
+        let mut buf = b"#!AMR-WB_MC1.0\n".to_vec();
+        buf.extend_from_slice(&2u32.to_be_bytes());
+
+        for _ in 0..2 {
+            frames.iter().for_each(|frame| buf.extend_from_slice(frame));
+        }
+
+        let mut reader = new_reader(buf).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.codec, CODEC_TYPE_AMRWB);
+        assert_eq!(params.sample_rate, Some(16_000));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT | Channels::FRONT_RIGHT));
+        assert_eq!(params.n_frames, Some(2 * 320));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur(), packet.buf().len()), (0, 320, 61 + 33));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.buf().len()), (320, 61 + 33));
+    }
+
+    #[test]
+    fn verify_invalid_header() {
+        assert!(new_reader(b"#!AMR-XB\n".to_vec()).is_err());
+        assert!(new_reader(b"#!AMR_without_line_feed".to_vec()).is_err());
+
+        // 4 channels is not a channel arrangement of RFC 3551.
+        let mut buf = b"#!AMR_MC1.0\n".to_vec();
+        buf.extend_from_slice(&4u32.to_be_bytes());
+
+        assert!(new_reader(buf).is_err());
+    }
+}
@@ -0,0 +1,747 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::CODEC_TYPE_WMA;
+use symphonia_core::codecs::{CodecParameters, CodecType, CODEC_TYPE_MP3, CODEC_TYPE_NULL};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_S16LE};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, info, warn};
+
+use crate::meta::{read_content_description, read_extended_content_description, read_metadata};
+use crate::objects::*;
+use crate::packet::{DataPacket, Payload};
+
+/// The maximum length of the header object. The header object is read into memory.
+const MAX_HEADER_LEN: u64 = 64 * 1024 * 1024;
+
+/// The time base of all presentation times.
+const ASF_TIME_BASE: TimeBase = TimeBase { numer: 1, denom: 1000 };
+
+/// The maximum number of frames in a PCM packet.
+const MAX_PCM_FRAMES_PER_PACKET: u64 = 1152;
+
+/// WMA version 1 format tag.
+const WAVE_FORMAT_WMAV1: u16 = 0x0160;
+/// WMA version 2 format tag.
+const WAVE_FORMAT_WMAV2: u16 = 0x0161;
+/// WAVEFORMATEXTENSIBLE format tag.
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// The state of an audio stream.
+struct StreamState {
+    stream_number: u8,
+    /// The average number of bytes per second, used to calculate packet durations.
+    avg_bytes_per_sec: u32,
+    /// If set, media objects are split into packets of at most this length.
+    block_len: Option<usize>,
+    spread: Option<AudioSpread>,
+    /// The media object being reassembled.
+    object: Vec<u8>,
+    /// The number of the media object being reassembled, if any.
+    object_number: Option<u32>,
+    object_len: u32,
+    pres_time: u32,
+}
+
+impl StreamState {
+    /// Gets the duration of the given number of bytes in milliseconds.
+    fn duration(&self, len: usize) -> u64 {
+        match u64::from(self.avg_bytes_per_sec) {
+            0 => 0,
+            rate => (1000 * len as u64 + rate / 2) / rate,
+        }
+    }
+
+    /// Appends a payload to the media object being reassembled. Returns the media object if it is
+    /// complete.
+    fn push_payload(&mut self, payload: &Payload<'_>) -> Option<Vec<u8>> {
+        if payload.object_offset == 0 {
+            if self.object_number.is_some() {
+                debug!("discarding incomplete media object on stream {}", self.stream_number);
+            }
+
+            self.object.clear();
+            self.object_number = Some(payload.object_number);
+            self.object_len = payload.object_len;
+            self.pres_time = payload.pres_time;
+        }
+        else if self.object_number != Some(payload.object_number)
+            || payload.object_offset as usize != self.object.len()
+        {
+            // The start of the media object, or a fragment of it, is missing.
+            self.reset();
+            return None;
+        }
+
+        self.object.extend_from_slice(payload.data);
+
+        if self.object.len() < self.object_len as usize {
+            return None;
+        }
+
+        self.object_number = None;
+
+        let object = std::mem::take(&mut self.object);
+
+        match self.spread {
+            Some(spread) if spread.is_scrambled() => spread.descramble(&object).or(Some(object)),
+            _ => Some(object),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.object.clear();
+        self.object_number = None;
+    }
+}
+
+/// Advanced Systems Format (ASF) reader.
+///
+/// `AsfReader` implements a demuxer for the audio streams of an ASF (.wma, .asf) file.
+pub struct AsfReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    streams: Vec<StreamState>,
+    /// The amount of time to buffer before playback, subtracted from all presentation times.
+    preroll: u64,
+    /// The duration of the presentation in milliseconds, if known.
+    duration: Option<u64>,
+    packet_len: u32,
+    n_packets: Option<u64>,
+    first_packet_pos: u64,
+    next_packet_num: u64,
+    index: Option<SimpleIndex>,
+    /// Packets of complete media objects that are yet to be returned.
+    pending: VecDeque<Packet>,
+}
+
+impl QueryDescriptor for AsfReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "asf",
+            "Advanced Systems Format",
+            &["asf", "wma"],
+            &["audio/x-ms-wma", "video/x-ms-asf"],
+            &[&HEADER_OBJECT]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Gets the codec from the format tag.
+fn codec_type(format_tag: u16, bits_per_sample: u16) -> CodecType {
+    match (format_tag, bits_per_sample) {
+        (0x0001, 8) => CODEC_TYPE_PCM_U8,
+        (0x0001, 16) => CODEC_TYPE_PCM_S16LE,
+        (0x0001, 24) => CODEC_TYPE_PCM_S24LE,
+        (0x0001, 32) => CODEC_TYPE_PCM_S32LE,
+        (0x0003, 32) => CODEC_TYPE_PCM_F32LE,
+        (0x0003, 64) => CODEC_TYPE_PCM_F64LE,
+        (0x0055, _) => CODEC_TYPE_MP3,
+        (WAVE_FORMAT_WMAV1, _) | (WAVE_FORMAT_WMAV2, _) => CODEC_TYPE_WMA,
+        _ => CODEC_TYPE_NULL,
+    }
+}
+
+/// Gets the codec parameters for an audio stream.
+fn codec_params(format: &WaveFormat) -> Result<CodecParameters> {
+    let mut format_tag = format.format_tag;
+    let mut channels = None;
+
+    // For WAVEFORMATEXTENSIBLE, the format tag and channel mask are in the extra data.
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && format.extra_data.len() >= 22 {
+        let extra = &format.extra_data;
+
+        let mask = u32::from_le_bytes([extra[2], extra[3], extra[4], extra[5]]);
+
+        channels = Channels::from_bits(mask)
+            .filter(|channels| channels.count() == usize::from(format.n_channels));
+
+        format_tag = u16::from_le_bytes([extra[6], extra[7]]);
+    }
+
+    // Otherwise, assign the channels in order.
+    let channels = match channels {
+        Some(channels) => channels,
+        None if format.n_channels > 0 && format.n_channels <= 32 => {
+            match Channels::from_bits(((1u64 << format.n_channels) - 1) as u32) {
+                Some(channels) => channels,
+                None => return unsupported_error("asf: unsupported channel count"),
+            }
+        }
+        None => return decode_error("asf: invalid channel count"),
+    };
+
+    if format.sample_rate == 0 {
+        return decode_error("asf: invalid sample rate");
+    }
+
+    let codec = codec_type(format_tag, format.bits_per_sample);
+
+    if codec == CODEC_TYPE_NULL {
+        info!("unsupported audio format tag {:#06x}", format_tag);
+    }
+
+    let mut params = CodecParameters::new();
+
+    params
+        .for_codec(codec)
+        .with_sample_rate(format.sample_rate)
+        .with_time_base(ASF_TIME_BASE)
+        .with_channels(channels);
+
+    match format_tag {
+        0x0001 | 0x0003 => {
+            params
+                .with_bits_per_sample(u32::from(format.bits_per_sample))
+                .with_bits_per_coded_sample(u32::from(format.bits_per_sample))
+                .with_max_frames_per_packet(MAX_PCM_FRAMES_PER_PACKET);
+        }
+        _ => {
+            if !format.extra_data.is_empty() {
+                params.with_extra_data(format.extra_data.clone());
+            }
+        }
+    }
+
+    Ok(params)
+}
+
+impl FormatReader for AsfReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let header = ObjectHeader::read(&mut source)?;
+
+        if header.guid != HEADER_OBJECT {
+            return unsupported_error("asf: missing header object");
+        }
+
+        if header.data_len() < 6 {
+            return decode_error("asf: invalid header object length");
+        }
+
+        if header.data_len() > MAX_HEADER_LEN {
+            return unsupported_error("asf: header object too large");
+        }
+
+        // Skip the number of header objects, and the reserved fields.
+        source.ignore_bytes(6)?;
+
+        let buf = source.read_boxed_slice_exact((header.data_len() - 6) as usize)?;
+
+        let mut file_props = None;
+        let mut stream_props = Vec::new();
+        let mut builder = MetadataBuilder::new();
+
+        for_each_object(&buf, |guid, data| {
+            match guid {
+                FILE_PROPERTIES_OBJECT => file_props = Some(FileProperties::read(data)?),
+                STREAM_PROPERTIES_OBJECT => stream_props.push(StreamProperties::read(data)?),
+                CONTENT_DESCRIPTION_OBJECT => {
+                    read_content_description(data, &mut builder)
+                        .unwrap_or_else(|_| warn!("invalid content description object"));
+                }
+                EXTENDED_CONTENT_DESCRIPTION_OBJECT => {
+                    read_extended_content_description(data, &mut builder)
+                        .unwrap_or_else(|_| warn!("invalid extended content description object"));
+                }
+                HEADER_EXTENSION_OBJECT => {
+                    for_each_object(read_header_extension(data)?, |guid, data| {
+                        if guid == METADATA_OBJECT || guid == METADATA_LIBRARY_OBJECT {
+                            read_metadata(data, &mut builder)
+                                .unwrap_or_else(|_| warn!("invalid metadata object"));
+                        }
+                        Ok(())
+                    })?;
+                }
+                _ => (),
+            }
+            Ok(())
+        })?;
+
+        let file_props = match file_props {
+            Some(file_props) => file_props,
+            None => return decode_error("asf: missing file properties object"),
+        };
+
+        // The data object immediately follows the header object.
+        let data_pos = source.pos();
+        let data_header = ObjectHeader::read(&mut source)?;
+
+        if data_header.guid != DATA_OBJECT {
+            return decode_error("asf: missing data object");
+        }
+
+        // Skip the file ID, total number of data packets, and reserved field.
+        source.ignore_bytes(26)?;
+
+        let first_packet_pos = source.pos();
+
+        // The presentation times include the preroll.
+        let duration = match file_props.play_duration / 10_000 {
+            0 => None,
+            duration => Some(duration.saturating_sub(file_props.preroll)),
+        };
+
+        let mut tracks = Vec::new();
+        let mut streams = Vec::new();
+
+        for props in stream_props {
+            let format = match props.format {
+                Some(format) => format,
+                // Not an audio stream.
+                _ => continue,
+            };
+
+            let mut params = codec_params(&format)?;
+
+            if let Some(duration) = duration {
+                params.with_n_frames(duration);
+            }
+
+            // Each WMA media object is a whole number of blocks, and each block can be decoded
+            // independently. PCM media objects are split into packets no larger than the maximum
+            // number of frames per packet.
+            let block_len = match (params.codec, format.block_align) {
+                (_, 0) => None,
+                (CODEC_TYPE_WMA, block_align) => Some(usize::from(block_align)),
+                (_, block_align) if params.max_frames_per_packet.is_some() => {
+                    Some(usize::from(block_align) * MAX_PCM_FRAMES_PER_PACKET as usize)
+                }
+                _ => None,
+            };
+
+            tracks.push(Track::new(u32::from(props.stream_number), params));
+
+            streams.push(StreamState {
+                stream_number: props.stream_number,
+                avg_bytes_per_sec: format.avg_bytes_per_sec,
+                block_len,
+                spread: props.spread,
+                object: Vec::new(),
+                object_number: None,
+                object_len: 0,
+                pres_time: 0,
+            });
+        }
+
+        if tracks.is_empty() {
+            return unsupported_error("asf: no audio streams");
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+        metadata.push(builder.metadata());
+
+        let mut reader = AsfReader {
+            reader: source,
+            tracks,
+            cues: Vec::new(),
+            metadata,
+            streams,
+            preroll: file_props.preroll,
+            duration,
+            packet_len: file_props.packet_len,
+            n_packets: file_props.n_packets,
+            first_packet_pos,
+            next_packet_num: 0,
+            index: None,
+            pending: Default::default(),
+        };
+
+        // The index objects follow the data object.
+        if reader.reader.is_seekable() && !file_props.is_broadcast && data_header.len > 0 {
+            reader.index = reader.read_simple_index(data_pos + data_header.len);
+            reader.reader.seek(SeekFrom::Start(first_packet_pos))?;
+        }
+
+        Ok(reader)
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        loop {
+            if let Some(packet) = self.pending.pop_front() {
+                return Ok(packet);
+            }
+
+            self.read_data_packet()?;
+        }
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let (required_ts, track_id) = match to {
+            SeekTo::TimeStamp { ts, track_id } => (ts, track_id),
+            SeekTo::Time { time, track_id } => {
+                (ASF_TIME_BASE.calc_timestamp(time), track_id.unwrap_or(self.tracks[0].id))
+            }
+        };
+
+        if !self.tracks.iter().any(|track| track.id == track_id) {
+            return seek_error(SeekErrorKind::InvalidTrack);
+        }
+
+        if let Some(duration) = self.duration {
+            if required_ts > duration {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to ts={} on track={}", required_ts, track_id);
+
+        if self.reader.is_seekable() {
+            // Find a data packet containing the start of a media object before the required
+            // timestamp. The estimated data packet may be too late, in which case, step back by
+            // increasingly larger amounts.
+            let mut packet_num = self.estimate_packet_num(required_ts);
+            let mut step = 1;
+
+            loop {
+                self.seek_to_packet(packet_num)?;
+
+                match self.next_track_packet(track_id) {
+                    Ok(packet) if packet.ts() <= required_ts || packet_num == 0 => {
+                        self.pending.push_front(packet);
+                        break;
+                    }
+                    Err(err) if packet_num == 0 => return Err(err),
+                    _ => {
+                        packet_num = packet_num.saturating_sub(step);
+                        step *= 2;
+                    }
+                }
+            }
+        }
+        else {
+            let packet = self.next_track_packet(track_id)?;
+
+            let is_past = packet.ts() > required_ts;
+
+            self.pending.push_front(packet);
+
+            if is_past {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        // Parse packets one-by-one until the packet containing the required timestamp is reached.
+        let packet = loop {
+            let packet = self.next_track_packet(track_id)?;
+
+            if packet.ts() + packet.dur() > required_ts {
+                break packet;
+            }
+        };
+
+        let actual_ts = packet.ts();
+
+        self.pending.push_front(packet);
+
+        debug!("seeked to ts={} (delta={})", actual_ts, actual_ts as i64 - required_ts as i64);
+
+        Ok(SeekedTo { track_id, required_ts, actual_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+impl AsfReader {
+    /// Reads the next data packet, and queues the packets of any media objects it completes.
+    fn read_data_packet(&mut self) -> Result<()> {
+        if let Some(n_packets) = self.n_packets {
+            if self.next_packet_num >= n_packets {
+                return end_of_stream_error();
+            }
+        }
+
+        let buf = self.reader.read_boxed_slice_exact(self.packet_len as usize)?;
+
+        self.next_packet_num += 1;
+
+        let data_packet = DataPacket::read(&buf)?;
+
+        for payload in data_packet.payloads.iter() {
+            // Ignore payloads of non-audio streams.
+            let stream =
+                match self.streams.iter_mut().find(|s| s.stream_number == payload.stream_number) {
+                    Some(stream) => stream,
+                    None => continue,
+                };
+
+            let object = match stream.push_payload(payload) {
+                Some(object) if !object.is_empty() => object,
+                _ => continue,
+            };
+
+            let track_id = u32::from(stream.stream_number);
+            let ts = u64::from(stream.pres_time).saturating_sub(self.preroll);
+
+            match stream.block_len {
+                Some(block_len) => {
+                    for (i, block) in object.chunks(block_len).enumerate() {
+                        let start = stream.duration(i * block_len);
+                        let end = stream.duration(i * block_len + block.len());
+
+                        self.pending.push_back(Packet::new_from_slice(
+                            track_id,
+                            ts + start,
+                            end - start,
+                            block,
+                        ));
+                    }
+                }
+                None => {
+                    let dur = stream.duration(object.len());
+
+                    self.pending.push_back(Packet::new_from_boxed_slice(
+                        track_id,
+                        ts,
+                        dur,
+                        object.into_boxed_slice(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the next packet of a track, discarding the packets of all other tracks.
+    fn next_track_packet(&mut self, track_id: u32) -> Result<Packet> {
+        loop {
+            match self.pending.pop_front() {
+                Some(packet) if packet.track_id() == track_id => return Ok(packet),
+                Some(_) => (),
+                None => self.read_data_packet()?,
+            }
+        }
+    }
+
+    /// Seeks to the start of a data packet, and discards all partially read media objects.
+    fn seek_to_packet(&mut self, packet_num: u64) -> Result<()> {
+        let pos = self.first_packet_pos + packet_num * u64::from(self.packet_len);
+
+        self.reader.seek(SeekFrom::Start(pos))?;
+
+        self.next_packet_num = packet_num;
+        self.pending.clear();
+
+        for stream in self.streams.iter_mut() {
+            stream.reset();
+        }
+
+        Ok(())
+    }
+
+    /// Estimates the number of the data packet containing the required timestamp using the
+    /// simple index if available, otherwise using the total duration.
+    fn estimate_packet_num(&self, required_ts: u64) -> u64 {
+        if let Some(index) = &self.index {
+            let i = ((required_ts + self.preroll) * 10_000 / index.interval) as usize;
+
+            if let Some(&packet_num) = index.packets.get(i).or_else(|| index.packets.last()) {
+                return u64::from(packet_num);
+            }
+        }
+
+        match (self.n_packets, self.duration) {
+            (Some(n_packets), Some(duration)) if n_packets > 0 && duration > 0 => {
+                let packet_num =
+                    (u128::from(required_ts) * u128::from(n_packets)) / u128::from(duration);
+
+                (packet_num as u64).min(n_packets - 1)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Reads the first simple index object in the top-level objects starting at `pos`.
+    fn read_simple_index(&mut self, pos: u64) -> Option<SimpleIndex> {
+        self.reader.seek(SeekFrom::Start(pos)).ok()?;
+
+        loop {
+            let header = ObjectHeader::read(&mut self.reader).ok()?;
+
+            if header.guid == SIMPLE_INDEX_OBJECT && header.data_len() <= MAX_HEADER_LEN {
+                let buf = self.reader.read_boxed_slice_exact(header.data_len() as usize).ok()?;
+
+                return match SimpleIndex::read(&buf) {
+                    Ok(index) => Some(index),
+                    Err(_) => {
+                        warn!("invalid simple index object");
+                        None
+                    }
+                };
+            }
+
+            self.reader.seek(SeekFrom::Current(header.data_len() as i64)).ok()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The length of each data packet.
+    const PACKET_LEN: usize = 64;
+
+    fn push_object(buf: &mut Vec<u8>, guid: &Guid, data: &[u8]) {
+        buf.extend_from_slice(guid);
+        buf.extend_from_slice(&(OBJECT_HEADER_LEN + data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(data);
+    }
+
+    /// Gets a file with a 16-bit mono PCM stream at 8 kHz, a 3 second preroll, a title, and a
+    /// simple index. Each of the 4 data packets contains a 2 ms media object.
+    fn file() -> Vec<u8> {
+        let mut file_props = vec![0; 32];
+        file_props.extend_from_slice(&4u64.to_le_bytes());
+        file_props.extend_from_slice(&30_080_000u64.to_le_bytes());
+        file_props.extend_from_slice(&0u64.to_le_bytes());
+        file_props.extend_from_slice(&3000u64.to_le_bytes());
+        file_props.extend_from_slice(&2u32.to_le_bytes());
+        file_props.extend_from_slice(&(PACKET_LEN as u32).to_le_bytes());
+        file_props.extend_from_slice(&(PACKET_LEN as u32).to_le_bytes());
+        file_props.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut stream_props = AUDIO_MEDIA.to_vec();
+        stream_props.extend_from_slice(&[0; 16]);
+        stream_props.extend_from_slice(&0u64.to_le_bytes());
+        stream_props.extend_from_slice(&18u32.to_le_bytes());
+        stream_props.extend_from_slice(&0u32.to_le_bytes());
+        stream_props.extend_from_slice(&1u16.to_le_bytes());
+        stream_props.extend_from_slice(&0u32.to_le_bytes());
+        stream_props.extend_from_slice(&[0x01, 0x00, 0x01, 0x00]);
+        stream_props.extend_from_slice(&8000u32.to_le_bytes());
+        stream_props.extend_from_slice(&16000u32.to_le_bytes());
+        stream_props.extend_from_slice(&[0x02, 0x00, 0x10, 0x00, 0x00, 0x00]);
+
+        // Only the title is set.
+        let mut content_desc = 8u16.to_le_bytes().to_vec();
+        content_desc.extend_from_slice(&[0; 8]);
+        content_desc.extend_from_slice(&[b'T', 0, b'e', 0, b's', 0, b't', 0]);
+
+        let mut header = 3u32.to_le_bytes().to_vec();
+        header.extend_from_slice(&[0x01, 0x02]);
+        push_object(&mut header, &FILE_PROPERTIES_OBJECT, &file_props);
+        push_object(&mut header, &STREAM_PROPERTIES_OBJECT, &stream_props);
+        push_object(&mut header, &CONTENT_DESCRIPTION_OBJECT, &content_desc);
+
+        let mut data = vec![0; 16];
+        data.extend_from_slice(&4u64.to_le_bytes());
+        data.extend_from_slice(&[0x01, 0x01]);
+
+        for i in 0..4u8 {
+            let start = data.len();
+
+            // A single payload containing the whole of media object i.
+            data.extend_from_slice(&[0x08, 0x5d, 8]);
+            data.extend_from_slice(&(3000 + 2 * u32::from(i)).to_le_bytes());
+            data.extend_from_slice(&0u16.to_le_bytes());
+            data.extend_from_slice(&[0x81, i]);
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.push(8);
+            data.extend_from_slice(&32u32.to_le_bytes());
+            data.extend_from_slice(&(3000 + 2 * u32::from(i)).to_le_bytes());
+            data.extend_from_slice(&[i; 32]);
+
+            data.resize(start + PACKET_LEN, 0);
+        }
+
+        // An index entry every 1 ms, including the preroll.
+        let mut index = vec![0; 16];
+        index.extend_from_slice(&10_000u64.to_le_bytes());
+        index.extend_from_slice(&1u32.to_le_bytes());
+        index.extend_from_slice(&3008u32.to_le_bytes());
+
+        for t in 0..3008 {
+            let packet = if t < 3000 { 0 } else { (t - 3000) / 2 };
+            index.extend_from_slice(&(packet as u32).to_le_bytes());
+            index.extend_from_slice(&1u16.to_le_bytes());
+        }
+
+        let mut buf = Vec::new();
+        push_object(&mut buf, &HEADER_OBJECT, &header);
+        push_object(&mut buf, &DATA_OBJECT, &data);
+        push_object(&mut buf, &SIMPLE_INDEX_OBJECT, &index);
+        buf
+    }
+
+    #[test]
+    fn verify_reader() {
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(file())), Default::default());
+
+        let mut reader = AsfReader::try_new(source, &Default::default()).unwrap();
+
+        assert_eq!(reader.tracks().len(), 1);
+
+        let track = &reader.tracks()[0];
+
+        assert_eq!(track.id, 1);
+        assert_eq!(track.codec_params.codec, CODEC_TYPE_PCM_S16LE);
+        assert_eq!(track.codec_params.sample_rate, Some(8000));
+        assert_eq!(track.codec_params.channels, Some(Channels::FRONT_LEFT));
+        assert_eq!(track.codec_params.n_frames, Some(8));
+
+        assert_eq!(reader.index.as_ref().map(|index| index.packets.len()), Some(3008));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+
+        assert_eq!(tags[0].key, "Title");
+        assert_eq!(tags[0].value.to_string(), "Test");
+
+        // Presentation times are offset by the preroll.
+        for i in 0..4u8 {
+            let packet = reader.next_packet().unwrap();
+
+            assert_eq!((packet.track_id(), packet.ts(), packet.dur()), (1, 2 * u64::from(i), 2));
+            assert_eq!(packet.buf(), &[i; 32][..]);
+        }
+
+        assert!(reader.next_packet().is_err());
+
+        // Seek using the simple index.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5, track_id: 1 });
+        assert_eq!(seeked.unwrap().actual_ts, 4);
+        assert_eq!(reader.next_packet().unwrap().buf(), &[2; 32][..]);
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1, track_id: 1 });
+        assert_eq!(seeked.unwrap().actual_ts, 0);
+        assert_eq!(reader.next_packet().unwrap().buf(), &[0; 32][..]);
+    }
+}
@@ -0,0 +1,242 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! ASF content description, extended content description, and metadata object readers.
+
+use std::collections::HashMap;
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::{BufReader, FiniteStream, ReadBytes};
+use symphonia_core::meta::{MetadataBuilder, StandardTagKey, Tag, Value, Visual};
+
+use symphonia_metadata::id3v2::util::apic_picture_type_to_visual_key;
+
+use lazy_static::lazy_static;
+use log::warn;
+
+lazy_static! {
+    static ref ASF_ATTRIBUTE_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        m.insert("acoustid/fingerprint", StandardTagKey::AcoustidFingerprint);
+        m.insert("acoustid/id", StandardTagKey::AcoustidId);
+        m.insert("musicbrainz/album artist id", StandardTagKey::MusicBrainzAlbumArtistId);
+        m.insert("musicbrainz/album id", StandardTagKey::MusicBrainzAlbumId);
+        m.insert("musicbrainz/album release country", StandardTagKey::ReleaseCountry);
+        m.insert("musicbrainz/album status", StandardTagKey::MusicBrainzReleaseStatus);
+        m.insert("musicbrainz/album type", StandardTagKey::MusicBrainzReleaseType);
+        m.insert("musicbrainz/artist id", StandardTagKey::MusicBrainzArtistId);
+        m.insert("musicbrainz/disc id", StandardTagKey::MusicBrainzDiscId);
+        m.insert("musicbrainz/release group id", StandardTagKey::MusicBrainzReleaseGroupId);
+        m.insert("musicbrainz/release track id", StandardTagKey::MusicBrainzReleaseTrackId);
+        m.insert("musicbrainz/track id", StandardTagKey::MusicBrainzRecordingId);
+        m.insert("musicbrainz/work id", StandardTagKey::MusicBrainzWorkId);
+        m.insert("replaygain_album_gain", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("replaygain_album_peak", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("replaygain_track_gain", StandardTagKey::ReplayGainTrackGain);
+        m.insert("replaygain_track_peak", StandardTagKey::ReplayGainTrackPeak);
+        m.insert("wm/albumartist", StandardTagKey::AlbumArtist);
+        m.insert("wm/albumartistsortorder", StandardTagKey::SortAlbumArtist);
+        m.insert("wm/albumsortorder", StandardTagKey::SortAlbum);
+        m.insert("wm/albumtitle", StandardTagKey::Album);
+        m.insert("wm/artistsortorder", StandardTagKey::SortArtist);
+        m.insert("wm/barcode", StandardTagKey::IdentBarcode);
+        m.insert("wm/beatsperminute", StandardTagKey::Bpm);
+        m.insert("wm/catalogno", StandardTagKey::IdentCatalogNumber);
+        m.insert("wm/composer", StandardTagKey::Composer);
+        m.insert("wm/composersortorder", StandardTagKey::SortComposer);
+        m.insert("wm/conductor", StandardTagKey::Conductor);
+        m.insert("wm/contentgroupdescription", StandardTagKey::ContentGroup);
+        m.insert("wm/encodedby", StandardTagKey::EncodedBy);
+        m.insert("wm/encodingsettings", StandardTagKey::EncoderSettings);
+        m.insert("wm/genre", StandardTagKey::Genre);
+        m.insert("wm/isrc", StandardTagKey::IdentIsrc);
+        m.insert("wm/language", StandardTagKey::Language);
+        m.insert("wm/lyrics", StandardTagKey::Lyrics);
+        m.insert("wm/modifiedby", StandardTagKey::Remixer);
+        m.insert("wm/mood", StandardTagKey::Mood);
+        m.insert("wm/originalalbumtitle", StandardTagKey::OriginalAlbum);
+        m.insert("wm/originalartist", StandardTagKey::OriginalArtist);
+        m.insert("wm/originalfilename", StandardTagKey::OriginalFile);
+        m.insert("wm/originalreleaseyear", StandardTagKey::OriginalDate);
+        m.insert("wm/partofset", StandardTagKey::DiscNumber);
+        m.insert("wm/producer", StandardTagKey::Producer);
+        m.insert("wm/publisher", StandardTagKey::Label);
+        m.insert("wm/setsubtitle", StandardTagKey::DiscSubtitle);
+        m.insert("wm/subtitle", StandardTagKey::TrackSubtitle);
+        m.insert("wm/titlesortorder", StandardTagKey::SortTrackTitle);
+        m.insert("wm/toolname", StandardTagKey::Encoder);
+        m.insert("wm/tracknumber", StandardTagKey::TrackNumber);
+        m.insert("wm/writer", StandardTagKey::Lyricist);
+        m.insert("wm/year", StandardTagKey::Date);
+        m
+    };
+}
+
+/// The name of the attribute containing an attached picture.
+const PICTURE_ATTRIBUTE: &str = "WM/Picture";
+
+/// Decodes a UTF-16LE string, ignoring any null terminator.
+fn utf16_string(buf: &[u8]) -> String {
+    let units: Vec<u16> = buf.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+
+    let end = units.iter().position(|&unit| unit == 0).unwrap_or(units.len());
+
+    String::from_utf16_lossy(&units[..end])
+}
+
+/// Reads a null-terminated UTF-16LE string.
+fn read_utf16_string(reader: &mut BufReader<'_>) -> Result<String> {
+    let buf = reader.scan_bytes_aligned_ref(&[0, 0], 2, reader.bytes_available() as usize)?;
+    Ok(utf16_string(buf))
+}
+
+/// Reads the content description object.
+pub fn read_content_description(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    let mut lens = [0; 5];
+
+    for len in lens.iter_mut() {
+        *len = reader.read_u16()? as usize;
+    }
+
+    let fields = [
+        (Some(StandardTagKey::TrackTitle), "Title"),
+        (Some(StandardTagKey::Artist), "Author"),
+        (Some(StandardTagKey::Copyright), "Copyright"),
+        (Some(StandardTagKey::Comment), "Description"),
+        (Some(StandardTagKey::Rating), "Rating"),
+    ];
+
+    for (&len, &(std_key, key)) in lens.iter().zip(fields.iter()) {
+        let value = utf16_string(reader.read_buf_bytes_ref(len)?);
+
+        if !value.is_empty() {
+            builder.add_tag(Tag::new(std_key, key, Value::from(value)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds an attribute as a tag, or as a visual for an attached picture.
+fn add_attribute(
+    builder: &mut MetadataBuilder,
+    name: String,
+    data_type: u16,
+    data: &[u8],
+    is_word_bool: bool,
+) {
+    let value = match data_type {
+        0 => Value::from(utf16_string(data)),
+        1 if name == PICTURE_ATTRIBUTE => {
+            match read_picture(data) {
+                Ok(visual) => {
+                    builder.add_visual(visual);
+                }
+                Err(_) => warn!("invalid picture attribute"),
+            }
+            return;
+        }
+        1 => Value::from(data),
+        // A boolean is 4 bytes in the extended content description object, but 2 bytes in the
+        // metadata objects.
+        2 if is_word_bool && data.len() == 2 => Value::from(data != [0, 0]),
+        2 if data.len() == 4 => Value::from(data != [0, 0, 0, 0]),
+        3 if data.len() == 4 => {
+            Value::from(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+        }
+        4 if data.len() == 8 => {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(data);
+            Value::from(u64::from_le_bytes(bytes))
+        }
+        5 if data.len() == 2 => Value::from(u16::from_le_bytes([data[0], data[1]])),
+        // A GUID.
+        6 => Value::from(data),
+        _ => {
+            warn!("invalid attribute '{}'", name);
+            return;
+        }
+    };
+
+    let std_key = ASF_ATTRIBUTE_MAP.get(name.to_lowercase().as_str()).copied();
+
+    builder.add_tag(Tag::new(std_key, &name, value));
+}
+
+/// Reads an attached picture attribute.
+fn read_picture(buf: &[u8]) -> Result<Visual> {
+    let mut reader = BufReader::new(buf);
+
+    let picture_type = reader.read_u8()?;
+    let len = reader.read_u32()? as usize;
+
+    let media_type = read_utf16_string(&mut reader)?;
+    let desc = read_utf16_string(&mut reader)?;
+
+    let data = Box::from(reader.read_buf_bytes_ref(len)?);
+
+    let mut tags = Vec::new();
+
+    if !desc.is_empty() {
+        tags.push(Tag::new(Some(StandardTagKey::Description), "Description", Value::from(desc)));
+    }
+
+    Ok(Visual {
+        media_type,
+        dimensions: None,
+        bits_per_pixel: None,
+        color_mode: None,
+        usage: apic_picture_type_to_visual_key(u32::from(picture_type)),
+        tags,
+        data,
+    })
+}
+
+/// Reads the extended content description object.
+pub fn read_extended_content_description(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    let n_descriptors = reader.read_u16()?;
+
+    for _ in 0..n_descriptors {
+        let name_len = reader.read_u16()? as usize;
+        let name = utf16_string(reader.read_buf_bytes_ref(name_len)?);
+
+        let data_type = reader.read_u16()?;
+        let data_len = reader.read_u16()? as usize;
+        let data = reader.read_buf_bytes_ref(data_len)?;
+
+        add_attribute(builder, name, data_type, data, false);
+    }
+
+    Ok(())
+}
+
+/// Reads the metadata, or metadata library, object. Unlike the extended content description
+/// object, these may contain large attributes, and attributes for specific streams.
+pub fn read_metadata(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    let n_records = reader.read_u16()?;
+
+    for _ in 0..n_records {
+        let _language_index = reader.read_u16()?;
+        let _stream_number = reader.read_u16()?;
+        let name_len = reader.read_u16()? as usize;
+        let data_type = reader.read_u16()?;
+        let data_len = reader.read_u32()? as usize;
+
+        let name = utf16_string(reader.read_buf_bytes_ref(name_len)?);
+        let data = reader.read_buf_bytes_ref(data_len)?;
+
+        add_attribute(builder, name, data_type, data, true);
+    }
+
+    Ok(())
+}
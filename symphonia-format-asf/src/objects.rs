@@ -0,0 +1,426 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BufReader, FiniteStream, ReadBytes};
+
+/// A GUID in the byte order it is stored in an ASF file.
+pub type Guid = [u8; 16];
+
+#[rustfmt::skip]
+pub const HEADER_OBJECT: Guid = [
+    0x30, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, 0xa6, 0xd9, 0x00, 0xaa, 0x00, 0x62, 0xce, 0x6c,
+];
+
+#[rustfmt::skip]
+pub const DATA_OBJECT: Guid = [
+    0x36, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, 0xa6, 0xd9, 0x00, 0xaa, 0x00, 0x62, 0xce, 0x6c,
+];
+
+#[rustfmt::skip]
+pub const SIMPLE_INDEX_OBJECT: Guid = [
+    0x90, 0x08, 0x00, 0x33, 0xb1, 0xe5, 0xcf, 0x11, 0x89, 0xf4, 0x00, 0xa0, 0xc9, 0x03, 0x49, 0xcb,
+];
+
+#[rustfmt::skip]
+pub const FILE_PROPERTIES_OBJECT: Guid = [
+    0xa1, 0xdc, 0xab, 0x8c, 0x47, 0xa9, 0xcf, 0x11, 0x8e, 0xe4, 0x00, 0xc0, 0x0c, 0x20, 0x53, 0x65,
+];
+
+#[rustfmt::skip]
+pub const STREAM_PROPERTIES_OBJECT: Guid = [
+    0x91, 0x07, 0xdc, 0xb7, 0xb7, 0xa9, 0xcf, 0x11, 0x8e, 0xe6, 0x00, 0xc0, 0x0c, 0x20, 0x53, 0x65,
+];
+
+#[rustfmt::skip]
+pub const HEADER_EXTENSION_OBJECT: Guid = [
+    0xb5, 0x03, 0xbf, 0x5f, 0x2e, 0xa9, 0xcf, 0x11, 0x8e, 0xe3, 0x00, 0xc0, 0x0c, 0x20, 0x53, 0x65,
+];
+
+#[rustfmt::skip]
+pub const CONTENT_DESCRIPTION_OBJECT: Guid = [
+    0x33, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, 0xa6, 0xd9, 0x00, 0xaa, 0x00, 0x62, 0xce, 0x6c,
+];
+
+#[rustfmt::skip]
+pub const EXTENDED_CONTENT_DESCRIPTION_OBJECT: Guid = [
+    0x40, 0xa4, 0xd0, 0xd2, 0x07, 0xe3, 0xd2, 0x11, 0x97, 0xf0, 0x00, 0xa0, 0xc9, 0x5e, 0xa8, 0x50,
+];
+
+#[rustfmt::skip]
+pub const METADATA_OBJECT: Guid = [
+    0xea, 0xcb, 0xf8, 0xc5, 0xaf, 0x5b, 0x77, 0x48, 0x84, 0x67, 0xaa, 0x8c, 0x44, 0xfa, 0x4c, 0xca,
+];
+
+#[rustfmt::skip]
+pub const METADATA_LIBRARY_OBJECT: Guid = [
+    0x94, 0x1c, 0x23, 0x44, 0x98, 0x94, 0xd1, 0x49, 0xa1, 0x41, 0x1d, 0x13, 0x4e, 0x45, 0x70, 0x54,
+];
+
+/// The stream type of an audio stream.
+#[rustfmt::skip]
+pub const AUDIO_MEDIA: Guid = [
+    0x40, 0x9e, 0x69, 0xf8, 0x4d, 0x5b, 0xcf, 0x11, 0xa8, 0xfd, 0x00, 0x80, 0x5f, 0x5c, 0x44, 0x2b,
+];
+
+/// The error correction type of an audio stream with interleaved (spread) media objects.
+#[rustfmt::skip]
+const AUDIO_SPREAD: Guid = [
+    0x50, 0xcd, 0xc3, 0xbf, 0x8f, 0x61, 0xcf, 0x11, 0x8b, 0xb2, 0x00, 0xaa, 0x00, 0xb4, 0xe2, 0x20,
+];
+
+/// The length of an object header.
+pub const OBJECT_HEADER_LEN: u64 = 24;
+
+/// The header common to all objects.
+#[derive(Copy, Clone, Debug)]
+pub struct ObjectHeader {
+    /// The object type.
+    pub guid: Guid,
+    /// The length of the object, including the header.
+    pub len: u64,
+}
+
+impl ObjectHeader {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<Self> {
+        let mut guid = [0; 16];
+        reader.read_buf_exact(&mut guid)?;
+
+        let len = reader.read_u64()?;
+
+        if len < OBJECT_HEADER_LEN {
+            return decode_error("asf: invalid object length");
+        }
+
+        Ok(ObjectHeader { guid, len })
+    }
+
+    /// Gets the length of the object excluding the header.
+    pub fn data_len(&self) -> u64 {
+        self.len - OBJECT_HEADER_LEN
+    }
+}
+
+/// Calls `f` with the type and data of each object in a buffer of consecutive objects.
+pub fn for_each_object<'a, F>(buf: &'a [u8], mut f: F) -> Result<()>
+where
+    F: FnMut(Guid, &'a [u8]) -> Result<()>,
+{
+    let mut reader = BufReader::new(buf);
+
+    while reader.bytes_available() >= OBJECT_HEADER_LEN {
+        let header = ObjectHeader::read(&mut reader)?;
+
+        if header.data_len() > reader.bytes_available() {
+            return decode_error("asf: object exceeds the length of its parent");
+        }
+
+        f(header.guid, reader.read_buf_bytes_ref(header.data_len() as usize)?)?;
+    }
+
+    Ok(())
+}
+
+/// The file properties object.
+#[derive(Debug)]
+pub struct FileProperties {
+    /// The number of data packets, if known.
+    pub n_packets: Option<u64>,
+    /// The duration of the presentation in 100 ns units, including the preroll.
+    pub play_duration: u64,
+    /// The amount of time to buffer before playback in milliseconds. Presentation times are offset
+    /// by this amount.
+    pub preroll: u64,
+    /// The length of every data packet.
+    pub packet_len: u32,
+    /// The file is being broadcast, and the sizes, counts, and durations are not valid.
+    pub is_broadcast: bool,
+}
+
+impl FileProperties {
+    pub fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = BufReader::new(buf);
+
+        // Skip the file ID, file size, and creation date.
+        reader.ignore_bytes(32)?;
+
+        let n_packets = reader.read_u64()?;
+        let play_duration = reader.read_u64()?;
+        let _send_duration = reader.read_u64()?;
+        let preroll = reader.read_u64()?;
+        let flags = reader.read_u32()?;
+        let min_packet_len = reader.read_u32()?;
+        let max_packet_len = reader.read_u32()?;
+
+        if min_packet_len != max_packet_len {
+            return unsupported_error("asf: variable length data packets are not supported");
+        }
+
+        if min_packet_len == 0 {
+            return decode_error("asf: invalid data packet length");
+        }
+
+        let is_broadcast = flags & 0x1 != 0;
+
+        Ok(FileProperties {
+            n_packets: if is_broadcast { None } else { Some(n_packets) },
+            play_duration: if is_broadcast { 0 } else { play_duration },
+            preroll,
+            packet_len: min_packet_len,
+            is_broadcast,
+        })
+    }
+}
+
+/// The audio format of a stream, a `WAVEFORMATEX` structure.
+#[derive(Debug)]
+pub struct WaveFormat {
+    pub format_tag: u16,
+    pub n_channels: u16,
+    pub sample_rate: u32,
+    pub avg_bytes_per_sec: u32,
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+    /// The codec specific data following the structure.
+    pub extra_data: Box<[u8]>,
+}
+
+impl WaveFormat {
+    fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = BufReader::new(buf);
+
+        let format_tag = reader.read_u16()?;
+        let n_channels = reader.read_u16()?;
+        let sample_rate = reader.read_u32()?;
+        let avg_bytes_per_sec = reader.read_u32()?;
+        let block_align = reader.read_u16()?;
+        let bits_per_sample = reader.read_u16()?;
+
+        // The extra data length is optional for some formats.
+        let extra_data = match reader.read_u16() {
+            Ok(len) => {
+                let len = (len as usize).min(reader.bytes_available() as usize);
+                Box::from(reader.read_buf_bytes_ref(len)?)
+            }
+            Err(_) => Box::default(),
+        };
+
+        Ok(WaveFormat {
+            format_tag,
+            n_channels,
+            sample_rate,
+            avg_bytes_per_sec,
+            block_align,
+            bits_per_sample,
+            extra_data,
+        })
+    }
+}
+
+/// The parameters of an audio stream's spread (interleaving) of media objects.
+#[derive(Copy, Clone, Debug)]
+pub struct AudioSpread {
+    /// The number of virtual packets the chunks of a media object are spread over.
+    pub span: u8,
+    /// The length of a virtual packet.
+    pub packet_len: u16,
+    /// The length of a chunk.
+    pub chunk_len: u16,
+}
+
+impl AudioSpread {
+    fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = BufReader::new(buf);
+
+        let span = reader.read_u8()?;
+        let packet_len = reader.read_u16()?;
+        let chunk_len = reader.read_u16()?;
+
+        Ok(AudioSpread { span, packet_len, chunk_len })
+    }
+
+    /// Gets if the media objects must be descrambled.
+    pub fn is_scrambled(&self) -> bool {
+        self.span > 1
+            && self.chunk_len > 0
+            && self.packet_len / self.chunk_len > 1
+            && self.packet_len % self.chunk_len == 0
+    }
+
+    /// Restores the order of the chunks of a media object.
+    pub fn descramble(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let chunk_len = usize::from(self.chunk_len);
+        let span = usize::from(self.span);
+        let chunks_per_packet = usize::from(self.packet_len / self.chunk_len);
+
+        if data.len() % chunk_len != 0 {
+            return None;
+        }
+
+        let mut out = Vec::with_capacity(data.len());
+
+        for i in 0..data.len() / chunk_len {
+            // The chunks are stored in column-major order, with a column for each virtual packet.
+            let row = i / span;
+            let col = i % span;
+
+            let start = (row + col * chunks_per_packet) * chunk_len;
+
+            out.extend_from_slice(data.get(start..start + chunk_len)?);
+        }
+
+        Some(out)
+    }
+}
+
+/// The stream properties object.
+#[derive(Debug)]
+pub struct StreamProperties {
+    /// The stream number.
+    pub stream_number: u8,
+    /// The audio format, or `None` if the stream is not an audio stream.
+    pub format: Option<WaveFormat>,
+    /// The spread of the media objects, if they are interleaved.
+    pub spread: Option<AudioSpread>,
+}
+
+impl StreamProperties {
+    pub fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = BufReader::new(buf);
+
+        let mut stream_type = [0; 16];
+        reader.read_buf_exact(&mut stream_type)?;
+
+        let mut error_correction_type = [0; 16];
+        reader.read_buf_exact(&mut error_correction_type)?;
+
+        let _time_offset = reader.read_u64()?;
+        let type_specific_len = reader.read_u32()?;
+        let error_correction_len = reader.read_u32()?;
+        let flags = reader.read_u16()?;
+        let _reserved = reader.read_u32()?;
+
+        let type_specific = reader.read_buf_bytes_ref(type_specific_len as usize)?;
+        let error_correction = reader.read_buf_bytes_ref(error_correction_len as usize)?;
+
+        let stream_number = (flags & 0x7f) as u8;
+
+        if flags & 0x8000 != 0 {
+            return unsupported_error("asf: encrypted streams are not supported");
+        }
+
+        let format =
+            if stream_type == AUDIO_MEDIA { Some(WaveFormat::read(type_specific)?) } else { None };
+
+        let spread = if error_correction_type == AUDIO_SPREAD {
+            Some(AudioSpread::read(error_correction)?)
+        }
+        else {
+            None
+        };
+
+        Ok(StreamProperties { stream_number, format, spread })
+    }
+}
+
+/// The header extension object. Returns the buffer of objects it contains.
+pub fn read_header_extension(buf: &[u8]) -> Result<&[u8]> {
+    let mut reader = BufReader::new(buf);
+
+    // Skip the reserved GUID, and reserved field.
+    reader.ignore_bytes(18)?;
+
+    let len = reader.read_u32()? as usize;
+
+    if len > reader.bytes_available() as usize {
+        return decode_error("asf: invalid header extension length");
+    }
+
+    Ok(reader.read_buf_bytes_ref(len)?)
+}
+
+/// The simple index object.
+#[derive(Debug)]
+pub struct SimpleIndex {
+    /// The time interval between index entries in 100 ns units.
+    pub interval: u64,
+    /// For each index entry, the data packet number.
+    pub packets: Vec<u32>,
+}
+
+impl SimpleIndex {
+    pub fn read(buf: &[u8]) -> Result<Self> {
+        let mut reader = BufReader::new(buf);
+
+        // Skip the file ID.
+        reader.ignore_bytes(16)?;
+
+        let interval = reader.read_u64()?;
+        let _max_packet_count = reader.read_u32()?;
+        let n_entries = reader.read_u32()?;
+
+        if interval == 0 {
+            return decode_error("asf: invalid simple index interval");
+        }
+
+        if u64::from(n_entries) * 6 > reader.bytes_available() {
+            return decode_error("asf: invalid simple index entry count");
+        }
+
+        let mut packets = Vec::with_capacity(n_entries as usize);
+
+        for _ in 0..n_entries {
+            packets.push(reader.read_u32()?);
+            let _packet_count = reader.read_u16()?;
+        }
+
+        Ok(SimpleIndex { interval, packets })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_audio_spread_descramble() {
+        // 2 virtual packets of 2 chunks each.
+        let spread = AudioSpread { span: 2, packet_len: 4, chunk_len: 2 };
+
+        assert!(spread.is_scrambled());
+        assert_eq!(spread.descramble(&[0, 1, 2, 3, 4, 5, 6, 7]).unwrap(), [0, 1, 4, 5, 2, 3, 6, 7]);
+
+        // Not a whole number of chunks.
+        assert!(spread.descramble(&[0, 1, 2]).is_none());
+
+        // A span of 1 is not scrambled.
+        assert!(!AudioSpread { span: 1, packet_len: 4, chunk_len: 2 }.is_scrambled());
+    }
+
+    #[test]
+    fn verify_simple_index() {
+        let mut buf = vec![0; 16];
+        buf.extend_from_slice(&50_000_000u64.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&3u32.to_le_bytes());
+
+        for &packet in [0u32, 7, 12].iter() {
+            buf.extend_from_slice(&packet.to_le_bytes());
+            buf.extend_from_slice(&1u16.to_le_bytes());
+        }
+
+        let index = SimpleIndex::read(&buf).unwrap();
+
+        assert_eq!(index.interval, 50_000_000);
+        assert_eq!(index.packets, [0, 7, 12]);
+
+        // More entries than the object contains.
+        buf[28..32].copy_from_slice(&4u32.to_le_bytes());
+
+        assert!(SimpleIndex::read(&buf).is_err());
+    }
+}
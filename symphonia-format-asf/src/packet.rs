@@ -0,0 +1,293 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+/// A payload of a data packet. A payload contains all, or a fragment of, a media object.
+#[derive(Debug)]
+pub struct Payload<'a> {
+    /// The stream number.
+    pub stream_number: u8,
+    /// The media object number.
+    pub object_number: u32,
+    /// The offset of the payload data into the media object.
+    pub object_offset: u32,
+    /// The length of the media object.
+    pub object_len: u32,
+    /// The presentation time of the media object in milliseconds.
+    pub pres_time: u32,
+    /// The payload data.
+    pub data: &'a [u8],
+}
+
+/// A data packet.
+#[derive(Debug)]
+pub struct DataPacket<'a> {
+    /// The payloads.
+    pub payloads: Vec<Payload<'a>>,
+}
+
+/// Reads a field with a length given by a 2-bit length type.
+fn read_field(reader: &mut BufReader<'_>, len_type: u8) -> Result<u32> {
+    let value = match len_type & 0x3 {
+        0 => 0,
+        1 => u32::from(reader.read_u8()?),
+        2 => u32::from(reader.read_u16()?),
+        _ => reader.read_u32()?,
+    };
+
+    Ok(value)
+}
+
+/// The length types of the fields of each payload.
+#[derive(Copy, Clone)]
+struct PayloadFieldTypes {
+    replicated_data_len: u8,
+    object_offset: u8,
+    object_number: u8,
+    stream_number: u8,
+    /// The length type of the payload length, if there are multiple payloads.
+    payload_len: Option<u8>,
+}
+
+impl<'a> DataPacket<'a> {
+    /// Reads a data packet from a buffer containing the entire packet.
+    pub fn read(buf: &'a [u8]) -> Result<Self> {
+        let mut reader = BufReader::new(buf);
+
+        let mut flags = reader.read_u8()?;
+
+        // The first byte is either the error correction flags, or the length type flags of the
+        // payload parsing information.
+        if flags & 0x80 != 0 {
+            if flags & 0x60 != 0 {
+                return unsupported_error("asf: unsupported error correction length type");
+            }
+
+            reader.ignore_bytes(u64::from(flags & 0xf))?;
+
+            flags = reader.read_u8()?;
+        }
+
+        let property_flags = reader.read_u8()?;
+
+        let packet_len = read_field(&mut reader, flags >> 5)? as usize;
+        let _sequence = read_field(&mut reader, flags >> 1)?;
+        let padding_len = read_field(&mut reader, flags >> 3)? as usize;
+
+        let send_time = reader.read_u32()?;
+        let _duration = reader.read_u16()?;
+
+        // If the packet length is not explicitly set, then the packet has the fixed data packet
+        // length.
+        let packet_len = if packet_len > 0 { packet_len.min(buf.len()) } else { buf.len() };
+
+        let end = match packet_len.checked_sub(padding_len) {
+            Some(end) if end >= reader.pos() as usize => end,
+            _ => return decode_error("asf: invalid padding length"),
+        };
+
+        let mut types = PayloadFieldTypes {
+            replicated_data_len: property_flags,
+            object_offset: property_flags >> 2,
+            object_number: property_flags >> 4,
+            stream_number: property_flags >> 6,
+            payload_len: None,
+        };
+
+        let mut payloads = Vec::new();
+
+        if flags & 0x1 != 0 {
+            // Multiple payloads.
+            let payload_flags = reader.read_u8()?;
+
+            types.payload_len = Some(payload_flags >> 6);
+
+            for _ in 0..payload_flags & 0x3f {
+                read_payload(&mut reader, types, end, send_time, &mut payloads)?;
+            }
+        }
+        else {
+            read_payload(&mut reader, types, end, send_time, &mut payloads)?;
+        }
+
+        Ok(DataPacket { payloads })
+    }
+}
+
+/// Reads a payload, and appends it to `payloads`. A compressed payload appends a payload for each
+/// media object it contains.
+fn read_payload<'a>(
+    reader: &mut BufReader<'a>,
+    types: PayloadFieldTypes,
+    end: usize,
+    send_time: u32,
+    payloads: &mut Vec<Payload<'a>>,
+) -> Result<()> {
+    let stream_number = (read_field(reader, types.stream_number)? & 0x7f) as u8;
+    let object_number = read_field(reader, types.object_number)?;
+    let object_offset = read_field(reader, types.object_offset)?;
+    let replicated_data_len = read_field(reader, types.replicated_data_len)?;
+
+    // For compressed payloads, the replicated data length is 1, and the offset into the media
+    // object is instead the presentation time.
+    let is_compressed = replicated_data_len == 1;
+
+    let (object_len, pres_time, pres_time_delta) = match replicated_data_len {
+        0 => (0, send_time, 0),
+        1 => (0, object_offset, reader.read_u8()?),
+        len if len >= 8 => {
+            let object_len = reader.read_u32()?;
+            let pres_time = reader.read_u32()?;
+
+            // Ignore the payload extension data.
+            reader.ignore_bytes(u64::from(len - 8))?;
+
+            (object_len, pres_time, 0)
+        }
+        _ => return decode_error("asf: invalid replicated data length"),
+    };
+
+    let pos = reader.pos() as usize;
+
+    let data_len = match types.payload_len {
+        Some(len_type) => read_field(reader, len_type)? as usize,
+        None => end.saturating_sub(pos),
+    };
+
+    if reader.pos() as usize + data_len > end {
+        return decode_error("asf: payload exceeds data packet");
+    }
+
+    let data = reader.read_buf_bytes_ref(data_len)?;
+
+    if is_compressed {
+        // A compressed payload contains a number of whole media objects, each prefixed with its
+        // length.
+        let mut sub_reader = BufReader::new(data);
+        let mut i = 0;
+
+        while let Ok(len) = sub_reader.read_u8() {
+            let data = sub_reader.read_buf_bytes_ref(usize::from(len))?;
+
+            payloads.push(Payload {
+                stream_number,
+                object_number: object_number.wrapping_add(i),
+                object_offset: 0,
+                object_len: u32::from(len),
+                pres_time: pres_time.wrapping_add(i * u32::from(pres_time_delta)),
+                data,
+            });
+
+            i += 1;
+        }
+    }
+    else {
+        // If the replicated data is missing, assume the payload is a whole media object.
+        let object_len = if replicated_data_len == 0 { data_len as u32 } else { object_len };
+
+        payloads.push(Payload {
+            stream_number,
+            object_number,
+            object_offset,
+            object_len,
+            pres_time,
+            data,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_single_payload() {
+        // Error correction data, a byte padding length, and a payload with byte stream and object
+        // numbers, a dword object offset, and a byte replicated data length.
+        let mut buf = vec![0x82, 0x00, 0x00, 0x08, 0x5d, 3];
+        buf.extend_from_slice(&3100u32.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+
+        // The payload of stream 1, a key frame, containing the whole of media object 5.
+        buf.extend_from_slice(&[0x81, 5]);
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.push(8);
+        buf.extend_from_slice(&10u32.to_le_bytes());
+        buf.extend_from_slice(&3100u32.to_le_bytes());
+        buf.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+        // Padding.
+        buf.extend_from_slice(&[0; 3]);
+
+        let packet = DataPacket::read(&buf).unwrap();
+
+        assert_eq!(packet.payloads.len(), 1);
+
+        let payload = &packet.payloads[0];
+
+        assert_eq!((payload.stream_number, payload.object_number), (1, 5));
+        assert_eq!((payload.object_offset, payload.object_len), (0, 10));
+        assert_eq!(payload.pres_time, 3100);
+        assert_eq!(payload.data, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn verify_multiple_payloads() {
+        // Multiple payloads with a byte padding length, then 3 payloads with byte payload lengths.
+        let mut buf = vec![0x09, 0x5d, 0];
+        buf.extend_from_slice(&3200u32.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.push(0x43);
+
+        // Two fragments of media object 6 of stream 1.
+        for &offset in [0u32, 6].iter() {
+            buf.extend_from_slice(&[1, 6]);
+            buf.extend_from_slice(&offset.to_le_bytes());
+            buf.push(8);
+            buf.extend_from_slice(&12u32.to_le_bytes());
+            buf.extend_from_slice(&3200u32.to_le_bytes());
+            buf.push(6);
+            buf.extend_from_slice(&[offset as u8; 6]);
+        }
+
+        // A compressed payload of stream 2 containing media objects 9 and 10, with a presentation
+        // time of 4000 ms and a delta of 5 ms.
+        buf.extend_from_slice(&[2, 9]);
+        buf.extend_from_slice(&4000u32.to_le_bytes());
+        buf.extend_from_slice(&[1, 5, 7, 2, 0xaa, 0xbb, 3, 0xcc, 0xdd, 0xee]);
+
+        let packet = DataPacket::read(&buf).unwrap();
+
+        let fields: Vec<(u8, u32, u32, u32, u32)> = packet
+            .payloads
+            .iter()
+            .map(|p| (p.stream_number, p.object_number, p.object_offset, p.object_len, p.pres_time))
+            .collect();
+
+        assert_eq!(
+            fields,
+            [(1, 6, 0, 12, 3200), (1, 6, 6, 12, 3200), (2, 9, 0, 2, 4000), (2, 10, 0, 3, 4005)]
+        );
+
+        assert_eq!(packet.payloads[1].data, &[6; 6]);
+        assert_eq!(packet.payloads[2].data, &[0xaa, 0xbb]);
+        assert_eq!(packet.payloads[3].data, &[0xcc, 0xdd, 0xee]);
+    }
+
+    #[test]
+    fn verify_invalid_padding() {
+        // The padding length exceeds the packet length.
+        let mut buf = vec![0x08, 0x5d, 40];
+        buf.extend_from_slice(&[0; 20]);
+
+        assert!(DataPacket::read(&buf).is_err());
+    }
+}
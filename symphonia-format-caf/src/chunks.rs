@@ -11,7 +11,8 @@ use symphonia_core::{
     audio::{Channels, Layout},
     codecs::*,
     errors::{decode_error, unsupported_error, Error, Result},
-    io::{MediaSourceStream, ReadBytes},
+    io::{BufReader, MediaSourceStream, ReadBytes},
+    meta::{StandardTagKey, Tag, Value, VendorData},
 };
 
 #[derive(Debug)]
@@ -21,6 +22,12 @@ pub enum Chunk {
     ChannelLayout(ChannelLayout),
     PacketTable(PacketTable),
     MagicCookie(Box<[u8]>),
+    Information(Information),
+    Strings(Strings),
+    Markers(Markers),
+    Regions(Regions),
+    Instrument(Instrument),
+    Uuid(VendorData),
     Free,
 }
 
@@ -57,6 +64,25 @@ impl Chunk {
                     return invalid_chunk_size_error("Magic Cookie", chunk_size);
                 }
             }
+            b"info" => Chunk::Information(Information::read(reader, chunk_size)?),
+            b"strg" => Chunk::Strings(Strings::read(reader, chunk_size)?),
+            b"mark" => Chunk::Markers(Markers::read(reader, chunk_size)?),
+            b"regn" => Chunk::Regions(Regions::read(reader, chunk_size)?),
+            b"inst" => Chunk::Instrument(Instrument::read(reader, chunk_size)?),
+            b"uuid" => {
+                if chunk_size < 16 {
+                    return invalid_chunk_size_error("User-Defined", chunk_size);
+                }
+
+                let uuid = reader.read_quad_bytes()?;
+                let mut ident = String::with_capacity(32);
+                for byte in uuid.iter().chain(reader.read_boxed_slice_exact(12)?.iter()) {
+                    ident.push_str(&format!("{:02x}", byte));
+                }
+
+                let data = read_chunk_data(reader, chunk_size - 16, "User-Defined")?;
+                Chunk::Uuid(VendorData { ident, data })
+            }
             b"free" => {
                 if chunk_size < 0 {
                     return invalid_chunk_size_error("Free", chunk_size);
@@ -158,8 +184,10 @@ impl AudioDescription {
                     }
                 }
             }
-            AppleIMA4 => CODEC_TYPE_ADPCM_IMA_WAV,
+            AppleIMA4 => CODEC_TYPE_ADPCM_IMA_QT,
             MPEG4AAC => CODEC_TYPE_AAC,
+            MACE3 => CODEC_TYPE_MACE3,
+            MACE6 => CODEC_TYPE_MACE6,
             ULaw => CODEC_TYPE_PCM_MULAW,
             ALaw => CODEC_TYPE_PCM_ALAW,
             MPEGLayer1 => CODEC_TYPE_MP1,
@@ -168,6 +196,10 @@ impl AudioDescription {
             AppleLossless => CODEC_TYPE_ALAC,
             Flac => CODEC_TYPE_FLAC,
             Opus => CODEC_TYPE_OPUS,
+            MicrosoftAcm { format_tag: 0x0045 } | MicrosoftAcm { format_tag: 0x0064 } => {
+                CODEC_TYPE_ADPCM_G726
+            }
+            MicrosoftAcm { format_tag: 0x028f } => CODEC_TYPE_ADPCM_G722,
             unsupported => {
                 error!("unsupported codec ({:?})", unsupported);
                 return unsupported_error("caf: unsupported codec");
@@ -180,6 +212,12 @@ impl AudioDescription {
     pub fn format_is_compressed(&self) -> bool {
         self.bits_per_channel == 0
     }
+
+    /// Returns true if every packet has the same size and duration, in which case the stream does
+    /// not require a packet table.
+    pub fn format_is_constant_bit_rate(&self) -> bool {
+        self.bytes_per_packet != 0 && self.frames_per_packet != 0
+    }
 }
 
 #[derive(Debug)]
@@ -213,7 +251,10 @@ impl AudioData {
 
 #[derive(Debug)]
 pub enum AudioDescriptionFormatId {
-    LinearPCM { floating_point: bool, little_endian: bool },
+    LinearPCM {
+        floating_point: bool,
+        little_endian: bool,
+    },
     AppleIMA4,
     MPEG4AAC,
     MACE3,
@@ -226,6 +267,10 @@ pub enum AudioDescriptionFormatId {
     AppleLossless,
     Flac,
     Opus,
+    /// A Microsoft ACM codec identified by its WAVE format tag.
+    MicrosoftAcm {
+        format_tag: u16,
+    },
 }
 
 impl AudioDescriptionFormatId {
@@ -260,6 +305,8 @@ impl AudioDescriptionFormatId {
             // Additional formats from CoreAudioBaseTypes.h
             b"flac" => Flac,
             b"opus" => Opus,
+            // Microsoft ACM codecs are identified by 'ms' followed by the WAVE format tag.
+            [b'm', b's', hi, lo] => MicrosoftAcm { format_tag: u16::from_be_bytes([*hi, *lo]) },
             other => {
                 error!("unsupported format id ({:?})", other);
                 return unsupported_error("caf: unsupported format id");
@@ -335,10 +382,51 @@ impl ChannelLayout {
             // Layout tags which have channel roles that match the standard channel layout
             LAYOUT_TAG_MONO => Layout::Mono.into_channels(),
             LAYOUT_TAG_STEREO | LAYOUT_TAG_STEREO_HEADPHONES => Layout::Stereo.into_channels(),
-            LAYOUT_TAG_MPEG_3_0_A => {
+            LAYOUT_TAG_MPEG_3_0_A | LAYOUT_TAG_MPEG_3_0_B => {
                 Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE
             }
             LAYOUT_TAG_MPEG_5_1_A => Layout::FivePointOne.into_channels(),
+            // Layout tags used by ALAC
+            LAYOUT_TAG_MPEG_4_0_B => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::REAR_CENTRE
+            }
+            LAYOUT_TAG_MPEG_5_0_D => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::SIDE_LEFT
+                    | Channels::SIDE_RIGHT
+            }
+            LAYOUT_TAG_MPEG_5_1_D => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::SIDE_LEFT
+                    | Channels::SIDE_RIGHT
+            }
+            LAYOUT_TAG_AAC_6_1 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::SIDE_LEFT
+                    | Channels::SIDE_RIGHT
+                    | Channels::REAR_CENTRE
+            }
+            LAYOUT_TAG_MPEG_7_1_B => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::SIDE_LEFT
+                    | Channels::SIDE_RIGHT
+                    | Channels::FRONT_LEFT_CENTRE
+                    | Channels::FRONT_RIGHT_CENTRE
+            }
             LAYOUT_TAG_MPEG_7_1_A => {
                 Channels::FRONT_LEFT
                     | Channels::FRONT_RIGHT
@@ -355,6 +443,17 @@ impl ChannelLayout {
                     | Channels::FRONT_CENTRE
                     | Channels::LFE1
             }
+            // Discrete channels without a speaker assignment, use the first N channels
+            tag if tag & 0xffff_0000 == LAYOUT_TAG_DISCRETE_IN_ORDER => {
+                let num_channels = tag & 0xffff;
+
+                if num_channels < 1 || num_channels > 32 {
+                    debug!("unsupported number of discrete channels: {}", num_channels);
+                    return None;
+                }
+
+                return Channels::from_bits(!((!0 << 1) << (num_channels - 1)));
+            }
             unsupported => {
                 debug!("unsupported channel layout: {}", unsupported);
                 return None;
@@ -385,6 +484,7 @@ impl ChannelDescription {
 }
 
 const LAYOUT_TAG_USE_CHANNEL_BITMAP: u32 = 1 << 16;
+
 // Layout tags from the CAF spec that match the first N channels of a standard layout
 const LAYOUT_TAG_MONO: u32 = (100 << 16) | 1;
 const LAYOUT_TAG_STEREO: u32 = (101 << 16) | 2;
@@ -394,6 +494,267 @@ const LAYOUT_TAG_MPEG_5_1_A: u32 = (121 << 16) | 6; // L R C LFE Ls Rs
 const LAYOUT_TAG_MPEG_7_1_A: u32 = (126 << 16) | 8; // L R C LFE Ls Rs Lc Rc
 const LAYOUT_TAG_DVD_10: u32 = (136 << 16) | 4; // L R C LFE
 
+// Layout tags used by ALAC
+const LAYOUT_TAG_MPEG_3_0_B: u32 = (114 << 16) | 3; // C L R
+const LAYOUT_TAG_MPEG_4_0_B: u32 = (116 << 16) | 4; // C L R Cs
+const LAYOUT_TAG_MPEG_5_0_D: u32 = (120 << 16) | 5; // C L R Ls Rs
+const LAYOUT_TAG_MPEG_5_1_D: u32 = (124 << 16) | 6; // C L R Ls Rs LFE
+const LAYOUT_TAG_AAC_6_1: u32 = (142 << 16) | 7; // C L R Ls Rs Cs LFE
+const LAYOUT_TAG_MPEG_7_1_B: u32 = (127 << 16) | 8; // C Lc Rc L R Ls Rs LFE
+
+// Discrete channels in order, the lower 16 bits are the number of channels
+const LAYOUT_TAG_DISCRETE_IN_ORDER: u32 = 147 << 16;
+
+#[derive(Debug)]
+pub struct Information {
+    pub tags: Vec<Tag>,
+}
+
+impl Information {
+    pub fn read(reader: &mut MediaSourceStream, chunk_size: i64) -> Result<Self> {
+        if chunk_size < 4 {
+            return invalid_chunk_size_error("Information", chunk_size);
+        }
+
+        let data = read_chunk_data(reader, chunk_size, "Information")?;
+        let mut reader = BufReader::new(&data);
+
+        let num_entries = reader.read_be_u32()?;
+
+        let mut tags = Vec::new();
+
+        for _ in 0..num_entries {
+            let key = read_null_terminated_string(&mut reader)?;
+            let value = read_null_terminated_string(&mut reader)?;
+
+            let std_key = match key.as_str() {
+                "album" => Some(StandardTagKey::Album),
+                "artist" => Some(StandardTagKey::Artist),
+                "comments" => Some(StandardTagKey::Comment),
+                "composer" => Some(StandardTagKey::Composer),
+                "copyright" => Some(StandardTagKey::Copyright),
+                "encoding application" => Some(StandardTagKey::Encoder),
+                "genre" => Some(StandardTagKey::Genre),
+                "lyricist" => Some(StandardTagKey::Lyricist),
+                "recorded date" => Some(StandardTagKey::Date),
+                "tempo" => Some(StandardTagKey::Bpm),
+                "title" => Some(StandardTagKey::TrackTitle),
+                "track number" => Some(StandardTagKey::TrackNumber),
+                "year" => Some(StandardTagKey::Date),
+                _ => None,
+            };
+
+            tags.push(Tag::new(std_key, &key, Value::from(value)));
+        }
+
+        Ok(Self { tags })
+    }
+}
+
+#[derive(Debug)]
+pub struct Strings {
+    /// Pairs of string IDs and strings.
+    pub strings: Vec<(u32, String)>,
+}
+
+impl Strings {
+    pub fn read(reader: &mut MediaSourceStream, chunk_size: i64) -> Result<Self> {
+        if chunk_size < 4 {
+            return invalid_chunk_size_error("Strings", chunk_size);
+        }
+
+        let data = read_chunk_data(reader, chunk_size, "Strings")?;
+        let mut reader = BufReader::new(&data);
+
+        let num_entries = reader.read_be_u32()?;
+
+        let mut entries = Vec::new();
+
+        for _ in 0..num_entries {
+            let string_id = reader.read_be_u32()?;
+            let offset = reader.read_be_i64()?;
+            entries.push((string_id, offset));
+        }
+
+        // The string offsets are relative to the end of the string ID table.
+        let string_data = reader.read_buf_bytes_available_ref();
+
+        let mut strings = Vec::with_capacity(entries.len());
+
+        for (string_id, offset) in entries {
+            let string = match usize::try_from(offset).ok().and_then(|o| string_data.get(o..)) {
+                Some(buf) => {
+                    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+                    String::from_utf8_lossy(&buf[..end]).into_owned()
+                }
+                None => {
+                    error!("invalid string offset ({})", offset);
+                    return decode_error("caf: invalid string offset");
+                }
+            };
+
+            strings.push((string_id, string));
+        }
+
+        Ok(Self { strings })
+    }
+}
+
+#[derive(Debug)]
+pub struct Marker {
+    pub marker_type: [u8; 4],
+    pub frame_position: f64,
+    /// The ID of the marker, used to find the marker's name in the strings chunk.
+    pub marker_id: u32,
+    #[allow(dead_code)]
+    pub channel: u32,
+}
+
+impl Marker {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<Self> {
+        let marker_type = reader.read_quad_bytes()?;
+        let frame_position = reader.read_be_f64()?;
+        let marker_id = reader.read_be_u32()?;
+        // Ignore the SMPTE time.
+        reader.ignore_bytes(8)?;
+        let channel = reader.read_be_u32()?;
+
+        Ok(Self { marker_type, frame_position, marker_id, channel })
+    }
+
+    /// The position of the marker in frames.
+    pub fn position(&self) -> u64 {
+        self.frame_position.max(0.0) as u64
+    }
+}
+
+#[derive(Debug)]
+pub struct Markers {
+    pub markers: Vec<Marker>,
+}
+
+impl Markers {
+    pub fn read(reader: &mut MediaSourceStream, chunk_size: i64) -> Result<Self> {
+        if chunk_size < 8 {
+            return invalid_chunk_size_error("Marker", chunk_size);
+        }
+
+        let data = read_chunk_data(reader, chunk_size, "Marker")?;
+        let mut reader = BufReader::new(&data);
+
+        // Ignore the SMPTE time type.
+        reader.read_be_u32()?;
+
+        let num_markers = reader.read_be_u32()?;
+
+        let markers = (0..num_markers).map(|_| Marker::read(&mut reader)).collect::<Result<_>>()?;
+
+        Ok(Self { markers })
+    }
+}
+
+/// The region should be looped.
+pub const REGION_FLAG_LOOP_ENABLE: u32 = 1;
+/// The region should be played forward.
+pub const REGION_FLAG_PLAY_FORWARD: u32 = 2;
+/// The region should be played backward.
+pub const REGION_FLAG_PLAY_BACKWARD: u32 = 4;
+
+#[derive(Debug)]
+pub struct Region {
+    /// The ID of the region, used to find the region's name in the strings chunk.
+    pub region_id: u32,
+    pub flags: u32,
+    pub markers: Vec<Marker>,
+}
+
+#[derive(Debug)]
+pub struct Regions {
+    pub regions: Vec<Region>,
+}
+
+impl Regions {
+    pub fn read(reader: &mut MediaSourceStream, chunk_size: i64) -> Result<Self> {
+        if chunk_size < 8 {
+            return invalid_chunk_size_error("Region", chunk_size);
+        }
+
+        let data = read_chunk_data(reader, chunk_size, "Region")?;
+        let mut reader = BufReader::new(&data);
+
+        // Ignore the SMPTE time type.
+        reader.read_be_u32()?;
+
+        let num_regions = reader.read_be_u32()?;
+
+        let mut regions = Vec::new();
+
+        for _ in 0..num_regions {
+            let region_id = reader.read_be_u32()?;
+            let flags = reader.read_be_u32()?;
+            let num_markers = reader.read_be_u32()?;
+
+            let markers =
+                (0..num_markers).map(|_| Marker::read(&mut reader)).collect::<Result<_>>()?;
+
+            regions.push(Region { region_id, flags, markers });
+        }
+
+        Ok(Self { regions })
+    }
+}
+
+#[derive(Debug)]
+pub struct Instrument {
+    /// The MIDI note that plays the sound at its original pitch, including fractional cents.
+    pub base_note: f32,
+    pub low_note: u8,
+    pub high_note: u8,
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+    /// The gain adjustment in decibels.
+    pub gain: f32,
+    #[allow(dead_code)]
+    pub start_region_id: u32,
+    pub sustain_region_id: u32,
+    pub release_region_id: u32,
+    #[allow(dead_code)]
+    pub instrument_id: u32,
+}
+
+impl Instrument {
+    pub fn read(reader: &mut MediaSourceStream, chunk_size: i64) -> Result<Self> {
+        if chunk_size != 28 {
+            return invalid_chunk_size_error("Instrument", chunk_size);
+        }
+
+        Ok(Self {
+            base_note: reader.read_be_f32()?,
+            low_note: reader.read_u8()?,
+            high_note: reader.read_u8()?,
+            low_velocity: reader.read_u8()?,
+            high_velocity: reader.read_u8()?,
+            gain: reader.read_be_f32()?,
+            start_region_id: reader.read_be_u32()?,
+            sustain_region_id: reader.read_be_u32()?,
+            release_region_id: reader.read_be_u32()?,
+            instrument_id: reader.read_be_u32()?,
+        })
+    }
+
+    /// Gets the instrument information as tags.
+    pub fn tags(&self) -> Vec<Tag> {
+        vec![
+            Tag::new(None, "UnshiftedNote", Value::from(self.base_note)),
+            Tag::new(None, "Gain", Value::from(self.gain)),
+            Tag::new(None, "LowNote", Value::from(self.low_note)),
+            Tag::new(None, "HighNote", Value::from(self.high_note)),
+            Tag::new(None, "LowVelocity", Value::from(self.low_velocity)),
+            Tag::new(None, "HighVelocity", Value::from(self.high_velocity)),
+        ]
+    }
+}
+
 pub struct PacketTable {
     pub valid_frames: i64,
     pub priming_frames: i32,
@@ -532,6 +893,23 @@ fn invalid_chunk_size_error<T>(chunk_type: &str, chunk_size: i64) -> Result<T> {
     decode_error("caf: invalid chunk size")
 }
 
+fn read_chunk_data(
+    reader: &mut MediaSourceStream,
+    chunk_size: i64,
+    chunk_type: &str,
+) -> Result<Box<[u8]>> {
+    match usize::try_from(chunk_size) {
+        Ok(chunk_size) => Ok(reader.read_boxed_slice_exact(chunk_size)?),
+        Err(_) => invalid_chunk_size_error(chunk_type, chunk_size),
+    }
+}
+
+fn read_null_terminated_string(reader: &mut BufReader<'_>) -> Result<String> {
+    let buf = reader.scan_bytes_ref(&[0], usize::MAX)?;
+    let buf = buf.strip_suffix(&[0]).unwrap_or(buf);
+    Ok(String::from_utf8_lossy(buf).into_owned())
+}
+
 fn read_variable_length_integer(reader: &mut MediaSourceStream) -> Result<u64> {
     let mut result = 0;
 
@@ -14,9 +14,11 @@ use symphonia_core::{
     errors::{
         decode_error, end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind,
     },
-    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    formats::util::trim_packet,
+    formats::Track,
+    formats::{Cue, CuePoint, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo},
     io::{MediaSource, MediaSourceStream, ReadBytes},
-    meta::{Metadata, MetadataLog},
+    meta::{Metadata, MetadataBuilder, MetadataLog, MetadataRevision, Tag, Value, VendorData},
     probe::{Descriptor, Instantiate, QueryDescriptor},
     support_format,
     units::{TimeBase, TimeStamp},
@@ -35,12 +37,22 @@ pub struct CafReader {
     data_start_pos: u64,
     data_len: Option<u64>,
     packet_info: PacketInfo,
+    options: FormatOptions,
 }
 
 enum PacketInfo {
     Unknown,
-    Uncompressed { bytes_per_frame: u32 },
-    Compressed { packets: Vec<CafPacket>, current_packet_index: usize },
+    /// Every packet has the same size and duration. The stream is read in groups of packets.
+    ConstantBitRate {
+        bytes_per_packet: u64,
+        frames_per_packet: u64,
+        packets_per_read: u64,
+    },
+    /// Packet sizes and durations are read from the packet table.
+    Compressed {
+        packets: Vec<CafPacket>,
+        current_packet_index: usize,
+    },
 }
 
 impl QueryDescriptor for CafReader {
@@ -54,7 +66,7 @@ impl QueryDescriptor for CafReader {
 }
 
 impl FormatReader for CafReader {
-    fn try_new(source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+    fn try_new(source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
         let mut reader = Self {
             reader: source,
             tracks: vec![],
@@ -63,6 +75,7 @@ impl FormatReader for CafReader {
             data_start_pos: 0,
             data_len: None,
             packet_info: PacketInfo::Unknown,
+            options: *options,
         };
 
         reader.check_file_header()?;
@@ -74,13 +87,75 @@ impl FormatReader for CafReader {
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
+        let mut packet = self.next_packet_inner()?;
+
+        if self.options.enable_gapless {
+            let params = &self.tracks[0].codec_params;
+            trim_packet(&mut packet, params.delay.unwrap_or(0), params.n_frames);
+        }
+
+        Ok(packet)
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                if let Some(time_base) = self.time_base() {
+                    time_base.calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        // If gapless playback is enabled, timestamps are offset by the delay.
+        let delay = if self.options.enable_gapless {
+            u64::from(self.tracks[0].codec_params.delay.unwrap_or(0))
+        }
+        else {
+            0
+        };
+
+        let seeked_to = self.seek_inner(required_ts + delay)?;
+
+        Ok(SeekedTo {
+            track_id: seeked_to.track_id,
+            actual_ts: seeked_to.actual_ts.saturating_sub(delay),
+            required_ts,
+        })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+impl CafReader {
+    fn next_packet_inner(&mut self) -> Result<Packet> {
         match &mut self.packet_info {
-            PacketInfo::Uncompressed { bytes_per_frame } => {
+            PacketInfo::ConstantBitRate {
+                bytes_per_packet,
+                frames_per_packet,
+                packets_per_read,
+            } => {
                 let pos = self.reader.pos();
                 let data_pos = pos - self.data_start_pos;
 
-                let bytes_per_frame = *bytes_per_frame as u64;
-                let max_bytes_to_read = bytes_per_frame * MAX_FRAMES_PER_PACKET;
+                let max_bytes_to_read = *bytes_per_packet * *packets_per_read;
 
                 let bytes_remaining = if let Some(data_len) = self.data_len {
                     data_len - data_pos
@@ -93,9 +168,16 @@ impl FormatReader for CafReader {
                     return end_of_stream_error();
                 }
 
+                // Only read whole packets.
                 let bytes_to_read = max_bytes_to_read.min(bytes_remaining);
-                let packet_duration = bytes_to_read / bytes_per_frame;
-                let packet_timestamp = data_pos / bytes_per_frame;
+                let bytes_to_read = bytes_to_read - bytes_to_read % *bytes_per_packet;
+
+                if bytes_to_read == 0 {
+                    return end_of_stream_error();
+                }
+
+                let packet_duration = bytes_to_read / *bytes_per_packet * *frames_per_packet;
+                let packet_timestamp = data_pos / *bytes_per_packet * *frames_per_packet;
                 let buffer = self.reader.read_boxed_slice(bytes_to_read as usize)?;
                 Ok(Packet::new_from_boxed_slice(0, packet_timestamp, packet_duration, buffer))
             }
@@ -116,39 +198,21 @@ impl FormatReader for CafReader {
         }
     }
 
-    fn metadata(&mut self) -> Metadata<'_> {
-        self.metadata.metadata()
-    }
-
-    fn cues(&self) -> &[Cue] {
-        &self.cues
-    }
-
-    fn tracks(&self) -> &[Track] {
-        &self.tracks
-    }
-
-    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
-        let required_ts = match to {
-            SeekTo::TimeStamp { ts, .. } => ts,
-            SeekTo::Time { time, .. } => {
-                if let Some(time_base) = self.time_base() {
-                    time_base.calc_timestamp(time)
-                }
-                else {
-                    return seek_error(SeekErrorKind::Unseekable);
-                }
-            }
-        };
-
+    fn seek_inner(&mut self, required_ts: u64) -> Result<SeekedTo> {
         match &mut self.packet_info {
-            PacketInfo::Uncompressed { bytes_per_frame } => {
-                // Packetization for PCM data is performed by chunking the stream into
-                // packets of MAX_FRAMES_PER_PACKET frames each.
+            PacketInfo::ConstantBitRate {
+                bytes_per_packet,
+                frames_per_packet,
+                packets_per_read,
+            } => {
+                // Packetization for constant bit-rate data is performed by chunking the stream
+                // into groups of packets_per_read packets each.
                 // To allow for determinstic packet timestamps, we want the seek to jump to the
                 // packet boundary before the requested seek time.
-                let actual_ts = (required_ts / MAX_FRAMES_PER_PACKET) * MAX_FRAMES_PER_PACKET;
-                let seek_pos = self.data_start_pos + actual_ts * (*bytes_per_frame as u64);
+                let frames_per_read = *frames_per_packet * *packets_per_read;
+                let actual_ts = (required_ts / frames_per_read) * frames_per_read;
+                let seek_pos =
+                    self.data_start_pos + actual_ts / *frames_per_packet * *bytes_per_packet;
 
                 if self.reader.is_seekable() {
                     self.reader.seek(SeekFrom::Start(seek_pos))?;
@@ -225,12 +289,6 @@ impl FormatReader for CafReader {
         }
     }
 
-    fn into_inner(self: Box<Self>) -> MediaSourceStream {
-        self.reader
-    }
-}
-
-impl CafReader {
     fn time_base(&self) -> Option<TimeBase> {
         self.tracks.first().and_then(|track| {
             track.codec_params.sample_rate.map(|sample_rate| TimeBase::new(1, sample_rate))
@@ -265,7 +323,10 @@ impl CafReader {
             .with_sample_rate(desc.sample_rate as u32)
             .with_time_base(TimeBase::new(1, desc.sample_rate as u32))
             .with_bits_per_sample(desc.bits_per_channel)
-            .with_bits_per_coded_sample((desc.bytes_per_packet * 8) / desc.channels_per_frame);
+            .with_bits_per_coded_sample(
+                (desc.bytes_per_packet * 8)
+                    / (desc.channels_per_frame * desc.frames_per_packet.max(1)),
+            );
 
         match desc.channels_per_frame {
             0 => {
@@ -292,13 +353,25 @@ impl CafReader {
             }
         }
 
-        if desc.format_is_compressed() {
-            self.packet_info =
-                PacketInfo::Compressed { packets: Vec::new(), current_packet_index: 0 };
+        if !desc.format_is_compressed() || desc.format_is_constant_bit_rate() {
+            // Uncompressed formats always have one frame per packet.
+            let bytes_per_packet = u64::from(desc.bytes_per_packet);
+            let frames_per_packet = u64::from(desc.frames_per_packet.max(1));
+            let packets_per_read = (MAX_FRAMES_PER_PACKET / frames_per_packet).max(1);
+
+            codec_params
+                .with_max_frames_per_packet(frames_per_packet * packets_per_read)
+                .with_frames_per_block(frames_per_packet);
+
+            self.packet_info = PacketInfo::ConstantBitRate {
+                bytes_per_packet,
+                frames_per_packet,
+                packets_per_read,
+            }
         }
         else {
-            codec_params.with_max_frames_per_packet(MAX_FRAMES_PER_PACKET).with_frames_per_block(1);
-            self.packet_info = PacketInfo::Uncompressed { bytes_per_frame: desc.bytes_per_packet }
+            self.packet_info =
+                PacketInfo::Compressed { packets: Vec::new(), current_packet_index: 0 };
         };
 
         Ok(())
@@ -309,6 +382,8 @@ impl CafReader {
 
         let mut codec_params = CodecParameters::new();
         let mut audio_description = None;
+        let mut packet_table = None;
+        let mut aux = CafMetadata::default();
 
         loop {
             match Chunk::read(&mut self.reader, &audio_description)? {
@@ -323,8 +398,14 @@ impl CafReader {
                     self.data_start_pos = data.start_pos;
                     self.data_len = data.data_len;
                     if let Some(data_len) = self.data_len {
-                        if let PacketInfo::Uncompressed { bytes_per_frame } = &self.packet_info {
-                            codec_params.with_n_frames(data_len / *bytes_per_frame as u64);
+                        if let PacketInfo::ConstantBitRate {
+                            bytes_per_packet,
+                            frames_per_packet,
+                            ..
+                        } = &self.packet_info
+                        {
+                            codec_params
+                                .with_n_frames(data_len / *bytes_per_packet * *frames_per_packet);
                         }
                     }
                 }
@@ -339,15 +420,21 @@ impl CafReader {
                         info!("couldn't convert the channel layout into a channel bitmap");
                     }
                 }
-                Some(PacketTable(table)) => {
+                Some(PacketTable(mut table)) => {
                     if let PacketInfo::Compressed { ref mut packets, .. } = &mut self.packet_info {
-                        codec_params.with_n_frames(table.valid_frames as u64);
-                        *packets = table.packets;
+                        *packets = std::mem::take(&mut table.packets);
                     }
+                    packet_table = Some(table);
                 }
                 Some(MagicCookie(data)) => {
                     codec_params.with_extra_data(data);
                 }
+                Some(Information(info)) => aux.tags.extend(info.tags),
+                Some(Strings(strings)) => aux.strings.extend(strings.strings),
+                Some(Markers(markers)) => aux.markers.extend(markers.markers),
+                Some(Regions(regions)) => aux.regions.extend(regions.regions),
+                Some(Instrument(inst)) => aux.inst = Some(inst),
+                Some(Uuid(vendor_data)) => aux.vendor_data.push(vendor_data),
                 Some(Free) | None => {}
             }
 
@@ -368,6 +455,161 @@ impl CafReader {
             }
         }
 
+        // The packet table contains the number of priming and remainder frames added by the
+        // encoder, in addition to the number of valid frames.
+        if let Some(table) = packet_table {
+            let delay = table.priming_frames.max(0) as u32;
+            let padding = table.remainder_frames.max(0) as u32;
+
+            codec_params.with_delay(delay).with_padding(padding);
+
+            if self.options.enable_gapless {
+                codec_params.with_n_frames(table.valid_frames as u64);
+            }
+            else {
+                let total_frames = table.valid_frames as u64 + u64::from(delay + padding);
+
+                // The number of frames of constant bit rate formats were already calculated from
+                // the length of the audio data.
+                let is_compressed = matches!(self.packet_info, PacketInfo::Compressed { .. });
+
+                if is_compressed || codec_params.n_frames.is_none() {
+                    codec_params.with_n_frames(total_frames);
+                }
+            }
+        }
+
+        if let Some(rev) = aux.metadata() {
+            self.metadata.push(rev);
+        }
+
+        self.cues = aux.cues();
+
         Ok(codec_params)
     }
 }
+
+/// Information, marker, region, instrument, and user-defined metadata.
+#[derive(Default)]
+struct CafMetadata {
+    tags: Vec<Tag>,
+    strings: Vec<(u32, String)>,
+    markers: Vec<Marker>,
+    regions: Vec<Region>,
+    inst: Option<Instrument>,
+    vendor_data: Vec<VendorData>,
+}
+
+impl CafMetadata {
+    /// Gets the information, instrument, and user-defined metadata as a metadata revision, if any
+    /// was read.
+    fn metadata(&self) -> Option<MetadataRevision> {
+        if self.tags.is_empty() && self.inst.is_none() && self.vendor_data.is_empty() {
+            return None;
+        }
+
+        let mut builder = MetadataBuilder::new();
+
+        let inst_tags = self.inst.iter().flat_map(|inst| inst.tags());
+
+        for tag in self.tags.iter().cloned().chain(inst_tags) {
+            builder.add_tag(tag);
+        }
+
+        for vendor_data in self.vendor_data.iter() {
+            builder.add_vendor_data(vendor_data.clone());
+        }
+
+        Some(builder.metadata())
+    }
+
+    /// Gets the name of a marker or region from the strings chunk.
+    fn label(&self, id: u32) -> Option<Tag> {
+        self.strings
+            .iter()
+            .find(|(string_id, _)| *string_id == id)
+            .map(|(_, string)| Tag::new(None, "Label", Value::from(string.clone())))
+    }
+
+    /// Gets the markers and regions as `Cue`s. Markers within a region are points of the region's
+    /// `Cue`.
+    fn cues(&self) -> Vec<Cue> {
+        let mut cues: Vec<Cue> = self
+            .markers
+            .iter()
+            .map(|marker| Cue {
+                index: marker.marker_id,
+                start_ts: marker.position(),
+                tags: self.marker_tags(marker),
+                points: Vec::new(),
+            })
+            .collect();
+
+        for region in self.regions.iter() {
+            let start_ts = match region.markers.iter().map(|marker| marker.position()).min() {
+                Some(start_ts) => start_ts,
+                None => continue,
+            };
+
+            let mut tags: Vec<Tag> = self.label(region.region_id).into_iter().collect();
+
+            if region.flags & REGION_FLAG_LOOP_ENABLE != 0 {
+                let forward = region.flags & REGION_FLAG_PLAY_FORWARD != 0;
+                let backward = region.flags & REGION_FLAG_PLAY_BACKWARD != 0;
+
+                let loop_type = match (forward, backward) {
+                    (true, true) => "Alternating",
+                    (false, true) => "Backward",
+                    _ => "Forward",
+                };
+
+                tags.push(Tag::new(None, "LoopType", Value::from(loop_type)));
+            }
+
+            if let Some(inst) = &self.inst {
+                if inst.sustain_region_id == region.region_id {
+                    tags.push(Tag::new(None, "Loop", Value::from("Sustain")));
+                }
+                else if inst.release_region_id == region.region_id {
+                    tags.push(Tag::new(None, "Loop", Value::from("Release")));
+                }
+            }
+
+            let end_ts = region.markers.iter().map(|marker| marker.position()).max();
+
+            let points = region
+                .markers
+                .iter()
+                .map(|marker| {
+                    let mut tags = self.marker_tags(marker);
+
+                    // The end of a looped region is the last marker of the region.
+                    if region.flags & REGION_FLAG_LOOP_ENABLE != 0
+                        && Some(marker.position()) == end_ts
+                    {
+                        tags.push(Tag::new(None, "LoopEnd", Value::Flag));
+                    }
+
+                    CuePoint { start_offset_ts: marker.position() - start_ts, tags }
+                })
+                .collect();
+
+            cues.push(Cue { index: region.region_id, start_ts, tags, points });
+        }
+
+        cues.sort_by_key(|cue| cue.start_ts);
+        cues
+    }
+
+    fn marker_tags(&self, marker: &Marker) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self.label(marker.marker_id).into_iter().collect();
+
+        // Generic markers have a type of 0.
+        if marker.marker_type != [0; 4] {
+            let marker_type = String::from_utf8_lossy(&marker.marker_type).into_owned();
+            tags.push(Tag::new(None, "MarkerType", Value::from(marker_type)));
+        }
+
+        tags
+    }
+}
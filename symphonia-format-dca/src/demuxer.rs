@@ -0,0 +1,508 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DCA};
+use symphonia_core::errors::{seek_error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, info, warn};
+
+use crate::header::{ext_substream_len, FrameHeader, Packing, EXT_SYNC_WORD, FRAME_HEADER_LEN};
+
+/// The maximum length of a core frame in bytes, in any packing.
+const MAX_FRAME_LEN: u64 = 18724;
+
+/// The number of bytes required to read the length of an extension substream.
+const EXT_HEADER_LEN: usize = 12;
+
+/// All packings, in order of preference.
+const PACKINGS: [Packing; 4] = [Packing::Be16, Packing::Le16, Packing::Be14, Packing::Le14];
+
+/// DTS Coherent Acoustics (DCA) elementary stream reader.
+///
+/// `DcaReader` implements a demuxer for raw DTS core frames in any of the 16-bit or 14-bit word
+/// packings. Each packet contains a core frame in the packing of the stream, and the extension
+/// substreams that follow it.
+pub struct DcaReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    packing: Packing,
+    first_packet_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for DcaReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "dca",
+            "DTS Coherent Acoustics",
+            &["dts", "cpt"],
+            &["audio/vnd.dts"],
+            &[
+                &[0x7f, 0xfe, 0x80, 0x01],
+                &[0xfe, 0x7f, 0x01, 0x80],
+                &[0x1f, 0xff, 0xe8, 0x00],
+                &[0xff, 0x1f, 0x00, 0xe8]
+            ]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for DcaReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // Read the first packet to get the stream parameters and packing.
+        let (header, packing, packet) = read_packet_strict(&mut source, None)?;
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_DCA)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_max_frames_per_packet(header.n_samples() as u64);
+
+        match header.channels() {
+            Some(channels) => {
+                params.with_channels(channels);
+            }
+            None => warn!("user-defined channel arrangement {}", header.amode),
+        }
+
+        debug!("packing={:?}, bit_rate={}kbps", packing, header.bit_rate);
+
+        // Rewind back to the start of the first packet so that it may be decoded.
+        source.seek_buffered_rev(packet.len());
+
+        let first_packet_pos = source.pos();
+
+        // The stream has no header, therefore estimate the duration if seekable.
+        if source.is_seekable() {
+            info!("estimating duration from bitrate, may be inaccurate for vbr streams");
+
+            if let Some(n_frames) = estimate_n_frames(&mut source, packing) {
+                params.with_n_frames(n_frames);
+            }
+        }
+
+        Ok(DcaReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            packing,
+            first_packet_pos,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let (header, _, packet) = read_packet(&mut self.reader, Some(self.packing))?;
+
+        let ts = self.next_packet_ts;
+        let dur = header.n_samples() as u64;
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, packet.into_boxed_slice()))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Get the timestamp of the desired audio frame.
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // Use the sample rate to calculate the frame timestamp. If sample rate is not
+                // known, the seek cannot be completed.
+                if let Some(sample_rate) = self.tracks[0].codec_params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        // If the stream is unseekable and the required timestamp in the past, then return an
+        // error, it is not possible to seek to it.
+        let is_seekable = self.reader.is_seekable();
+
+        if !is_seekable && required_ts < self.next_packet_ts {
+            return seek_error(SeekErrorKind::ForwardOnly);
+        }
+
+        debug!("seeking to ts={}", required_ts);
+
+        // In coarse seek mode, the underlying media source stream will be roughly seeked based on
+        // the required timestamp and the total duration of the media. In accurate seek mode, the
+        // stream is only seeked back to the start if the required timestamp is in the past.
+        match mode {
+            SeekMode::Coarse if is_seekable => self.preseek_coarse(required_ts)?,
+            SeekMode::Accurate => self.preseek_accurate(required_ts)?,
+            _ => (),
+        };
+
+        // Parse packets one-by-one until the packet containing the required timestamp is reached.
+        loop {
+            let (header, _, packet) = read_packet(&mut self.reader, Some(self.packing))?;
+
+            let dur = header.n_samples() as u64;
+
+            if self.next_packet_ts + dur > required_ts {
+                // Rewind back to the start of the packet.
+                self.reader.seek_buffered_rev(packet.len());
+                break;
+            }
+
+            self.next_packet_ts += dur;
+        }
+
+        debug!(
+            "seeked to ts={} (delta={})",
+            self.next_packet_ts,
+            self.next_packet_ts as i64 - required_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts: self.next_packet_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+impl DcaReader {
+    /// Seeks the media source stream to a byte position roughly where the packet with the required
+    /// timestamp should be located.
+    fn preseek_coarse(&mut self, required_ts: u64) -> Result<()> {
+        // Get the total byte length of the stream. It is not possible to seek without this.
+        let total_byte_len = match self.reader.byte_len() {
+            Some(byte_len) => byte_len,
+            None => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        // Get the total duration of the stream. It is not possible to seek without this.
+        let duration = match self.tracks[0].codec_params.n_frames {
+            Some(n_frames) if n_frames > 0 => n_frames,
+            _ => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        let audio_byte_len = total_byte_len - self.first_packet_pos;
+
+        // Calculate, roughly, where the packet containing the required timestamp is located, then
+        // subtract the maximum frame length to prefer a packet before the required timestamp.
+        let packet_pos =
+            ((u128::from(required_ts) * u128::from(audio_byte_len)) / u128::from(duration)) as u64;
+
+        let seek_pos = packet_pos.saturating_sub(MAX_FRAME_LEN) + self.first_packet_pos;
+
+        self.reader.seek(SeekFrom::Start(seek_pos))?;
+
+        // Resync to the start of the next packet.
+        let (header, _, _) = read_packet_strict(&mut self.reader, Some(self.packing))?;
+
+        // Calculate, roughly, the timestamp of the packet based on the byte position after resync,
+        // and round it to a multiple of the packet duration.
+        let seeked_pos = self.reader.pos();
+
+        let ts = ((u128::from(seeked_pos - self.first_packet_pos) * u128::from(duration))
+            / u128::from(audio_byte_len)) as u64;
+
+        let packet_dur = header.n_samples() as u64;
+
+        self.next_packet_ts = (ts / packet_dur) * packet_dur;
+
+        Ok(())
+    }
+
+    /// Seeks the media source stream back to the start of the first packet if the required
+    /// timestamp is in the past.
+    fn preseek_accurate(&mut self, required_ts: u64) -> Result<()> {
+        if required_ts < self.next_packet_ts {
+            let seeked_pos = self.reader.seek(SeekFrom::Start(self.first_packet_pos))?;
+
+            // Since the elementary stream has no timestamp information, the position seeked
+            // to must be exactly as requested.
+            if seeked_pos != self.first_packet_pos {
+                return seek_error(SeekErrorKind::Unseekable);
+            }
+
+            self.next_packet_ts = 0;
+        }
+
+        Ok(())
+    }
+}
+
+/// Synchronizes to, and reads, the next core frame. If the packing is not known, the core frame
+/// may be in any packing.
+fn read_frame(
+    reader: &mut MediaSourceStream,
+    packing: Option<Packing>,
+) -> Result<(FrameHeader, Packing, Vec<u8>)> {
+    loop {
+        let mut sync = 0u32;
+
+        loop {
+            sync = (sync << 8) | u32::from(reader.read_u8()?);
+
+            let is_sync = match packing {
+                Some(packing) => sync == packing.sync_word(),
+                None => PACKINGS.iter().any(|packing| sync == packing.sync_word()),
+            };
+
+            if is_sync {
+                break;
+            }
+        }
+
+        let mut buf = vec![0; FRAME_HEADER_LEN];
+
+        buf[..4].copy_from_slice(&sync.to_be_bytes());
+        reader.read_buf_exact(&mut buf[4..])?;
+
+        let header = match Packing::detect(&buf) {
+            Some(detected) if packing.map_or(true, |packing| packing == detected) => {
+                FrameHeader::parse(&detected.unpack(&buf)).ok().map(|header| (header, detected))
+            }
+            _ => None,
+        };
+
+        match header {
+            Some((header, packing)) => {
+                let frame_len = packing.packed_len(header.frame_len);
+
+                buf.resize(frame_len, 0);
+                reader.read_buf_exact(&mut buf[FRAME_HEADER_LEN..])?;
+
+                return Ok((header, packing, buf));
+            }
+            None => {
+                // Random data can look like a sync word. Rewind to the byte after the sync word to
+                // try again.
+                reader.seek_buffered_rev(FRAME_HEADER_LEN - 4);
+            }
+        }
+    }
+}
+
+/// Reads the next packet. A packet starts with a core frame, and contains all extension
+/// substreams up-to the next core frame.
+fn read_packet(
+    reader: &mut MediaSourceStream,
+    packing: Option<Packing>,
+) -> Result<(FrameHeader, Packing, Vec<u8>)> {
+    let (header, packing, mut packet) = read_frame(reader, packing)?;
+
+    // Extension substreams are only found in streams with the 16-bit big-endian packing.
+    if packing != Packing::Be16 {
+        return Ok((header, packing, packet));
+    }
+
+    loop {
+        let mut buf = [0; EXT_HEADER_LEN];
+
+        // If the header of the next substream can't be read, then the stream has likely ended.
+        if reader.read_buf_exact(&mut buf).is_err() {
+            break;
+        }
+
+        let is_ext_sync = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) == EXT_SYNC_WORD;
+
+        match ext_substream_len(&buf) {
+            Ok(len) if is_ext_sync && len >= EXT_HEADER_LEN => {
+                let start = packet.len();
+
+                packet.extend_from_slice(&buf);
+                packet.resize(start + len, 0);
+
+                reader.read_buf_exact(&mut packet[start + EXT_HEADER_LEN..])?;
+            }
+            _ => {
+                reader.seek_buffered_rev(EXT_HEADER_LEN);
+                break;
+            }
+        }
+    }
+
+    Ok((header, packing, packet))
+}
+
+/// Reads the next packet, and checks if the next core frame begins immediately after the packet.
+fn read_packet_strict(
+    reader: &mut MediaSourceStream,
+    packing: Option<Packing>,
+) -> Result<(FrameHeader, Packing, Vec<u8>)> {
+    loop {
+        let (header, packing, packet) = read_packet(reader, packing)?;
+
+        // Get the position before trying to read the next sync word.
+        let pos = reader.pos();
+
+        // If the sync word could not be read, the stream may have ended and this check cannot be
+        // performed.
+        if let Ok(sync) = reader.read_be_u32() {
+            if sync != packing.sync_word() {
+                warn!("skipping junk at {} bytes", pos - packet.len() as u64);
+
+                // Rewind to the second byte of the rejected packet to prevent syncing to the same
+                // spot again.
+                reader.seek_buffered_rev(packet.len() + 4 - 1);
+                continue;
+            }
+        }
+
+        reader.seek_buffered(pos);
+
+        break Ok((header, packing, packet));
+    }
+}
+
+/// Estimates the total number of frames (samples per channel) in the media source stream.
+fn estimate_n_frames(reader: &mut MediaSourceStream, packing: Packing) -> Option<u64> {
+    const MAX_PACKETS: u64 = 16;
+    const MAX_LEN: u64 = 16 * 1024;
+
+    let start_pos = reader.pos();
+
+    let total_len = match reader.byte_len() {
+        Some(len) => len - start_pos,
+        _ => return None,
+    };
+
+    let mut total_packet_len = 0;
+    let mut total_packets = 0;
+    let mut total_dur = 0;
+
+    let n_frames = loop {
+        let (header, _, packet) = match read_packet(reader, Some(packing)) {
+            Ok(packet) => packet,
+            // If the stream ended, then the duration is exact.
+            Err(_) if total_packets > 0 => break Some(total_dur),
+            _ => break None,
+        };
+
+        total_packet_len += packet.len() as u64;
+        total_packets += 1;
+        total_dur += header.n_samples() as u64;
+
+        // Read up-to 16 packets, or 16kB, then calculate the average packet length, and from that,
+        // the total number of packets.
+        if total_packets > MAX_PACKETS || total_packet_len > MAX_LEN {
+            let avg_packet_len = total_packet_len as f64 / total_packets as f64;
+            let avg_packet_dur = total_dur as f64 / total_packets as f64;
+
+            break Some(((total_len as f64 / avg_packet_len) * avg_packet_dur) as u64);
+        }
+    };
+
+    // Rewind back to the first packet. Packets with extension substreams may be too large to
+    // rewind within the buffer.
+    if reader.seek(SeekFrom::Start(start_pos)).is_err() {
+        return None;
+    }
+
+    n_frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::tests::core_frame;
+
+    fn new_reader(buf: Vec<u8>) -> DcaReader {
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        DcaReader::try_new(source, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_be16_stream() {
+        // An extension substream of 64 bytes.
+        let ext_bits = (u128::from(EXT_SYNC_WORD) << 35) | (15 << 16) | 63;
+        let mut ext = (ext_bits << 61).to_be_bytes().to_vec();
+        ext.resize(64, 0);
+
+        // Junk, then 3 core frames. The first core frame is followed by an extension substream.
+        let mut buf = vec![0x7f, 0xfe, 0x80, 0x01, 0x00, 0x00];
+        buf.extend_from_slice(&core_frame(Packing::Be16, 1024));
+        buf.extend_from_slice(&ext);
+        buf.extend_from_slice(&core_frame(Packing::Be16, 1024));
+        buf.extend_from_slice(&core_frame(Packing::Be16, 1024));
+
+        let mut reader = new_reader(buf);
+
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.codec, CODEC_TYPE_DCA);
+        assert_eq!(params.sample_rate, Some(48000));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(6));
+        assert_eq!(params.n_frames, Some(3 * 512));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur(), packet.buf().len()), (0, 512, 1024 + 64));
+        assert_eq!(&packet.buf()[1024..1028], &EXT_SYNC_WORD.to_be_bytes()[..]);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur(), packet.buf().len()), (512, 512, 1024));
+
+        // Seeking returns the packet containing the required timestamp.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1100, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 1024);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.buf().len()), (1024, 1024));
+
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_le14_stream() {
+        let frame = core_frame(Packing::Le14, 1024);
+
+        let mut buf = frame.clone();
+        buf.extend_from_slice(&frame);
+
+        let mut reader = new_reader(buf);
+
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(2 * 512));
+
+        // Packets are in the packing of the stream.
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.buf(), &frame[..]);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.buf().len()), (512, Packing::Le14.packed_len(1024)));
+
+        assert!(reader.next_packet().is_err());
+    }
+}
@@ -0,0 +1,402 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, ReadBitsLtr};
+
+/// The core sync word in the 16-bit big-endian packing.
+pub const SYNC_WORD: u32 = 0x7ffe_8001;
+
+/// The extension substream sync word in the 16-bit big-endian packing.
+pub const EXT_SYNC_WORD: u32 = 0x6458_2025;
+
+/// The number of bytes read from the start of a frame, in any packing, to parse the frame header.
+pub const FRAME_HEADER_LEN: usize = 16;
+
+/// The minimum length of a core frame in bytes.
+const MIN_FRAME_LEN: usize = 96;
+
+/// The sample rates indexed by the core sample rate code. A rate of 0 is invalid.
+const SAMPLE_RATES: [u32; 16] =
+    [0, 8000, 16000, 32000, 0, 0, 11025, 22050, 44100, 0, 0, 12000, 24000, 48000, 0, 0];
+
+/// The nominal bit rates in kbit/s indexed by the core bit rate code. A rate of 0 is open, or
+/// invalid.
+const BIT_RATES: [u32; 32] = [
+    32, 56, 64, 96, 112, 128, 192, 224, 256, 320, 384, 448, 512, 576, 640, 768, 960, 1024, 1152,
+    1280, 1344, 1408, 1411, 1472, 1536, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// The way the bitstream is packed into 16-bit words.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Packing {
+    /// 16 bits per big-endian word.
+    Be16,
+    /// 16 bits per little-endian word.
+    Le16,
+    /// 14 bits per big-endian word. Commonly found on CDs, and in WAV files.
+    Be14,
+    /// 14 bits per little-endian word. Commonly found on CDs, and in WAV files.
+    Le14,
+}
+
+impl Packing {
+    /// Detects the packing from the first 6 bytes of a core frame.
+    pub fn detect(buf: &[u8]) -> Option<Packing> {
+        if buf.len() < 6 {
+            return None;
+        }
+
+        // The 14-bit packings continue the sync word into the upper bits of the third word.
+        match u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) {
+            0x7ffe_8001 => Some(Packing::Be16),
+            0xfe7f_0180 => Some(Packing::Le16),
+            0x1fff_e800 if buf[4] == 0x07 && buf[5] & 0xf0 == 0xf0 => Some(Packing::Be14),
+            0xff1f_00e8 if buf[5] == 0x07 && buf[4] & 0xf0 == 0xf0 => Some(Packing::Le14),
+            _ => None,
+        }
+    }
+
+    /// Gets the first 4 bytes of the core sync word in this packing as a big-endian word.
+    pub fn sync_word(&self) -> u32 {
+        match self {
+            Packing::Be16 => 0x7ffe_8001,
+            Packing::Le16 => 0xfe7f_0180,
+            Packing::Be14 => 0x1fff_e800,
+            Packing::Le14 => 0xff1f_00e8,
+        }
+    }
+
+    /// Gets the number of bytes used to store `len` bytes of the bitstream in this packing.
+    pub fn packed_len(&self, len: usize) -> usize {
+        match self {
+            Packing::Be16 | Packing::Le16 => len,
+            Packing::Be14 | Packing::Le14 => 2 * ((8 * len) / 14),
+        }
+    }
+
+    /// Unpacks a buffer in this packing to the 16-bit big-endian packing. A trailing odd byte is
+    /// ignored.
+    pub fn unpack(&self, buf: &[u8]) -> Vec<u8> {
+        let words = buf.chunks_exact(2).map(|word| match self {
+            Packing::Be16 | Packing::Be14 => u16::from_be_bytes([word[0], word[1]]),
+            Packing::Le16 | Packing::Le14 => u16::from_le_bytes([word[0], word[1]]),
+        });
+
+        match self {
+            Packing::Be16 | Packing::Le16 => {
+                let mut out = Vec::with_capacity(buf.len());
+
+                for word in words {
+                    out.extend_from_slice(&word.to_be_bytes());
+                }
+
+                out
+            }
+            Packing::Be14 | Packing::Le14 => {
+                let mut out = Vec::with_capacity((buf.len() * 7) / 8);
+
+                let mut acc = 0u32;
+                let mut n_bits = 0;
+
+                for word in words {
+                    acc = (acc << 14) | u32::from(word & 0x3fff);
+                    n_bits += 14;
+
+                    while n_bits >= 8 {
+                        n_bits -= 8;
+                        out.push((acc >> n_bits) as u8);
+                    }
+
+                    acc &= (1 << n_bits) - 1;
+                }
+
+                out
+            }
+        }
+    }
+}
+
+/// The fixed fields at the start of a core frame.
+#[derive(Clone, Debug)]
+pub struct FrameHeader {
+    /// The length of the core frame in bytes in the 16-bit packing, including the sync word.
+    pub frame_len: usize,
+    /// The number of PCM sample blocks of 32 samples in the frame.
+    pub n_pcm_blocks: usize,
+    /// The audio channel arrangement.
+    pub amode: u8,
+    /// The sample rate.
+    pub sample_rate: u32,
+    /// The nominal bit rate in kbit/s, or 0 if open or unknown.
+    pub bit_rate: u32,
+    /// The low-frequency effects channel is present.
+    pub lfe: bool,
+}
+
+impl FrameHeader {
+    /// Parses the header from the start of a core frame in the 16-bit big-endian packing.
+    pub fn parse(buf: &[u8]) -> Result<FrameHeader> {
+        let mut bs = BitReaderLtr::new(buf);
+
+        if bs.read_bits_leq32(32)? != SYNC_WORD {
+            return decode_error("dca: missing sync word");
+        }
+
+        // Skip the frame type.
+        bs.ignore_bits(1)?;
+
+        // Termination frames with a short final block are not supported.
+        if bs.read_bits_leq32(5)? != 31 {
+            return unsupported_error("dca: unsupported deficit sample count");
+        }
+
+        // Skip the CRC present flag.
+        bs.ignore_bits(1)?;
+
+        let n_pcm_blocks = bs.read_bits_leq32(7)? as usize + 1;
+        let frame_len = bs.read_bits_leq32(14)? as usize + 1;
+        let amode = bs.read_bits_leq32(6)? as u8;
+        let sr_code = bs.read_bits_leq32(4)? as usize;
+        let br_code = bs.read_bits_leq32(5)? as usize;
+
+        if n_pcm_blocks < 6 {
+            return decode_error("dca: invalid number of pcm sample blocks");
+        }
+
+        if frame_len < MIN_FRAME_LEN {
+            return decode_error("dca: invalid frame size");
+        }
+
+        if SAMPLE_RATES[sr_code] == 0 {
+            return decode_error("dca: invalid sample rate code");
+        }
+
+        // The reserved bit must be 0.
+        if bs.read_bool()? {
+            return decode_error("dca: reserved bit is set");
+        }
+
+        // Skip the dynamic range, time stamp, auxiliary data, and HDCD flags.
+        bs.ignore_bits(4)?;
+
+        // Skip the extension audio descriptor, and extension audio present flag.
+        bs.ignore_bits(4)?;
+
+        // Skip the audio sync word insertion flag.
+        bs.ignore_bits(1)?;
+
+        let lfe = match bs.read_bits_leq32(2)? {
+            0 => false,
+            1 | 2 => true,
+            _ => return decode_error("dca: invalid lfe flag"),
+        };
+
+        Ok(FrameHeader {
+            frame_len,
+            n_pcm_blocks,
+            amode,
+            sample_rate: SAMPLE_RATES[sr_code],
+            bit_rate: BIT_RATES[br_code],
+            lfe,
+        })
+    }
+
+    /// Gets the number of samples per channel in the frame.
+    pub fn n_samples(&self) -> usize {
+        32 * self.n_pcm_blocks
+    }
+
+    /// Gets the channels, including the low-frequency effects channel, if the audio channel
+    /// arrangement is not user-defined.
+    pub fn channels(&self) -> Option<Channels> {
+        let channels = match self.amode {
+            0 => Channels::FRONT_CENTRE,
+            1..=4 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+            5 => Channels::FRONT_LEFT | Channels::FRONT_CENTRE | Channels::FRONT_RIGHT,
+            6 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::REAR_CENTRE,
+            7 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_CENTRE
+                    | Channels::FRONT_RIGHT
+                    | Channels::REAR_CENTRE
+            }
+            8 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            }
+            9 => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_CENTRE
+                    | Channels::FRONT_RIGHT
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            }
+            _ => return None,
+        };
+
+        if self.lfe {
+            Some(channels | Channels::LFE1)
+        }
+        else {
+            Some(channels)
+        }
+    }
+}
+
+/// Gets the length in bytes of an extension substream from the start of its header in the 16-bit
+/// big-endian packing.
+pub fn ext_substream_len(buf: &[u8]) -> Result<usize> {
+    let mut bs = BitReaderLtr::new(buf);
+
+    if bs.read_bits_leq32(32)? != EXT_SYNC_WORD {
+        return decode_error("dca: missing extension substream sync word");
+    }
+
+    // Skip the user-defined bits, and the substream index.
+    bs.ignore_bits(10)?;
+
+    // The header and frame sizes have wider fields in blown-up headers.
+    let extra_bits = if bs.read_bool()? { 4 } else { 0 };
+
+    let _header_len = bs.read_bits_leq32(8 + extra_bits)? + 1;
+    let len = bs.read_bits_leq32(16 + extra_bits)? as usize + 1;
+
+    Ok(len)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Builds a core frame of `frame_len` bytes in the 16-bit packing with 512 samples, 48 kHz
+    /// sample rate, and 5.1 channels, then stores it in the given packing. The audio data is
+    /// zeroed.
+    pub fn core_frame(packing: Packing, frame_len: usize) -> Vec<u8> {
+        // The header fields, and their lengths in bits, up-to and including the lfe flag.
+        let fields: [(u128, u32); 14] = [
+            (u128::from(SYNC_WORD), 32),
+            (1, 1),
+            (31, 5),
+            (0, 1),
+            (15, 7),
+            (frame_len as u128 - 1, 14),
+            (9, 6),
+            (13, 4),
+            (15, 5),
+            (0, 1),
+            (0, 4),
+            (0, 4),
+            (0, 1),
+            (1, 2),
+        ];
+
+        let (bits, n_bits) = fields.iter().fold((0u128, 0), |(bits, n), &(value, len)| {
+            ((bits << len) | value, n + len)
+        });
+
+        let mut frame = (bits << (128 - n_bits)).to_be_bytes().to_vec();
+        frame.resize(frame_len, 0);
+
+        // Get the 16-bit words of the frame. In the 14-bit packings, each word holds the next 14
+        // bits of the frame, sign-extended to 16 bits.
+        let words: Vec<u16> = match packing {
+            Packing::Be16 | Packing::Le16 => {
+                frame.chunks_exact(2).map(|word| u16::from_be_bytes([word[0], word[1]])).collect()
+            }
+            Packing::Be14 | Packing::Le14 => (0..packing.packed_len(frame_len) / 2)
+                .map(|i| {
+                    let word = (0..14).fold(0u16, |word, j| {
+                        let pos = 14 * i + j;
+                        let bit = frame.get(pos / 8).map_or(0, |byte| (byte >> (7 - pos % 8)) & 1);
+                        (word << 1) | u16::from(bit)
+                    });
+
+                    if word & 0x2000 != 0 {
+                        word | 0xc000
+                    }
+                    else {
+                        word
+                    }
+                })
+                .collect(),
+        };
+
+        words
+            .iter()
+            .flat_map(|word| match packing {
+                Packing::Be16 | Packing::Be14 => word.to_be_bytes(),
+                Packing::Le16 | Packing::Le14 => word.to_le_bytes(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify_packing_detect() {
+        let packings = [Packing::Be16, Packing::Le16, Packing::Be14, Packing::Le14];
+
+        for &packing in packings.iter() {
+            let frame = core_frame(packing, 96);
+
+            assert_eq!(Packing::detect(&frame), Some(packing));
+            assert_eq!(frame.len(), packing.packed_len(96));
+
+            let sync = u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]);
+            assert_eq!(sync, packing.sync_word());
+        }
+
+        // 16-bit PCM audio.
+        assert_eq!(Packing::detect(&[0; FRAME_HEADER_LEN]), None);
+    }
+
+    #[test]
+    fn verify_frame_header_parse() {
+        let five_one = Channels::FRONT_LEFT
+            | Channels::FRONT_CENTRE
+            | Channels::FRONT_RIGHT
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT
+            | Channels::LFE1;
+
+        let packings = [Packing::Be16, Packing::Le16, Packing::Be14, Packing::Le14];
+
+        // Every packing unpacks to the same header.
+        for &packing in packings.iter() {
+            let frame = packing.unpack(&core_frame(packing, 1024));
+            let header = FrameHeader::parse(&frame).unwrap();
+
+            assert_eq!(header.frame_len, 1024);
+            assert_eq!(header.n_samples(), 512);
+            assert_eq!(header.sample_rate, 48000);
+            assert_eq!(header.bit_rate, 768);
+            assert_eq!(header.channels(), Some(five_one));
+        }
+
+        // A frame shorter than the minimum frame length.
+        let mut frame = core_frame(Packing::Be16, 96);
+        frame[6] &= 0xf0;
+        frame[7] = 0;
+
+        assert!(FrameHeader::parse(&frame).is_err());
+
+        // A missing sync word.
+        assert!(FrameHeader::parse(&[0; FRAME_HEADER_LEN]).is_err());
+    }
+
+    #[test]
+    fn verify_ext_substream_len() {
+        // Sync word, user-defined bits, substream index 0, normal header, 16 byte header, and a
+        // 1000 byte substream.
+        let bits = (u128::from(EXT_SYNC_WORD) << 35) | (15 << 16) | 999;
+
+        assert_eq!(ext_substream_len(&(bits << 61).to_be_bytes()).unwrap(), 1000);
+        assert!(ext_substream_len(&[0; 8]).is_err());
+    }
+}
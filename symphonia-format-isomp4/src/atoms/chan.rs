@@ -0,0 +1,342 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader};
+
+use log::debug;
+
+/// Use the channel descriptions.
+const LAYOUT_TAG_USE_CHANNEL_DESCRIPTIONS: u32 = 0;
+/// Use the channel bitmap.
+const LAYOUT_TAG_USE_CHANNEL_BITMAP: u32 = 1 << 16;
+/// Discrete channels in order, the lower 16 bits are the number of channels.
+const LAYOUT_TAG_DISCRETE_IN_ORDER: u32 = 147 << 16;
+
+/// Channel layout atom.
+#[derive(Debug)]
+pub struct ChanAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The channel layout tag.
+    pub channel_layout: u32,
+    /// The channel bitmap.
+    pub channel_bitmap: u32,
+    /// The channel labels of each channel description.
+    pub channel_labels: Vec<u32>,
+}
+
+impl Atom for ChanAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let (version, _) = AtomHeader::read_extra(reader)?;
+
+        if version != 0 {
+            return unsupported_error("isomp4 (chan): unsupported chan version");
+        }
+
+        let channel_layout = reader.read_be_u32()?;
+        let channel_bitmap = reader.read_be_u32()?;
+        let num_descriptions = reader.read_be_u32()?;
+
+        // Each channel description is 20 bytes long.
+        let len = header.data_len.saturating_sub(AtomHeader::EXTRA_DATA_SIZE + 12);
+
+        if u64::from(num_descriptions) * 20 > len {
+            return decode_error("isomp4 (chan): invalid number of channel descriptions");
+        }
+
+        let mut channel_labels = Vec::with_capacity(num_descriptions as usize);
+
+        for _ in 0..num_descriptions {
+            channel_labels.push(reader.read_be_u32()?);
+            // Skip the channel flags and coordinates.
+            reader.ignore_bytes(16)?;
+        }
+
+        Ok(ChanAtom { header, channel_layout, channel_bitmap, channel_labels })
+    }
+}
+
+impl ChanAtom {
+    /// Get the channels described by the channel layout, if the layout is supported.
+    pub fn channels(&self) -> Option<Channels> {
+        let channels = match self.channel_layout {
+            LAYOUT_TAG_USE_CHANNEL_DESCRIPTIONS => {
+                let mut channels = Channels::empty();
+
+                for &label in self.channel_labels.iter() {
+                    let channel = match channel_label_to_channel(label) {
+                        Some(channel) => channel,
+                        None => {
+                            debug!("unsupported channel label: {}", label);
+                            return None;
+                        }
+                    };
+
+                    // The channels of a stream are always in the order of their bits. A bitmask
+                    // can't represent descriptions out of that order, or duplicate descriptions,
+                    // without changing the order or number of channels.
+                    if channel.bits() <= channels.bits() {
+                        debug!("unsupported channel description order");
+                        return None;
+                    }
+
+                    channels |= channel;
+                }
+
+                channels
+            }
+            LAYOUT_TAG_USE_CHANNEL_BITMAP => Channels::from_bits(self.channel_bitmap)?,
+            tag if tag & 0xffff_0000 == LAYOUT_TAG_DISCRETE_IN_ORDER => {
+                // Discrete channels have no speaker assignment. Use the first N channels.
+                let num_channels = tag & 0xffff;
+
+                if num_channels < 1 || num_channels > 32 {
+                    return None;
+                }
+
+                Channels::from_bits(!((!0 << 1) << (num_channels - 1)))?
+            }
+            tag => {
+                let channels = channel_layout_tag_to_channels(tag);
+
+                if channels.is_none() {
+                    debug!("unsupported channel layout: {:#x}", tag);
+                }
+
+                channels?
+            }
+        };
+
+        if channels.is_empty() {
+            return None;
+        }
+
+        Some(channels)
+    }
+}
+
+/// Get the channel for a CoreAudio channel label.
+fn channel_label_to_channel(label: u32) -> Option<Channels> {
+    let channel = match label {
+        1 => Channels::FRONT_LEFT,
+        2 => Channels::FRONT_RIGHT,
+        3 => Channels::FRONT_CENTRE,
+        4 => Channels::LFE1,
+        5 => Channels::REAR_LEFT,
+        6 => Channels::REAR_RIGHT,
+        7 => Channels::FRONT_LEFT_CENTRE,
+        8 => Channels::FRONT_RIGHT_CENTRE,
+        9 => Channels::REAR_CENTRE,
+        10 => Channels::SIDE_LEFT,
+        11 => Channels::SIDE_RIGHT,
+        12 => Channels::TOP_CENTRE,
+        13 => Channels::TOP_FRONT_LEFT,
+        14 => Channels::TOP_FRONT_CENTRE,
+        15 => Channels::TOP_FRONT_RIGHT,
+        16 => Channels::TOP_REAR_LEFT,
+        17 => Channels::TOP_REAR_CENTRE,
+        18 => Channels::TOP_REAR_RIGHT,
+        _ => return None,
+    };
+
+    Some(channel)
+}
+
+/// Get the channels for a predefined CoreAudio channel layout tag.
+fn channel_layout_tag_to_channels(tag: u32) -> Option<Channels> {
+    const L: Channels = Channels::FRONT_LEFT;
+    const R: Channels = Channels::FRONT_RIGHT;
+    const C: Channels = Channels::FRONT_CENTRE;
+    const LFE: Channels = Channels::LFE1;
+    const LS: Channels = Channels::SIDE_LEFT;
+    const RS: Channels = Channels::SIDE_RIGHT;
+    const LR: Channels = Channels::REAR_LEFT;
+    const RR: Channels = Channels::REAR_RIGHT;
+    const CS: Channels = Channels::REAR_CENTRE;
+    const LC: Channels = Channels::FRONT_LEFT_CENTRE;
+    const RC: Channels = Channels::FRONT_RIGHT_CENTRE;
+
+    let channels = match tag >> 16 {
+        // Mono
+        100 => L,
+        // Stereo, StereoHeadphones, MatrixStereo, MidSide, XY, Binaural
+        101..=106 => L | R,
+        // Quadraphonic
+        108 => L | R | LR | RR,
+        // Pentagonal
+        109 => L | R | LR | RR | C,
+        // Hexagonal
+        110 => L | R | LR | RR | C | CS,
+        // MPEG 3.0 A & B
+        113 | 114 => L | R | C,
+        // MPEG 4.0 A & B
+        115 | 116 => L | R | C | CS,
+        // MPEG 5.0 A, B, C & D
+        117..=120 => L | R | C | LS | RS,
+        // MPEG 5.1 A, B, C & D
+        121..=124 => L | R | C | LFE | LS | RS,
+        // MPEG 6.1 A
+        125 => L | R | C | LFE | LS | RS | CS,
+        // MPEG 7.1 A & B
+        126 | 127 => L | R | C | LFE | LS | RS | LC | RC,
+        // MPEG 7.1 C
+        128 => L | R | C | LFE | LS | RS | LR | RR,
+        // ITU 2.1
+        131 => L | R | CS,
+        // ITU 2.2
+        132 => L | R | LS | RS,
+        // DVD 4
+        133 => L | R | LFE,
+        // DVD 5
+        134 => L | R | LFE | CS,
+        // DVD 6
+        135 => L | R | LFE | LS | RS,
+        // DVD 10
+        136 => L | R | C | LFE,
+        // DVD 11
+        137 => L | R | C | LFE | CS,
+        // DVD 18
+        138 => L | R | LS | RS | LFE,
+        // AAC 6.0
+        141 => L | R | C | LS | RS | CS,
+        // AAC 6.1
+        142 => L | R | C | LS | RS | CS | LFE,
+        // AAC 7.0
+        143 => L | R | C | LS | RS | LR | RR,
+        _ => return None,
+    };
+
+    // The lower 16 bits of the tag are the number of channels.
+    if channels.count() != (tag & 0xffff) as usize {
+        return None;
+    }
+
+    Some(channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::Channels;
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::*;
+
+    /// Reads a version 0 chan atom with the provided layout tag, bitmap, and channel labels.
+    fn read_chan(channel_layout: u32, channel_bitmap: u32, labels: &[u32]) -> Result<ChanAtom> {
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&channel_layout.to_be_bytes());
+        data.extend_from_slice(&channel_bitmap.to_be_bytes());
+        data.extend_from_slice(&(labels.len() as u32).to_be_bytes());
+
+        for label in labels {
+            data.extend_from_slice(&label.to_be_bytes());
+            data.extend_from_slice(&[0; 16]);
+        }
+
+        let mut buf = (8 + data.len() as u32).to_be_bytes().to_vec();
+        buf.extend_from_slice(b"chan");
+        buf.extend_from_slice(&data);
+
+        let mut source = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+
+        let header = AtomHeader::read(&mut source)?;
+        ChanAtom::read(&mut source, header)
+    }
+
+    #[test]
+    fn verify_layout_tags() -> Result<()> {
+        // MPEG 5.1 D
+        let chan = read_chan((124 << 16) | 6, 0, &[])?;
+
+        assert_eq!(
+            chan.channels(),
+            Some(
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::SIDE_LEFT
+                    | Channels::SIDE_RIGHT
+            )
+        );
+
+        // The number of channels must match the layout.
+        assert_eq!(read_chan((124 << 16) | 5, 0, &[])?.channels(), None);
+
+        // 10 discrete channels are assigned to the first 10 channels.
+        let chan = read_chan((147 << 16) | 10, 0, &[])?;
+
+        assert_eq!(chan.channels().map(|channels| channels.bits()), Some(0x3ff));
+
+        // Unknown layout tags are not supported.
+        assert_eq!(read_chan((200 << 16) | 2, 0, &[])?.channels(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_channel_bitmap() -> Result<()> {
+        let chan = read_chan(1 << 16, 0x7, &[])?;
+
+        assert_eq!(
+            chan.channels(),
+            Some(Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE)
+        );
+
+        // An empty bitmap has no channels.
+        assert_eq!(read_chan(1 << 16, 0, &[])?.channels(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_channel_descriptions() -> Result<()> {
+        let chan = read_chan(0, 0, &[1, 2, 3, 9])?;
+
+        assert_eq!(chan.channel_labels, &[1, 2, 3, 9]);
+        assert_eq!(
+            chan.channels(),
+            Some(
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::REAR_CENTRE
+            )
+        );
+
+        // Unknown channel labels are not supported.
+        assert_eq!(read_chan(0, 0, &[1, 100])?.channels(), None);
+
+        // Descriptions that are out of order, or duplicated, can't be represented by a bitmask
+        // without reordering or merging channels.
+        assert_eq!(read_chan(0, 0, &[3, 1, 2, 9])?.channels(), None);
+        assert_eq!(read_chan(0, 0, &[1, 2, 2])?.channels(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_invalid_number_of_descriptions() {
+        let mut buf = 24u32.to_be_bytes().to_vec();
+        buf.extend_from_slice(b"chan");
+        buf.extend_from_slice(&[0; 12]);
+        // One channel description, but no space for it.
+        buf.extend_from_slice(&1u32.to_be_bytes());
+
+        let mut source = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+
+        let header = AtomHeader::read(&mut source).unwrap();
+        assert!(ChanAtom::read(&mut source, header).is_err());
+    }
+}
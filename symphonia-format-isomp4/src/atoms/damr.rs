@@ -0,0 +1,83 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_AMRNB, CODEC_TYPE_AMRWB};
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader};
+
+/// The number of samples in an AMR-NB speech frame.
+const AMRNB_FRAME_LEN: u64 = 160;
+
+/// The number of samples in an AMR-WB speech frame.
+const AMRWB_FRAME_LEN: u64 = 320;
+
+/// AMR specific atom (3GPP TS 26.244).
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct DamrAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The four character code of the encoder vendor.
+    vendor: [u8; 4],
+    /// The version of the encoder.
+    decoder_version: u8,
+    /// A bitmask of the codec modes that may be used in the stream.
+    mode_set: u16,
+    /// The number of frames between codec mode changes, or 0 if not restricted.
+    mode_change_period: u8,
+    /// The number of speech frames in each sample.
+    frames_per_sample: u8,
+}
+
+impl Atom for DamrAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        // The damr atom is not a full atom, therefore it has no version or flags.
+        if header.data_len < 9 {
+            return decode_error("isomp4 (amr): damr atom too short");
+        }
+
+        let vendor = reader.read_quad_bytes()?;
+        let decoder_version = reader.read_u8()?;
+        let mode_set = reader.read_be_u16()?;
+        let mode_change_period = reader.read_u8()?;
+        let frames_per_sample = reader.read_u8()?;
+
+        Ok(DamrAtom {
+            header,
+            vendor,
+            decoder_version,
+            mode_set,
+            mode_change_period,
+            frames_per_sample,
+        })
+    }
+}
+
+impl DamrAtom {
+    pub fn fill_codec_params(&self, codec_params: &mut CodecParameters, is_wideband: bool) {
+        let (codec, frame_len) = if is_wideband {
+            (CODEC_TYPE_AMRWB, AMRWB_FRAME_LEN)
+        }
+        else {
+            (CODEC_TYPE_AMRNB, AMRNB_FRAME_LEN)
+        };
+
+        // AMR is always mono, regardless of the channel count in the sample entry.
+        codec_params.for_codec(codec).with_channels(Channels::FRONT_LEFT);
+
+        if self.frames_per_sample > 0 {
+            codec_params.with_max_frames_per_packet(u64::from(self.frames_per_sample) * frame_len);
+        }
+    }
+}
@@ -9,8 +9,10 @@ use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 
 pub(crate) mod alac;
+pub(crate) mod chan;
 pub(crate) mod co64;
 pub(crate) mod ctts;
+pub(crate) mod damr;
 pub(crate) mod edts;
 pub(crate) mod elst;
 pub(crate) mod esds;
@@ -49,9 +51,11 @@ pub(crate) mod wave;
 
 pub use self::meta::MetaAtom;
 pub use alac::AlacAtom;
+pub use chan::ChanAtom;
 pub use co64::Co64Atom;
 #[allow(unused_imports)]
 pub use ctts::CttsAtom;
+pub use damr::DamrAtom;
 pub use edts::EdtsAtom;
 pub use elst::ElstAtom;
 pub use esds::EsdsAtom;
@@ -97,9 +101,13 @@ pub enum AtomType {
     ALaw,
     AlbumArtistTag,
     AlbumTag,
+    AmrDsConfig,
+    AmrNb,
+    AmrWb,
     ArtistLowerTag,
     ArtistTag,
     CategoryTag,
+    ChannelLayout,
     ChunkOffset,
     ChunkOffset64,
     CommentTag,
@@ -112,6 +120,7 @@ pub enum AtomType {
     DateTag,
     DescriptionTag,
     DiskNumberTag,
+    Eac3,
     Edit,
     EditList,
     EncodedByTag,
@@ -205,11 +214,14 @@ impl From<[u8; 4]> for AtomType {
             b"ac-3" => AtomType::Ac3,
             b"alac" => AtomType::Alac,
             b"alaw" => AtomType::ALaw,
+            b"chan" => AtomType::ChannelLayout,
             b"co64" => AtomType::ChunkOffset64,
             b"ctts" => AtomType::CompositionTimeToSample,
             b"data" => AtomType::MetaTagData,
+            b"damr" => AtomType::AmrDsConfig,
             b"dfLa" => AtomType::FlacDsConfig,
             b"dOps" => AtomType::OpusDsConfig,
+            b"ec-3" => AtomType::Eac3,
             b"edts" => AtomType::Edit,
             b"elst" => AtomType::EditList,
             b"esds" => AtomType::Esds,
@@ -239,6 +251,8 @@ impl From<[u8; 4]> for AtomType {
             b"name" => AtomType::MetaTagName,
             b"Opus" => AtomType::Opus,
             b"raw " => AtomType::U8SampleEntry,
+            b"samr" => AtomType::AmrNb,
+            b"sawb" => AtomType::AmrWb,
             b"sidx" => AtomType::SegmentIndex,
             b"skip" => AtomType::Skip,
             b"smhd" => AtomType::SoundMediaHeader,
@@ -7,6 +7,7 @@
 
 use symphonia_core::audio::Channels;
 use symphonia_core::codecs::{CodecParameters, CodecType, CODEC_TYPE_MP3, CODEC_TYPE_NULL};
+use symphonia_core::codecs::{CODEC_TYPE_AC3, CODEC_TYPE_EAC3};
 use symphonia_core::codecs::{CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F32LE};
 use symphonia_core::codecs::{CODEC_TYPE_PCM_F64BE, CODEC_TYPE_PCM_F64LE};
 use symphonia_core::codecs::{CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE};
@@ -19,7 +20,11 @@ use symphonia_core::codecs::{CODEC_TYPE_PCM_U32BE, CODEC_TYPE_PCM_U32LE};
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::io::ReadBytes;
 
-use crate::atoms::{AlacAtom, Atom, AtomHeader, AtomType, EsdsAtom, FlacAtom, OpusAtom, WaveAtom};
+use log::info;
+
+use crate::atoms::OpusAtom;
+use crate::atoms::WaveAtom;
+use crate::atoms::{AlacAtom, Atom, AtomHeader, AtomType, ChanAtom, DamrAtom, EsdsAtom, FlacAtom};
 use crate::fp::FpU16;
 
 use super::AtomIterator;
@@ -59,6 +64,10 @@ impl Atom for StsdAtom {
             | AtomType::Flac
             | AtomType::Opus
             | AtomType::Mp3
+            | AtomType::Ac3
+            | AtomType::Eac3
+            | AtomType::AmrNb
+            | AtomType::AmrWb
             | AtomType::Lpcm
             | AtomType::QtWave
             | AtomType::ALaw
@@ -105,6 +114,18 @@ impl StsdAtom {
                 Some(AudioCodecSpecific::Mp3) => {
                     codec_params.for_codec(CODEC_TYPE_MP3);
                 }
+                Some(AudioCodecSpecific::Ac3) => {
+                    codec_params.for_codec(CODEC_TYPE_AC3);
+                }
+                Some(AudioCodecSpecific::Eac3) => {
+                    codec_params.for_codec(CODEC_TYPE_EAC3);
+                }
+                Some(AudioCodecSpecific::AmrNb(ref damr)) => {
+                    damr.fill_codec_params(codec_params, false);
+                }
+                Some(AudioCodecSpecific::AmrWb(ref damr)) => {
+                    damr.fill_codec_params(codec_params, true);
+                }
                 Some(AudioCodecSpecific::Pcm(ref pcm)) => {
                     // PCM codecs.
                     codec_params
@@ -116,6 +137,19 @@ impl StsdAtom {
                 }
                 _ => (),
             }
+
+            // The channel layout, if provided, supersedes any codec-derived channels, but only if
+            // it does not change the number of channels. For example, AMR is always mono.
+            if let Some(channels) = entry.channels {
+                match codec_params.channels {
+                    Some(codec_channels) if codec_channels.count() != channels.count() => {
+                        info!("ignoring channel layout that mismatches the codec");
+                    }
+                    _ => {
+                        codec_params.with_channels(channels);
+                    }
+                }
+            }
         }
     }
 }
@@ -141,6 +175,14 @@ pub enum AudioCodecSpecific {
     Opus(OpusAtom),
     /// MP3.
     Mp3,
+    /// AC-3, Dolby Digital.
+    Ac3,
+    /// Enhanced AC-3 (E-AC-3), Dolby Digital Plus.
+    Eac3,
+    /// Adaptive Multi-Rate Narrowband (AMR-NB).
+    AmrNb(DamrAtom),
+    /// Adaptive Multi-Rate Wideband (AMR-WB).
+    AmrWb(DamrAtom),
     /// PCM codecs.
     Pcm(Pcm),
 }
@@ -151,6 +193,7 @@ pub struct AudioSampleEntry {
     pub num_channels: u32,
     pub sample_size: u16,
     pub sample_rate: f64,
+    pub channels: Option<Channels>,
     pub codec_specific: Option<AudioCodecSpecific>,
 }
 
@@ -469,6 +512,8 @@ fn read_audio_sample_entry<B: ReadBytes>(
 
     let mut iter = AtomIterator::new(reader, header);
 
+    let mut channels = None;
+
     while let Some(entry_header) = iter.next()? {
         match entry_header.atype {
             AtomType::Esds => {
@@ -503,6 +548,20 @@ fn read_audio_sample_entry<B: ReadBytes>(
 
                 codec_specific = Some(AudioCodecSpecific::Opus(iter.read_atom::<OpusAtom>()?));
             }
+            AtomType::AmrDsConfig => {
+                // AMR-NB and AMR-WB codec-specific atom.
+                if codec_specific.is_some() {
+                    return decode_error("isomp4: invalid sample entry");
+                }
+
+                let damr = iter.read_atom::<DamrAtom>()?;
+
+                codec_specific = match header.atype {
+                    AtomType::AmrNb => Some(AudioCodecSpecific::AmrNb(damr)),
+                    AtomType::AmrWb => Some(AudioCodecSpecific::AmrWb(damr)),
+                    _ => return decode_error("isomp4: invalid sample entry"),
+                };
+            }
             AtomType::QtWave => {
                 // The QuickTime WAVE (aka. siDecompressionParam) atom may contain many different
                 // types of sub-atoms to store decoder parameters.
@@ -516,6 +575,18 @@ fn read_audio_sample_entry<B: ReadBytes>(
                     codec_specific = Some(AudioCodecSpecific::Esds(esds));
                 }
             }
+            AtomType::ChannelLayout => {
+                // The channel layout atom assigns roles to each channel.
+                let chan = iter.read_atom::<ChanAtom>()?;
+
+                channels = match chan.channels() {
+                    Some(channels) if channels.count() == num_channels as usize => Some(channels),
+                    _ => {
+                        info!("ignoring unsupported or mismatched channel layout");
+                        None
+                    }
+                };
+            }
             _ => (),
         }
     }
@@ -529,10 +600,147 @@ fn read_audio_sample_entry<B: ReadBytes>(
         codec_specific = Some(AudioCodecSpecific::Mp3);
     }
 
+    // The AC-3 and E-AC-3 specific atoms (dac3 and dec3) duplicate information signalled in every
+    // frame, and are therefore ignored.
+    if header.atype == AtomType::Ac3 || header.atype == AtomType::Eac3 {
+        if codec_specific.is_some() {
+            return decode_error("isomp4: invalid sample entry");
+        }
+
+        codec_specific = Some(if header.atype == AtomType::Ac3 {
+            AudioCodecSpecific::Ac3
+        }
+        else {
+            AudioCodecSpecific::Eac3
+        });
+    }
+
     Ok(SampleEntry::Audio(AudioSampleEntry {
         num_channels,
         sample_size,
         sample_rate,
+        channels,
         codec_specific,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{CODEC_TYPE_AMRNB, CODEC_TYPE_AMRWB};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::*;
+
+    /// Appends an atom with the provided type and data.
+    fn push_atom(buf: &mut Vec<u8>, atype: &[u8; 4], data: &[u8]) {
+        buf.extend_from_slice(&(8 + data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(atype);
+        buf.extend_from_slice(data);
+    }
+
+    /// Reads a stsd atom with one version 0 audio sample entry of the provided type, channel
+    /// count, and sample rate, followed by the provided nested atoms.
+    fn read_stsd(atype: &[u8; 4], num_channels: u16, sample_rate: u16, atoms: &[u8]) -> StsdAtom {
+        let mut entry = vec![0; 6];
+        entry.extend_from_slice(&1u16.to_be_bytes());
+        entry.extend_from_slice(&[0; 8]);
+        entry.extend_from_slice(&num_channels.to_be_bytes());
+        entry.extend_from_slice(&16u16.to_be_bytes());
+        entry.extend_from_slice(&[0; 4]);
+        entry.extend_from_slice(&sample_rate.to_be_bytes());
+        entry.extend_from_slice(&[0; 2]);
+        entry.extend_from_slice(atoms);
+
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&1u32.to_be_bytes());
+        push_atom(&mut data, atype, &entry);
+
+        let mut buf = Vec::new();
+        push_atom(&mut buf, b"stsd", &data);
+
+        let mut source = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+
+        let header = AtomHeader::read(&mut source).unwrap();
+        StsdAtom::read(&mut source, header).unwrap()
+    }
+
+    /// Gets a damr atom with the provided number of speech frames per sample.
+    fn damr(frames_per_sample: u8) -> Vec<u8> {
+        // Vendor, decoder version, mode set, mode change period, and frames per sample.
+        let data = [b'S', b'M', b'P', b'L', 0, 0x81, 0xff, 0, frames_per_sample];
+
+        let mut buf = Vec::new();
+        push_atom(&mut buf, b"damr", &data);
+        buf
+    }
+
+    #[test]
+    fn verify_amr_sample_entries() {
+        let mut params = CodecParameters::new();
+        read_stsd(b"samr", 2, 8_000, &damr(10)).fill_codec_params(&mut params);
+
+        // AMR is always mono, regardless of the channel count in the sample entry.
+        assert_eq!(params.codec, CODEC_TYPE_AMRNB);
+        assert_eq!(params.sample_rate, Some(8_000));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT));
+        assert_eq!(params.max_frames_per_packet, Some(10 * 160));
+
+        let mut params = CodecParameters::new();
+        read_stsd(b"sawb", 1, 16_000, &damr(1)).fill_codec_params(&mut params);
+
+        assert_eq!(params.codec, CODEC_TYPE_AMRWB);
+        assert_eq!(params.sample_rate, Some(16_000));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT));
+        assert_eq!(params.max_frames_per_packet, Some(320));
+    }
+
+    /// Gets a chan atom that uses the channel bitmap.
+    fn chan(channel_bitmap: u32) -> Vec<u8> {
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&(1u32 << 16).to_be_bytes());
+        data.extend_from_slice(&channel_bitmap.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut buf = Vec::new();
+        push_atom(&mut buf, b"chan", &data);
+        buf
+    }
+
+    #[test]
+    fn verify_channel_layout() {
+        // Front-left, front-right, and rear-centre.
+        let layout = Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::REAR_CENTRE;
+
+        // The layout is applied if it matches the channel count of the sample entry.
+        let mut params = CodecParameters::new();
+        read_stsd(b"ac-3", 3, 48_000, &chan(layout.bits())).fill_codec_params(&mut params);
+
+        assert_eq!(params.channels, Some(layout));
+
+        // Otherwise, it is ignored.
+        let mut params = CodecParameters::new();
+        read_stsd(b"ac-3", 2, 48_000, &chan(layout.bits())).fill_codec_params(&mut params);
+
+        assert_eq!(params.channels, None);
+
+        // A layout may not change the number of channels of the codec.
+        let mut atoms = damr(1);
+        atoms.extend_from_slice(&chan((Channels::FRONT_LEFT | Channels::FRONT_RIGHT).bits()));
+
+        let mut params = CodecParameters::new();
+        read_stsd(b"samr", 2, 8_000, &atoms).fill_codec_params(&mut params);
+
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT));
+
+        // A mono layout may still assign a role to the single channel of AMR.
+        let mut atoms = damr(1);
+        atoms.extend_from_slice(&chan(Channels::FRONT_CENTRE.bits()));
+
+        let mut params = CodecParameters::new();
+        read_stsd(b"samr", 1, 8_000, &atoms).fill_codec_params(&mut params);
+
+        assert_eq!(params.channels, Some(Channels::FRONT_CENTRE));
+    }
+}
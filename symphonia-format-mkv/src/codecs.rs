@@ -20,6 +20,10 @@ pub(crate) fn codec_id_to_type(track: &TrackElement) -> Option<CodecType> {
         "A_FLAC" => Some(codecs::CODEC_TYPE_FLAC),
         "A_OPUS" => Some(codecs::CODEC_TYPE_OPUS),
         "A_VORBIS" => Some(codecs::CODEC_TYPE_VORBIS),
+        "A_TTA1" => Some(codecs::CODEC_TYPE_TTA),
+        "A_AC3" => Some(codecs::CODEC_TYPE_AC3),
+        "A_EAC3" => Some(codecs::CODEC_TYPE_EAC3),
+        "A_DTS" => Some(codecs::CODEC_TYPE_DCA),
         "A_AAC/MPEG2/MAIN" | "A_AAC/MPEG2/LC" | "A_AAC/MPEG2/LC/SBR" | "A_AAC/MPEG2/SSR"
         | "A_AAC/MPEG4/MAIN" | "A_AAC/MPEG4/LC" | "A_AAC/MPEG4/LC/SBR" | "A_AAC/MPEG4/SSR"
         | "A_AAC/MPEG4/LTP" | "A_AAC" => Some(codecs::CODEC_TYPE_AAC),
@@ -40,9 +44,35 @@ pub(crate) fn codec_id_to_type(track: &TrackElement) -> Option<CodecType> {
             64 => Some(codecs::CODEC_TYPE_PCM_F64LE),
             _ => None,
         },
+        "A_MS/ACM" => match acm_format_tag(track)? {
+            0x0045 | 0x0064 => Some(codecs::CODEC_TYPE_ADPCM_G726),
+            0x028f => Some(codecs::CODEC_TYPE_ADPCM_G722),
+            format_tag => {
+                log::info!("unknown acm format tag: {:#06x}", format_tag);
+                None
+            }
+        },
         _ => {
             log::info!("unknown codec: {}", &track.codec_id);
             None
         }
     }
 }
+
+/// Gets the format tag of the `WAVEFORMATEX` structure stored in the codec private data of an
+/// "A_MS/ACM" track.
+fn acm_format_tag(track: &TrackElement) -> Option<u16> {
+    let buf = track.codec_private.as_deref()?.get(0..2)?;
+    Some(u16::from_le_bytes([buf[0], buf[1]]))
+}
+
+/// Gets the bits per sample of the `WAVEFORMATEX` structure stored in the codec private data of an
+/// "A_MS/ACM" track.
+pub(crate) fn acm_bits_per_sample(track: &TrackElement) -> Option<u16> {
+    if track.codec_id != "A_MS/ACM" {
+        return None;
+    }
+
+    let buf = track.codec_private.as_deref()?.get(14..16)?;
+    Some(u16::from_le_bytes([buf[0], buf[1]]))
+}
@@ -26,7 +26,7 @@ use symphonia_core::support_format;
 use symphonia_core::units::TimeBase;
 use symphonia_utils_xiph::flac::metadata::{MetadataBlockHeader, MetadataBlockType};
 
-use crate::codecs::codec_id_to_type;
+use crate::codecs::{acm_bits_per_sample, codec_id_to_type};
 use crate::ebml::{EbmlElement, ElementHeader, ElementIterator};
 use crate::element_ids::{ElementType, ELEMENTS};
 use crate::lacing::{extract_frames, read_xiph_sizes, Frame};
@@ -439,6 +439,7 @@ impl FormatReader for MkvReader {
         let mut states = HashMap::new();
         for track in segment_tracks.tracks.into_vec() {
             let codec_type = codec_id_to_type(&track);
+            let acm_bits_per_sample = acm_bits_per_sample(&track);
 
             let mut codec_params = CodecParameters::new();
             codec_params.with_time_base(time_base);
@@ -485,6 +486,11 @@ impl FormatReader for MkvReader {
                     codec_params.with_channel_layout(layout);
                 }
 
+                // Microsoft ACM codecs encode their sample size in the codec private data.
+                if let Some(bits) = acm_bits_per_sample {
+                    codec_params.with_bits_per_coded_sample(u32::from(bits));
+                }
+
                 if let Some(codec_type) = codec_type {
                     codec_params.for_codec(codec_type);
                     if let Some(codec_private) = track.codec_private {
@@ -0,0 +1,124 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::codecs::CodecParameters;
+use symphonia_core::errors::{seek_error, Result, SeekErrorKind};
+use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision, StandardTagKey, Tag, Value};
+
+/// The number of samples per channel in a Musepack frame.
+pub const MPC_FRAME_LEN: u32 = 1152;
+
+/// The sample rates indexed by the sample frequency field of the stream header.
+pub const MPC_SAMPLE_RATES: [u32; 4] = [44100, 48000, 37800, 32000];
+
+/// ReplayGain values read from the stream header.
+#[derive(Debug, Default)]
+pub struct ReplayGain {
+    /// The track gain in dB.
+    pub track_gain: Option<f32>,
+    /// The track peak, relative to full scale.
+    pub track_peak: Option<f32>,
+    /// The album gain in dB.
+    pub album_gain: Option<f32>,
+    /// The album peak, relative to full scale.
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Gets a metadata revision containing the ReplayGain tags, if any values are set.
+    pub fn metadata(&self) -> Option<MetadataRevision> {
+        let tags = [
+            (self.track_gain, StandardTagKey::ReplayGainTrackGain, "REPLAYGAIN_TRACK_GAIN", true),
+            (self.track_peak, StandardTagKey::ReplayGainTrackPeak, "REPLAYGAIN_TRACK_PEAK", false),
+            (self.album_gain, StandardTagKey::ReplayGainAlbumGain, "REPLAYGAIN_ALBUM_GAIN", true),
+            (self.album_peak, StandardTagKey::ReplayGainAlbumPeak, "REPLAYGAIN_ALBUM_PEAK", false),
+        ];
+
+        let mut builder = MetadataBuilder::new();
+        let mut has_tags = false;
+
+        // Format the values the same way as ReplayGain tags in other tagging formats.
+        for &(value, std_key, key, is_gain) in tags.iter() {
+            if let Some(value) = value {
+                let value =
+                    if is_gain { format!("{:.2} dB", value) } else { format!("{:.6}", value) };
+
+                builder.add_tag(Tag::new(Some(std_key), key, Value::from(value)));
+                has_tags = true;
+            }
+        }
+
+        if has_tags {
+            Some(builder.metadata())
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Information about a stream read from its header.
+pub struct StreamInfo {
+    pub codec_params: CodecParameters,
+    pub replay_gain: ReplayGain,
+}
+
+/// Moves the reader to the absolute position `pos`. The buffer is used if possible, otherwise the
+/// reader seeks or, if unseekable, skips forward.
+pub fn reposition(reader: &mut MediaSourceStream, pos: u64) -> Result<()> {
+    if reader.seek_buffered(pos) == pos {
+        return Ok(());
+    }
+
+    let current_pos = reader.pos();
+
+    if pos > current_pos {
+        reader.ignore_bytes(pos - current_pos)?;
+    }
+    else if reader.is_seekable() {
+        reader.seek(SeekFrom::Start(pos))?;
+    }
+    else {
+        return seek_error(SeekErrorKind::ForwardOnly);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    /// A bit writer producing a bitstream read from the most-significant bit of each byte.
+    #[derive(Default)]
+    pub struct BitWriter {
+        buf: Vec<u8>,
+        n_bits: usize,
+    }
+
+    impl BitWriter {
+        /// Writes the `n` least-significant bits of `value`, most-significant first.
+        pub fn put(&mut self, value: u64, n: u32) {
+            for i in (0..n).rev() {
+                if self.n_bits % 8 == 0 {
+                    self.buf.push(0);
+                }
+
+                let bit = ((value >> i) & 1) as u8;
+                *self.buf.last_mut().unwrap() |= bit << (7 - self.n_bits % 8);
+
+                self.n_bits += 1;
+            }
+        }
+
+        /// Gets the written bits, padded with zeros to a whole number of bytes.
+        pub fn into_bytes(self) -> Vec<u8> {
+            self.buf
+        }
+    }
+}
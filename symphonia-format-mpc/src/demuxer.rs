@@ -0,0 +1,188 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::errors::{seek_error, unsupported_error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::{apev2, id3v1};
+
+use log::{debug, warn};
+
+use crate::sv7::{Sv7Stream, SV7_STREAM_MARKER};
+use crate::sv8::{Sv8Stream, SV8_STREAM_MARKER};
+
+/// The length of an ID3v1 tag.
+const ID3V1_TAG_LEN: u64 = 128;
+
+/// The stream version specific state.
+enum MpcStream {
+    Sv7(Sv7Stream),
+    Sv8(Sv8Stream),
+}
+
+/// Musepack (MPC) format reader.
+///
+/// `MpcReader` implements a demuxer for SV7 and SV8 Musepack streams.
+pub struct MpcReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    stream: MpcStream,
+}
+
+impl QueryDescriptor for MpcReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "mpc",
+            "Musepack",
+            &["mpc", "mp+", "mpp"],
+            &["audio/musepack", "audio/x-musepack"],
+            &[b"MP+", b"MPCK"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for MpcReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let marker = source.read_triple_bytes()?;
+
+        let (stream, info) = if marker == SV7_STREAM_MARKER {
+            let (stream, info) = Sv7Stream::try_new(&mut source)?;
+            (MpcStream::Sv7(stream), info)
+        }
+        else if marker == SV8_STREAM_MARKER[..3] && source.read_byte()? == SV8_STREAM_MARKER[3] {
+            let (stream, info) = Sv8Stream::try_new(&mut source)?;
+            (MpcStream::Sv8(stream), info)
+        }
+        else {
+            return unsupported_error("mpc: missing stream marker");
+        };
+
+        let mut metadata = MetadataLog::default();
+
+        if let Some(rev) = info.replay_gain.metadata() {
+            metadata.push(rev);
+        }
+
+        // Tags are stored at the end of the stream, and can only be read if the stream is
+        // seekable.
+        if source.is_seekable() {
+            let data_start = source.pos();
+            read_trailing_tags(&mut source, &mut metadata)?;
+            source.seek(SeekFrom::Start(data_start))?;
+        }
+
+        Ok(MpcReader {
+            reader: source,
+            tracks: vec![Track::new(0, info.codec_params)],
+            cues: Vec::new(),
+            metadata,
+            stream,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        match &mut self.stream {
+            MpcStream::Sv7(stream) => stream.read_packet(&mut self.reader),
+            MpcStream::Sv8(stream) => stream.read_packet(&mut self.reader),
+        }
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                if let Some(sample_rate) = params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        // Packets are always seeked to, so the actual timestamp is the start of the packet
+        // containing the required timestamp.
+        let actual_ts = match &mut self.stream {
+            MpcStream::Sv7(stream) => stream.seek(&mut self.reader, required_ts)?,
+            MpcStream::Sv8(stream) => stream.seek(&mut self.reader, required_ts)?,
+        };
+
+        debug!("seeked to actual_ts={}, required_ts={}", actual_ts, required_ts);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// Reads the ID3v1 and APE tags at the end of a seekable stream.
+fn read_trailing_tags(reader: &mut MediaSourceStream, metadata: &mut MetadataLog) -> Result<()> {
+    let mut end = reader.seek(SeekFrom::End(0))?;
+
+    // An ID3v1 tag is always the last 128 bytes of the stream.
+    if end >= ID3V1_TAG_LEN {
+        reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+        if reader.read_triple_bytes()? == *b"TAG" {
+            reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+            let mut builder = MetadataBuilder::new();
+
+            match id3v1::read_id3v1(reader, &mut builder) {
+                Ok(_) => metadata.push(builder.metadata()),
+                Err(err) => warn!("mpc: failed to read id3v1 tag: {}", err),
+            }
+
+            end -= ID3V1_TAG_LEN;
+        }
+    }
+
+    // An APE tag preceeds the ID3v1 tag, if there is one.
+    let mut builder = MetadataBuilder::new();
+
+    match apev2::read_ape_tag_before(reader, end, &mut builder) {
+        Ok(Some(_)) => metadata.push(builder.metadata()),
+        Ok(None) => (),
+        Err(err) => warn!("mpc: failed to read ape tag: {}", err),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_MUSEPACK};
+use symphonia_core::errors::{end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BitReaderLtr, MediaSourceStream, ReadBitsLtr, ReadBytes};
+use symphonia_core::units::TimeBase;
+
+use log::debug;
+
+use crate::common::{reposition, ReplayGain, StreamInfo, MPC_FRAME_LEN, MPC_SAMPLE_RATES};
+
+/// The SV7 stream marker: "MP+" in ASCII.
+pub const SV7_STREAM_MARKER: [u8; 3] = *b"MP+";
+
+/// The length of the SV7 stream header, including the stream marker and version.
+const SV7_HEADER_LEN: usize = 24;
+
+/// The SV7 stream header.
+#[derive(Debug)]
+struct Sv7Header {
+    /// The number of frames in the stream.
+    n_frames: u32,
+    /// The sample rate.
+    sample_rate: u32,
+    /// The title gain in hundredths of a dB, or 0 if not set.
+    title_gain: i16,
+    /// The title peak as a 16-bit sample value, or 0 if not set.
+    title_peak: u16,
+    /// The album gain in hundredths of a dB, or 0 if not set.
+    album_gain: i16,
+    /// The album peak as a 16-bit sample value, or 0 if not set.
+    album_peak: u16,
+    /// The number of samples per channel in the last frame.
+    last_frame_len: u32,
+}
+
+impl Sv7Header {
+    /// Parses the header. The header is a sequence of little-endian 32-bit words, and the fields of
+    /// each word are read from the most-significant bit.
+    fn parse(buf: &[u8; SV7_HEADER_LEN]) -> Result<Sv7Header> {
+        // Only the lower nibble of the version is the stream version, the upper nibble is the
+        // minor version.
+        if buf[3] & 0xf != 7 {
+            return unsupported_error("mpc: unsupported stream version");
+        }
+
+        let word = |i: usize| {
+            u32::from_le_bytes([buf[4 + 4 * i], buf[5 + 4 * i], buf[6 + 4 * i], buf[7 + 4 * i]])
+        };
+
+        let n_frames = word(0);
+
+        let flags = word(1);
+
+        // Intensity stereo was never implemented by any encoder.
+        if flags & 0x8000_0000 != 0 {
+            return unsupported_error("mpc: intensity stereo is not supported");
+        }
+
+        let sample_rate = MPC_SAMPLE_RATES[((flags >> 16) & 0x3) as usize];
+
+        let title = word(2);
+        let album = word(3);
+
+        let gapless = word(4);
+
+        // If the stream is not gapless, then the last frame is a complete frame.
+        let last_frame_len = match (gapless >> 20) & 0x7ff {
+            len @ 1..=1152 if gapless & 0x8000_0000 != 0 => len,
+            _ => MPC_FRAME_LEN,
+        };
+
+        Ok(Sv7Header {
+            n_frames,
+            sample_rate,
+            title_gain: (title >> 16) as i16,
+            title_peak: title as u16,
+            album_gain: (album >> 16) as i16,
+            album_peak: album as u16,
+            last_frame_len,
+        })
+    }
+
+    /// Gets the total number of samples per channel.
+    fn n_samples(&self) -> u64 {
+        match self.n_frames {
+            0 => 0,
+            n => u64::from(n - 1) * u64::from(MPC_FRAME_LEN) + u64::from(self.last_frame_len),
+        }
+    }
+
+    /// Gets the ReplayGain values.
+    fn replay_gain(&self) -> ReplayGain {
+        let gain = |gain: i16| if gain != 0 { Some(f32::from(gain) / 100.0) } else { None };
+        let peak = |peak: u16| if peak != 0 { Some(f32::from(peak) / 32768.0) } else { None };
+
+        ReplayGain {
+            track_gain: gain(self.title_gain),
+            track_peak: peak(self.title_peak),
+            album_gain: gain(self.album_gain),
+            album_peak: peak(self.album_peak),
+        }
+    }
+}
+
+/// The position of a SV7 frame.
+struct Sv7Frame {
+    /// The byte position of the word containing the first bit of the frame.
+    pos: u64,
+    /// The number of bits in that word preceeding the frame.
+    skip: u32,
+}
+
+/// A SV7 stream.
+///
+/// SV7 frames are not byte-aligned. The stream is a sequence of little-endian 32-bit words that
+/// are read from the most-significant bit, and each frame is prefixed with its length in bits.
+/// Since the stream contains no seek table, frame positions are indexed as the stream is read.
+///
+/// Packets contain the bits of a single frame, excluding the length prefix, re-packed into bytes
+/// starting from the most-significant bit of the first byte.
+pub struct Sv7Stream {
+    frames: Vec<Sv7Frame>,
+    n_frames: u32,
+    last_frame_len: u32,
+    next_frame: u32,
+}
+
+impl Sv7Stream {
+    /// Reads the stream header. The reader must be positioned after the stream marker.
+    pub fn try_new(reader: &mut MediaSourceStream) -> Result<(Sv7Stream, StreamInfo)> {
+        let mut buf = [0; SV7_HEADER_LEN];
+        buf[..3].copy_from_slice(&SV7_STREAM_MARKER);
+        reader.read_buf_exact(&mut buf[3..])?;
+
+        let header = Sv7Header::parse(&buf)?;
+
+        debug!("{:?}", header);
+
+        // The most-significant 8 bits of the word following the header is the encoder version,
+        // the first frame immediately follows it.
+        let first_frame = Sv7Frame { pos: reader.pos(), skip: 8 };
+
+        // The codec extra data contains the complete stream header, including the stream marker,
+        // to allow a decoder to distinguish between stream versions.
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_MUSEPACK)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT)
+            .with_n_frames(header.n_samples())
+            .with_max_frames_per_packet(u64::from(MPC_FRAME_LEN))
+            .with_extra_data(Box::new(buf));
+
+        let stream = Sv7Stream {
+            frames: vec![first_frame],
+            n_frames: header.n_frames,
+            last_frame_len: header.last_frame_len,
+            next_frame: 0,
+        };
+
+        Ok((stream, StreamInfo { codec_params, replay_gain: header.replay_gain() }))
+    }
+
+    pub fn read_packet(&mut self, reader: &mut MediaSourceStream) -> Result<Packet> {
+        if self.next_frame >= self.n_frames {
+            return end_of_stream_error();
+        }
+
+        let (mut buf, start, len) = self.read_frame_header(reader, self.next_frame)?;
+
+        // Read the remainder of the words containing the frame.
+        let n_words = ((start + len + 31) / 32) as usize;
+        let n_read = buf.len() / 4;
+
+        buf.resize(4 * n_words, 0);
+        read_words(reader, &mut buf[4 * n_read..])?;
+
+        // Re-pack the frame into bytes.
+        let mut bs = BitReaderLtr::new(&buf);
+        bs.ignore_bits(start as u32)?;
+
+        let mut data = vec![0; ((len + 7) / 8) as usize];
+
+        let (full, rem) = ((len / 8) as usize, (len % 8) as u32);
+
+        for byte in data[..full].iter_mut() {
+            *byte = bs.read_bits_leq32(8)? as u8;
+        }
+
+        if rem > 0 {
+            data[full] = (bs.read_bits_leq32(rem)? << (8 - rem)) as u8;
+        }
+
+        let ts = u64::from(self.next_frame) * u64::from(MPC_FRAME_LEN);
+
+        let dur = if self.next_frame + 1 == self.n_frames {
+            u64::from(self.last_frame_len)
+        }
+        else {
+            u64::from(MPC_FRAME_LEN)
+        };
+
+        self.next_frame += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, data.into_boxed_slice()))
+    }
+
+    pub fn seek(&mut self, reader: &mut MediaSourceStream, ts: u64) -> Result<u64> {
+        if self.n_frames == 0 {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let target = (ts / u64::from(MPC_FRAME_LEN)).min(u64::from(self.n_frames - 1)) as u32;
+
+        // Index the frames up-to the target frame. Only the length of each frame is read.
+        while self.frames.len() <= target as usize {
+            let index = (self.frames.len() - 1) as u32;
+            let (buf, start, len) = self.read_frame_header(reader, index)?;
+
+            let remaining = 4 * ((start + len + 31) / 32) - buf.len() as u64;
+            reader.ignore_bytes(remaining)?;
+        }
+
+        self.next_frame = target;
+
+        Ok(u64::from(target) * u64::from(MPC_FRAME_LEN))
+    }
+
+    /// Reads the words containing the length prefix of a frame. Returns the words as big-endian
+    /// bytes, the bit position of the frame data within them, and the length of the frame data in
+    /// bits. The position of the following frame is indexed if not already.
+    fn read_frame_header(
+        &mut self,
+        reader: &mut MediaSourceStream,
+        index: u32,
+    ) -> Result<(Vec<u8>, u64, u64)> {
+        let frame = &self.frames[index as usize];
+
+        let pos = frame.pos;
+        let skip = frame.skip;
+
+        reposition(reader, pos)?;
+
+        // The 20-bit length prefix may straddle two words.
+        let mut buf = vec![0; if skip + 20 > 32 { 8 } else { 4 }];
+        read_words(reader, &mut buf)?;
+
+        let mut bs = BitReaderLtr::new(&buf);
+        bs.ignore_bits(skip)?;
+
+        let len = u64::from(bs.read_bits_leq32(20)?);
+
+        let start = u64::from(skip) + 20;
+        let end = start + len;
+
+        if index as usize + 1 == self.frames.len() && index + 1 < self.n_frames {
+            self.frames.push(Sv7Frame { pos: pos + 4 * (end / 32), skip: (end % 32) as u32 });
+        }
+
+        Ok((buf, start, len))
+    }
+}
+
+/// Reads little-endian 32-bit words into `buf`, and converts them to big-endian so that the bits
+/// may be read in order.
+fn read_words(reader: &mut MediaSourceStream, buf: &mut [u8]) -> Result<()> {
+    reader.read_buf_exact(buf)?;
+
+    for word in buf.chunks_exact_mut(4) {
+        word.reverse();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::common::tests::BitWriter;
+
+    /// Gets a SV7 stream header with 3 frames, a 48 kHz sample rate, ReplayGain values, and a
+    /// last frame of 500 samples.
+    fn header_buf() -> [u8; SV7_HEADER_LEN] {
+        let words: [u32; 5] =
+            [3, 1 << 16, (650 << 16) | 16384, (0xfed4 << 16) | 32767, 0x8000_0000 | (500 << 20)];
+
+        let mut buf = [0; SV7_HEADER_LEN];
+        buf[..4].copy_from_slice(b"MP+\x17");
+
+        for (i, word) in words.iter().enumerate() {
+            buf[4 + 4 * i..8 + 4 * i].copy_from_slice(&word.to_le_bytes());
+        }
+
+        buf
+    }
+
+    #[test]
+    fn verify_header_parse() {
+        let header = Sv7Header::parse(&header_buf()).unwrap();
+
+        assert_eq!(header.n_frames, 3);
+        assert_eq!(header.sample_rate, 48000);
+        assert_eq!(header.n_samples(), 2 * 1152 + 500);
+
+        let gain = header.replay_gain();
+
+        assert_eq!(gain.track_gain, Some(6.5));
+        assert_eq!(gain.track_peak, Some(0.5));
+        assert_eq!(gain.album_gain, Some(-3.0));
+        assert_eq!(gain.album_peak, Some(32767.0 / 32768.0));
+
+        let tags = gain.metadata().unwrap();
+        let values: Vec<String> = tags.tags().iter().map(|tag| tag.value.to_string()).collect();
+
+        assert_eq!(values, ["6.50 dB", "0.500000", "-3.00 dB", "0.999969"]);
+
+        // A stream that is not gapless ends with a complete frame.
+        let mut buf = header_buf();
+        buf[23] = 0;
+
+        assert_eq!(Sv7Header::parse(&buf).unwrap().n_samples(), 3 * 1152);
+
+        // SV8 streams, and intensity stereo, are not supported.
+        let mut buf = header_buf();
+        buf[3] = 0x08;
+
+        assert!(Sv7Header::parse(&buf).is_err());
+
+        let mut buf = header_buf();
+        buf[11] = 0x80;
+
+        assert!(Sv7Header::parse(&buf).is_err());
+    }
+
+    #[test]
+    fn verify_stream() {
+        // The frames, as their data and length in bits.
+        let frames: [(&[u8], u64); 3] = [
+            (&[0xde, 0xad, 0xbe, 0xef, 0x01], 40),
+            (&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfc], 70),
+            (&[0xa5, 0x80], 10),
+        ];
+
+        // The encoder version, followed by each frame prefixed with its length.
+        let mut bw = BitWriter::default();
+        bw.put(0x71, 8);
+
+        for (data, len) in frames.iter() {
+            bw.put(*len, 20);
+
+            for (i, byte) in data.iter().enumerate() {
+                let n_bits = (len - 8 * i as u64).min(8) as u32;
+                bw.put(u64::from(byte >> (8 - n_bits)), n_bits);
+            }
+        }
+
+        // The stream is a sequence of little-endian words.
+        let mut buf = header_buf()[3..].to_vec();
+
+        let mut bits = bw.into_bytes();
+        bits.resize((bits.len() + 3) / 4 * 4, 0);
+
+        for word in bits.chunks_exact(4) {
+            buf.extend(word.iter().rev());
+        }
+
+        let mut reader = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+
+        let (mut stream, info) = Sv7Stream::try_new(&mut reader).unwrap();
+
+        assert_eq!(info.codec_params.n_frames, Some(2 * 1152 + 500));
+        assert_eq!(info.codec_params.extra_data.as_deref(), Some(&header_buf()[..]));
+
+        for (i, (data, _)) in frames.iter().enumerate() {
+            let packet = stream.read_packet(&mut reader).unwrap();
+
+            assert_eq!(packet.ts(), 1152 * i as u64);
+            assert_eq!(packet.dur(), if i < 2 { 1152 } else { 500 });
+            assert_eq!(packet.buf(), *data);
+        }
+
+        assert!(stream.read_packet(&mut reader).is_err());
+
+        // Seek to the frame containing the timestamp.
+        assert_eq!(stream.seek(&mut reader, 1200).unwrap(), 1152);
+        assert_eq!(stream.read_packet(&mut reader).unwrap().buf(), frames[1].0);
+    }
+}
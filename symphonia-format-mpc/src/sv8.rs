@@ -0,0 +1,583 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::convert::TryFrom;
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::checksum::Crc32Le;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_MUSEPACK};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BitReaderLtr, BufReader, MediaSourceStream, Monitor, ReadBitsLtr};
+use symphonia_core::io::{MediaSource, ReadBytes};
+use symphonia_core::units::TimeBase;
+
+use log::{debug, warn};
+
+use crate::common::{reposition, ReplayGain, StreamInfo, MPC_FRAME_LEN, MPC_SAMPLE_RATES};
+
+/// The SV8 stream marker: "MPCK" in ASCII.
+pub const SV8_STREAM_MARKER: [u8; 4] = *b"MPCK";
+
+/// Stream header packet key.
+const SV8_KEY_STREAM_HEADER: [u8; 2] = *b"SH";
+/// ReplayGain packet key.
+const SV8_KEY_REPLAY_GAIN: [u8; 2] = *b"RG";
+/// Seek table offset packet key.
+const SV8_KEY_SEEK_TABLE_OFFSET: [u8; 2] = *b"SO";
+/// Seek table packet key.
+const SV8_KEY_SEEK_TABLE: [u8; 2] = *b"ST";
+/// Audio packet key.
+const SV8_KEY_AUDIO: [u8; 2] = *b"AP";
+/// Stream end packet key.
+const SV8_KEY_STREAM_END: [u8; 2] = *b"SE";
+
+/// The maximum length of a packet that will be read into memory.
+const SV8_MAX_PACKET_LEN: u64 = 16 * 1024 * 1024;
+
+/// The loudness level that SV8 gains are relative to, in dB.
+const SV8_REPLAY_GAIN_REF: f32 = 64.82;
+
+/// A SV8 packet header.
+struct PacketHeader {
+    /// The packet key. Always two upper-case ASCII letters.
+    key: [u8; 2],
+    /// The length of the packet payload.
+    len: u64,
+}
+
+impl PacketHeader {
+    fn read<B: ReadBytes>(reader: &mut B) -> Result<PacketHeader> {
+        let key = reader.read_double_bytes()?;
+
+        if !key.iter().all(u8::is_ascii_uppercase) {
+            return decode_error("mpc: invalid packet key");
+        }
+
+        // The packet size includes the key and the size itself.
+        let (size, size_len) = read_size(reader)?;
+
+        let len = match size.checked_sub(2 + size_len) {
+            Some(len) => len,
+            None => return decode_error("mpc: invalid packet size"),
+        };
+
+        Ok(PacketHeader { key, len })
+    }
+
+    /// Reads the packet payload.
+    fn read_payload<B: ReadBytes>(&self, reader: &mut B) -> Result<Box<[u8]>> {
+        if self.len > SV8_MAX_PACKET_LEN {
+            return decode_error("mpc: packet is too large");
+        }
+
+        Ok(reader.read_boxed_slice_exact(self.len as usize)?)
+    }
+}
+
+/// Reads a variable-length size. Each byte contributes 7 bits, most-significant first, and has
+/// its most-significant bit set if another byte follows. Returns the size and the number of bytes
+/// read.
+fn read_size<B: ReadBytes>(reader: &mut B) -> Result<(u64, u64)> {
+    let mut size = 0;
+
+    for len in 1..=9 {
+        let byte = reader.read_u8()?;
+
+        size = (size << 7) | u64::from(byte & 0x7f);
+
+        if byte & 0x80 == 0 {
+            return Ok((size, len));
+        }
+    }
+
+    decode_error("mpc: invalid variable-length size")
+}
+
+/// Reads a variable-length size from a bitstream.
+fn read_bits_size<B: ReadBitsLtr>(bs: &mut B) -> Result<u64> {
+    let mut size = 0;
+
+    for _ in 0..9 {
+        let more = bs.read_bool()?;
+
+        size = (size << 7) | u64::from(bs.read_bits_leq32(7)?);
+
+        if !more {
+            return Ok(size);
+        }
+    }
+
+    decode_error("mpc: invalid variable-length size")
+}
+
+/// The SV8 stream header.
+#[derive(Debug)]
+struct StreamHeader {
+    /// The number of samples per channel, excluding the beginning silence.
+    n_samples: u64,
+    /// The number of samples per channel of silence added to the start of the stream.
+    beginning_silence: u64,
+    /// The sample rate.
+    sample_rate: u32,
+    /// The number of channels.
+    n_channels: u32,
+    /// The number of frames in each audio packet.
+    frames_per_packet: u32,
+}
+
+impl StreamHeader {
+    fn read(buf: &[u8]) -> Result<StreamHeader> {
+        if buf.len() < 4 {
+            return decode_error("mpc: stream header is too short");
+        }
+
+        let (crc, buf) = buf.split_at(4);
+
+        // The CRC covers the remainder of the header.
+        let mut crc32 = Crc32Le::new(!0);
+        crc32.process_buf_bytes(buf);
+
+        if !crc32.crc() != u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return decode_error("mpc: stream header crc mismatch");
+        }
+
+        let mut reader = BufReader::new(buf);
+
+        if reader.read_u8()? != 8 {
+            return unsupported_error("mpc: unsupported stream version");
+        }
+
+        let (n_samples, _) = read_size(&mut reader)?;
+        let (beginning_silence, _) = read_size(&mut reader)?;
+
+        // The sample rate index (3 bits), the highest subband used (5 bits), the number of channels
+        // (4 bits), the mid-side stereo flag (1 bit), and the log4 of the number of frames per audio
+        // packet (3 bits). Only the fields required by the demuxer are used.
+        let fields = reader.read_be_u16()?;
+
+        let sample_rate = match MPC_SAMPLE_RATES.get(usize::from(fields >> 13)) {
+            Some(&sample_rate) => sample_rate,
+            None => return unsupported_error("mpc: unsupported sample rate"),
+        };
+
+        let n_channels = u32::from((fields >> 4) & 0xf) + 1;
+        let frames_per_packet = 1 << (2 * (fields & 0x7));
+
+        Ok(StreamHeader {
+            n_samples,
+            beginning_silence,
+            sample_rate,
+            n_channels,
+            frames_per_packet,
+        })
+    }
+}
+
+/// Reads a ReplayGain packet. Gains are stored as a loudness level, and peaks as a level relative to
+/// a 16-bit sample, both in 1/256 dB.
+fn read_replay_gain(buf: &[u8]) -> Result<Option<ReplayGain>> {
+    let mut reader = BufReader::new(buf);
+
+    if reader.read_u8()? != 1 {
+        warn!("mpc: unsupported replaygain version");
+        return Ok(None);
+    }
+
+    let title_gain = reader.read_be_u16()?;
+    let title_peak = reader.read_be_u16()?;
+    let album_gain = reader.read_be_u16()?;
+    let album_peak = reader.read_be_u16()?;
+
+    let gain = |gain: u16| match gain {
+        0 => None,
+        _ => Some(SV8_REPLAY_GAIN_REF - f32::from(gain) / 256.0),
+    };
+
+    let peak = |peak: u16| match peak {
+        0 => None,
+        _ => Some(10f32.powf(f32::from(peak) / (20.0 * 256.0)) / 32768.0),
+    };
+
+    Ok(Some(ReplayGain {
+        track_gain: gain(title_gain),
+        track_peak: peak(title_peak),
+        album_gain: gain(album_gain),
+        album_peak: peak(album_peak),
+    }))
+}
+
+/// Reads a seek table. Returns the absolute positions of the seek points, and the log2 of the
+/// number of audio packets between seek points.
+///
+/// Positions are relative to the stream marker. The first two are stored directly, the remainder
+/// are stored as the error of a linear prediction from the previous two.
+fn read_seek_table(buf: &[u8], stream_pos: u64, n_packets: u64) -> Result<(Vec<u64>, u32)> {
+    let mut bs = BitReaderLtr::new(buf);
+
+    let n_entries = read_bits_size(&mut bs)?;
+    let distance = bs.read_bits_leq32(4)?;
+
+    // There cannot be more seek points than audio packets.
+    if n_entries > (n_packets >> distance) + 1 {
+        return decode_error("mpc: too many seek table entries");
+    }
+
+    let mut points: Vec<u64> = Vec::with_capacity(n_entries as usize);
+
+    for i in 0..n_entries as usize {
+        let pos = if i < 2 {
+            read_bits_size(&mut bs)?
+        }
+        else {
+            let code = (bs.read_unary_zeros_capped(33)? << 12) | bs.read_bits_leq32(12)?;
+
+            // The least-significant bit is the sign of the prediction error.
+            let error = if code & 1 != 0 { -i64::from(code >> 1) } else { i64::from(code >> 1) };
+
+            let prediction = 2 * points[i - 1] as i64 - points[i - 2] as i64;
+
+            match prediction.checked_add(error) {
+                Some(pos) if pos >= 0 => pos as u64,
+                _ => return decode_error("mpc: invalid seek table entry"),
+            }
+        };
+
+        points.push(pos);
+    }
+
+    // Make the positions absolute.
+    for pos in points.iter_mut() {
+        *pos += stream_pos;
+    }
+
+    Ok((points, distance))
+}
+
+/// A SV8 stream.
+///
+/// The stream is a sequence of packets, each identified by a two letter key. Packets contain the
+/// payload of a single audio packet, which in turn contains one or more frames.
+pub struct Sv8Stream {
+    /// The positions of the seek points. The n-th seek point is the position of the audio packet
+    /// at index `n << seek_distance`.
+    seek_points: Vec<u64>,
+    seek_distance: u32,
+    /// The number of samples per channel in an audio packet.
+    packet_len: u64,
+    /// The total number of samples per channel, including the beginning silence.
+    n_samples: u64,
+    n_packets: u64,
+    next_packet: u64,
+}
+
+impl Sv8Stream {
+    /// Reads all packets preceeding the first audio packet. The reader must be positioned after
+    /// the stream marker.
+    pub fn try_new(reader: &mut MediaSourceStream) -> Result<(Sv8Stream, StreamInfo)> {
+        let stream_pos = reader.pos() - SV8_STREAM_MARKER.len() as u64;
+
+        let mut header = None;
+        let mut extra_data = Vec::new();
+        let mut replay_gain = ReplayGain::default();
+        let mut seek_table_pos = None;
+        let mut seek_table_buf = None;
+
+        let first_packet_pos = loop {
+            let pos = reader.pos();
+            let packet = PacketHeader::read(reader)?;
+
+            match packet.key {
+                SV8_KEY_STREAM_HEADER => {
+                    let buf = packet.read_payload(reader)?;
+
+                    header = Some(StreamHeader::read(&buf)?);
+
+                    // The codec extra data contains the stream marker followed by the stream
+                    // header payload to allow a decoder to distinguish between stream versions.
+                    extra_data.clear();
+                    extra_data.extend_from_slice(&SV8_STREAM_MARKER);
+                    extra_data.extend_from_slice(&buf);
+                }
+                SV8_KEY_REPLAY_GAIN => {
+                    let buf = packet.read_payload(reader)?;
+
+                    if let Some(gain) = read_replay_gain(&buf)? {
+                        replay_gain = gain;
+                    }
+                }
+                SV8_KEY_SEEK_TABLE_OFFSET => {
+                    let buf = packet.read_payload(reader)?;
+
+                    // The offset is relative to the start of this packet.
+                    let (offset, _) = read_size(&mut BufReader::new(&buf))?;
+                    seek_table_pos = Some(pos + offset);
+                }
+                SV8_KEY_SEEK_TABLE => {
+                    seek_table_buf = Some(packet.read_payload(reader)?);
+                }
+                SV8_KEY_AUDIO | SV8_KEY_STREAM_END => break pos,
+                _ => reader.ignore_bytes(packet.len)?,
+            }
+        };
+
+        let header = match header {
+            Some(header) => header,
+            None => return decode_error("mpc: missing stream header"),
+        };
+
+        debug!("{:?}", header);
+
+        let packet_len = u64::from(MPC_FRAME_LEN) * u64::from(header.frames_per_packet);
+        let n_samples = header.beginning_silence + header.n_samples;
+        let n_packets = (n_samples + packet_len - 1) / packet_len;
+
+        // The seek table is usually stored after the audio packets, and can only be read if the
+        // stream is seekable.
+        if seek_table_buf.is_none() && reader.is_seekable() {
+            if let Some(pos) = seek_table_pos {
+                match read_seek_table_packet(reader, pos) {
+                    Ok(buf) => seek_table_buf = Some(buf),
+                    Err(err) => warn!("mpc: failed to read seek table: {}", err),
+                }
+            }
+        }
+
+        reposition(reader, first_packet_pos)?;
+
+        let mut seek_points = vec![first_packet_pos];
+        let mut seek_distance = 0;
+
+        if let Some(buf) = seek_table_buf {
+            match read_seek_table(&buf, stream_pos, n_packets) {
+                // The first seek point must be the first audio packet.
+                Ok((points, distance)) if points.first() == Some(&first_packet_pos) => {
+                    seek_points = points;
+                    seek_distance = distance;
+                }
+                Ok(_) => warn!("mpc: seek table does not match the stream"),
+                Err(err) => warn!("mpc: failed to read seek table: {}", err),
+            }
+        }
+
+        // When the channel count is >2 then enable the first N channels.
+        let channels = match Channels::from_bits(((1u64 << header.n_channels) - 1) as u32) {
+            Some(channels) => channels,
+            None => return unsupported_error("mpc: unsupported number of channels"),
+        };
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_MUSEPACK)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(channels)
+            .with_n_frames(n_samples)
+            .with_max_frames_per_packet(packet_len)
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        if let Ok(delay) = u32::try_from(header.beginning_silence) {
+            codec_params.with_delay(delay);
+        }
+
+        let stream = Sv8Stream {
+            seek_points,
+            seek_distance,
+            packet_len,
+            n_samples,
+            n_packets,
+            next_packet: 0,
+        };
+
+        Ok((stream, StreamInfo { codec_params, replay_gain }))
+    }
+
+    pub fn read_packet(&mut self, reader: &mut MediaSourceStream) -> Result<Packet> {
+        let packet = read_audio_packet_header(reader)?;
+
+        let data = packet.read_payload(reader)?;
+
+        let ts = self.next_packet * self.packet_len;
+        let dur = self.packet_len.min(self.n_samples.saturating_sub(ts));
+
+        self.next_packet += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, data))
+    }
+
+    pub fn seek(&mut self, reader: &mut MediaSourceStream, ts: u64) -> Result<u64> {
+        if self.n_packets == 0 {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let target = (ts / self.packet_len).min(self.n_packets - 1);
+
+        let point = ((target >> self.seek_distance) as usize).min(self.seek_points.len() - 1);
+        let point_packet = (point as u64) << self.seek_distance;
+
+        // Continue from the current packet if it is closer to the target than the seek point.
+        let mut packet = if self.next_packet >= point_packet && self.next_packet <= target {
+            self.next_packet
+        }
+        else {
+            reposition(reader, self.seek_points[point])?;
+            point_packet
+        };
+
+        while packet < target {
+            let header = read_audio_packet_header(reader)?;
+            reader.ignore_bytes(header.len)?;
+            packet += 1;
+        }
+
+        self.next_packet = target;
+
+        Ok(target * self.packet_len)
+    }
+}
+
+/// Reads packet headers until the header of an audio packet is found. Other packets are skipped.
+fn read_audio_packet_header(reader: &mut MediaSourceStream) -> Result<PacketHeader> {
+    loop {
+        let packet = PacketHeader::read(reader)?;
+
+        match packet.key {
+            SV8_KEY_AUDIO => return Ok(packet),
+            SV8_KEY_STREAM_END => return end_of_stream_error(),
+            _ => reader.ignore_bytes(packet.len)?,
+        }
+    }
+}
+
+/// Seeks to, and reads the payload of, the seek table packet at `pos`.
+fn read_seek_table_packet(reader: &mut MediaSourceStream, pos: u64) -> Result<Box<[u8]>> {
+    reader.seek(SeekFrom::Start(pos))?;
+
+    let packet = PacketHeader::read(reader)?;
+
+    if packet.key != SV8_KEY_SEEK_TABLE {
+        return decode_error("mpc: expected a seek table packet");
+    }
+
+    packet.read_payload(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::common::tests::BitWriter;
+
+    /// The stream header payload: 3000 samples, 100 samples of beginning silence, 44.1 kHz, 2
+    /// channels, and 1 frame per audio packet.
+    const STREAM_HEADER: [u8; 10] = [0x9a, 0xc0, 0xfd, 0xb4, 0x08, 0x97, 0x38, 0x64, 0x1f, 0x18];
+
+    /// The payloads of the audio packets.
+    const AUDIO_LENS: [usize; 3] = [30, 10, 20];
+
+    fn push_packet(buf: &mut Vec<u8>, key: &[u8; 2], payload: &[u8]) {
+        buf.extend_from_slice(key);
+        buf.push(3 + payload.len() as u8);
+        buf.extend_from_slice(payload);
+    }
+
+    /// Gets a stream with a ReplayGain packet, and a seek table after the audio packets.
+    fn stream(header: &[u8]) -> Vec<u8> {
+        let mut buf = SV8_STREAM_MARKER.to_vec();
+        push_packet(&mut buf, b"SH", header);
+        push_packet(&mut buf, b"RG", &[1, 0x3a, 0x98, 0x50, 0x00, 0, 0, 0, 0]);
+
+        // The seek table follows the audio packets, 73 bytes from the start of this packet.
+        push_packet(&mut buf, b"SO", &[73]);
+
+        for (i, &len) in AUDIO_LENS.iter().enumerate() {
+            push_packet(&mut buf, b"AP", &vec![i as u8; len]);
+        }
+
+        // Three seek points, one per audio packet, at 33, 66, and 79 bytes. The third is stored as
+        // the error (-20) of the prediction from the previous two.
+        let mut bw = BitWriter::default();
+        bw.put(3, 8);
+        bw.put(0, 4);
+        bw.put(33, 8);
+        bw.put(66, 8);
+        bw.put(1, 1);
+        bw.put((20 << 1) | 1, 12);
+
+        push_packet(&mut buf, b"ST", &bw.into_bytes());
+        push_packet(&mut buf, b"SE", &[]);
+
+        buf
+    }
+
+    fn try_new_stream(buf: Vec<u8>) -> Result<(MediaSourceStream, Sv8Stream, StreamInfo)> {
+        let mut reader = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        reader.ignore_bytes(4)?;
+
+        let (stream, info) = Sv8Stream::try_new(&mut reader)?;
+        Ok((reader, stream, info))
+    }
+
+    #[test]
+    fn verify_read_size() {
+        assert_eq!(read_size(&mut BufReader::new(&[0x97, 0x38])).unwrap(), (3000, 2));
+        assert_eq!(read_size(&mut BufReader::new(&[0x64])).unwrap(), (100, 1));
+        assert!(read_size(&mut BufReader::new(&[0x80; 10])).is_err());
+    }
+
+    #[test]
+    fn verify_stream() {
+        let (mut reader, mut stream, info) = try_new_stream(stream(&STREAM_HEADER)).unwrap();
+
+        let params = &info.codec_params;
+
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(2));
+        assert_eq!(params.n_frames, Some(3100));
+        assert_eq!(params.delay, Some(100));
+        assert_eq!(params.max_frames_per_packet, Some(1152));
+        assert_eq!(params.extra_data.as_deref().map(|data| &data[4..]), Some(&STREAM_HEADER[..]));
+
+        // Only the track gain and peak are set.
+        let tags = info.replay_gain.metadata().unwrap();
+        let values: Vec<String> = tags.tags().iter().map(|tag| tag.value.to_string()).collect();
+
+        assert_eq!(values, ["6.23 dB", "0.305176"]);
+
+        // The seek table was read from the end of the stream.
+        assert_eq!(stream.seek_points, [33, 66, 79]);
+
+        for (i, &len) in AUDIO_LENS.iter().enumerate() {
+            let packet = stream.read_packet(&mut reader).unwrap();
+
+            assert_eq!(packet.ts(), 1152 * i as u64);
+            assert_eq!(packet.dur(), if i < 2 { 1152 } else { 3100 - 2304 });
+            assert_eq!(packet.buf(), &vec![i as u8; len][..]);
+        }
+
+        assert!(stream.read_packet(&mut reader).is_err());
+
+        // Seek to the audio packet containing the timestamp.
+        assert_eq!(stream.seek(&mut reader, 2400).unwrap(), 2304);
+        assert_eq!(stream.read_packet(&mut reader).unwrap().buf(), &[2; 20][..]);
+
+        assert_eq!(stream.seek(&mut reader, 0).unwrap(), 0);
+        assert_eq!(stream.read_packet(&mut reader).unwrap().buf(), &[0; 30][..]);
+    }
+
+    #[test]
+    fn verify_stream_header_crc() {
+        let mut header = STREAM_HEADER;
+        header[9] ^= 1;
+
+        assert!(try_new_stream(stream(&header)).is_err());
+    }
+}
@@ -8,16 +8,19 @@
 use std::fmt;
 
 use symphonia_core::codecs::{
+    CodecType, CODEC_TYPE_ADPCM_IMA_QT, CODEC_TYPE_GSM, CODEC_TYPE_MACE3, CODEC_TYPE_MACE6,
     CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F64BE, CODEC_TYPE_PCM_MULAW,
     CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE, CODEC_TYPE_PCM_S32BE,
     CODEC_TYPE_PCM_S8,
 };
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
-use symphonia_core::io::{MediaSourceStream, ReadBytes};
+use symphonia_core::io::{MediaSourceStream, ReadBytes, ScopedStream};
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision, StandardTagKey, Tag, Value};
+use symphonia_metadata::id3v2;
 
 use crate::common::{
-    try_channel_count_to_mask, ChunkParser, FormatALaw, FormatData, FormatIeeeFloat, FormatMuLaw,
-    FormatPcm, PacketInfo, ParseChunk, ParseChunkTag,
+    try_channel_count_to_mask, ChunkParser, FormatALaw, FormatAdpcm, FormatData, FormatGsm,
+    FormatIeeeFloat, FormatMace, FormatMuLaw, FormatPcm, PacketInfo, ParseChunk, ParseChunkTag,
 };
 
 use extended::Extended;
@@ -104,6 +107,29 @@ impl CommonChunk {
         Ok(FormatData::Pcm(FormatPcm { bits_per_sample, channels, codec }))
     }
 
+    fn read_ima4_fmt(n_channels: u16) -> Result<FormatData> {
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Adpcm(FormatAdpcm {
+            bits_per_sample: 4,
+            channels,
+            codec: CODEC_TYPE_ADPCM_IMA_QT,
+        }))
+    }
+
+    fn read_gsm_fmt(n_channels: u16) -> Result<FormatData> {
+        if n_channels != 1 {
+            return decode_error("aifc: gsm must have 1 channel");
+        }
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Gsm(FormatGsm { channels, codec: CODEC_TYPE_GSM }))
+    }
+
+    fn read_mace_fmt(codec: CodecType, n_channels: u16) -> Result<FormatData> {
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Mace(FormatMace { channels, codec }))
+    }
+
     pub fn packet_info(&self) -> Result<PacketInfo> {
         match &self.format_data {
             FormatData::Pcm(_) => {
@@ -120,15 +146,42 @@ impl CommonChunk {
                 let block_align = self.n_channels;
                 Ok(PacketInfo::without_blocks(block_align as u16))
             }
-            FormatData::IeeeFloat(_) => {
-                let block_align = self.n_channels * self.sample_size / 8;
+            FormatData::IeeeFloat(FormatIeeeFloat { codec, .. }) => {
+                // The sample size of floating point formats is implied by the compression type.
+                let sample_size = if *codec == CODEC_TYPE_PCM_F64BE { 8 } else { 4 };
+                let block_align = self.n_channels * sample_size;
                 Ok(PacketInfo::without_blocks(block_align as u16))
             }
-            FormatData::Extensible(_) => {
-                unsupported_error("aiff: packet info not implemented for format Extensible")
+            FormatData::Extensible(ext) => {
+                let block_align = self.n_channels as u16 * ext.bits_per_sample / 8;
+                Ok(PacketInfo::without_blocks(block_align))
             }
-            FormatData::Adpcm(_) => {
-                unsupported_error("aiff: packet info not implemented for format Adpcm")
+            FormatData::Adpcm(FormatAdpcm { codec, .. }) if *codec == CODEC_TYPE_ADPCM_IMA_QT => {
+                // Each channel of a block is 34 bytes, encoding 64 frames.
+                let block_align = 34 * self.n_channels;
+                PacketInfo::with_blocks(block_align as u16, 64)
+            }
+            FormatData::Adpcm(FormatAdpcm { bits_per_sample, .. }) => {
+                // Other ADPCM formats are unframed streams of code words. The smallest block is
+                // the number of frames that fill a whole number of bytes.
+                let block_align = *bits_per_sample * self.n_channels as u16;
+                PacketInfo::with_blocks(block_align, 8)
+            }
+            FormatData::Gsm(_) => {
+                // Each 33 byte GSM frame encodes 160 samples.
+                PacketInfo::with_blocks(33, 160)
+            }
+            FormatData::Mpeg(_) => {
+                unsupported_error("aiff: packet info not implemented for format Mpeg")
+            }
+            FormatData::Mace(FormatMace { codec, .. }) => {
+                // MACE 3:1 encodes 6 frames in 2 bytes per channel, and MACE 6:1 encodes 6 frames
+                // in 1 byte per channel.
+                let block_align = match *codec {
+                    CODEC_TYPE_MACE3 => 2 * self.n_channels,
+                    _ => self.n_channels,
+                };
+                PacketInfo::with_blocks(block_align as u16, 6)
             }
         }
     }
@@ -189,8 +242,24 @@ impl fmt::Display for CommonChunk {
             FormatData::Extensible(_) => {
                 writeln!(f, "\tformat_data: Extensible DISPLAY UNSUPPORTED {{")?;
             }
-            FormatData::Adpcm(_) => {
-                writeln!(f, "\tformat_data: Adpcm DISPLAY UNSUPPORTED {{")?;
+            FormatData::Adpcm(ref adpcm) => {
+                writeln!(f, "\tformat_data: Adpcm {{")?;
+                writeln!(f, "\t\tbits_per_sample: {},", adpcm.bits_per_sample)?;
+                writeln!(f, "\t\tchannels: {},", adpcm.channels)?;
+                writeln!(f, "\t\tcodec: {},", adpcm.codec)?;
+            }
+            FormatData::Gsm(ref gsm) => {
+                writeln!(f, "\tformat_data: Gsm {{")?;
+                writeln!(f, "\t\tchannels: {},", gsm.channels)?;
+                writeln!(f, "\t\tcodec: {},", gsm.codec)?;
+            }
+            FormatData::Mpeg(_) => {
+                writeln!(f, "\tformat_data: Mpeg DISPLAY UNSUPPORTED {{")?;
+            }
+            FormatData::Mace(ref mace) => {
+                writeln!(f, "\tformat_data: Mace {{")?;
+                writeln!(f, "\t\tchannels: {},", mace.channels)?;
+                writeln!(f, "\t\tcodec: {},", mace.codec)?;
             }
         };
 
@@ -234,9 +303,15 @@ impl CommonChunkParser for ChunkParser<CommonChunk> {
             b"none" | b"NONE" => CommonChunk::read_pcm_fmt(sample_size as u16, n_channels as u16),
             b"alaw" | b"ALAW" => CommonChunk::read_alaw_pcm_fmt(n_channels as u16),
             b"ulaw" | b"ULAW" => CommonChunk::read_mulaw_pcm_fmt(n_channels as u16),
-            b"fl32" | b"fl64" => CommonChunk::read_ieee_fmt(sample_size as u16, n_channels as u16),
+            // The sample size of floating point formats may not be set, use the compression type.
+            b"fl32" | b"FL32" => CommonChunk::read_ieee_fmt(32, n_channels as u16),
+            b"fl64" | b"FL64" => CommonChunk::read_ieee_fmt(64, n_channels as u16),
             b"sowt" | b"SOWT" => CommonChunk::read_sowt_fmt(sample_size as u16, n_channels as u16),
             b"twos" | b"TWOS" => CommonChunk::read_twos_fmt(sample_size as u16, n_channels as u16),
+            b"ima4" | b"IMA4" => CommonChunk::read_ima4_fmt(n_channels as u16),
+            b"GSM " | b"gsm " => CommonChunk::read_gsm_fmt(n_channels as u16),
+            b"MAC3" => CommonChunk::read_mace_fmt(CODEC_TYPE_MACE3, n_channels as u16),
+            b"MAC6" => CommonChunk::read_mace_fmt(CODEC_TYPE_MACE6, n_channels as u16),
             _ => return unsupported_error("aifc: Compression type not implemented"),
         };
 
@@ -251,9 +326,12 @@ impl CommonChunkParser for ChunkParser<CommonChunk> {
 
 /// `SoundChunk` is a required AIFF chunk, containing the audio data.
 pub struct SoundChunk {
+    /// The length of the sound data.
     pub len: u32,
+    /// The offset of the first sample frame from the start of the sound data.
     #[allow(dead_code)]
     pub offset: u32,
+    /// The alignment of the sound data in bytes.
     #[allow(dead_code)]
     pub block_size: u32,
 }
@@ -263,17 +341,240 @@ impl ParseChunk for SoundChunk {
         let offset = reader.read_be_u32()?;
         let block_size = reader.read_be_u32()?;
 
-        if offset != 0 || block_size != 0 {
-            return unsupported_error("riff: No support for AIFF block-aligned data");
-        }
+        // The sound data follows the offset and block size fields, and is preceeded by offset
+        // bytes of padding to align the first sample frame to the block size.
+        let len = match len.checked_sub(8).and_then(|len| len.checked_sub(offset)) {
+            Some(len) => len,
+            None => return decode_error("aiff: malformed ssnd chunk"),
+        };
+
+        reader.ignore_bytes(u64::from(offset))?;
 
         Ok(SoundChunk { len, offset, block_size })
     }
 }
 
+/// Read a Pascal-style string, padded to an even number of bytes including the count byte.
+fn read_pstring<B: ReadBytes>(reader: &mut B) -> Result<String> {
+    let count = reader.read_u8()?;
+    let buf = reader.read_boxed_slice_exact(usize::from(count))?;
+
+    if count % 2 == 0 {
+        reader.read_u8()?;
+    }
+
+    Ok(text_string(&buf))
+}
+
+/// Decode a text field, ignoring any trailing NUL padding or whitespace.
+fn text_string(buf: &[u8]) -> String {
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).trim_end().to_string()
+}
+
+/// A marker in a `MarkerChunk`.
+pub struct Marker {
+    /// The marker identifier. Must be positive.
+    pub id: i16,
+    /// The position of the marker in sample frames.
+    pub position: u32,
+    pub name: String,
+}
+
+/// `MarkerChunk` contains markers pointing to positions in the sound data.
+pub struct MarkerChunk {
+    pub markers: Vec<Marker>,
+}
+
+impl ParseChunk for MarkerChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<MarkerChunk> {
+        let mut reader = ScopedStream::new(reader, u64::from(len));
+
+        let num_markers = reader.read_be_u16()?;
+
+        let mut markers = Vec::with_capacity(usize::from(num_markers));
+
+        for _ in 0..num_markers {
+            let id = reader.read_be_i16()?;
+            let position = reader.read_be_u32()?;
+            let name = read_pstring(&mut reader)?;
+
+            markers.push(Marker { id, position, name });
+        }
+
+        reader.ignore()?;
+
+        Ok(MarkerChunk { markers })
+    }
+}
+
+/// A sustain or release loop in an `InstrumentChunk`.
+pub struct InstrumentLoop {
+    /// The play mode: 0 for no looping, 1 for forward looping, or 2 for forward-backward looping.
+    pub play_mode: i16,
+    /// The marker identifier of the start of the loop.
+    pub begin_loop: i16,
+    /// The marker identifier of the end of the loop.
+    pub end_loop: i16,
+}
+
+impl InstrumentLoop {
+    fn read<B: ReadBytes>(reader: &mut B) -> Result<Self> {
+        Ok(InstrumentLoop {
+            play_mode: reader.read_be_i16()?,
+            begin_loop: reader.read_be_i16()?,
+            end_loop: reader.read_be_i16()?,
+        })
+    }
+}
+
+/// `InstrumentChunk` contains the parameters to play the sound as a sampled musical instrument.
+pub struct InstrumentChunk {
+    /// The MIDI note that plays the sound at its original pitch.
+    pub base_note: i8,
+    /// The pitch adjustment in cents.
+    pub detune: i8,
+    pub low_note: i8,
+    pub high_note: i8,
+    pub low_velocity: i8,
+    pub high_velocity: i8,
+    /// The gain adjustment in decibels.
+    pub gain: i16,
+    pub sustain_loop: InstrumentLoop,
+    pub release_loop: InstrumentLoop,
+}
+
+impl InstrumentChunk {
+    /// Gets the instrument information as tags.
+    pub fn tags(&self) -> Vec<Tag> {
+        vec![
+            Tag::new(None, "UnshiftedNote", Value::from(self.base_note)),
+            Tag::new(None, "FineTune", Value::from(self.detune)),
+            Tag::new(None, "Gain", Value::from(self.gain)),
+            Tag::new(None, "LowNote", Value::from(self.low_note)),
+            Tag::new(None, "HighNote", Value::from(self.high_note)),
+            Tag::new(None, "LowVelocity", Value::from(self.low_velocity)),
+            Tag::new(None, "HighVelocity", Value::from(self.high_velocity)),
+        ]
+    }
+}
+
+impl ParseChunk for InstrumentChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<InstrumentChunk> {
+        if len < 20 {
+            return decode_error("aiff: malformed inst chunk");
+        }
+
+        let inst = InstrumentChunk {
+            base_note: reader.read_i8()?,
+            detune: reader.read_i8()?,
+            low_note: reader.read_i8()?,
+            high_note: reader.read_i8()?,
+            low_velocity: reader.read_i8()?,
+            high_velocity: reader.read_i8()?,
+            gain: reader.read_be_i16()?,
+            sustain_loop: InstrumentLoop::read(reader)?,
+            release_loop: InstrumentLoop::read(reader)?,
+        };
+
+        reader.ignore_bytes(u64::from(len - 20))?;
+
+        Ok(inst)
+    }
+}
+
+/// A comment in a `CommentsChunk`.
+pub struct Comment {
+    /// The identifier of the marker the comment is attached to, or 0 if none.
+    pub marker_id: i16,
+    pub text: String,
+}
+
+/// `CommentsChunk` contains comments, optionally attached to markers.
+pub struct CommentsChunk {
+    pub comments: Vec<Comment>,
+}
+
+impl ParseChunk for CommentsChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<CommentsChunk> {
+        let mut reader = ScopedStream::new(reader, u64::from(len));
+
+        let num_comments = reader.read_be_u16()?;
+
+        let mut comments = Vec::with_capacity(usize::from(num_comments));
+
+        for _ in 0..num_comments {
+            // Ignore the timestamp.
+            reader.read_be_u32()?;
+
+            let marker_id = reader.read_be_i16()?;
+            let count = reader.read_be_u16()?;
+            let buf = reader.read_boxed_slice_exact(usize::from(count))?;
+
+            // The text is padded to an even number of bytes.
+            if count % 2 == 1 {
+                reader.read_u8()?;
+            }
+
+            comments.push(Comment { marker_id, text: text_string(&buf) });
+        }
+
+        reader.ignore()?;
+
+        Ok(CommentsChunk { comments })
+    }
+}
+
+/// `TextChunk` is one of the name, author, copyright, or annotation chunks.
+pub struct TextChunk {
+    pub tag: Tag,
+}
+
+impl ParseChunk for TextChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<TextChunk> {
+        // TODO: Apply limit.
+        let buf = reader.read_boxed_slice_exact(len as usize)?;
+
+        let key = match &tag {
+            b"NAME" => Some(StandardTagKey::TrackTitle),
+            b"AUTH" => Some(StandardTagKey::Artist),
+            b"(c) " => Some(StandardTagKey::Copyright),
+            b"ANNO" => Some(StandardTagKey::Comment),
+            _ => None,
+        };
+
+        let tag = Tag::new(key, &String::from_utf8_lossy(&tag), Value::from(text_string(&buf)));
+
+        Ok(TextChunk { tag })
+    }
+}
+
+/// `Id3Chunk` contains an embedded ID3v2 tag.
+pub struct Id3Chunk {
+    pub metadata: MetadataRevision,
+}
+
+impl ParseChunk for Id3Chunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<Id3Chunk> {
+        let mut reader = ScopedStream::new(reader, u64::from(len));
+
+        let mut builder = MetadataBuilder::new();
+        id3v2::read_id3v2(&mut reader, &mut builder)?;
+
+        reader.ignore()?;
+
+        Ok(Id3Chunk { metadata: builder.metadata() })
+    }
+}
+
 pub enum RiffAiffChunks {
     Common(ChunkParser<CommonChunk>),
     Sound(ChunkParser<SoundChunk>),
+    Marker(ChunkParser<MarkerChunk>),
+    Instrument(ChunkParser<InstrumentChunk>),
+    Comments(ChunkParser<CommentsChunk>),
+    Text(ChunkParser<TextChunk>),
+    Id3(ChunkParser<Id3Chunk>),
 }
 
 macro_rules! parser {
@@ -287,6 +588,13 @@ impl ParseChunkTag for RiffAiffChunks {
         match &tag {
             b"COMM" => parser!(RiffAiffChunks::Common, CommonChunk, tag, len),
             b"SSND" => parser!(RiffAiffChunks::Sound, SoundChunk, tag, len),
+            b"MARK" => parser!(RiffAiffChunks::Marker, MarkerChunk, tag, len),
+            b"INST" => parser!(RiffAiffChunks::Instrument, InstrumentChunk, tag, len),
+            b"COMT" => parser!(RiffAiffChunks::Comments, CommentsChunk, tag, len),
+            b"NAME" | b"AUTH" | b"(c) " | b"ANNO" => {
+                parser!(RiffAiffChunks::Text, TextChunk, tag, len)
+            }
+            b"ID3 " | b"id3 " => parser!(RiffAiffChunks::Id3, Id3Chunk, tag, len),
             _ => None,
         }
     }
@@ -11,12 +11,14 @@ use symphonia_core::codecs::CodecParameters;
 use symphonia_core::errors::{seek_error, unsupported_error};
 use symphonia_core::errors::{Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::CuePoint;
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, MetadataRevision};
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_format;
 
-use log::debug;
+use log::{debug, warn};
 
 use crate::common::{
     append_data_params, append_format_params, next_packet, ByteOrder, ChunksReader, PacketInfo,
@@ -80,16 +82,16 @@ impl FormatReader for AiffReader {
         let mut riff_chunks = ChunksReader::<RiffAiffChunks>::new(riff_len, ByteOrder::BigEndian);
 
         let mut codec_params = CodecParameters::new();
-        //TODO: Chunks such as marker contain metadata, get it.
-        let metadata: MetadataLog = Default::default();
+        let mut metadata: MetadataLog = Default::default();
         let mut packet_info = PacketInfo::without_blocks(0);
 
-        loop {
+        let mut aux = AiffMetadata::default();
+
+        let (data_start_pos, data_end_pos) = loop {
             let chunk = riff_chunks.next(&mut source)?;
 
-            // The last chunk should always be a data chunk, if it is not, then the stream is
+            // The sound chunk must be found before the end of the stream, otherwise the stream is
             // unsupported.
-            // TODO: According to the spec additional chunks can be added after the sound data chunk. In fact any order can be possible.
             if chunk.is_none() {
                 return unsupported_error("aiff: missing sound chunk");
             }
@@ -119,32 +121,55 @@ impl FormatReader for AiffReader {
                 RiffAiffChunks::Sound(dat) => {
                     let data = dat.parse(&mut source)?;
 
-                    // Record the bounds of the data chunk.
-                    let data_start_pos = source.pos();
-                    let data_end_pos = data_start_pos + u64::from(data.len);
-
                     // Append Sound chunk fields to codec parameters.
                     append_data_params(&mut codec_params, data.len as u64, &packet_info);
 
-                    // Add a new track using the collected codec parameters.
-                    return Ok(AiffReader {
-                        reader: source,
-                        tracks: vec![Track::new(0, codec_params)],
-                        cues: Vec::new(),
-                        metadata,
-                        packet_info,
-                        data_start_pos,
-                        data_end_pos,
-                    });
+                    // Record the bounds of the data chunk.
+                    let data_start_pos = source.pos();
+
+                    break (data_start_pos, data_start_pos + u64::from(data.len));
                 }
+                chunk => read_metadata_chunk(&mut source, chunk, &mut aux)?,
+            }
+        };
+
+        // Metadata chunks, such as ID3 tags, may also follow the sound chunk. If the source is
+        // seekable, skip over the sound chunk to read them, and then return to the start of the
+        // sound data.
+        if source.is_seekable() {
+            if let Err(err) =
+                read_trailing_chunks(&mut source, data_end_pos, &mut riff_chunks, &mut aux)
+            {
+                warn!("aiff: failed to read chunks after the sound chunk: {}", err);
             }
+
+            source.seek(SeekFrom::Start(data_start_pos))?;
         }
+
+        for rev in aux.id3.drain(..) {
+            metadata.push(rev);
+        }
+
+        if let Some(rev) = aux.metadata() {
+            metadata.push(rev);
+        }
+
+        // Add a new track using the collected codec parameters.
+        Ok(AiffReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: aux.cues(),
+            metadata,
+            packet_info,
+            data_start_pos,
+            data_end_pos,
+        })
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
         next_packet(
             &mut self.reader,
-            &self.packet_info,
+            &mut self.packet_info,
             &self.tracks,
             self.data_start_pos,
             self.data_end_pos,
@@ -204,8 +229,9 @@ impl FormatReader for AiffReader {
         // timestamps regardless if the stream was seeked or not.
         let actual_ts = self.packet_info.get_actual_ts(ts);
 
-        // Calculate the absolute byte offset of the desired audio frame.
-        let seek_pos = self.data_start_pos + (actual_ts * self.packet_info.block_size);
+        // Calculate the absolute byte offset of the block starting with the desired audio frame.
+        let seek_pos = self.data_start_pos
+            + (actual_ts / self.packet_info.frames_per_block * self.packet_info.block_size);
 
         // If the reader supports seeking we can seek directly to the frame's offset wherever it may
         // be.
@@ -233,3 +259,269 @@ impl FormatReader for AiffReader {
         self.reader
     }
 }
+
+/// Marker, instrument, comment, text, and ID3 metadata.
+#[derive(Default)]
+struct AiffMetadata {
+    markers: Vec<Marker>,
+    inst: Option<InstrumentChunk>,
+    comments: Vec<Comment>,
+    text: Vec<Tag>,
+    id3: Vec<MetadataRevision>,
+}
+
+impl AiffMetadata {
+    /// Gets the text, comment, and instrument metadata as a metadata revision, if any was read.
+    fn metadata(&self) -> Option<MetadataRevision> {
+        // Comments attached to a marker are added to the marker's cue instead.
+        let comments = self
+            .comments
+            .iter()
+            .filter(|comment| comment.marker_id <= 0 || !self.has_marker(comment.marker_id))
+            .map(|comment| {
+                Tag::new(Some(StandardTagKey::Comment), "COMT", Value::from(comment.text.clone()))
+            });
+
+        let inst_tags = self.inst.iter().flat_map(|inst| inst.tags());
+
+        let mut tags = self.text.iter().cloned().chain(comments).chain(inst_tags).peekable();
+
+        tags.peek()?;
+
+        let mut builder = MetadataBuilder::new();
+
+        for tag in tags {
+            builder.add_tag(tag);
+        }
+
+        Some(builder.metadata())
+    }
+
+    fn has_marker(&self, id: i16) -> bool {
+        self.markers.iter().any(|marker| marker.id == id)
+    }
+
+    /// Gets the markers, with their associated comments and the instrument loops, as `Cue`s.
+    fn cues(&self) -> Vec<Cue> {
+        let mut cues: Vec<Cue> = self
+            .markers
+            .iter()
+            .map(|marker| {
+                let mut tags = Vec::new();
+
+                if !marker.name.is_empty() {
+                    tags.push(Tag::new(None, "Label", Value::from(marker.name.clone())));
+                }
+
+                Cue {
+                    index: marker.id as u32,
+                    start_ts: u64::from(marker.position),
+                    tags,
+                    points: Vec::new(),
+                }
+            })
+            .collect();
+
+        for comment in self.comments.iter().filter(|comment| comment.marker_id > 0) {
+            if let Some(cue) = cues.iter_mut().find(|cue| cue.index == comment.marker_id as u32) {
+                cue.tags.push(Tag::new(
+                    Some(StandardTagKey::Comment),
+                    "Comment",
+                    Value::from(comment.text.clone()),
+                ));
+            }
+        }
+
+        if let Some(inst) = &self.inst {
+            let loops = [("Sustain", &inst.sustain_loop), ("Release", &inst.release_loop)];
+
+            for (name, inst_loop) in loops.iter() {
+                let loop_type = match inst_loop.play_mode {
+                    0 => continue,
+                    1 => Value::from("Forward"),
+                    2 => Value::from("Alternating"),
+                    play_mode => Value::from(play_mode),
+                };
+
+                let begin = self.markers.iter().find(|marker| marker.id == inst_loop.begin_loop);
+                let end = self.markers.iter().find(|marker| marker.id == inst_loop.end_loop);
+
+                // A loop must be bounded by two markers.
+                let (begin, end) = match (begin, end) {
+                    (Some(begin), Some(end)) => (begin, end),
+                    _ => continue,
+                };
+
+                // The end of the loop is a point within the cue of the loop start marker.
+                let end = CuePoint {
+                    start_offset_ts: u64::from(end.position.saturating_sub(begin.position)),
+                    tags: vec![Tag::new(None, "LoopEnd", Value::Flag)],
+                };
+
+                if let Some(cue) = cues.iter_mut().find(|cue| cue.index == begin.id as u32) {
+                    cue.tags.push(Tag::new(None, "Loop", Value::from(*name)));
+                    cue.tags.push(Tag::new(None, "LoopType", loop_type));
+                    cue.points.push(end);
+                }
+            }
+        }
+
+        cues.sort_by_key(|cue| cue.start_ts);
+        cues
+    }
+}
+
+/// Read a metadata chunk.
+fn read_metadata_chunk(
+    source: &mut MediaSourceStream,
+    chunk: RiffAiffChunks,
+    aux: &mut AiffMetadata,
+) -> Result<()> {
+    match chunk {
+        RiffAiffChunks::Marker(mark) => aux.markers.extend(mark.parse(source)?.markers),
+        RiffAiffChunks::Instrument(inst) => aux.inst = Some(inst.parse(source)?),
+        RiffAiffChunks::Comments(comt) => aux.comments.extend(comt.parse(source)?.comments),
+        RiffAiffChunks::Text(text) => aux.text.push(text.parse(source)?.tag),
+        RiffAiffChunks::Id3(id3) => aux.id3.push(id3.parse(source)?.metadata),
+        // Chunks that may not follow the sound chunk are ignored.
+        RiffAiffChunks::Common(chunk) => source.ignore_bytes(u64::from(chunk.len))?,
+        RiffAiffChunks::Sound(chunk) => source.ignore_bytes(u64::from(chunk.len))?,
+    }
+    Ok(())
+}
+
+/// Read the metadata chunks following the sound chunk.
+fn read_trailing_chunks(
+    source: &mut MediaSourceStream,
+    data_end_pos: u64,
+    riff_chunks: &mut ChunksReader<RiffAiffChunks>,
+    aux: &mut AiffMetadata,
+) -> Result<()> {
+    source.seek(SeekFrom::Start(data_end_pos))?;
+
+    while let Some(chunk) = riff_chunks.next(source)? {
+        read_metadata_chunk(source, chunk, aux)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn push_chunk(buf: &mut Vec<u8>, tag: &[u8; 4], data: &[u8]) {
+    buf.extend_from_slice(tag);
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+
+    // Chunks are padded to an even length.
+    if data.len() % 2 != 0 {
+        buf.push(0);
+    }
+}
+
+#[cfg(test)]
+fn read_aiff(form: &[u8; 4], chunks: &[u8]) -> AiffReader {
+    let mut data = form.to_vec();
+    data.extend_from_slice(chunks);
+
+    let mut buf = Vec::new();
+    push_chunk(&mut buf, b"FORM", &data);
+
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+    AiffReader::try_new(source, &Default::default()).unwrap()
+}
+
+#[test]
+fn test_aifc_sound_offset() {
+    use symphonia_core::codecs::CODEC_TYPE_MACE3;
+
+    // Stereo MACE 3:1 at 8 kHz, with an empty compression name.
+    let mut comm = vec![0x00, 0x02];
+    comm.extend_from_slice(&12u32.to_be_bytes());
+    comm.extend_from_slice(&[0x00, 0x08]);
+    comm.extend_from_slice(&[0x40, 0x0b, 0xfa, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    comm.extend_from_slice(b"MAC3");
+    comm.extend_from_slice(&[0x00, 0x00]);
+
+    // The sound data is aligned to an 8 byte block by 4 bytes of padding.
+    let mut ssnd = 4u32.to_be_bytes().to_vec();
+    ssnd.extend_from_slice(&8u32.to_be_bytes());
+    ssnd.extend_from_slice(&[0xee; 4]);
+    ssnd.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+    let mut chunks = Vec::new();
+    push_chunk(&mut chunks, b"COMM", &comm);
+    push_chunk(&mut chunks, b"SSND", &ssnd);
+
+    let mut reader = read_aiff(b"AIFC", &chunks);
+
+    let params = &reader.tracks()[0].codec_params;
+    assert_eq!(params.codec, CODEC_TYPE_MACE3);
+    assert_eq!(params.n_frames, Some(12));
+
+    // The padding is skipped, and is not counted as sound data.
+    let packet = reader.next_packet().unwrap();
+    assert_eq!((packet.ts(), packet.dur()), (0, 12));
+    assert_eq!(packet.buf(), &[1, 2, 3, 4, 5, 6, 7, 8][..]);
+
+    assert!(reader.next_packet().is_err());
+}
+
+#[test]
+fn test_aiff_markers_and_comments() {
+    // 16-bit mono PCM at 8 kHz.
+    let mut comm = vec![0x00, 0x01];
+    comm.extend_from_slice(&100u32.to_be_bytes());
+    comm.extend_from_slice(&[0x00, 0x10]);
+    comm.extend_from_slice(&[0x40, 0x0b, 0xfa, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    // Two markers, listed out of order, one of which is unnamed.
+    let mut mark = 2u16.to_be_bytes().to_vec();
+    mark.extend_from_slice(&2i16.to_be_bytes());
+    mark.extend_from_slice(&60u32.to_be_bytes());
+    mark.extend_from_slice(&[5, b'V', b'e', b'r', b's', b'e']);
+    mark.extend_from_slice(&1i16.to_be_bytes());
+    mark.extend_from_slice(&10u32.to_be_bytes());
+    mark.extend_from_slice(&[0, 0]);
+
+    // A comment attached to the second marker, and a comment on the whole file.
+    let mut comt = 2u16.to_be_bytes().to_vec();
+    comt.extend_from_slice(&0u32.to_be_bytes());
+    comt.extend_from_slice(&2i16.to_be_bytes());
+    comt.extend_from_slice(&[0x00, 0x05, b'L', b'o', b'u', b'd', b'!', 0x00]);
+    comt.extend_from_slice(&0u32.to_be_bytes());
+    comt.extend_from_slice(&0i16.to_be_bytes());
+    comt.extend_from_slice(&[0x00, 0x04, b'D', b'e', b'm', b'o']);
+
+    let mut ssnd = vec![0; 8];
+    ssnd.extend_from_slice(&[0; 200]);
+
+    let mut chunks = Vec::new();
+    push_chunk(&mut chunks, b"COMM", &comm);
+    push_chunk(&mut chunks, b"MARK", &mark);
+    push_chunk(&mut chunks, b"SSND", &ssnd);
+    push_chunk(&mut chunks, b"COMT", &comt);
+
+    let mut reader = read_aiff(b"AIFF", &chunks);
+
+    // The markers are sorted by position, and the comment follows its marker.
+    let cues = reader.cues();
+    assert_eq!(cues.len(), 2);
+
+    assert_eq!((cues[0].index, cues[0].start_ts), (1, 10));
+    assert!(cues[0].tags.is_empty());
+
+    assert_eq!((cues[1].index, cues[1].start_ts), (2, 60));
+    assert_eq!(cues[1].tags.len(), 2);
+    assert_eq!(cues[1].tags[0].key, "Label");
+    assert_eq!(cues[1].tags[0].value.to_string(), "Verse");
+    assert_eq!(cues[1].tags[1].std_key, Some(StandardTagKey::Comment));
+    assert_eq!(cues[1].tags[1].value.to_string(), "Loud!");
+
+    // The comment that is not attached to a marker is a tag of the stream. It is read from after
+    // the sound chunk.
+    let metadata = reader.metadata();
+    let tags = metadata.current().unwrap().tags();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].std_key, Some(StandardTagKey::Comment));
+    assert_eq!(tags[0].value.to_string(), "Demo");
+}
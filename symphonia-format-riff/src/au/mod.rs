@@ -0,0 +1,403 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::convert::TryFrom;
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_ADPCM_G722, CODEC_TYPE_ADPCM_G726LE};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F64BE};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S24BE};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_S32BE, CODEC_TYPE_PCM_S8};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::debug;
+
+use crate::common::{
+    append_data_params, append_format_params, next_packet, try_channel_count_to_mask, FormatALaw,
+    FormatAdpcm, FormatData, FormatIeeeFloat, FormatMuLaw, FormatPcm, PacketInfo,
+};
+
+/// AU files start with the ".snd" magic number.
+const AU_STREAM_MARKER: [u8; 4] = *b".snd";
+
+/// The minimum offset of the audio data, the length of the fixed header.
+const AU_HEADER_LEN: u32 = 24;
+
+/// The data size if it is unknown.
+const AU_UNKNOWN_DATA_SIZE: u32 = 0xffff_ffff;
+
+/// The maximum length of the annotation that will be read.
+const AU_MAX_ANNOTATION_LEN: u32 = 64 * 1024;
+
+/// Sun/NeXT AU format reader.
+///
+/// `AuReader` implements a demuxer for the AU (.au, .snd) format.
+pub struct AuReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    packet_info: PacketInfo,
+    data_start_pos: u64,
+    data_end_pos: u64,
+}
+
+impl QueryDescriptor for AuReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "au",
+            "Sun/NeXT AU",
+            &["au", "snd"],
+            &["audio/basic", "audio/x-au"],
+            &[b".snd"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Gets the format data for an AU encoding.
+fn read_encoding(encoding: u32, n_channels: u16) -> Result<FormatData> {
+    let channels = try_channel_count_to_mask(n_channels)?;
+
+    let pcm =
+        |bits_per_sample, codec| FormatData::Pcm(FormatPcm { bits_per_sample, channels, codec });
+
+    // G.721 and G.723 are G.726 ADPCM at 32, 24, and 40 kbps. Code words are packed starting
+    // from the least-significant bit of each byte.
+    let g726 = |bits_per_sample| {
+        FormatData::Adpcm(FormatAdpcm { bits_per_sample, channels, codec: CODEC_TYPE_ADPCM_G726LE })
+    };
+
+    let format_data = match encoding {
+        1 => FormatData::MuLaw(FormatMuLaw { channels, codec: CODEC_TYPE_PCM_MULAW }),
+        2 => pcm(8, CODEC_TYPE_PCM_S8),
+        3 => pcm(16, CODEC_TYPE_PCM_S16BE),
+        4 => pcm(24, CODEC_TYPE_PCM_S24BE),
+        5 => pcm(32, CODEC_TYPE_PCM_S32BE),
+        6 => FormatData::IeeeFloat(FormatIeeeFloat { channels, codec: CODEC_TYPE_PCM_F32BE }),
+        7 => FormatData::IeeeFloat(FormatIeeeFloat { channels, codec: CODEC_TYPE_PCM_F64BE }),
+        23 => g726(4),
+        24 => FormatData::Adpcm(FormatAdpcm {
+            bits_per_sample: 4,
+            channels,
+            codec: CODEC_TYPE_ADPCM_G722,
+        }),
+        25 => g726(3),
+        26 => g726(5),
+        27 => FormatData::ALaw(FormatALaw { channels, codec: CODEC_TYPE_PCM_ALAW }),
+        _ => return unsupported_error("au: unsupported encoding"),
+    };
+
+    Ok(format_data)
+}
+
+/// Gets the packet information for the format data.
+fn packet_info(format_data: &FormatData, n_channels: u16) -> Result<PacketInfo> {
+    let packet_info = match format_data {
+        FormatData::Pcm(FormatPcm { bits_per_sample, .. }) => {
+            PacketInfo::without_blocks((bits_per_sample / 8) * n_channels)
+        }
+        FormatData::IeeeFloat(FormatIeeeFloat { codec, .. }) if *codec == CODEC_TYPE_PCM_F64BE => {
+            PacketInfo::without_blocks(8 * n_channels)
+        }
+        FormatData::IeeeFloat(_) => PacketInfo::without_blocks(4 * n_channels),
+        FormatData::Adpcm(FormatAdpcm { codec, bits_per_sample, .. })
+            if *codec == CODEC_TYPE_ADPCM_G726LE =>
+        {
+            // Samples are not byte-aligned, but 8 frames always fill a whole number of bytes.
+            PacketInfo::with_blocks(bits_per_sample * n_channels, 8)?
+        }
+        // Each byte of G.722 encodes two samples.
+        FormatData::Adpcm(_) => PacketInfo::with_blocks(n_channels, 2)?,
+        _ => PacketInfo::without_blocks(n_channels),
+    };
+
+    Ok(packet_info)
+}
+
+impl FormatReader for AuReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let marker = source.read_quad_bytes()?;
+        if marker != AU_STREAM_MARKER {
+            return unsupported_error("au: missing au stream marker");
+        }
+
+        let data_offset = source.read_be_u32()?;
+        let data_size = source.read_be_u32()?;
+        let encoding = source.read_be_u32()?;
+        let sample_rate = source.read_be_u32()?;
+        let n_channels = source.read_be_u32()?;
+
+        if data_offset < AU_HEADER_LEN {
+            return decode_error("au: invalid data offset");
+        }
+
+        if sample_rate == 0 {
+            return decode_error("au: invalid sample rate");
+        }
+
+        let n_channels = match u16::try_from(n_channels) {
+            Ok(n_channels) if n_channels > 0 => n_channels,
+            _ => return decode_error("au: invalid channel count"),
+        };
+
+        let format_data = read_encoding(encoding, n_channels)?;
+
+        // The annotation fills the remainder of the header. It is usually a short null-terminated
+        // string, so only read up-to the maximum length, and ignore the remainder.
+        let annotation_len = data_offset - AU_HEADER_LEN;
+        let text_len = annotation_len.min(AU_MAX_ANNOTATION_LEN);
+
+        let annotation = source.read_boxed_slice_exact(text_len as usize)?;
+
+        source.ignore_bytes(u64::from(annotation_len - text_len))?;
+
+        let mut metadata: MetadataLog = Default::default();
+
+        let text = annotation.split(|&b| b == 0).next().unwrap_or_default();
+        let text = String::from_utf8_lossy(text);
+        let text = text.trim();
+
+        if !text.is_empty() {
+            let mut builder = MetadataBuilder::new();
+
+            builder.add_tag(Tag::new(
+                Some(StandardTagKey::Comment),
+                "Annotation",
+                Value::from(text),
+            ));
+
+            metadata.push(builder.metadata());
+        }
+
+        let data_start_pos = source.pos();
+
+        // If the data size is unknown, the audio data continues to the end of the stream.
+        let data_end_pos = if data_size != AU_UNKNOWN_DATA_SIZE {
+            Some(data_start_pos + u64::from(data_size))
+        }
+        else {
+            source.byte_len()
+        };
+
+        let packet_info = packet_info(&format_data, n_channels)?;
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .with_max_frames_per_packet(packet_info.get_max_frames_per_packet())
+            .with_frames_per_block(packet_info.frames_per_block);
+
+        append_format_params(&mut codec_params, &format_data, sample_rate);
+
+        if let Some(data_end_pos) = data_end_pos {
+            append_data_params(&mut codec_params, data_end_pos - data_start_pos, &packet_info);
+        }
+
+        Ok(AuReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata,
+            packet_info,
+            data_start_pos,
+            data_end_pos: data_end_pos.unwrap_or(u64::MAX),
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let mut packet = next_packet(
+            &mut self.reader,
+            &mut self.packet_info,
+            &self.tracks,
+            self.data_start_pos,
+            self.data_end_pos,
+        )?;
+
+        // If the data size is unknown, the final packet is shortened to the number of whole blocks
+        // read before the end of the stream.
+        if self.data_end_pos == u64::MAX {
+            let n_blocks = packet.data.len() as u64 / self.packet_info.block_size;
+
+            if n_blocks == 0 {
+                return end_of_stream_error();
+            }
+
+            let len = (n_blocks * self.packet_info.block_size) as usize;
+
+            if len < packet.data.len() || n_blocks * self.packet_info.frames_per_block < packet.dur
+            {
+                packet.data = Box::from(&packet.data[..len]);
+                packet.dur = n_blocks * self.packet_info.frames_per_block;
+            }
+        }
+
+        Ok(packet)
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                if let Some(sample_rate) = params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to frame_ts={}", ts);
+
+        // Seek to a packet boundary such that packets have the same timestamps regardless if the
+        // stream was seeked or not.
+        let actual_ts = self.packet_info.get_actual_ts(ts);
+
+        let seek_pos = self.data_start_pos
+            + (actual_ts / self.packet_info.frames_per_block * self.packet_info.block_size);
+
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        debug!("seeked to packet_ts={} (delta={})", actual_ts, actual_ts as i64 - ts as i64);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts: ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+fn au_stream(encoding: u32, data_size: u32, annotation: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut buf = AU_STREAM_MARKER.to_vec();
+    buf.extend_from_slice(&(AU_HEADER_LEN + annotation.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&data_size.to_be_bytes());
+    buf.extend_from_slice(&encoding.to_be_bytes());
+    buf.extend_from_slice(&8000u32.to_be_bytes());
+    buf.extend_from_slice(&1u32.to_be_bytes());
+    buf.extend_from_slice(annotation);
+    buf.extend_from_slice(data);
+    buf
+}
+
+#[test]
+fn test_au_annotation() {
+    let buf = au_stream(1, 4, b"Hello, AU!\0\0", &[1, 2, 3, 4]);
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+    let mut reader = AuReader::try_new(source, &Default::default()).unwrap();
+
+    // The annotation is reported as a comment, up-to the null terminator.
+    let metadata = reader.metadata();
+    let tags = metadata.current().unwrap().tags();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].std_key, Some(StandardTagKey::Comment));
+    assert_eq!(tags[0].value.to_string(), "Hello, AU!");
+
+    assert_eq!(reader.tracks()[0].codec_params.codec, CODEC_TYPE_PCM_MULAW);
+    assert_eq!(reader.next_packet().unwrap().buf(), &[1, 2, 3, 4][..]);
+}
+
+#[test]
+fn test_au_long_annotation() {
+    let annotation = vec![b'a'; AU_MAX_ANNOTATION_LEN as usize + 100];
+    let buf = au_stream(1, 4, &annotation, &[1, 2, 3, 4]);
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+    let mut reader = AuReader::try_new(source, &Default::default()).unwrap();
+
+    // Only the maximum length of the annotation is read, and the remainder is skipped.
+    let metadata = reader.metadata();
+    let tags = metadata.current().unwrap().tags();
+    assert_eq!(tags[0].value.to_string().len(), AU_MAX_ANNOTATION_LEN as usize);
+
+    assert_eq!(reader.next_packet().unwrap().buf(), &[1, 2, 3, 4][..]);
+}
+
+#[test]
+fn test_au_unknown_data_size() {
+    // G.721 with 4 bits per sample, therefore 4 bytes for every 8 frames.
+    let buf = au_stream(23, AU_UNKNOWN_DATA_SIZE, &[0; 8], &[0x55; 13]);
+    let source = MediaSourceStream::new(
+        Box::new(ReadOnlySource::new(std::io::Cursor::new(buf))),
+        Default::default(),
+    );
+
+    let mut reader = AuReader::try_new(source, &Default::default()).unwrap();
+
+    // The length of the stream is unknown.
+    assert_eq!(reader.tracks()[0].codec_params.n_frames, None);
+
+    // The audio data continues to the end of the stream, less the partial block.
+    let packet = reader.next_packet().unwrap();
+    assert_eq!((packet.ts(), packet.dur(), packet.buf().len()), (0, 24, 12));
+
+    assert!(reader.next_packet().is_err());
+}
+
+#[test]
+fn test_au_g72x_encodings() {
+    // G.721 and G.723 are mapped to G.726 at the corresponding code size.
+    for &(encoding, bits) in [(23, 4), (25, 3), (26, 5)].iter() {
+        let buf = au_stream(encoding, 5 * bits, &[0; 8], &vec![0; 5 * bits as usize]);
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+        let reader = AuReader::try_new(source, &Default::default()).unwrap();
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.codec, CODEC_TYPE_ADPCM_G726LE);
+        assert_eq!(params.bits_per_coded_sample, Some(bits));
+        assert_eq!(params.frames_per_block, Some(8));
+        assert_eq!(params.n_frames, Some(40));
+    }
+}
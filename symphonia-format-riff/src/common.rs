@@ -7,14 +7,20 @@
 
 /// `PacketInfo` helps to simulate packetization over a number of blocks of data.
 /// In case the codec is blockless the block size equals one full audio frame in bytes.
+use std::fmt;
+use std::io::{Seek, SeekFrom};
 use std::marker::PhantomData;
 
 use symphonia_core::audio::Channels;
 use symphonia_core::codecs::CodecParameters;
 use symphonia_core::codecs::CodecType;
-use symphonia_core::errors::{decode_error, end_of_stream_error, Error, Result};
+use symphonia_core::errors::SeekErrorKind;
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, Error, Result};
 use symphonia_core::formats::prelude::*;
-use symphonia_core::io::{MediaSourceStream, ReadBytes};
+use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
+
+#[cfg(feature = "wav")]
+use symphonia_format_dca::{FrameHeader, Packing, FRAME_HEADER_LEN};
 
 use log::{debug, info};
 
@@ -31,6 +37,20 @@ const MAX_FRAMES_PER_PACKET: u64 = 1152;
 /// enumeration
 pub trait ParseChunkTag: Sized {
     fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self>;
+
+    /// Map a 16-byte chunk GUID, as used by Wave64, to the enumeration.
+    fn parse_guid(_guid: [u8; 16], _len: u64) -> Option<Self> {
+        None
+    }
+}
+
+/// The layout of a chunk header.
+#[derive(Copy, Clone)]
+pub enum ChunkHeader {
+    /// A 4-byte tag, and a 32-bit length excluding the header. Chunks are 2-byte aligned.
+    Riff,
+    /// A 16-byte GUID, and a 64-bit length including the header. Chunks are 8-byte aligned.
+    Wave64,
 }
 
 pub enum NullChunks {}
@@ -104,40 +124,107 @@ fn test_try_channel_count_to_mask() {
 /// This makes reading the actual chunk data lazy in that the  chunk is not read until the object is
 /// consumed.
 pub struct ChunksReader<T: ParseChunkTag> {
-    len: u32,
+    len: u64,
     byte_order: ByteOrder,
-    consumed: u32,
+    header: ChunkHeader,
+    consumed: u64,
+    chunk_sizes: Vec<([u8; 4], u64)>,
+    is_padded: bool,
     phantom: PhantomData<T>,
 }
 
 impl<T: ParseChunkTag> ChunksReader<T> {
     pub fn new(len: u32, byte_order: ByteOrder) -> Self {
-        ChunksReader { len, byte_order, consumed: 0, phantom: PhantomData }
+        Self::new_64(u64::from(len), byte_order)
+    }
+
+    /// Instantiate a `ChunksReader` for a parent chunk with a 64-bit length (e.g., RF64).
+    pub fn new_64(len: u64, byte_order: ByteOrder) -> Self {
+        ChunksReader {
+            len,
+            byte_order,
+            header: ChunkHeader::Riff,
+            consumed: 0,
+            chunk_sizes: Vec::new(),
+            is_padded: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Instantiate a `ChunksReader` for Wave64 chunks.
+    pub fn new_wave64(len: u64) -> Self {
+        ChunksReader { header: ChunkHeader::Wave64, ..Self::new_64(len, ByteOrder::LittleEndian) }
+    }
+
+    /// Provide a table of 64-bit chunk lengths. When a chunk with a 32-bit length of 0xffffffff is
+    /// encountered, the length of the first unused table entry with the same chunk ID is used
+    /// instead.
+    pub fn with_chunk_sizes(mut self, chunk_sizes: Vec<([u8; 4], u64)>) -> Self {
+        self.chunk_sizes = chunk_sizes;
+        self
+    }
+
+    /// Do not pad the parent chunk to the next 2-byte boundary when finishing. For example, a RIFF
+    /// list nested in a Wave64 chunk is aligned by the Wave64 chunk reader instead.
+    pub fn without_padding(mut self) -> Self {
+        self.is_padded = false;
+        self
     }
 
     pub fn next<B: ReadBytes>(&mut self, reader: &mut B) -> Result<Option<T>> {
+        let (header_len, align) = match self.header {
+            ChunkHeader::Riff => (8, 2),
+            ChunkHeader::Wave64 => (24, 8),
+        };
+
         // Loop until a chunk is recognized and returned, or the end of stream is reached.
         loop {
-            // Align to the next 2-byte boundary if not currently aligned.
-            if self.consumed & 0x1 == 1 {
-                reader.read_u8()?;
-                self.consumed += 1;
-            }
+            // The number of bytes to the next aligned boundary.
+            let padding = (align - self.consumed % align) % align;
 
             // Check if there are enough bytes for another chunk, if not, there are no more chunks.
-            if self.consumed + 8 > self.len {
+            if self.consumed + padding + header_len > self.len {
                 return Ok(None);
             }
 
-            // Read tag and len, the chunk header.
-            let tag = reader.read_quad_bytes()?;
+            // Align to the next boundary if not currently aligned.
+            reader.ignore_bytes(padding)?;
+            self.consumed += padding;
+
+            // Read the chunk header.
+            let (tag, len) = match self.header {
+                ChunkHeader::Riff => {
+                    let tag = reader.read_quad_bytes()?;
+
+                    let len = match self.byte_order {
+                        ByteOrder::LittleEndian => reader.read_u32()?,
+                        ByteOrder::BigEndian => reader.read_be_u32()?,
+                    };
+
+                    // A length of 0xffffffff may signal that the actual length is in the chunk size
+                    // table.
+                    let len = match self.chunk_sizes.iter().position(|(id, _)| *id == tag) {
+                        Some(i) if len == u32::MAX => self.chunk_sizes.remove(i).1,
+                        _ => u64::from(len),
+                    };
+
+                    (ChunkTag::Tag(tag), len)
+                }
+                ChunkHeader::Wave64 => {
+                    let mut guid = [0; 16];
+                    reader.read_buf_exact(&mut guid)?;
+
+                    // The length includes the chunk header.
+                    let len = match reader.read_u64()?.checked_sub(header_len) {
+                        Some(len) => len,
+                        None => return decode_error("riff: invalid wave64 chunk length"),
+                    };
 
-            let len = match self.byte_order {
-                ByteOrder::LittleEndian => reader.read_u32()?,
-                ByteOrder::BigEndian => reader.read_be_u32()?,
+                    (ChunkTag::Guid(guid), len)
+                }
             };
 
-            self.consumed += 8;
+            self.consumed += header_len;
 
             // Check if the ChunkReader has enough unread bytes to fully read the chunk.
             //
@@ -146,11 +233,8 @@ impl<T: ParseChunkTag> ChunksReader<T> {
             if self.len - self.consumed < len {
                 // When ffmpeg encodes wave to stdout the riff (parent) and data chunk lengths are
                 // (2^32)-1 since the size can't be known ahead of time.
-                if !(self.len == len && len == u32::MAX) {
-                    debug!(
-                        "chunk length of {} exceeds parent (list) chunk length",
-                        String::from_utf8_lossy(&tag)
-                    );
+                if !(self.len == len && len == u64::from(u32::MAX)) {
+                    debug!("chunk length of {} exceeds parent (list) chunk length", tag);
                     return decode_error("riff: chunk length exceeds parent (list) chunk length");
                 }
             }
@@ -158,17 +242,19 @@ impl<T: ParseChunkTag> ChunksReader<T> {
             // The length of the chunk has been validated, so "consume" the chunk.
             self.consumed = self.consumed.saturating_add(len);
 
-            match T::parse_tag(tag, len) {
+            let chunk = match tag {
+                // Chunks longer than 4 GiB are passed to the parser with a length of 0xffffffff.
+                ChunkTag::Tag(tag) => T::parse_tag(tag, len.min(u64::from(u32::MAX)) as u32),
+                ChunkTag::Guid(guid) => T::parse_guid(guid, len),
+            };
+
+            match chunk {
                 Some(chunk) => return Ok(Some(chunk)),
                 None => {
                     // As per the RIFF spec, unknown chunks are to be ignored.
-                    info!(
-                        "ignoring unknown chunk: tag={}, len={}.",
-                        String::from_utf8_lossy(&tag),
-                        len
-                    );
+                    info!("ignoring unknown chunk: tag={}, len={}.", tag, len);
 
-                    reader.ignore_bytes(u64::from(len))?
+                    reader.ignore_bytes(len)?
                 }
             }
         }
@@ -177,12 +263,12 @@ impl<T: ParseChunkTag> ChunksReader<T> {
         // If data is remaining in this chunk, skip it.
         if self.consumed < self.len {
             let remaining = self.len - self.consumed;
-            reader.ignore_bytes(u64::from(remaining))?;
+            reader.ignore_bytes(remaining)?;
             self.consumed += remaining;
         }
 
         // Pad the chunk to the next 2-byte boundary.
-        if self.len & 0x1 == 1 {
+        if self.is_padded && self.len & 0x1 == 1 {
             reader.read_u8()?;
         }
 
@@ -190,6 +276,21 @@ impl<T: ParseChunkTag> ChunksReader<T> {
     }
 }
 
+/// A chunk identifier.
+enum ChunkTag {
+    Tag([u8; 4]),
+    Guid([u8; 16]),
+}
+
+impl fmt::Display for ChunkTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkTag::Tag(tag) => write!(f, "{}", String::from_utf8_lossy(tag)),
+            ChunkTag::Guid(guid) => guid.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+        }
+    }
+}
+
 /// Common trait implemented for all chunks that are parsed by a `ChunkParser`.
 pub trait ParseChunk: Sized {
     fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<Self>;
@@ -219,6 +320,9 @@ pub enum FormatData {
     Extensible(FormatExtensible),
     ALaw(FormatALaw),
     MuLaw(FormatMuLaw),
+    Gsm(FormatGsm),
+    Mpeg(FormatMpeg),
+    Mace(FormatMace),
 }
 
 pub struct FormatPcm {
@@ -275,10 +379,34 @@ pub struct FormatMuLaw {
     pub codec: CodecType,
 }
 
+pub struct FormatGsm {
+    /// Channel bitmask.
+    pub channels: Channels,
+    /// Codec type.
+    pub codec: CodecType,
+}
+
+pub struct FormatMace {
+    /// Channel bitmask.
+    pub channels: Channels,
+    /// Codec type.
+    pub codec: CodecType,
+}
+
+pub struct FormatMpeg {
+    /// Channel bitmask.
+    pub channels: Channels,
+    /// Codec type.
+    pub codec: CodecType,
+}
+
 pub struct PacketInfo {
     pub block_size: u64,
     pub frames_per_block: u64,
     pub max_blocks_per_packet: u64,
+    /// If the data is a stream of MPEG audio frames, the timestamp of the next frame. MPEG audio
+    /// frames are variable in length and are found by frame sync instead of being read in blocks.
+    mpeg_ts: Option<u64>,
 }
 
 impl PacketInfo {
@@ -290,6 +418,7 @@ impl PacketInfo {
             block_size: u64::from(block_size),
             frames_per_block,
             max_blocks_per_packet: frames_per_block.max(MAX_FRAMES_PER_PACKET) / frames_per_block,
+            mpeg_ts: None,
         })
     }
 
@@ -298,9 +427,36 @@ impl PacketInfo {
             block_size: u64::from(frame_len),
             frames_per_block: 1,
             max_blocks_per_packet: MAX_FRAMES_PER_PACKET,
+            mpeg_ts: None,
+        }
+    }
+
+    /// Packetize a stream of MPEG audio frames, each containing the given number of frames, by
+    /// frame sync. Each packet contains one MPEG audio frame.
+    pub fn with_mpeg_frames(frames_per_frame: u64) -> Self {
+        Self {
+            block_size: 1,
+            frames_per_block: frames_per_frame,
+            max_blocks_per_packet: 1,
+            mpeg_ts: Some(0),
+        }
+    }
+
+    /// Packetize a stream of constant length codec frames, each containing the given number of
+    /// frames. Each packet contains one codec frame.
+    pub fn with_codec_frames(frame_len: u64, frames_per_frame: u64) -> Self {
+        Self {
+            block_size: frame_len,
+            frames_per_block: frames_per_frame,
+            max_blocks_per_packet: 1,
+            mpeg_ts: None,
         }
     }
 
+    pub fn is_mpeg(&self) -> bool {
+        self.mpeg_ts.is_some()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.block_size == 0
     }
@@ -321,7 +477,7 @@ impl PacketInfo {
 
 pub fn next_packet(
     reader: &mut MediaSourceStream,
-    packet_info: &PacketInfo,
+    packet_info: &mut PacketInfo,
     tracks: &[Track],
     data_start_pos: u64,
     data_end_pos: u64,
@@ -334,6 +490,21 @@ pub fn next_packet(
         return decode_error("riff: block size is 0");
     }
 
+    if let Some(ts) = packet_info.mpeg_ts {
+        let (header, frame) = match next_mpeg_frame(reader, data_end_pos)? {
+            Some(frame) => frame,
+            None => return end_of_stream_error(),
+        };
+
+        let mut packet_buf = vec![0; frame.len as usize];
+        packet_buf[..4].copy_from_slice(&header.to_be_bytes());
+        reader.read_buf_exact(&mut packet_buf[4..])?;
+
+        packet_info.mpeg_ts = Some(ts + frame.dur);
+
+        return Ok(Packet::new_from_boxed_slice(0, ts, frame.dur, packet_buf.into_boxed_slice()));
+    }
+
     // Determine the number of complete blocks remaining in the data chunk.
     let num_blocks_left =
         if pos < data_end_pos { (data_end_pos - pos) / packet_info.block_size } else { 0 };
@@ -357,6 +528,363 @@ pub fn next_packet(
     Ok(Packet::new_from_boxed_slice(0, pts, dur, packet_buf))
 }
 
+/// Seek to the packet containing the desired timestamp, and return the timestamp of that packet.
+/// Timestamps are offset by `start_ts`, the start timestamp of the track.
+pub fn seek(
+    reader: &mut MediaSourceStream,
+    packet_info: &mut PacketInfo,
+    tracks: &[Track],
+    data_start_pos: u64,
+    data_end_pos: u64,
+    start_ts: u64,
+    to: SeekTo,
+) -> Result<SeekedTo> {
+    if tracks.is_empty() || packet_info.is_empty() {
+        return seek_error(SeekErrorKind::Unseekable);
+    }
+
+    let params = &tracks[0].codec_params;
+
+    let ts = match to {
+        // Frame timestamp given.
+        SeekTo::TimeStamp { ts, .. } => ts,
+        // Time value given, calculate frame timestamp from sample rate.
+        SeekTo::Time { time, .. } => {
+            // Use the sample rate to calculate the frame timestamp. If sample rate is not
+            // known, the seek cannot be completed.
+            if let Some(sample_rate) = params.sample_rate {
+                TimeBase::new(1, sample_rate).calc_timestamp(time)
+            }
+            else {
+                return seek_error(SeekErrorKind::Unseekable);
+            }
+        }
+    };
+
+    // A timestamp before the start of the track is clamped to the first frame.
+    let required_ts = ts;
+    let ts = ts.saturating_sub(start_ts);
+
+    // If the total number of frames in the track is known, verify the desired frame timestamp
+    // does not exceed it.
+    if let Some(n_frames) = params.n_frames {
+        if ts > n_frames {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+    }
+
+    debug!("seeking to frame_ts={}", ts);
+
+    // MPEG audio frames are found by frame sync, and can't be seeked to directly.
+    if packet_info.is_mpeg() {
+        let actual_ts = seek_mpeg(reader, packet_info, data_start_pos, data_end_pos, ts)?;
+
+        debug!("seeked to packet_ts={} (delta={})", actual_ts, actual_ts as i64 - ts as i64);
+
+        return Ok(SeekedTo { track_id: 0, actual_ts: actual_ts + start_ts, required_ts });
+    }
+
+    // RIFF streams are not internally packetized for PCM codecs. Packetization is simulated by
+    // trying to read a constant number of samples or blocks every call to next_packet. Therefore,
+    // a packet begins wherever the data stream is currently positioned. Since timestamps on
+    // packets should be deterministic, instead of seeking to the exact timestamp requested and
+    // starting the next packet there, seek to a packet boundary. In this way, packets will have
+    // the same timestamps regardless if the stream was seeked or not.
+    let actual_ts = packet_info.get_actual_ts(ts);
+
+    // Calculate the absolute byte offset of the block starting with the desired audio frame.
+    let seek_pos =
+        data_start_pos + (actual_ts / packet_info.frames_per_block * packet_info.block_size);
+
+    // If the reader supports seeking we can seek directly to the frame's offset wherever it may
+    // be.
+    if reader.is_seekable() {
+        reader.seek(SeekFrom::Start(seek_pos))?;
+    }
+    // If the reader does not support seeking, we can only emulate forward seeks by consuming
+    // bytes. If the reader has to seek backwards, return an error.
+    else {
+        let current_pos = reader.pos();
+        if seek_pos >= current_pos {
+            reader.ignore_bytes(seek_pos - current_pos)?;
+        }
+        else {
+            return seek_error(SeekErrorKind::ForwardOnly);
+        }
+    }
+
+    debug!("seeked to packet_ts={} (delta={})", actual_ts, actual_ts as i64 - ts as i64);
+
+    Ok(SeekedTo { track_id: 0, actual_ts: actual_ts + start_ts, required_ts })
+}
+
+/// Seek to the MPEG audio frame containing the frame timestamp `ts`, and return the timestamp of
+/// that MPEG audio frame.
+fn seek_mpeg(
+    reader: &mut MediaSourceStream,
+    packet_info: &mut PacketInfo,
+    data_start_pos: u64,
+    data_end_pos: u64,
+    ts: u64,
+) -> Result<u64> {
+    let mut mpeg_ts = match packet_info.mpeg_ts {
+        Some(mpeg_ts) => mpeg_ts,
+        None => return seek_error(SeekErrorKind::Unseekable),
+    };
+
+    // MPEG audio frames are variable in length. Therefore, the frame headers must be scanned from
+    // the start of the data chunk, or the current frame if seeking forward.
+    if ts < mpeg_ts {
+        if !reader.is_seekable() {
+            return seek_error(SeekErrorKind::ForwardOnly);
+        }
+
+        reader.seek(SeekFrom::Start(data_start_pos))?;
+        mpeg_ts = 0;
+    }
+
+    while let Some((_, frame)) = next_mpeg_frame(reader, data_end_pos)? {
+        // If the desired timestamp is within this frame, rewind to the start of the frame header.
+        if mpeg_ts + frame.dur > ts {
+            reader.seek_buffered_rel(-4);
+            break;
+        }
+
+        reader.ignore_bytes(frame.len - 4)?;
+        mpeg_ts += frame.dur;
+    }
+
+    packet_info.mpeg_ts = Some(mpeg_ts);
+
+    Ok(mpeg_ts)
+}
+
+/// An MPEG audio frame.
+struct MpegFrame {
+    /// The length of the frame in bytes, including the header.
+    len: u64,
+    /// The number of audio frames in the frame.
+    dur: u64,
+}
+
+impl MpegFrame {
+    /// The maximum length of an MPEG audio frame in bytes, including the header (MPEG2 layer 2 at
+    /// 160 kbps and 8 kHz, with padding).
+    const MAX_LEN: u64 = 2881;
+
+    /// Parse an MPEG audio frame header.
+    fn parse(header: u32) -> Option<MpegFrame> {
+        // Bit-rates in kbps for MPEG1 layers 1, 2, and 3, and MPEG2/2.5 layer 1, and layers 2 & 3.
+        const BIT_RATES: [[u32; 15]; 5] = [
+            [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+            [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+            [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+            [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+            [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        ];
+
+        const SAMPLE_RATES: [u32; 3] = [44_100, 48_000, 32_000];
+
+        if header >> 21 != 0x7ff {
+            return None;
+        }
+
+        // The MPEG version: 0 is MPEG2.5, 2 is MPEG2, and 3 is MPEG1.
+        let version = (header >> 19) & 0x3;
+        // The layer: 1 is layer 3, 2 is layer 2, and 3 is layer 1.
+        let layer = 4 - ((header >> 17) & 0x3);
+        let bit_rate_index = ((header >> 12) & 0xf) as usize;
+        let sample_rate_index = ((header >> 10) & 0x3) as usize;
+        let padding = u64::from((header >> 9) & 0x1);
+
+        // Reject reserved values, and free-format streams which can't be framed by the header.
+        if version == 1 || layer == 4 || bit_rate_index == 0 || bit_rate_index == 15 {
+            return None;
+        }
+        if sample_rate_index == 3 || header & 0x3 == 2 {
+            return None;
+        }
+
+        let is_mpeg1 = version == 3;
+
+        let bit_rate = match (is_mpeg1, layer) {
+            (true, _) => BIT_RATES[layer as usize - 1][bit_rate_index],
+            (false, 1) => BIT_RATES[3][bit_rate_index],
+            (false, _) => BIT_RATES[4][bit_rate_index],
+        };
+
+        let sample_rate = match version {
+            3 => SAMPLE_RATES[sample_rate_index],
+            2 => SAMPLE_RATES[sample_rate_index] / 2,
+            _ => SAMPLE_RATES[sample_rate_index] / 4,
+        };
+
+        let bytes_per_sec = u64::from(bit_rate) * 1000 / 8;
+
+        let (len, dur) = match layer {
+            1 => ((12 * 8 * bytes_per_sec / u64::from(sample_rate) + padding) * 4, 384),
+            2 => (1152 * bytes_per_sec / u64::from(sample_rate) + padding, 1152),
+            _ if is_mpeg1 => (1152 * bytes_per_sec / u64::from(sample_rate) + padding, 1152),
+            _ => (576 * bytes_per_sec / u64::from(sample_rate) + padding, 576),
+        };
+
+        Some(MpegFrame { len, dur })
+    }
+}
+
+/// Find the next MPEG audio frame by frame sync, and return its header. The reader is positioned
+/// immediately after the header.
+///
+/// Since a frame sync may also occur in the audio data, a frame is only accepted if it is followed
+/// by another frame header, or by the end of the data chunk.
+fn next_mpeg_frame(
+    reader: &mut MediaSourceStream,
+    data_end_pos: u64,
+) -> Result<Option<(u32, MpegFrame)>> {
+    let mut header = 0u32;
+    let mut num_read = 0;
+
+    // Reading the following frame header requires seeking back over the frame.
+    reader.ensure_seekback_buffer(MpegFrame::MAX_LEN as usize + 4);
+
+    while reader.pos() < data_end_pos {
+        header = (header << 8) | u32::from(reader.read_u8()?);
+        num_read += 1;
+
+        if num_read < 4 {
+            continue;
+        }
+
+        if let Some(frame) = MpegFrame::parse(header) {
+            let next_pos = reader.pos() - 4 + frame.len;
+
+            // Ignore a truncated frame at the end of the data chunk.
+            if next_pos > data_end_pos {
+                break;
+            }
+
+            // Accept the last frame of the data chunk without checking the following header.
+            if next_pos + 4 > data_end_pos {
+                return Ok(Some((header, frame)));
+            }
+
+            // Read the following header, and then rewind to immediately after this header.
+            reader.ignore_bytes(frame.len - 4)?;
+            let next_header = reader.read_be_u32()?;
+            reader.seek_buffered_rev(frame.len as usize);
+
+            if MpegFrame::parse(next_header).is_some() {
+                return Ok(Some((header, frame)));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// A DTS core frame.
+#[cfg(feature = "wav")]
+pub struct DtsFrame {
+    /// The sample rate.
+    pub sample_rate: u32,
+    /// The channels, including the low-frequency effects channel.
+    pub channels: Channels,
+    /// The number of audio frames in the frame.
+    pub dur: u64,
+}
+
+#[cfg(feature = "wav")]
+impl DtsFrame {
+    /// Parse a DTS core frame header in the 16-bit or 14-bit little-endian word packing.
+    fn parse(buf: &[u8]) -> Option<DtsFrame> {
+        // A WAVE file is little-endian, therefore the big-endian packings are not expected.
+        let packing = match Packing::detect(buf)? {
+            packing @ Packing::Le16 | packing @ Packing::Le14 => packing,
+            _ => return None,
+        };
+
+        let header = FrameHeader::parse(&packing.unpack(buf)).ok()?;
+
+        // User-defined channel arrangements are not supported.
+        let channels = header.channels()?;
+
+        Some(DtsFrame {
+            sample_rate: header.sample_rate,
+            channels,
+            dur: header.n_samples() as u64,
+        })
+    }
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn test_dts_frame_parse() {
+    let five_one = Channels::FRONT_LEFT
+        | Channels::FRONT_CENTRE
+        | Channels::FRONT_RIGHT
+        | Channels::REAR_LEFT
+        | Channels::REAR_RIGHT
+        | Channels::LFE1;
+
+    // 14-bit little-endian packing, 44.1 kHz, 5.1 channels.
+    let frame = DtsFrame::parse(&[
+        0xff, 0x1f, 0x00, 0xe8, 0xf0, 0x07, 0x6f, 0xfc, 0x98, 0xfc, 0x00, 0x1e, 0x10, 0x09, 0x82,
+        0xe0,
+    ])
+    .unwrap();
+
+    assert_eq!(frame.sample_rate, 44100);
+    assert_eq!(frame.channels, five_one);
+    assert_eq!(frame.dur, 512);
+
+    // 16-bit little-endian packing, 48 kHz, 5.1 channels.
+    let frame = DtsFrame::parse(&[
+        0xfe, 0x7f, 0x01, 0x80, 0x3c, 0xfc, 0xf2, 0x3f, 0xe0, 0x75, 0x44, 0x02, 0x82, 0x20, 0xfd,
+        0x3c,
+    ])
+    .unwrap();
+
+    assert_eq!(frame.sample_rate, 48000);
+    assert_eq!(frame.channels, five_one);
+    assert_eq!(frame.dur, 512);
+
+    // 16-bit PCM audio.
+    assert!(DtsFrame::parse(&[0; 16]).is_none());
+}
+
+/// Detect a stream of DTS core frames stored in a data chunk as 16-bit stereo PCM, as found on
+/// DTS audio CDs. Each DTS core frame spans the same duration as the PCM audio it replaces. The
+/// reader is returned to its original position.
+#[cfg(feature = "wav")]
+pub fn detect_dts(reader: &mut MediaSourceStream, data_len: u64) -> Option<DtsFrame> {
+    let start_pos = reader.pos();
+
+    let mut buf = [0; FRAME_HEADER_LEN];
+
+    let mut read_frame = |reader: &mut MediaSourceStream| -> Option<DtsFrame> {
+        reader.read_buf_exact(&mut buf).ok()?;
+        DtsFrame::parse(&buf)
+    };
+
+    let frame = read_frame(reader).and_then(|frame| {
+        // PCM audio may start with a sync word by chance, therefore, if the data chunk is long
+        // enough, check that the next frame immediately follows.
+        let frame_len = 4 * frame.dur;
+
+        if data_len < frame_len + FRAME_HEADER_LEN as u64 {
+            return Some(frame);
+        }
+
+        reader.ignore_bytes(frame_len - FRAME_HEADER_LEN as u64).ok()?;
+
+        read_frame(reader).map(|_| frame)
+    });
+
+    reader.seek_buffered(start_pos);
+
+    frame
+}
+
 /// TODO: format here refers to format chunk in Wave terminology, but the data being handled here is generic - find a better name, or combine with append_data_params
 pub fn append_format_params(
     codec_params: &mut CodecParameters,
@@ -374,7 +902,10 @@ pub fn append_format_params(
                 .with_channels(pcm.channels);
         }
         FormatData::Adpcm(adpcm) => {
-            codec_params.for_codec(adpcm.codec).with_channels(adpcm.channels);
+            codec_params
+                .for_codec(adpcm.codec)
+                .with_bits_per_coded_sample(u32::from(adpcm.bits_per_sample))
+                .with_channels(adpcm.channels);
         }
         FormatData::IeeeFloat(ieee) => {
             codec_params.for_codec(ieee.codec).with_channels(ieee.channels);
@@ -392,6 +923,15 @@ pub fn append_format_params(
         FormatData::MuLaw(mulaw) => {
             codec_params.for_codec(mulaw.codec).with_channels(mulaw.channels);
         }
+        FormatData::Gsm(gsm) => {
+            codec_params.for_codec(gsm.codec).with_channels(gsm.channels);
+        }
+        FormatData::Mpeg(mpeg) => {
+            codec_params.for_codec(mpeg.codec).with_channels(mpeg.channels);
+        }
+        FormatData::Mace(mace) => {
+            codec_params.for_codec(mace.codec).with_channels(mace.channels);
+        }
     }
 }
 
@@ -401,9 +941,50 @@ pub fn append_data_params(
     data_len: u64,
     packet_info: &PacketInfo,
 ) {
-    if !packet_info.is_empty() {
+    // The number of frames in a stream of MPEG audio frames can't be determined from its length.
+    if !packet_info.is_empty() && !packet_info.is_mpeg() {
         //let n_frames = packet_info.get_frames(u64::from(data.len));
         let n_frames = packet_info.get_frames(data_len);
         codec_params.with_n_frames(n_frames);
     }
 }
+
+#[test]
+fn test_next_mpeg_frame_sync_in_payload() {
+    // MPEG1 layer 3 at 128 kbps and 44.1 kHz without padding is 417 bytes long.
+    const HEADER: [u8; 4] = [0xff, 0xfb, 0x90, 0x00];
+
+    let mut frame = HEADER.to_vec();
+    frame.resize(417, 0);
+
+    // The end of a frame, with a frame sync in the audio data, followed by two complete frames.
+    let mut buf = vec![0x12, 0x34];
+    buf.extend_from_slice(&HEADER);
+    buf.extend_from_slice(&[0x56; 4]);
+    buf.extend_from_slice(&frame);
+    buf.extend_from_slice(&frame);
+
+    let data_end_pos = buf.len() as u64;
+
+    let mut reader =
+        MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+    // The frame sync in the audio data is skipped.
+    let (header, frame) = next_mpeg_frame(&mut reader, data_end_pos).unwrap().unwrap();
+
+    assert_eq!(header, u32::from_be_bytes(HEADER));
+    assert_eq!((frame.len, frame.dur), (417, 1152));
+    assert_eq!(reader.pos(), 14);
+
+    reader.ignore_bytes(frame.len - 4).unwrap();
+
+    // The last frame is not followed by another header.
+    let (_, frame) = next_mpeg_frame(&mut reader, data_end_pos).unwrap().unwrap();
+
+    assert_eq!(frame.len, 417);
+    assert_eq!(reader.pos(), 14 + 417);
+
+    reader.ignore_bytes(frame.len - 4).unwrap();
+
+    assert!(next_mpeg_frame(&mut reader, data_end_pos).unwrap().is_none());
+}
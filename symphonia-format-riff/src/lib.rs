@@ -18,10 +18,22 @@ mod common;
 
 #[cfg(feature = "aiff")]
 mod aiff;
+#[cfg(feature = "au")]
+mod au;
+#[cfg(feature = "voc")]
+mod voc;
 #[cfg(feature = "wav")]
 mod wave;
+#[cfg(feature = "w64")]
+mod wave64;
 
 #[cfg(feature = "aiff")]
 pub use aiff::AiffReader;
+#[cfg(feature = "au")]
+pub use au::AuReader;
+#[cfg(feature = "voc")]
+pub use voc::VocReader;
 #[cfg(feature = "wav")]
-pub use wave::WavReader;
+pub use wave::{WavReader, WavReaderOptions};
+#[cfg(feature = "w64")]
+pub use wave64::W64Reader;
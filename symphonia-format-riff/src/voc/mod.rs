@@ -0,0 +1,767 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::codecs::{CodecParameters, CodecType, CODEC_TYPE_PCM_ALAW};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_U8};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::CuePoint;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, warn};
+
+use crate::common::try_channel_count_to_mask;
+
+/// VOC files start with this signature, followed by the end-of-file character.
+const VOC_SIGNATURE: &[u8; 20] = b"Creative Voice File\x1a";
+
+/// The maximum number of frames in a packet.
+const MAX_FRAMES_PER_PACKET: u64 = 1152;
+
+/// The repeat count of a repeat block that repeats forever.
+const VOC_REPEAT_FOREVER: u16 = 0xffff;
+
+/// The audio format of a sound data block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct SoundFormat {
+    sample_rate: u32,
+    n_channels: u16,
+    codec: CodecType,
+    bits_per_sample: u16,
+}
+
+impl SoundFormat {
+    /// Gets the sound format for a VOC codec identifier.
+    fn new(sample_rate: u32, n_channels: u16, codec_id: u16) -> Result<SoundFormat> {
+        let (codec, bits_per_sample) = match codec_id {
+            0x0 => (CODEC_TYPE_PCM_U8, 8),
+            0x4 => (CODEC_TYPE_PCM_S16LE, 16),
+            0x6 => (CODEC_TYPE_PCM_ALAW, 8),
+            0x7 => (CODEC_TYPE_PCM_MULAW, 8),
+            0x1..=0x3 | 0x200 => {
+                return unsupported_error("voc: creative adpcm is not supported");
+            }
+            _ => return unsupported_error("voc: unsupported codec"),
+        };
+
+        if sample_rate == 0 || n_channels == 0 {
+            return decode_error("voc: invalid sample rate or channel count");
+        }
+
+        Ok(SoundFormat { sample_rate, n_channels, codec, bits_per_sample })
+    }
+
+    /// Gets the length of an audio frame in bytes.
+    fn frame_len(&self) -> u64 {
+        u64::from(self.bits_per_sample / 8) * u64::from(self.n_channels)
+    }
+
+    /// Gets the byte value of a silent sample.
+    fn silence(&self) -> u8 {
+        match self.codec {
+            CODEC_TYPE_PCM_U8 => 0x80,
+            CODEC_TYPE_PCM_ALAW => 0xd5,
+            CODEC_TYPE_PCM_MULAW => 0xff,
+            _ => 0,
+        }
+    }
+}
+
+/// A block of a VOC file.
+enum Block {
+    /// The terminator block, or the end of the stream.
+    Terminator,
+    /// Sound data of the given length in bytes, in the given format.
+    Sound(SoundFormat, u64),
+    /// Sound data of the given length in bytes, continuing the previous sound data block.
+    Continuation(u64),
+    /// Silence of the given number of frames at the given sample rate.
+    Silence(u64, u32),
+    /// A marker with the given identifier.
+    Marker(u16),
+    /// A text string.
+    Text(String),
+    /// The start of a repeated section, with the given repeat count.
+    RepeatStart(u16),
+    /// The end of a repeated section.
+    RepeatEnd,
+    /// Any other block.
+    Other,
+}
+
+/// The extended block sets the format of the next sound data block.
+struct Extended {
+    sample_rate: u32,
+    n_channels: u16,
+    codec_id: u16,
+}
+
+/// Reads blocks, and tracks the state needed to interpret the sound data blocks.
+#[derive(Default)]
+struct BlockReader {
+    extended: Option<Extended>,
+}
+
+impl BlockReader {
+    /// Reads the next block header, and the fields preceeding any sound data. For sound data
+    /// blocks, the reader is left positioned at the start of the sound data. Other blocks are
+    /// consumed completely.
+    fn read_block<B: ReadBytes>(&mut self, reader: &mut B) -> Result<Block> {
+        // The terminator block may be missing.
+        let block_type = match reader.read_u8() {
+            Ok(block_type) => block_type,
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(Block::Terminator);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if block_type == 0 {
+            return Ok(Block::Terminator);
+        }
+
+        let len = u64::from(reader.read_u24()?);
+
+        let block = match block_type {
+            1 => {
+                if len < 2 {
+                    return decode_error("voc: invalid sound data block");
+                }
+
+                let divisor = reader.read_u8()?;
+                let codec_id = reader.read_u8()?;
+
+                // An extended block overrides the format of this block.
+                let format = match self.extended.take() {
+                    Some(ext) => SoundFormat::new(ext.sample_rate, ext.n_channels, ext.codec_id)?,
+                    None => {
+                        let sample_rate = 1_000_000 / (256 - u32::from(divisor));
+                        SoundFormat::new(sample_rate, 1, u16::from(codec_id))?
+                    }
+                };
+
+                Block::Sound(format, len - 2)
+            }
+            2 => Block::Continuation(len),
+            3 => {
+                if len < 3 {
+                    return decode_error("voc: invalid silence block");
+                }
+
+                let n_frames = u64::from(reader.read_u16()?) + 1;
+                let divisor = reader.read_u8()?;
+
+                reader.ignore_bytes(len - 3)?;
+
+                Block::Silence(n_frames, 1_000_000 / (256 - u32::from(divisor)))
+            }
+            4 => {
+                if len < 2 {
+                    return decode_error("voc: invalid marker block");
+                }
+
+                let id = reader.read_u16()?;
+
+                reader.ignore_bytes(len - 2)?;
+
+                Block::Marker(id)
+            }
+            5 => {
+                let buf = reader.read_boxed_slice_exact(len as usize)?;
+
+                let text = buf.split(|&b| b == 0).next().unwrap_or_default();
+
+                Block::Text(String::from_utf8_lossy(text).to_string())
+            }
+            6 => {
+                if len < 2 {
+                    return decode_error("voc: invalid repeat block");
+                }
+
+                let count = reader.read_u16()?;
+
+                reader.ignore_bytes(len - 2)?;
+
+                Block::RepeatStart(count)
+            }
+            7 => {
+                reader.ignore_bytes(len)?;
+                Block::RepeatEnd
+            }
+            8 => {
+                if len < 4 {
+                    return decode_error("voc: invalid extended block");
+                }
+
+                let time_constant = u32::from(reader.read_u16()?);
+                let codec_id = u16::from(reader.read_u8()?);
+                let n_channels = u16::from(reader.read_u8()?) + 1;
+
+                reader.ignore_bytes(len - 4)?;
+
+                // The time constant is rounded, therefore, round the sample rate to the nearest
+                // integer.
+                let divisor = u32::from(n_channels) * (65536 - time_constant);
+                let sample_rate = (256_000_000 + divisor / 2) / divisor;
+
+                self.extended = Some(Extended { sample_rate, n_channels, codec_id });
+
+                Block::Other
+            }
+            9 => {
+                if len < 12 {
+                    return decode_error("voc: invalid sound data block");
+                }
+
+                let sample_rate = reader.read_u32()?;
+                let bits_per_sample = reader.read_u8()?;
+                let n_channels = u16::from(reader.read_u8()?);
+                let codec_id = reader.read_u16()?;
+
+                // Reserved.
+                reader.read_u32()?;
+
+                let format = SoundFormat::new(sample_rate, n_channels, codec_id)?;
+
+                if format.bits_per_sample != u16::from(bits_per_sample) {
+                    return decode_error("voc: bits per sample does not match the codec");
+                }
+
+                Block::Sound(format, len - 12)
+            }
+            _ => {
+                reader.ignore_bytes(len)?;
+                Block::Other
+            }
+        };
+
+        Ok(block)
+    }
+}
+
+/// A span of the audio stream that is either read from a sound data block, or is silence.
+#[derive(Copy, Clone, Debug)]
+struct Segment {
+    /// The timestamp of the first frame.
+    ts: u64,
+    /// The number of frames.
+    n_frames: u64,
+    /// The position of the sound data, or, if silence, the position of the following block.
+    pos: u64,
+    /// The length of the sound data in bytes, or 0 if silence.
+    len: u64,
+}
+
+/// The state of the block currently being read.
+#[derive(Default)]
+struct ReadState {
+    /// The number of bytes of sound data remaining in the current block.
+    data_left: u64,
+    /// The number of frames of silence remaining.
+    silence_left: u64,
+    /// The timestamp of the next packet.
+    ts: u64,
+    /// The sound data of the current block is being skipped because its format does not match
+    /// the track.
+    is_skipping: bool,
+}
+
+/// The contents of a VOC file, as determined by a complete scan of its blocks.
+#[derive(Default)]
+struct Index {
+    segments: Vec<Segment>,
+    cues: Vec<Cue>,
+    text: Vec<String>,
+}
+
+/// Creative Voice File (VOC) format reader.
+///
+/// `VocReader` implements a demuxer for the VOC format. The sound data and silence blocks of
+/// the file are read as packets of the format of the first sound data block. Sound data in any
+/// other format, including a different sample rate, is skipped. Markers and repeated sections are
+/// exposed as cues. Repeated sections are only played once.
+pub struct VocReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    blocks: BlockReader,
+    format: SoundFormat,
+    state: ReadState,
+    /// The sound data and silence segments, if the stream is seekable.
+    segments: Vec<Segment>,
+}
+
+impl QueryDescriptor for VocReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "voc",
+            "Creative Voice File",
+            &["voc"],
+            &["audio/x-voc"],
+            &[b"Creative Voice F"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl VocReader {
+    /// Converts a number of frames at the given sample rate to a number of frames at the track's
+    /// sample rate.
+    fn scale_frames(format: &SoundFormat, n_frames: u64, sample_rate: u32) -> u64 {
+        n_frames * u64::from(format.sample_rate) / u64::from(sample_rate)
+    }
+
+    /// Scans all the blocks following the first sound data block.
+    fn scan(
+        reader: &mut MediaSourceStream,
+        format: &SoundFormat,
+        first_block_len: u64,
+    ) -> Result<Index> {
+        let mut index = Index::default();
+        let mut blocks = BlockReader::default();
+
+        let mut ts = 0;
+        let mut pos = reader.pos();
+        let mut len = first_block_len;
+
+        // Sound data that does not match the format of the first block is skipped.
+        let mut is_skipping = false;
+
+        // The cue and timestamp of the repeated sections that have started, but not yet ended.
+        let mut repeats: Vec<(Cue, u64)> = Vec::new();
+
+        loop {
+            if len > 0 {
+                let n_frames = len / format.frame_len();
+
+                if n_frames > 0 && !is_skipping {
+                    index.segments.push(Segment { ts, n_frames, pos, len });
+                    ts += n_frames;
+                }
+
+                reader.seek(SeekFrom::Start(pos + len))?;
+                len = 0;
+            }
+
+            match blocks.read_block(reader)? {
+                Block::Terminator => break,
+                Block::Sound(block_format, block_len) => {
+                    pos = reader.pos();
+                    len = block_len;
+
+                    is_skipping = block_format != *format;
+
+                    if is_skipping {
+                        warn!("voc: skipping sound data with a different format");
+                    }
+                }
+                Block::Continuation(block_len) => {
+                    pos = reader.pos();
+                    len = block_len;
+                }
+                Block::Silence(n_frames, sample_rate) => {
+                    let n_frames = Self::scale_frames(format, n_frames, sample_rate);
+
+                    index.segments.push(Segment { ts, n_frames, pos: reader.pos(), len: 0 });
+                    ts += n_frames;
+                }
+                Block::Marker(id) => {
+                    index.cues.push(Cue {
+                        index: index.cues.len() as u32,
+                        start_ts: ts,
+                        tags: vec![Tag::new(None, "Marker", Value::from(id))],
+                        points: Vec::new(),
+                    });
+                }
+                Block::Text(text) => index.text.push(text),
+                Block::RepeatStart(count) => {
+                    let count = if count == VOC_REPEAT_FOREVER {
+                        Value::from("Forever")
+                    }
+                    else {
+                        Value::from(u32::from(count) + 1)
+                    };
+
+                    let cue = Cue {
+                        index: 0,
+                        start_ts: ts,
+                        tags: vec![
+                            Tag::new(None, "Loop", Value::Flag),
+                            Tag::new(None, "LoopCount", count),
+                        ],
+                        points: Vec::new(),
+                    };
+
+                    repeats.push((cue, ts));
+                }
+                Block::RepeatEnd => {
+                    if let Some((mut cue, start_ts)) = repeats.pop() {
+                        // The end of the repeated section is a point within the cue.
+                        cue.points.push(CuePoint {
+                            start_offset_ts: ts - start_ts,
+                            tags: vec![Tag::new(None, "LoopEnd", Value::Flag)],
+                        });
+
+                        cue.index = index.cues.len() as u32;
+                        index.cues.push(cue);
+                    }
+                }
+                Block::Other => (),
+            }
+        }
+
+        index.cues.sort_by_key(|cue| cue.start_ts);
+
+        Ok(index)
+    }
+}
+
+impl FormatReader for VocReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut signature = [0; 20];
+        source.read_buf_exact(&mut signature)?;
+
+        if signature != *VOC_SIGNATURE {
+            return unsupported_error("voc: missing voc signature");
+        }
+
+        let header_len = source.read_u16()?;
+        let version = source.read_u16()?;
+        let checksum = source.read_u16()?;
+
+        if header_len < 26 {
+            return decode_error("voc: invalid header size");
+        }
+
+        if checksum != (!version).wrapping_add(0x1234) {
+            warn!("voc: invalid header checksum");
+        }
+
+        source.ignore_bytes(u64::from(header_len) - 26)?;
+
+        let mut blocks = BlockReader::default();
+        let mut builder = MetadataBuilder::new();
+
+        // Read blocks until the first sound data block, which determines the format of the track.
+        let (format, first_block_len) = loop {
+            match blocks.read_block(&mut source)? {
+                Block::Terminator => return decode_error("voc: missing sound data block"),
+                Block::Sound(format, len) => break (format, len),
+                Block::Text(text) => {
+                    builder.add_tag(Tag::new(
+                        Some(StandardTagKey::Comment),
+                        "Text",
+                        Value::from(text),
+                    ));
+                }
+                _ => (),
+            }
+        };
+
+        debug!("{:?}", format);
+
+        let data_start_pos = source.pos();
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(format.codec)
+            .with_sample_rate(format.sample_rate)
+            .with_time_base(TimeBase::new(1, format.sample_rate))
+            .with_channels(try_channel_count_to_mask(format.n_channels)?)
+            .with_max_frames_per_packet(MAX_FRAMES_PER_PACKET);
+
+        if format.codec == CODEC_TYPE_PCM_U8 || format.codec == CODEC_TYPE_PCM_S16LE {
+            codec_params
+                .with_bits_per_sample(u32::from(format.bits_per_sample))
+                .with_bits_per_coded_sample(u32::from(format.bits_per_sample));
+        }
+
+        let mut cues = Vec::new();
+        let mut segments = Vec::new();
+
+        // If the stream is seekable, scan all blocks to find the duration, cues, and the segments
+        // needed for seeking.
+        if source.is_seekable() {
+            match Self::scan(&mut source, &format, first_block_len) {
+                Ok(index) => {
+                    let n_frames = index.segments.iter().map(|seg| seg.n_frames).sum();
+
+                    codec_params.with_n_frames(n_frames);
+
+                    for text in index.text {
+                        builder.add_tag(Tag::new(
+                            Some(StandardTagKey::Comment),
+                            "Text",
+                            Value::from(text),
+                        ));
+                    }
+
+                    cues = index.cues;
+                    segments = index.segments;
+                }
+                Err(err) => warn!("voc: failed to scan blocks: {}", err),
+            }
+
+            source.seek(SeekFrom::Start(data_start_pos))?;
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+        metadata.push(builder.metadata());
+
+        Ok(VocReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues,
+            metadata,
+            blocks,
+            format,
+            state: ReadState { data_left: first_block_len, ..Default::default() },
+            segments,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame_len = self.format.frame_len();
+
+        loop {
+            if self.state.silence_left > 0 {
+                let n_frames = self.state.silence_left.min(MAX_FRAMES_PER_PACKET);
+
+                let buf = vec![self.format.silence(); (n_frames * frame_len) as usize];
+
+                let packet = Packet::new_from_slice(0, self.state.ts, n_frames, &buf);
+
+                self.state.silence_left -= n_frames;
+                self.state.ts += n_frames;
+
+                return Ok(packet);
+            }
+
+            if self.state.data_left >= frame_len && !self.state.is_skipping {
+                let n_frames = (self.state.data_left / frame_len).min(MAX_FRAMES_PER_PACKET);
+
+                let buf = self.reader.read_boxed_slice_exact((n_frames * frame_len) as usize)?;
+
+                let packet = Packet::new_from_boxed_slice(0, self.state.ts, n_frames, buf);
+
+                self.state.data_left -= n_frames * frame_len;
+                self.state.ts += n_frames;
+
+                return Ok(packet);
+            }
+
+            // Skip a partial frame at the end of a block, or a skipped block.
+            self.reader.ignore_bytes(self.state.data_left)?;
+            self.state.data_left = 0;
+
+            match self.blocks.read_block(&mut self.reader)? {
+                Block::Terminator => return end_of_stream_error(),
+                Block::Sound(format, len) => {
+                    self.state.data_left = len;
+                    self.state.is_skipping = format != self.format;
+                }
+                Block::Continuation(len) => self.state.data_left = len,
+                Block::Silence(n_frames, sample_rate) => {
+                    self.state.silence_left =
+                        Self::scale_frames(&self.format, n_frames, sample_rate);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        if self.segments.is_empty() {
+            return seek_error(SeekErrorKind::Unseekable);
+        }
+
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                TimeBase::new(1, self.format.sample_rate).calc_timestamp(time)
+            }
+        };
+
+        if let Some(n_frames) = self.tracks[0].codec_params.n_frames {
+            if ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to frame_ts={}", ts);
+
+        // Find the segment containing the timestamp, or the last segment.
+        let seg_idx = self.segments.partition_point(|seg| seg.ts + seg.n_frames <= ts);
+        let seg = self.segments[seg_idx.min(self.segments.len() - 1)];
+
+        // Packets are read from the start of a segment, therefore, seek to the start of the packet
+        // containing the timestamp.
+        let offset = (ts.saturating_sub(seg.ts) / MAX_FRAMES_PER_PACKET) * MAX_FRAMES_PER_PACKET;
+        let offset = offset.min(seg.n_frames);
+
+        let frame_len = self.format.frame_len();
+
+        if seg.len > 0 {
+            self.reader.seek(SeekFrom::Start(seg.pos + offset * frame_len))?;
+
+            self.state.data_left = seg.len - offset * frame_len;
+            self.state.silence_left = 0;
+        }
+        else {
+            self.reader.seek(SeekFrom::Start(seg.pos))?;
+
+            self.state.data_left = 0;
+            self.state.silence_left = seg.n_frames - offset;
+        }
+
+        self.blocks = BlockReader::default();
+        self.state.is_skipping = false;
+
+        let actual_ts = seg.ts + offset;
+        self.state.ts = actual_ts;
+
+        debug!("seeked to packet_ts={} (delta={})", actual_ts, actual_ts as i64 - ts as i64);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts: ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::codecs::{CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_U8};
+    use symphonia_core::formats::FormatReader;
+    use symphonia_core::io::{BufReader, MediaSourceStream, ReadBytes};
+
+    use super::{Block, BlockReader, VocReader};
+
+    #[test]
+    fn verify_read_block() {
+        #[rustfmt::skip]
+        let buf = [
+            // Sound data, 8 kHz, 8-bit unsigned.
+            0x01, 0x04, 0x00, 0x00, 0x83, 0x00, 0x80, 0x80,
+            // Extended, 22.05 kHz stereo, 8-bit unsigned.
+            0x08, 0x04, 0x00, 0x00, 0x53, 0xe9, 0x00, 0x01,
+            // Sound data, with the format of the extended block.
+            0x01, 0x02, 0x00, 0x00, 0x00, 0x00,
+            // New sound data, 44.1 kHz stereo, 16-bit signed.
+            0x09, 0x0c, 0x00, 0x00, 0x44, 0xac, 0x00, 0x00, 0x10, 0x02, 0x04, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+            // Silence, 100 frames at 8 kHz.
+            0x03, 0x03, 0x00, 0x00, 0x63, 0x00, 0x83,
+            // Terminator.
+            0x00,
+        ];
+
+        let mut reader = BufReader::new(&buf);
+        let mut blocks = BlockReader::default();
+
+        match blocks.read_block(&mut reader).unwrap() {
+            Block::Sound(format, len) => {
+                assert_eq!(format.sample_rate, 8000);
+                assert_eq!(format.n_channels, 1);
+                assert_eq!(format.codec, CODEC_TYPE_PCM_U8);
+                assert_eq!(len, 2);
+            }
+            _ => panic!("expected a sound data block"),
+        }
+
+        reader.ignore_bytes(2).unwrap();
+
+        assert!(matches!(blocks.read_block(&mut reader).unwrap(), Block::Other));
+
+        match blocks.read_block(&mut reader).unwrap() {
+            Block::Sound(format, len) => {
+                assert_eq!(format.sample_rate, 22050);
+                assert_eq!(format.n_channels, 2);
+                assert_eq!(len, 0);
+            }
+            _ => panic!("expected a sound data block"),
+        }
+
+        match blocks.read_block(&mut reader).unwrap() {
+            Block::Sound(format, len) => {
+                assert_eq!(format.sample_rate, 44100);
+                assert_eq!(format.n_channels, 2);
+                assert_eq!(format.codec, CODEC_TYPE_PCM_S16LE);
+                assert_eq!(format.frame_len(), 4);
+                assert_eq!(len, 0);
+            }
+            _ => panic!("expected a sound data block"),
+        }
+
+        assert!(matches!(blocks.read_block(&mut reader).unwrap(), Block::Silence(100, 8000)));
+        assert!(matches!(blocks.read_block(&mut reader).unwrap(), Block::Terminator));
+    }
+
+    #[test]
+    fn verify_skip_sample_rate_change() {
+        #[rustfmt::skip]
+        let buf = [
+            // Signature, header size, version, and checksum.
+            b'C', b'r', b'e', b'a', b't', b'i', b'v', b'e', b' ', b'V', b'o', b'i', b'c', b'e',
+            b' ', b'F', b'i', b'l', b'e', 0x1a, 0x1a, 0x00, 0x0a, 0x01, 0x29, 0x11,
+            // Sound data, 8 kHz, 8-bit unsigned.
+            0x01, 0x06, 0x00, 0x00, 0x83, 0x00, 0x10, 0x11, 0x12, 0x13,
+            // Sound data, 11.1 kHz, 8-bit unsigned.
+            0x01, 0x06, 0x00, 0x00, 0xa6, 0x00, 0x20, 0x21, 0x22, 0x23,
+            // Sound data, 8 kHz, 8-bit unsigned.
+            0x01, 0x04, 0x00, 0x00, 0x83, 0x00, 0x14, 0x15,
+            // Terminator.
+            0x00,
+        ];
+
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+        let mut reader = VocReader::try_new(source, &Default::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.sample_rate, Some(8000));
+        assert_eq!(params.n_frames, Some(6));
+
+        // The sound data at a different sample rate is skipped.
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur()), (0, 4));
+        assert_eq!(packet.buf(), &[0x10, 0x11, 0x12, 0x13]);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur()), (4, 2));
+        assert_eq!(packet.buf(), &[0x14, 0x15]);
+
+        assert!(reader.next_packet().is_err());
+        assert!(reader.cues().is_empty());
+    }
+}
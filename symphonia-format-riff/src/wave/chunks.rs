@@ -5,25 +5,30 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::cmp;
 use std::fmt;
 
 use symphonia_core::audio::Channels;
 use symphonia_core::codecs::CodecParameters;
 use symphonia_core::codecs::CodecType;
 use symphonia_core::codecs::{
-    CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS, CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32LE,
-    CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE,
-    CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+    CODEC_TYPE_ADPCM_G722, CODEC_TYPE_ADPCM_G726, CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS,
+    CODEC_TYPE_ADPCM_OKI, CODEC_TYPE_GSM_MS, CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3,
+    CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_MULAW,
+    CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
 };
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
-use symphonia_core::io::{MediaSourceStream, ReadBytes};
-use symphonia_core::meta::{MetadataBuilder, MetadataRevision, Tag};
+use symphonia_core::io::ReadBytes;
+use symphonia_core::meta::VendorData;
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision, StandardTagKey, Tag, Value};
 use symphonia_metadata::riff;
 
+use log::warn;
+
 use crate::common::{
     fix_channel_mask, try_channel_count_to_mask, ByteOrder, ChunkParser, ChunksReader, FormatALaw,
-    FormatAdpcm, FormatData, FormatExtensible, FormatIeeeFloat, FormatMuLaw, FormatPcm, NullChunks,
-    PacketInfo, ParseChunk, ParseChunkTag,
+    FormatAdpcm, FormatData, FormatExtensible, FormatGsm, FormatIeeeFloat, FormatMpeg, FormatMuLaw,
+    FormatPcm, NullChunks, PacketInfo, ParseChunk, ParseChunkTag,
 };
 
 pub struct WaveFormatChunk {
@@ -341,6 +346,139 @@ impl WaveFormatChunk {
         Ok(FormatData::MuLaw(FormatMuLaw { codec: CODEC_TYPE_PCM_MULAW, channels }))
     }
 
+    fn read_g726_fmt<B: ReadBytes>(
+        reader: &mut B,
+        bits_per_sample: u16,
+        n_channels: u16,
+        len: u32,
+    ) -> Result<FormatData> {
+        // G.726 encodes each sample in 2, 3, 4, or 5 bits for a bit-rate of 16, 24, 32, or 40 kbps.
+        if bits_per_sample < 2 || bits_per_sample > 5 {
+            return decode_error("wav: bits per sample for fmt_g726 must be 2 to 5 bits");
+        }
+
+        Self::ignore_extra_data(reader, len)?;
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Adpcm(FormatAdpcm {
+            bits_per_sample,
+            channels,
+            codec: CODEC_TYPE_ADPCM_G726,
+        }))
+    }
+
+    fn read_oki_fmt<B: ReadBytes>(
+        reader: &mut B,
+        bits_per_sample: u16,
+        n_channels: u16,
+        len: u32,
+    ) -> Result<FormatData> {
+        if bits_per_sample != 4 {
+            return unsupported_error("wav: bits per sample for fmt_oki must be 4 bits");
+        }
+
+        Self::ignore_extra_data(reader, len)?;
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Adpcm(FormatAdpcm {
+            bits_per_sample,
+            channels,
+            codec: CODEC_TYPE_ADPCM_OKI,
+        }))
+    }
+
+    fn read_g722_fmt<B: ReadBytes>(
+        reader: &mut B,
+        n_channels: u16,
+        len: u32,
+    ) -> Result<FormatData> {
+        Self::ignore_extra_data(reader, len)?;
+
+        // G.722 encodes a pair of samples in each byte. Some encoders write a bits per sample of
+        // 8 instead of 4, therefore it is not validated.
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Adpcm(FormatAdpcm {
+            bits_per_sample: 4,
+            channels,
+            codec: CODEC_TYPE_ADPCM_G722,
+        }))
+    }
+
+    fn read_gsm_fmt<B: ReadBytes>(
+        reader: &mut B,
+        n_channels: u16,
+        block_align: u16,
+        len: u32,
+    ) -> Result<FormatData> {
+        // WaveFormatEx with extension data length field present and with samples per block data.
+        if len < 20 {
+            return decode_error("wav: malformed fmt_gsm chunk");
+        }
+
+        let extra_size = reader.read_u16()?;
+
+        if extra_size < 2 || u32::from(extra_size) > len - 18 {
+            return decode_error("wav: malformed fmt_gsm chunk");
+        }
+
+        let frames_per_block = reader.read_u16()?;
+
+        reader.ignore_bytes(u64::from(len - 20))?;
+
+        // Microsoft GSM packs two 160 sample GSM frames into a 65 byte block.
+        if block_align != 65 || frames_per_block != 320 {
+            return decode_error("wav: invalid block size for fmt_gsm");
+        }
+
+        if n_channels != 1 {
+            return unsupported_error("wav: only mono is supported for fmt_gsm");
+        }
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Gsm(FormatGsm { channels, codec: CODEC_TYPE_GSM_MS }))
+    }
+
+    fn read_mpeg_fmt<B: ReadBytes>(
+        reader: &mut B,
+        n_channels: u16,
+        len: u32,
+        codec: CodecType,
+    ) -> Result<FormatData> {
+        let mut codec = codec;
+
+        // For MPEG layers 1 and 2, the extension data starts with the layer of the stream.
+        if codec == CODEC_TYPE_MP2 && len >= 20 {
+            let extra_size = reader.read_u16()?;
+
+            if extra_size >= 2 {
+                codec = match reader.read_u16()? {
+                    0x1 => CODEC_TYPE_MP1,
+                    0x4 => CODEC_TYPE_MP3,
+                    _ => CODEC_TYPE_MP2,
+                };
+            }
+
+            reader.ignore_bytes(u64::from(len - if extra_size >= 2 { 20 } else { 18 }))?;
+        }
+        else {
+            Self::ignore_extra_data(reader, len)?;
+        }
+
+        if n_channels < 1 || n_channels > 2 {
+            return decode_error("wav: invalid channel count for fmt_mpeg");
+        }
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Mpeg(FormatMpeg { channels, codec }))
+    }
+
+    /// Ignore the optional extension data of a WaveFormatEx.
+    fn ignore_extra_data<B: ReadBytes>(reader: &mut B, len: u32) -> Result<()> {
+        // The extension data length field is optional, and the extension data may be truncated.
+        reader.ignore_bytes(u64::from(len - 16))?;
+        Ok(())
+    }
+
     pub(crate) fn packet_info(&self) -> Result<PacketInfo> {
         match self.format_data {
             FormatData::Adpcm(FormatAdpcm { codec, bits_per_sample, .. })
@@ -360,6 +498,28 @@ impl WaveFormatChunk {
                     + 1) as u64;
                 PacketInfo::with_blocks(self.block_align, frames_per_block)
             }
+            FormatData::Adpcm(FormatAdpcm { codec, bits_per_sample, .. })
+                if codec == CODEC_TYPE_ADPCM_G726 =>
+            {
+                // Samples are not byte-aligned, but 8 frames always fill a whole number of bytes.
+                PacketInfo::with_blocks(bits_per_sample * self.n_channels, 8)
+            }
+            FormatData::Adpcm(FormatAdpcm { codec, .. })
+                if codec == CODEC_TYPE_ADPCM_G722 || codec == CODEC_TYPE_ADPCM_OKI =>
+            {
+                // Each byte encodes two samples.
+                PacketInfo::with_blocks(self.n_channels, 2)
+            }
+            FormatData::Gsm(_) => PacketInfo::with_blocks(self.block_align, 320),
+            FormatData::Mpeg(FormatMpeg { codec, .. }) => {
+                // MPEG2 and MPEG2.5 layer 3 frames have half as many frames as MPEG1.
+                let frames_per_frame = match codec {
+                    CODEC_TYPE_MP1 => 384,
+                    CODEC_TYPE_MP3 if self.sample_rate < 32_000 => 576,
+                    _ => 1152,
+                };
+                Ok(PacketInfo::with_mpeg_frames(frames_per_frame))
+            }
             _ => Ok(PacketInfo::without_blocks(self.block_align)),
         }
     }
@@ -387,7 +547,15 @@ impl ParseChunk for WaveFormatChunk {
         const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
         const WAVE_FORMAT_ALAW: u16 = 0x0006;
         const WAVE_FORMAT_MULAW: u16 = 0x0007;
+        const WAVE_FORMAT_OKI_ADPCM: u16 = 0x0010;
         const WAVE_FORMAT_ADPCM_IMA: u16 = 0x0011;
+        const WAVE_FORMAT_DIALOGIC_OKI_ADPCM: u16 = 0x0017;
+        const WAVE_FORMAT_GSM610: u16 = 0x0031;
+        const WAVE_FORMAT_G726_ADPCM: u16 = 0x0045;
+        const WAVE_FORMAT_MPEG: u16 = 0x0050;
+        const WAVE_FORMAT_MPEGLAYER3: u16 = 0x0055;
+        const WAVE_FORMAT_APICOM_G726_ADPCM: u16 = 0x0064;
+        const WAVE_FORMAT_G722_ADPCM: u16 = 0x028f;
         const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
 
         let format_data = match format {
@@ -413,6 +581,22 @@ impl ParseChunk for WaveFormatChunk {
                 len,
                 CODEC_TYPE_ADPCM_IMA_WAV,
             ),
+            // The OKI ADPCM Formats
+            WAVE_FORMAT_OKI_ADPCM | WAVE_FORMAT_DIALOGIC_OKI_ADPCM => {
+                Self::read_oki_fmt(reader, bits_per_sample, n_channels, len)
+            }
+            // The GSM 6.10 Format
+            WAVE_FORMAT_GSM610 => Self::read_gsm_fmt(reader, n_channels, block_align, len),
+            // The G.726 ADPCM Formats
+            WAVE_FORMAT_G726_ADPCM | WAVE_FORMAT_APICOM_G726_ADPCM => {
+                Self::read_g726_fmt(reader, bits_per_sample, n_channels, len)
+            }
+            // The G.722 ADPCM Format
+            WAVE_FORMAT_G722_ADPCM => Self::read_g722_fmt(reader, n_channels, len),
+            // The MPEG Layer 1 and 2 Format
+            WAVE_FORMAT_MPEG => Self::read_mpeg_fmt(reader, n_channels, len, CODEC_TYPE_MP2),
+            // The MPEG Layer 3 Format
+            WAVE_FORMAT_MPEGLAYER3 => Self::read_mpeg_fmt(reader, n_channels, len, CODEC_TYPE_MP3),
             // Unsupported format.
             _ => return unsupported_error("wav: unsupported wave format"),
         }?;
@@ -465,6 +649,21 @@ impl fmt::Display for WaveFormatChunk {
                 writeln!(f, "\t\tchannels: {},", mulaw.channels)?;
                 writeln!(f, "\t\tcodec: {},", mulaw.codec)?;
             }
+            FormatData::Gsm(ref gsm) => {
+                writeln!(f, "\tformat_data: Gsm {{")?;
+                writeln!(f, "\t\tchannels: {},", gsm.channels)?;
+                writeln!(f, "\t\tcodec: {},", gsm.codec)?;
+            }
+            FormatData::Mpeg(ref mpeg) => {
+                writeln!(f, "\tformat_data: Mpeg {{")?;
+                writeln!(f, "\t\tchannels: {},", mpeg.channels)?;
+                writeln!(f, "\t\tcodec: {},", mpeg.codec)?;
+            }
+            FormatData::Mace(ref mace) => {
+                writeln!(f, "\tformat_data: Mace {{")?;
+                writeln!(f, "\t\tchannels: {},", mace.channels)?;
+                writeln!(f, "\t\tcodec: {},", mace.codec)?;
+            }
         };
 
         writeln!(f, "\t}}")?;
@@ -473,18 +672,20 @@ impl fmt::Display for WaveFormatChunk {
 }
 
 pub struct FactChunk {
-    pub n_frames: u32,
+    pub n_frames: u64,
 }
 
 impl ParseChunk for FactChunk {
     fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<Self> {
         // A Fact chunk is exactly 4 bytes long, though there is some mystery as to whether there
-        // can be more fields in the chunk.
-        if len != 4 {
-            return decode_error("wav: malformed fact chunk");
-        }
+        // can be more fields in the chunk. Wave64 uses an 8 byte Fact chunk.
+        let n_frames = match len {
+            4 => u64::from(reader.read_u32()?),
+            8 => reader.read_u64()?,
+            _ => return decode_error("wav: malformed fact chunk"),
+        };
 
-        Ok(FactChunk { n_frames: reader.read_u32()? })
+        Ok(FactChunk { n_frames })
     }
 }
 
@@ -496,6 +697,127 @@ impl fmt::Display for FactChunk {
     }
 }
 
+pub struct Ds64Chunk {
+    /// The 64-bit length of the RIFF chunk.
+    pub riff_len: u64,
+    /// The 64-bit length of the data chunk.
+    pub data_len: u64,
+    /// The 64-bit number of frames (replaces the fact chunk).
+    pub n_frames: u64,
+    /// The 64-bit lengths of other chunks.
+    pub chunk_sizes: Vec<([u8; 4], u64)>,
+}
+
+impl ParseChunk for Ds64Chunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<Self> {
+        // A ds64 chunk is atleast 28 bytes long, followed by the chunk size table.
+        if len < 28 {
+            return decode_error("wav: malformed ds64 chunk");
+        }
+
+        let riff_len = reader.read_u64()?;
+        let data_len = reader.read_u64()?;
+        let n_frames = reader.read_u64()?;
+        let table_len = reader.read_u32()?;
+
+        // Each entry of the chunk size table is 12 bytes long.
+        if u64::from(table_len) * 12 > u64::from(len - 28) {
+            return decode_error("wav: malformed ds64 chunk size table");
+        }
+
+        let mut chunk_sizes = Vec::with_capacity(table_len as usize);
+
+        for _ in 0..table_len {
+            chunk_sizes.push((reader.read_quad_bytes()?, reader.read_u64()?));
+        }
+
+        // Ignore any remaining bytes.
+        reader.ignore_bytes(u64::from(len - 28) - u64::from(table_len) * 12)?;
+
+        Ok(Ds64Chunk { riff_len, data_len, n_frames, chunk_sizes })
+    }
+}
+
+#[test]
+fn test_ds64_chunk_parse() {
+    use symphonia_core::io::BufReader;
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&12_000_000_100u64.to_le_bytes());
+    buf.extend_from_slice(&12_000_000_000u64.to_le_bytes());
+    buf.extend_from_slice(&6_000_000_000u64.to_le_bytes());
+    buf.extend_from_slice(&1u32.to_le_bytes());
+    buf.extend_from_slice(b"LIST");
+    buf.extend_from_slice(&5_000_000_000u64.to_le_bytes());
+
+    let ds64 = Ds64Chunk::parse(&mut BufReader::new(&buf), *b"ds64", 40).unwrap();
+
+    assert_eq!(ds64.riff_len, 12_000_000_100);
+    assert_eq!(ds64.data_len, 12_000_000_000);
+    assert_eq!(ds64.n_frames, 6_000_000_000);
+    assert_eq!(ds64.chunk_sizes, [(*b"LIST", 5_000_000_000)]);
+
+    // The chunk size table does not fit in the chunk.
+    assert!(Ds64Chunk::parse(&mut BufReader::new(&buf), *b"ds64", 39).is_err());
+    assert!(Ds64Chunk::parse(&mut BufReader::new(&buf), *b"ds64", 27).is_err());
+}
+
+#[test]
+fn test_chunks_reader_chunk_sizes() {
+    use symphonia_core::io::BufReader;
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(b"fact");
+    buf.extend_from_slice(&u32::MAX.to_le_bytes());
+    buf.extend_from_slice(&u64::MAX.to_le_bytes());
+    buf.extend_from_slice(b"data");
+    buf.extend_from_slice(&u32::MAX.to_le_bytes());
+
+    let chunk_sizes = vec![(*b"fact", 8), (*b"data", 12_000_000_000)];
+
+    let mut reader = BufReader::new(&buf);
+    let mut chunks =
+        ChunksReader::<RiffWaveChunks>::new_64(12_000_000_100, ByteOrder::LittleEndian)
+            .with_chunk_sizes(chunk_sizes);
+
+    // The 32-bit lengths are replaced by the lengths in the chunk size table.
+    match chunks.next(&mut reader).unwrap() {
+        Some(RiffWaveChunks::Fact(fact)) => {
+            assert_eq!(fact.len, 8);
+            assert_eq!(fact.parse(&mut reader).unwrap().n_frames, u64::MAX);
+        }
+        _ => panic!("expected a fact chunk"),
+    }
+
+    // Chunks longer than 4 GiB are passed to the parser with a length of 0xffffffff.
+    match chunks.next(&mut reader).unwrap() {
+        Some(RiffWaveChunks::Data(data)) => assert_eq!(data.len, u32::MAX),
+        _ => panic!("expected a data chunk"),
+    }
+
+    // The data chunk exceeds the 32-bit length of the parent chunk.
+    let mut reader = BufReader::new(&buf[16..]);
+    let mut chunks = ChunksReader::<RiffWaveChunks>::new(100, ByteOrder::LittleEndian)
+        .with_chunk_sizes(vec![(*b"data", 12_000_000_000)]);
+
+    assert!(chunks.next(&mut reader).is_err());
+}
+
+impl fmt::Display for Ds64Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Ds64Chunk {{")?;
+        writeln!(f, "\triff_len: {},", self.riff_len)?;
+        writeln!(f, "\tdata_len: {},", self.data_len)?;
+        writeln!(f, "\tn_frames: {},", self.n_frames)?;
+        writeln!(f, "\tchunk_sizes: [")?;
+        for (id, len) in self.chunk_sizes.iter() {
+            writeln!(f, "\t\t{}: {},", String::from_utf8_lossy(id), len)?;
+        }
+        writeln!(f, "\t]")?;
+        writeln!(f, "}}")
+    }
+}
+
 pub struct ListChunk {
     pub form: [u8; 4],
     pub len: u32,
@@ -542,6 +864,470 @@ impl ParseChunk for InfoChunk {
     }
 }
 
+pub struct BextChunk {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    pub origination_date: String,
+    pub origination_time: String,
+    /// The number of samples since midnight of the first sample.
+    pub time_reference: u64,
+    pub umid: [u8; 64],
+    /// Loudness value, loudness range, max. true peak level, max. momentary loudness, and max.
+    /// short-term loudness, each multiplied by 100. Only present for version 2 and above.
+    pub loudness: Option<[i16; 5]>,
+    pub coding_history: String,
+}
+
+impl BextChunk {
+    /// The maximum length of the coding history that will be read.
+    const MAX_CODING_HISTORY_LEN: u32 = 64 * 1024;
+
+    /// Read a fixed-length, null-terminated, ASCII string.
+    fn read_string<B: ReadBytes>(reader: &mut B, len: usize) -> Result<String> {
+        let buf = reader.read_boxed_slice_exact(len)?;
+        Ok(bext_string(&buf))
+    }
+
+    /// Gets the BWF metadata as tags.
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+
+        let mut add_string = |std_key, key, value: &str| {
+            if !value.is_empty() {
+                tags.push(Tag::new(std_key, key, Value::from(value)));
+            }
+        };
+
+        add_string(Some(StandardTagKey::Description), "Description", &self.description);
+        add_string(None, "Originator", &self.originator);
+        add_string(None, "OriginatorReference", &self.originator_reference);
+        add_string(Some(StandardTagKey::Date), "OriginationDate", &self.origination_date);
+        add_string(None, "OriginationTime", &self.origination_time);
+        add_string(None, "CodingHistory", &self.coding_history);
+
+        tags.push(Tag::new(None, "TimeReference", Value::from(self.time_reference)));
+
+        // The UMID is only present if it is not all zeros.
+        if self.umid.iter().any(|&b| b != 0) {
+            // A basic UMID is 32 bytes, and an extended UMID is 64 bytes.
+            let len = if self.umid[32..].iter().any(|&b| b != 0) { 64 } else { 32 };
+
+            let mut umid = String::with_capacity(2 * len);
+            for b in &self.umid[..len] {
+                umid.push_str(&format!("{:02x}", b));
+            }
+
+            tags.push(Tag::new(None, "UMID", Value::from(umid)));
+        }
+
+        if let Some(loudness) = self.loudness {
+            const KEYS: [&str; 5] = [
+                "LoudnessValue",
+                "LoudnessRange",
+                "MaxTruePeakLevel",
+                "MaxMomentaryLoudness",
+                "MaxShortTermLoudness",
+            ];
+
+            // A value of 0x7fff indicates the value was not set.
+            for (&key, &value) in KEYS.iter().zip(loudness.iter()) {
+                if value != 0x7fff {
+                    tags.push(Tag::new(None, key, Value::from(f64::from(value) / 100.0)));
+                }
+            }
+        }
+
+        tags
+    }
+}
+
+impl ParseChunk for BextChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<BextChunk> {
+        // The fixed-length fields of the Broadcast Audio Extension chunk are 602 bytes long.
+        if len < 602 {
+            return decode_error("wav: malformed bext chunk");
+        }
+
+        let description = Self::read_string(reader, 256)?;
+        let originator = Self::read_string(reader, 32)?;
+        let originator_reference = Self::read_string(reader, 32)?;
+        let origination_date = Self::read_string(reader, 10)?;
+        let origination_time = Self::read_string(reader, 8)?;
+        let time_reference = reader.read_u64()?;
+        let version = reader.read_u16()?;
+
+        let mut umid = [0; 64];
+        reader.read_buf_exact(&mut umid)?;
+
+        let mut loudness = [0; 5];
+        for value in loudness.iter_mut() {
+            *value = reader.read_u16()? as i16;
+        }
+
+        // Version 1 and below reserved the loudness fields.
+        let loudness = if version >= 2 { Some(loudness) } else { None };
+
+        reader.ignore_bytes(180)?;
+
+        // The coding history is free text that is normally a few lines long. Only read up-to the
+        // maximum length, and ignore the remainder.
+        let coding_history_len = cmp::min(len - 602, BextChunk::MAX_CODING_HISTORY_LEN);
+
+        let coding_history = Self::read_string(reader, coding_history_len as usize)?;
+
+        reader.ignore_bytes(u64::from(len - 602 - coding_history_len))?;
+
+        Ok(BextChunk {
+            description,
+            originator,
+            originator_reference,
+            origination_date,
+            origination_time,
+            time_reference,
+            umid,
+            loudness,
+            coding_history,
+        })
+    }
+}
+
+/// Convert a null-terminated ASCII string to a `String`, trimming trailing whitespace.
+fn bext_string(buf: &[u8]) -> String {
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).trim_end().to_string()
+}
+
+pub struct VendorDataChunk {
+    /// The vendor data, or `None` if the chunk exceeded the maximum length and was skipped.
+    pub vendor_data: Option<VendorData>,
+}
+
+impl VendorDataChunk {
+    /// The maximum length of a vendor data chunk that will be read. iXML and axml chunks are
+    /// usually small, but ADM metadata in an axml chunk may be a few megabytes.
+    const MAX_LEN: u32 = 16 * 1024 * 1024;
+}
+
+impl ParseChunk for VendorDataChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<VendorDataChunk> {
+        let ident = String::from_utf8_lossy(&tag).to_string();
+
+        // Truncated XML is not useful, so skip chunks exceeding the maximum length entirely.
+        if len > VendorDataChunk::MAX_LEN {
+            warn!("wav: skipping {} chunk of {} bytes", ident, len);
+            reader.ignore_bytes(u64::from(len))?;
+            return Ok(VendorDataChunk { vendor_data: None });
+        }
+
+        let data = reader.read_boxed_slice_exact(len as usize)?;
+
+        Ok(VendorDataChunk { vendor_data: Some(VendorData { ident, data }) })
+    }
+}
+
+#[test]
+fn test_bext_chunk_coding_history_limit() {
+    use symphonia_core::io::BufReader;
+
+    let mut buf = vec![0; 602];
+    buf.extend_from_slice(&[b'A'; 70_000]);
+    buf.push(0xaa);
+
+    let mut reader = BufReader::new(&buf);
+
+    let bext = BextChunk::parse(&mut reader, *b"bext", 602 + 70_000).unwrap();
+
+    // The coding history is truncated, and the remainder of the chunk is skipped.
+    assert_eq!(bext.coding_history.len(), 64 * 1024);
+    assert_eq!(reader.read_u8().unwrap(), 0xaa);
+}
+
+#[test]
+fn test_vendor_data_chunk_limit() {
+    use symphonia_core::io::BufReader;
+
+    let mut buf = vec![0; 16 * 1024 * 1024 + 1];
+    buf.push(0xaa);
+
+    // A chunk at the maximum length is read.
+    let mut reader = BufReader::new(&buf);
+
+    let chunk = VendorDataChunk::parse(&mut reader, *b"axml", 16 * 1024 * 1024).unwrap();
+
+    assert_eq!(
+        chunk.vendor_data.map(|vd| (vd.ident, vd.data.len())),
+        Some(("axml".into(), 16 * 1024 * 1024))
+    );
+
+    // A chunk exceeding the maximum length is skipped.
+    let mut reader = BufReader::new(&buf);
+
+    let chunk = VendorDataChunk::parse(&mut reader, *b"axml", 16 * 1024 * 1024 + 1).unwrap();
+
+    assert!(chunk.vendor_data.is_none());
+    assert_eq!(reader.read_u8().unwrap(), 0xaa);
+}
+
+pub struct CuePointEntry {
+    /// The unique identifier of the cue point.
+    pub id: u32,
+    /// The offset of the cue point in frames.
+    pub sample_offset: u32,
+}
+
+pub struct CueChunk {
+    pub points: Vec<CuePointEntry>,
+}
+
+impl ParseChunk for CueChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<CueChunk> {
+        if len < 4 {
+            return decode_error("wav: malformed cue chunk");
+        }
+
+        let num_points = reader.read_u32()?;
+
+        // Each cue point is 24 bytes long.
+        if u64::from(num_points) * 24 > u64::from(len - 4) {
+            return decode_error("wav: invalid number of cue points");
+        }
+
+        let mut points = Vec::with_capacity(num_points as usize);
+
+        for _ in 0..num_points {
+            let id = reader.read_u32()?;
+
+            // Ignore the play order position, and the chunk and block offsets which are only
+            // meaningful for wave lists.
+            reader.ignore_bytes(16)?;
+
+            let sample_offset = reader.read_u32()?;
+
+            points.push(CuePointEntry { id, sample_offset });
+        }
+
+        reader.ignore_bytes(u64::from(len - 4 - 24 * num_points))?;
+
+        Ok(CueChunk { points })
+    }
+}
+
+/// An associated data list (adtl) chunk. Labels, notes, and labeled text are attached to a cue
+/// point.
+pub struct AdtlChunk {
+    /// The identifier of the cue point.
+    pub cue_id: u32,
+    pub tags: Vec<Tag>,
+}
+
+impl AdtlChunk {
+    /// The maximum length of the text that will be read.
+    const MAX_TEXT_LEN: u32 = 64 * 1024;
+}
+
+impl ParseChunk for AdtlChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<AdtlChunk> {
+        // The fixed-length header of a labeled text chunk is 20 bytes long, otherwise 4 bytes.
+        let header_len = if &tag == b"ltxt" { 20 } else { 4 };
+
+        if len < header_len {
+            return decode_error("wav: malformed adtl chunk");
+        }
+
+        let cue_id = reader.read_u32()?;
+
+        let mut tags = Vec::new();
+
+        if &tag == b"ltxt" {
+            let sample_len = reader.read_u32()?;
+            let purpose = reader.read_quad_bytes()?;
+
+            // Ignore the country, language, dialect, and code page.
+            reader.ignore_bytes(8)?;
+
+            tags.push(Tag::new(None, "Length", Value::from(sample_len)));
+            tags.push(Tag::new(None, "Purpose", Value::from(String::from_utf8_lossy(&purpose))));
+        }
+
+        // Labels and notes are short, so only read up-to the maximum length, and ignore the
+        // remainder.
+        let text_len = cmp::min(len - header_len, AdtlChunk::MAX_TEXT_LEN);
+
+        let buf = reader.read_boxed_slice_exact(text_len as usize)?;
+        let text = bext_string(&buf);
+
+        reader.ignore_bytes(u64::from(len - header_len - text_len))?;
+
+        if !text.is_empty() {
+            let key = match &tag {
+                b"labl" => "Label",
+                b"note" => "Note",
+                _ => "Text",
+            };
+
+            tags.push(Tag::new(None, key, Value::from(text)));
+        }
+
+        Ok(AdtlChunk { cue_id, tags })
+    }
+}
+
+pub struct SampleLoop {
+    /// The identifier of the cue point of the loop.
+    pub cue_id: u32,
+    /// The loop type: 0 is forward, 1 is alternating, and 2 is backward.
+    pub loop_type: u32,
+    /// The first frame of the loop.
+    pub start: u32,
+    /// The last frame of the loop.
+    pub end: u32,
+    /// The fractional position of the loop end, in units of 1/2^32 of a frame.
+    pub fraction: u32,
+    /// The number of times to play the loop, or 0 for an infinite loop.
+    pub play_count: u32,
+}
+
+pub struct SmplChunk {
+    pub manufacturer: u32,
+    pub product: u32,
+    /// The duration of a frame in nanoseconds.
+    pub sample_period: u32,
+    /// The MIDI note that plays the sample at its original pitch.
+    pub midi_unity_note: u32,
+    /// The fraction of a semitone above the unity note, in units of 1/2^32 of a semitone.
+    pub midi_pitch_fraction: u32,
+    pub smpte_format: u32,
+    pub smpte_offset: u32,
+    pub loops: Vec<SampleLoop>,
+}
+
+impl SmplChunk {
+    /// Gets the sampler information as tags.
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+
+        if self.manufacturer != 0 {
+            tags.push(Tag::new(None, "Manufacturer", Value::from(self.manufacturer)));
+        }
+        if self.product != 0 {
+            tags.push(Tag::new(None, "Product", Value::from(self.product)));
+        }
+
+        tags.push(Tag::new(None, "SamplePeriod", Value::from(self.sample_period)));
+        tags.push(Tag::new(None, "MIDIUnityNote", Value::from(self.midi_unity_note)));
+        tags.push(Tag::new(None, "MIDIPitchFraction", Value::from(self.midi_pitch_fraction)));
+
+        if self.smpte_format != 0 {
+            tags.push(Tag::new(None, "SMPTEFormat", Value::from(self.smpte_format)));
+            tags.push(Tag::new(None, "SMPTEOffset", Value::from(self.smpte_offset)));
+        }
+
+        tags
+    }
+}
+
+impl ParseChunk for SmplChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<SmplChunk> {
+        // The fixed-length fields of the sampler chunk are 36 bytes long.
+        if len < 36 {
+            return decode_error("wav: malformed smpl chunk");
+        }
+
+        let manufacturer = reader.read_u32()?;
+        let product = reader.read_u32()?;
+        let sample_period = reader.read_u32()?;
+        let midi_unity_note = reader.read_u32()?;
+        let midi_pitch_fraction = reader.read_u32()?;
+        let smpte_format = reader.read_u32()?;
+        let smpte_offset = reader.read_u32()?;
+        let num_loops = reader.read_u32()?;
+        let _sampler_data_len = reader.read_u32()?;
+
+        // Each loop is 24 bytes long.
+        if u64::from(num_loops) * 24 > u64::from(len - 36) {
+            return decode_error("wav: invalid number of sample loops");
+        }
+
+        let mut loops = Vec::with_capacity(num_loops as usize);
+
+        for _ in 0..num_loops {
+            loops.push(SampleLoop {
+                cue_id: reader.read_u32()?,
+                loop_type: reader.read_u32()?,
+                start: reader.read_u32()?,
+                end: reader.read_u32()?,
+                fraction: reader.read_u32()?,
+                play_count: reader.read_u32()?,
+            });
+        }
+
+        // Ignore the sampler specific data.
+        reader.ignore_bytes(u64::from(len - 36 - 24 * num_loops))?;
+
+        Ok(SmplChunk {
+            manufacturer,
+            product,
+            sample_period,
+            midi_unity_note,
+            midi_pitch_fraction,
+            smpte_format,
+            smpte_offset,
+            loops,
+        })
+    }
+}
+
+pub struct InstChunk {
+    /// The MIDI note that plays the sample at its original pitch.
+    pub unshifted_note: u8,
+    /// The pitch adjustment in cents.
+    pub fine_tune: i8,
+    /// The gain adjustment in decibels.
+    pub gain: i8,
+    pub low_note: u8,
+    pub high_note: u8,
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+}
+
+impl InstChunk {
+    /// Gets the instrument information as tags.
+    pub fn tags(&self) -> Vec<Tag> {
+        vec![
+            Tag::new(None, "UnshiftedNote", Value::from(self.unshifted_note)),
+            Tag::new(None, "FineTune", Value::from(self.fine_tune)),
+            Tag::new(None, "Gain", Value::from(self.gain)),
+            Tag::new(None, "LowNote", Value::from(self.low_note)),
+            Tag::new(None, "HighNote", Value::from(self.high_note)),
+            Tag::new(None, "LowVelocity", Value::from(self.low_velocity)),
+            Tag::new(None, "HighVelocity", Value::from(self.high_velocity)),
+        ]
+    }
+}
+
+impl ParseChunk for InstChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<InstChunk> {
+        if len < 7 {
+            return decode_error("wav: malformed inst chunk");
+        }
+
+        let inst = InstChunk {
+            unshifted_note: reader.read_u8()?,
+            fine_tune: reader.read_i8()?,
+            gain: reader.read_i8()?,
+            low_note: reader.read_u8()?,
+            high_note: reader.read_u8()?,
+            low_velocity: reader.read_u8()?,
+            high_velocity: reader.read_u8()?,
+        };
+
+        reader.ignore_bytes(u64::from(len - 7))?;
+
+        Ok(inst)
+    }
+}
+
 pub struct DataChunk {
     pub len: u32,
 }
@@ -556,6 +1342,11 @@ pub enum RiffWaveChunks {
     Format(ChunkParser<WaveFormatChunk>),
     List(ChunkParser<ListChunk>),
     Fact(ChunkParser<FactChunk>),
+    Bext(ChunkParser<BextChunk>),
+    VendorData(ChunkParser<VendorDataChunk>),
+    Cue(ChunkParser<CueChunk>),
+    Smpl(ChunkParser<SmplChunk>),
+    Inst(ChunkParser<InstChunk>),
     Data(ChunkParser<DataChunk>),
 }
 
@@ -571,6 +1362,11 @@ impl ParseChunkTag for RiffWaveChunks {
             b"fmt " => parser!(RiffWaveChunks::Format, WaveFormatChunk, tag, len),
             b"LIST" => parser!(RiffWaveChunks::List, ListChunk, tag, len),
             b"fact" => parser!(RiffWaveChunks::Fact, FactChunk, tag, len),
+            b"bext" => parser!(RiffWaveChunks::Bext, BextChunk, tag, len),
+            b"iXML" | b"axml" => parser!(RiffWaveChunks::VendorData, VendorDataChunk, tag, len),
+            b"cue " => parser!(RiffWaveChunks::Cue, CueChunk, tag, len),
+            b"smpl" => parser!(RiffWaveChunks::Smpl, SmplChunk, tag, len),
+            b"inst" => parser!(RiffWaveChunks::Inst, InstChunk, tag, len),
             b"data" => parser!(RiffWaveChunks::Data, DataChunk, tag, len),
             _ => None,
         }
@@ -591,13 +1387,32 @@ impl ParseChunkTag for RiffInfoListChunks {
     }
 }
 
+pub enum RiffAdtlListChunks {
+    Adtl(ChunkParser<AdtlChunk>),
+}
+
+impl ParseChunkTag for RiffAdtlListChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"labl" | b"note" | b"ltxt" => parser!(RiffAdtlListChunks::Adtl, AdtlChunk, tag, len),
+            _ => None,
+        }
+    }
+}
+
 pub fn append_fact_params(codec_params: &mut CodecParameters, fact: &FactChunk) {
-    codec_params.with_n_frames(u64::from(fact.n_frames));
+    codec_params.with_n_frames(fact.n_frames);
 }
 
-pub fn read_info_chunk(source: &mut MediaSourceStream, len: u32) -> Result<MetadataRevision> {
-    let mut info_list = ChunksReader::<RiffInfoListChunks>::new(len, ByteOrder::LittleEndian);
+pub fn read_info_chunk<B: ReadBytes>(source: &mut B, len: u32) -> Result<MetadataRevision> {
+    read_info_list(source, ChunksReader::new(len, ByteOrder::LittleEndian))
+}
 
+/// Read the chunks of an INFO list using the provided `ChunksReader`.
+pub fn read_info_list<B: ReadBytes>(
+    source: &mut B,
+    mut info_list: ChunksReader<RiffInfoListChunks>,
+) -> Result<MetadataRevision> {
     let mut metadata_builder = MetadataBuilder::new();
 
     loop {
@@ -616,3 +1431,17 @@ pub fn read_info_chunk(source: &mut MediaSourceStream, len: u32) -> Result<Metad
 
     Ok(metadata_builder.metadata())
 }
+
+pub fn read_adtl_chunk<B: ReadBytes>(source: &mut B, len: u32) -> Result<Vec<AdtlChunk>> {
+    let mut adtl_list = ChunksReader::<RiffAdtlListChunks>::new(len, ByteOrder::LittleEndian);
+
+    let mut adtl = Vec::new();
+
+    while let Some(RiffAdtlListChunks::Adtl(chunk)) = adtl_list.next(source)? {
+        adtl.push(chunk.parse(source)?);
+    }
+
+    adtl_list.finish(source)?;
+
+    Ok(adtl)
+}
@@ -7,28 +7,51 @@
 
 use std::io::{Seek, SeekFrom};
 
-use symphonia_core::codecs::CodecParameters;
-use symphonia_core::errors::{seek_error, unsupported_error};
-use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DCA, CODEC_TYPE_PCM_S16LE};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::CuePoint;
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, MetadataRevision};
+use symphonia_core::meta::{Tag, Value, VendorData};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_format;
 
-use log::{debug, error};
+use log::{debug, error, warn};
 
 use crate::common::{
-    append_data_params, append_format_params, next_packet, ByteOrder, ChunksReader, PacketInfo,
+    append_data_params, append_format_params, detect_dts, next_packet, seek, ByteOrder,
+    ChunkParser, ChunksReader, PacketInfo,
 };
-mod chunks;
+pub(crate) mod chunks;
 use chunks::*;
 
 /// WAVE is actually a RIFF stream, with a "RIFF" ASCII stream marker.
 const WAVE_STREAM_MARKER: [u8; 4] = *b"RIFF";
+/// RF64 (EBU Tech 3306) is a 64-bit RIFF stream, with a "RF64" ASCII stream marker.
+const RF64_STREAM_MARKER: [u8; 4] = *b"RF64";
+/// BW64 (ITU-R BS.2088) is a 64-bit RIFF stream, with a "BW64" ASCII stream marker.
+const BW64_STREAM_MARKER: [u8; 4] = *b"BW64";
 /// A possible RIFF form is "wave".
 const WAVE_RIFF_FORM: [u8; 4] = *b"WAVE";
 
+/// WAVE specific format reader options.
+///
+/// A reader instantiated by a probe, or by `FormatReader::try_new`, uses the default options. To
+/// use other options, instantiate the reader with `WavReader::try_new_with_options`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct WavReaderOptions {
+    /// If `true`, the Broadcast WAVE `TimeReference` is used as the start timestamp of the track.
+    /// Packet timestamps, and the timestamps used to seek, are then offset by the start timestamp.
+    /// Defaults to `false`.
+    pub enable_time_reference: bool,
+    /// If `true`, a data chunk of 16-bit stereo PCM is checked for DTS core frames, as found on DTS
+    /// audio CDs. If found, the track is presented as a DTS track instead of a PCM track. Symphonia
+    /// does not provide a DTS decoder, therefore this is only useful to pass the frames through to
+    /// another decoder. Defaults to `false`.
+    pub enable_dts_detection: bool,
+}
+
 /// Waveform Audio File Format (WAV) format reader.
 ///
 /// `WavReader` implements a demuxer for the WAVE container format.
@@ -40,6 +63,7 @@ pub struct WavReader {
     packet_info: PacketInfo,
     data_start_pos: u64,
     data_end_pos: u64,
+    start_ts: u64,
 }
 
 impl QueryDescriptor for WavReader {
@@ -51,7 +75,7 @@ impl QueryDescriptor for WavReader {
                 "Waveform Audio File Format",
                 &["wav", "wave"],
                 &["audio/vnd.wave", "audio/x-wav", "audio/wav", "audio/wave"],
-                &[b"RIFF"]
+                &[b"RIFF", b"RF64", b"BW64"]
             ),
         ]
     }
@@ -61,14 +85,21 @@ impl QueryDescriptor for WavReader {
     }
 }
 
-impl FormatReader for WavReader {
-    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
-        // The RIFF marker should be present.
+impl WavReader {
+    /// Instantiates the reader with WAVE specific options.
+    pub fn try_new_with_options(
+        mut source: MediaSourceStream,
+        _options: &FormatOptions,
+        wav_options: WavReaderOptions,
+    ) -> Result<Self> {
+        // The RIFF, RF64, or BW64 marker should be present.
         let marker = source.read_quad_bytes()?;
 
-        if marker != WAVE_STREAM_MARKER {
-            return unsupported_error("wav: missing riff stream marker");
-        }
+        let is_64bit = match marker {
+            WAVE_STREAM_MARKER => false,
+            RF64_STREAM_MARKER | BW64_STREAM_MARKER => true,
+            _ => return unsupported_error("wav: missing riff stream marker"),
+        };
 
         // A Wave file is one large RIFF chunk, with the actual meta and audio data as sub-chunks.
         // Therefore, the header was the chunk ID, and the next 4 bytes is the length of the RIFF
@@ -83,14 +114,56 @@ impl FormatReader for WavReader {
             return unsupported_error("wav: riff form is not wave");
         }
 
-        let mut riff_chunks =
-            ChunksReader::<RiffWaveChunks>::new(riff_len, ByteOrder::LittleEndian);
+        // For 64-bit RIFF streams, the 32-bit lengths of the RIFF and data chunks are placeholders.
+        // The actual lengths are stored in the ds64 chunk which must immediately follow the RIFF
+        // form.
+        let ds64 = if is_64bit {
+            let tag = source.read_quad_bytes()?;
+            let len = source.read_u32()?;
+
+            if tag != *b"ds64" {
+                return decode_error("wav: missing ds64 chunk");
+            }
+
+            let ds64 = ChunkParser::<Ds64Chunk>::new(tag, len).parse(&mut source)?;
+
+            // Pad the chunk to the next 2-byte boundary.
+            if len & 0x1 == 1 {
+                source.read_u8()?;
+            }
+
+            Some(ds64)
+        }
+        else {
+            None
+        };
+
+        let mut riff_chunks = match &ds64 {
+            Some(ds64) => {
+                // The data chunk length is an implicit entry in the chunk size table.
+                let mut chunk_sizes = ds64.chunk_sizes.clone();
+                chunk_sizes.push((*b"data", ds64.data_len));
+
+                // The RIFF length is reduced by the length of the ds64 chunk which was already read.
+                // If the RIFF length is unknown (0), assume the RIFF chunk spans the entire stream.
+                let riff_len = match ds64.riff_len {
+                    0 => u64::MAX,
+                    riff_len => riff_len.saturating_sub(8 + source.pos() - 20),
+                };
+
+                ChunksReader::<RiffWaveChunks>::new_64(riff_len, ByteOrder::LittleEndian)
+                    .with_chunk_sizes(chunk_sizes)
+            }
+            None => ChunksReader::<RiffWaveChunks>::new(riff_len, ByteOrder::LittleEndian),
+        };
 
         let mut codec_params = CodecParameters::new();
         let mut metadata: MetadataLog = Default::default();
         let mut packet_info = PacketInfo::without_blocks(0);
 
-        loop {
+        let mut aux = WaveMetadata::default();
+
+        let (data_start_pos, data_end_pos) = loop {
             let chunk = riff_chunks.next(&mut source)?;
 
             // The last chunk should always be a data chunk, if it is not, then the stream is
@@ -118,54 +191,123 @@ impl FormatReader for WavReader {
                     );
                 }
                 RiffWaveChunks::Fact(fct) => {
-                    let fact = fct.parse(&mut source)?;
+                    let mut fact = fct.parse(&mut source)?;
+
+                    // For 64-bit RIFF streams, the number of frames is stored in the ds64 chunk.
+                    if let Some(ds64) = &ds64 {
+                        if fact.n_frames == u64::from(u32::MAX) {
+                            fact.n_frames = ds64.n_frames;
+                        }
+                    }
 
                     // Append Fact chunk fields to codec parameters.
                     append_fact_params(&mut codec_params, &fact);
                 }
-                RiffWaveChunks::List(lst) => {
-                    let list = lst.parse(&mut source)?;
-
-                    // Riff Lists can have many different forms, but WavReader only supports Info
-                    // lists.
-                    match &list.form {
-                        b"INFO" => metadata.push(read_info_chunk(&mut source, list.len)?),
-                        _ => list.skip(&mut source)?,
-                    }
-                }
                 RiffWaveChunks::Data(dat) => {
                     let data = dat.parse(&mut source)?;
 
+                    // For 64-bit RIFF streams, the data chunk may be longer than 4 GiB, in which case
+                    // the length is stored in the ds64 chunk.
+                    let data_len = match &ds64 {
+                        Some(ds64) if data.len == u32::MAX => ds64.data_len,
+                        _ => u64::from(data.len),
+                    };
+
+                    // DTS audio CDs store DTS core frames as if they were 16-bit stereo PCM.
+                    if wav_options.enable_dts_detection
+                        && codec_params.codec == CODEC_TYPE_PCM_S16LE
+                        && codec_params.channels.map(|channels| channels.count()) == Some(2)
+                    {
+                        if let Some(dts) = detect_dts(&mut source, data_len) {
+                            debug!("found dts core frames in data chunk");
+
+                            packet_info = PacketInfo::with_codec_frames(4 * dts.dur, dts.dur);
+
+                            codec_params
+                                .for_codec(CODEC_TYPE_DCA)
+                                .with_sample_rate(dts.sample_rate)
+                                .with_time_base(TimeBase::new(1, dts.sample_rate))
+                                .with_channels(dts.channels)
+                                .with_max_frames_per_packet(dts.dur)
+                                .with_frames_per_block(dts.dur);
+
+                            codec_params.bits_per_sample = None;
+                            codec_params.bits_per_coded_sample = None;
+                        }
+                    }
+
+                    // Append Data chunk fields to codec parameters.
+                    append_data_params(&mut codec_params, data_len, &packet_info);
+
                     // Record the bounds of the data chunk.
                     let data_start_pos = source.pos();
-                    let data_end_pos = data_start_pos + u64::from(data.len);
 
-                    // Append Data chunk fields to codec parameters.
-                    append_data_params(&mut codec_params, data.len as u64, &packet_info);
-
-                    // Add a new track using the collected codec parameters.
-                    return Ok(WavReader {
-                        reader: source,
-                        tracks: vec![Track::new(0, codec_params)],
-                        cues: Vec::new(),
-                        metadata,
-                        packet_info,
-                        data_start_pos,
-                        data_end_pos,
-                    });
+                    break (data_start_pos, data_start_pos + data_len);
                 }
+                chunk => read_metadata_chunk(&mut source, chunk, &mut metadata, &mut aux)?,
+            }
+        };
+
+        // Metadata chunks may also follow the data chunk. If the source is seekable, skip over the
+        // data chunk to read them, and then return to the start of the data chunk.
+        if source.is_seekable() {
+            if let Err(err) = read_trailing_chunks(
+                &mut source,
+                data_end_pos,
+                &mut riff_chunks,
+                &mut metadata,
+                &mut aux,
+            ) {
+                warn!("wav: failed to read chunks after the data chunk: {}", err);
             }
+
+            source.seek(SeekFrom::Start(data_start_pos))?;
+        }
+
+        // Use the BWF time reference as the start timestamp of the track, if enabled.
+        let start_ts = match &aux.bext {
+            Some(bext) if wav_options.enable_time_reference => bext.time_reference,
+            _ => 0,
+        };
+
+        codec_params.with_start_ts(start_ts);
+
+        if let Some(rev) = aux.metadata() {
+            metadata.push(rev);
         }
+
+        // Add a new track using the collected codec parameters.
+        Ok(WavReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: aux.cues(),
+            metadata,
+            packet_info,
+            data_start_pos,
+            data_end_pos,
+            start_ts,
+        })
+    }
+}
+
+impl FormatReader for WavReader {
+    fn try_new(source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        WavReader::try_new_with_options(source, options, Default::default())
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
-        next_packet(
+        let mut packet = next_packet(
             &mut self.reader,
-            &self.packet_info,
+            &mut self.packet_info,
             &self.tracks,
             self.data_start_pos,
             self.data_end_pos,
-        )
+        )?;
+
+        // Offset the timestamp by the start timestamp of the track.
+        packet.ts += self.start_ts;
+
+        Ok(packet)
     }
 
     fn metadata(&mut self) -> Metadata<'_> {
@@ -181,72 +323,334 @@ impl FormatReader for WavReader {
     }
 
     fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
-        if self.tracks.is_empty() || self.packet_info.is_empty() {
-            return seek_error(SeekErrorKind::Unseekable);
+        seek(
+            &mut self.reader,
+            &mut self.packet_info,
+            &self.tracks,
+            self.data_start_pos,
+            self.data_end_pos,
+            self.start_ts,
+            to,
+        )
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// Broadcast WAVE (BWF), cue, and sampler metadata.
+#[derive(Default)]
+struct WaveMetadata {
+    bext: Option<BextChunk>,
+    vendor_data: Vec<VendorData>,
+    cue: Option<CueChunk>,
+    adtl: Vec<AdtlChunk>,
+    smpl: Option<SmplChunk>,
+    inst: Option<InstChunk>,
+}
+
+impl WaveMetadata {
+    /// Gets the BWF and sampler metadata as a metadata revision, if any was read.
+    fn metadata(&self) -> Option<MetadataRevision> {
+        if self.bext.is_none()
+            && self.vendor_data.is_empty()
+            && self.smpl.is_none()
+            && self.inst.is_none()
+        {
+            return None;
+        }
+
+        let mut builder = MetadataBuilder::new();
+
+        let bext_tags = self.bext.iter().flat_map(|bext| bext.tags());
+        let smpl_tags = self.smpl.iter().flat_map(|smpl| smpl.tags());
+        let inst_tags = self.inst.iter().flat_map(|inst| inst.tags());
+
+        for tag in bext_tags.chain(smpl_tags).chain(inst_tags) {
+            builder.add_tag(tag);
+        }
+
+        for vendor_data in self.vendor_data.iter() {
+            builder.add_vendor_data(vendor_data.clone());
         }
 
-        let params = &self.tracks[0].codec_params;
-
-        let ts = match to {
-            // Frame timestamp given.
-            SeekTo::TimeStamp { ts, .. } => ts,
-            // Time value given, calculate frame timestamp from sample rate.
-            SeekTo::Time { time, .. } => {
-                // Use the sample rate to calculate the frame timestamp. If sample rate is not
-                // known, the seek cannot be completed.
-                if let Some(sample_rate) = params.sample_rate {
-                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+        Some(builder.metadata())
+    }
+
+    /// Gets the cue points, with their associated labels and notes, and the sample loops as
+    /// `Cue`s. The `Cue`s are relative to the start of the stream, the BWF time reference is
+    /// reported by the `TimeReference` tag instead.
+    fn cues(&self) -> Vec<Cue> {
+        let mut cues: Vec<Cue> = self
+            .cue
+            .iter()
+            .flat_map(|cue| cue.points.iter())
+            .map(|point| Cue {
+                index: point.id,
+                start_ts: u64::from(point.sample_offset),
+                tags: Vec::new(),
+                points: Vec::new(),
+            })
+            .collect();
+
+        for adtl in self.adtl.iter() {
+            if let Some(cue) = cues.iter_mut().find(|cue| cue.index == adtl.cue_id) {
+                cue.tags.extend(adtl.tags.iter().cloned());
+            }
+        }
+
+        for sample_loop in self.smpl.iter().flat_map(|smpl| smpl.loops.iter()) {
+            let loop_type = match sample_loop.loop_type {
+                0 => Value::from("Forward"),
+                1 => Value::from("Alternating"),
+                2 => Value::from("Backward"),
+                loop_type => Value::from(loop_type),
+            };
+
+            let mut tags = vec![
+                Tag::new(None, "LoopType", loop_type),
+                Tag::new(None, "LoopPlayCount", Value::from(sample_loop.play_count)),
+            ];
+
+            if sample_loop.fraction != 0 {
+                tags.push(Tag::new(None, "LoopFraction", Value::from(sample_loop.fraction)));
+            }
+
+            // The end of the loop is a point within the cue of the loop.
+            let end = CuePoint {
+                start_offset_ts: u64::from(sample_loop.end.saturating_sub(sample_loop.start)),
+                tags: vec![Tag::new(None, "LoopEnd", Value::Flag)],
+            };
+
+            let loop_start_ts = u64::from(sample_loop.start);
+
+            // If the loop starts at its cue point, add the loop to that cue. Otherwise, add a new
+            // cue for the loop.
+            match cues
+                .iter_mut()
+                .find(|cue| cue.index == sample_loop.cue_id && cue.start_ts == loop_start_ts)
+            {
+                Some(cue) => {
+                    cue.tags.extend(tags);
+                    cue.points.push(end);
                 }
-                else {
-                    return seek_error(SeekErrorKind::Unseekable);
+                None => {
+                    let index =
+                        cues.iter().map(|cue| cue.index.saturating_add(1)).max().unwrap_or(0);
+
+                    cues.push(Cue { index, start_ts: loop_start_ts, tags, points: vec![end] });
                 }
             }
-        };
+        }
 
-        // If the total number of frames in the track is known, verify the desired frame timestamp
-        // does not exceed it.
-        if let Some(n_frames) = params.n_frames {
-            if ts > n_frames {
-                return seek_error(SeekErrorKind::OutOfRange);
+        cues.sort_by_key(|cue| cue.start_ts);
+        cues
+    }
+}
+
+/// Read a metadata chunk.
+fn read_metadata_chunk(
+    source: &mut MediaSourceStream,
+    chunk: RiffWaveChunks,
+    metadata: &mut MetadataLog,
+    aux: &mut WaveMetadata,
+) -> Result<()> {
+    match chunk {
+        RiffWaveChunks::List(lst) => {
+            let list = lst.parse(source)?;
+
+            // Riff Lists can have many different forms, but WavReader only supports Info lists.
+            match &list.form {
+                b"INFO" => metadata.push(read_info_chunk(source, list.len)?),
+                b"adtl" => aux.adtl.extend(read_adtl_chunk(source, list.len)?),
+                _ => list.skip(source)?,
             }
         }
+        RiffWaveChunks::Bext(bext) => aux.bext = Some(bext.parse(source)?),
+        RiffWaveChunks::VendorData(vnd) => aux.vendor_data.extend(vnd.parse(source)?.vendor_data),
+        RiffWaveChunks::Cue(cue) => aux.cue = Some(cue.parse(source)?),
+        RiffWaveChunks::Smpl(smpl) => aux.smpl = Some(smpl.parse(source)?),
+        RiffWaveChunks::Inst(inst) => aux.inst = Some(inst.parse(source)?),
+        // Chunks that may not follow the data chunk are ignored.
+        RiffWaveChunks::Format(chunk) => source.ignore_bytes(u64::from(chunk.len))?,
+        RiffWaveChunks::Fact(chunk) => source.ignore_bytes(u64::from(chunk.len))?,
+        RiffWaveChunks::Data(chunk) => source.ignore_bytes(u64::from(chunk.len))?,
+    }
+    Ok(())
+}
 
-        debug!("seeking to frame_ts={}", ts);
+/// Read the metadata chunks following the data chunk.
+fn read_trailing_chunks(
+    source: &mut MediaSourceStream,
+    data_end_pos: u64,
+    riff_chunks: &mut ChunksReader<RiffWaveChunks>,
+    metadata: &mut MetadataLog,
+    aux: &mut WaveMetadata,
+) -> Result<()> {
+    source.seek(SeekFrom::Start(data_end_pos))?;
+
+    while let Some(chunk) = riff_chunks.next(source)? {
+        read_metadata_chunk(source, chunk, metadata, aux)?;
+    }
+    Ok(())
+}
 
-        // WAVE is not internally packetized for PCM codecs. Packetization is simulated by trying to
-        // read a constant number of samples or blocks every call to next_packet. Therefore, a packet begins
-        // wherever the data stream is currently positioned. Since timestamps on packets should be
-        // determinstic, instead of seeking to the exact timestamp requested and starting the next
-        // packet there, seek to a packet boundary. In this way, packets will have have the same
-        // timestamps regardless if the stream was seeked or not.
-        let actual_ts = self.packet_info.get_actual_ts(ts);
+#[test]
+fn test_rf64_reader() {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(b"RF64");
+    buf.extend_from_slice(&u32::MAX.to_le_bytes());
+    buf.extend_from_slice(b"WAVE");
 
-        // Calculate the absolute byte offset of the desired audio frame.
-        let seek_pos = self.data_start_pos + (actual_ts * self.packet_info.block_size);
+    // The ds64 chunk, with an empty chunk size table.
+    buf.extend_from_slice(b"ds64");
+    buf.extend_from_slice(&28u32.to_le_bytes());
+    buf.extend_from_slice(&12_000_000_100u64.to_le_bytes());
+    buf.extend_from_slice(&12_000_000_000u64.to_le_bytes());
+    buf.extend_from_slice(&6_000_000_000u64.to_le_bytes());
+    buf.extend_from_slice(&0u32.to_le_bytes());
 
-        // If the reader supports seeking we can seek directly to the frame's offset wherever it may
-        // be.
-        if self.reader.is_seekable() {
-            self.reader.seek(SeekFrom::Start(seek_pos))?;
-        }
-        // If the reader does not support seeking, we can only emulate forward seeks by consuming
-        // bytes. If the reader has to seek backwards, return an error.
-        else {
-            let current_pos = self.reader.pos();
-            if seek_pos >= current_pos {
-                self.reader.ignore_bytes(seek_pos - current_pos)?;
-            }
-            else {
-                return seek_error(SeekErrorKind::ForwardOnly);
-            }
-        }
+    // 16-bit mono PCM at 48 kHz.
+    buf.extend_from_slice(b"fmt ");
+    buf.extend_from_slice(&16u32.to_le_bytes());
+    buf.extend_from_slice(&[0x01, 0x00, 0x01, 0x00]);
+    buf.extend_from_slice(&48_000u32.to_le_bytes());
+    buf.extend_from_slice(&96_000u32.to_le_bytes());
+    buf.extend_from_slice(&[0x02, 0x00, 0x10, 0x00]);
+
+    buf.extend_from_slice(b"fact");
+    buf.extend_from_slice(&4u32.to_le_bytes());
+    buf.extend_from_slice(&u32::MAX.to_le_bytes());
+
+    buf.extend_from_slice(b"data");
+    buf.extend_from_slice(&u32::MAX.to_le_bytes());
+    buf.extend_from_slice(&[0; 64]);
+
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
 
-        debug!("seeked to packet_ts={} (delta={})", actual_ts, actual_ts as i64 - ts as i64);
+    let mut reader = WavReader::try_new(source, &Default::default()).unwrap();
+
+    let params = &reader.tracks()[0].codec_params;
+
+    assert_eq!(params.codec, CODEC_TYPE_PCM_S16LE);
+    assert_eq!(params.n_frames, Some(6_000_000_000));
+
+    // The data chunk starts immediately after its header.
+    assert_eq!(reader.next_packet().unwrap().buf(), &[0; 64][..]);
+}
 
-        Ok(SeekedTo { track_id: 0, actual_ts, required_ts: ts })
+#[test]
+fn test_bwf_cues_relative_to_stream() {
+    fn push_chunk(buf: &mut Vec<u8>, tag: &[u8; 4], data: &[u8]) {
+        buf.extend_from_slice(tag);
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(data);
     }
 
-    fn into_inner(self: Box<Self>) -> MediaSourceStream {
-        self.reader
+    // 16-bit mono PCM at 48 kHz.
+    let mut fmt = vec![0x01, 0x00, 0x01, 0x00];
+    fmt.extend_from_slice(&48_000u32.to_le_bytes());
+    fmt.extend_from_slice(&96_000u32.to_le_bytes());
+    fmt.extend_from_slice(&[0x02, 0x00, 0x10, 0x00]);
+
+    // A bext chunk with a time reference of 10 seconds.
+    let mut bext = vec![0; 602];
+    bext[338..346].copy_from_slice(&480_000u64.to_le_bytes());
+
+    // A cue chunk with one cue point at frame 1000.
+    let mut cue = 1u32.to_le_bytes().to_vec();
+    cue.extend_from_slice(&7u32.to_le_bytes());
+    cue.extend_from_slice(&[0; 16]);
+    cue.extend_from_slice(&1000u32.to_le_bytes());
+
+    let mut chunks = b"WAVE".to_vec();
+    push_chunk(&mut chunks, b"fmt ", &fmt);
+    push_chunk(&mut chunks, b"bext", &bext);
+    push_chunk(&mut chunks, b"cue ", &cue);
+    push_chunk(&mut chunks, b"data", &[0; 4000]);
+
+    let mut buf = Vec::new();
+    push_chunk(&mut buf, b"RIFF", &chunks);
+
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+    let options = WavReaderOptions { enable_time_reference: true, ..Default::default() };
+
+    let mut reader = WavReader::try_new_with_options(source, &Default::default(), options).unwrap();
+
+    // The cue is relative to the start of the stream.
+    assert_eq!(reader.cues().len(), 1);
+    assert_eq!((reader.cues()[0].index, reader.cues()[0].start_ts), (7, 1000));
+
+    // The time reference is reported as a tag, and offsets the packet timestamps.
+    let metadata = reader.metadata();
+    let tag = metadata.current().unwrap().tags().iter().find(|tag| tag.key == "TimeReference");
+
+    assert_eq!(tag.map(|tag| tag.value.to_string()), Some("480000".to_string()));
+    assert_eq!(reader.tracks()[0].codec_params.start_ts, 480_000);
+    assert_eq!(reader.next_packet().unwrap().ts(), 480_000);
+}
+
+#[test]
+fn test_dts_detection() {
+    // 14-bit and 16-bit little-endian packed DTS core frame headers, each with 512 frames.
+    const LE14_HEADER: [u8; 16] = [
+        0xff, 0x1f, 0x00, 0xe8, 0xf0, 0x07, 0x6f, 0xfc, 0x98, 0xfc, 0x00, 0x1e, 0x10, 0x09, 0x82,
+        0xe0,
+    ];
+    const LE16_HEADER: [u8; 16] = [
+        0xfe, 0x7f, 0x01, 0x80, 0x3c, 0xfc, 0xf2, 0x3f, 0xe0, 0x75, 0x44, 0x02, 0x82, 0x20, 0xfd,
+        0x3c,
+    ];
+
+    fn new_reader(header: &[u8; 16], options: WavReaderOptions) -> WavReader {
+        // 16-bit stereo PCM at 44.1 kHz.
+        let mut fmt = vec![0x01, 0x00, 0x02, 0x00];
+        fmt.extend_from_slice(&44_100u32.to_le_bytes());
+        fmt.extend_from_slice(&176_400u32.to_le_bytes());
+        fmt.extend_from_slice(&[0x04, 0x00, 0x10, 0x00]);
+
+        // Two DTS core frames, each replacing 512 frames of PCM audio.
+        let mut data = vec![0; 2 * 2048];
+        data[..16].copy_from_slice(header);
+        data[2048..2048 + 16].copy_from_slice(header);
+
+        let mut buf = b"RIFF".to_vec();
+        buf.extend_from_slice(&(4 + 8 + 16 + 8 + data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(b"WAVE");
+        buf.extend_from_slice(b"fmt ");
+        buf.extend_from_slice(&16u32.to_le_bytes());
+        buf.extend_from_slice(&fmt);
+        buf.extend_from_slice(b"data");
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&data);
+
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+        WavReader::try_new_with_options(source, &Default::default(), options).unwrap()
+    }
+
+    let options = WavReaderOptions { enable_dts_detection: true, ..Default::default() };
+
+    for (header, sample_rate) in [(&LE14_HEADER, 44_100), (&LE16_HEADER, 48_000)].iter() {
+        let mut reader = new_reader(header, options);
+
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.codec, CODEC_TYPE_DCA);
+        assert_eq!(params.sample_rate, Some(*sample_rate));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(6));
+        assert_eq!(params.n_frames, Some(2 * 512));
+
+        // Each packet is one core frame.
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur(), packet.buf().len()), (0, 512, 2048));
+        assert_eq!(&packet.buf()[..16], &header[..]);
     }
+
+    // Without the option, the data chunk is read as PCM audio.
+    let reader = new_reader(&LE16_HEADER, Default::default());
+
+    assert_eq!(reader.tracks()[0].codec_params.codec, CODEC_TYPE_PCM_S16LE);
 }
@@ -0,0 +1,114 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::ReadBytes;
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision};
+use symphonia_metadata::riff;
+
+use crate::common::{ChunkParser, ParseChunk, ParseChunkTag};
+use crate::wave::chunks::{FactChunk, ListChunk, WaveFormatChunk};
+
+/// Wave64 "riff" chunk GUID.
+pub const W64_GUID_RIFF: [u8; 16] = *b"riff\x2e\x91\xcf\x11\xa5\xd6\x28\xdb\x04\xc1\x00\x00";
+/// Wave64 "wave" form GUID.
+pub const W64_GUID_WAVE: [u8; 16] = *b"wave\xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+/// Wave64 "fmt " chunk GUID.
+pub const W64_GUID_FMT: [u8; 16] = *b"fmt \xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+/// Wave64 "fact" chunk GUID.
+pub const W64_GUID_FACT: [u8; 16] = *b"fact\xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+/// Wave64 "data" chunk GUID.
+pub const W64_GUID_DATA: [u8; 16] = *b"data\xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a";
+/// Wave64 "list" chunk GUID.
+pub const W64_GUID_LIST: [u8; 16] = *b"list\x2f\x91\xcf\x11\xa5\xd6\x28\xdb\x04\xc1\x00\x00";
+/// Wave64 summary list chunk GUID.
+pub const W64_GUID_SUMMARY_LIST: [u8; 16] =
+    *b"\xbc\x94\x5f\x92\x5a\x52\xd2\x11\x86\xdc\x00\xc0\x4f\x8e\xdb\x8a";
+
+pub struct SummaryListChunk {
+    pub metadata: MetadataRevision,
+}
+
+impl ParseChunk for SummaryListChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<Self> {
+        if len < 4 {
+            return decode_error("w64: malformed summary list chunk");
+        }
+
+        let n_entries = reader.read_u32()?;
+
+        let mut builder = MetadataBuilder::new();
+        let mut remaining = len - 4;
+
+        // Each entry is a 4-byte key, a 32-bit length, and a value.
+        for _ in 0..n_entries {
+            if remaining < 8 {
+                break;
+            }
+
+            let key = reader.read_quad_bytes()?;
+            let value_len = reader.read_u32()?;
+
+            if value_len > remaining - 8 {
+                return decode_error("w64: malformed summary list entry");
+            }
+
+            let value = reader.read_boxed_slice_exact(value_len as usize)?;
+
+            // Values may be null-terminated.
+            let value = match value.iter().position(|&b| b == 0) {
+                Some(end) => &value[..end],
+                None => &value[..],
+            };
+
+            builder.add_tag(riff::parse(key, value));
+
+            remaining -= 8 + value_len;
+        }
+
+        // Ignore any remaining bytes.
+        reader.ignore_bytes(u64::from(remaining))?;
+
+        Ok(SummaryListChunk { metadata: builder.metadata() })
+    }
+}
+
+pub struct DataChunk {
+    pub len: u64,
+}
+
+pub enum Wave64Chunks {
+    Format(ChunkParser<WaveFormatChunk>),
+    List(ChunkParser<ListChunk>),
+    SummaryList(ChunkParser<SummaryListChunk>),
+    Fact(ChunkParser<FactChunk>),
+    Data(DataChunk),
+}
+
+impl ParseChunkTag for Wave64Chunks {
+    fn parse_tag(_tag: [u8; 4], _len: u32) -> Option<Self> {
+        // Wave64 only uses GUIDs to identify chunks.
+        None
+    }
+
+    fn parse_guid(guid: [u8; 16], len: u64) -> Option<Self> {
+        // Apart from the data chunk, the parsed chunks are never longer than 4 GiB.
+        let len32 = len.min(u64::from(u32::MAX)) as u32;
+
+        // Use the 4-byte tag from the start of the GUID for the chunk parser.
+        let tag = [guid[0], guid[1], guid[2], guid[3]];
+
+        match guid {
+            W64_GUID_FMT => Some(Wave64Chunks::Format(ChunkParser::new(tag, len32))),
+            W64_GUID_LIST => Some(Wave64Chunks::List(ChunkParser::new(tag, len32))),
+            W64_GUID_SUMMARY_LIST => Some(Wave64Chunks::SummaryList(ChunkParser::new(tag, len32))),
+            W64_GUID_FACT => Some(Wave64Chunks::Fact(ChunkParser::new(tag, len32))),
+            W64_GUID_DATA => Some(Wave64Chunks::Data(DataChunk { len })),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,277 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::codecs::CodecParameters;
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use crate::common::{
+    append_data_params, append_format_params, next_packet, seek, ByteOrder, ChunksReader,
+    PacketInfo,
+};
+use crate::wave::chunks::{append_fact_params, read_info_list};
+
+mod chunks;
+use chunks::*;
+
+/// Sony Wave64 (W64) format reader.
+///
+/// `W64Reader` implements a demuxer for the Wave64 container format. Wave64 is similar to WAVE,
+/// but uses GUIDs to identify chunks and 64-bit chunk lengths.
+pub struct W64Reader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    packet_info: PacketInfo,
+    data_start_pos: u64,
+    data_end_pos: u64,
+}
+
+impl QueryDescriptor for W64Reader {
+    fn query() -> &'static [Descriptor] {
+        &[
+            // Wave64
+            support_format!("w64", "Sony Wave64", &["w64"], &["audio/x-w64"], &[&W64_GUID_RIFF]),
+        ]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for W64Reader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // The riff GUID should be present.
+        let mut guid = [0; 16];
+        source.read_buf_exact(&mut guid)?;
+
+        if guid != W64_GUID_RIFF {
+            return unsupported_error("w64: missing riff guid");
+        }
+
+        // The length of the riff chunk includes the 24-byte chunk header.
+        let riff_len = source.read_u64()?;
+
+        source.read_buf_exact(&mut guid)?;
+
+        // The riff chunk contains wave data.
+        if guid != W64_GUID_WAVE {
+            return unsupported_error("w64: riff form is not wave");
+        }
+
+        // The chunks follow the 24-byte header and 16-byte form GUID.
+        let mut riff_chunks = ChunksReader::<Wave64Chunks>::new_wave64(riff_len.saturating_sub(40));
+
+        let mut codec_params = CodecParameters::new();
+        let mut metadata: MetadataLog = Default::default();
+        let mut packet_info = PacketInfo::without_blocks(0);
+
+        loop {
+            let chunk = riff_chunks.next(&mut source)?;
+
+            // The last chunk should always be a data chunk, if it is not, then the stream is
+            // unsupported.
+            if chunk.is_none() {
+                return unsupported_error("w64: missing data chunk");
+            }
+
+            match chunk.unwrap() {
+                Wave64Chunks::Format(fmt) => {
+                    let format = fmt.parse(&mut source)?;
+
+                    // The Format chunk contains the block_align field and possible additional
+                    // information to handle packetization and seeking.
+                    packet_info = format.packet_info()?;
+                    codec_params
+                        .with_max_frames_per_packet(packet_info.get_max_frames_per_packet())
+                        .with_frames_per_block(packet_info.frames_per_block);
+
+                    // Append Format chunk fields to codec parameters.
+                    append_format_params(
+                        &mut codec_params,
+                        &format.format_data,
+                        format.sample_rate,
+                    );
+                }
+                Wave64Chunks::Fact(fct) => {
+                    let fact = fct.parse(&mut source)?;
+
+                    // Append Fact chunk fields to codec parameters.
+                    append_fact_params(&mut codec_params, &fact);
+                }
+                Wave64Chunks::List(lst) => {
+                    let list = lst.parse(&mut source)?;
+
+                    // Only Info lists are supported.
+                    if list.form == *b"INFO" {
+                        // The list contains 2-byte aligned RIFF chunks. However, the list itself
+                        // is aligned by the Wave64 chunk reader, so it must not be padded.
+                        let info_list =
+                            ChunksReader::new(list.len, ByteOrder::LittleEndian).without_padding();
+
+                        metadata.push(read_info_list(&mut source, info_list)?);
+                    }
+                    else {
+                        source.ignore_bytes(u64::from(list.len))?;
+                    }
+                }
+                Wave64Chunks::SummaryList(smry) => {
+                    let summary = smry.parse(&mut source)?;
+
+                    metadata.push(summary.metadata);
+                }
+                Wave64Chunks::Data(data) => {
+                    // Record the bounds of the data chunk.
+                    let data_start_pos = source.pos();
+                    let data_end_pos = data_start_pos + data.len;
+
+                    // Append Data chunk fields to codec parameters.
+                    append_data_params(&mut codec_params, data.len, &packet_info);
+
+                    // Add a new track using the collected codec parameters.
+                    return Ok(W64Reader {
+                        reader: source,
+                        tracks: vec![Track::new(0, codec_params)],
+                        cues: Vec::new(),
+                        metadata,
+                        packet_info,
+                        data_start_pos,
+                        data_end_pos,
+                    });
+                }
+            }
+        }
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        next_packet(
+            &mut self.reader,
+            &mut self.packet_info,
+            &self.tracks,
+            self.data_start_pos,
+            self.data_end_pos,
+        )
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        seek(
+            &mut self.reader,
+            &mut self.packet_info,
+            &self.tracks,
+            self.data_start_pos,
+            self.data_end_pos,
+            0,
+            to,
+        )
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[test]
+fn test_w64_chunks_parse() {
+    use symphonia_core::codecs::CODEC_TYPE_PCM_S16LE;
+    use symphonia_core::meta::StandardTagKey;
+
+    fn push_chunk(buf: &mut Vec<u8>, guid: &[u8; 16], data: &[u8]) {
+        buf.extend_from_slice(guid);
+        buf.extend_from_slice(&(24 + data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(data);
+
+        // Chunks are aligned to 8 bytes.
+        buf.resize((buf.len() + 7) & !7, 0);
+    }
+
+    // 16-bit stereo PCM at 44.1 kHz.
+    let mut fmt = vec![0x01, 0x00, 0x02, 0x00];
+    fmt.extend_from_slice(&44_100u32.to_le_bytes());
+    fmt.extend_from_slice(&176_400u32.to_le_bytes());
+    fmt.extend_from_slice(&[0x04, 0x00, 0x10, 0x00]);
+
+    // A summary list with one title entry.
+    let mut summary = 1u32.to_le_bytes().to_vec();
+    summary.extend_from_slice(b"INAM");
+    summary.extend_from_slice(&6u32.to_le_bytes());
+    summary.extend_from_slice(b"Title\0");
+
+    // An INFO list with one artist entry, and an odd length.
+    let mut info = b"INFO".to_vec();
+    info.extend_from_slice(b"IART");
+    info.extend_from_slice(&5u32.to_le_bytes());
+    info.extend_from_slice(b"Artst");
+
+    let data: Vec<u8> = (0..32).collect();
+
+    let mut chunks = Vec::new();
+    push_chunk(&mut chunks, &W64_GUID_FMT, &fmt);
+    // An unknown chunk with a length that is not a multiple of 8.
+    push_chunk(&mut chunks, b"junk\xf3\xac\xd3\x11\x8c\xd1\x00\xc0\x4f\x8e\xdb\x8a", &[0; 5]);
+    push_chunk(&mut chunks, &W64_GUID_SUMMARY_LIST, &summary);
+    push_chunk(&mut chunks, &W64_GUID_LIST, &info);
+    push_chunk(&mut chunks, &W64_GUID_DATA, &data);
+
+    let mut buf = W64_GUID_RIFF.to_vec();
+    buf.extend_from_slice(&(40 + chunks.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&W64_GUID_WAVE);
+    buf.extend_from_slice(&chunks);
+
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+    let mut reader = W64Reader::try_new(source, &Default::default()).unwrap();
+
+    let params = &reader.tracks()[0].codec_params;
+
+    assert_eq!(params.codec, CODEC_TYPE_PCM_S16LE);
+    assert_eq!(params.sample_rate, Some(44_100));
+    assert_eq!(params.n_frames, Some(8));
+
+    let title = reader.metadata().current().unwrap().tags()[0].clone();
+
+    assert_eq!(title.std_key, Some(StandardTagKey::TrackTitle));
+    assert_eq!(title.value.to_string(), "Title");
+
+    let mut metadata = reader.metadata();
+    metadata.pop();
+
+    let artist = metadata.current().unwrap().tags()[0].clone();
+
+    assert_eq!(artist.std_key, Some(StandardTagKey::Artist));
+    assert_eq!(artist.value.to_string(), "Artst");
+
+    let packet = reader.next_packet().unwrap();
+
+    assert_eq!(packet.ts(), 0);
+    assert_eq!(packet.dur(), 8);
+    assert_eq!(packet.buf(), &data[..]);
+
+    // Seeking within the only packet returns to the start of the data chunk.
+    let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5, track_id: 0 }).unwrap();
+
+    assert_eq!(seeked.actual_ts, 0);
+    assert_eq!(seeked.required_ts, 5);
+    assert_eq!(reader.next_packet().unwrap().buf(), &data[..]);
+}
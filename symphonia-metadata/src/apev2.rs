@@ -0,0 +1,325 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An APEv2 (and APEv1) tag reader for Monkey's Audio, WavPack, Musepack, and TrueAudio.
+
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom};
+
+use lazy_static::lazy_static;
+use log::warn;
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BufReader, FiniteStream, ReadBytes};
+use symphonia_core::meta::{
+    MetadataBuilder, StandardTagKey, StandardVisualKey, Tag, Value, Visual,
+};
+
+lazy_static! {
+    static ref APE_TAG_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        m.insert("album artist", StandardTagKey::AlbumArtist);
+        m.insert("album", StandardTagKey::Album);
+        m.insert("albumartist", StandardTagKey::AlbumArtist);
+        m.insert("albumartistsort", StandardTagKey::SortAlbumArtist);
+        m.insert("albumsort", StandardTagKey::SortAlbum);
+        m.insert("arranger", StandardTagKey::Arranger);
+        m.insert("artist", StandardTagKey::Artist);
+        m.insert("artistsort", StandardTagKey::SortArtist);
+        m.insert("barcode", StandardTagKey::IdentBarcode);
+        m.insert("bpm", StandardTagKey::Bpm);
+        m.insert("catalog", StandardTagKey::IdentCatalogNumber);
+        m.insert("catalognumber", StandardTagKey::IdentCatalogNumber);
+        m.insert("comment", StandardTagKey::Comment);
+        m.insert("compilation", StandardTagKey::Compilation);
+        m.insert("composer", StandardTagKey::Composer);
+        m.insert("conductor", StandardTagKey::Conductor);
+        m.insert("copyright", StandardTagKey::Copyright);
+        m.insert("disc", StandardTagKey::DiscNumber);
+        m.insert("discnumber", StandardTagKey::DiscNumber);
+        m.insert("discsubtitle", StandardTagKey::DiscSubtitle);
+        m.insert("djmixer", StandardTagKey::MixDj);
+        m.insert("ean/upc", StandardTagKey::IdentEanUpn);
+        m.insert("encodedby", StandardTagKey::EncodedBy);
+        m.insert("encoder", StandardTagKey::Encoder);
+        m.insert("engineer", StandardTagKey::Engineer);
+        m.insert("genre", StandardTagKey::Genre);
+        m.insert("isrc", StandardTagKey::IdentIsrc);
+        m.insert("label", StandardTagKey::Label);
+        m.insert("language", StandardTagKey::Language);
+        m.insert("lyricist", StandardTagKey::Lyricist);
+        m.insert("lyrics", StandardTagKey::Lyrics);
+        m.insert("media", StandardTagKey::MediaFormat);
+        m.insert("mixer", StandardTagKey::MixEngineer);
+        m.insert("mood", StandardTagKey::Mood);
+        m.insert("musicbrainz_albumartistid", StandardTagKey::MusicBrainzAlbumArtistId);
+        m.insert("musicbrainz_albumid", StandardTagKey::MusicBrainzAlbumId);
+        m.insert("musicbrainz_artistid", StandardTagKey::MusicBrainzArtistId);
+        m.insert("musicbrainz_discid", StandardTagKey::MusicBrainzDiscId);
+        m.insert("musicbrainz_releasegroupid", StandardTagKey::MusicBrainzReleaseGroupId);
+        m.insert("musicbrainz_releasetrackid", StandardTagKey::MusicBrainzReleaseTrackId);
+        m.insert("musicbrainz_trackid", StandardTagKey::MusicBrainzTrackId);
+        m.insert("musicbrainz_workid", StandardTagKey::MusicBrainzWorkId);
+        m.insert("originaldate", StandardTagKey::OriginalDate);
+        m.insert("performer", StandardTagKey::Performer);
+        m.insert("producer", StandardTagKey::Producer);
+        m.insert("publisher", StandardTagKey::Label);
+        m.insert("rating", StandardTagKey::Rating);
+        m.insert("record date", StandardTagKey::Date);
+        m.insert("releasecountry", StandardTagKey::ReleaseCountry);
+        m.insert("remixer", StandardTagKey::Remixer);
+        m.insert("replaygain_album_gain", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("replaygain_album_peak", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("replaygain_track_gain", StandardTagKey::ReplayGainTrackGain);
+        m.insert("replaygain_track_peak", StandardTagKey::ReplayGainTrackPeak);
+        m.insert("script", StandardTagKey::Script);
+        m.insert("subtitle", StandardTagKey::TrackSubtitle);
+        m.insert("title", StandardTagKey::TrackTitle);
+        m.insert("titlesort", StandardTagKey::SortTrackTitle);
+        m.insert("track", StandardTagKey::TrackNumber);
+        m.insert("tracknumber", StandardTagKey::TrackNumber);
+        m.insert("writer", StandardTagKey::Writer);
+        m.insert("year", StandardTagKey::Date);
+        m
+    };
+}
+
+/// The APEv2 tag header and footer preamble: "APETAGEX" in ASCII.
+pub const APE_TAG_PREAMBLE: [u8; 8] = *b"APETAGEX";
+
+/// The length of an APE tag header or footer in bytes, including the preamble.
+pub const APE_TAG_HEADER_LEN: u64 = 32;
+
+/// The tag contains a header.
+const APE_TAG_FLAG_HAS_HEADER: u32 = 1 << 31;
+/// This is the header, not the footer.
+const APE_TAG_FLAG_IS_HEADER: u32 = 1 << 29;
+
+/// Item values are UTF-8 text.
+const APE_ITEM_TYPE_TEXT: u32 = 0;
+/// Item values are binary data.
+const APE_ITEM_TYPE_BINARY: u32 = 1;
+/// Item values are UTF-8 links to external data.
+const APE_ITEM_TYPE_LOCATOR: u32 = 2;
+
+/// An APE tag header or footer.
+#[derive(Debug)]
+pub struct ApeTagHeader {
+    /// The tag version. 1000 for APEv1, and 2000 for APEv2.
+    pub version: u32,
+    /// The length of the tag items and footer in bytes. The header is not included.
+    pub size: u32,
+    /// The number of items in the tag.
+    pub n_items: u32,
+    /// The tag flags.
+    pub flags: u32,
+}
+
+impl ApeTagHeader {
+    /// Reads an APE tag header or footer, including the preamble.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<ApeTagHeader> {
+        let mut preamble = [0; 8];
+        reader.read_buf_exact(&mut preamble)?;
+
+        if preamble != APE_TAG_PREAMBLE {
+            return unsupported_error("apev2: not an ape tag");
+        }
+
+        let version = reader.read_u32()?;
+        let size = reader.read_u32()?;
+        let n_items = reader.read_u32()?;
+        let flags = reader.read_u32()?;
+
+        // Reserved.
+        reader.ignore_bytes(8)?;
+
+        if u64::from(size) < APE_TAG_HEADER_LEN {
+            return decode_error("apev2: invalid tag size");
+        }
+
+        Ok(ApeTagHeader { version, size, n_items, flags })
+    }
+
+    /// Returns true if the tag is preceeded by a header.
+    pub fn has_header(&self) -> bool {
+        self.flags & APE_TAG_FLAG_HAS_HEADER != 0
+    }
+
+    /// Returns true if this is the header of the tag rather than the footer.
+    pub fn is_header(&self) -> bool {
+        self.flags & APE_TAG_FLAG_IS_HEADER != 0
+    }
+}
+
+/// Reads the items of an APE tag described by `header`. The reader must be positioned at the
+/// first item.
+pub fn read_ape_tag_items<B: ReadBytes>(
+    reader: &mut B,
+    header: &ApeTagHeader,
+    metadata: &mut MetadataBuilder,
+) -> Result<()> {
+    // The items are followed by the footer.
+    let items_len = (u64::from(header.size) - APE_TAG_HEADER_LEN) as usize;
+
+    let buf = reader.read_boxed_slice_exact(items_len)?;
+    let mut reader = BufReader::new(&buf);
+
+    for _ in 0..header.n_items {
+        if reader.bytes_available() == 0 {
+            warn!("apev2: tag ended after fewer items than stated");
+            break;
+        }
+
+        read_ape_tag_item(&mut reader, header.version, metadata)?;
+    }
+
+    Ok(())
+}
+
+/// Reads an APE tag that ends at the byte position `end` of a seekable stream. Returns the
+/// position of the start of the tag (including the header, if present) if a tag was found.
+pub fn read_ape_tag_before<R: ReadBytes + Seek>(
+    reader: &mut R,
+    end: u64,
+    metadata: &mut MetadataBuilder,
+) -> Result<Option<u64>> {
+    if end < APE_TAG_HEADER_LEN {
+        return Ok(None);
+    }
+
+    reader.seek(SeekFrom::Start(end - APE_TAG_HEADER_LEN))?;
+
+    let footer = match ApeTagHeader::read(reader) {
+        Ok(footer) if !footer.is_header() => footer,
+        _ => return Ok(None),
+    };
+
+    if u64::from(footer.size) > end {
+        return decode_error("apev2: tag size exceeds the length of the stream");
+    }
+
+    let items_pos = end - u64::from(footer.size);
+
+    reader.seek(SeekFrom::Start(items_pos))?;
+
+    read_ape_tag_items(reader, &footer, metadata)?;
+
+    // APEv1 tags never have a header.
+    if footer.version >= 2000 && footer.has_header() && items_pos >= APE_TAG_HEADER_LEN {
+        Ok(Some(items_pos - APE_TAG_HEADER_LEN))
+    }
+    else {
+        Ok(Some(items_pos))
+    }
+}
+
+fn read_ape_tag_item(
+    reader: &mut BufReader<'_>,
+    version: u32,
+    metadata: &mut MetadataBuilder,
+) -> Result<()> {
+    let value_len = reader.read_u32()? as usize;
+    let flags = reader.read_u32()?;
+
+    // The key is a null-terminated ASCII string.
+    let key_buf = reader.scan_bytes_ref(&[0], reader.bytes_available() as usize)?;
+
+    let key = match key_buf.split_last() {
+        Some((0, key)) => String::from_utf8_lossy(key).into_owned(),
+        _ => return decode_error("apev2: unterminated item key"),
+    };
+
+    if value_len > reader.bytes_available() as usize {
+        return decode_error("apev2: item value exceeds the length of the tag");
+    }
+
+    let value = reader.read_buf_bytes_ref(value_len)?;
+
+    let key_lower = key.to_ascii_lowercase();
+
+    // APEv1 does not define item types, all values are text.
+    let item_type = if version >= 2000 { (flags >> 1) & 0x3 } else { APE_ITEM_TYPE_TEXT };
+
+    match item_type {
+        APE_ITEM_TYPE_TEXT | APE_ITEM_TYPE_LOCATOR => {
+            let std_key = APE_TAG_MAP.get(key_lower.as_str()).copied();
+
+            // A text item may contain a list of values separated by null characters.
+            for text in String::from_utf8_lossy(value).split('\0') {
+                metadata.add_tag(Tag::new(std_key, &key, Value::from(text)));
+            }
+        }
+        APE_ITEM_TYPE_BINARY if key_lower.starts_with("cover art") => {
+            // Cover art items begin with a null-terminated file name, followed by the image data.
+            let data = match value.iter().position(|&b| b == 0) {
+                Some(pos) => &value[pos + 1..],
+                None => value,
+            };
+
+            metadata.add_visual(Visual {
+                media_type: media_type(data).into(),
+                dimensions: None,
+                bits_per_pixel: None,
+                color_mode: None,
+                usage: cover_art_visual_key(&key_lower),
+                tags: Vec::new(),
+                data: Box::from(data),
+            });
+        }
+        APE_ITEM_TYPE_BINARY => {
+            metadata.add_tag(Tag::new(None, &key, Value::from(value)));
+        }
+        _ => warn!("apev2: ignoring item with reserved type"),
+    }
+
+    Ok(())
+}
+
+/// Gets the `StandardVisualKey` for a "Cover Art (...)" item key.
+fn cover_art_visual_key(key: &str) -> Option<StandardVisualKey> {
+    match key {
+        "cover art (front)" => Some(StandardVisualKey::FrontCover),
+        "cover art (back)" => Some(StandardVisualKey::BackCover),
+        "cover art (leaflet)" => Some(StandardVisualKey::Leaflet),
+        "cover art (media)" => Some(StandardVisualKey::Media),
+        "cover art (lead artist)" => Some(StandardVisualKey::LeadArtistPerformerSoloist),
+        "cover art (artist)" => Some(StandardVisualKey::ArtistPerformer),
+        "cover art (conductor)" => Some(StandardVisualKey::Conductor),
+        "cover art (band)" => Some(StandardVisualKey::BandOrchestra),
+        "cover art (composer)" => Some(StandardVisualKey::Composer),
+        "cover art (lyricist)" => Some(StandardVisualKey::Lyricist),
+        "cover art (recording location)" => Some(StandardVisualKey::RecordingLocation),
+        "cover art (during recording)" => Some(StandardVisualKey::RecordingSession),
+        "cover art (during performance)" => Some(StandardVisualKey::Performance),
+        "cover art (video capture)" => Some(StandardVisualKey::ScreenCapture),
+        "cover art (illustration)" => Some(StandardVisualKey::Illustration),
+        "cover art (band logotype)" => Some(StandardVisualKey::BandArtistLogo),
+        "cover art (publisher logotype)" => Some(StandardVisualKey::PublisherStudioLogo),
+        "cover art (icon)" => Some(StandardVisualKey::FileIcon),
+        "cover art (other icon)" => Some(StandardVisualKey::OtherIcon),
+        _ => None,
+    }
+}
+
+/// Guesses the media type of an image from its signature.
+fn media_type(data: &[u8]) -> &'static str {
+    if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        "image/jpeg"
+    }
+    else if data.starts_with(b"\x89PNG") {
+        "image/png"
+    }
+    else if data.starts_with(b"GIF8") {
+        "image/gif"
+    }
+    else if data.starts_with(b"BM") {
+        "image/bmp"
+    }
+    else {
+        ""
+    }
+}
@@ -0,0 +1,478 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A CUE sheet parser.
+//!
+//! A CUE sheet is an external text file describing the layout of tracks within one or more audio
+//! files. The parsed CUE sheet can produce a list of `Cue`s for each file. These may be presented
+//! as virtual tracks of a `FormatReader` using a `CueTrackReader`.
+
+use std::collections::HashMap;
+
+use encoding_rs::WINDOWS_1252;
+use lazy_static::lazy_static;
+use log::debug;
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::formats::cue::CueTrackReader;
+use symphonia_core::formats::{Cue, CuePoint, FormatReader};
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+lazy_static! {
+    static ref REM_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        m.insert("COMMENT", StandardTagKey::Comment);
+        m.insert("COMPOSER", StandardTagKey::Composer);
+        m.insert("DATE", StandardTagKey::Date);
+        m.insert("DISCNUMBER", StandardTagKey::DiscNumber);
+        m.insert("GENRE", StandardTagKey::Genre);
+        m.insert("LABEL", StandardTagKey::Label);
+        m.insert("REPLAYGAIN_ALBUM_GAIN", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("REPLAYGAIN_ALBUM_PEAK", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("REPLAYGAIN_TRACK_GAIN", StandardTagKey::ReplayGainTrackGain);
+        m.insert("REPLAYGAIN_TRACK_PEAK", StandardTagKey::ReplayGainTrackPeak);
+        m.insert("TOTALDISCS", StandardTagKey::DiscTotal);
+        m
+    };
+}
+
+/// The number of CUE sheet frames (sectors) per second.
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// A file referenced by a CUE sheet.
+#[derive(Clone, Debug)]
+pub struct CueSheetFile {
+    /// The path of the file, as written in the CUE sheet.
+    pub path: String,
+    /// The type of the file (e.g., `WAVE`, `MP3`, `AIFF`, `BINARY`).
+    pub file_type: String,
+}
+
+/// A track index within a CUE sheet.
+#[derive(Copy, Clone, Debug)]
+pub struct CueSheetIndex {
+    /// The index number. Index 0 is the pre-gap, and index 1 is the start of the track.
+    pub number: u32,
+    /// The index into the list of files of the file containing the index.
+    pub file: usize,
+    /// The position of the index relative to the start of the file in CUE sheet frames (1/75th of
+    /// a second).
+    pub msf: u64,
+}
+
+/// A track within a CUE sheet.
+#[derive(Clone, Debug)]
+pub struct CueSheetTrack {
+    /// The track number.
+    pub number: u32,
+    /// The data type of the track (e.g., `AUDIO`).
+    pub data_type: String,
+    /// The index into the list of files of the file in which the track was declared.
+    pub file: usize,
+    /// The track's indices.
+    pub indices: Vec<CueSheetIndex>,
+    /// The track's tags.
+    pub tags: Vec<Tag>,
+}
+
+/// A parsed CUE sheet.
+#[derive(Clone, Debug, Default)]
+pub struct CueSheet {
+    /// The disc-level tags.
+    pub tags: Vec<Tag>,
+    /// The files referenced by the CUE sheet in the order they were declared.
+    pub files: Vec<CueSheetFile>,
+    /// The tracks in the order they were declared.
+    pub tracks: Vec<CueSheetTrack>,
+}
+
+impl CueSheet {
+    /// Parse a CUE sheet from a byte buffer. The CUE sheet may be encoded in UTF-8 (with or without
+    /// a byte order mark). Otherwise, it is assumed to be encoded in Windows-1252.
+    pub fn from_bytes(buf: &[u8]) -> Result<CueSheet> {
+        let buf = buf.strip_prefix(b"\xef\xbb\xbf").unwrap_or(buf);
+
+        match std::str::from_utf8(buf) {
+            Ok(text) => Self::parse(text),
+            Err(_) => Self::parse(&WINDOWS_1252.decode_without_bom_handling(buf).0),
+        }
+    }
+
+    /// Parse a CUE sheet from a string.
+    pub fn parse(text: &str) -> Result<CueSheet> {
+        let mut sheet = CueSheet::default();
+
+        for line in text.lines() {
+            let mut args = Arguments::new(line);
+
+            let command = match args.next() {
+                Some(command) => command.to_ascii_uppercase(),
+                None => continue,
+            };
+
+            match command.as_str() {
+                "FILE" => {
+                    let mut rest = args.collect::<Vec<_>>();
+
+                    // The file type is the last argument. An unquoted path containing spaces
+                    // forms the remaining arguments.
+                    let file_type = match rest.pop() {
+                        Some(file_type) if !rest.is_empty() => file_type.to_ascii_uppercase(),
+                        _ => return decode_error("cue: malformed file command"),
+                    };
+
+                    sheet.files.push(CueSheetFile { path: rest.join(" "), file_type });
+                }
+                "TRACK" => {
+                    if sheet.files.is_empty() {
+                        return decode_error("cue: track declared before any file");
+                    }
+
+                    let number = match args.next().and_then(|n| n.parse::<u32>().ok()) {
+                        Some(number) => number,
+                        None => return decode_error("cue: malformed track number"),
+                    };
+
+                    let data_type = args.next().unwrap_or_default().to_ascii_uppercase();
+
+                    sheet.tracks.push(CueSheetTrack {
+                        number,
+                        data_type,
+                        file: sheet.files.len() - 1,
+                        indices: Vec::new(),
+                        tags: Vec::new(),
+                    });
+                }
+                "INDEX" => {
+                    let file = sheet.files.len().saturating_sub(1);
+
+                    let track = match sheet.tracks.last_mut() {
+                        Some(track) => track,
+                        None => return decode_error("cue: index declared before any track"),
+                    };
+
+                    let number = match args.next().and_then(|n| n.parse::<u32>().ok()) {
+                        Some(number) => number,
+                        None => return decode_error("cue: malformed index number"),
+                    };
+
+                    let msf = match args.next().and_then(parse_msf) {
+                        Some(msf) => msf,
+                        None => return decode_error("cue: malformed index timestamp"),
+                    };
+
+                    track.indices.push(CueSheetIndex { number, file, msf });
+                }
+                "TITLE" | "PERFORMER" | "SONGWRITER" => {
+                    let value = args.collect::<Vec<_>>().join(" ");
+
+                    let is_disc = sheet.tracks.is_empty();
+
+                    let std_key = match command.as_str() {
+                        "TITLE" if is_disc => StandardTagKey::Album,
+                        "TITLE" => StandardTagKey::TrackTitle,
+                        "PERFORMER" if is_disc => StandardTagKey::AlbumArtist,
+                        "PERFORMER" => StandardTagKey::Artist,
+                        _ => StandardTagKey::Writer,
+                    };
+
+                    sheet.push_tag(Tag::new(Some(std_key), &command, Value::from(value)));
+                }
+                "ISRC" => {
+                    let value = args.next().unwrap_or_default();
+                    let tag = Tag::new(Some(StandardTagKey::IdentIsrc), "ISRC", Value::from(value));
+                    sheet.push_tag(tag);
+                }
+                "CATALOG" => {
+                    let value = args.next().unwrap_or_default();
+                    let tag = Tag::new(
+                        Some(StandardTagKey::IdentCatalogNumber),
+                        "CATALOG",
+                        Value::from(value),
+                    );
+                    sheet.push_tag(tag);
+                }
+                "REM" => {
+                    let key = match args.next() {
+                        Some(key) => key.to_ascii_uppercase(),
+                        None => continue,
+                    };
+
+                    let value = args.collect::<Vec<_>>().join(" ");
+
+                    let std_key = REM_MAP.get(key.as_str()).copied();
+
+                    sheet.push_tag(Tag::new(std_key, &key, Value::from(value)));
+                }
+                "FLAGS" | "PREGAP" | "POSTGAP" | "CDTEXTFILE" => {
+                    let value = args.collect::<Vec<_>>().join(" ");
+                    sheet.push_tag(Tag::new(None, &command, Value::from(value)));
+                }
+                _ => debug!("ignoring unknown cue sheet command: {}", command),
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    /// Push a tag onto the last declared track, or the disc if no tracks have been declared.
+    fn push_tag(&mut self, tag: Tag) {
+        match self.tracks.last_mut() {
+            Some(track) => track.tags.push(tag),
+            None => self.tags.push(tag),
+        }
+    }
+
+    /// Find the index into the list of files of the file with the provided file name. The directory
+    /// and extension of the file name are ignored since CUE sheets frequently refer to a file
+    /// before it was converted to another format.
+    pub fn find_file(&self, file_name: &str) -> Option<usize> {
+        let stem = file_stem(file_name);
+        self.files.iter().position(|file| file_stem(&file.path).eq_ignore_ascii_case(stem))
+    }
+
+    /// Get the `Cue`s for the file at the provided index into the list of files. The timestamps of
+    /// the `Cue`s are calculated using the sample rate of the file.
+    ///
+    /// Each track with an index, other than the pre-gap, within the file produces one `Cue`. The
+    /// `Cue` starts at the track's first index (normally `INDEX 01`) within the file, and each of
+    /// the track's indices within the file is a `CuePoint`. Since the pre-gap (`INDEX 00`) of a
+    /// track precedes the start of the track, it is a `CuePoint` of the preceding `Cue`, tagged
+    /// with the number of the track it belongs to. The tags of a `Cue` are the disc-level tags
+    /// followed by the track's tags.
+    pub fn cues(&self, file: usize, sample_rate: u32) -> Vec<Cue> {
+        let to_frames = |msf: u64| msf * u64::from(sample_rate) / FRAMES_PER_SECOND;
+
+        let mut cues = Vec::new();
+
+        for (i, track) in self.tracks.iter().enumerate() {
+            let indices =
+                track.indices.iter().filter(|index| index.file == file && index.number > 0);
+
+            let start_msf = match indices.clone().map(|index| index.msf).min() {
+                Some(start_msf) => start_msf,
+                None => continue,
+            };
+
+            let start_ts = to_frames(start_msf);
+
+            let mut points: Vec<CuePoint> = indices
+                .map(|index| CuePoint {
+                    start_offset_ts: to_frames(index.msf) - start_ts,
+                    tags: vec![Tag::new(None, "INDEX", Value::from(index.number))],
+                })
+                .collect();
+
+            // The pre-gap of the next track.
+            if let Some(next) = self.tracks.get(i + 1) {
+                let pregap = next.indices.iter().find(|index| {
+                    index.file == file && index.number == 0 && index.msf >= start_msf
+                });
+
+                if let Some(index) = pregap {
+                    points.push(CuePoint {
+                        start_offset_ts: to_frames(index.msf) - start_ts,
+                        tags: vec![
+                            Tag::new(
+                                Some(StandardTagKey::TrackNumber),
+                                "TRACK",
+                                Value::from(next.number),
+                            ),
+                            Tag::new(None, "INDEX", Value::from(index.number)),
+                        ],
+                    });
+                }
+            }
+
+            let mut tags = Vec::with_capacity(self.tags.len() + track.tags.len() + 2);
+
+            tags.extend(self.tags.iter().cloned());
+
+            // If the track does not have a performer, the disc performer is also the track artist.
+            if !track.tags.iter().any(|tag| tag.std_key == Some(StandardTagKey::Artist)) {
+                if let Some(tag) =
+                    self.tags.iter().find(|tag| tag.std_key == Some(StandardTagKey::AlbumArtist))
+                {
+                    tags.push(Tag::new(
+                        Some(StandardTagKey::Artist),
+                        "PERFORMER",
+                        tag.value.clone(),
+                    ));
+                }
+            }
+
+            tags.push(Tag::new(
+                Some(StandardTagKey::TrackNumber),
+                "TRACK",
+                Value::from(track.number),
+            ));
+
+            tags.extend(track.tags.iter().cloned());
+
+            cues.push(Cue { index: track.number, start_ts, tags, points });
+        }
+
+        cues
+    }
+
+    /// Attach the `Cue`s for the file at the provided index into the list of files to a
+    /// `FormatReader` reading that file. The tracks of the CUE sheet are presented as virtual
+    /// tracks of the reader's default track.
+    pub fn attach(&self, file: usize, reader: Box<dyn FormatReader>) -> Result<CueTrackReader> {
+        let sample_rate =
+            match reader.default_track().and_then(|track| track.codec_params.sample_rate) {
+                Some(sample_rate) => sample_rate,
+                None => return decode_error("cue: the sample rate of the file is unknown"),
+            };
+
+        CueTrackReader::with_cues(reader, self.cues(file, sample_rate))
+    }
+}
+
+/// Get the file name of a path, without the directory and extension.
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit(|c| c == '/' || c == '\\').next().unwrap_or(path);
+
+    match name.rfind('.') {
+        Some(pos) if pos > 0 => &name[..pos],
+        _ => name,
+    }
+}
+
+/// Parse a timestamp in the form of `mm:ss:ff` into the number of CUE sheet frames.
+fn parse_msf(msf: String) -> Option<u64> {
+    let mut parts = msf.split(':').map(|part| part.parse::<u64>().ok());
+
+    let (m, s, f) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(m)), Some(Some(s)), Some(Some(f)), None) => (m, s, f),
+        _ => return None,
+    };
+
+    if s >= 60 || f >= FRAMES_PER_SECOND {
+        return None;
+    }
+
+    Some((m * 60 + s) * FRAMES_PER_SECOND + f)
+}
+
+/// An iterator over the whitespace-separated, and optionally double-quoted, arguments of a CUE
+/// sheet line.
+struct Arguments<'a> {
+    line: &'a str,
+}
+
+impl<'a> Arguments<'a> {
+    fn new(line: &'a str) -> Self {
+        Arguments { line }
+    }
+}
+
+impl<'a> Iterator for Arguments<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let line = self.line.trim_start();
+
+        if line.is_empty() {
+            return None;
+        }
+
+        if let Some(quoted) = line.strip_prefix('"') {
+            // A quoted argument ends at the next quote, or the end of the line if unterminated.
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            self.line = quoted.get(end + 1..).unwrap_or("");
+            Some(quoted[..end].to_string())
+        }
+        else {
+            let end = line.find(char::is_whitespace).unwrap_or(line.len());
+            self.line = &line[end..];
+            Some(line[..end].to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CueSheet;
+    use symphonia_core::meta::StandardTagKey;
+
+    const SHEET: &str = "REM GENRE \"Progressive Rock\"
+REM DATE 1973
+PERFORMER \"Some Band\"
+TITLE \"Some Album\"
+FILE \"CD1.wav\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"First\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Second\"
+    PERFORMER \"Guest\"
+    INDEX 00 03:59:70
+    INDEX 01 04:01:00
+  TRACK 03 AUDIO
+    TITLE \"Third\"
+    INDEX 00 08:00:00
+FILE \"CD1 part 2.wav\" WAVE
+    INDEX 01 00:00:00
+  TRACK 04 AUDIO
+    TITLE \"Fourth\"
+    INDEX 01 01:00:37
+";
+
+    #[test]
+    fn verify_cue_sheet_parse() {
+        let sheet = CueSheet::parse(SHEET).unwrap();
+
+        assert_eq!(sheet.files.len(), 2);
+        assert_eq!(sheet.files[1].path, "CD1 part 2.wav");
+        assert_eq!(sheet.files[1].file_type, "WAVE");
+        assert_eq!(sheet.tracks.len(), 4);
+        assert_eq!(sheet.tags.len(), 4);
+        assert_eq!(sheet.find_file("/music/cd1 PART 2.flac"), Some(1));
+
+        let cues = sheet.cues(0, 44_100);
+
+        // Track 3 only has a pre-gap in the first file.
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].index, 1);
+        assert_eq!(cues[0].start_ts, 0);
+        assert_eq!(cues[0].points.len(), 2);
+        assert_eq!(cues[0].points[1].start_offset_ts, (239 * 75 + 70) * 588);
+        assert_eq!(cues[0].points[1].tags[0].value.to_string(), "2");
+        assert_eq!(cues[0].points[1].tags[1].value.to_string(), "0");
+        assert_eq!(cues[1].index, 2);
+        assert_eq!(cues[1].start_ts, 241 * 44_100);
+        assert_eq!(cues[1].points.len(), 2);
+        assert_eq!(cues[1].points[0].start_offset_ts, 0);
+        assert_eq!(cues[1].points[1].start_offset_ts, 239 * 44_100);
+        assert_eq!(cues[1].points[1].tags[0].value.to_string(), "3");
+
+        let artists: Vec<String> = cues[1]
+            .tags
+            .iter()
+            .filter(|tag| tag.std_key == Some(StandardTagKey::Artist))
+            .map(|tag| tag.value.to_string())
+            .collect();
+
+        assert_eq!(artists, ["Guest"]);
+
+        let cues = sheet.cues(1, 48_000);
+
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].index, 3);
+        assert_eq!(cues[0].start_ts, 0);
+        assert_eq!(cues[0].points.len(), 1);
+        assert_eq!(cues[1].start_ts, 60 * 48_000 + 37 * 640);
+    }
+
+    #[test]
+    fn verify_cue_sheet_malformed() {
+        assert!(CueSheet::parse("TRACK 01 AUDIO").is_err());
+        assert!(CueSheet::parse("FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:60:00").is_err());
+        assert!(CueSheet::parse("FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:75").is_err());
+    }
+}
@@ -14,6 +14,8 @@
 #![allow(clippy::identity_op)]
 #![allow(clippy::manual_range_contains)]
 
+pub mod apev2;
+pub mod cuesheet;
 pub mod flac;
 pub mod id3v1;
 pub mod id3v2;
@@ -26,12 +26,24 @@
 //!
 //! | Format   | Feature Flag | Gapless* | Default |
 //! |----------|--------------|----------|---------|
+//! | AC-3     | `ac3`        | No       | No      |
 //! | AIFF     | `aiff`       | Yes      | No      |
+//! | AMR      | `amr`        | No       | No      |
+//! | APE      | `ape`        | No       | No      |
+//! | ASF      | `asf`        | No       | No      |
+//! | AU       | `au`         | Yes      | No      |
 //! | CAF      | `caf`        | No       | No      |
+//! | DTS      | `dca`        | No       | No      |
+//! | DSDIFF   | `dsd`        | No       | No      |
+//! | DSF      | `dsd`        | No       | No      |
 //! | ISO/MP4  | `isomp4`     | No       | No      |
 //! | MKV/WebM | `mkv`        | No       | Yes     |
+//! | Musepack | `mpc`        | No       | No      |
 //! | OGG      | `ogg`        | Yes      | Yes     |
+//! | TTA      | `tta`        | No       | No      |
+//! | VOC      | `voc`        | Yes      | No      |
 //! | Wave     | `wav`        | Yes      | Yes     |
+//! | Wave64   | `w64`        | Yes      | No      |
 //!
 //! \* Gapless playback requires support from both the demuxer and decoder.
 //!
@@ -44,13 +56,18 @@
 //! | Codec    | Feature Flag | Gapless | Default |
 //! |----------|--------------|---------|---------|
 //! | AAC-LC   | `aac`        | No      | No      |
+//! | AC-3     | `ac3`        | No      | No      |
+//! | E-AC-3   | `ac3`        | No      | No      |
 //! | ADPCM    | `adpcm`      | Yes     | Yes     |
 //! | ALAC     | `alac`       | Yes     | No      |
+//! | APE      | `ape`        | No      | No      |
+//! | DSD      | `dsd`        | No      | No      |
 //! | FLAC     | `flac`       | Yes     | Yes     |
 //! | MP1      | `mp1`, `mpa` | No      | No      |
 //! | MP2      | `mp2`, `mpa` | No      | No      |
 //! | MP3      | `mp3`, `mpa` | Yes     | No      |
 //! | PCM      | `pcm`        | Yes     | Yes     |
+//! | TTA      | `tta`        | No      | No      |
 //! | Vorbis   | `vorbis`     | Yes     | Yes     |
 //!
 //! **Tip:** All codecs can be enabled with the `all-codecs` feature flag. Similarly, all MPEG
@@ -60,6 +77,7 @@
 //!
 //! The following metadata tagging formats are supported. These are always enabled.
 //!
+//! * APEv2
 //! * ID3v1
 //! * ID3v2
 //! * ISO/MP4
@@ -142,18 +160,26 @@ pub mod default {
     pub mod codecs {
         //! The `codecs` module re-exports all enabled Symphonia decoders.
 
+        #[cfg(feature = "dsd")]
+        pub use symphonia_bundle_dsd::DsdDecoder;
         #[cfg(feature = "flac")]
         pub use symphonia_bundle_flac::FlacDecoder;
         #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
         pub use symphonia_bundle_mp3::MpaDecoder;
         #[cfg(feature = "aac")]
         pub use symphonia_codec_aac::AacDecoder;
+        #[cfg(feature = "ac3")]
+        pub use symphonia_codec_ac3::{Ac3Decoder, Ac3DecoderOptions, DynamicRange};
         #[cfg(feature = "adpcm")]
         pub use symphonia_codec_adpcm::AdpcmDecoder;
         #[cfg(feature = "alac")]
         pub use symphonia_codec_alac::AlacDecoder;
+        #[cfg(feature = "ape")]
+        pub use symphonia_codec_ape::ApeDecoder;
         #[cfg(feature = "pcm")]
         pub use symphonia_codec_pcm::PcmDecoder;
+        #[cfg(feature = "tta")]
+        pub use symphonia_codec_tta::TtaDecoder;
         #[cfg(feature = "vorbis")]
         pub use symphonia_codec_vorbis::VorbisDecoder;
 
@@ -165,24 +191,46 @@ pub mod default {
     pub mod formats {
         //! The `formats` module re-exports all enabled Symphonia format readers.
 
+        #[cfg(feature = "dsd")]
+        pub use symphonia_bundle_dsd::{DffReader, DsfReader};
         #[cfg(feature = "flac")]
         pub use symphonia_bundle_flac::FlacReader;
         #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
         pub use symphonia_bundle_mp3::MpaReader;
         #[cfg(feature = "aac")]
         pub use symphonia_codec_aac::AdtsReader;
+        #[cfg(feature = "ac3")]
+        pub use symphonia_codec_ac3::Ac3Reader;
+        #[cfg(feature = "ape")]
+        pub use symphonia_codec_ape::ApeReader;
+        #[cfg(feature = "tta")]
+        pub use symphonia_codec_tta::TtaReader;
+        #[cfg(feature = "amr")]
+        pub use symphonia_format_amr::AmrReader;
+        #[cfg(feature = "asf")]
+        pub use symphonia_format_asf::AsfReader;
         #[cfg(feature = "caf")]
         pub use symphonia_format_caf::CafReader;
+        #[cfg(feature = "dca")]
+        pub use symphonia_format_dca::DcaReader;
         #[cfg(feature = "isomp4")]
         pub use symphonia_format_isomp4::IsoMp4Reader;
         #[cfg(feature = "mkv")]
         pub use symphonia_format_mkv::MkvReader;
+        #[cfg(feature = "mpc")]
+        pub use symphonia_format_mpc::MpcReader;
         #[cfg(feature = "ogg")]
         pub use symphonia_format_ogg::OggReader;
         #[cfg(feature = "aiff")]
         pub use symphonia_format_riff::AiffReader;
+        #[cfg(feature = "au")]
+        pub use symphonia_format_riff::AuReader;
+        #[cfg(feature = "voc")]
+        pub use symphonia_format_riff::VocReader;
+        #[cfg(feature = "w64")]
+        pub use symphonia_format_riff::W64Reader;
         #[cfg(feature = "wav")]
-        pub use symphonia_format_riff::WavReader;
+        pub use symphonia_format_riff::{WavReader, WavReaderOptions};
 
         #[deprecated = "use `default::formats::MpaReader` instead"]
         #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
@@ -239,12 +287,21 @@ pub mod default {
         #[cfg(feature = "aac")]
         registry.register_all::<codecs::AacDecoder>();
 
+        #[cfg(feature = "ac3")]
+        registry.register_all::<codecs::Ac3Decoder>();
+
         #[cfg(feature = "adpcm")]
         registry.register_all::<codecs::AdpcmDecoder>();
 
         #[cfg(feature = "alac")]
         registry.register_all::<codecs::AlacDecoder>();
 
+        #[cfg(feature = "ape")]
+        registry.register_all::<codecs::ApeDecoder>();
+
+        #[cfg(feature = "dsd")]
+        registry.register_all::<codecs::DsdDecoder>();
+
         #[cfg(feature = "flac")]
         registry.register_all::<codecs::FlacDecoder>();
 
@@ -254,6 +311,9 @@ pub mod default {
         #[cfg(feature = "pcm")]
         registry.register_all::<codecs::PcmDecoder>();
 
+        #[cfg(feature = "tta")]
+        registry.register_all::<codecs::TtaDecoder>();
+
         #[cfg(feature = "vorbis")]
         registry.register_all::<codecs::VorbisDecoder>();
     }
@@ -270,9 +330,30 @@ pub mod default {
         #[cfg(feature = "aac")]
         probe.register_all::<formats::AdtsReader>();
 
+        #[cfg(feature = "ac3")]
+        probe.register_all::<formats::Ac3Reader>();
+
+        #[cfg(feature = "amr")]
+        probe.register_all::<formats::AmrReader>();
+
+        #[cfg(feature = "ape")]
+        probe.register_all::<formats::ApeReader>();
+
+        #[cfg(feature = "asf")]
+        probe.register_all::<formats::AsfReader>();
+
         #[cfg(feature = "caf")]
         probe.register_all::<formats::CafReader>();
 
+        #[cfg(feature = "dca")]
+        probe.register_all::<formats::DcaReader>();
+
+        #[cfg(feature = "dsd")]
+        probe.register_all::<formats::DffReader>();
+
+        #[cfg(feature = "dsd")]
+        probe.register_all::<formats::DsfReader>();
+
         #[cfg(feature = "flac")]
         probe.register_all::<formats::FlacReader>();
 
@@ -285,15 +366,30 @@ pub mod default {
         #[cfg(feature = "aiff")]
         probe.register_all::<formats::AiffReader>();
 
+        #[cfg(feature = "au")]
+        probe.register_all::<formats::AuReader>();
+
+        #[cfg(feature = "voc")]
+        probe.register_all::<formats::VocReader>();
+
         #[cfg(feature = "wav")]
         probe.register_all::<formats::WavReader>();
 
+        #[cfg(feature = "w64")]
+        probe.register_all::<formats::W64Reader>();
+
         #[cfg(feature = "ogg")]
         probe.register_all::<formats::OggReader>();
 
+        #[cfg(feature = "tta")]
+        probe.register_all::<formats::TtaReader>();
+
         #[cfg(feature = "mkv")]
         probe.register_all::<formats::MkvReader>();
 
+        #[cfg(feature = "mpc")]
+        probe.register_all::<formats::MpcReader>();
+
         // Metadata
         probe.register_all::<Id3v2Reader>();
     }